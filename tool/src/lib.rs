@@ -1 +1,9 @@
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+#[cfg(feature = "petgraph")]
+extern crate petgraph;
+
+pub mod model;
 pub mod use_case_diagram;
@@ -0,0 +1,210 @@
+//! Migrations of saved use case diagrams from older format versions, so that
+//! files written before the model changed still load. Fields added to the
+//! model need no migration as long as deserialization defaults them when
+//! they are missing; older documents of every version lack some of them.
+
+use std::error::Error;
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde_json::{self, Map, Value};
+
+use use_case_diagram::{FORMAT_VERSION, UseCaseDiagram};
+
+/// The migrations of documents that deserialization cannot read as they
+/// are, each with the version it migrates from, in order. Version 0 needs
+/// none: documents of it lack dependencies and, depending on when they were
+/// written, actor kinds, abstractness, flows, packages and other fields,
+/// all of which deserialization defaults, as it does fields added since.
+const MIGRATIONS: &[(u64, Migration)] = &[];
+
+type Migration = fn(&mut Map<String, Value>);
+
+/// An error that occurred while migrating or loading a saved diagram.
+#[derive(Debug)]
+pub enum MigrationError {
+    /// The document could not be parsed, or does not describe a valid
+    /// diagram after migration.
+    Json(serde_json::Error),
+
+    /// The document is not a JSON object.
+    NotAnObject,
+
+    /// The `format_version` is not a non-negative integer.
+    InvalidVersion,
+
+    /// The document was written by a newer version of this library.
+    UnsupportedVersion(u64),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MigrationError::Json(ref err) =>
+                write!(f, "invalid JSON: {}", err),
+            MigrationError::NotAnObject =>
+                write!(f, "saved diagram is not an object"),
+            MigrationError::InvalidVersion =>
+                write!(f, "invalid format version"),
+            MigrationError::UnsupportedVersion(version) =>
+                write!(f, "format version {} is newer than {}", version, FORMAT_VERSION),
+        }
+    }
+}
+
+impl Error for MigrationError {
+    fn description(&self) -> &str {
+        match *self {
+            MigrationError::Json(_) =>
+                "invalid JSON",
+            MigrationError::NotAnObject =>
+                "saved diagram is not an object",
+            MigrationError::InvalidVersion =>
+                "invalid format version",
+            MigrationError::UnsupportedVersion(_) =>
+                "unsupported format version",
+        }
+    }
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            MigrationError::Json(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for MigrationError {
+    fn from(err: serde_json::Error) -> Self {
+        MigrationError::Json(err)
+    }
+}
+
+/// Bring a saved diagram of any version up to `FORMAT_VERSION`, applying
+/// every migration from its version onwards. Documents of the current
+/// version are returned unchanged.
+pub fn migrate(mut value: Value) -> Result<Value, MigrationError> {
+    {
+        let document = value.as_object_mut().ok_or(MigrationError::NotAnObject)?;
+        let version = match document.get("format_version") {
+            None => 0,
+            Some(version) => version.as_u64().ok_or(MigrationError::InvalidVersion)?,
+        };
+        if version > FORMAT_VERSION {
+            return Err(MigrationError::UnsupportedVersion(version));
+        }
+        for &(from, migration) in MIGRATIONS {
+            if from >= version {
+                migration(document);
+            }
+        }
+        document.insert("format_version".to_string(), Value::from(FORMAT_VERSION));
+    }
+    Ok(value)
+}
+
+/// Parse a saved diagram of any version, migrating it first.
+pub fn load<S>(json: &str) -> Result<UseCaseDiagram<S>, MigrationError>
+    where S: AsRef<str> + DeserializeOwned {
+    let value = migrate(serde_json::from_str(json)?)?;
+    Ok(serde_json::from_value(value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use use_case_diagram::{Actor, ActorKind, Navigability, UseCase};
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_migrate() {
+        let mut diagram = UseCaseDiagram::new();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        let json = serde_json::to_string(&diagram).unwrap();
+        assert!(json.starts_with("{\"format_version\":1,"));
+
+        let mut old = serde_json::from_str::<Value>(&json).unwrap();
+        old.as_object_mut().unwrap().remove("format_version");
+        old.as_object_mut().unwrap().remove("dependencies");
+        let old = old.to_string();
+        let loaded: UseCaseDiagram = serde_json::from_str(&old).unwrap();
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), json);
+        let loaded: UseCaseDiagram = load(&old).unwrap();
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), json);
+        let loaded: UseCaseDiagram = load(&json).unwrap();
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), json);
+
+        let newer = json.replace("\"format_version\":1", "\"format_version\":2");
+        match load::<Rc<str>>(&newer) {
+            Err(MigrationError::UnsupportedVersion(2)) => (),
+            other => panic!("{:?}", other),
+        }
+        match migrate(Value::from(1)) {
+            Err(MigrationError::NotAnObject) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    /// A diagram as saved by the first version that could save diagrams.
+    const VERSION_0: &str = concat!(
+        "{\"next_actor_id\":2,\"next_use_case_id\":3,\"next_extension_point_id\":1,",
+        "\"next_system_boundary_id\":1,\"next_note_id\":1,\"unique_names\":false,",
+        "\"actors\":{",
+        "\"0\":{\"name\":\"Moderator\",\"key\":\"moderator\",",
+        "\"description\":\"Keeps the peace\",\"stereotypes\":[\"human\"],",
+        "\"metadata\":{\"owner\":\"trust and safety\"}},",
+        "\"1\":{\"name\":\"Administrator\",\"key\":null,\"description\":null,",
+        "\"stereotypes\":[],\"metadata\":{}}},",
+        "\"use_cases\":{",
+        "\"0\":{\"title\":\"Ban subscriber\",\"key\":null,\"description\":null,",
+        "\"preconditions\":[\"The subscriber is reported\"],\"postconditions\":[],",
+        "\"priority\":\"High\",\"status\":\"Draft\",\"tags\":[\"moderation\"],",
+        "\"stereotypes\":[],\"metadata\":{}},",
+        "\"1\":{\"title\":\"Review report\",\"key\":null,\"description\":null,",
+        "\"preconditions\":[],\"postconditions\":[],\"priority\":null,\"status\":null,",
+        "\"tags\":[],\"stereotypes\":[],\"metadata\":{}},",
+        "\"2\":{\"title\":\"Notify subscriber\",\"key\":null,\"description\":null,",
+        "\"preconditions\":[],\"postconditions\":[],\"priority\":null,\"status\":null,",
+        "\"tags\":[],\"stereotypes\":[],\"metadata\":{}}},",
+        "\"extension_points\":{\"0\":{\"use_case\":1,\"name\":\"appeal\"}},",
+        "\"associations\":[",
+        "{\"actor\":0,\"use_case\":0,\"details\":{\"label\":\"bans\",",
+        "\"actor_multiplicity\":{\"lower\":1,\"upper\":null},",
+        "\"use_case_multiplicity\":null,\"navigability\":\"Undirected\",\"metadata\":{}}},",
+        "{\"actor\":0,\"use_case\":1,\"details\":{\"label\":null,",
+        "\"actor_multiplicity\":null,\"use_case_multiplicity\":null,",
+        "\"navigability\":\"Undirected\",\"metadata\":{}}}],",
+        "\"includes\":[[0,2]],",
+        "\"extends\":[{\"extension\":0,\"base\":1,\"extension_point\":0}],",
+        "\"actor_generalizations\":[[1,0]],\"use_case_generalizations\":[],",
+        "\"system_boundaries\":{\"0\":{\"name\":\"Forum\",\"use_cases\":[0,1]}},",
+        "\"notes\":{\"0\":{\"anchor\":{\"UseCase\":0},\"text\":\"Permanent\"}}}",
+    );
+
+    #[test]
+    fn test_load_version_0() {
+        let diagram: UseCaseDiagram = load(VERSION_0).unwrap();
+        let (moderator, actor) = diagram.actor_by_name("Moderator").unwrap();
+        assert_eq!(actor.kind, ActorKind::Primary);
+        assert!(actor.layers.is_empty() && actor.span.is_none());
+        let (ban, use_case) = diagram.use_case_by_title("Ban subscriber").unwrap();
+        assert!(!use_case.is_abstract);
+        assert!(use_case.package.is_empty() && use_case.main_flow.is_empty());
+        assert!(use_case.alternative_flows.is_empty() && use_case.deprecated.is_none());
+        let association = diagram.association(moderator, ban).unwrap();
+        assert_eq!(association.navigability, Navigability::Undirected);
+        assert_eq!(association.weight, None);
+        assert_eq!(diagram.dependencies().count(), 0);
+        assert_eq!(diagram.extends().count(), 1);
+        assert_eq!(diagram.notes().len(), 1);
+
+        let json = serde_json::to_string(&diagram).unwrap();
+        assert!(json.starts_with("{\"format_version\":1,"));
+        let reloaded: UseCaseDiagram = load(&json).unwrap();
+        assert_eq!(serde_json::to_string(&reloaded).unwrap(), json);
+    }
+}
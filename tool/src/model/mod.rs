@@ -0,0 +1,342 @@
+//! Models: several named diagrams that share a catalogue of actors, so that
+//! the same actor can appear in more than one diagram and stay consistent.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+use use_case_diagram::{Actor, ActorId, NameError, UseCaseDiagram};
+
+pub mod migrate;
+
+/// A shared actor identifier is unique per model.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct SharedActorId(pub usize);
+
+impl fmt::Display for SharedActorId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A diagram in a model.
+#[derive(Clone, Debug)]
+pub enum Diagram<S = Rc<str>> {
+    UseCase(UseCaseDiagram<S>),
+}
+
+impl<S> Diagram<S> {
+    /// The use case diagram, if this is one.
+    pub fn as_use_case_diagram(&self) -> Option<&UseCaseDiagram<S>> {
+        match *self {
+            Diagram::UseCase(ref diagram) => Some(diagram),
+        }
+    }
+
+    /// Mutable access to the use case diagram, if this is one.
+    pub fn as_use_case_diagram_mut(&mut self) -> Option<&mut UseCaseDiagram<S>> {
+        match *self {
+            Diagram::UseCase(ref mut diagram) => Some(diagram),
+        }
+    }
+}
+
+/// An error that occurred while changing a model.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ModelError {
+    /// The model has no diagram with the given name.
+    NonexistentDiagram(String),
+
+    /// The diagram with the given name is not of the kind required.
+    WrongDiagramKind(String),
+
+    /// The shared actor does not exist.
+    NonexistentSharedActor(SharedActorId),
+
+    /// The diagram has unique names and another actor has the name.
+    Name(NameError),
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ModelError::NonexistentDiagram(ref name) =>
+                write!(f, "nonexistent diagram {:?}", name),
+            ModelError::WrongDiagramKind(ref name) =>
+                write!(f, "diagram {:?} is of the wrong kind", name),
+            ModelError::NonexistentSharedActor(actor_id) =>
+                write!(f, "nonexistent shared actor {}", actor_id),
+            ModelError::Name(ref err) =>
+                write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for ModelError {
+    fn description(&self) -> &str {
+        match *self {
+            ModelError::NonexistentDiagram(_) =>
+                "nonexistent diagram",
+            ModelError::WrongDiagramKind(_) =>
+                "wrong diagram kind",
+            ModelError::NonexistentSharedActor(_) =>
+                "nonexistent shared actor",
+            ModelError::Name(_) =>
+                "duplicate name",
+        }
+    }
+}
+
+impl From<NameError> for ModelError {
+    fn from(err: NameError) -> Self {
+        ModelError::Name(err)
+    }
+}
+
+/// An inconsistency between the diagrams of a model, as reported by
+/// `Model::validate`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ModelViolation {
+    /// The actor of the named diagram corresponds to the shared actor but
+    /// has different details.
+    InconsistentActor(String, ActorId, SharedActorId),
+}
+
+impl fmt::Display for ModelViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ModelViolation::InconsistentActor(ref diagram, actor_id, shared_actor_id) =>
+                write!(f, "actor {} of diagram {:?} differs from shared actor {}",
+                       actor_id, diagram, shared_actor_id),
+        }
+    }
+}
+
+/// Named diagrams and the actors they share. An actor of a diagram
+/// corresponds to the shared actor with the same key, or, if it has no key,
+/// to the first shared actor with the same name.
+#[derive(Clone, Debug)]
+pub struct Model<S = Rc<str>> {
+    next_shared_actor_id: usize,
+    actors: BTreeMap<SharedActorId, Actor<S>>,
+    diagrams: BTreeMap<String, Diagram<S>>,
+}
+
+impl<S> Model<S> where S: AsRef<str> {
+    /// A new model with no actors and no diagrams.
+    pub fn new() -> Self {
+        Model{
+            next_shared_actor_id: 0,
+            actors: BTreeMap::new(),
+            diagrams: BTreeMap::new(),
+        }
+    }
+
+    /// Get the shared actor with the given identifier.
+    pub fn actor(&self, actor_id: SharedActorId) -> Option<&Actor<S>> {
+        self.actors.get(&actor_id)
+    }
+
+    /// Get mutable access to the shared actor with the given identifier.
+    /// Diagrams are not changed along with it; see `sync_actors`.
+    pub fn actor_mut(&mut self, actor_id: SharedActorId) -> Option<&mut Actor<S>> {
+        self.actors.get_mut(&actor_id)
+    }
+
+    /// All shared actors in this model.
+    pub fn actors(&self) -> impl Iterator<Item = (SharedActorId, &Actor<S>)> {
+        self.actors.iter().map(|(&actor_id, actor)| (actor_id, actor))
+    }
+
+    /// Insert a new shared actor, returning its identifier.
+    pub fn insert_actor(&mut self, actor: Actor<S>) -> SharedActorId {
+        let actor_id = SharedActorId(self.next_shared_actor_id);
+        self.next_shared_actor_id += 1;
+        self.actors.insert(actor_id, actor);
+        actor_id
+    }
+
+    /// Remove a shared actor, returning it if it existed. Diagrams keep their
+    /// copies of it.
+    pub fn remove_actor(&mut self, actor_id: SharedActorId) -> Option<Actor<S>> {
+        self.actors.remove(&actor_id)
+    }
+
+    /// The shared actor that the given actor of a diagram corresponds to.
+    pub fn shared_actor_of(&self, actor: &Actor<S>) -> Option<SharedActorId> {
+        let same_key = |shared: &Actor<S>| match (actor.key.as_ref(), shared.key.as_ref()) {
+            (Some(key), Some(shared_key)) => key.as_ref() == shared_key.as_ref(),
+            _ => false,
+        };
+        if actor.key.is_some() {
+            if let Some((&actor_id, _)) = self.actors.iter().find(|&(_, shared)| same_key(shared)) {
+                return Some(actor_id);
+            }
+        }
+        self.actors.iter()
+            .find(|&(_, shared)| shared.name.as_ref() == actor.name.as_ref())
+            .map(|(&actor_id, _)| actor_id)
+    }
+
+    /// Get the diagram with the given name.
+    pub fn diagram(&self, name: &str) -> Option<&Diagram<S>> {
+        self.diagrams.get(name)
+    }
+
+    /// Get mutable access to the diagram with the given name.
+    pub fn diagram_mut(&mut self, name: &str) -> Option<&mut Diagram<S>> {
+        self.diagrams.get_mut(name)
+    }
+
+    /// All diagrams in this model, in order of name.
+    pub fn diagrams(&self) -> impl Iterator<Item = (&str, &Diagram<S>)> {
+        self.diagrams.iter().map(|(name, diagram)| (name.as_str(), diagram))
+    }
+
+    /// Get the use case diagram with the given name.
+    pub fn use_case_diagram(&self, name: &str) -> Option<&UseCaseDiagram<S>> {
+        self.diagram(name)?.as_use_case_diagram()
+    }
+
+    /// Get mutable access to the use case diagram with the given name.
+    pub fn use_case_diagram_mut(&mut self, name: &str) -> Option<&mut UseCaseDiagram<S>> {
+        self.diagram_mut(name)?.as_use_case_diagram_mut()
+    }
+
+    /// Insert a diagram under the given name, returning the diagram it
+    /// replaces, if any.
+    pub fn insert_diagram(&mut self, name: &str, diagram: Diagram<S>) -> Option<Diagram<S>> {
+        self.diagrams.insert(name.to_string(), diagram)
+    }
+
+    /// Remove the diagram with the given name, returning it if it existed.
+    pub fn remove_diagram(&mut self, name: &str) -> Option<Diagram<S>> {
+        self.diagrams.remove(name)
+    }
+
+    /// Put a shared actor into the named use case diagram, returning the
+    /// identifier of the diagram's actor that corresponds to it. A copy of
+    /// the shared actor is inserted if there is no such actor yet.
+    pub fn place_actor(&mut self, diagram_name: &str, actor_id: SharedActorId)
+                       -> Result<ActorId, ModelError>
+        where S: Clone {
+        let shared = self.actors.get(&actor_id)
+            .ok_or(ModelError::NonexistentSharedActor(actor_id))?
+            .clone();
+        let existing = {
+            let diagram = self.use_case_diagram(diagram_name).ok_or_else(|| {
+                self.diagram_error(diagram_name)
+            })?;
+            diagram.actors()
+                .find(|&(_, actor)| self.shared_actor_of(actor) == Some(actor_id))
+                .map(|(diagram_actor_id, _)| diagram_actor_id)
+        };
+        match existing {
+            Some(diagram_actor_id) => Ok(diagram_actor_id),
+            None => {
+                let diagram = self.use_case_diagram_mut(diagram_name).unwrap();
+                Ok(diagram.insert_actor(shared)?)
+            },
+        }
+    }
+
+    /// Check that every actor of every diagram that corresponds to a shared
+    /// actor has the same details as it, apart from where it was defined,
+    /// returning every inconsistency.
+    pub fn validate(&self) -> Result<(), Vec<ModelViolation>> where S: PartialEq {
+        let mut violations = Vec::new();
+        for (name, diagram) in self.diagrams() {
+            let diagram = match diagram.as_use_case_diagram() {
+                Some(diagram) => diagram,
+                None => continue,
+            };
+            for (actor_id, actor) in diagram.actors() {
+                if let Some(shared_actor_id) = self.shared_actor_of(actor) {
+                    if !actor.eq_ignoring_span(&self.actors[&shared_actor_id]) {
+                        violations.push(ModelViolation::InconsistentActor(
+                            name.to_string(), actor_id, shared_actor_id));
+                    }
+                }
+            }
+        }
+        if violations.is_empty() { Ok(()) } else { Err(violations) }
+    }
+
+    /// Copy the details of every shared actor onto the actors of the
+    /// diagrams that correspond to it, after which `validate` succeeds.
+    /// Return an error, and change nothing, if a diagram has unique names and
+    /// a renamed actor would take the name of another.
+    pub fn sync_actors(&mut self) -> Result<(), ModelError> where S: Clone {
+        let mut updates = Vec::new();
+        for (name, diagram) in self.diagrams() {
+            if let Some(diagram) = diagram.as_use_case_diagram() {
+                for (actor_id, actor) in diagram.actors() {
+                    if let Some(shared_actor_id) = self.shared_actor_of(actor) {
+                        updates.push((name.to_string(), actor_id, shared_actor_id));
+                    }
+                }
+            }
+        }
+        let mut synced = self.diagrams.clone();
+        for (name, actor_id, shared_actor_id) in updates {
+            let shared = &self.actors[&shared_actor_id];
+            let diagram = synced.get_mut(&name).unwrap().as_use_case_diagram_mut().unwrap();
+            diagram.rename_actor(actor_id, shared.name.clone())?;
+            let mut actor = diagram.actor_mut(actor_id).unwrap();
+            let span = actor.span.take();
+            *actor = Actor{span, ..shared.clone()};
+        }
+        self.diagrams = synced;
+        Ok(())
+    }
+
+    fn diagram_error(&self, name: &str) -> ModelError {
+        if self.diagrams.contains_key(name) {
+            ModelError::WrongDiagramKind(name.to_string())
+        } else {
+            ModelError::NonexistentDiagram(name.to_string())
+        }
+    }
+}
+
+impl<S> Default for Model<S> where S: AsRef<str> {
+    fn default() -> Self {
+        Model::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use use_case_diagram::UseCase;
+
+    #[test]
+    fn test_shared_actors() {
+        let mut model = Model::new();
+        let a = model.insert_actor(Actor::new(Rc::from("Administrator")));
+        model.insert_diagram("Moderation", Diagram::UseCase(UseCaseDiagram::new()));
+        model.insert_diagram("Billing", Diagram::UseCase(UseCaseDiagram::new()));
+
+        let ma = model.place_actor("Moderation", a).unwrap();
+        assert_eq!(model.place_actor("Moderation", a), Ok(ma));
+        let ba = model.place_actor("Billing", a).unwrap();
+        let moderation = model.use_case_diagram_mut("Moderation").unwrap();
+        let bs = moderation.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        moderation.insert_association(ma, bs).unwrap();
+        assert_eq!(model.validate(), Ok(()));
+        assert_eq!(model.place_actor("Sales", a),
+                   Err(ModelError::NonexistentDiagram("Sales".to_string())));
+
+        model.actor_mut(a).unwrap().description = Some(Rc::from("Runs the journal"));
+        assert_eq!(model.validate(), Err(vec![
+            ModelViolation::InconsistentActor("Billing".to_string(), ba, a),
+            ModelViolation::InconsistentActor("Moderation".to_string(), ma, a),
+        ]));
+        model.sync_actors().unwrap();
+        assert_eq!(model.validate(), Ok(()));
+        assert_eq!(model.use_case_diagram("Billing").unwrap()[ba].description.as_deref(),
+                   Some("Runs the journal"));
+    }
+}
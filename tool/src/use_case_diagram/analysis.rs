@@ -0,0 +1,239 @@
+use std::cmp;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use use_case_diagram::{ActorId, UseCaseDiagram, UseCaseId};
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> {
+    /// All maximal bicliques of the association graph, as pairs of a set of
+    /// actors and the set of use cases associated with every one of them.
+    /// Such groups are candidates for role abstractions. Actors and use cases
+    /// without associations do not take part in any biclique.
+    ///
+    /// The result is sorted. Its size, and hence the running time, can be
+    /// exponential in the number of actors for adversarial diagrams; for
+    /// diagrams with few shared use cases it stays close to the number of
+    /// actors.
+    pub fn maximal_bicliques(&self) -> Vec<(BTreeSet<ActorId>, BTreeSet<UseCaseId>)> {
+        let mut neighborhoods = HashMap::<ActorId, BTreeSet<UseCaseId>>::new();
+        for (actor_id, use_case_id) in self.associations() {
+            neighborhoods.entry(actor_id).or_default().insert(use_case_id);
+        }
+
+        // Every maximal biclique has as its use cases an intersection of
+        // actor neighborhoods, so close the neighborhoods under intersection.
+        let mut intents = BTreeSet::new();
+        for neighborhood in neighborhoods.values() {
+            let intersections =
+                intents.iter()
+                .map(|intent: &BTreeSet<UseCaseId>|
+                     intent.intersection(neighborhood).cloned().collect())
+                .filter(|intent: &BTreeSet<UseCaseId>| !intent.is_empty())
+                .collect::<Vec<_>>();
+            intents.insert(neighborhood.clone());
+            intents.extend(intersections);
+        }
+
+        let mut bicliques =
+            intents.into_iter()
+            .map(|intent| {
+                let extent =
+                    neighborhoods.iter()
+                    .filter(|&(_, neighborhood)| neighborhood.is_superset(&intent))
+                    .map(|(&actor_id, _)| actor_id)
+                    .collect();
+                (extent, intent)
+            })
+            .collect::<Vec<_>>();
+        bicliques.sort();
+        bicliques
+    }
+
+    /// The groups of actors and use cases that are connected to each other
+    /// through associations, includes, and extends, in either direction.
+    /// Every actor and use case is in exactly one group, and the result is
+    /// sorted. More than one group usually means that the diagram has split
+    /// into unrelated islands.
+    pub fn connected_components(&self) -> Vec<(BTreeSet<ActorId>, BTreeSet<UseCaseId>)> {
+        let mut neighbors = HashMap::<Node, Vec<Node>>::new();
+        let mut connect = |a, b| {
+            neighbors.entry(a).or_default().push(b);
+            neighbors.entry(b).or_default().push(a);
+        };
+        for (actor_id, use_case_id) in self.associations() {
+            connect(Node::Actor(actor_id), Node::UseCase(use_case_id));
+        }
+        for (base, included) in self.includes() {
+            connect(Node::UseCase(base), Node::UseCase(included));
+        }
+        for extend in self.extends() {
+            connect(Node::UseCase(extend.extension), Node::UseCase(extend.base));
+        }
+
+        let mut visited = HashSet::new();
+        let mut components = Vec::new();
+        let nodes =
+            self.actors().map(|(actor_id, _)| Node::Actor(actor_id))
+            .chain(self.use_cases().map(|(use_case_id, _)| Node::UseCase(use_case_id)));
+        for node in nodes {
+            if !visited.insert(node) {
+                continue;
+            }
+            let mut component = (BTreeSet::new(), BTreeSet::new());
+            let mut stack = vec![node];
+            while let Some(node) = stack.pop() {
+                match node {
+                    Node::Actor(actor_id) => component.0.insert(actor_id),
+                    Node::UseCase(use_case_id) => component.1.insert(use_case_id),
+                };
+                for &neighbor in neighbors.get(&node).into_iter().flatten() {
+                    if visited.insert(neighbor) {
+                        stack.push(neighbor);
+                    }
+                }
+            }
+            components.push(component);
+        }
+        components.sort();
+        components
+    }
+
+    /// Whether all actors and use cases form a single connected component.
+    /// An empty diagram is connected.
+    pub fn is_connected(&self) -> bool {
+        self.connected_components().len() <= 1
+    }
+
+    /// The use cases the actor can reach: those it is associated with, and
+    /// from every reachable use case the use cases it includes and the use
+    /// cases that extend it.
+    pub fn reachable_from(&self, actor_id: ActorId) -> BTreeSet<UseCaseId> {
+        let mut reachable = BTreeSet::new();
+        let mut stack = self.use_cases_of_actor(actor_id).collect::<Vec<_>>();
+        while let Some(use_case_id) = stack.pop() {
+            if !reachable.insert(use_case_id) {
+                continue;
+            }
+            stack.extend(self.included_use_cases(use_case_id));
+            stack.extend(
+                self.extends()
+                .filter(|extend| extend.base == use_case_id)
+                .map(|extend| extend.extension)
+            );
+        }
+        reachable
+    }
+
+    /// The sum of the weights of the associations with the use case, zero if
+    /// none has a weight.
+    pub fn use_case_usage(&self, use_case_id: UseCaseId) -> u64 {
+        self.actors_of_use_case(use_case_id)
+            .filter_map(|actor_id| self.associations[&(actor_id, use_case_id)].weight)
+            .sum()
+    }
+
+    /// Every use case with its usage as given by `use_case_usage`, most used
+    /// first and then by identifier.
+    pub fn use_cases_by_usage(&self) -> Vec<(UseCaseId, u64)> {
+        let mut usage =
+            self.use_cases()
+            .map(|(use_case_id, _)| (use_case_id, self.use_case_usage(use_case_id)))
+            .collect::<Vec<_>>();
+        usage.sort_by_key(|&(use_case_id, usage)| (cmp::Reverse(usage), use_case_id));
+        usage
+    }
+
+    /// The heaviest weight of any association, for scaling edges when
+    /// rendering. `None` if no association has a weight.
+    pub fn max_association_weight(&self) -> Option<u64> {
+        self.associations.values().filter_map(|association| association.weight).max()
+    }
+}
+
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+enum Node {
+    Actor(ActorId),
+    UseCase(UseCaseId),
+}
+
+#[cfg(test)]
+mod tests {
+    use use_case_diagram::{Actor, Association, Extend, UseCase, UseCaseDiagram};
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_maximal_bicliques() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let m = diagram.insert_actor(Actor::new(Rc::from("Moderator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let ds = diagram.insert_use_case(UseCase::new(Rc::from("Delete comment"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_association(a, ds).unwrap();
+        diagram.insert_association(m, bs).unwrap();
+        diagram.insert_association(m, ds).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+
+        assert_eq!(diagram.maximal_bicliques(), vec![
+            ([a, m].iter().cloned().collect(), [bs, ds].iter().cloned().collect()),
+            ([s].iter().cloned().collect(), [pc].iter().cloned().collect()),
+        ]);
+
+        diagram.insert_association(s, ds).unwrap();
+        assert_eq!(diagram.maximal_bicliques(), vec![
+            ([a, m].iter().cloned().collect(), [bs, ds].iter().cloned().collect()),
+            ([a, m, s].iter().cloned().collect(), [ds].iter().cloned().collect()),
+            ([s].iter().cloned().collect(), [ds, pc].iter().cloned().collect()),
+        ]);
+    }
+
+    #[test]
+    fn test_connected_components() {
+        let mut diagram = UseCaseDiagram::new();
+        assert!(diagram.is_connected());
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        let at = diagram.insert_use_case(UseCase::new(Rc::from("Attach image"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        diagram.insert_include(pc, li).unwrap();
+        diagram.insert_extend(Extend{extension: at, base: pc, extension_point: None}).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+
+        assert_eq!(diagram.connected_components(), vec![
+            ([s].iter().cloned().collect(), [pc, li, at].iter().cloned().collect()),
+            ([a].iter().cloned().collect(), [bs].iter().cloned().collect()),
+        ]);
+        assert!(!diagram.is_connected());
+        assert_eq!(diagram.reachable_from(s), [pc, li, at].iter().cloned().collect());
+        assert_eq!(diagram.reachable_from(a), [bs].iter().cloned().collect());
+
+        diagram.insert_include(bs, li).unwrap();
+        assert!(diagram.is_connected());
+        assert_eq!(diagram.reachable_from(a), [bs, li].iter().cloned().collect());
+    }
+
+    #[test]
+    fn test_use_cases_by_usage() {
+        let mut diagram = UseCaseDiagram::new();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let g = diagram.insert_actor(Actor::new(Rc::from("Guest"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let rc = diagram.insert_use_case(UseCase::new(Rc::from("Read comments"))).unwrap();
+        let ex = diagram.insert_use_case(UseCase::new(Rc::from("Export"))).unwrap();
+        assert_eq!(diagram.max_association_weight(), None);
+        let weighted = |weight| Association{weight: Some(weight), ..Association::default()};
+        diagram.insert_association_with(s, pc, weighted(30)).unwrap();
+        diagram.insert_association_with(s, rc, weighted(50)).unwrap();
+        diagram.insert_association_with(g, rc, weighted(200)).unwrap();
+        diagram.insert_association(g, ex).unwrap();
+
+        assert_eq!(diagram.use_case_usage(rc), 250);
+        assert_eq!(diagram.use_cases_by_usage(), vec![(rc, 250), (pc, 30), (ex, 0)]);
+        assert_eq!(diagram.max_association_weight(), Some(200));
+    }
+}
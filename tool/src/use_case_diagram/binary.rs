@@ -0,0 +1,787 @@
+//! A compact binary form of the serde representation of diagrams, for
+//! embedding diagrams in build artifacts and loading them quickly.
+//!
+//! Values are written in the order serde visits them, without field names
+//! or type tags: integers as LEB128 varints, signed ones zigzag-encoded,
+//! floats in little-endian, strings, sequences and maps prefixed with their
+//! lengths, options with a tag byte, and enum variants as their indices.
+//! Because nothing describes itself, documents can only be read by the same
+//! version of the format, which the header records.
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::str;
+
+use serde::de::{self, Deserialize, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+
+use use_case_diagram::{FORMAT_VERSION, UseCaseDiagram};
+
+/// The bytes every document starts with.
+pub const MAGIC: &[u8; 4] = b"BFLY";
+
+/// An error that occurred while converting a diagram to or from binary.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BinaryError {
+    /// The document does not start with `MAGIC`.
+    NotBinary,
+
+    /// The document is of a format version other than the one of this
+    /// library, and must be converted through JSON.
+    UnsupportedVersion(u64),
+
+    /// The document ends in the middle of a value.
+    UnexpectedEnd,
+
+    /// The document goes on after the diagram.
+    TrailingBytes,
+
+    /// A value is malformed, e.g. a string is not UTF-8, or does not
+    /// describe a valid diagram.
+    Invalid(String),
+}
+
+impl fmt::Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BinaryError::NotBinary =>
+                write!(f, "not a binary diagram"),
+            BinaryError::UnsupportedVersion(version) =>
+                write!(f, "format version {} is not {}", version, FORMAT_VERSION),
+            BinaryError::UnexpectedEnd =>
+                write!(f, "unexpected end of binary diagram"),
+            BinaryError::TrailingBytes =>
+                write!(f, "trailing bytes after binary diagram"),
+            BinaryError::Invalid(ref message) =>
+                write!(f, "invalid binary diagram: {}", message),
+        }
+    }
+}
+
+impl Error for BinaryError {
+    fn description(&self) -> &str {
+        match *self {
+            BinaryError::NotBinary => "not a binary diagram",
+            BinaryError::UnsupportedVersion(_) => "unsupported format version",
+            BinaryError::UnexpectedEnd => "unexpected end of binary diagram",
+            BinaryError::TrailingBytes => "trailing bytes after binary diagram",
+            BinaryError::Invalid(_) => "invalid binary diagram",
+        }
+    }
+}
+
+impl ser::Error for BinaryError {
+    fn custom<T>(message: T) -> Self where T: fmt::Display {
+        BinaryError::Invalid(message.to_string())
+    }
+}
+
+impl de::Error for BinaryError {
+    fn custom<T>(message: T) -> Self where T: fmt::Display {
+        BinaryError::Invalid(message.to_string())
+    }
+}
+
+/// The diagram as `MAGIC`, the format version as a varint, and the serde
+/// representation of the diagram.
+pub fn to_binary<S>(diagram: &UseCaseDiagram<S>) -> Vec<u8>
+    where S: AsRef<str> + Serialize {
+    let mut serializer = Serializer{output: MAGIC.to_vec()};
+    write_varint(&mut serializer.output, FORMAT_VERSION);
+    diagram.serialize(&mut serializer).expect("diagrams serialize with lengths");
+    serializer.output
+}
+
+/// Read a diagram written by `to_binary`. Fails like deserializing from
+/// JSON if the document does not describe a valid diagram.
+pub fn from_binary<S>(bytes: &[u8]) -> Result<UseCaseDiagram<S>, BinaryError>
+    where S: AsRef<str> + DeserializeOwned {
+    if !bytes.starts_with(MAGIC) {
+        return Err(BinaryError::NotBinary);
+    }
+    let mut deserializer = Deserializer{input: &bytes[MAGIC.len()..]};
+    let version = deserializer.varint()?;
+    if version != FORMAT_VERSION {
+        return Err(BinaryError::UnsupportedVersion(version));
+    }
+    let diagram = UseCaseDiagram::deserialize(&mut deserializer)?;
+    if !deserializer.input.is_empty() {
+        return Err(BinaryError::TrailingBytes);
+    }
+    Ok(diagram)
+}
+
+fn write_varint(output: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        output.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+struct Serializer {
+    output: Vec<u8>,
+}
+
+impl Serializer {
+    fn length(&mut self, length: Option<usize>) -> Result<(), BinaryError> {
+        let length = length.ok_or_else(|| {
+            BinaryError::Invalid("sequence or map without a length".to_string())
+        })?;
+        write_varint(&mut self.output, length as u64);
+        Ok(())
+    }
+}
+
+impl ser::Serializer for &mut Serializer {
+    type Ok = ();
+    type Error = BinaryError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<(), BinaryError> {
+        self.output.push(v as u8);
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), BinaryError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), BinaryError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), BinaryError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), BinaryError> {
+        write_varint(&mut self.output, zigzag(v));
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), BinaryError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), BinaryError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), BinaryError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), BinaryError> {
+        write_varint(&mut self.output, v);
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), BinaryError> {
+        self.output.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), BinaryError> {
+        self.output.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), BinaryError> {
+        self.serialize_u64(u64::from(v))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), BinaryError> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), BinaryError> {
+        write_varint(&mut self.output, v.len() as u64);
+        self.output.extend_from_slice(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), BinaryError> {
+        self.output.push(0);
+        Ok(())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<(), BinaryError>
+        where T: ?Sized + Serialize {
+        self.output.push(1);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), BinaryError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), BinaryError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, index: u32, _variant: &'static str)
+                              -> Result<(), BinaryError> {
+        self.serialize_u32(index)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T)
+                                   -> Result<(), BinaryError>
+        where T: ?Sized + Serialize {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(self, _name: &'static str, index: u32,
+                                    _variant: &'static str, value: &T)
+                                    -> Result<(), BinaryError>
+        where T: ?Sized + Serialize {
+        write_varint(&mut self.output, index.into());
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, length: Option<usize>) -> Result<Self, BinaryError> {
+        self.length(length)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _length: usize) -> Result<Self, BinaryError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _length: usize)
+                              -> Result<Self, BinaryError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, index: u32, _variant: &'static str,
+                               _length: usize) -> Result<Self, BinaryError> {
+        write_varint(&mut self.output, index.into());
+        Ok(self)
+    }
+
+    fn serialize_map(self, length: Option<usize>) -> Result<Self, BinaryError> {
+        self.length(length)?;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _length: usize) -> Result<Self, BinaryError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, index: u32, _variant: &'static str,
+                                _length: usize) -> Result<Self, BinaryError> {
+        write_varint(&mut self.output, index.into());
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl ser::SerializeSeq for &mut Serializer {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), BinaryError>
+        where T: ?Sized + Serialize {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), BinaryError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut Serializer {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), BinaryError>
+        where T: ?Sized + Serialize {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), BinaryError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut Serializer {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), BinaryError>
+        where T: ?Sized + Serialize {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), BinaryError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut Serializer {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), BinaryError>
+        where T: ?Sized + Serialize {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), BinaryError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut Serializer {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), BinaryError>
+        where T: ?Sized + Serialize {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), BinaryError>
+        where T: ?Sized + Serialize {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), BinaryError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut Serializer {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<(), BinaryError>
+        where T: ?Sized + Serialize {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), BinaryError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut Serializer {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<(), BinaryError>
+        where T: ?Sized + Serialize {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), BinaryError> {
+        Ok(())
+    }
+}
+
+struct Deserializer<'de> {
+    input: &'de [u8],
+}
+
+impl<'de> Deserializer<'de> {
+    fn take(&mut self, length: usize) -> Result<&'de [u8], BinaryError> {
+        if self.input.len() < length {
+            return Err(BinaryError::UnexpectedEnd);
+        }
+        let (taken, rest) = self.input.split_at(length);
+        self.input = rest;
+        Ok(taken)
+    }
+
+    fn varint(&mut self) -> Result<u64, BinaryError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(BinaryError::Invalid("varint is too long".to_string()))
+    }
+
+    fn length(&mut self) -> Result<usize, BinaryError> {
+        let length = self.varint()? as usize;
+        // Every element takes at least a byte, except units, which diagrams
+        // do not have in sequences, so longer lengths are corrupt.
+        if length > self.input.len() {
+            return Err(BinaryError::UnexpectedEnd);
+        }
+        Ok(length)
+    }
+
+    fn str(&mut self) -> Result<&'de str, BinaryError> {
+        let length = self.length()?;
+        let bytes = self.take(length)?;
+        str::from_utf8(bytes).map_err(|err| BinaryError::Invalid(err.to_string()))
+    }
+
+    fn integer<T>(&mut self) -> Result<T, BinaryError> where T: TryFrom<u64> {
+        let value = self.varint()?;
+        T::try_from(value)
+            .map_err(|_| BinaryError::Invalid(format!("integer {} is out of range", value)))
+    }
+
+    fn signed<T>(&mut self) -> Result<T, BinaryError> where T: TryFrom<i64> {
+        let value = unzigzag(self.varint()?);
+        T::try_from(value)
+            .map_err(|_| BinaryError::Invalid(format!("integer {} is out of range", value)))
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = BinaryError;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        Err(BinaryError::Invalid("the format does not describe itself".to_string()))
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        match self.take(1)?[0] {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            byte => Err(BinaryError::Invalid(format!("invalid boolean {}", byte))),
+        }
+    }
+
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        visitor.visit_i8(self.signed()?)
+    }
+
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        visitor.visit_i16(self.signed()?)
+    }
+
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        visitor.visit_i32(self.signed()?)
+    }
+
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        visitor.visit_i64(self.signed()?)
+    }
+
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        visitor.visit_u8(self.integer()?)
+    }
+
+    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        visitor.visit_u16(self.integer()?)
+    }
+
+    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        visitor.visit_u32(self.integer()?)
+    }
+
+    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        visitor.visit_u64(self.varint()?)
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        visitor.visit_f32(f32::from_le_bytes(bytes))
+    }
+
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        visitor.visit_f64(f64::from_le_bytes(bytes))
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        let value = self.integer::<u32>()?;
+        let c = char::from_u32(value)
+            .ok_or_else(|| BinaryError::Invalid(format!("invalid character {}", value)))?;
+        visitor.visit_char(c)
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        visitor.visit_borrowed_str(self.str()?)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        visitor.visit_borrowed_str(self.str()?)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        let length = self.length()?;
+        visitor.visit_borrowed_bytes(self.take(length)?)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        match self.take(1)?[0] {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            byte => Err(BinaryError::Invalid(format!("invalid option tag {}", byte))),
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V)
+                                  -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V)
+                                     -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        let remaining = self.length()?;
+        visitor.visit_seq(Elements{deserializer: self, remaining})
+    }
+
+    fn deserialize_tuple<V>(self, length: usize, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        visitor.visit_seq(Elements{deserializer: self, remaining: length})
+    }
+
+    fn deserialize_tuple_struct<V>(self, _name: &'static str, length: usize, visitor: V)
+                                   -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        self.deserialize_tuple(length, visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        let remaining = self.length()?;
+        visitor.visit_map(Elements{deserializer: self, remaining})
+    }
+
+    fn deserialize_struct<V>(self, _name: &'static str, fields: &'static [&'static str],
+                             visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V>(self, _name: &'static str, _variants: &'static [&'static str],
+                           visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V>(self, _visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        Err(BinaryError::Invalid("the format has no identifiers".to_string()))
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        self.deserialize_any(visitor)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// The elements of a sequence, tuple or struct, or the entries of a map.
+struct Elements<'a, 'de> where 'de: 'a {
+    deserializer: &'a mut Deserializer<'de>,
+    remaining: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for Elements<'_, 'de> {
+    type Error = BinaryError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, BinaryError>
+        where T: DeserializeSeed<'de> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.deserializer).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Elements<'_, 'de> {
+    type Error = BinaryError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, BinaryError>
+        where K: DeserializeSeed<'de> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.deserializer).map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, BinaryError>
+        where V: DeserializeSeed<'de> {
+        seed.deserialize(&mut *self.deserializer)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for &mut Deserializer<'de> {
+    type Error = BinaryError;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self), BinaryError>
+        where V: DeserializeSeed<'de> {
+        let index = self.integer::<u32>()?;
+        let variant = seed.deserialize(index.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &mut Deserializer<'de> {
+    type Error = BinaryError;
+
+    fn unit_variant(self) -> Result<(), BinaryError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, BinaryError>
+        where T: DeserializeSeed<'de> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V>(self, length: usize, visitor: V) -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        de::Deserializer::deserialize_tuple(self, length, visitor)
+    }
+
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V)
+                         -> Result<V::Value, BinaryError>
+        where V: Visitor<'de> {
+        de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json;
+
+    use std::rc::Rc;
+
+    use use_case_diagram::{Actor, Association, Extend, ExtensionPoint, Multiplicity,
+                           Navigability, Note, NoteAnchor, SystemBoundary, UseCase};
+
+    #[test]
+    fn test_binary_round_trip() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut administrator = Actor::new(Rc::from("Administrator"));
+        administrator.description = Some(Rc::from("Runs the site ✓"));
+        administrator.metadata.insert("team".to_string(), "ops".to_string());
+        let a = diagram.insert_actor(administrator).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association_with(a, bs, Association{
+            label: Some(Rc::from("bans")),
+            actor_multiplicity: Some(Multiplicity{lower: 1, upper: None}),
+            navigability: Navigability::ToActor,
+            ..Association::default()
+        }).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        let ep = diagram.insert_extension_point(ExtensionPoint{use_case: pc,
+                                                               name: Rc::from("After")})
+            .unwrap();
+        diagram.insert_extend(Extend{extension: bs, base: pc, extension_point: Some(ep)})
+            .unwrap();
+        diagram.insert_dependency(bs, pc, None).unwrap();
+        diagram.insert_system_boundary(SystemBoundary{name: Rc::from("Forum"),
+                                                      use_cases: vec![pc].into_iter().collect()})
+            .unwrap();
+        diagram.insert_note(Note{anchor: NoteAnchor::Association(a, bs), text: Rc::from("!")})
+            .unwrap();
+        diagram.remove_actor(s);
+
+        let bytes = to_binary(&diagram);
+        assert!(bytes.starts_with(b"BFLY\x01"));
+        let json = serde_json::to_string(&diagram).unwrap();
+        assert!(bytes.len() < json.len() / 2);
+        let decoded: UseCaseDiagram = from_binary(&bytes).unwrap();
+        assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+        let from_json: UseCaseDiagram = serde_json::from_str(&json).unwrap();
+        assert_eq!(to_binary(&from_json), bytes);
+    }
+
+    #[test]
+    fn test_binary_errors() {
+        let mut diagram = UseCaseDiagram::new();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_include(bs, pc).unwrap();
+        let bytes = to_binary(&diagram);
+
+        let decode = |bytes: &[u8]| from_binary::<Rc<str>>(bytes).map(|_| ());
+        assert_eq!(decode(b"{}"), Err(BinaryError::NotBinary));
+        assert_eq!(decode(b"BFLY\x02"), Err(BinaryError::UnsupportedVersion(2)));
+        assert_eq!(decode(&bytes[..bytes.len() - 1]), Err(BinaryError::UnexpectedEnd));
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(decode(&trailing), Err(BinaryError::TrailingBytes));
+
+        let mut corrupt = bytes.clone();
+        let title = bytes.windows(3).position(|window| window == b"Ban").unwrap();
+        corrupt[title] = 0xff;
+        match decode(&corrupt) {
+            Err(BinaryError::Invalid(_)) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+use use_case_diagram::{Actor, ActorId, Extend, NameError, RelationshipError, UseCase,
+                       UseCaseDiagram, UseCaseId};
+
+/// An error that occurred while building a diagram with a
+/// `UseCaseDiagramBuilder`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BuildError {
+    /// A relationship refers to an actor name that was not added.
+    UnknownActorName(String),
+
+    /// A relationship refers to a use case title that was not added.
+    UnknownUseCaseTitle(String),
+
+    /// The diagram has unique names and a name or title was added twice.
+    Name(NameError),
+
+    /// A relationship is invalid, e.g. closes a cycle.
+    Relationship(RelationshipError),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BuildError::UnknownActorName(ref name) =>
+                write!(f, "invalid relationship: unknown actor {:?}", name),
+            BuildError::UnknownUseCaseTitle(ref title) =>
+                write!(f, "invalid relationship: unknown use case {:?}", title),
+            BuildError::Name(ref err) =>
+                write!(f, "{}", err),
+            BuildError::Relationship(ref err) =>
+                write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for BuildError {
+    fn description(&self) -> &str {
+        match *self {
+            BuildError::UnknownActorName(_) =>
+                "invalid relationship: unknown actor",
+            BuildError::UnknownUseCaseTitle(_) =>
+                "invalid relationship: unknown use case",
+            BuildError::Name(_) =>
+                "duplicate name",
+            BuildError::Relationship(_) =>
+                "invalid relationship",
+        }
+    }
+}
+
+impl From<NameError> for BuildError {
+    fn from(err: NameError) -> Self {
+        BuildError::Name(err)
+    }
+}
+
+impl From<RelationshipError> for BuildError {
+    fn from(err: RelationshipError) -> Self {
+        BuildError::Relationship(err)
+    }
+}
+
+/// Builds a diagram from actor names and use case titles, resolving them to
+/// identifiers as it goes. Relationships refer to the first actor or use
+/// case added with the name or title. The first error is reported by
+/// `build`; every step after it is ignored.
+#[derive(Clone, Debug)]
+pub struct UseCaseDiagramBuilder<S = Rc<str>> {
+    diagram: Result<UseCaseDiagram<S>, BuildError>,
+}
+
+impl<S> UseCaseDiagramBuilder<S> where S: AsRef<str> + for<'a> From<&'a str> {
+    /// A builder for a diagram in which names need not be unique.
+    pub fn new() -> Self {
+        UseCaseDiagramBuilder{diagram: Ok(UseCaseDiagram::new())}
+    }
+
+    /// A builder for a diagram with unique names. See
+    /// `UseCaseDiagram::with_unique_names`.
+    pub fn with_unique_names() -> Self {
+        UseCaseDiagramBuilder{diagram: Ok(UseCaseDiagram::with_unique_names())}
+    }
+
+    /// The diagram built, or the first error that occurred.
+    pub fn build(self) -> Result<UseCaseDiagram<S>, BuildError> {
+        self.diagram
+    }
+
+    fn step<F>(mut self, f: F) -> Self
+        where F: FnOnce(&mut UseCaseDiagram<S>) -> Result<(), BuildError> {
+        let result = match self.diagram {
+            Ok(ref mut diagram) => f(diagram),
+            Err(_) => Ok(()),
+        };
+        if let Err(err) = result {
+            self.diagram = Err(err);
+        }
+        self
+    }
+
+    /// Add an actor with the given name.
+    pub fn actor(self, name: &str) -> Self {
+        self.step(|diagram| {
+            diagram.insert_actor(Actor::new(S::from(name)))?;
+            Ok(())
+        })
+    }
+
+    /// Add a use case with the given title.
+    pub fn use_case(self, title: &str) -> Self {
+        self.step(|diagram| {
+            diagram.insert_use_case(UseCase::new(S::from(title)))?;
+            Ok(())
+        })
+    }
+
+    /// Associate an actor with a use case.
+    pub fn associate(self, actor: &str, use_case: &str) -> Self {
+        self.step(|diagram| {
+            let actor_id = actor_id(diagram, actor)?;
+            let use_case_id = use_case_id(diagram, use_case)?;
+            diagram.insert_association(actor_id, use_case_id)
+                .expect("association of existing elements");
+            Ok(())
+        })
+    }
+
+    /// Make one use case include another.
+    pub fn include(self, base: &str, included: &str) -> Self {
+        self.step(|diagram| {
+            let base = use_case_id(diagram, base)?;
+            let included = use_case_id(diagram, included)?;
+            diagram.insert_include(base, included)?;
+            Ok(())
+        })
+    }
+
+    /// Make one use case extend another, not at any extension point.
+    pub fn extend(self, extension: &str, base: &str) -> Self {
+        self.step(|diagram| {
+            let extension = use_case_id(diagram, extension)?;
+            let base = use_case_id(diagram, base)?;
+            diagram.insert_extend(Extend{extension, base, extension_point: None})?;
+            Ok(())
+        })
+    }
+
+    /// Make one actor a specialization of another.
+    pub fn generalize_actor(self, child: &str, parent: &str) -> Self {
+        self.step(|diagram| {
+            let child = actor_id(diagram, child)?;
+            let parent = actor_id(diagram, parent)?;
+            diagram.insert_actor_generalization(child, parent)?;
+            Ok(())
+        })
+    }
+
+    /// Make one use case a specialization of another.
+    pub fn generalize_use_case(self, child: &str, parent: &str) -> Self {
+        self.step(|diagram| {
+            let child = use_case_id(diagram, child)?;
+            let parent = use_case_id(diagram, parent)?;
+            diagram.insert_use_case_generalization(child, parent)?;
+            Ok(())
+        })
+    }
+}
+
+impl<S> Default for UseCaseDiagramBuilder<S> where S: AsRef<str> + for<'a> From<&'a str> {
+    fn default() -> Self {
+        UseCaseDiagramBuilder::new()
+    }
+}
+
+fn actor_id<S>(diagram: &UseCaseDiagram<S>, name: &str) -> Result<ActorId, BuildError>
+    where S: AsRef<str> {
+    diagram.actor_by_name(name)
+        .map(|(actor_id, _)| actor_id)
+        .ok_or_else(|| BuildError::UnknownActorName(name.to_string()))
+}
+
+fn use_case_id<S>(diagram: &UseCaseDiagram<S>, title: &str) -> Result<UseCaseId, BuildError>
+    where S: AsRef<str> {
+    diagram.use_case_by_title(title)
+        .map(|(use_case_id, _)| use_case_id)
+        .ok_or_else(|| BuildError::UnknownUseCaseTitle(title.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder() {
+        let diagram: UseCaseDiagram =
+            UseCaseDiagramBuilder::new()
+            .actor("Admin")
+            .actor("Moderator")
+            .use_case("Ban subscriber")
+            .use_case("Log in")
+            .associate("Moderator", "Ban subscriber")
+            .include("Ban subscriber", "Log in")
+            .generalize_actor("Admin", "Moderator")
+            .build()
+            .unwrap();
+        let (a, _) = diagram.actor_by_name("Admin").unwrap();
+        let (bs, _) = diagram.use_case_by_title("Ban subscriber").unwrap();
+        let (li, _) = diagram.use_case_by_title("Log in").unwrap();
+        assert_eq!(diagram.effective_use_cases(a), vec![bs].into_iter().collect());
+        assert_eq!(diagram.included_use_cases(bs).collect::<Vec<_>>(), vec![li]);
+    }
+
+    #[test]
+    fn test_builder_errors() {
+        let unknown =
+            UseCaseDiagramBuilder::<Rc<str>>::new()
+            .use_case("Ban subscriber")
+            .associate("Admin", "Ban subscriber")
+            .actor("Admin")
+            .build();
+        assert_eq!(unknown.unwrap_err(), BuildError::UnknownActorName("Admin".to_string()));
+
+        let cyclic =
+            UseCaseDiagramBuilder::<Rc<str>>::new()
+            .use_case("Ban subscriber")
+            .use_case("Log in")
+            .include("Ban subscriber", "Log in")
+            .include("Log in", "Ban subscriber")
+            .build();
+        assert!(matches!(cyclic,
+                         Err(BuildError::Relationship(RelationshipError::UseCaseCycle(_, _)))));
+
+        let duplicate =
+            UseCaseDiagramBuilder::<Rc<str>>::with_unique_names()
+            .actor("Admin")
+            .actor("Admin")
+            .build();
+        assert!(matches!(duplicate, Err(BuildError::Name(NameError::ActorNameTaken(_)))));
+    }
+}
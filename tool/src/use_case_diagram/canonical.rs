@@ -0,0 +1,250 @@
+use std::cmp;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use use_case_diagram::{Extend, ExtensionPoint, Note, NoteAnchor, SystemBoundary, UseCaseDiagram};
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> + Clone {
+    /// The normal form of this diagram, whose serialization depends only on
+    /// its contents and not on the order in which they were inserted.
+    /// Identifiers are reassigned from zero: actors in order of name, use
+    /// cases in order of title, extension points in order of use case and
+    /// then name, system boundaries in order of name, and notes in order of
+    /// anchor and then text.
+    ///
+    /// Elements that share a name are merged, as in `merge_actors`, into one
+    /// with the details of the element that orders first by all of its
+    /// fields; the same goes for associations and dependency labels that a
+    /// merge brings together. System boundaries that share a name contain the
+    /// use cases of all of them. Relationships that a merge turns into
+    /// self-relationships or cycles are dropped.
+    pub fn canonicalize(&self) -> UseCaseDiagram<S> where S: Ord {
+        let mut canonical = UseCaseDiagram::new();
+        canonical.unique_names = self.unique_names;
+        canonical.invariant_checks = self.invariant_checks;
+        canonical.batch(|canonical| {
+            let mut actors = self.actors().collect::<Vec<_>>();
+            actors.sort_by_key(|&(actor_id, actor)| (actor.name.as_ref(), actor, actor_id));
+            let mut actor_map = HashMap::new();
+            for (actor_id, actor) in actors {
+                let canonical_id = match canonical.actor_by_name(actor.name.as_ref()) {
+                    Some((canonical_id, _)) => canonical_id,
+                    None => canonical.insert_actor(actor.clone()).expect("name is not taken"),
+                };
+                actor_map.insert(actor_id, canonical_id);
+            }
+
+            let mut use_cases = self.use_cases().collect::<Vec<_>>();
+            use_cases.sort_by_key(|&(use_case_id, use_case)| {
+                (use_case.title.as_ref(), use_case, use_case_id)
+            });
+            let mut use_case_map = HashMap::new();
+            for (use_case_id, use_case) in use_cases {
+                let canonical_id = match canonical.use_case_by_title(use_case.title.as_ref()) {
+                    Some((canonical_id, _)) => canonical_id,
+                    None => {
+                        canonical.insert_use_case(use_case.clone()).expect("title is not taken")
+                    },
+                };
+                use_case_map.insert(use_case_id, canonical_id);
+            }
+
+            let mut extension_points = self.extension_points().collect::<Vec<_>>();
+            extension_points.sort_by_key(|&(extension_point_id, extension_point)| {
+                (use_case_map[&extension_point.use_case], extension_point.name.as_ref(),
+                 extension_point_id)
+            });
+            let mut extension_point_map = HashMap::new();
+            for (extension_point_id, extension_point) in extension_points {
+                let use_case_id = use_case_map[&extension_point.use_case];
+                let existing =
+                    canonical.extension_points_of_use_case(use_case_id)
+                    .find(|&(_, canonical_extension_point)| {
+                        canonical_extension_point.name.as_ref() == extension_point.name.as_ref()
+                    })
+                    .map(|(canonical_id, _)| canonical_id);
+                let canonical_id = match existing {
+                    Some(canonical_id) => canonical_id,
+                    None => {
+                        let extension_point = ExtensionPoint{
+                            use_case: use_case_id,
+                            name: extension_point.name.clone(),
+                        };
+                        canonical.insert_extension_point(extension_point)
+                            .expect("use case was just inserted")
+                    },
+                };
+                extension_point_map.insert(extension_point_id, canonical_id);
+            }
+
+            let mut associations = BTreeMap::new();
+            for (actor_id, use_case_id) in self.associations() {
+                let association = self.association(actor_id, use_case_id).unwrap();
+                associations.entry((actor_map[&actor_id], use_case_map[&use_case_id]))
+                    .and_modify(|kept| *kept = cmp::min(*kept, association))
+                    .or_insert(association);
+            }
+            for ((actor_id, use_case_id), association) in associations {
+                canonical.insert_association_with(actor_id, use_case_id, association.clone())
+                    .expect("actor and use case were just inserted");
+            }
+            let includes = self.includes()
+                .map(|(base, included)| (use_case_map[&base], use_case_map[&included]))
+                .collect::<BTreeSet<_>>();
+            for (base, included) in includes {
+                let _ = canonical.insert_include(base, included);
+            }
+            let mut dependencies = BTreeMap::new();
+            for (dependent, dependency) in self.dependencies() {
+                let label = self.dependency_label(dependent, dependency);
+                dependencies.entry((use_case_map[&dependent], use_case_map[&dependency]))
+                    .and_modify(|kept| *kept = cmp::min(*kept, label))
+                    .or_insert(label);
+            }
+            for ((dependent, dependency), label) in dependencies {
+                let _ = canonical.insert_dependency(dependent, dependency, label.cloned());
+            }
+            let extends = self.extends()
+                .map(|extend| Extend{
+                    extension: use_case_map[&extend.extension],
+                    base: use_case_map[&extend.base],
+                    extension_point: extend.extension_point.map(|id| extension_point_map[&id]),
+                })
+                .collect::<BTreeSet<_>>();
+            for extend in extends {
+                let _ = canonical.insert_extend(extend);
+            }
+            let actor_generalizations = self.actor_generalizations()
+                .map(|(child, parent)| (actor_map[&child], actor_map[&parent]))
+                .collect::<BTreeSet<_>>();
+            for (child, parent) in actor_generalizations {
+                let _ = canonical.insert_actor_generalization(child, parent);
+            }
+            let use_case_generalizations = self.use_case_generalizations()
+                .map(|(child, parent)| (use_case_map[&child], use_case_map[&parent]))
+                .collect::<BTreeSet<_>>();
+            for (child, parent) in use_case_generalizations {
+                let _ = canonical.insert_use_case_generalization(child, parent);
+            }
+
+            let mut system_boundaries = self.system_boundaries().collect::<Vec<_>>();
+            system_boundaries.sort_by_key(|&(system_boundary_id, system_boundary)| {
+                (system_boundary.name.as_ref(), system_boundary_id)
+            });
+            for (_, system_boundary) in system_boundaries {
+                let use_cases =
+                    system_boundary.use_cases.iter()
+                    .map(|use_case_id| use_case_map[use_case_id]);
+                let existing =
+                    canonical.system_boundaries()
+                    .find(|&(_, canonical_system_boundary)| {
+                        canonical_system_boundary.name.as_ref() == system_boundary.name.as_ref()
+                    })
+                    .map(|(canonical_id, _)| canonical_id);
+                match existing {
+                    Some(canonical_id) => for use_case_id in use_cases {
+                        canonical.insert_system_boundary_use_case(canonical_id, use_case_id)
+                            .expect("system boundary and use case were just inserted");
+                    },
+                    None => {
+                        let system_boundary = SystemBoundary{
+                            name: system_boundary.name.clone(),
+                            use_cases: use_cases.collect(),
+                        };
+                        canonical.insert_system_boundary(system_boundary)
+                            .expect("use cases were just inserted");
+                    },
+                }
+            }
+
+            let mut notes =
+                self.notes()
+                .map(|(_, note)| {
+                    let anchor = match note.anchor {
+                        NoteAnchor::Actor(actor_id) => NoteAnchor::Actor(actor_map[&actor_id]),
+                        NoteAnchor::UseCase(use_case_id) =>
+                            NoteAnchor::UseCase(use_case_map[&use_case_id]),
+                        NoteAnchor::Association(actor_id, use_case_id) =>
+                            NoteAnchor::Association(actor_map[&actor_id],
+                                                    use_case_map[&use_case_id]),
+                    };
+                    (anchor, &note.text)
+                })
+                .collect::<Vec<_>>();
+            notes.sort_by_key(|&(anchor, text)| (anchor, text.as_ref()));
+            for (anchor, text) in notes {
+                canonical.insert_note(Note{anchor, text: text.clone()})
+                    .expect("anchor was just inserted");
+            }
+        });
+        canonical
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use use_case_diagram::{Actor, Association, UseCase};
+
+    use serde_json;
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_canonicalize() {
+        let mut left = UseCaseDiagram::new();
+        let s = left.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let a = left.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let pc = left.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let li = left.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        left.insert_association(s, pc).unwrap();
+        left.insert_association(a, li).unwrap();
+        left.insert_include(pc, li).unwrap();
+
+        let mut right = UseCaseDiagram::new();
+        let li = right.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        let a = right.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let pc = right.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let s = right.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let s2 = right.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        right.insert_association(a, li).unwrap();
+        right.insert_association(s2, pc).unwrap();
+        right.insert_include(pc, li).unwrap();
+        assert_ne!(s, s2);
+
+        let left = left.canonicalize();
+        let right = right.canonicalize();
+        assert_eq!(serde_json::to_string(&left).unwrap(), serde_json::to_string(&right).unwrap());
+        let (a, _) = left.actor_by_name("Administrator").unwrap();
+        let (s, _) = left.actor_by_name("Subscriber").unwrap();
+        assert!(a < s);
+        assert_eq!(left.actors().count(), 2);
+        assert_eq!(right.actors().count(), 2);
+    }
+
+    #[test]
+    fn test_canonicalize_duplicates() {
+        let diagram = |reversed: bool| {
+            let mut diagram = UseCaseDiagram::new();
+            let mut subscribers = vec![Actor::new(Rc::from("Subscriber")),
+                                       Actor::new(Rc::from("Subscriber"))];
+            subscribers[0].description = Some(Rc::from("Reads the blog"));
+            subscribers[1].description = Some(Rc::from("Pays for the blog"));
+            let mut labels = vec![Rc::from("writes"), Rc::from("posts")];
+            if reversed {
+                subscribers.reverse();
+                labels.reverse();
+            }
+            let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+            for (subscriber, label) in subscribers.into_iter().zip(labels) {
+                let s = diagram.insert_actor(subscriber).unwrap();
+                let association = Association{label: Some(label), ..Association::default()};
+                diagram.insert_association_with(s, pc, association).unwrap();
+            }
+            serde_json::to_string(&diagram.canonicalize()).unwrap()
+        };
+        assert_eq!(diagram(false), diagram(true));
+        assert!(diagram(false).contains("Pays for the blog"));
+        assert!(!diagram(false).contains("Reads the blog"));
+    }
+}
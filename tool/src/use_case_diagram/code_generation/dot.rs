@@ -0,0 +1,619 @@
+use std::collections::{BTreeSet, HashSet};
+use std::io;
+
+use use_case_diagram::{ActorKind, Navigability, UseCaseDiagram, UseCaseId};
+use use_case_diagram::code_generation::{CodeGenerator, GenOptions, GenerationError,
+                                        SectionContext, generate_note_comments};
+
+/// GraphViz graphs, named after the module: `"dot"` for directed graphs as
+/// in `generate_directed_graph`, and `"neato"` for undirected ones as in
+/// `generate_undirected_graph`, after the layout engines they suit.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Dot {
+    pub directed: bool,
+}
+
+impl CodeGenerator for Dot {
+    fn name(&self) -> &'static str {
+        if self.directed { "dot" } else { "neato" }
+    }
+
+    fn generate(&self, diagram: &UseCaseDiagram, mut w: &mut dyn io::Write,
+                options: &GenOptions) -> Result<(), GenerationError> {
+        let diagram = &*options.subdiagram(diagram);
+        generate_graph(&mut w, diagram, &options.module_name, self.directed)
+            .section(|| format!("graph {}", options.module_name))
+    }
+}
+
+/// Generate an undirected GraphViz graph suited for the `neato` and `fdp`
+/// layout engines. Actors are drawn as boxes, dashed for secondary actors, as
+/// components if they are systems and as octagons if they are time. Use cases
+/// are drawn as ellipses, dotted if deprecated, associations as plain lines
+/// with their labels and multiplicities and an arrow if navigable in one
+/// direction only, thicker the heavier their weight, includes and extends as
+/// dashed lines, dependencies as dashed arrows towards the use case depended
+/// on, and generalizations as hollow arrows towards the parent. System
+/// boundaries are emitted as clusters, which `fdp` draws as rectangles, and
+/// packages as clusters nested like the packages themselves, inside the
+/// cluster of the system boundary of their use cases if any. As clusters
+/// cannot overlap, a use case in several system boundaries is drawn inside
+/// the first of them only. Descriptions are
+/// emitted as tooltips, and notes as comments. Actor colors are emitted as
+/// colors, and actor icons as classes, which end up in SVG output for
+/// stylesheets to pick up.
+pub fn generate_undirected_graph<W, S>(w: &mut W, diagram: &UseCaseDiagram<S>, name: &str)
+                                       -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    generate_graph(w, diagram, name, false)
+}
+
+/// Generate a directed GraphViz graph suited for the `dot` layout engine,
+/// drawn like `generate_undirected_graph`. Edges point from actors to their
+/// use cases, from base use cases to the use cases they include, from
+/// extensions to their bases, from dependent use cases to their
+/// dependencies and from children to parents, so that `dot` ranks elements
+/// from left to right in that order. Arrowheads are drawn only where the
+/// undirected graph has them.
+pub fn generate_directed_graph<W, S>(w: &mut W, diagram: &UseCaseDiagram<S>, name: &str)
+                                     -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    generate_graph(w, diagram, name, true)
+}
+
+fn generate_graph<W, S>(w: &mut W, diagram: &UseCaseDiagram<S>, name: &str, directed: bool)
+                        -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    let edge = if directed { "->" } else { "--" };
+    if directed {
+        writeln!(w, "digraph {} {{", quote(name))?;
+        writeln!(w, "  rankdir=LR;")?;
+    } else {
+        writeln!(w, "graph {} {{", quote(name))?;
+        writeln!(w, "  layout=neato;")?;
+        writeln!(w, "  overlap=false;")?;
+        writeln!(w, "  splines=true;")?;
+    }
+    writeln!(w, "  edge [dir=none];")?;
+    generate_note_comments(w, diagram, "  //")?;
+    generate_nodes(w, diagram)?;
+    generate_clusters(w, diagram)?;
+    let mut associations = diagram.associations().collect::<Vec<_>>();
+    associations.sort();
+    let max_weight = diagram.max_association_weight();
+    for (actor_id, use_case_id) in associations {
+        let association = diagram.association(actor_id, use_case_id).unwrap();
+        let mut attributes = Vec::new();
+        if let Some(ref label) = association.label {
+            attributes.push(format!("label={}", quote(label.as_ref())));
+        }
+        if let Some(multiplicity) = association.actor_multiplicity {
+            attributes.push(format!("taillabel={}", quote(&multiplicity.to_string())));
+        }
+        if let Some(multiplicity) = association.use_case_multiplicity {
+            attributes.push(format!("headlabel={}", quote(&multiplicity.to_string())));
+        }
+        match association.navigability {
+            Navigability::Undirected => (),
+            Navigability::ToUseCase => attributes.push("dir=forward".to_string()),
+            Navigability::ToActor => attributes.push("dir=back".to_string()),
+        }
+        if let (Some(weight), Some(max_weight)) = (association.weight, max_weight) {
+            if max_weight > 0 {
+                let penwidth = 1.0 + 4.0 * weight as f64 / max_weight as f64;
+                attributes.push(format!("penwidth={:.1}", penwidth));
+            }
+        }
+        if attributes.is_empty() {
+            writeln!(w, "  actor_{} {} use_case_{};", actor_id, edge, use_case_id)?;
+        } else {
+            writeln!(w, "  actor_{} {} use_case_{} [{}];", actor_id, edge, use_case_id,
+                     attributes.join(", "))?;
+        }
+    }
+    let mut generalizations = diagram.actor_generalizations().collect::<Vec<_>>();
+    generalizations.sort();
+    for (child, parent) in generalizations {
+        writeln!(w, "  actor_{} {} actor_{} [dir=forward, arrowhead=empty];",
+                 child, edge, parent)?;
+    }
+    let mut generalizations = diagram.use_case_generalizations().collect::<Vec<_>>();
+    generalizations.sort();
+    for (child, parent) in generalizations {
+        writeln!(w, "  use_case_{} {} use_case_{} [dir=forward, arrowhead=empty];",
+                 child, edge, parent)?;
+    }
+    let mut includes = diagram.includes().collect::<Vec<_>>();
+    includes.sort();
+    for (base, included) in includes {
+        writeln!(w, "  use_case_{} {} use_case_{} [style=dashed, label=\"«include»\"];",
+                 base, edge, included)?;
+    }
+    let mut dependencies = diagram.dependencies().collect::<Vec<_>>();
+    dependencies.sort();
+    for (dependent, dependency) in dependencies {
+        let label = match diagram.dependency_label(dependent, dependency) {
+            Some(label) => format!("«depends on» {}", label.as_ref()),
+            None => "«depends on»".to_string(),
+        };
+        writeln!(w, "  use_case_{} {} use_case_{} [style=dashed, dir=forward, label={}];",
+                 dependent, edge, dependency, quote(&label))?;
+    }
+    let mut extends = diagram.extends().collect::<Vec<_>>();
+    extends.sort();
+    for extend in extends {
+        let label = match extend.extension_point {
+            Some(id) => {
+                format!("«extend» {}", diagram.extension_point(id).unwrap().name.as_ref())
+            },
+            None => "«extend»".to_string(),
+        };
+        writeln!(w, "  use_case_{} {} use_case_{} [style=dashed, label={}];",
+                 extend.extension, edge, extend.base, quote(&label))?;
+    }
+    writeln!(w, "}}")?;
+    Ok(())
+}
+
+fn generate_nodes<W, S>(w: &mut W, diagram: &UseCaseDiagram<S>) -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    for (actor_id, actor) in diagram.actors_in_order() {
+        let shape = match actor.kind {
+            ActorKind::Primary => "shape=box",
+            ActorKind::Secondary => "shape=box, style=dashed",
+            ActorKind::System => "shape=component",
+            ActorKind::Time => "shape=octagon",
+        };
+        let mut hints = String::new();
+        if let Some(ref color) = actor.color {
+            hints.push_str(&format!(", color={}", quote(color.as_ref())));
+        }
+        if let Some(ref icon) = actor.icon {
+            hints.push_str(&format!(", class={}", quote(icon.as_ref())));
+        }
+        writeln!(w, "  actor_{} [{}, label={}{}{}];",
+                 actor_id, shape, stereotyped_label(&actor.stereotypes, actor.name.as_ref()),
+                 tooltip(&actor.description), hints)?;
+    }
+    for (use_case_id, use_case) in diagram.use_cases_in_order() {
+        let style = if use_case.deprecated.is_some() { ", style=dotted" } else { "" };
+        writeln!(w, "  use_case_{} [shape=ellipse{}, label={}{}];",
+                 use_case_id, style,
+                 stereotyped_label(&use_case.stereotypes, use_case.title.as_ref()),
+                 tooltip(&use_case.description))?;
+    }
+    Ok(())
+}
+
+/// Generate a cluster for every system boundary, holding the use cases that
+/// are in no system boundary before it, with their packages nested inside,
+/// followed by the packages of the use cases in no system boundary.
+fn generate_clusters<W, S>(w: &mut W, diagram: &UseCaseDiagram<S>) -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    let packages = diagram.packages().into_iter().collect::<Vec<_>>();
+    let mut system_boundaries = diagram.system_boundaries().collect::<Vec<_>>();
+    system_boundaries.sort_by_key(|&(system_boundary_id, _)| system_boundary_id);
+    let mut placed = HashSet::new();
+    for (system_boundary_id, system_boundary) in system_boundaries {
+        writeln!(w, "  subgraph cluster_system_boundary_{} {{", system_boundary_id)?;
+        writeln!(w, "    label={};", quote(system_boundary.name.as_ref()))?;
+        let use_case_ids =
+            system_boundary.use_cases.iter().cloned()
+            .filter(|&use_case_id| placed.insert(use_case_id))
+            .collect::<BTreeSet<_>>();
+        for &use_case_id in &use_case_ids {
+            if diagram[use_case_id].package.is_empty() {
+                writeln!(w, "    use_case_{};", use_case_id)?;
+            }
+        }
+        let prefix = format!("system_boundary_{}_", system_boundary_id);
+        generate_packages(w, diagram, &packages, &use_case_ids, &prefix, &[], 2)?;
+        writeln!(w, "  }}")?;
+    }
+    let use_case_ids =
+        diagram.use_cases().map(|(use_case_id, _)| use_case_id)
+        .filter(|use_case_id| !placed.contains(use_case_id))
+        .collect::<BTreeSet<_>>();
+    generate_packages(w, diagram, &packages, &use_case_ids, "", &[], 1)
+}
+
+/// Generate a cluster for every package directly inside the given one that
+/// holds any of the given use cases, directly or through nested packages,
+/// and recursively for the packages inside those. Clusters are numbered by
+/// the position of their package in `packages`, which must be sorted, after
+/// the given prefix.
+fn generate_packages<W, S>(w: &mut W, diagram: &UseCaseDiagram<S>, packages: &[Vec<&str>],
+                           use_case_ids: &BTreeSet<UseCaseId>, prefix: &str, parent: &[&str],
+                           depth: usize) -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    let indent = "  ".repeat(depth);
+    for (index, package) in packages.iter().enumerate() {
+        if package.len() != parent.len() + 1 || !package.starts_with(parent) {
+            continue;
+        }
+        let within = |use_case_id: &UseCaseId| {
+            let use_case_package = &diagram[*use_case_id].package;
+            use_case_package.len() >= package.len()
+                && use_case_package.iter().zip(package).all(|(name, &other)| {
+                    name.as_ref() == other
+                })
+        };
+        if !use_case_ids.iter().any(within) {
+            continue;
+        }
+        writeln!(w, "{}subgraph cluster_{}package_{} {{", indent, prefix, index)?;
+        writeln!(w, "{}  label={};", indent, quote(package[parent.len()]))?;
+        for &use_case_id in use_case_ids {
+            if diagram[use_case_id].is_in_package(package) {
+                writeln!(w, "{}  use_case_{};", indent, use_case_id)?;
+            }
+        }
+        generate_packages(w, diagram, packages, use_case_ids, prefix, package, depth + 1)?;
+        writeln!(w, "{}}}", indent)?;
+    }
+    Ok(())
+}
+
+/// The tooltip attribute for a description, if any, including the leading
+/// separator.
+fn tooltip<S>(description: &Option<S>) -> String where S: AsRef<str> {
+    match *description {
+        Some(ref description) => format!(", tooltip={}", quote(description.as_ref())),
+        None => String::new(),
+    }
+}
+
+/// Quote a node label, preceded by a line listing the stereotypes if any.
+fn stereotyped_label<S>(stereotypes: &[S], name: &str) -> String where S: AsRef<str> {
+    if stereotypes.is_empty() {
+        return quote(name);
+    }
+    let stereotypes = stereotypes.iter().map(|s| s.as_ref()).collect::<Vec<_>>();
+    let stereotypes = quote(&format!("«{}»", stereotypes.join(", ")));
+    let name = quote(name);
+    format!("{}\\n{}", &stereotypes[.. stereotypes.len() - 1], &name[1 ..])
+}
+
+/// Quote a string as a DOT identifier.
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use use_case_diagram::{Actor, Association, Extend, ExtensionPoint, Multiplicity, Note,
+                           NoteAnchor, SystemBoundary, UseCase};
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_undirected_graph() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban \"bad\" subscriber"))).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_actor_generalization(a, s).unwrap();
+        diagram.insert_include(bs, li).unwrap();
+        diagram.insert_note(Note{anchor: NoteAnchor::Association(a, bs),
+                                 text: Rc::from("Requires two-factor\nauthentication.")})
+            .unwrap();
+        let bt = diagram.insert_use_case(UseCase::new(Rc::from("Ban troll"))).unwrap();
+        diagram.insert_use_case_generalization(bt, bs).unwrap();
+        let moderation = SystemBoundary{name: Rc::from("Moderation"),
+                                        use_cases: [bt, bs].iter().cloned().collect()};
+        diagram.insert_system_boundary(moderation).unwrap();
+        let bsp = diagram.insert_extension_point(ExtensionPoint{use_case: bs,
+                                                                name: Rc::from("Confirm")})
+            .unwrap();
+        diagram.insert_extend(Extend{extension: li, base: bs, extension_point: Some(bsp)})
+            .unwrap();
+
+        let mut out = Vec::new();
+        generate_undirected_graph(&mut out, &diagram, "Journal").unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.starts_with("graph \"Journal\" {\n"));
+        assert_eq!(out, concat!(
+            "graph \"Journal\" {\n",
+            "  layout=neato;\n",
+            "  overlap=false;\n",
+            "  splines=true;\n",
+            "  edge [dir=none];\n",
+            "  // association \"Administrator\" -- \"Ban \\\"bad\\\" subscriber\": ",
+            "Requires two-factor\n",
+            "  // association \"Administrator\" -- \"Ban \\\"bad\\\" subscriber\": ",
+            "authentication.\n",
+            "  actor_0 [shape=box, label=\"Administrator\"];\n",
+            "  actor_1 [shape=box, label=\"Subscriber\"];\n",
+            "  use_case_0 [shape=ellipse, label=\"Ban \\\"bad\\\" subscriber\"];\n",
+            "  use_case_1 [shape=ellipse, label=\"Log in\"];\n",
+            "  use_case_2 [shape=ellipse, label=\"Ban troll\"];\n",
+            "  subgraph cluster_system_boundary_0 {\n",
+            "    label=\"Moderation\";\n",
+            "    use_case_0;\n",
+            "    use_case_2;\n",
+            "  }\n",
+            "  actor_0 -- use_case_0;\n",
+            "  actor_0 -- actor_1 [dir=forward, arrowhead=empty];\n",
+            "  use_case_2 -- use_case_0 [dir=forward, arrowhead=empty];\n",
+            "  use_case_0 -- use_case_1 [style=dashed, label=\"«include»\"];\n",
+            "  use_case_1 -- use_case_0 [style=dashed, label=\"«extend» Confirm\"];\n",
+            "}\n",
+        ));
+    }
+
+
+    #[test]
+    fn test_rich_association() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let association = Association{
+            label: Some(Rc::from("bans")),
+            actor_multiplicity: Some(Multiplicity{lower: 1, upper: Some(1)}),
+            use_case_multiplicity: Some(Multiplicity{lower: 0, upper: None}),
+            navigability: Navigability::ToUseCase,
+            ..Association::default()
+        };
+        diagram.insert_association_with(a, bs, association).unwrap();
+
+        let mut out = Vec::new();
+        generate_undirected_graph(&mut out, &diagram, "Journal").unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(concat!("  actor_0 -- use_case_0 [label=\"bans\", taillabel=\"1\", ",
+                                     "headlabel=\"*\", dir=forward];\n")));
+    }
+
+
+    #[test]
+    fn test_stereotyped_labels() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut scheduler = Actor::new(Rc::from("Scheduler"));
+        scheduler.stereotypes.push(Rc::from("system"));
+        scheduler.stereotypes.push(Rc::from("cron"));
+        diagram.insert_actor(scheduler).unwrap();
+
+        let mut out = Vec::new();
+        generate_undirected_graph(&mut out, &diagram, "Journal").unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("  actor_0 [shape=box, label=\"«system, cron»\\nScheduler\"];\n"));
+    }
+
+
+    #[test]
+    fn test_description_tooltips() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut actor = Actor::new(Rc::from("Administrator"));
+        actor.description = Some(Rc::from("Manages \"subscribers\"."));
+        diagram.insert_actor(actor).unwrap();
+
+        let mut out = Vec::new();
+        generate_undirected_graph(&mut out, &diagram, "Journal").unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(concat!("  actor_0 [shape=box, label=\"Administrator\", ",
+                                     "tooltip=\"Manages \\\"subscribers\\\".\"];\n")));
+    }
+
+    #[test]
+    fn test_actor_kind_shapes() {
+        let mut diagram = UseCaseDiagram::new();
+        for &kind in &[ActorKind::Secondary, ActorKind::System, ActorKind::Time] {
+            let mut actor = Actor::new(Rc::from("Actor"));
+            actor.kind = kind;
+            diagram.insert_actor(actor).unwrap();
+        }
+
+        let mut out = Vec::new();
+        generate_undirected_graph(&mut out, &diagram, "Journal").unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("  actor_0 [shape=box, style=dashed, label=\"Actor\"];\n"));
+        assert!(out.contains("  actor_1 [shape=component, label=\"Actor\"];\n"));
+        assert!(out.contains("  actor_2 [shape=octagon, label=\"Actor\"];\n"));
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let mut diagram = UseCaseDiagram::new();
+        let cs = diagram.insert_use_case(UseCase::new(Rc::from("Create subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let mc = diagram.insert_use_case(UseCase::new(Rc::from("Moderate comment"))).unwrap();
+        diagram.insert_dependency(pc, cs, None).unwrap();
+        diagram.insert_dependency(mc, pc, Some(Rc::from("needs a comment"))).unwrap();
+
+        let mut out = Vec::new();
+        generate_undirected_graph(&mut out, &diagram, "Journal").unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("  use_case_1 -- use_case_0 \
+                              [style=dashed, dir=forward, label=\"«depends on»\"];\n"));
+        assert!(out.contains("  use_case_2 -- use_case_1 \
+                              [style=dashed, dir=forward, \
+                              label=\"«depends on» needs a comment\"];\n"));
+    }
+
+    #[test]
+    fn test_packages() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut cs = UseCase::new(Rc::from("Cancel subscription"));
+        cs.package = vec![Rc::from("Billing")];
+        let mut si = UseCase::new(Rc::from("Send invoice"));
+        si.package = vec![Rc::from("Billing"), Rc::from("Invoices")];
+        diagram.insert_use_case(cs).unwrap();
+        diagram.insert_use_case(si).unwrap();
+        diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+
+        let mut out = Vec::new();
+        generate_undirected_graph(&mut out, &diagram, "Journal").unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(concat!(
+            "  subgraph cluster_package_0 {\n",
+            "    label=\"Billing\";\n",
+            "    use_case_0;\n",
+            "    subgraph cluster_package_1 {\n",
+            "      label=\"Invoices\";\n",
+            "      use_case_1;\n",
+            "    }\n",
+            "  }\n",
+        )));
+        assert!(!out.contains("    use_case_2;\n"));
+    }
+
+    #[test]
+    fn test_packages_in_system_boundaries() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut cs = UseCase::new(Rc::from("Cancel subscription"));
+        cs.package = vec![Rc::from("Billing")];
+        let cs = diagram.insert_use_case(cs).unwrap();
+        let mut si = UseCase::new(Rc::from("Send invoice"));
+        si.package = vec![Rc::from("Billing")];
+        diagram.insert_use_case(si).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        for name in &["Shop", "Account"] {
+            diagram.insert_system_boundary(SystemBoundary{
+                name: Rc::from(*name), use_cases: [cs, li].iter().cloned().collect(),
+            }).unwrap();
+        }
+
+        let mut out = Vec::new();
+        generate_undirected_graph(&mut out, &diagram, "Journal").unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(concat!(
+            "  subgraph cluster_system_boundary_0 {\n",
+            "    label=\"Shop\";\n",
+            "    use_case_2;\n",
+            "    subgraph cluster_system_boundary_0_package_0 {\n",
+            "      label=\"Billing\";\n",
+            "      use_case_0;\n",
+            "    }\n",
+            "  }\n",
+            "  subgraph cluster_system_boundary_1 {\n",
+            "    label=\"Account\";\n",
+            "  }\n",
+            "  subgraph cluster_package_0 {\n",
+            "    label=\"Billing\";\n",
+            "    use_case_1;\n",
+            "  }\n",
+        )));
+        for use_case_id in 0 .. 3 {
+            assert_eq!(out.matches(&format!(" use_case_{};\n", use_case_id)).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_actor_render_hints() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut actor = Actor::new(Rc::from("Administrator"));
+        actor.icon = Some(Rc::from("shield"));
+        actor.color = Some(Rc::from("#1f77b4"));
+        diagram.insert_actor(actor).unwrap();
+
+        let mut out = Vec::new();
+        generate_undirected_graph(&mut out, &diagram, "Journal").unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(concat!("  actor_0 [shape=box, label=\"Administrator\", ",
+                                     "color=\"#1f77b4\", class=\"shield\"];\n")));
+    }
+
+    #[test]
+    fn test_association_weights() {
+        let mut diagram = UseCaseDiagram::new();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let rc = diagram.insert_use_case(UseCase::new(Rc::from("Read comments"))).unwrap();
+        let weighted = |weight| Association{weight: Some(weight), ..Association::default()};
+        diagram.insert_association_with(s, pc, weighted(10)).unwrap();
+        diagram.insert_association_with(s, rc, weighted(40)).unwrap();
+
+        let mut out = Vec::new();
+        generate_undirected_graph(&mut out, &diagram, "Journal").unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("  actor_0 -- use_case_0 [penwidth=2.0];\n"));
+        assert!(out.contains("  actor_0 -- use_case_1 [penwidth=5.0];\n"));
+    }
+
+    #[test]
+    fn test_deprecated_use_cases_dotted() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut fax = UseCase::new(Rc::from("Fax comment"));
+        fax.deprecated = Some(Rc::from("Use Post comment instead."));
+        diagram.insert_use_case(fax).unwrap();
+
+        let mut out = Vec::new();
+        generate_undirected_graph(&mut out, &diagram, "Journal").unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(
+            "  use_case_0 [shape=ellipse, style=dotted, label=\"Fax comment\"];\n"));
+    }
+
+    #[test]
+    fn test_directed_graph() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_include(bs, li).unwrap();
+        let moderation = SystemBoundary{name: Rc::from("Moderation"),
+                                        use_cases: [bs].iter().cloned().collect()};
+        diagram.insert_system_boundary(moderation).unwrap();
+
+        let mut out = Vec::new();
+        generate_directed_graph(&mut out, &diagram, "Journal").unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), concat!(
+            "digraph \"Journal\" {\n",
+            "  rankdir=LR;\n",
+            "  edge [dir=none];\n",
+            "  actor_0 [shape=box, label=\"Administrator\"];\n",
+            "  use_case_0 [shape=ellipse, label=\"Ban subscriber\"];\n",
+            "  use_case_1 [shape=ellipse, label=\"Log in\"];\n",
+            "  subgraph cluster_system_boundary_0 {\n",
+            "    label=\"Moderation\";\n",
+            "    use_case_0;\n",
+            "  }\n",
+            "  actor_0 -> use_case_0;\n",
+            "  use_case_0 -> use_case_1 [style=dashed, label=\"«include»\"];\n",
+            "}\n",
+        ));
+    }
+
+    #[test]
+    fn test_layers() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut subscriber = Actor::new(Rc::from("Subscriber"));
+        subscriber.layers.push(Rc::from("MVP"));
+        let s = diagram.insert_actor(subscriber).unwrap();
+        let mut moderator = Actor::new(Rc::from("Moderator"));
+        moderator.layers.push(Rc::from("Phase 2"));
+        diagram.insert_actor(moderator).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+
+        let generate = |layer: Option<&str>| {
+            let options = GenOptions{module_name: "Forum".to_string(),
+                                     layers: vec!["MVP".to_string(), "Phase 2".to_string()],
+                                     layer: layer.map(str::to_string),
+                                     ..GenOptions::default()};
+            let mut out = Vec::new();
+            Dot{directed: true}.generate(&diagram, &mut out, &options).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let mvp = generate(Some("MVP"));
+        assert!(mvp.contains("Subscriber") && !mvp.contains("Moderator"));
+        assert!(mvp.contains("actor_0 -> use_case_0;"));
+        let phase_2 = generate(Some("Phase 2"));
+        assert!(phase_2.contains("Subscriber") && phase_2.contains("Moderator"));
+        assert_eq!(generate(None), phase_2);
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::io;
+
+use use_case_diagram::{UseCaseDiagram, UseCaseId};
+use use_case_diagram::code_generation::{CodeGenerator, GenOptions, GenerationError,
+                                        SectionContext, button_actors, camel_case,
+                                        generate_note_comments, generate_portal_documentation,
+                                        portal_use_cases};
+
+/// Complete modules, `"elm"`: the module header, the imports and a portal
+/// as in `generate_portal_definition`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Elm;
+
+impl CodeGenerator for Elm {
+    fn name(&self) -> &'static str {
+        "elm"
+    }
+
+    fn generate(&self, diagram: &UseCaseDiagram, mut w: &mut dyn io::Write,
+                options: &GenOptions) -> Result<(), GenerationError> {
+        let diagram = &*options.subdiagram(diagram);
+        (|| {
+            generate_module_header(&mut w, &options.module_name, &options.definition_name)?;
+            writeln!(w)?;
+            generate_imports(&mut w)?;
+            writeln!(w)?;
+            writeln!(w)
+        })().section(|| format!("header of module {}", options.module_name))?;
+        generate_portal_definition(&mut w, diagram, &options.definition_name)
+    }
+}
+
+/// Generate a module header exposing everything that
+/// `generate_portal_definition` defines for a portal with the given name.
+pub fn generate_module_header<W>(w: &mut W, name: &str, portal: &str) -> io::Result<()>
+    where W: io::Write {
+    writeln!(w, "module {} exposing (Actor, UseCase(..), actorsFor, {}, title, useCases)",
+             name, portal)?;
+    Ok(())
+}
+
+/// Generate the imports necessary for the other generated code.
+pub fn generate_imports<W>(w: &mut W) -> io::Result<()>
+    where W: io::Write {
+    writeln!(w, "import Html exposing (Html)")?;
+    writeln!(w, "import Html.Events exposing (onClick)")?;
+    writeln!(w, "import Set exposing (Set)")?;
+    Ok(())
+}
+
+/// The name of the constructor of the `UseCase` type for a use case: its
+/// title in camel case with the first letter capitalized, prefixed with
+/// `UseCase` if that does not start with an uppercase letter.
+pub fn constructor_name(title: &str) -> String {
+    let name = camel_case(title, true);
+    if name.chars().next().is_some_and(char::is_uppercase) {
+        name
+    } else {
+        format!("UseCase{}", name)
+    }
+}
+
+/// Generate Elm definitions for a portal: `Actor`, an alias of `String` as
+/// sets need comparable elements, a `UseCase` type with a constructor for
+/// every use case that gets a button, named by `constructor_name`, the list
+/// `useCases` of them all, and the functions `title` and `actorsFor`, which
+/// give the title and the actors of a use case. Use cases and their actors
+/// are as in `purescript::generate_portal_definition`, and so are notes and
+/// documentation. The portal itself is a view, named `name`, with a button
+/// for every use case of the given actor, which sends the message for it.
+/// Descriptions and the like in its documentation comment have their `{-`
+/// and `-}` broken up, so that they cannot end the comment or open a nested
+/// one. Write failures are annotated with the section being written. Return
+/// an error of kind `InvalidInput` if `name` is that of another definition
+/// or variable in the generated code, if no use case gets a button, as types
+/// need constructors, or if two use cases get the same constructor name.
+pub fn generate_portal_definition<W, S>(w: &mut W, diagram: &UseCaseDiagram<S>, name: &str)
+                                        -> Result<(), GenerationError>
+    where W: io::Write, S: AsRef<str> {
+    let use_case_ids = portal_use_cases(diagram).collect::<Vec<_>>();
+    let invalid_input = |message| {
+        let error = io::Error::new(io::ErrorKind::InvalidInput, message);
+        Err(error).section(|| format!("portal {}", name))
+    };
+    if RESERVED_NAMES.contains(&name) {
+        return invalid_input(format!("the portal cannot be named {:?}, which the generated \
+                                      code uses", name));
+    }
+    if use_case_ids.is_empty() {
+        return invalid_input("no use case gets a button".to_string());
+    }
+    let mut constructors = HashMap::new();
+    for &use_case_id in &use_case_ids {
+        let title = diagram[use_case_id].title.as_ref();
+        if let Some(other) = constructors.insert(constructor_name(title), title) {
+            return invalid_input(format!("use cases {:?} and {:?} have the same constructor \
+                                          name", other, title));
+        }
+    }
+    let constructor =
+        |use_case_id: UseCaseId| constructor_name(diagram[use_case_id].title.as_ref());
+
+    generate_note_comments(w, diagram, "--")
+        .section(|| format!("notes of portal {}", name))?;
+
+    (|| {
+        writeln!(w, "type alias Actor =")?;
+        writeln!(w, "    String")?;
+        writeln!(w)?;
+        writeln!(w)?;
+        writeln!(w, "type UseCase")?;
+        for (i, &use_case_id) in use_case_ids.iter().enumerate() {
+            let separator = if i == 0 { '=' } else { '|' };
+            writeln!(w, "    {} {}", separator, constructor(use_case_id))?;
+        }
+        writeln!(w)?;
+        writeln!(w)?;
+        writeln!(w, "useCases : List UseCase")?;
+        writeln!(w, "useCases =")?;
+        for (i, &use_case_id) in use_case_ids.iter().enumerate() {
+            let separator = if i == 0 { '[' } else { ',' };
+            writeln!(w, "    {} {}", separator, constructor(use_case_id))?;
+        }
+        writeln!(w, "    ]")?;
+        writeln!(w)?;
+        writeln!(w)
+    })().section(|| "type UseCase".to_string())?;
+
+    (|| {
+        writeln!(w, "title : UseCase -> String")?;
+        writeln!(w, "title useCase =")?;
+        writeln!(w, "    case useCase of")?;
+        for (i, &use_case_id) in use_case_ids.iter().enumerate() {
+            if i > 0 {
+                writeln!(w)?;
+            }
+            writeln!(w, "        {} ->", constructor(use_case_id))?;
+            writeln!(w, "            {:?}", diagram[use_case_id].title.as_ref())?;
+        }
+        writeln!(w)?;
+        writeln!(w)
+    })().section(|| "function title".to_string())?;
+
+    (|| {
+        writeln!(w, "actorsFor : UseCase -> Set Actor")?;
+        writeln!(w, "actorsFor useCase =")?;
+        writeln!(w, "    case useCase of")?;
+        for (i, &use_case_id) in use_case_ids.iter().enumerate() {
+            if i > 0 {
+                writeln!(w)?;
+            }
+            writeln!(w, "        {} ->", constructor(use_case_id))?;
+            write!(w, "            Set.fromList [")?;
+            let actor_ids = button_actors(diagram, use_case_id);
+            for (i, &actor_id) in actor_ids.iter().enumerate() {
+                let separator = if i == 0 { "" } else { "," };
+                write!(w, "{} {:?}", separator, diagram[actor_id].name.as_ref())?;
+            }
+            writeln!(w, " ]")?;
+        }
+        writeln!(w)?;
+        writeln!(w)
+    })().section(|| "function actorsFor".to_string())?;
+
+    let mut documentation = Vec::new();
+    generate_portal_documentation(&mut documentation, diagram, "", "   ")
+        .section(|| format!("documentation of portal {}", name))?;
+    let documentation = String::from_utf8_lossy(&documentation)
+        .replace("{-", "{ -")
+        .replace("-}", "- }");
+    (|| {
+        if !documentation.is_empty() {
+            write!(w, "{{-|{}", documentation)?;
+            writeln!(w, "-}}")?;
+        }
+        writeln!(w, "{} : Actor -> (UseCase -> msg) -> Html msg", name)?;
+        writeln!(w, "{} actor toMsg =", name)?;
+        writeln!(w, "    useCases")?;
+        writeln!(w, "        |> List.filter (\\useCase -> Set.member actor (actorsFor useCase))")?;
+        writeln!(w, "        |> List.map")?;
+        writeln!(w, "            (\\useCase ->")?;
+        writeln!(w, "                Html.button [ onClick (toMsg useCase) ]")?;
+        writeln!(w, "                    [ Html.text (title useCase) ]")?;
+        writeln!(w, "            )")?;
+        writeln!(w, "        |> Html.div []")
+    })().section(|| format!("definition of portal {}", name))?;
+
+    Ok(())
+}
+
+/// The names that the generated code defines or binds besides the portal.
+const RESERVED_NAMES: &[&str] = &["actor", "actorsFor", "onClick", "title", "toMsg", "useCase",
+                                  "useCases"];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use use_case_diagram::{Actor, ActorKind, UseCase};
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_constructor_name() {
+        assert_eq!(constructor_name("Ban subscriber"), "BanSubscriber");
+        assert_eq!(constructor_name("2FA setup"), "UseCase2FASetup");
+        assert_eq!(constructor_name("!"), "UseCase");
+    }
+
+    #[test]
+    fn test_portal_definition() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let mut timer = Actor::new(Rc::from("Timer"));
+        timer.kind = ActorKind::Time;
+        let t = diagram.insert_actor(timer).unwrap();
+        let mut bs = UseCase::new(Rc::from("Ban subscriber"));
+        bs.description = Some(Rc::from("Keep a troll out."));
+        let bs = diagram.insert_use_case(bs).unwrap();
+        let mut pc = UseCase::new(Rc::from("Post comment"));
+        pc.preconditions.push(Rc::from("Logged in"));
+        let pc = diagram.insert_use_case(pc).unwrap();
+        let pd = diagram.insert_use_case(UseCase::new(Rc::from("Purge drafts"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_association(a, pc).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        diagram.insert_association(t, pd).unwrap();
+
+        let mut out = Vec::new();
+        Elm.generate(&diagram, &mut out, &GenOptions::default()).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), concat!(
+            "module Portal exposing (Actor, UseCase(..), actorsFor, portal, title, useCases)\n",
+            "\n",
+            "import Html exposing (Html)\n",
+            "import Html.Events exposing (onClick)\n",
+            "import Set exposing (Set)\n",
+            "\n",
+            "\n",
+            "type alias Actor =\n",
+            "    String\n",
+            "\n",
+            "\n",
+            "type UseCase\n",
+            "    = BanSubscriber\n",
+            "    | PostComment\n",
+            "\n",
+            "\n",
+            "useCases : List UseCase\n",
+            "useCases =\n",
+            "    [ BanSubscriber\n",
+            "    , PostComment\n",
+            "    ]\n",
+            "\n",
+            "\n",
+            "title : UseCase -> String\n",
+            "title useCase =\n",
+            "    case useCase of\n",
+            "        BanSubscriber ->\n",
+            "            \"Ban subscriber\"\n",
+            "\n",
+            "        PostComment ->\n",
+            "            \"Post comment\"\n",
+            "\n",
+            "\n",
+            "actorsFor : UseCase -> Set Actor\n",
+            "actorsFor useCase =\n",
+            "    case useCase of\n",
+            "        BanSubscriber ->\n",
+            "            Set.fromList [ \"Administrator\" ]\n",
+            "\n",
+            "        PostComment ->\n",
+            "            Set.fromList [ \"Administrator\", \"Subscriber\" ]\n",
+            "\n",
+            "\n",
+            "{-| Ban subscriber:\n",
+            "      Keep a troll out.\n",
+            "\n",
+            "    Post comment:\n",
+            "      Preconditions:\n",
+            "      - Logged in\n",
+            "-}\n",
+            "portal : Actor -> (UseCase -> msg) -> Html msg\n",
+            "portal actor toMsg =\n",
+            "    useCases\n",
+            "        |> List.filter (\\useCase -> Set.member actor (actorsFor useCase))\n",
+            "        |> List.map\n",
+            "            (\\useCase ->\n",
+            "                Html.button [ onClick (toMsg useCase) ]\n",
+            "                    [ Html.text (title useCase) ]\n",
+            "            )\n",
+            "        |> Html.div []\n",
+        ));
+    }
+
+    #[test]
+    fn test_invalid_portals() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut out = Vec::new();
+        let err = generate_portal_definition(&mut out, &diagram, "portal").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(err.section(), "portal portal");
+
+        let _ = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        let _ = diagram.insert_use_case(UseCase::new(Rc::from("Log-in"))).unwrap();
+        let err = generate_portal_definition(&mut out, &diagram, "portal").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(out.is_empty());
+
+        let mut diagram = UseCaseDiagram::new();
+        let _ = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        for &name in &["title", "useCases", "actorsFor", "actor", "toMsg"] {
+            let err = generate_portal_definition(&mut out, &diagram, name).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_documentation_comment() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut li = UseCase::new(Rc::from("Log in"));
+        li.description = Some(Rc::from("Ends with -} and opens {- here"));
+        let _ = diagram.insert_use_case(li).unwrap();
+        let mut out = Vec::new();
+        generate_portal_definition(&mut out, &diagram, "portal").unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(concat!(
+            "{-| Log in:\n",
+            "      Ends with - } and opens { - here\n",
+            "-}\n",
+        )));
+    }
+}
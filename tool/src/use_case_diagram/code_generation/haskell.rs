@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use std::io;
+
+use use_case_diagram::{UseCaseDiagram, UseCaseId};
+use use_case_diagram::code_generation::{CodeGenerator, GenOptions, GenerationError,
+                                        SectionContext, button_actors, camel_case,
+                                        generate_note_comments, generate_portal_documentation,
+                                        portal_use_cases};
+
+/// Complete modules, `"haskell"`: the module header, the imports and a
+/// portal definition as in `generate_portal_definition`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Haskell;
+
+impl CodeGenerator for Haskell {
+    fn name(&self) -> &'static str {
+        "haskell"
+    }
+
+    fn generate(&self, diagram: &UseCaseDiagram, mut w: &mut dyn io::Write,
+                options: &GenOptions) -> Result<(), GenerationError> {
+        let diagram = &*options.subdiagram(diagram);
+        (|| {
+            generate_module_header(&mut w, &options.module_name)?;
+            writeln!(w)?;
+            generate_imports(&mut w)?;
+            writeln!(w)
+        })().section(|| format!("header of module {}", options.module_name))?;
+        generate_portal_definition(&mut w, diagram, &options.definition_name)
+    }
+}
+
+/// Generate a module header.
+pub fn generate_module_header<W>(w: &mut W, name: &str) -> io::Result<()>
+    where W: io::Write {
+    writeln!(w, "module {} where", name)?;
+    Ok(())
+}
+
+/// Generate the imports necessary for the other generated code. Nothing is
+/// imported from the Prelude, so fields of the actions record named after
+/// use cases such as Show or Lookup do not clash with Prelude functions.
+pub fn generate_imports<W>(w: &mut W) -> io::Result<()>
+    where W: io::Write {
+    writeln!(w, "import Prelude ()")?;
+    writeln!(w, "import qualified Data.Set as Set")?;
+    writeln!(w, "import Butterfly.Actor (Actor (..))")?;
+    writeln!(w, "import Butterfly.Portal (Button (..), Portal (..))")?;
+    Ok(())
+}
+
+/// The name of the field of the actions record for a use case: its title in
+/// camel case, prefixed with `useCase` if that does not start with a letter
+/// and suffixed with a prime if it is a keyword. Field names have no primes
+/// otherwise, so the portal takes the record as `actions'`, which no field
+/// shadows.
+pub fn field_name(title: &str) -> String {
+    let mut name = camel_case(title, false);
+    if !name.chars().next().is_some_and(char::is_alphabetic) {
+        name = format!("useCase{}", camel_case(title, true));
+    }
+    if KEYWORDS.contains(&&*name) {
+        name.push('\'');
+    }
+    name
+}
+
+const KEYWORDS: &[&str] = &[
+    "case", "class", "data", "default", "deriving", "do", "else", "foreign", "if", "import",
+    "in", "infix", "infixl", "infixr", "instance", "let", "module", "newtype", "of", "then",
+    "type", "where",
+];
+
+/// Generate a Haskell definition for a portal, and the record of actions it
+/// takes, named after it with the first letter capitalized and `Actions`
+/// appended. The record has a field for every use case that gets a button,
+/// named by `field_name`. Buttons and their actors are as in
+/// `purescript::generate_portal_definition`, and so are notes and
+/// documentation. Write failures are annotated with the section being
+/// written. Return an error of kind `InvalidInput` if two use cases get the
+/// same field name, or one gets the name of the portal.
+pub fn generate_portal_definition<W, S>(w: &mut W, diagram: &UseCaseDiagram<S>, name: &str)
+                                        -> Result<(), GenerationError>
+    where W: io::Write, S: AsRef<str> {
+    let invalid_input = |message| {
+        let error = io::Error::new(io::ErrorKind::InvalidInput, message);
+        Err(error).section(|| format!("portal {}", name))
+    };
+    let mut fields = HashMap::new();
+    for use_case_id in portal_use_cases(diagram) {
+        let title = diagram[use_case_id].title.as_ref();
+        let field = field_name(title);
+        if field == name {
+            return invalid_input(format!("use case {:?} has the field name {:?} of the portal",
+                                         title, name));
+        }
+        if let Some(other) = fields.insert(field, title) {
+            return invalid_input(format!("use cases {:?} and {:?} have the same field name",
+                                         other, title));
+        }
+    }
+    let actions_type = format!("{}Actions", camel_case(name, true));
+
+    generate_note_comments(w, diagram, "--")
+        .section(|| format!("notes of portal {}", name))?;
+    generate_actions_type(w, diagram, &actions_type)
+        .section(|| format!("type {}", actions_type))?;
+    generate_portal_documentation(w, diagram, "-- |", "--")
+        .section(|| format!("documentation of portal {}", name))?;
+
+    (|| {
+        writeln!(w, "{} :: {} f -> Portal f", name, actions_type)?;
+        writeln!(w, "{} actions' =", name)?;
+        writeln!(w, "  Portal")?;
+        write!(w, "    [")
+    })().section(|| format!("definition of portal {}", name))?;
+    for (i, use_case_id) in portal_use_cases(diagram).enumerate() {
+        (|| {
+            if i == 0 {
+                write!(w, " ")?;
+            } else {
+                write!(w, "\n    , ")?;
+            }
+            generate_button(w, diagram, use_case_id)
+        })().section(|| format!("button for use case {}", use_case_id))?;
+    }
+    writeln!(w, " ]").section(|| format!("definition of portal {}", name))?;
+
+    Ok(())
+}
+
+fn generate_actions_type<W, S>(w: &mut W, diagram: &UseCaseDiagram<S>, name: &str)
+                               -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    write!(w, "data {} f = {}", name, name)?;
+    let mut empty = true;
+    for (i, use_case_id) in portal_use_cases(diagram).enumerate() {
+        if i == 0 {
+            write!(w, "\n  {{ ")?;
+        } else {
+            write!(w, "\n  , ")?;
+        }
+        write!(w, "{} :: f ()", field_name(diagram[use_case_id].title.as_ref()))?;
+        empty = false;
+    }
+    if !empty {
+        write!(w, "\n  }}")?;
+    }
+    writeln!(w)?;
+    writeln!(w)
+}
+
+fn generate_button<W, S>(w: &mut W, diagram: &UseCaseDiagram<S>, use_case_id: UseCaseId)
+                         -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    let use_case = &diagram[use_case_id];
+    writeln!(w, "Button {:?}", use_case.title.as_ref())?;
+    writeln!(w, "             (Set.fromList")?;
+    write!(w, "                [")?;
+    let actors = button_actors(diagram, use_case_id).into_iter()
+        .map(|actor_id| &diagram[actor_id]);
+    for (i, actor) in actors.enumerate() {
+        if i == 0 {
+            write!(w, " ")?;
+        } else {
+            write!(w, "\n                , ")?;
+        }
+        write!(w, "Actor {:?}", actor.name.as_ref())?;
+    }
+    writeln!(w, " ])")?;
+    write!(w, "             ({} actions')", field_name(use_case.title.as_ref()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use use_case_diagram::{Actor, ActorKind, UseCase};
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_field_name() {
+        assert_eq!(field_name("Ban subscriber"), "banSubscriber");
+        assert_eq!(field_name("2FA setup"), "useCase2FASetup");
+        assert_eq!(field_name("!"), "useCase");
+        assert_eq!(field_name("Type"), "type'");
+    }
+
+    #[test]
+    fn test_portal_definition() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let mut timer = Actor::new(Rc::from("Timer"));
+        timer.kind = ActorKind::Time;
+        let t = diagram.insert_actor(timer).unwrap();
+        let mut bs = UseCase::new(Rc::from("Ban subscriber"));
+        bs.description = Some(Rc::from("Keep a troll out."));
+        let bs = diagram.insert_use_case(bs).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let pd = diagram.insert_use_case(UseCase::new(Rc::from("Purge drafts"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_association(a, pc).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        diagram.insert_association(t, pd).unwrap();
+
+        let mut out = Vec::new();
+        Haskell.generate(&diagram, &mut out, &GenOptions::default()).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), concat!(
+            "module Portal where\n",
+            "\n",
+            "import Prelude ()\n",
+            "import qualified Data.Set as Set\n",
+            "import Butterfly.Actor (Actor (..))\n",
+            "import Butterfly.Portal (Button (..), Portal (..))\n",
+            "\n",
+            "data PortalActions f = PortalActions\n",
+            "  { banSubscriber :: f ()\n",
+            "  , postComment :: f ()\n",
+            "  }\n",
+            "\n",
+            "-- | Ban subscriber:\n",
+            "--   Keep a troll out.\n",
+            "portal :: PortalActions f -> Portal f\n",
+            "portal actions' =\n",
+            "  Portal\n",
+            "    [ Button \"Ban subscriber\"\n",
+            "             (Set.fromList\n",
+            "                [ Actor \"Administrator\" ])\n",
+            "             (banSubscriber actions')\n",
+            "    , Button \"Post comment\"\n",
+            "             (Set.fromList\n",
+            "                [ Actor \"Administrator\"\n",
+            "                , Actor \"Subscriber\" ])\n",
+            "             (postComment actions') ]\n",
+        ));
+    }
+
+    #[test]
+    fn test_prelude_names() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_use_case(UseCase::new(Rc::from("Show"))).unwrap();
+        diagram.insert_association(a, s).unwrap();
+
+        let mut out = Vec::new();
+        Haskell.generate(&diagram, &mut out, &GenOptions::default()).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("import Prelude ()\n"));
+        assert!(out.contains("  { show :: f ()\n"));
+        assert!(out.contains("(show actions') ]\n"));
+    }
+
+    #[test]
+    fn test_invalid_field_names() {
+        let mut diagram = UseCaseDiagram::new();
+        let _ = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        let _ = diagram.insert_use_case(UseCase::new(Rc::from("Log-in"))).unwrap();
+        let mut out = Vec::new();
+        let err = generate_portal_definition(&mut out, &diagram, "portal").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(err.section(), "portal portal");
+        assert!(out.is_empty());
+
+        let mut diagram = UseCaseDiagram::new();
+        let _ = diagram.insert_use_case(UseCase::new(Rc::from("Portal"))).unwrap();
+        let _ = diagram.insert_use_case(UseCase::new(Rc::from("Actions"))).unwrap();
+        let err = generate_portal_definition(&mut out, &diagram, "portal").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(out.is_empty());
+
+        let diagram: UseCaseDiagram = UseCaseDiagram::new();
+        generate_portal_definition(&mut out, &diagram, "portal").unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), concat!(
+            "data PortalActions f = PortalActions\n",
+            "\n",
+            "portal :: PortalActions f -> Portal f\n",
+            "portal actions' =\n",
+            "  Portal\n",
+            "    [ ]\n",
+        ));
+    }
+}
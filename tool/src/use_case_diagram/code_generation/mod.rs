@@ -1 +1,354 @@
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use use_case_diagram::{ActorId, ActorKind, NoteAnchor, SYSTEM_STEREOTYPE, UseCaseDiagram,
+                       UseCaseId};
+
+pub mod dot;
+pub mod elm;
+pub mod haskell;
 pub mod purescript;
+
+/// A language that code can be generated in from a diagram, behind a common
+/// interface so that tools can select targets by name.
+pub trait CodeGenerator {
+    /// The name of the target, unique among `generators`, e.g.
+    /// `"purescript"`.
+    fn name(&self) -> &'static str;
+
+    fn generate(&self, diagram: &UseCaseDiagram, w: &mut dyn io::Write, options: &GenOptions)
+                -> Result<(), GenerationError>;
+}
+
+/// What every target needs to know about the code to generate. Targets
+/// without modules or definitions ignore the names they have no use for.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GenOptions {
+    /// The name of the generated module, or of the graph.
+    pub module_name: String,
+
+    /// The name of the generated definition, such as the portal.
+    pub definition_name: String,
+
+    /// The layers of the diagram in order, e.g. `MVP` before `Phase 2`.
+    pub layers: Vec<String>,
+
+    /// The layer to generate code for, or none to generate code for the
+    /// whole diagram; see `UseCaseDiagram::layer_subdiagram`.
+    pub layer: Option<String>,
+}
+
+impl GenOptions {
+    /// The part of the diagram to generate code for.
+    pub fn subdiagram<'a>(&self, diagram: &'a UseCaseDiagram) -> Cow<'a, UseCaseDiagram> {
+        match self.layer {
+            Some(ref layer) => Cow::Owned(diagram.layer_subdiagram(&self.layers, layer)),
+            None => Cow::Borrowed(diagram),
+        }
+    }
+}
+
+impl Default for GenOptions {
+    fn default() -> Self {
+        GenOptions{module_name: "Portal".to_string(), definition_name: "portal".to_string(),
+                   layers: Vec::new(), layer: None}
+    }
+}
+
+/// Every target, with its default configuration.
+pub fn generators() -> Vec<Box<dyn CodeGenerator>> {
+    vec![
+        Box::new(purescript::PureScript::default()),
+        Box::new(haskell::Haskell),
+        Box::new(elm::Elm),
+        Box::new(dot::Dot{directed: true}),
+        Box::new(dot::Dot{directed: false}),
+    ]
+}
+
+/// The target with the given name, if any.
+pub fn generator_by_name(name: &str) -> Option<Box<dyn CodeGenerator>> {
+    generators().into_iter().find(|generator| generator.name() == name)
+}
+
+/// An error that occurred while generating code, annotated with the section
+/// of the output that was being written.
+#[derive(Debug)]
+pub struct GenerationError {
+    section: String,
+    error: io::Error,
+}
+
+impl GenerationError {
+    /// Annotate an I/O error with the section that was being written.
+    pub fn new(section: String, error: io::Error) -> Self {
+        GenerationError{section, error}
+    }
+
+    /// The section that was being written, e.g. "button for use case 3".
+    pub fn section(&self) -> &str {
+        &self.section
+    }
+
+    /// The kind of the underlying I/O error.
+    pub fn kind(&self) -> io::ErrorKind {
+        self.error.kind()
+    }
+
+    /// The underlying I/O error.
+    pub fn into_inner(self) -> io::Error {
+        self.error
+    }
+}
+
+impl fmt::Display for GenerationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "while writing {}: {}", self.section, self.error)
+    }
+}
+
+impl Error for GenerationError {
+    fn description(&self) -> &str {
+        "code generation failed"
+    }
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<GenerationError> for io::Error {
+    fn from(err: GenerationError) -> Self {
+        io::Error::new(err.kind(), err)
+    }
+}
+
+/// Annotate I/O results with the section that was being written.
+trait SectionContext<T> {
+    fn section<F>(self, section: F) -> Result<T, GenerationError>
+        where F: FnOnce() -> String;
+}
+
+impl<T> SectionContext<T> for io::Result<T> {
+    fn section<F>(self, section: F) -> Result<T, GenerationError>
+        where F: FnOnce() -> String {
+        self.map_err(|error| GenerationError::new(section(), error))
+    }
+}
+
+/// Write every note of the diagram as line comments starting with `prefix`,
+/// ordered by identifier. Every line of a note is labelled with the element
+/// the note is attached to.
+pub(super) fn generate_note_comments<W, S>(w: &mut W, diagram: &UseCaseDiagram<S>,
+                                           prefix: &str) -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    let mut notes = diagram.notes().collect::<Vec<_>>();
+    notes.sort_by_key(|&(note_id, _)| note_id);
+    for (_, note) in notes {
+        let label = match note.anchor {
+            NoteAnchor::Actor(actor_id) =>
+                format!("actor {:?}", diagram[actor_id].name.as_ref()),
+            NoteAnchor::UseCase(use_case_id) =>
+                format!("use case {:?}", diagram[use_case_id].title.as_ref()),
+            NoteAnchor::Association(actor_id, use_case_id) =>
+                format!("association {:?} -- {:?}",
+                        diagram[actor_id].name.as_ref(),
+                        diagram[use_case_id].title.as_ref()),
+        };
+        for line in note.text.as_ref().lines() {
+            writeln!(w, "{} {}: {}", prefix, label, line)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the descriptions, preconditions, postconditions and flows of the
+/// use cases that are not abstract as documentation lines, the first of
+/// which starts with `first` and every other with `rest`, trimmed where the
+/// line is otherwise empty. Values of several lines are indented under their
+/// first, so that none of them ends the documentation early.
+pub(super) fn generate_portal_documentation<W, S>(w: &mut W, diagram: &UseCaseDiagram<S>,
+                                                  first: &str, rest: &str) -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    let mut lines = Vec::new();
+    let use_cases =
+        diagram.use_cases_in_order().into_iter()
+        .filter(|&(_, use_case)| !use_case.is_abstract)
+        .filter(|&(_, use_case)| use_case.deprecated.is_some() ||
+                                 use_case.description.is_some() ||
+                                 !use_case.preconditions.is_empty() ||
+                                 !use_case.postconditions.is_empty() ||
+                                 !use_case.main_flow.is_empty() ||
+                                 !use_case.alternative_flows.is_empty())
+        .collect::<Vec<_>>();
+    for (i, (_, use_case)) in use_cases.into_iter().enumerate() {
+        if i > 0 {
+            lines.push(String::new());
+        }
+        push_value(&mut lines, "", &format!("{}:", use_case.title.as_ref()));
+        if let Some(ref reason) = use_case.deprecated {
+            push_value(&mut lines, "  Deprecated: ", reason.as_ref());
+        }
+        if let Some(ref description) = use_case.description {
+            for line in description.as_ref().lines() {
+                lines.push(format!("  {}", line));
+            }
+        }
+        for &(heading, conditions) in &[("Preconditions", &use_case.preconditions),
+                                        ("Postconditions", &use_case.postconditions)] {
+            if conditions.is_empty() {
+                continue;
+            }
+            lines.push(format!("  {}:", heading));
+            for condition in conditions {
+                push_value(&mut lines, "  - ", condition.as_ref());
+            }
+        }
+        let main_flow = ("Main flow", &use_case.main_flow);
+        let alternative_flows =
+            use_case.alternative_flows.iter().map(|flow| (flow.name.as_ref(), &flow.steps));
+        for (heading, steps) in Some(main_flow).into_iter().chain(alternative_flows) {
+            if steps.is_empty() {
+                continue;
+            }
+            lines.push(format!("  {}:", heading));
+            for (i, step) in steps.iter().enumerate() {
+                push_value(&mut lines, &format!("  {}. ", i + 1), step.as_ref());
+            }
+        }
+    }
+    for (i, line) in lines.iter().enumerate() {
+        let prefix = if i == 0 { first } else { rest };
+        if line.trim().is_empty() {
+            writeln!(w, "{}", prefix.trim_end())?;
+        } else {
+            writeln!(w, "{} {}", prefix, line)?;
+        }
+    }
+    Ok(())
+}
+
+/// Add the lines of a value to documentation lines, the first after `head`
+/// and the others indented as far.
+fn push_value(lines: &mut Vec<String>, head: &str, value: &str) {
+    let indent = " ".repeat(head.chars().count());
+    lines.push(head.to_string());
+    for (i, line) in value.lines().enumerate() {
+        if i > 0 {
+            lines.push(indent.clone());
+        }
+        lines.last_mut().expect("a line was pushed").push_str(line);
+    }
+}
+
+/// The use cases that get a button, in the order of `use_cases_in_order`:
+/// all but the abstract ones and those whose actors are all of kind `Time`.
+pub(super) fn portal_use_cases<S>(diagram: &UseCaseDiagram<S>)
+                                  -> impl Iterator<Item = UseCaseId> + '_
+    where S: AsRef<str> {
+    diagram.use_cases_in_order().into_iter()
+        .filter(|&(_, use_case)| !use_case.is_abstract)
+        .map(|(use_case_id, _)| use_case_id)
+        .filter(move |&use_case_id| {
+            let actor_ids = diagram.effective_actors(use_case_id);
+            actor_ids.is_empty() ||
+            actor_ids.iter()
+            .any(|&actor_id| diagram[actor_id].kind != ActorKind::Time)
+        })
+}
+
+/// The actors that a button lists for its use case, in the order of
+/// `actors_in_order`: the effective actors that are people, and not systems
+/// by stereotype.
+pub(super) fn button_actors<S>(diagram: &UseCaseDiagram<S>, use_case_id: UseCaseId)
+                               -> Vec<ActorId>
+    where S: AsRef<str> {
+    let mut actor_ids =
+        diagram.effective_actors(use_case_id).into_iter()
+        .filter(|&actor_id| {
+            let actor = &diagram[actor_id];
+            actor.kind.is_human() && !actor.has_stereotype(SYSTEM_STEREOTYPE)
+        })
+        .collect::<Vec<_>>();
+    diagram.sort_actors(&mut actor_ids);
+    actor_ids
+}
+
+/// A title in camel case, for identifiers: its words of letters and digits
+/// joined, each capitalized but the first, which is capitalized only if
+/// `capitalize`. Fails to be an identifier if the title has no letters or
+/// starts with a digit.
+pub(super) fn camel_case(title: &str, capitalize: bool) -> String {
+    let mut name = String::new();
+    let words = title.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty());
+    for (i, word) in words.enumerate() {
+        let mut chars = word.chars();
+        let first = chars.next().expect("nonempty word");
+        if i == 0 && !capitalize {
+            name.extend(first.to_lowercase());
+        } else {
+            name.extend(first.to_uppercase());
+        }
+        name.extend(chars);
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    use use_case_diagram::{Actor, UseCase};
+
+    #[test]
+    fn test_generator_by_name() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        let names = generators().iter().map(|generator| generator.name()).collect::<Vec<_>>();
+        for name in names {
+            let generator = generator_by_name(name).unwrap();
+            assert_eq!(generator.name(), name);
+            let mut output = Vec::new();
+            generator.generate(&diagram, &mut output, &GenOptions::default()).unwrap();
+            assert!(String::from_utf8(output).unwrap().contains("Ban subscriber"), "{}", name);
+        }
+        assert!(generator_by_name("cobol").is_none());
+    }
+
+    #[test]
+    fn test_multi_line_documentation() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut bs = UseCase::new(Rc::from("Ban\nsubscriber"));
+        bs.preconditions.push(Rc::from("Logged in\nas an administrator"));
+        bs.main_flow.push(Rc::from("Confirm\n\nthe ban"));
+        diagram.insert_use_case(bs).unwrap();
+        let mut output = Vec::new();
+        generate_portal_documentation(&mut output, &diagram, "-- |", "--").unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), concat!(
+            "-- | Ban\n",
+            "-- subscriber:\n",
+            "--   Preconditions:\n",
+            "--   - Logged in\n",
+            "--     as an administrator\n",
+            "--   Main flow:\n",
+            "--   1. Confirm\n",
+            "--\n",
+            "--      the ban\n",
+        ));
+    }
+
+    #[test]
+    fn test_camel_case() {
+        assert_eq!(camel_case("Ban subscriber", false), "banSubscriber");
+        assert_eq!(camel_case("Ban subscriber", true), "BanSubscriber");
+        assert_eq!(camel_case("  Set up 2FA (optional)", false), "setUp2FAOptional");
+        assert_eq!(camel_case("Éditer la page", false), "éditerLaPage");
+        assert_eq!(camel_case("?", true), "");
+    }
+}
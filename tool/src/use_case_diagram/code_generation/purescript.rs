@@ -1,72 +1,299 @@
+use std::collections::HashSet;
 use std::io;
 
-use use_case_diagram::UseCaseDiagram;
+use use_case_diagram::{PACKAGE_SEPARATOR, UseCaseDiagram, UseCaseId};
+use use_case_diagram::code_generation::{CodeGenerator, GenOptions, GenerationError,
+                                        SectionContext, button_actors, generate_note_comments,
+                                        generate_portal_documentation, portal_use_cases};
+
+/// Complete modules, `"purescript"`: the module header, the imports and a
+/// portal definition as in `generate_portal_definition`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PureScript {
+    pub collection: PortalCollection,
+}
+
+impl Default for PureScript {
+    fn default() -> Self {
+        PureScript{collection: PortalCollection::List}
+    }
+}
+
+impl CodeGenerator for PureScript {
+    fn name(&self) -> &'static str {
+        "purescript"
+    }
+
+    fn generate(&self, diagram: &UseCaseDiagram, mut w: &mut dyn io::Write,
+                options: &GenOptions) -> Result<(), GenerationError> {
+        let diagram = &*options.subdiagram(diagram);
+        (|| {
+            generate_module_header(&mut w, &options.module_name)?;
+            writeln!(w)?;
+            generate_imports(&mut w, self.collection)?;
+            writeln!(w)
+        })().section(|| format!("header of module {}", options.module_name))?;
+        generate_portal_definition(&mut w, diagram, &options.definition_name, self.collection)
+    }
+}
 
 /// Generate a module header.
 pub fn generate_module_header<W>(w: &mut W, name: &str) -> io::Result<()>
     where W: io::Write {
-    write!(w, "module {} where\n", name)?;
+    writeln!(w, "module {} where", name)?;
     Ok(())
 }
 
+/// The collection in which a generated portal stores its buttons.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PortalCollection {
+    /// A `Portal` wrapping a `List` of buttons, in use case order.
+    List,
+
+    /// A `Map String Button` keyed by use case title. Use case titles must be
+    /// unique in this mode.
+    Map,
+}
+
 /// Generate the imports necessary for the other generated code.
-pub fn generate_imports<W>(w: &mut W) -> io::Result<()>
+pub fn generate_imports<W>(w: &mut W, collection: PortalCollection) -> io::Result<()>
     where W: io::Write {
-    write!(w, "import Prelude\n")?;
-    write!(w, "import Data.List as List\n")?;
-    write!(w, "import Data.Set as Set\n")?;
-    write!(w, "import Butterfly.Actor (Actor (..))\n")?;
-    write!(w, "import Butterfly.Portal (Button (..), Portal (..))\n")?;
+    writeln!(w, "import Prelude")?;
+    match collection {
+        PortalCollection::List => {
+            writeln!(w, "import Data.List as List")?;
+        },
+        PortalCollection::Map => {
+            writeln!(w, "import Data.Map (Map)")?;
+            writeln!(w, "import Data.Map as Map")?;
+            writeln!(w, "import Data.Tuple (Tuple (..))")?;
+        },
+    }
+    writeln!(w, "import Data.Set as Set")?;
+    writeln!(w, "import Butterfly.Actor (Actor (..))")?;
+    match collection {
+        PortalCollection::List =>
+            writeln!(w, "import Butterfly.Portal (Button (..), Portal (..))")?,
+        PortalCollection::Map =>
+            writeln!(w, "import Butterfly.Portal (Button (..))")?,
+    }
     Ok(())
 }
 
-/// Generate a PureScript definition for a portal.
-pub fn generate_portal_definition<W>(w: &mut W, diagram: &UseCaseDiagram, name: &str)
-                                     -> io::Result<()>
-    where W: io::Write {
-    write!(w, "{}\n", name)?;
-    write!(w, "  :: ∀ f\n")?;
+/// Generate a PureScript definition for a portal. Every button lists the
+/// actors associated with its use case, including actors that inherit the
+/// association from an ancestor, but only people: not actors of kind `System`
+/// or `Time`, nor actors with the `system` stereotype (see
+/// `SYSTEM_STEREOTYPE`). Abstract use cases and use cases triggered only by
+/// time get no button at all. Buttons and their actors are ordered as by
+/// `use_cases_in_order` and `actors_in_order`. Notes are emitted as comments
+/// above the definition, and use case descriptions, preconditions,
+/// postconditions, and flows as its documentation. Write failures are
+/// annotated with the section being written. In `Map` mode, return an error
+/// of kind `InvalidInput` if two use cases share a title.
+pub fn generate_portal_definition<W, S>(w: &mut W, diagram: &UseCaseDiagram<S>,
+                                        name: &str, collection: PortalCollection)
+                                        -> Result<(), GenerationError>
+    where W: io::Write, S: AsRef<str> {
+    if collection == PortalCollection::Map {
+        let mut titles = HashSet::new();
+        for (_, use_case) in diagram.use_cases() {
+            if !titles.insert(use_case.title.as_ref()) {
+                let error = io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("duplicate use case title {:?}", use_case.title.as_ref()),
+                );
+                return Err(error).section(|| format!("portal {}", name));
+            }
+        }
+    }
+
+    generate_note_comments(w, diagram, "--")
+        .section(|| format!("notes of portal {}", name))?;
+    generate_portal_documentation(w, diagram, "-- |", "-- |")
+        .section(|| format!("documentation of portal {}", name))?;
+    generate_portal_signature(w, diagram, name, collection)
+        .section(|| format!("type signature of portal {}", name))?;
+
+    (|| {
+        writeln!(w, "{} actions =", name)?;
+        match collection {
+            PortalCollection::List => writeln!(w, "  Portal <<< List.fromFoldable $")?,
+            PortalCollection::Map => writeln!(w, "  Map.fromFoldable $")?,
+        }
+        write!(w, "    [")
+    })().section(|| format!("definition of portal {}", name))?;
+    for (i, use_case_id) in portal_use_cases(diagram).enumerate() {
+        (|| {
+            if i == 0 {
+                write!(w, " ")?;
+            } else {
+                write!(w, "\n    , ")?;
+            }
+            generate_button(w, diagram, use_case_id, collection)
+        })().section(|| format!("button for use case {}", use_case_id))?;
+    }
+    writeln!(w, " ]").section(|| format!("definition of portal {}", name))?;
+
+    Ok(())
+}
+
+/// The name of the module for the given package, nested in the base module.
+/// Every package name becomes a module name segment of its letters and
+/// digits, with the first letter capitalized. Return an error of kind
+/// `InvalidInput` if a package name contains `PACKAGE_SEPARATOR`, as it
+/// would not survive a round trip through JSON, or if its segment does not
+/// start with an uppercase letter, e.g. because it starts with a digit.
+pub fn package_module_name(base: &str, package: &[&str]) -> io::Result<String> {
+    let invalid_input = |message| Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+    let mut module_name = base.to_string();
+    for name in package {
+        if name.contains(PACKAGE_SEPARATOR) {
+            return invalid_input(format!("package name {:?} contains {:?}",
+                                         name, PACKAGE_SEPARATOR));
+        }
+        let mut letters = name.chars().filter(|c| c.is_alphanumeric());
+        let segment = letters.next().into_iter().flat_map(char::to_uppercase)
+            .chain(letters)
+            .collect::<String>();
+        if !segment.chars().next().is_some_and(char::is_uppercase) {
+            return invalid_input(format!("package name {:?} gives no valid module name",
+                                         name));
+        }
+        module_name.push('.');
+        module_name.push_str(&segment);
+    }
+    Ok(module_name)
+}
+
+/// Generate a complete module, named by `package_module_name`, for the
+/// given package: the imports and a portal definition named `portal` for the
+/// use cases directly in the package, as in `generate_portal_definition`.
+/// Use the empty package for the use cases at the top level, and
+/// `UseCaseDiagram::packages` for the others. Return an error of kind
+/// `InvalidInput` if `package_module_name` does, or if another package of
+/// the diagram gets the same module name.
+pub fn generate_package_module<W, S>(w: &mut W, diagram: &UseCaseDiagram<S>, base: &str,
+                                     package: &[&str], collection: PortalCollection)
+                                     -> Result<(), GenerationError>
+    where W: io::Write, S: AsRef<str> + Clone {
+    let section = || format!("module for package {:?}", package);
+    let module_name = package_module_name(base, package).section(section)?;
+    for other in diagram.packages() {
+        let same_name = package_module_name(base, &other).is_ok_and(|name| name == module_name);
+        if other != package && same_name {
+            let error = io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("packages {:?} and {:?} have the same module name", package, other),
+            );
+            return Err(error).section(section);
+        }
+    }
+    (|| {
+        generate_module_header(w, &module_name)?;
+        writeln!(w)?;
+        generate_imports(w, collection)?;
+        writeln!(w)
+    })().section(|| format!("header of module {}", module_name))?;
+    generate_portal_definition(w, &diagram.package_subdiagram(package), "portal", collection)
+}
+
+/// Generate a PureScript definition listing, for every use case that
+/// includes other use cases, the titles of the use cases it includes.
+pub fn generate_includes_definition<W, S>(w: &mut W, diagram: &UseCaseDiagram<S>,
+                                          name: &str)
+                                          -> Result<(), GenerationError>
+    where W: io::Write, S: AsRef<str> {
+    let mut bases = diagram.includes().map(|(base, _)| base).collect::<Vec<_>>();
+    bases.sort();
+    bases.dedup();
+
+    (|| {
+        writeln!(w, "{}", name)?;
+        writeln!(w, "  :: Array {{ useCase :: String, includes :: Array String }}")?;
+        writeln!(w, "{} =", name)?;
+        write!(w, "  [")
+    })().section(|| format!("definition of includes {}", name))?;
+    for (i, &base) in bases.iter().enumerate() {
+        (|| {
+            if i == 0 {
+                write!(w, " ")?;
+            } else {
+                write!(w, "\n  , ")?;
+            }
+            let title = diagram[base].title.as_ref();
+            write!(w, "{{ useCase: {:?}, includes: [", title)?;
+            let mut included = diagram.included_use_cases(base).collect::<Vec<_>>();
+            included.sort();
+            for (i, included) in included.into_iter().enumerate() {
+                if i > 0 {
+                    write!(w, ",")?;
+                }
+                let title = diagram[included].title.as_ref();
+                write!(w, " {:?}", title)?;
+            }
+            write!(w, " ] }}")
+        })().section(|| format!("includes of use case {}", base))?;
+    }
+    writeln!(w, " ]").section(|| format!("definition of includes {}", name))?;
+
+    Ok(())
+}
+
+fn generate_portal_signature<W, S>(w: &mut W, diagram: &UseCaseDiagram<S>,
+                                   name: &str, collection: PortalCollection)
+                                   -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    writeln!(w, "{}", name)?;
+    writeln!(w, "  :: ∀ f")?;
     write!(w, "   . {{")?;
-    for (i, (_, use_case)) in diagram.use_cases().enumerate() {
+    for (i, use_case_id) in portal_use_cases(diagram).enumerate() {
         if i == 0 {
             write!(w, " ")?;
         } else {
             write!(w, "\n     , ")?;
         }
-        write!(w, "{:?} :: f Unit", use_case.title)?;
+        write!(w, "{:?} :: f Unit", diagram[use_case_id].title.as_ref())?;
+    }
+    writeln!(w, " }}")?;
+    match collection {
+        PortalCollection::List => writeln!(w, "  -> Portal f")?,
+        PortalCollection::Map => writeln!(w, "  -> Map String (Button f)")?,
     }
-    write!(w, " }}\n")?;
-    write!(w, "  -> Portal f\n")?;
+    Ok(())
+}
 
-    write!(w, "{} actions =\n", name)?;
-    write!(w, "  Portal <<< List.fromFoldable $\n")?;
-    write!(w, "    [")?;
-    for (i, (use_case_id, use_case)) in diagram.use_cases().enumerate() {
+fn generate_button<W, S>(w: &mut W, diagram: &UseCaseDiagram<S>, use_case_id: UseCaseId,
+                         collection: PortalCollection)
+                         -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    let use_case = &diagram[use_case_id];
+    let indent = match collection {
+        PortalCollection::List => "",
+        PortalCollection::Map => {
+            writeln!(w, "Tuple {:?}", use_case.title.as_ref())?;
+            write!(w, "            (")?;
+            "       "
+        },
+    };
+    writeln!(w, "Button {:?}", use_case.title.as_ref())?;
+    writeln!(w, "{}             (Set.fromFoldable", indent)?;
+    write!(w, "{}                [", indent)?;
+    let actors = button_actors(diagram, use_case_id).into_iter()
+        .map(|actor_id| &diagram[actor_id]);
+    for (i, actor) in actors.enumerate() {
         if i == 0 {
             write!(w, " ")?;
         } else {
-            write!(w, "\n    , ")?;
+            write!(w, "\n{}                , ", indent)?;
         }
-        write!(w, "Button {:?}\n", use_case.title)?;
-        write!(w, "             (Set.fromFoldable\n")?;
-        write!(w, "                [")?;
-        let actors =
-            diagram.associations()
-            .filter(|&(_, assoc_use_case_id)| assoc_use_case_id == use_case_id)
-            .map(|(actor_id, _)| diagram.actor(actor_id).unwrap());
-        for (i, actor) in actors.enumerate() {
-            if i == 0 {
-                write!(w, " ")?;
-            } else {
-                write!(w, "\n                , ")?;
-            }
-            write!(w, "Actor {:?}", actor.name)?;
-        }
-        write!(w, " ])\n")?;
-        write!(w, "             actions.{:?}", use_case.title)?;
+        write!(w, "Actor {:?}", actor.name.as_ref())?;
+    }
+    writeln!(w, " ])")?;
+    write!(w, "{}             actions.{:?}", indent, use_case.title.as_ref())?;
+    if collection == PortalCollection::Map {
+        write!(w, ")")?;
     }
-    write!(w, " ]\n")?;
-
     Ok(())
 }
 
@@ -74,38 +301,41 @@ pub fn generate_portal_definition<W>(w: &mut W, diagram: &UseCaseDiagram, name:
 mod tests {
     use super::*;
 
-    use use_case_diagram::{Actor, UseCase};
+    use use_case_diagram::{Actor, ActorKind, Flow, Note, NoteAnchor, UseCase};
 
+    use std::error::Error;
     use std::fs::File;
     use std::rc::Rc;
 
     #[test]
     fn test_empty() {
-        let diagram = UseCaseDiagram::new();
+        let diagram: UseCaseDiagram = UseCaseDiagram::new();
         generate_module_header(&mut io::stdout(), "ExamplePortal").unwrap();
-        generate_imports(&mut io::stdout()).unwrap();
-        generate_portal_definition(&mut io::stdout(), &diagram, "portal")
+        generate_imports(&mut io::stdout(), PortalCollection::List).unwrap();
+        generate_portal_definition(&mut io::stdout(), &diagram, "portal",
+                                   PortalCollection::List)
             .unwrap();
     }
 
     #[test]
     fn test_single_use_case() {
         let mut diagram = UseCaseDiagram::new();
-        let _ = diagram.insert_use_case(UseCase{title: Rc::from("Ban subscriber")});
+        let _ = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
         generate_module_header(&mut io::stdout(), "ExamplePortal").unwrap();
-        generate_imports(&mut io::stdout()).unwrap();
-        generate_portal_definition(&mut io::stdout(), &diagram, "portal")
+        generate_imports(&mut io::stdout(), PortalCollection::List).unwrap();
+        generate_portal_definition(&mut io::stdout(), &diagram, "portal",
+                                   PortalCollection::List)
             .unwrap();
     }
 
     #[test]
     fn test_many_use_cases() {
         let mut diagram = UseCaseDiagram::new();
-        let a = diagram.insert_actor(Actor{name: Rc::from("Administrator")});
-        let s = diagram.insert_actor(Actor{name: Rc::from("Subscriber")});
-        let bs = diagram.insert_use_case(UseCase{title: Rc::from("Ban subscriber")});
-        let cs = diagram.insert_use_case(UseCase{title: Rc::from("Create subscriber")});
-        let pc = diagram.insert_use_case(UseCase{title: Rc::from("Post comment")});
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let cs = diagram.insert_use_case(UseCase::new(Rc::from("Create subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
         diagram.insert_association(a, bs).unwrap();
         diagram.insert_association(a, cs).unwrap();
         diagram.insert_association(a, pc).unwrap();
@@ -113,8 +343,394 @@ mod tests {
         diagram.insert_association(s, pc).unwrap();
         let mut file = File::create("/tmp/ExamplePortal.purs").unwrap();
         generate_module_header(&mut file, "ExamplePortal").unwrap();
-        generate_imports(&mut file).unwrap();
-        generate_portal_definition(&mut file, &diagram, "portal")
+        generate_imports(&mut file, PortalCollection::List).unwrap();
+        generate_portal_definition(&mut file, &diagram, "portal",
+                                   PortalCollection::List)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_map_collection() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+
+        let mut list = Vec::new();
+        generate_imports(&mut list, PortalCollection::List).unwrap();
+        generate_portal_definition(&mut list, &diagram, "portal",
+                                   PortalCollection::List)
+            .unwrap();
+        let list = String::from_utf8(list).unwrap();
+
+        let mut map = Vec::new();
+        generate_imports(&mut map, PortalCollection::Map).unwrap();
+        generate_portal_definition(&mut map, &diagram, "portal",
+                                   PortalCollection::Map)
+            .unwrap();
+        let map = String::from_utf8(map).unwrap();
+
+        assert_ne!(list, map);
+        assert!(list.contains("import Data.List as List\n"));
+        assert!(list.contains("  Portal <<< List.fromFoldable $\n"));
+        assert!(!list.contains("Map"));
+        assert_eq!(map, concat!(
+            "import Prelude\n",
+            "import Data.Map (Map)\n",
+            "import Data.Map as Map\n",
+            "import Data.Tuple (Tuple (..))\n",
+            "import Data.Set as Set\n",
+            "import Butterfly.Actor (Actor (..))\n",
+            "import Butterfly.Portal (Button (..))\n",
+            "portal\n",
+            "  :: ∀ f\n",
+            "   . { \"Ban subscriber\" :: f Unit }\n",
+            "  -> Map String (Button f)\n",
+            "portal actions =\n",
+            "  Map.fromFoldable $\n",
+            "    [ Tuple \"Ban subscriber\"\n",
+            "            (Button \"Ban subscriber\"\n",
+            "                    (Set.fromFoldable\n",
+            "                       [ Actor \"Administrator\" ])\n",
+            "                    actions.\"Ban subscriber\") ]\n",
+        ));
+    }
+
+    #[test]
+    fn test_map_collection_duplicate_titles() {
+        let mut diagram = UseCaseDiagram::new();
+        let _ = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let _ = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+
+        let mut out = Vec::new();
+        let err = generate_portal_definition(&mut out, &diagram, "portal",
+                                             PortalCollection::Map)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(err.section(), "portal portal");
+        assert!(out.is_empty());
+
+        generate_portal_definition(&mut out, &diagram, "portal",
+                                   PortalCollection::List)
+            .unwrap();
+    }
+
+    struct FailingWriter {
+        remaining: usize,
+    }
+
+    impl io::Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.remaining == 0 {
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe"));
+            }
+            let n = buf.len().min(self.remaining);
+            self.remaining -= n;
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_error_section() {
+        let mut diagram = UseCaseDiagram::new();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+
+        let mut w = FailingWriter{remaining: 10};
+        let err = generate_portal_definition(&mut w, &diagram, "portal",
+                                             PortalCollection::List)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(err.section(), "type signature of portal portal");
+
+        let mut w = FailingWriter{remaining: 130};
+        let err = generate_portal_definition(&mut w, &diagram, "portal",
+                                             PortalCollection::List)
+            .unwrap_err();
+        assert_eq!(err.section(), format!("button for use case {}", bs));
+        assert_eq!(err.to_string(),
+                   format!("while writing button for use case {}: broken pipe", bs));
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn test_includes_definition() {
+        let mut diagram = UseCaseDiagram::new();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        let vc = diagram.insert_use_case(UseCase::new(Rc::from("Verify captcha"))).unwrap();
+        diagram.insert_include(pc, li).unwrap();
+        diagram.insert_include(pc, vc).unwrap();
+        diagram.insert_include(li, vc).unwrap();
+
+        let mut out = Vec::new();
+        generate_includes_definition(&mut out, &diagram, "includes").unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), concat!(
+            "includes\n",
+            "  :: Array { useCase :: String, includes :: Array String }\n",
+            "includes =\n",
+            "  [ { useCase: \"Post comment\", includes: [ \"Log in\", \"Verify captcha\" ] }\n",
+            "  , { useCase: \"Log in\", includes: [ \"Verify captcha\" ] } ]\n",
+        ));
+    }
+
+    #[test]
+    fn test_inherited_actors() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        diagram.insert_actor_generalization(a, s).unwrap();
+
+        let mut out = Vec::new();
+        generate_portal_definition(&mut out, &diagram, "portal", PortalCollection::List)
+            .unwrap();
+        assert!(String::from_utf8(out).unwrap().contains(concat!(
+            "                [ Actor \"Administrator\"\n",
+            "                , Actor \"Subscriber\" ])\n",
+        )));
+    }
+
+
+    #[test]
+    fn test_notes_as_comments() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_note(Note{anchor: NoteAnchor::UseCase(bs),
+                                 text: Rc::from("Bans are permanent.")})
+            .unwrap();
+        diagram.insert_note(Note{anchor: NoteAnchor::Actor(a),
+                                 text: Rc::from("Staff only.")})
+            .unwrap();
+
+        let mut out = Vec::new();
+        generate_portal_definition(&mut out, &diagram, "portal", PortalCollection::List)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with(concat!(
+            "-- use case \"Ban subscriber\": Bans are permanent.\n",
+            "-- actor \"Administrator\": Staff only.\n",
+            "portal\n",
+        )));
+    }
+
+
+    #[test]
+    fn test_system_actors_excluded() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let mut scheduler = Actor::new(Rc::from("Scheduler"));
+        scheduler.stereotypes.push(Rc::from("system"));
+        let s = diagram.insert_actor(scheduler).unwrap();
+        let pa = diagram.insert_use_case(UseCase::new(Rc::from("Purge accounts"))).unwrap();
+        diagram.insert_association(a, pa).unwrap();
+        diagram.insert_association(s, pa).unwrap();
+
+        let mut out = Vec::new();
+        generate_portal_definition(&mut out, &diagram, "portal", PortalCollection::List)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Actor \"Administrator\""));
+        assert!(!out.contains("Scheduler"));
+    }
+
+
+    #[test]
+    fn test_descriptions_as_documentation() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut ban = UseCase::new(Rc::from("Ban subscriber"));
+        ban.description = Some(Rc::from("Prevent a subscriber\nfrom logging in."));
+        diagram.insert_use_case(ban).unwrap();
+        diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        let mut post = UseCase::new(Rc::from("Post comment"));
+        post.description = Some(Rc::from("Reply to an article."));
+        post.preconditions.push(Rc::from("The subscriber is logged in."));
+        post.postconditions.push(Rc::from("The comment is visible."));
+        post.postconditions.push(Rc::from("The author is notified."));
+        diagram.insert_use_case(post).unwrap();
+
+        let mut out = Vec::new();
+        generate_portal_definition(&mut out, &diagram, "portal", PortalCollection::List)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with(concat!(
+            "-- | Ban subscriber:\n",
+            "-- |   Prevent a subscriber\n",
+            "-- |   from logging in.\n",
+            "-- |\n",
+            "-- | Post comment:\n",
+            "-- |   Reply to an article.\n",
+            "-- |   Preconditions:\n",
+            "-- |   - The subscriber is logged in.\n",
+            "-- |   Postconditions:\n",
+            "-- |   - The comment is visible.\n",
+            "-- |   - The author is notified.\n",
+            "portal\n",
+        )));
+    }
+
+    #[test]
+    fn test_time_triggered_use_cases_excluded() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let mut timer = Actor::new(Rc::from("Timer"));
+        timer.kind = ActorKind::Time;
+        let t = diagram.insert_actor(timer).unwrap();
+        let pa = diagram.insert_use_case(UseCase::new(Rc::from("Purge accounts"))).unwrap();
+        let sd = diagram.insert_use_case(UseCase::new(Rc::from("Send digest"))).unwrap();
+        diagram.insert_association(a, pa).unwrap();
+        diagram.insert_association(t, pa).unwrap();
+        diagram.insert_association(t, sd).unwrap();
+
+        let mut out = Vec::new();
+        generate_portal_definition(&mut out, &diagram, "portal", PortalCollection::List)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Button \"Purge accounts\""));
+        assert!(!out.contains("Timer"));
+        assert!(!out.contains("Send digest"));
+    }
+
+    #[test]
+    fn test_abstract_use_cases_excluded() {
+        let mut diagram = UseCaseDiagram::new();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let mut pay = UseCase::new(Rc::from("Pay"));
+        pay.is_abstract = true;
+        pay.description = Some(Rc::from("Settle the bill."));
+        let p = diagram.insert_use_case(pay).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Pay by card"))).unwrap();
+        diagram.insert_use_case_generalization(pc, p).unwrap();
+        diagram.insert_association(s, p).unwrap();
+
+        let mut out = Vec::new();
+        generate_portal_definition(&mut out, &diagram, "portal", PortalCollection::List)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Button \"Pay by card\""));
+        assert!(!out.contains("\"Pay\""));
+        assert!(!out.contains("Settle"));
+    }
+
+    #[test]
+    fn test_flows_as_documentation() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut log_in = UseCase::new(Rc::from("Log in"));
+        log_in.main_flow.push(Rc::from("The subscriber enters their credentials."));
+        log_in.main_flow.push(Rc::from("The system opens the front page."));
+        log_in.alternative_flows.push(Flow{
+            name: Rc::from("Wrong password"),
+            steps: vec![Rc::from("The system shows an error.")],
+        });
+        diagram.insert_use_case(log_in).unwrap();
+
+        let mut out = Vec::new();
+        generate_portal_definition(&mut out, &diagram, "portal", PortalCollection::List)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with(concat!(
+            "-- | Log in:\n",
+            "-- |   Main flow:\n",
+            "-- |   1. The subscriber enters their credentials.\n",
+            "-- |   2. The system opens the front page.\n",
+            "-- |   Wrong password:\n",
+            "-- |   1. The system shows an error.\n",
+            "portal\n",
+        )));
+    }
+
+    #[test]
+    fn test_package_module() {
+        assert_eq!(package_module_name("Portal", &["billing", "Open invoices"]).unwrap(),
+                   "Portal.Billing.Openinvoices");
+        for package in &[&["Billing::Invoices"][..], &["2FA"], &["--"], &["Billing", ""]] {
+            let err = package_module_name("Portal", package).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+
+        let mut diagram = UseCaseDiagram::new();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let mut cs = UseCase::new(Rc::from("Cancel subscription"));
+        cs.package = vec![Rc::from("Billing")];
+        let cs = diagram.insert_use_case(cs).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        diagram.insert_association(s, cs).unwrap();
+        diagram.insert_association(s, li).unwrap();
+        assert_eq!(diagram.packages().into_iter().collect::<Vec<_>>(), vec![vec!["Billing"]]);
+
+        let mut out = Vec::new();
+        generate_package_module(&mut out, &diagram, "Portal", &["Billing"],
+                                PortalCollection::List)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("module Portal.Billing where\n\nimport Prelude\n"));
+        assert!(out.contains("\"Cancel subscription\""));
+        assert!(!out.contains("\"Log in\""));
+
+        let mut ci = UseCase::new(Rc::from("Cancel invoice"));
+        ci.package = vec![Rc::from("billing")];
+        diagram.insert_use_case(ci).unwrap();
+        let err = generate_package_module(&mut Vec::new(), &diagram, "Portal", &["Billing"],
+                                          PortalCollection::List)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(err.section(), "module for package [\"Billing\"]");
+    }
+
+    #[test]
+    fn test_deprecation_as_documentation() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut fax = UseCase::new(Rc::from("Fax comment"));
+        fax.deprecated = Some(Rc::from("Use Post comment instead."));
+        diagram.insert_use_case(fax).unwrap();
+
+        let mut out = Vec::new();
+        generate_portal_definition(&mut out, &diagram, "portal", PortalCollection::List)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with(concat!(
+            "-- | Fax comment:\n",
+            "-- |   Deprecated: Use Post comment instead.\n",
+            "portal\n",
+        )));
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut administrator = Actor::new(Rc::from("Administrator"));
+        administrator.sort_key = Some(2);
+        let a = diagram.insert_actor(administrator).unwrap();
+        let mut moderator = Actor::new(Rc::from("Moderator"));
+        moderator.sort_key = Some(1);
+        let m = diagram.insert_actor(moderator).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let mut log_in = UseCase::new(Rc::from("Log in"));
+        log_in.sort_key = Some(0);
+        let li = diagram.insert_use_case(log_in).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_association(m, bs).unwrap();
+        diagram.insert_association(a, li).unwrap();
+
+        let mut out = Vec::new();
+        generate_portal_definition(&mut out, &diagram, "portal", PortalCollection::List)
             .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(concat!(
+            "   . { \"Log in\" :: f Unit\n",
+            "     , \"Ban subscriber\" :: f Unit }\n",
+        )));
+        assert!(out.contains(concat!(
+            "    , Button \"Ban subscriber\"\n",
+            "             (Set.fromFoldable\n",
+            "                [ Actor \"Moderator\"\n",
+            "                , Actor \"Administrator\" ])\n",
+        )));
     }
 }
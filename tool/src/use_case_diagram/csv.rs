@@ -0,0 +1,370 @@
+//! Diagrams from CSV tables, as exported by spreadsheets.
+//!
+//! Every table starts with a header row naming its columns, which are
+//! matched without regard to case or surrounding whitespace; columns that
+//! are not understood are skipped, so sheets may keep notes alongside.
+//! Fields are separated by commas and may be quoted with `"`, doubling
+//! quotes inside, as in RFC 4180.
+
+use std::error::Error;
+use std::fmt;
+
+use use_case_diagram::parser::{error, ParseError, ParseErrorKind, Position};
+use use_case_diagram::source_map::span;
+use use_case_diagram::{Actor, ActorId, Association, SourceMap, UseCase, UseCaseDiagram,
+                       UseCaseId};
+
+/// An error in one of the tables given to `from_csv_tables`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CsvError {
+    /// `"actors"`, `"use_cases"` or `"associations"`.
+    pub table: &'static str,
+
+    pub error: ParseError,
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.table, self.error)
+    }
+}
+
+impl Error for CsvError {
+    fn description(&self) -> &str {
+        "invalid CSV table"
+    }
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Read a diagram with unique names from an edge list with `actor` and
+/// `use_case` columns and an optional `label` column. Actors and use cases
+/// are created when first mentioned, in order, and rows with both cells
+/// empty are skipped.
+pub fn from_csv<S>(associations: &str) -> Result<UseCaseDiagram<S>, ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    from_csv_with_source_map(associations, "").map(|(diagram, _)| diagram)
+}
+
+/// Read a diagram from the edge list in the file like `from_csv`, and map
+/// every actor and use case to the cell that first mentions it, and every
+/// association to the first row that gives it.
+pub fn from_csv_with_source_map<S>(associations: &str, file: &str)
+                                   -> Result<(UseCaseDiagram<S>, SourceMap<S>), ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    let mut diagram = UseCaseDiagram::with_unique_names();
+    let mut source_map = SourceMap::new();
+    insert_associations(&mut diagram, &mut source_map, associations, file)?;
+    Ok((diagram, source_map))
+}
+
+/// Read a diagram with unique names from a table of actors with `name` and
+/// optional `description` columns, a table of use cases with `title` and
+/// optional `description` columns, and an edge list as read by `from_csv`.
+/// The edge list may mention actors and use cases missing from the other
+/// tables, which are then created.
+pub fn from_csv_tables<S>(actors: &str, use_cases: &str, associations: &str)
+                          -> Result<UseCaseDiagram<S>, CsvError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    from_csv_tables_with_source_map(actors, use_cases, associations, ["", "", ""])
+        .map(|(diagram, _)| diagram)
+}
+
+/// Read a diagram from tables like `from_csv_tables`, with the files of the
+/// actors, use cases and associations tables in that order, and map every
+/// element to its row, or to the cell that first mentions it if it is only
+/// in the edge list.
+pub fn from_csv_tables_with_source_map<S>(actors: &str, use_cases: &str, associations: &str,
+                                          files: [&str; 3])
+                                          -> Result<(UseCaseDiagram<S>, SourceMap<S>), CsvError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    let in_table = |table| move |error| CsvError{table, error};
+    let mut diagram = UseCaseDiagram::with_unique_names();
+    let mut source_map = SourceMap::new();
+    for (name, description) in elements(actors, "name").map_err(in_table("actors"))? {
+        let mut actor = Actor::new(S::from(&name.1));
+        actor.description = description.map(|description| S::from(&description));
+        let actor_id = diagram.insert_actor(actor)
+            .map_err(|err| error(name.0, ParseErrorKind::Name(err)))
+            .map_err(in_table("actors"))?;
+        source_map.actors.insert(actor_id, span(files[0], name.0));
+    }
+    for (title, description) in elements(use_cases, "title").map_err(in_table("use_cases"))? {
+        let mut use_case = UseCase::new(S::from(&title.1));
+        use_case.description = description.map(|description| S::from(&description));
+        let use_case_id = diagram.insert_use_case(use_case)
+            .map_err(|err| error(title.0, ParseErrorKind::Name(err)))
+            .map_err(in_table("use_cases"))?;
+        source_map.use_cases.insert(use_case_id, span(files[1], title.0));
+    }
+    insert_associations(&mut diagram, &mut source_map, associations, files[2])
+        .map_err(in_table("associations"))?;
+    Ok((diagram, source_map))
+}
+
+type Field = (Position, String);
+
+/// The names or titles in the given column, each with its description if
+/// any.
+fn elements(text: &str, key: &'static str) -> Result<Vec<(Field, Option<String>)>, ParseError> {
+    let table = Table::parse(text, &[key], &["description"])?;
+    let mut elements = Vec::new();
+    for row in &table.rows {
+        let name = table.cell(row, 0);
+        if name.1.is_empty() {
+            if row.iter().all(|(_, field)| field.is_empty()) {
+                continue;
+            }
+            return Err(error(name.0, ParseErrorKind::ExpectedName));
+        }
+        let description = Some(table.cell(row, 1).1).filter(|description| !description.is_empty());
+        elements.push((name, description));
+    }
+    Ok(elements)
+}
+
+fn insert_associations<S>(diagram: &mut UseCaseDiagram<S>, source_map: &mut SourceMap<S>,
+                          text: &str, file: &str) -> Result<(), ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    let table = Table::parse(text, &["actor", "use_case"], &["label"])?;
+    for row in &table.rows {
+        let (actor_position, actor) = table.cell(row, 0);
+        let (use_case_position, use_case) = table.cell(row, 1);
+        if actor.is_empty() && use_case.is_empty() {
+            continue;
+        }
+        if actor.is_empty() {
+            return Err(error(actor_position, ParseErrorKind::ExpectedName));
+        }
+        if use_case.is_empty() {
+            return Err(error(use_case_position, ParseErrorKind::ExpectedName));
+        }
+        let actor_id = actor_named(diagram, &actor);
+        source_map.actors.entry(actor_id).or_insert_with(|| span(file, actor_position));
+        let use_case_id = use_case_titled(diagram, &use_case);
+        source_map.use_cases.entry(use_case_id).or_insert_with(|| span(file, use_case_position));
+        let label = Some(table.cell(row, 2).1).filter(|label| !label.is_empty());
+        let association = Association{label: label.map(|label| S::from(&label)),
+                                      ..Association::default()};
+        diagram.insert_association_with(actor_id, use_case_id, association).unwrap();
+        source_map.associations.entry((actor_id, use_case_id))
+            .or_insert_with(|| span(file, row[0].0));
+    }
+    Ok(())
+}
+
+fn actor_named<S>(diagram: &mut UseCaseDiagram<S>, name: &str) -> ActorId
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    match diagram.actor_by_name(name) {
+        Some((actor_id, _)) => actor_id,
+        None => diagram.insert_actor(Actor::new(S::from(name))).expect("name is not taken"),
+    }
+}
+
+fn use_case_titled<S>(diagram: &mut UseCaseDiagram<S>, title: &str) -> UseCaseId
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    match diagram.use_case_by_title(title) {
+        Some((use_case_id, _)) => use_case_id,
+        None => {
+            diagram.insert_use_case(UseCase::new(S::from(title))).expect("title is not taken")
+        },
+    }
+}
+
+/// The rows of a table after its header, and where the wanted columns are
+/// in them.
+struct Table {
+    columns: Vec<Option<usize>>,
+    rows: Vec<Vec<Field>>,
+}
+
+impl Table {
+    /// Parse a table that must have the required columns and may have the
+    /// optional ones, in that order in `columns`.
+    fn parse(text: &str, required: &[&str], optional: &[&str]) -> Result<Self, ParseError> {
+        let mut rows = parse_records(text)?.into_iter();
+        let header = rows.next().unwrap_or_default();
+        let find = |name: &str| header.iter().position(|(_, field)| {
+            field.trim().eq_ignore_ascii_case(name) ||
+                field.trim().replace(' ', "_").eq_ignore_ascii_case(name)
+        });
+        let mut columns = Vec::new();
+        for &name in required {
+            let column = find(name).ok_or_else(|| {
+                error(Position{line: 1, column: 1}, ParseErrorKind::MissingKey(name.to_string()))
+            })?;
+            columns.push(Some(column));
+        }
+        columns.extend(optional.iter().map(|&name| find(name)));
+        Ok(Table{columns, rows: rows.collect()})
+    }
+
+    /// The trimmed cell of the row in the wanted column, empty if the row or
+    /// the table does not have it.
+    fn cell(&self, row: &[Field], column: usize) -> Field {
+        let end = row.last().map_or(Position{line: 1, column: 1}, |&(position, _)| position);
+        match self.columns[column].and_then(|column| row.get(column)) {
+            Some(&(position, ref field)) => (position, field.trim().to_string()),
+            None => (end, String::new()),
+        }
+    }
+}
+
+/// The records of the text, each a list of fields and where they start.
+fn parse_records(text: &str) -> Result<Vec<Vec<Field>>, ParseError> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut position = Position{line: 1, column: 1};
+    let mut chars = text.chars().peekable();
+    loop {
+        let start = position;
+        let mut field = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            position.column += 1;
+            loop {
+                match chars.next() {
+                    None => return Err(error(start, ParseErrorKind::UnterminatedString)),
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        position.column += 2;
+                        field.push('"');
+                    },
+                    Some('"') => {
+                        position.column += 1;
+                        break;
+                    },
+                    Some('\n') => {
+                        position = Position{line: position.line + 1, column: 1};
+                        field.push('\n');
+                    },
+                    Some(c) => {
+                        position.column += 1;
+                        field.push(c);
+                    },
+                }
+            }
+            match chars.peek() {
+                None | Some(&',') | Some(&'\n') | Some(&'\r') => (),
+                Some(&c) => return Err(error(position, ParseErrorKind::UnexpectedCharacter(c))),
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c == ',' || c == '\n' || c == '\r' {
+                    break;
+                }
+                chars.next();
+                position.column += 1;
+                field.push(c);
+            }
+        }
+        record.push((start, field));
+        match chars.next() {
+            Some(',') => position.column += 1,
+            Some('\r') if chars.peek() == Some(&'\n') => {
+                chars.next();
+                position = Position{line: position.line + 1, column: 1};
+                records.push(record);
+                record = Vec::new();
+            },
+            Some('\n') | Some('\r') => {
+                position = Position{line: position.line + 1, column: 1};
+                records.push(record);
+                record = Vec::new();
+            },
+            _ => {
+                if record.len() > 1 || !record[0].1.is_empty() {
+                    records.push(record);
+                }
+                return Ok(records);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    use use_case_diagram::Span;
+
+    #[test]
+    fn test_from_csv() {
+        let text = concat!(
+            "Actor,Use case,Label,Owner\r\n",
+            "Administrator,Ban subscriber,bans,Ops\r\n",
+            "Subscriber,\"Post \"\"first\"\"\ncomment\",,\r\n",
+            ",,,\r\n",
+            " Administrator ,Post \"first\"\n",
+        );
+        let diagram = from_csv::<Rc<str>>(text).unwrap();
+        let titles = diagram.use_cases_in_order().iter()
+            .map(|&(_, use_case)| use_case.title.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(titles, vec!["Ban subscriber", "Post \"first\"\ncomment", "Post \"first\""]);
+        let (a, _) = diagram.actor_by_name("Administrator").unwrap();
+        let (s, _) = diagram.actor_by_name("Subscriber").unwrap();
+        let (bs, _) = diagram.use_case_by_title("Ban subscriber").unwrap();
+        assert_eq!(diagram.association(a, bs).unwrap().label.as_deref(), Some("bans"));
+        assert_eq!(diagram.use_cases_of_actor(a).count(), 2);
+        assert_eq!(diagram.use_cases_of_actor(s).count(), 1);
+
+        fn err(text: &str) -> (usize, usize, ParseErrorKind) {
+            let err = from_csv::<Rc<str>>(text).unwrap_err();
+            (err.position.line, err.position.column, err.kind)
+        }
+        assert_eq!(err("actor,title\n"),
+                   (1, 1, ParseErrorKind::MissingKey("use_case".to_string())));
+        assert_eq!(err("actor,use_case\nA,\"B\n"), (2, 3, ParseErrorKind::UnterminatedString));
+        assert_eq!(err("actor,use_case\nA,\"B\"C\n"),
+                   (2, 6, ParseErrorKind::UnexpectedCharacter('C')));
+        assert_eq!(err("actor,use_case\n,B\n"), (2, 1, ParseErrorKind::ExpectedName));
+        assert_eq!(err("actor,use_case\nA\n"), (2, 1, ParseErrorKind::ExpectedName));
+    }
+
+    #[test]
+    fn test_from_csv_tables() {
+        let diagram = from_csv_tables::<Rc<str>>(
+            "name,description\nSubscriber,Reads the site\nAdministrator,\n",
+            "title\nPost comment\n",
+            "actor,use_case\nAdministrator,Ban subscriber\nSubscriber,Post comment\n",
+        ).unwrap();
+        let actors = diagram.actors_in_order().iter()
+            .map(|&(_, actor)| (actor.name.to_string(), actor.description.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(actors, vec![("Subscriber".to_string(), Some(Rc::from("Reads the site"))),
+                                ("Administrator".to_string(), None)]);
+        assert_eq!(diagram.use_cases().count(), 2);
+        assert_eq!(diagram.associations().count(), 2);
+
+        let err = from_csv_tables::<Rc<str>>("name\nA\n", "title\nU\nU\n", "actor,use_case\n")
+            .unwrap_err();
+        assert_eq!(err.table, "use_cases");
+        assert_eq!(err.error.position, Position{line: 3, column: 1});
+        assert_eq!(err.to_string(), "use_cases: 3:1: duplicate title: taken by use case 0");
+    }
+
+    #[test]
+    fn test_from_csv_tables_with_source_map() {
+        let (diagram, source_map) = from_csv_tables_with_source_map::<Rc<str>>(
+            "name\nSubscriber\n",
+            "title\nPost comment\n",
+            "actor,use_case\nAdministrator,Ban subscriber\nSubscriber,Post comment\n",
+            ["actors.csv", "use_cases.csv", "associations.csv"],
+        ).unwrap();
+        let (a, _) = diagram.actor_by_name("Administrator").unwrap();
+        let (s, _) = diagram.actor_by_name("Subscriber").unwrap();
+        let (bs, _) = diagram.use_case_by_title("Ban subscriber").unwrap();
+        let (pc, _) = diagram.use_case_by_title("Post comment").unwrap();
+        let span = |file, line, column| Span{file: Rc::from(file), line, column};
+        assert_eq!(source_map.actors[&s], span("actors.csv", 2, 1));
+        assert_eq!(source_map.actors[&a], span("associations.csv", 2, 1));
+        assert_eq!(source_map.use_cases[&pc], span("use_cases.csv", 2, 1));
+        assert_eq!(source_map.use_cases[&bs], span("associations.csv", 2, 15));
+        assert_eq!(source_map.associations[&(s, pc)], span("associations.csv", 3, 1));
+    }
+}
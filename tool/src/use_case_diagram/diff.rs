@@ -0,0 +1,305 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fmt;
+use std::rc::Rc;
+
+use use_case_diagram::{ActorId, UseCaseDiagram, UseCaseId};
+
+/// The old and the new name of a renamed element.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rename<S = Rc<str>> {
+    pub old: S,
+    pub new: S,
+}
+
+/// The structural differences between two diagrams, as computed by
+/// `UseCaseDiagram::diff`. Elements are matched by name or title first, so
+/// that diagrams built independently of each other, whose identifiers need
+/// not agree, compare by what they contain. Elements left over on both sides
+/// are then matched by identifier, so that a diagram compared with an edited
+/// copy of itself reports renames as such. Identifiers of removed elements
+/// are those of the old diagram, and all other identifiers those of the new.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DiagramDiff<S = Rc<str>> {
+    /// Actors only in the new diagram, with their names.
+    pub added_actors: BTreeMap<ActorId, S>,
+
+    /// Actors only in the old diagram, with their names.
+    pub removed_actors: BTreeMap<ActorId, S>,
+
+    /// Actors in both diagrams whose names differ.
+    pub renamed_actors: BTreeMap<ActorId, Rename<S>>,
+
+    /// Use cases only in the new diagram, with their titles.
+    pub added_use_cases: BTreeMap<UseCaseId, S>,
+
+    /// Use cases only in the old diagram, with their titles.
+    pub removed_use_cases: BTreeMap<UseCaseId, S>,
+
+    /// Use cases in both diagrams whose titles differ.
+    pub renamed_use_cases: BTreeMap<UseCaseId, Rename<S>>,
+
+    /// The identifiers in the old diagram of the actors in both, by their
+    /// identifiers in the new diagram.
+    pub matched_actors: BTreeMap<ActorId, ActorId>,
+
+    /// The identifiers in the old diagram of the use cases in both, by their
+    /// identifiers in the new diagram.
+    pub matched_use_cases: BTreeMap<UseCaseId, UseCaseId>,
+
+    /// Associations only in the new diagram.
+    pub added_associations: BTreeSet<(ActorId, UseCaseId)>,
+
+    /// Associations only in the old diagram, by identifiers in the old
+    /// diagram.
+    pub removed_associations: BTreeSet<(ActorId, UseCaseId)>,
+}
+
+impl<S> DiagramDiff<S> {
+    /// Whether the diagrams have the same actors, use cases, and
+    /// associations.
+    pub fn is_empty(&self) -> bool {
+        self.added_actors.is_empty() && self.removed_actors.is_empty() &&
+        self.renamed_actors.is_empty() &&
+        self.added_use_cases.is_empty() && self.removed_use_cases.is_empty() &&
+        self.renamed_use_cases.is_empty() &&
+        self.added_associations.is_empty() && self.removed_associations.is_empty()
+    }
+}
+
+/// One line per difference, removals before additions.
+impl<S> fmt::Display for DiagramDiff<S> where S: AsRef<str> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (actor_id, name) in &self.removed_actors {
+            writeln!(f, "removed actor {} {:?}", actor_id, name.as_ref())?;
+        }
+        for (actor_id, name) in &self.added_actors {
+            writeln!(f, "added actor {} {:?}", actor_id, name.as_ref())?;
+        }
+        for (actor_id, rename) in &self.renamed_actors {
+            writeln!(f, "renamed actor {} from {:?} to {:?}", actor_id, rename.old.as_ref(),
+                     rename.new.as_ref())?;
+        }
+        for (use_case_id, title) in &self.removed_use_cases {
+            writeln!(f, "removed use case {} {:?}", use_case_id, title.as_ref())?;
+        }
+        for (use_case_id, title) in &self.added_use_cases {
+            writeln!(f, "added use case {} {:?}", use_case_id, title.as_ref())?;
+        }
+        for (use_case_id, rename) in &self.renamed_use_cases {
+            writeln!(f, "renamed use case {} from {:?} to {:?}", use_case_id,
+                     rename.old.as_ref(), rename.new.as_ref())?;
+        }
+        for &(actor_id, use_case_id) in &self.removed_associations {
+            writeln!(f, "removed association of actor {} with use case {}", actor_id,
+                     use_case_id)?;
+        }
+        for &(actor_id, use_case_id) in &self.added_associations {
+            writeln!(f, "added association of actor {} with use case {}", actor_id,
+                     use_case_id)?;
+        }
+        Ok(())
+    }
+}
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> + Clone {
+    /// The differences from this diagram to the given newer diagram.
+    pub fn diff(&self, new: &UseCaseDiagram<S>) -> DiagramDiff<S> {
+        let mut diff = DiagramDiff{
+            added_actors: BTreeMap::new(),
+            removed_actors: BTreeMap::new(),
+            renamed_actors: BTreeMap::new(),
+            added_use_cases: BTreeMap::new(),
+            removed_use_cases: BTreeMap::new(),
+            renamed_use_cases: BTreeMap::new(),
+            matched_actors: BTreeMap::new(),
+            matched_use_cases: BTreeMap::new(),
+            added_associations: BTreeSet::new(),
+            removed_associations: BTreeSet::new(),
+        };
+
+        diff.matched_actors = match_elements(
+            self.actors().map(|(actor_id, actor)| (actor_id, actor.name.as_ref())),
+            new.actors().map(|(actor_id, actor)| (actor_id, actor.name.as_ref())),
+        );
+        let old_actors: BTreeSet<_> = diff.matched_actors.values().cloned().collect();
+        for (actor_id, old_actor) in self.actors() {
+            if !old_actors.contains(&actor_id) {
+                diff.removed_actors.insert(actor_id, old_actor.name.clone());
+            }
+        }
+        for (actor_id, new_actor) in new.actors() {
+            match diff.matched_actors.get(&actor_id) {
+                None => { diff.added_actors.insert(actor_id, new_actor.name.clone()); },
+                Some(&old_actor_id) => {
+                    let old_actor = self.actor(old_actor_id).expect("matched actor");
+                    if old_actor.name.as_ref() != new_actor.name.as_ref() {
+                        let rename = Rename{old: old_actor.name.clone(),
+                                            new: new_actor.name.clone()};
+                        diff.renamed_actors.insert(actor_id, rename);
+                    }
+                },
+            }
+        }
+
+        diff.matched_use_cases = match_elements(
+            self.use_cases().map(|(use_case_id, use_case)| (use_case_id, use_case.title.as_ref())),
+            new.use_cases().map(|(use_case_id, use_case)| (use_case_id, use_case.title.as_ref())),
+        );
+        let old_use_cases: BTreeSet<_> = diff.matched_use_cases.values().cloned().collect();
+        for (use_case_id, old_use_case) in self.use_cases() {
+            if !old_use_cases.contains(&use_case_id) {
+                diff.removed_use_cases.insert(use_case_id, old_use_case.title.clone());
+            }
+        }
+        for (use_case_id, new_use_case) in new.use_cases() {
+            match diff.matched_use_cases.get(&use_case_id) {
+                None => { diff.added_use_cases.insert(use_case_id, new_use_case.title.clone()); },
+                Some(&old_use_case_id) => {
+                    let old_use_case = self.use_case(old_use_case_id).expect("matched use case");
+                    if old_use_case.title.as_ref() != new_use_case.title.as_ref() {
+                        let rename = Rename{old: old_use_case.title.clone(),
+                                            new: new_use_case.title.clone()};
+                        diff.renamed_use_cases.insert(use_case_id, rename);
+                    }
+                },
+            }
+        }
+
+        let old_associations: BTreeSet<_> = self.associations().collect();
+        let new_associations: BTreeSet<_> =
+            new.associations()
+            .filter_map(|(actor_id, use_case_id)| {
+                let actor_id = *diff.matched_actors.get(&actor_id)?;
+                let use_case_id = *diff.matched_use_cases.get(&use_case_id)?;
+                Some((actor_id, use_case_id))
+            })
+            .collect();
+        diff.removed_associations =
+            old_associations.difference(&new_associations).cloned().collect();
+        diff.added_associations =
+            new.associations()
+            .filter(|&(actor_id, use_case_id)| {
+                let old_actor_id = diff.matched_actors.get(&actor_id);
+                let old_use_case_id = diff.matched_use_cases.get(&use_case_id);
+                match (old_actor_id, old_use_case_id) {
+                    (Some(&old_actor_id), Some(&old_use_case_id)) =>
+                        !old_associations.contains(&(old_actor_id, old_use_case_id)),
+                    _ => true,
+                }
+            })
+            .collect();
+
+        diff
+    }
+}
+
+/// Pair up elements of an old and a new diagram, first by equal names, in
+/// order of identifier where names repeat, and then by equal identifiers
+/// among the elements that are left. The result maps new identifiers to old.
+fn match_elements<'a, I, O, N>(old: O, new: N) -> BTreeMap<I, I>
+    where I: Copy + Ord,
+          O: Iterator<Item = (I, &'a str)>,
+          N: Iterator<Item = (I, &'a str)> {
+    let old: Vec<_> = old.collect();
+    let mut by_name: HashMap<&str, VecDeque<I>> = HashMap::new();
+    for &(id, name) in &old {
+        by_name.entry(name).or_default().push_back(id);
+    }
+
+    let mut matches = BTreeMap::new();
+    let mut unmatched = Vec::new();
+    for (id, name) in new {
+        match by_name.get_mut(name).and_then(VecDeque::pop_front) {
+            Some(old_id) => { matches.insert(id, old_id); },
+            None => unmatched.push(id),
+        }
+    }
+
+    let left: BTreeSet<_> = by_name.values().flatten().cloned().collect();
+    for id in unmatched {
+        if left.contains(&id) {
+            matches.insert(id, id);
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use use_case_diagram::{Actor, UseCase};
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_diff() {
+        let mut old = UseCaseDiagram::new();
+        let a = old.insert_actor(Actor::new(Rc::from("Admin"))).unwrap();
+        let s = old.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = old.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = old.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        old.insert_association(a, bs).unwrap();
+        old.insert_association(s, pc).unwrap();
+
+        let mut new = old.clone();
+        assert!(old.diff(&new).is_empty());
+
+        new.rename_actor(a, Rc::from("Administrator")).unwrap();
+        new.remove_use_case(pc);
+        let g = new.insert_actor(Actor::new(Rc::from("Guest"))).unwrap();
+        new.insert_association(g, bs).unwrap();
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added_actors, vec![(g, Rc::from("Guest"))].into_iter().collect());
+        assert!(diff.removed_actors.is_empty());
+        assert_eq!(diff.renamed_actors,
+                   vec![(a, Rename{old: Rc::from("Admin"), new: Rc::from("Administrator")})]
+                   .into_iter().collect());
+        assert!(diff.added_use_cases.is_empty());
+        assert_eq!(diff.removed_use_cases,
+                   vec![(pc, Rc::from("Post comment"))].into_iter().collect());
+        assert!(diff.renamed_use_cases.is_empty());
+        assert_eq!(diff.added_associations, vec![(g, bs)].into_iter().collect());
+        assert_eq!(diff.removed_associations, vec![(s, pc)].into_iter().collect());
+        assert_eq!(diff.to_string(), concat!(
+            "added actor 2 \"Guest\"\n",
+            "renamed actor 0 from \"Admin\" to \"Administrator\"\n",
+            "removed use case 1 \"Post comment\"\n",
+            "removed association of actor 1 with use case 1\n",
+            "added association of actor 2 with use case 0\n",
+        ));
+    }
+
+    #[test]
+    fn test_diff_independent() {
+        let mut old = UseCaseDiagram::new();
+        let a = old.insert_actor(Actor::new(Rc::from("Admin"))).unwrap();
+        let s = old.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = old.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = old.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        old.insert_association(a, bs).unwrap();
+        old.insert_association(s, pc).unwrap();
+
+        let mut new = UseCaseDiagram::new();
+        let pc2 = new.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let s2 = new.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let a2 = new.insert_actor(Actor::new(Rc::from("Admin"))).unwrap();
+        let bs2 = new.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        new.insert_association(s2, pc2).unwrap();
+        new.insert_association(a2, bs2).unwrap();
+        assert!(old.diff(&new).is_empty());
+
+        let g = new.insert_actor(Actor::new(Rc::from("Guest"))).unwrap();
+        new.insert_association(g, pc2).unwrap();
+        new.remove_association(a2, bs2);
+        let diff = old.diff(&new);
+        assert_eq!(diff.added_actors, vec![(g, Rc::from("Guest"))].into_iter().collect());
+        assert!(diff.removed_actors.is_empty() && diff.renamed_actors.is_empty());
+        assert!(diff.added_use_cases.is_empty() && diff.removed_use_cases.is_empty());
+        assert_eq!(diff.matched_actors, vec![(s2, s), (a2, a)].into_iter().collect());
+        assert_eq!(diff.matched_use_cases, vec![(pc2, pc), (bs2, bs)].into_iter().collect());
+        assert_eq!(diff.added_associations, vec![(g, pc2)].into_iter().collect());
+        assert_eq!(diff.removed_associations, vec![(a, bs)].into_iter().collect());
+    }
+}
@@ -0,0 +1,189 @@
+//! draw.io files, for touching up the layout of diagrams by hand in draw.io
+//! or diagrams.net.
+//!
+//! The diagram is an uncompressed mxGraph model placed as by `layout`.
+//! Actors are stick figures and use cases are ellipses, with identifiers as
+//! in the other formats, e.g. `actor_0` and `use_case_1`, and system
+//! boundaries are rectangles behind them. Associations are plain lines,
+//! includes, extends and dependencies are dashed arrows labelled as in UML,
+//! and generalizations are arrows with hollow heads towards the parent.
+//! Notes are left out, since the layout does not place them.
+
+use std::io;
+
+use use_case_diagram::layout::Rectangle;
+use use_case_diagram::xml::escape;
+use use_case_diagram::UseCaseDiagram;
+
+const SYSTEM_BOUNDARY_STYLE: &str = "rounded=0;whiteSpace=wrap;verticalAlign=top;fontStyle=1;";
+const ACTOR_STYLE: &str =
+    "shape=umlActor;verticalLabelPosition=bottom;verticalAlign=top;outlineConnect=0;";
+const USE_CASE_STYLE: &str = "ellipse;whiteSpace=wrap;";
+const ASSOCIATION_STYLE: &str = "endArrow=none;";
+const DEPENDENCY_STYLE: &str = "endArrow=open;endFill=0;dashed=1;";
+const GENERALIZATION_STYLE: &str = "endArrow=block;endFill=0;";
+
+/// Write the diagram as a draw.io file with a single page with the given
+/// name.
+pub fn to_drawio<W, S>(diagram: &UseCaseDiagram<S>, name: &str, w: &mut W) -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    let layout = diagram.layout();
+    writeln!(w, "<mxfile host=\"butterfly\">")?;
+    writeln!(w, "  <diagram id=\"use_case_diagram\" name=\"{}\">", escape(name))?;
+    writeln!(w, "    <mxGraphModel grid=\"1\" gridSize=\"10\" page=\"1\" pageWidth=\"{}\" \
+                 pageHeight=\"{}\">", layout.width, layout.height)?;
+    writeln!(w, "      <root>")?;
+    writeln!(w, "        <mxCell id=\"0\"/>")?;
+    writeln!(w, "        <mxCell id=\"1\" parent=\"0\"/>")?;
+
+    for (system_boundary_id, rectangle) in &layout.system_boundaries {
+        let system_boundary = diagram.system_boundary(*system_boundary_id).unwrap();
+        generate_vertex(w, &format!("system_boundary_{}", system_boundary_id),
+                        system_boundary.name.as_ref(), SYSTEM_BOUNDARY_STYLE, rectangle)?;
+    }
+    for (actor_id, actor) in diagram.actors_in_order() {
+        let style = if actor.kind.is_human() {
+            ACTOR_STYLE.to_string()
+        } else {
+            format!("{}dashed=1;", ACTOR_STYLE)
+        };
+        generate_vertex(w, &format!("actor_{}", actor_id), actor.name.as_ref(), &style,
+                        &layout.actors[&actor_id])?;
+    }
+    for (use_case_id, use_case) in diagram.use_cases_in_order() {
+        let style = if use_case.is_abstract {
+            format!("{}fontStyle=2;", USE_CASE_STYLE)
+        } else {
+            USE_CASE_STYLE.to_string()
+        };
+        generate_vertex(w, &format!("use_case_{}", use_case_id), use_case.title.as_ref(), &style,
+                        &layout.use_cases[&use_case_id])?;
+    }
+
+    let mut associations = diagram.associations().collect::<Vec<_>>();
+    associations.sort();
+    for (actor_id, use_case_id) in associations {
+        let association = diagram.association(actor_id, use_case_id).unwrap();
+        let label = association.label.as_ref().map_or("", AsRef::as_ref);
+        generate_edge(w, "association", &format!("actor_{}", actor_id),
+                      &format!("use_case_{}", use_case_id), label, ASSOCIATION_STYLE)?;
+    }
+    let mut includes = diagram.includes().collect::<Vec<_>>();
+    includes.sort();
+    for (base, included) in includes {
+        generate_edge(w, "include", &format!("use_case_{}", base),
+                      &format!("use_case_{}", included), "«include»", DEPENDENCY_STYLE)?;
+    }
+    let mut extends = diagram.extends().collect::<Vec<_>>();
+    extends.sort();
+    for extend in extends {
+        generate_edge(w, "extend", &format!("use_case_{}", extend.extension),
+                      &format!("use_case_{}", extend.base), "«extend»", DEPENDENCY_STYLE)?;
+    }
+    let mut dependencies = diagram.dependencies().collect::<Vec<_>>();
+    dependencies.sort();
+    for (dependent, dependency) in dependencies {
+        let label = match diagram.dependency_label(dependent, dependency) {
+            Some(label) => format!("«depends on» {}", label.as_ref()),
+            None => "«depends on»".to_string(),
+        };
+        generate_edge(w, "dependency", &format!("use_case_{}", dependent),
+                      &format!("use_case_{}", dependency), &label, DEPENDENCY_STYLE)?;
+    }
+    let mut generalizations = diagram.actor_generalizations().collect::<Vec<_>>();
+    generalizations.sort();
+    for (child, parent) in generalizations {
+        generate_edge(w, "generalization", &format!("actor_{}", child),
+                      &format!("actor_{}", parent), "", GENERALIZATION_STYLE)?;
+    }
+    let mut generalizations = diagram.use_case_generalizations().collect::<Vec<_>>();
+    generalizations.sort();
+    for (child, parent) in generalizations {
+        generate_edge(w, "generalization", &format!("use_case_{}", child),
+                      &format!("use_case_{}", parent), "", GENERALIZATION_STYLE)?;
+    }
+
+    writeln!(w, "      </root>")?;
+    writeln!(w, "    </mxGraphModel>")?;
+    writeln!(w, "  </diagram>")?;
+    writeln!(w, "</mxfile>")
+}
+
+/// Write the given layer of the diagram as a draw.io file, like `to_drawio`,
+/// with the layers in the given order; see
+/// `UseCaseDiagram::layer_subdiagram`.
+pub fn to_drawio_in_layer<W, S, L>(diagram: &UseCaseDiagram<S>, order: &[L], layer: &str,
+                                   name: &str, w: &mut W) -> io::Result<()>
+    where W: io::Write, S: AsRef<str> + Clone, L: AsRef<str> {
+    to_drawio(&diagram.layer_subdiagram(order, layer), name, w)
+}
+
+fn generate_vertex<W>(w: &mut W, id: &str, value: &str, style: &str, rectangle: &Rectangle)
+                      -> io::Result<()> where W: io::Write {
+    writeln!(w, "        <mxCell id=\"{}\" value=\"{}\" style=\"{}\" vertex=\"1\" parent=\"1\">",
+             id, escape(value), style)?;
+    writeln!(w, "          <mxGeometry x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" \
+                 as=\"geometry\"/>", rectangle.x, rectangle.y, rectangle.width,
+             rectangle.height)?;
+    writeln!(w, "        </mxCell>")
+}
+
+/// Write an edge, identified by its kind and ends, since there is at most
+/// one edge of each kind between two elements.
+fn generate_edge<W>(w: &mut W, kind: &str, source: &str, target: &str, value: &str,
+                    style: &str) -> io::Result<()> where W: io::Write {
+    writeln!(w, "        <mxCell id=\"{}_{}_{}\" value=\"{}\" style=\"{}\" edge=\"1\" \
+                 parent=\"1\" source=\"{}\" target=\"{}\">",
+             kind, source, target, escape(value), style, source, target)?;
+    writeln!(w, "          <mxGeometry relative=\"1\" as=\"geometry\"/>")?;
+    writeln!(w, "        </mxCell>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str;
+
+    use use_case_diagram::xml::{Event, Reader};
+    use use_case_diagram::{Actor, SystemBoundary, UseCase};
+
+    #[test]
+    fn test_to_drawio() {
+        let mut diagram = UseCaseDiagram::<&str>::new();
+        let subscriber = diagram.insert_actor(Actor::new("Subscriber")).unwrap();
+        let post = diagram.insert_use_case(UseCase::new("Post <comment>")).unwrap();
+        let reply = diagram.insert_use_case(UseCase::new("Reply")).unwrap();
+        diagram.insert_system_boundary(SystemBoundary{
+            name: "Forum", use_cases: vec![post, reply].into_iter().collect(),
+        }).unwrap();
+        diagram.insert_association(subscriber, post).unwrap();
+        diagram.insert_include(reply, post).unwrap();
+
+        let mut output = Vec::new();
+        to_drawio(&diagram, "Comments", &mut output).unwrap();
+        let output = str::from_utf8(&output).unwrap();
+        assert!(output.contains(
+            "<mxCell id=\"use_case_0\" value=\"Post &lt;comment&gt;\" \
+             style=\"ellipse;whiteSpace=wrap;\" vertex=\"1\" parent=\"1\">\n\
+             \x20         <mxGeometry x=\"260\" y=\"50\" width=\"138\" height=\"60\" \
+             as=\"geometry\"/>\n"));
+        assert!(output.contains(
+            "<mxCell id=\"include_use_case_1_use_case_0\" value=\"«include»\" \
+             style=\"endArrow=open;endFill=0;dashed=1;\" edge=\"1\" parent=\"1\" \
+             source=\"use_case_1\" target=\"use_case_0\">"));
+
+        let mut reader = Reader::new(output);
+        let mut cells = Vec::new();
+        while let Some((_, event)) = reader.next_event().unwrap() {
+            if let Event::Start{name: "mxCell", attributes, ..} = event {
+                cells.extend(attributes.into_iter()
+                                 .filter(|&(name, _)| name == "id")
+                                 .map(|(_, value)| value));
+            }
+        }
+        assert_eq!(cells, vec!["0", "1", "system_boundary_0", "actor_0", "use_case_0",
+                               "use_case_1", "association_actor_0_use_case_0",
+                               "include_use_case_1_use_case_0"]);
+    }
+}
@@ -0,0 +1,688 @@
+use std::collections::VecDeque;
+use std::mem;
+use std::rc::Rc;
+use std::vec;
+
+use use_case_diagram::{Actor, ActorId, Association, AssociationError, Event, Extend,
+                       ExtensionPoint, ExtensionPointId, NameError, Note, NoteId, RelationshipError,
+                       SystemBoundary, SystemBoundaryId, UseCase, UseCaseDiagram, UseCaseId};
+
+/// How many commands an editor can undo unless told otherwise.
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// A change made through a `DiagramEditor`, identifying the elements it
+/// affected.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Command {
+    InsertActor(ActorId),
+    InsertUseCase(UseCaseId),
+    RenameActor(ActorId),
+    RetitleUseCase(UseCaseId),
+    RemoveActor(ActorId),
+    RemoveUseCase(UseCaseId),
+    InsertAssociation(ActorId, UseCaseId),
+    RemoveAssociation(ActorId, UseCaseId),
+    InsertInclude(UseCaseId, UseCaseId),
+    RemoveInclude(UseCaseId, UseCaseId),
+    InsertExtend(Extend),
+    RemoveExtend(Extend),
+    InsertActorGeneralization(ActorId, ActorId),
+    RemoveActorGeneralization(ActorId, ActorId),
+    InsertUseCaseGeneralization(UseCaseId, UseCaseId),
+    RemoveUseCaseGeneralization(UseCaseId, UseCaseId),
+    EditActor(ActorId),
+    EditUseCase(UseCaseId),
+    EditAssociation(ActorId, UseCaseId),
+    InsertDependency(UseCaseId, UseCaseId),
+    RemoveDependency(UseCaseId, UseCaseId),
+    InsertExtensionPoint(ExtensionPointId),
+    RemoveExtensionPoint(ExtensionPointId),
+    InsertSystemBoundary(SystemBoundaryId),
+    RemoveSystemBoundary(SystemBoundaryId),
+    InsertSystemBoundaryUseCase(SystemBoundaryId, UseCaseId),
+    RemoveSystemBoundaryUseCase(SystemBoundaryId, UseCaseId),
+    InsertNote(NoteId),
+    RemoveNote(NoteId),
+    MergeActors(ActorId, ActorId),
+}
+
+/// A use case diagram with an edit history. Every change made through the
+/// editor is recorded as a command that can be undone and redone; making a
+/// new change discards the commands that could be redone. Changes that leave
+/// the diagram as it was are not recorded, except edits of elements through
+/// closures, which are recorded whenever the element exists.
+///
+/// Each recorded command keeps the diagram as it was on the other side of
+/// the command, so undoing a removal restores every relationship and note
+/// the removal took with it, under the original identifiers. Labels are
+/// shared between these copies when `S` is `Rc<str>` or `Arc<str>`. As the
+/// copies are as large as the diagram, only the most recent commands are
+/// kept, up to the history limit.
+///
+/// When the diagram records events, the editor takes them over as they are
+/// recorded; drain them through the editor. Undoing a command records the
+/// inverses of the events it recorded, newest first, and redoing it records
+/// its events again.
+#[derive(Clone, Debug)]
+pub struct DiagramEditor<S = Rc<str>> {
+    diagram: UseCaseDiagram<S>,
+    undo_stack: VecDeque<(Command, UseCaseDiagram<S>, Vec<Event>)>,
+    redo_stack: Vec<(Command, UseCaseDiagram<S>, Vec<Event>)>,
+    history_limit: usize,
+    events: Vec<Event>,
+}
+
+impl<S> DiagramEditor<S> where S: AsRef<str> + Clone {
+    /// An editor for the given diagram, with an empty history and the
+    /// default history limit.
+    pub fn new(mut diagram: UseCaseDiagram<S>) -> Self {
+        let events = diagram.events.drain(..).collect();
+        DiagramEditor{diagram, undo_stack: VecDeque::new(), redo_stack: Vec::new(),
+                      history_limit: DEFAULT_HISTORY_LIMIT, events}
+    }
+
+    /// How many commands can be undone at most.
+    pub fn history_limit(&self) -> usize {
+        self.history_limit
+    }
+
+    /// Set how many commands can be undone at most, forgetting the oldest
+    /// commands beyond the limit.
+    pub fn set_history_limit(&mut self, history_limit: usize) {
+        self.history_limit = history_limit;
+        while self.undo_stack.len() > history_limit {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    /// The diagram being edited.
+    pub fn diagram(&self) -> &UseCaseDiagram<S> {
+        &self.diagram
+    }
+
+    /// The diagram being edited, discarding the history. Events not yet
+    /// drained are handed back to the diagram.
+    pub fn into_diagram(self) -> UseCaseDiagram<S> {
+        let mut diagram = self.diagram;
+        diagram.events = self.events;
+        diagram
+    }
+
+    /// Remove and return the events recorded so far, oldest first. See
+    /// `UseCaseDiagram::drain_events`.
+    pub fn drain_events(&mut self) -> vec::Drain<'_, Event> {
+        self.events.drain(..)
+    }
+
+    /// The command `undo` would undo, if any.
+    pub fn undo_command(&self) -> Option<Command> {
+        self.undo_stack.back().map(|&(command, _, _)| command)
+    }
+
+    /// The command `redo` would redo, if any.
+    pub fn redo_command(&self) -> Option<Command> {
+        self.redo_stack.last().map(|&(command, _, _)| command)
+    }
+
+    /// Undo the most recent command that has not been undone, returning it.
+    pub fn undo(&mut self) -> Option<Command> {
+        let (command, before, events) = self.undo_stack.pop_back()?;
+        let after = mem::replace(&mut self.diagram, before);
+        if self.diagram.has_event_recording() {
+            self.events.extend(events.iter().rev().map(|&event| event.inverse()));
+        }
+        self.redo_stack.push((command, after, events));
+        Some(command)
+    }
+
+    /// Redo the most recently undone command, returning it.
+    pub fn redo(&mut self) -> Option<Command> {
+        let (command, after, events) = self.redo_stack.pop()?;
+        let before = mem::replace(&mut self.diagram, after);
+        if self.diagram.has_event_recording() {
+            self.events.extend_from_slice(&events);
+        }
+        self.undo_stack.push_back((command, before, events));
+        Some(command)
+    }
+
+    /// Forget every command, keeping the diagram as it is.
+    pub fn clear_history(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    /// Run the given edit on the diagram, recording the command it returns,
+    /// if any, along with the events the edit recorded. The diagram is only
+    /// copied for the history when `record` is set, which it must be
+    /// whenever the edit could return a command.
+    fn execute<T, F>(&mut self, record: bool, edit: F) -> T
+        where F: FnOnce(&mut UseCaseDiagram<S>) -> (T, Option<Command>) {
+        let before = if record { Some(self.diagram.clone()) } else { None };
+        let (result, command) = edit(&mut self.diagram);
+        let events: Vec<Event> = self.diagram.events.drain(..).collect();
+        self.events.extend_from_slice(&events);
+        debug_assert!(record || command.is_none(), "unexpected command {:?}", command);
+        if let (Some(command), Some(before)) = (command, before) {
+            self.undo_stack.push_back((command, before, events));
+            self.redo_stack.clear();
+            if self.undo_stack.len() > self.history_limit {
+                self.undo_stack.pop_front();
+            }
+        }
+        result
+    }
+
+    /// Whether the given actor name or key is taken in a diagram with unique
+    /// names, so that inserting or renaming an actor with it fails.
+    fn actor_name_taken(&self, name: &str, key: Option<&S>) -> bool {
+        self.diagram.has_unique_names()
+            && (self.diagram.actor_by_name(name).is_some()
+                || key.is_some_and(|key| self.diagram.actor_by_key(key.as_ref()).is_some()))
+    }
+
+    /// Whether the given use case title or key is taken in a diagram with
+    /// unique names, so that inserting or retitling a use case with it fails.
+    fn use_case_title_taken(&self, title: &str, key: Option<&S>) -> bool {
+        self.diagram.has_unique_names()
+            && (self.diagram.use_case_by_title(title).is_some()
+                || key.is_some_and(|key| self.diagram.use_case_by_key(key.as_ref()).is_some()))
+    }
+
+    /// See `UseCaseDiagram::insert_actor`.
+    pub fn insert_actor(&mut self, actor: Actor<S>) -> Result<ActorId, NameError> {
+        let record = !self.actor_name_taken(actor.name.as_ref(), actor.key.as_ref());
+        self.execute(record, |diagram| {
+            let result = diagram.insert_actor(actor);
+            let command = result.ok().map(Command::InsertActor);
+            (result, command)
+        })
+    }
+
+    /// See `UseCaseDiagram::insert_use_case`.
+    pub fn insert_use_case(&mut self, use_case: UseCase<S>) -> Result<UseCaseId, NameError> {
+        let record = !self.use_case_title_taken(use_case.title.as_ref(), use_case.key.as_ref());
+        self.execute(record, |diagram| {
+            let result = diagram.insert_use_case(use_case);
+            let command = result.ok().map(Command::InsertUseCase);
+            (result, command)
+        })
+    }
+
+    /// See `UseCaseDiagram::rename_actor`.
+    pub fn rename_actor(&mut self, actor_id: ActorId, name: S) -> Result<bool, NameError> {
+        let changed = self.diagram.actor(actor_id)
+            .is_some_and(|actor| actor.name.as_ref() != name.as_ref())
+            && !self.actor_name_taken(name.as_ref(), None);
+        self.execute(changed, |diagram| {
+            let result = diagram.rename_actor(actor_id, name);
+            (result, if changed { Some(Command::RenameActor(actor_id)) } else { None })
+        })
+    }
+
+    /// See `UseCaseDiagram::retitle_use_case`.
+    pub fn retitle_use_case(&mut self, use_case_id: UseCaseId, title: S)
+                            -> Result<bool, NameError> {
+        let changed = self.diagram.use_case(use_case_id)
+            .is_some_and(|use_case| use_case.title.as_ref() != title.as_ref())
+            && !self.use_case_title_taken(title.as_ref(), None);
+        self.execute(changed, |diagram| {
+            let result = diagram.retitle_use_case(use_case_id, title);
+            (result, if changed { Some(Command::RetitleUseCase(use_case_id)) } else { None })
+        })
+    }
+
+    /// See `UseCaseDiagram::remove_actor`.
+    pub fn remove_actor(&mut self, actor_id: ActorId) -> Option<Actor<S>> {
+        let record = self.diagram.actor(actor_id).is_some();
+        self.execute(record, |diagram| {
+            let actor = diagram.remove_actor(actor_id);
+            let command = actor.as_ref().map(|_| Command::RemoveActor(actor_id));
+            (actor, command)
+        })
+    }
+
+    /// See `UseCaseDiagram::remove_use_case`.
+    pub fn remove_use_case(&mut self, use_case_id: UseCaseId) -> Option<UseCase<S>> {
+        let record = self.diagram.use_case(use_case_id).is_some();
+        self.execute(record, |diagram| {
+            let use_case = diagram.remove_use_case(use_case_id);
+            let command = use_case.as_ref().map(|_| Command::RemoveUseCase(use_case_id));
+            (use_case, command)
+        })
+    }
+
+    /// See `UseCaseDiagram::insert_association`.
+    pub fn insert_association(&mut self, actor_id: ActorId, use_case_id: UseCaseId)
+                              -> Result<(), AssociationError> {
+        let record = self.diagram.association(actor_id, use_case_id).is_none();
+        self.execute(record, |diagram| {
+            let result = diagram.insert_association(actor_id, use_case_id);
+            let changed = result.is_ok() && record;
+            let command = Command::InsertAssociation(actor_id, use_case_id);
+            (result, if changed { Some(command) } else { None })
+        })
+    }
+
+    /// See `UseCaseDiagram::remove_association`.
+    pub fn remove_association(&mut self, actor_id: ActorId, use_case_id: UseCaseId) -> bool {
+        let record = self.diagram.association(actor_id, use_case_id).is_some();
+        self.execute(record, |diagram| {
+            let removed = diagram.remove_association(actor_id, use_case_id);
+            let command = Command::RemoveAssociation(actor_id, use_case_id);
+            (removed, if removed { Some(command) } else { None })
+        })
+    }
+
+    /// See `UseCaseDiagram::insert_include`.
+    pub fn insert_include(&mut self, base: UseCaseId, included: UseCaseId)
+                          -> Result<(), RelationshipError> {
+        let record = !self.diagram.includes.contains(&(base, included));
+        self.execute(record, |diagram| {
+            let result = diagram.insert_include(base, included);
+            let changed = result.is_ok() && record;
+            (result, if changed { Some(Command::InsertInclude(base, included)) } else { None })
+        })
+    }
+
+    /// See `UseCaseDiagram::remove_include`.
+    pub fn remove_include(&mut self, base: UseCaseId, included: UseCaseId) -> bool {
+        let record = self.diagram.includes.contains(&(base, included));
+        self.execute(record, |diagram| {
+            let removed = diagram.remove_include(base, included);
+            (removed, if removed { Some(Command::RemoveInclude(base, included)) } else { None })
+        })
+    }
+
+    /// See `UseCaseDiagram::insert_extend`.
+    pub fn insert_extend(&mut self, extend: Extend) -> Result<(), RelationshipError> {
+        let record = !self.diagram.extends.contains(&extend);
+        self.execute(record, |diagram| {
+            let result = diagram.insert_extend(extend);
+            let changed = result.is_ok() && record;
+            (result, if changed { Some(Command::InsertExtend(extend)) } else { None })
+        })
+    }
+
+    /// See `UseCaseDiagram::remove_extend`.
+    pub fn remove_extend(&mut self, extend: Extend) -> bool {
+        let record = self.diagram.extends.contains(&extend);
+        self.execute(record, |diagram| {
+            let removed = diagram.remove_extend(extend);
+            (removed, if removed { Some(Command::RemoveExtend(extend)) } else { None })
+        })
+    }
+
+    /// See `UseCaseDiagram::insert_actor_generalization`.
+    pub fn insert_actor_generalization(&mut self, child: ActorId, parent: ActorId)
+                                       -> Result<(), RelationshipError> {
+        let record = !self.diagram.actor_generalizations.contains(&(child, parent));
+        self.execute(record, |diagram| {
+            let result = diagram.insert_actor_generalization(child, parent);
+            let changed = result.is_ok() && record;
+            let command = Command::InsertActorGeneralization(child, parent);
+            (result, if changed { Some(command) } else { None })
+        })
+    }
+
+    /// See `UseCaseDiagram::remove_actor_generalization`.
+    pub fn remove_actor_generalization(&mut self, child: ActorId, parent: ActorId) -> bool {
+        let record = self.diagram.actor_generalizations.contains(&(child, parent));
+        self.execute(record, |diagram| {
+            let removed = diagram.remove_actor_generalization(child, parent);
+            let command = Command::RemoveActorGeneralization(child, parent);
+            (removed, if removed { Some(command) } else { None })
+        })
+    }
+
+    /// See `UseCaseDiagram::insert_use_case_generalization`.
+    pub fn insert_use_case_generalization(&mut self, child: UseCaseId, parent: UseCaseId)
+                                          -> Result<(), RelationshipError> {
+        let record = !self.diagram.use_case_generalizations.contains(&(child, parent));
+        self.execute(record, |diagram| {
+            let result = diagram.insert_use_case_generalization(child, parent);
+            let changed = result.is_ok() && record;
+            let command = Command::InsertUseCaseGeneralization(child, parent);
+            (result, if changed { Some(command) } else { None })
+        })
+    }
+
+    /// See `UseCaseDiagram::remove_use_case_generalization`.
+    pub fn remove_use_case_generalization(&mut self, child: UseCaseId, parent: UseCaseId)
+                                          -> bool {
+        let record = self.diagram.use_case_generalizations.contains(&(child, parent));
+        self.execute(record, |diagram| {
+            let removed = diagram.remove_use_case_generalization(child, parent);
+            let command = Command::RemoveUseCaseGeneralization(child, parent);
+            (removed, if removed { Some(command) } else { None })
+        })
+    }
+
+    /// Edit the actor with the given identifier through `actor_mut`,
+    /// returning whether it exists.
+    pub fn edit_actor<F>(&mut self, actor_id: ActorId, edit: F) -> bool
+        where F: FnOnce(&mut Actor<S>) {
+        let record = self.diagram.actor(actor_id).is_some();
+        self.execute(record, |diagram| match diagram.actor_mut(actor_id) {
+            Some(mut actor) => {
+                edit(&mut actor);
+                (true, Some(Command::EditActor(actor_id)))
+            },
+            None => (false, None),
+        })
+    }
+
+    /// Edit the use case with the given identifier through `use_case_mut`,
+    /// returning whether it exists.
+    pub fn edit_use_case<F>(&mut self, use_case_id: UseCaseId, edit: F) -> bool
+        where F: FnOnce(&mut UseCase<S>) {
+        let record = self.diagram.use_case(use_case_id).is_some();
+        self.execute(record, |diagram| match diagram.use_case_mut(use_case_id) {
+            Some(mut use_case) => {
+                edit(&mut use_case);
+                (true, Some(Command::EditUseCase(use_case_id)))
+            },
+            None => (false, None),
+        })
+    }
+
+    /// Edit the details of the association between the given actor and use
+    /// case, returning whether it exists.
+    pub fn edit_association<F>(&mut self, actor_id: ActorId, use_case_id: UseCaseId, edit: F)
+                               -> bool
+        where F: FnOnce(&mut Association<S>) {
+        let record = self.diagram.association(actor_id, use_case_id).is_some();
+        self.execute(record, |diagram| match diagram.association_mut(actor_id, use_case_id) {
+            Some(association) => {
+                edit(association);
+                (true, Some(Command::EditAssociation(actor_id, use_case_id)))
+            },
+            None => (false, None),
+        })
+    }
+
+    /// See `UseCaseDiagram::insert_association_with`.
+    pub fn insert_association_with(&mut self, actor_id: ActorId, use_case_id: UseCaseId,
+                                   association: Association<S>)
+                                   -> Result<(), AssociationError> {
+        let record = self.diagram.actor(actor_id).is_some()
+            && self.diagram.use_case(use_case_id).is_some();
+        self.execute(record, |diagram| {
+            let result = diagram.insert_association_with(actor_id, use_case_id, association);
+            let changed = result.is_ok();
+            let command = Command::InsertAssociation(actor_id, use_case_id);
+            (result, if changed { Some(command) } else { None })
+        })
+    }
+
+    /// See `UseCaseDiagram::insert_dependency`.
+    pub fn insert_dependency(&mut self, dependent: UseCaseId, dependency: UseCaseId,
+                             label: Option<S>) -> Result<(), RelationshipError> {
+        let record = !self.diagram.dependencies.get(&(dependent, dependency))
+            .is_some_and(|existing| {
+                existing.as_ref().map(AsRef::as_ref) == label.as_ref().map(AsRef::as_ref)
+            });
+        self.execute(record, |diagram| {
+            let result = diagram.insert_dependency(dependent, dependency, label);
+            let changed = result.is_ok() && record;
+            let command = Command::InsertDependency(dependent, dependency);
+            (result, if changed { Some(command) } else { None })
+        })
+    }
+
+    /// See `UseCaseDiagram::remove_dependency`.
+    pub fn remove_dependency(&mut self, dependent: UseCaseId, dependency: UseCaseId) -> bool {
+        let record = self.diagram.dependencies.contains_key(&(dependent, dependency));
+        self.execute(record, |diagram| {
+            let removed = diagram.remove_dependency(dependent, dependency);
+            let command = Command::RemoveDependency(dependent, dependency);
+            (removed, if removed { Some(command) } else { None })
+        })
+    }
+
+    /// See `UseCaseDiagram::insert_extension_point`.
+    pub fn insert_extension_point(&mut self, extension_point: ExtensionPoint<S>)
+                                  -> Result<ExtensionPointId, RelationshipError> {
+        let record = self.diagram.use_case(extension_point.use_case).is_some();
+        self.execute(record, |diagram| {
+            let result = diagram.insert_extension_point(extension_point);
+            let command = result.as_ref().ok().map(|&id| Command::InsertExtensionPoint(id));
+            (result, command)
+        })
+    }
+
+    /// See `UseCaseDiagram::remove_extension_point`.
+    pub fn remove_extension_point(&mut self, extension_point_id: ExtensionPointId)
+                                  -> Option<ExtensionPoint<S>> {
+        let record = self.diagram.extension_point(extension_point_id).is_some();
+        self.execute(record, |diagram| {
+            let extension_point = diagram.remove_extension_point(extension_point_id);
+            let command = extension_point.as_ref()
+                .map(|_| Command::RemoveExtensionPoint(extension_point_id));
+            (extension_point, command)
+        })
+    }
+
+    /// See `UseCaseDiagram::insert_system_boundary`.
+    pub fn insert_system_boundary(&mut self, system_boundary: SystemBoundary<S>)
+                                  -> Result<SystemBoundaryId, RelationshipError> {
+        let record = system_boundary.use_cases.iter()
+            .all(|&use_case_id| self.diagram.use_case(use_case_id).is_some());
+        self.execute(record, |diagram| {
+            let result = diagram.insert_system_boundary(system_boundary);
+            let command = result.as_ref().ok().map(|&id| Command::InsertSystemBoundary(id));
+            (result, command)
+        })
+    }
+
+    /// See `UseCaseDiagram::remove_system_boundary`.
+    pub fn remove_system_boundary(&mut self, system_boundary_id: SystemBoundaryId)
+                                  -> Option<SystemBoundary<S>> {
+        let record = self.diagram.system_boundary(system_boundary_id).is_some();
+        self.execute(record, |diagram| {
+            let system_boundary = diagram.remove_system_boundary(system_boundary_id);
+            let command = system_boundary.as_ref()
+                .map(|_| Command::RemoveSystemBoundary(system_boundary_id));
+            (system_boundary, command)
+        })
+    }
+
+    /// See `UseCaseDiagram::insert_system_boundary_use_case`.
+    pub fn insert_system_boundary_use_case(&mut self, system_boundary_id: SystemBoundaryId,
+                                           use_case_id: UseCaseId)
+                                           -> Result<(), RelationshipError> {
+        let record = self.diagram.system_boundary(system_boundary_id)
+            .is_some_and(|system_boundary| !system_boundary.use_cases.contains(&use_case_id));
+        self.execute(record, |diagram| {
+            let result = diagram.insert_system_boundary_use_case(system_boundary_id, use_case_id);
+            let changed = result.is_ok() && record;
+            let command = Command::InsertSystemBoundaryUseCase(system_boundary_id, use_case_id);
+            (result, if changed { Some(command) } else { None })
+        })
+    }
+
+    /// See `UseCaseDiagram::remove_system_boundary_use_case`.
+    pub fn remove_system_boundary_use_case(&mut self, system_boundary_id: SystemBoundaryId,
+                                           use_case_id: UseCaseId) -> bool {
+        let record = self.diagram.system_boundary(system_boundary_id)
+            .is_some_and(|system_boundary| system_boundary.use_cases.contains(&use_case_id));
+        self.execute(record, |diagram| {
+            let removed = diagram.remove_system_boundary_use_case(system_boundary_id, use_case_id);
+            let command = Command::RemoveSystemBoundaryUseCase(system_boundary_id, use_case_id);
+            (removed, if removed { Some(command) } else { None })
+        })
+    }
+
+    /// See `UseCaseDiagram::insert_note`.
+    pub fn insert_note(&mut self, note: Note<S>) -> Result<NoteId, RelationshipError> {
+        let record = self.diagram.note_anchor_exists(note.anchor);
+        self.execute(record, |diagram| {
+            let result = diagram.insert_note(note);
+            let command = result.as_ref().ok().map(|&id| Command::InsertNote(id));
+            (result, command)
+        })
+    }
+
+    /// See `UseCaseDiagram::remove_note`.
+    pub fn remove_note(&mut self, note_id: NoteId) -> Option<Note<S>> {
+        let record = self.diagram.note(note_id).is_some();
+        self.execute(record, |diagram| {
+            let note = diagram.remove_note(note_id);
+            let command = note.as_ref().map(|_| Command::RemoveNote(note_id));
+            (note, command)
+        })
+    }
+
+    /// See `UseCaseDiagram::merge_actors`.
+    pub fn merge_actors(&mut self, keep: ActorId, remove: ActorId)
+                        -> Result<(), AssociationError> {
+        let record = keep != remove && self.diagram.actor(keep).is_some()
+            && self.diagram.actor(remove).is_some();
+        self.execute(record, |diagram| {
+            let result = diagram.merge_actors(keep, remove);
+            let changed = result.is_ok() && keep != remove;
+            (result, if changed { Some(Command::MergeActors(keep, remove)) } else { None })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use use_case_diagram::{Note, NoteAnchor};
+
+    #[test]
+    fn test_undo_redo() {
+        let mut editor = DiagramEditor::new(UseCaseDiagram::new());
+        let s = editor.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let pc = editor.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        editor.insert_association(s, pc).unwrap();
+        editor.insert_association(s, pc).unwrap();
+        assert_eq!(editor.undo_command(), Some(Command::InsertAssociation(s, pc)));
+        assert!(!editor.remove_include(pc, pc));
+        assert_eq!(editor.undo_command(), Some(Command::InsertAssociation(s, pc)));
+
+        assert_eq!(editor.undo(), Some(Command::InsertAssociation(s, pc)));
+        assert!(editor.diagram().association(s, pc).is_none());
+        assert_eq!(editor.redo(), Some(Command::InsertAssociation(s, pc)));
+        assert!(editor.diagram().association(s, pc).is_some());
+        assert_eq!(editor.redo(), None);
+    }
+
+    #[test]
+    fn test_undo_removal() {
+        let mut diagram = UseCaseDiagram::new();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        diagram.insert_note(Note{anchor: NoteAnchor::Association(s, pc),
+                                 text: Rc::from("Rate limited")})
+            .unwrap();
+        let mut editor = DiagramEditor::new(diagram);
+
+        editor.rename_actor(s, Rc::from("Member")).unwrap();
+        assert!(editor.remove_actor(s).is_some());
+        assert_eq!(editor.diagram().notes().len(), 0);
+        assert_eq!(editor.undo(), Some(Command::RemoveActor(s)));
+        assert_eq!(editor.diagram().actor(s).map(|actor| &*actor.name), Some("Member"));
+        assert!(editor.diagram().association(s, pc).is_some());
+        assert_eq!(editor.diagram().notes().len(), 1);
+        assert_eq!(editor.undo(), Some(Command::RenameActor(s)));
+        assert_eq!(editor.diagram().actor_by_name("Subscriber").map(|(id, _)| id), Some(s));
+        assert_eq!(editor.undo(), None);
+
+        editor.redo();
+        editor.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        assert_eq!(editor.redo_command(), None);
+        assert_eq!(editor.diagram().use_cases().len(), 2);
+    }
+
+    #[test]
+    fn test_edits() {
+        let mut editor = DiagramEditor::new(UseCaseDiagram::new());
+        let s = editor.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let m = editor.insert_actor(Actor::new(Rc::from("Member"))).unwrap();
+        let pc = editor.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        editor.insert_association(m, pc).unwrap();
+        assert!(editor.edit_use_case(pc, |use_case| {
+            use_case.description = Some(Rc::from("Reply to a post"));
+        }));
+        assert!(!editor.edit_actor(ActorId(7), |_| ()));
+        let note = editor.insert_note(Note{anchor: NoteAnchor::Actor(m), text: Rc::from("New")})
+            .unwrap();
+        editor.merge_actors(s, m).unwrap();
+        assert!(editor.diagram().association(s, pc).is_some());
+
+        assert_eq!(editor.undo(), Some(Command::MergeActors(s, m)));
+        assert!(editor.diagram().association(m, pc).is_some());
+        assert_eq!(editor.undo(), Some(Command::InsertNote(note)));
+        assert_eq!(editor.undo(), Some(Command::EditUseCase(pc)));
+        assert_eq!(editor.diagram()[pc].description, None);
+    }
+
+    #[test]
+    fn test_history_limit() {
+        let mut editor = DiagramEditor::new(UseCaseDiagram::new());
+        assert_eq!(editor.history_limit(), DEFAULT_HISTORY_LIMIT);
+        editor.set_history_limit(2);
+        let s = editor.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let pc = editor.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        editor.insert_association(s, pc).unwrap();
+        assert_eq!(editor.undo(), Some(Command::InsertAssociation(s, pc)));
+        assert_eq!(editor.undo(), Some(Command::InsertUseCase(pc)));
+        assert_eq!(editor.undo(), None);
+        assert_eq!(editor.diagram().actors().len(), 1);
+
+        editor.redo();
+        editor.set_history_limit(0);
+        assert_eq!(editor.undo_command(), None);
+        assert_eq!(editor.redo_command(), Some(Command::InsertAssociation(s, pc)));
+    }
+
+    #[test]
+    fn test_rejected_edits() {
+        let mut editor = DiagramEditor::new(UseCaseDiagram::with_unique_names());
+        let s = editor.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let m = editor.insert_actor(Actor::new(Rc::from("Member"))).unwrap();
+        assert!(editor.insert_actor(Actor::new(Rc::from("Member"))).is_err());
+        assert!(editor.rename_actor(s, Rc::from("Member")).is_err());
+        assert_eq!(editor.rename_actor(m, Rc::from("Member")), Ok(true));
+        assert!(editor.remove_use_case(UseCaseId(7)).is_none());
+        assert!(editor.merge_actors(s, s).is_ok());
+        assert_eq!(editor.undo(), Some(Command::InsertActor(m)));
+        assert_eq!(editor.undo(), Some(Command::InsertActor(s)));
+        assert_eq!(editor.undo(), None);
+    }
+
+    #[test]
+    fn test_undo_events() {
+        let mut diagram = UseCaseDiagram::new();
+        diagram.set_event_recording(true);
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let mut editor = DiagramEditor::new(diagram);
+        let pc = editor.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        editor.insert_association(s, pc).unwrap();
+        assert_eq!(editor.drain_events().collect::<Vec<_>>(), vec![
+            Event::ActorInserted(s),
+            Event::UseCaseInserted(pc),
+            Event::AssociationInserted(s, pc),
+        ]);
+
+        editor.remove_use_case(pc);
+        editor.undo();
+        editor.undo();
+        assert_eq!(editor.drain_events().collect::<Vec<_>>(), vec![
+            Event::AssociationRemoved(s, pc),
+            Event::UseCaseRemoved(pc),
+            Event::UseCaseInserted(pc),
+            Event::AssociationInserted(s, pc),
+            Event::AssociationRemoved(s, pc),
+        ]);
+
+        editor.redo();
+        editor.insert_actor(Actor::new(Rc::from("Member"))).unwrap();
+        let mut diagram = editor.into_diagram();
+        assert_eq!(diagram.drain_events().count(), 2);
+    }
+}
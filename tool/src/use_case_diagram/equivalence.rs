@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+use use_case_diagram::{Extend, NoteAnchor, UseCaseDiagram};
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> + PartialEq {
+    /// Whether this diagram and the other describe the same model, up to
+    /// identifiers. Actors are matched by name, use cases by title,
+    /// extension points by name within their use case, and system
+    /// boundaries by name; elements sharing a name are matched in order of
+    /// their identifiers. Matched elements must have the same details, apart
+    /// from where they were defined, and the relationships and notes between
+    /// them must be the same.
+    /// Configuration such as unique names and event recording is ignored.
+    pub fn equivalent(&self, other: &UseCaseDiagram<S>) -> bool {
+        let actor_map = match match_by_name(
+            self.actors().map(|(id, actor)| (id, actor.name.as_ref())),
+            other.actors().map(|(id, actor)| (id, actor.name.as_ref())),
+        ) {
+            Some(actor_map) => actor_map,
+            None => return false,
+        };
+        let use_case_map = match match_by_name(
+            self.use_cases().map(|(id, use_case)| (id, use_case.title.as_ref())),
+            other.use_cases().map(|(id, use_case)| (id, use_case.title.as_ref())),
+        ) {
+            Some(use_case_map) => use_case_map,
+            None => return false,
+        };
+        if actor_map.iter().any(|(&ours, &theirs)| !self[ours].eq_ignoring_span(&other[theirs])) ||
+           use_case_map.iter()
+           .any(|(&ours, &theirs)| !self[ours].eq_ignoring_span(&other[theirs])) {
+            return false;
+        }
+
+        let mut extension_point_map = HashMap::new();
+        for (use_case_id, _) in self.use_cases() {
+            match match_by_name(
+                self.extension_points_of_use_case(use_case_id)
+                .map(|(id, extension_point)| (id, extension_point.name.as_ref())),
+                other.extension_points_of_use_case(use_case_map[&use_case_id])
+                .map(|(id, extension_point)| (id, extension_point.name.as_ref())),
+            ) {
+                Some(map) => extension_point_map.extend(map),
+                None => return false,
+            }
+        }
+
+        let associations_equivalent =
+            self.associations.len() == other.associations.len() &&
+            self.associations.iter().all(|(&(actor_id, use_case_id), association)| {
+                other.association(actor_map[&actor_id], use_case_map[&use_case_id])
+                    .is_some_and(|theirs| association.eq_ignoring_span(theirs))
+            });
+        let includes_equivalent =
+            self.includes.len() == other.includes.len() &&
+            self.includes().all(|(base, included)| {
+                other.includes.contains(&(use_case_map[&base], use_case_map[&included]))
+            });
+        let dependencies_equivalent =
+            self.dependencies.len() == other.dependencies.len() &&
+            self.dependencies.iter().all(|(&(dependent, dependency), label)| {
+                other.dependencies.get(&(use_case_map[&dependent], use_case_map[&dependency])) ==
+                    Some(label)
+            });
+        let extends_equivalent =
+            self.extends.len() == other.extends.len() &&
+            self.extends().all(|extend| {
+                other.extends.contains(&Extend{
+                    extension: use_case_map[&extend.extension],
+                    base: use_case_map[&extend.base],
+                    extension_point: extend.extension_point.map(|id| extension_point_map[&id]),
+                })
+            });
+        let generalizations_equivalent =
+            self.actor_generalizations.len() == other.actor_generalizations.len() &&
+            self.actor_generalizations().all(|(child, parent)| {
+                other.actor_generalizations.contains(&(actor_map[&child], actor_map[&parent]))
+            }) &&
+            self.use_case_generalizations.len() == other.use_case_generalizations.len() &&
+            self.use_case_generalizations().all(|(child, parent)| {
+                other.use_case_generalizations.contains(&(use_case_map[&child],
+                                                          use_case_map[&parent]))
+            });
+        if !(associations_equivalent && includes_equivalent && dependencies_equivalent &&
+             extends_equivalent && generalizations_equivalent) {
+            return false;
+        }
+
+        let system_boundary_map = match match_by_name(
+            self.system_boundaries().map(|(id, boundary)| (id, boundary.name.as_ref())),
+            other.system_boundaries().map(|(id, boundary)| (id, boundary.name.as_ref())),
+        ) {
+            Some(system_boundary_map) => system_boundary_map,
+            None => return false,
+        };
+        let system_boundaries_equivalent =
+            system_boundary_map.iter().all(|(ours, theirs)| {
+                let use_cases = &other.system_boundaries[theirs].use_cases;
+                self.system_boundaries[ours].use_cases.len() == use_cases.len() &&
+                self.system_boundaries[ours].use_cases.iter()
+                    .all(|use_case_id| use_cases.contains(&use_case_map[use_case_id]))
+            });
+
+        let mut notes =
+            self.notes()
+            .map(|(_, note)| {
+                let anchor = match note.anchor {
+                    NoteAnchor::Actor(actor_id) => NoteAnchor::Actor(actor_map[&actor_id]),
+                    NoteAnchor::UseCase(use_case_id) =>
+                        NoteAnchor::UseCase(use_case_map[&use_case_id]),
+                    NoteAnchor::Association(actor_id, use_case_id) =>
+                        NoteAnchor::Association(actor_map[&actor_id], use_case_map[&use_case_id]),
+                };
+                (anchor, note.text.as_ref())
+            })
+            .collect::<Vec<_>>();
+        let mut other_notes =
+            other.notes()
+            .map(|(_, note)| (note.anchor, note.text.as_ref()))
+            .collect::<Vec<_>>();
+        notes.sort();
+        other_notes.sort();
+
+        system_boundaries_equivalent && notes == other_notes
+    }
+}
+
+/// Match the elements of one diagram to those of another by name, pairing
+/// elements that share a name in the order given. Return `None` if some
+/// name is not shared by equally many elements of both.
+fn match_by_name<'a, I, J, Id>(ours: I, theirs: J) -> Option<HashMap<Id, Id>>
+    where I: Iterator<Item = (Id, &'a str)>, J: Iterator<Item = (Id, &'a str)>,
+          Id: Eq + Hash {
+    let mut ours_by_name = BTreeMap::<&str, Vec<Id>>::new();
+    for (id, name) in ours {
+        ours_by_name.entry(name).or_default().push(id);
+    }
+    let mut theirs_by_name = BTreeMap::<&str, Vec<Id>>::new();
+    for (id, name) in theirs {
+        theirs_by_name.entry(name).or_default().push(id);
+    }
+    if ours_by_name.len() != theirs_by_name.len() {
+        return None;
+    }
+    let mut map = HashMap::new();
+    for (name, ours) in ours_by_name {
+        let theirs = theirs_by_name.remove(name)?;
+        if ours.len() != theirs.len() {
+            return None;
+        }
+        map.extend(ours.into_iter().zip(theirs));
+    }
+    Some(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use use_case_diagram::{Actor, Note, Span, UseCase};
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_equivalent() {
+        let mut diagram = UseCaseDiagram::new();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        diagram.insert_include(pc, li).unwrap();
+        diagram.insert_actor_generalization(a, s).unwrap();
+        diagram.insert_note(Note{anchor: NoteAnchor::Association(s, pc),
+                                 text: Rc::from("Rate limited")})
+            .unwrap();
+
+        let json = diagram.to_json_by_name().unwrap();
+        let round_tripped = UseCaseDiagram::from_json_by_name(&json).unwrap();
+        assert_ne!(round_tripped.actor_by_name("Subscriber").unwrap().0, s);
+        assert!(diagram.equivalent(&round_tripped));
+        assert!(round_tripped.equivalent(&diagram));
+
+        let mut changed = round_tripped.clone();
+        let (li2, _) = changed.use_case_by_title("Log in").unwrap();
+        let (pc2, _) = changed.use_case_by_title("Post comment").unwrap();
+        changed.remove_include(pc2, li2);
+        assert!(!diagram.equivalent(&changed));
+
+        let mut changed = round_tripped.clone();
+        changed.actor_mut(changed.actor_by_name("Subscriber").unwrap().0).unwrap()
+            .description = Some(Rc::from("Reads and posts"));
+        assert!(!diagram.equivalent(&changed));
+
+        let mut changed = round_tripped;
+        changed.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        assert!(!diagram.equivalent(&changed));
+    }
+
+    #[test]
+    fn test_spans_ignored_by_equivalence() {
+        let mut diagram = UseCaseDiagram::new();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+
+        let mut parsed = diagram.clone();
+        let span = |line| Some(Span{file: Rc::from("journal.uc"), line, column: 1});
+        parsed.actor_mut(s).unwrap().span = span(1);
+        parsed.use_case_mut(pc).unwrap().span = span(2);
+        parsed.association_mut(s, pc).unwrap().span = span(3);
+        assert!(diagram.equivalent(&parsed));
+        assert!(!diagram[s].eq_ignoring_span(&Actor::new(Rc::from("Guest"))));
+    }
+}
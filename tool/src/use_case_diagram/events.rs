@@ -0,0 +1,152 @@
+use std::vec;
+
+use use_case_diagram::{ActorId, Extend, ExtensionPointId, NoteId, SystemBoundaryId,
+                       UseCaseDiagram, UseCaseId};
+
+/// A change to a diagram, as recorded when event recording is enabled.
+///
+/// Removing an element also removes the relationships and notes that refer
+/// to it; events for those come before the event for the element itself.
+/// `Changed` events are recorded whenever mutable access to an element is
+/// handed out, whether or not the element is then changed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Event {
+    ActorInserted(ActorId),
+    ActorChanged(ActorId),
+    ActorRemoved(ActorId),
+    UseCaseInserted(UseCaseId),
+    UseCaseChanged(UseCaseId),
+    UseCaseRemoved(UseCaseId),
+    AssociationInserted(ActorId, UseCaseId),
+    AssociationChanged(ActorId, UseCaseId),
+    AssociationRemoved(ActorId, UseCaseId),
+    IncludeInserted(UseCaseId, UseCaseId),
+    IncludeRemoved(UseCaseId, UseCaseId),
+    DependencyInserted(UseCaseId, UseCaseId),
+    DependencyChanged(UseCaseId, UseCaseId),
+    DependencyRemoved(UseCaseId, UseCaseId),
+    ExtendInserted(Extend),
+    ExtendRemoved(Extend),
+    ExtensionPointInserted(ExtensionPointId),
+    ExtensionPointRemoved(ExtensionPointId),
+    ActorGeneralizationInserted(ActorId, ActorId),
+    ActorGeneralizationRemoved(ActorId, ActorId),
+    UseCaseGeneralizationInserted(UseCaseId, UseCaseId),
+    UseCaseGeneralizationRemoved(UseCaseId, UseCaseId),
+    SystemBoundaryInserted(SystemBoundaryId),
+    SystemBoundaryChanged(SystemBoundaryId),
+    SystemBoundaryRemoved(SystemBoundaryId),
+    NoteInserted(NoteId),
+    NoteChanged(NoteId),
+    NoteRemoved(NoteId),
+}
+
+impl Event {
+    /// The event that undoes this one: insertions and removals swap, and
+    /// changes stay changes.
+    pub(super) fn inverse(self) -> Event {
+        match self {
+            Event::ActorInserted(a) => Event::ActorRemoved(a),
+            Event::ActorRemoved(a) => Event::ActorInserted(a),
+            Event::UseCaseInserted(u) => Event::UseCaseRemoved(u),
+            Event::UseCaseRemoved(u) => Event::UseCaseInserted(u),
+            Event::AssociationInserted(a, u) => Event::AssociationRemoved(a, u),
+            Event::AssociationRemoved(a, u) => Event::AssociationInserted(a, u),
+            Event::IncludeInserted(b, i) => Event::IncludeRemoved(b, i),
+            Event::IncludeRemoved(b, i) => Event::IncludeInserted(b, i),
+            Event::DependencyInserted(d, e) => Event::DependencyRemoved(d, e),
+            Event::DependencyRemoved(d, e) => Event::DependencyInserted(d, e),
+            Event::ExtendInserted(e) => Event::ExtendRemoved(e),
+            Event::ExtendRemoved(e) => Event::ExtendInserted(e),
+            Event::ExtensionPointInserted(e) => Event::ExtensionPointRemoved(e),
+            Event::ExtensionPointRemoved(e) => Event::ExtensionPointInserted(e),
+            Event::ActorGeneralizationInserted(c, p) => Event::ActorGeneralizationRemoved(c, p),
+            Event::ActorGeneralizationRemoved(c, p) => Event::ActorGeneralizationInserted(c, p),
+            Event::UseCaseGeneralizationInserted(c, p) =>
+                Event::UseCaseGeneralizationRemoved(c, p),
+            Event::UseCaseGeneralizationRemoved(c, p) =>
+                Event::UseCaseGeneralizationInserted(c, p),
+            Event::SystemBoundaryInserted(s) => Event::SystemBoundaryRemoved(s),
+            Event::SystemBoundaryRemoved(s) => Event::SystemBoundaryInserted(s),
+            Event::NoteInserted(n) => Event::NoteRemoved(n),
+            Event::NoteRemoved(n) => Event::NoteInserted(n),
+            Event::ActorChanged(_) | Event::UseCaseChanged(_) | Event::AssociationChanged(..)
+            | Event::DependencyChanged(..) | Event::SystemBoundaryChanged(_)
+            | Event::NoteChanged(_) => self,
+        }
+    }
+}
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> {
+    /// Start or stop recording an event for every change to this diagram.
+    /// Stopping discards the events not yet drained. Recording is off by
+    /// default.
+    pub fn set_event_recording(&mut self, enabled: bool) {
+        self.event_recording = enabled;
+        if !enabled {
+            self.events.clear();
+        }
+    }
+
+    /// Whether changes to this diagram are being recorded.
+    pub fn has_event_recording(&self) -> bool {
+        self.event_recording
+    }
+
+    /// Remove and return the events recorded so far, oldest first.
+    pub fn drain_events(&mut self) -> vec::Drain<'_, Event> {
+        self.events.drain(..)
+    }
+
+    /// Record the given event, if recording.
+    pub(super) fn emit(&mut self, event: Event) {
+        if self.event_recording {
+            self.events.push(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use use_case_diagram::{Actor, Note, NoteAnchor, UseCase};
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_events() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        assert!(!diagram.has_event_recording());
+        assert_eq!(diagram.drain_events().count(), 0);
+
+        diagram.set_event_recording(true);
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        diagram.insert_actor_generalization(s, a).unwrap();
+        let n = diagram.insert_note(Note{anchor: NoteAnchor::Association(s, pc),
+                                         text: Rc::from("Rate limited")})
+            .unwrap();
+        diagram.rename_actor(a, Rc::from("Admin")).unwrap();
+        assert_eq!(diagram.drain_events().collect::<Vec<_>>(), vec![
+            Event::ActorInserted(s),
+            Event::UseCaseInserted(pc),
+            Event::AssociationInserted(s, pc),
+            Event::ActorGeneralizationInserted(s, a),
+            Event::NoteInserted(n),
+            Event::ActorChanged(a),
+        ]);
+
+        diagram.remove_actor(s);
+        assert_eq!(diagram.drain_events().collect::<Vec<_>>(), vec![
+            Event::NoteRemoved(n),
+            Event::AssociationRemoved(s, pc),
+            Event::ActorGeneralizationRemoved(s, a),
+            Event::ActorRemoved(s),
+        ]);
+        assert_eq!(diagram.drain_events().count(), 0);
+    }
+}
@@ -0,0 +1,474 @@
+//! Excalidraw scenes, for teams who sketch in Excalidraw but keep the
+//! diagram as the source of truth.
+//!
+//! Elements are placed as by `layout`. Actors are stick figures grouped with
+//! their names, use cases are ellipses with their titles inside, and system
+//! boundaries are rectangles behind them with their names in the top left
+//! corner. Edges are drawn as in the draw.io export, and are bound to the use
+//! cases at their ends so that they follow them when the sketch is touched
+//! up. Identifiers are as in the other formats, e.g. `actor_0` is the group
+//! of the first actor. Notes are left out.
+
+use std::collections::BTreeMap;
+use std::io;
+
+use use_case_diagram::layout::{Layout, Rectangle};
+use use_case_diagram::{ActorId, UseCaseDiagram, UseCaseId};
+
+const FONT_SIZE: i64 = 16;
+const LINE_HEIGHT: i64 = 20;
+
+/// Roughly how wide a character of the hand-drawn font is.
+const CHARACTER_WIDTH: i64 = 9;
+
+/// How far edges between use cases in the same column bulge out to the
+/// right, at least.
+const BULGE: i64 = 40;
+
+/// Write the diagram as an Excalidraw scene.
+pub fn to_excalidraw<W, S>(diagram: &UseCaseDiagram<S>, w: &mut W) -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    let layout = diagram.layout();
+    let edges = edges(diagram);
+    let mut scene = Scene{type_: "excalidraw", version: 2, source: "butterfly",
+                          elements: Vec::new(),
+                          app_state: AppState{view_background_color: "#ffffff", grid_size: None},
+                          files: BTreeMap::new()};
+
+    for (system_boundary_id, rectangle) in &layout.system_boundaries {
+        let system_boundary = diagram.system_boundary(*system_boundary_id).unwrap();
+        let id = format!("system_boundary_{}", system_boundary_id);
+        let mut frame = Element::new(format!("{}_frame", id), "rectangle", *rectangle);
+        frame.group_ids = vec![id.clone()];
+        scene.push(frame);
+        let name = Rectangle{x: rectangle.x + 10, y: rectangle.y + 5, ..Rectangle::default()};
+        let mut name = Element::text(format!("{}_name", id), name,
+                                     system_boundary.name.as_ref(), None);
+        name.group_ids = vec![id];
+        name.text.as_mut().unwrap().text_align = "left";
+        scene.push(name);
+    }
+
+    for (actor_id, actor) in diagram.actors_in_order() {
+        let id = format!("actor_{}", actor_id);
+        let rectangle = layout.actors[&actor_id];
+        let (x, y, middle) = (rectangle.x, rectangle.y, rectangle.x + rectangle.width / 2);
+        let head = Rectangle{x: middle - 8, y, width: 16, height: 16};
+        let mut parts = vec![
+            Element::new(format!("{}_head", id), "ellipse", head),
+            Element::line(format!("{}_body", id), &[(middle, y + 16), (middle, y + 40)], None),
+            Element::line(format!("{}_arms", id), &[(x, y + 24), (x + rectangle.width, y + 24)],
+                          None),
+            Element::line(format!("{}_legs", id),
+                          &[(x, rectangle.bottom()), (middle, y + 40),
+                            (x + rectangle.width, rectangle.bottom())], None),
+            Element::text(format!("{}_name", id),
+                          Rectangle{x: middle, y: rectangle.bottom() + 4, ..Rectangle::default()},
+                          actor.name.as_ref(), None),
+        ];
+        for part in &mut parts {
+            part.group_ids = vec![id.clone()];
+            if !actor.kind.is_human() {
+                part.stroke_style = "dashed";
+            }
+        }
+        parts[4].x -= parts[4].width / 2;
+        for part in parts {
+            scene.push(part);
+        }
+    }
+
+    for (use_case_id, use_case) in diagram.use_cases_in_order() {
+        let id = format!("use_case_{}", use_case_id);
+        let rectangle = layout.use_cases[&use_case_id];
+        let mut ellipse = Element::new(id.clone(), "ellipse", rectangle);
+        let title = Element::text(format!("{}_title", id), rectangle, use_case.title.as_ref(),
+                                  Some(&id));
+        ellipse.bound_elements.push(BoundElement{id: title.id.clone(), type_: "text"});
+        ellipse.bound_elements.extend(
+            edges.iter()
+                .filter(|edge| edge.source == Node::UseCase(use_case_id) ||
+                               edge.target == Node::UseCase(use_case_id))
+                .map(|edge| BoundElement{id: edge.id(), type_: "arrow"}));
+        scene.push(ellipse);
+        scene.push(title);
+    }
+
+    for edge in &edges {
+        let (start, end) = (edge.source.rectangle(&layout), edge.target.rectangle(&layout));
+        let mut arrow = Element::line(edge.id(), &route(start, end), edge.arrowhead);
+        arrow.type_ = "arrow";
+        if edge.dashed {
+            arrow.stroke_style = "dashed";
+        }
+        {
+            let linear = arrow.linear.as_mut().unwrap();
+            linear.start_binding = edge.source.binding();
+            linear.end_binding = edge.target.binding();
+        }
+        let label = edge.label.as_ref().map(|label| {
+            let center = Rectangle{x: arrow.x + arrow.width / 2, y: arrow.y + arrow.height / 2,
+                                   ..Rectangle::default()};
+            Element::text(format!("{}_label", arrow.id), center, label, Some(&arrow.id))
+        });
+        if let Some(ref label) = label {
+            arrow.bound_elements.push(BoundElement{id: label.id.clone(), type_: "text"});
+        }
+        scene.push(arrow);
+        if let Some(label) = label {
+            scene.push(label);
+        }
+    }
+
+    ::serde_json::to_writer_pretty(&mut *w, &scene)?;
+    writeln!(w)
+}
+
+/// Write the given layer of the diagram as an Excalidraw scene, like
+/// `to_excalidraw`, with the layers in the given order; see
+/// `UseCaseDiagram::layer_subdiagram`.
+pub fn to_excalidraw_in_layer<W, S, L>(diagram: &UseCaseDiagram<S>, order: &[L], layer: &str,
+                                       w: &mut W) -> io::Result<()>
+    where W: io::Write, S: AsRef<str> + Clone, L: AsRef<str> {
+    to_excalidraw(&diagram.layer_subdiagram(order, layer), w)
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Node {
+    Actor(ActorId),
+    UseCase(UseCaseId),
+}
+
+impl Node {
+    fn rectangle(self, layout: &Layout) -> Rectangle {
+        match self {
+            Node::Actor(actor_id) => layout.actors[&actor_id],
+            Node::UseCase(use_case_id) => layout.use_cases[&use_case_id],
+        }
+    }
+
+    /// Stick figures are made of several elements, so edges are bound only
+    /// to use cases.
+    fn binding(self) -> Option<Binding> {
+        match self {
+            Node::Actor(_) => None,
+            Node::UseCase(use_case_id) =>
+                Some(Binding{element_id: format!("use_case_{}", use_case_id), focus: 0, gap: 4}),
+        }
+    }
+
+    fn id(self) -> String {
+        match self {
+            Node::Actor(actor_id) => format!("actor_{}", actor_id),
+            Node::UseCase(use_case_id) => format!("use_case_{}", use_case_id),
+        }
+    }
+}
+
+struct Edge {
+    kind: &'static str,
+    source: Node,
+    target: Node,
+    label: Option<String>,
+    dashed: bool,
+    arrowhead: Option<&'static str>,
+}
+
+impl Edge {
+    fn id(&self) -> String {
+        format!("{}_{}_{}", self.kind, self.source.id(), self.target.id())
+    }
+}
+
+/// Every edge of the diagram, in the order of the draw.io export.
+fn edges<S>(diagram: &UseCaseDiagram<S>) -> Vec<Edge> where S: AsRef<str> {
+    let mut edges = Vec::new();
+    let edge = |kind, source, target, label: Option<String>| {
+        let (dashed, arrowhead) = match kind {
+            "association" => (false, None),
+            "generalization" => (false, Some("triangle")),
+            _ => (true, Some("arrow")),
+        };
+        Edge{kind, source, target, label, dashed, arrowhead}
+    };
+
+    let mut associations = diagram.associations().collect::<Vec<_>>();
+    associations.sort();
+    for (actor_id, use_case_id) in associations {
+        let label = diagram.association(actor_id, use_case_id).unwrap().label.as_ref()
+            .map(|label| label.as_ref().to_string());
+        edges.push(edge("association", Node::Actor(actor_id), Node::UseCase(use_case_id),
+                        label));
+    }
+    let mut includes = diagram.includes().collect::<Vec<_>>();
+    includes.sort();
+    for (base, included) in includes {
+        edges.push(edge("include", Node::UseCase(base), Node::UseCase(included),
+                        Some("«include»".to_string())));
+    }
+    let mut extends = diagram.extends().collect::<Vec<_>>();
+    extends.sort();
+    for extend in extends {
+        edges.push(edge("extend", Node::UseCase(extend.extension), Node::UseCase(extend.base),
+                        Some("«extend»".to_string())));
+    }
+    let mut dependencies = diagram.dependencies().collect::<Vec<_>>();
+    dependencies.sort();
+    for (dependent, dependency) in dependencies {
+        let label = match diagram.dependency_label(dependent, dependency) {
+            Some(label) => format!("«depends on» {}", label.as_ref()),
+            None => "«depends on»".to_string(),
+        };
+        edges.push(edge("dependency", Node::UseCase(dependent), Node::UseCase(dependency),
+                        Some(label)));
+    }
+    let mut generalizations = diagram.actor_generalizations().collect::<Vec<_>>();
+    generalizations.sort();
+    for (child, parent) in generalizations {
+        edges.push(edge("generalization", Node::Actor(child), Node::Actor(parent), None));
+    }
+    let mut generalizations = diagram.use_case_generalizations().collect::<Vec<_>>();
+    generalizations.sort();
+    for (child, parent) in generalizations {
+        edges.push(edge("generalization", Node::UseCase(child), Node::UseCase(parent), None));
+    }
+    edges
+}
+
+/// The points of an edge between two rectangles. Edges between rectangles
+/// side by side go straight across; edges between rectangles in the same
+/// column bulge out to the right, further the further apart they are.
+fn route(start: Rectangle, end: Rectangle) -> Vec<(i64, i64)> {
+    let (start_center, end_center) = (start.center(), end.center());
+    if start.x < end.x + end.width && end.x < start.x + start.width {
+        let right = start.x.max(end.x) + start.width.max(end.width);
+        let bulge = BULGE + (end_center.1 - start_center.1).abs() / 4;
+        let from = (start.x + start.width, start_center.1);
+        let to = (end.x + end.width, end_center.1);
+        vec![from, (right + bulge, (from.1 + to.1) / 2), to]
+    } else if start_center.0 < end_center.0 {
+        vec![(start.x + start.width, start_center.1), (end.x, end_center.1)]
+    } else {
+        vec![(start.x, start_center.1), (end.x + end.width, end_center.1)]
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Scene {
+    #[serde(rename = "type")]
+    type_: &'static str,
+    version: u32,
+    source: &'static str,
+    elements: Vec<Element>,
+    app_state: AppState,
+    files: BTreeMap<String, String>,
+}
+
+impl Scene {
+    /// Add an element, seeding its hand-drawn look by its position in the
+    /// scene so that regenerating the scene draws it the same.
+    fn push(&mut self, mut element: Element) {
+        element.seed = self.elements.len() as u64 + 1;
+        element.version_nonce = element.seed;
+        self.elements.push(element);
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AppState {
+    view_background_color: &'static str,
+    grid_size: Option<u32>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Element {
+    id: String,
+    #[serde(rename = "type")]
+    type_: &'static str,
+    x: i64,
+    y: i64,
+    width: i64,
+    height: i64,
+    angle: i64,
+    stroke_color: &'static str,
+    background_color: &'static str,
+    fill_style: &'static str,
+    stroke_width: i64,
+    stroke_style: &'static str,
+    roughness: i64,
+    opacity: i64,
+    group_ids: Vec<String>,
+    frame_id: Option<String>,
+    roundness: Option<Roundness>,
+    seed: u64,
+    version: u64,
+    version_nonce: u64,
+    is_deleted: bool,
+    bound_elements: Vec<BoundElement>,
+    updated: u64,
+    link: Option<String>,
+    locked: bool,
+    #[serde(flatten)]
+    text: Option<Text>,
+    #[serde(flatten)]
+    linear: Option<Linear>,
+}
+
+impl Element {
+    fn new(id: String, type_: &'static str, rectangle: Rectangle) -> Self {
+        let roundness = if type_ == "rectangle" { Some(Roundness{type_: 3}) } else { None };
+        Element{id, type_, x: rectangle.x, y: rectangle.y, width: rectangle.width,
+                height: rectangle.height, angle: 0, stroke_color: "#1e1e1e",
+                background_color: "transparent", fill_style: "solid", stroke_width: 2,
+                stroke_style: "solid", roughness: 1, opacity: 100, group_ids: Vec::new(),
+                frame_id: None, roundness, seed: 0, version: 1, version_nonce: 0,
+                is_deleted: false, bound_elements: Vec::new(), updated: 1, link: None,
+                locked: false, text: None, linear: None}
+    }
+
+    /// Text centred in the container if any, and otherwise starting at the
+    /// top left corner of the rectangle, whose size is ignored.
+    fn text(id: String, rectangle: Rectangle, text: &str, container_id: Option<&str>) -> Self {
+        let lines = text.lines().collect::<Vec<_>>();
+        let width = lines.iter().map(|line| line.chars().count() as i64).max().unwrap_or(0) *
+            CHARACTER_WIDTH;
+        let height = lines.len().max(1) as i64 * LINE_HEIGHT;
+        let (x, y) = match container_id {
+            Some(_) => {
+                let (x, y) = rectangle.center();
+                (x - width / 2, y - height / 2)
+            },
+            None => (rectangle.x, rectangle.y),
+        };
+        let mut element = Element::new(id, "text", Rectangle{x, y, width, height});
+        element.stroke_width = 1;
+        element.text = Some(Text{text: text.to_string(), font_size: FONT_SIZE, font_family: 1,
+                                 text_align: "center", vertical_align: "middle",
+                                 container_id: container_id.map(str::to_string),
+                                 original_text: text.to_string(), line_height: 1.25,
+                                 auto_resize: true});
+        element
+    }
+
+    /// A line through the points, with the given arrowhead at its end.
+    fn line(id: String, points: &[(i64, i64)], arrowhead: Option<&'static str>) -> Self {
+        let (x, y) = points[0];
+        let points = points.iter().map(|&(px, py)| (px - x, py - y)).collect::<Vec<_>>();
+        let left = points.iter().map(|point| point.0).min().unwrap();
+        let right = points.iter().map(|point| point.0).max().unwrap();
+        let top = points.iter().map(|point| point.1).min().unwrap();
+        let bottom = points.iter().map(|point| point.1).max().unwrap();
+        let mut element = Element::new(id, "line",
+                                       Rectangle{x, y, width: right - left,
+                                                 height: bottom - top});
+        element.roundness = Some(Roundness{type_: 2});
+        element.linear = Some(Linear{points, start_binding: None, end_binding: None,
+                                     start_arrowhead: None,
+                                     end_arrowhead: arrowhead,
+                                     last_committed_point: None});
+        element
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Text {
+    text: String,
+    font_size: i64,
+    font_family: i64,
+    text_align: &'static str,
+    vertical_align: &'static str,
+    container_id: Option<String>,
+    original_text: String,
+    line_height: f64,
+    auto_resize: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Linear {
+    points: Vec<(i64, i64)>,
+    start_binding: Option<Binding>,
+    end_binding: Option<Binding>,
+    start_arrowhead: Option<&'static str>,
+    end_arrowhead: Option<&'static str>,
+    last_committed_point: Option<(i64, i64)>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Binding {
+    element_id: String,
+    focus: i64,
+    gap: i64,
+}
+
+#[derive(Serialize)]
+struct BoundElement {
+    id: String,
+    #[serde(rename = "type")]
+    type_: &'static str,
+}
+
+#[derive(Serialize)]
+struct Roundness {
+    #[serde(rename = "type")]
+    type_: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::{self, Value};
+
+    use use_case_diagram::{Actor, UseCase};
+
+    #[test]
+    fn test_to_excalidraw() {
+        let mut diagram = UseCaseDiagram::<&str>::new();
+        let subscriber = diagram.insert_actor(Actor::new("Subscriber")).unwrap();
+        let post = diagram.insert_use_case(UseCase::new("Post comment")).unwrap();
+        let reply = diagram.insert_use_case(UseCase::new("Reply")).unwrap();
+        diagram.insert_association_with(subscriber, post, ::use_case_diagram::Association{
+            label: Some("writes"), ..Default::default()
+        }).unwrap();
+        diagram.insert_include(reply, post).unwrap();
+
+        let mut output = Vec::new();
+        to_excalidraw(&diagram, &mut output).unwrap();
+        let scene = serde_json::from_slice::<Value>(&output).unwrap();
+        assert_eq!(scene["type"], "excalidraw");
+        let elements = scene["elements"].as_array().unwrap();
+        let ids = elements.iter().map(|element| element["id"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["actor_0_head", "actor_0_body", "actor_0_arms", "actor_0_legs",
+                             "actor_0_name", "use_case_0", "use_case_0_title", "use_case_1",
+                             "use_case_1_title", "association_actor_0_use_case_0",
+                             "association_actor_0_use_case_0_label",
+                             "include_use_case_1_use_case_0",
+                             "include_use_case_1_use_case_0_label"]);
+        let seeds = elements.iter().map(|element| element["seed"].as_u64().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(seeds, (1..14).collect::<Vec<_>>());
+
+        let association = &elements[9];
+        assert_eq!(association["points"], serde_json::json!([[0, 0], [175, 0]]));
+        assert_eq!(association["startBinding"], Value::Null);
+        assert_eq!(association["endBinding"]["elementId"], "use_case_0");
+        assert_eq!(association["endArrowhead"], Value::Null);
+        let include = &elements[11];
+        assert_eq!(include["strokeStyle"], "dashed");
+        assert_eq!(include["points"], serde_json::json!([[0, 0], [60, -40], [0, -80]]));
+        assert_eq!(elements[12]["containerId"], "include_use_case_1_use_case_0");
+        let bound = elements[5]["boundElements"].as_array().unwrap().iter()
+            .map(|bound| bound["id"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(bound, vec!["use_case_0_title", "association_actor_0_use_case_0",
+                               "include_use_case_1_use_case_0"]);
+        assert_eq!(elements[0]["groupIds"], serde_json::json!(["actor_0"]));
+    }
+}
@@ -0,0 +1,549 @@
+//! The formats that diagrams can be both read from and written to, behind a
+//! common interface so that tools can convert between any two of them.
+//!
+//! Formats differ in what they keep. Lossless formats keep everything:
+//! identifiers and the counters that hand them out, metadata, sort keys and
+//! configuration such as unique names, so that parsing what was emitted gives
+//! back the very same diagram. Every other format keeps less, but parsing,
+//! emitting and parsing again gives the same diagram as parsing once, with
+//! the same identifiers in the same order: whatever survives being read
+//! survives every conversion after. The tests of this module check both
+//! guarantees for every format.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::str::{self, Utf8Error};
+
+use model::migrate::{self, MigrationError};
+#[cfg(feature = "binary")]
+use use_case_diagram::binary::{self, BinaryError};
+use use_case_diagram::json::{INTERCHANGE_FORMAT, JsonError};
+use use_case_diagram::parser::{self, ParseError};
+use use_case_diagram::{formatter, mermaid, plantuml, xmi, yaml, NameError, UseCaseDiagram};
+
+/// A format that diagrams can be both parsed from and emitted in.
+pub trait Format {
+    /// The name of the format, unique among `formats`, e.g. `"mermaid"`.
+    fn name(&self) -> &'static str;
+
+    /// Whether parsing what was emitted gives back the same diagram for every
+    /// diagram, rather than only for diagrams parsed from this format.
+    fn is_lossless(&self) -> bool;
+
+    fn parse(&self, input: &[u8]) -> Result<UseCaseDiagram, FormatError>;
+
+    fn emit(&self, diagram: &UseCaseDiagram) -> Result<Vec<u8>, FormatError>;
+}
+
+/// Every format, lossless ones first.
+pub fn formats() -> Vec<Box<dyn Format>> {
+    vec![
+        Box::new(Saved),
+        #[cfg(feature = "binary")]
+        Box::new(Binary),
+        Box::new(Json),
+        Box::new(Dsl),
+        Box::new(Yaml),
+        Box::new(Mermaid),
+        Box::new(PlantUml),
+        Box::new(Xmi),
+    ]
+}
+
+/// The format with the given name, if any.
+pub fn format_by_name(name: &str) -> Option<Box<dyn Format>> {
+    formats().into_iter().find(|format| format.name() == name)
+}
+
+/// The format of a file, for tools that are not told which it is: by the
+/// extension of its path, or else by its contents. JSON is the interchange
+/// format if its envelope names it, and a saved diagram otherwise. YAML is
+/// recognized by the keys `yaml::to_yaml` writes first; other text that is
+/// recognizably no other format is taken to be in the language of `parser`.
+pub fn detect_format(path: &str, input: &[u8]) -> Box<dyn Format> {
+    let extension = Path::new(path).extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("bfly") => return Box::new(Dsl),
+        Some("yaml") | Some("yml") => return Box::new(Yaml),
+        Some("mmd") | Some("mermaid") => return Box::new(Mermaid),
+        Some("puml") | Some("plantuml") => return Box::new(PlantUml),
+        Some("xmi") => return Box::new(Xmi),
+        _ => (),
+    }
+    #[cfg(feature = "binary")]
+    {
+        if input.starts_with(binary::MAGIC) {
+            return Box::new(Binary);
+        }
+    }
+    let text = str::from_utf8(input).unwrap_or("");
+    let mut lines = text.lines().map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with("%%"));
+    let first = lines.next().unwrap_or("");
+    if first.starts_with('{') {
+        let envelope = ::serde_json::from_str::<::serde_json::Value>(text).ok();
+        let format = envelope.as_ref().and_then(|envelope| envelope.get("format"));
+        if format.and_then(|format| format.as_str()) == Some(INTERCHANGE_FORMAT) {
+            return Box::new(Json);
+        }
+        return Box::new(Saved);
+    }
+    if first.starts_with('<') {
+        return Box::new(Xmi);
+    }
+    if first.starts_with("@startuml") {
+        return Box::new(PlantUml);
+    }
+    if first.starts_with("flowchart") || first.starts_with("graph") {
+        return Box::new(Mermaid);
+    }
+    let is_yaml_key = |line: &str| {
+        ["actors:", "use_cases:", "associations:"].iter().any(|key| line.starts_with(key))
+    };
+    if first == "---" || is_yaml_key(first) {
+        return Box::new(Yaml);
+    }
+    Box::new(Dsl)
+}
+
+/// Saved diagrams, `"saved"`: the serde representation as JSON, migrated
+/// from older format versions when parsed. Lossless.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Saved;
+
+impl Format for Saved {
+    fn name(&self) -> &'static str {
+        "saved"
+    }
+
+    fn is_lossless(&self) -> bool {
+        true
+    }
+
+    fn parse(&self, input: &[u8]) -> Result<UseCaseDiagram, FormatError> {
+        Ok(migrate::load(str::from_utf8(input)?)?)
+    }
+
+    fn emit(&self, diagram: &UseCaseDiagram) -> Result<Vec<u8>, FormatError> {
+        let mut output = ::serde_json::to_vec_pretty(diagram)
+            .map_err(|err| FormatError::Saved(MigrationError::Json(err)))?;
+        output.push(b'\n');
+        Ok(output)
+    }
+}
+
+/// The compact binary serialization, `"binary"`. Lossless.
+#[cfg(feature = "binary")]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Binary;
+
+#[cfg(feature = "binary")]
+impl Format for Binary {
+    fn name(&self) -> &'static str {
+        "binary"
+    }
+
+    fn is_lossless(&self) -> bool {
+        true
+    }
+
+    fn parse(&self, input: &[u8]) -> Result<UseCaseDiagram, FormatError> {
+        Ok(binary::from_binary(input)?)
+    }
+
+    fn emit(&self, diagram: &UseCaseDiagram) -> Result<Vec<u8>, FormatError> {
+        Ok(binary::to_binary(diagram))
+    }
+}
+
+/// The JSON interchange format, `"json"`, for other tools. Names must be
+/// unique, and identifiers are not kept.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Json;
+
+impl Format for Json {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn is_lossless(&self) -> bool {
+        false
+    }
+
+    fn parse(&self, input: &[u8]) -> Result<UseCaseDiagram, FormatError> {
+        Ok(UseCaseDiagram::from_json(str::from_utf8(input)?)?)
+    }
+
+    fn emit(&self, diagram: &UseCaseDiagram) -> Result<Vec<u8>, FormatError> {
+        Ok(diagram.to_json()?.into_bytes())
+    }
+}
+
+/// The language of the `parser` module, `"dsl"`, emitted by `formatter`.
+/// Names must be unique.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Dsl;
+
+impl Format for Dsl {
+    fn name(&self) -> &'static str {
+        "dsl"
+    }
+
+    fn is_lossless(&self) -> bool {
+        false
+    }
+
+    fn parse(&self, input: &[u8]) -> Result<UseCaseDiagram, FormatError> {
+        Ok(parser::parse(str::from_utf8(input)?)?)
+    }
+
+    fn emit(&self, diagram: &UseCaseDiagram) -> Result<Vec<u8>, FormatError> {
+        Ok(formatter::format(diagram)?.into_bytes())
+    }
+}
+
+/// YAML, `"yaml"`. Names must be unique.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Yaml;
+
+impl Format for Yaml {
+    fn name(&self) -> &'static str {
+        "yaml"
+    }
+
+    fn is_lossless(&self) -> bool {
+        false
+    }
+
+    fn parse(&self, input: &[u8]) -> Result<UseCaseDiagram, FormatError> {
+        Ok(yaml::from_yaml(str::from_utf8(input)?)?)
+    }
+
+    fn emit(&self, diagram: &UseCaseDiagram) -> Result<Vec<u8>, FormatError> {
+        Ok(yaml::to_yaml(diagram)?.into_bytes())
+    }
+}
+
+/// Mermaid flowcharts, `"mermaid"`. Names must be unique.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Mermaid;
+
+impl Format for Mermaid {
+    fn name(&self) -> &'static str {
+        "mermaid"
+    }
+
+    fn is_lossless(&self) -> bool {
+        false
+    }
+
+    fn parse(&self, input: &[u8]) -> Result<UseCaseDiagram, FormatError> {
+        Ok(mermaid::from_mermaid(str::from_utf8(input)?)?)
+    }
+
+    fn emit(&self, diagram: &UseCaseDiagram) -> Result<Vec<u8>, FormatError> {
+        diagram.validate_unique_names()?;
+        let mut output = Vec::new();
+        mermaid::to_mermaid(diagram, &mut output)?;
+        Ok(output)
+    }
+}
+
+/// PlantUML, `"plantuml"`. Names must be unique.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PlantUml;
+
+impl Format for PlantUml {
+    fn name(&self) -> &'static str {
+        "plantuml"
+    }
+
+    fn is_lossless(&self) -> bool {
+        false
+    }
+
+    fn parse(&self, input: &[u8]) -> Result<UseCaseDiagram, FormatError> {
+        Ok(plantuml::from_plantuml(str::from_utf8(input)?)?)
+    }
+
+    fn emit(&self, diagram: &UseCaseDiagram) -> Result<Vec<u8>, FormatError> {
+        diagram.validate_unique_names()?;
+        let mut output = Vec::new();
+        plantuml::to_plantuml(diagram, &mut output)?;
+        Ok(output)
+    }
+}
+
+/// XMI, `"xmi"`, in a model named after the format.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Xmi;
+
+impl Format for Xmi {
+    fn name(&self) -> &'static str {
+        "xmi"
+    }
+
+    fn is_lossless(&self) -> bool {
+        false
+    }
+
+    fn parse(&self, input: &[u8]) -> Result<UseCaseDiagram, FormatError> {
+        Ok(xmi::from_xmi(str::from_utf8(input)?)?)
+    }
+
+    fn emit(&self, diagram: &UseCaseDiagram) -> Result<Vec<u8>, FormatError> {
+        let mut output = Vec::new();
+        xmi::to_xmi(diagram, "butterfly", &mut output)?;
+        Ok(output)
+    }
+}
+
+/// An error that occurred while parsing or emitting a diagram.
+#[derive(Debug)]
+pub enum FormatError {
+    /// The input of a textual format is not UTF-8.
+    Utf8(Utf8Error),
+
+    Parse(ParseError),
+
+    /// The diagram cannot be emitted because its names are not unique.
+    Name(NameError),
+
+    Json(JsonError),
+
+    Saved(MigrationError),
+
+    #[cfg(feature = "binary")]
+    Binary(BinaryError),
+
+    Io(io::Error),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FormatError::Utf8(ref err) => write!(f, "invalid UTF-8: {}", err),
+            FormatError::Parse(ref err) => write!(f, "{}", err),
+            FormatError::Name(ref err) => write!(f, "{}", err),
+            FormatError::Json(ref err) => write!(f, "{}", err),
+            FormatError::Saved(ref err) => write!(f, "{}", err),
+            #[cfg(feature = "binary")]
+            FormatError::Binary(ref err) => write!(f, "{}", err),
+            FormatError::Io(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for FormatError {
+    fn description(&self) -> &str {
+        match *self {
+            FormatError::Utf8(_) => "invalid UTF-8",
+            FormatError::Parse(_) => "invalid diagram text",
+            FormatError::Name(_) => "names are not unique",
+            FormatError::Json(_) => "invalid JSON diagram",
+            FormatError::Saved(_) => "invalid saved diagram",
+            #[cfg(feature = "binary")]
+            FormatError::Binary(_) => "invalid binary diagram",
+            FormatError::Io(_) => "I/O error",
+        }
+    }
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            FormatError::Utf8(ref err) => Some(err),
+            FormatError::Parse(ref err) => Some(err),
+            FormatError::Name(ref err) => Some(err),
+            FormatError::Json(ref err) => Some(err),
+            FormatError::Saved(ref err) => Some(err),
+            #[cfg(feature = "binary")]
+            FormatError::Binary(ref err) => Some(err),
+            FormatError::Io(ref err) => Some(err),
+        }
+    }
+}
+
+impl From<Utf8Error> for FormatError {
+    fn from(err: Utf8Error) -> Self {
+        FormatError::Utf8(err)
+    }
+}
+
+impl From<ParseError> for FormatError {
+    fn from(err: ParseError) -> Self {
+        FormatError::Parse(err)
+    }
+}
+
+impl From<NameError> for FormatError {
+    fn from(err: NameError) -> Self {
+        FormatError::Name(err)
+    }
+}
+
+impl From<JsonError> for FormatError {
+    fn from(err: JsonError) -> Self {
+        FormatError::Json(err)
+    }
+}
+
+impl From<MigrationError> for FormatError {
+    fn from(err: MigrationError) -> Self {
+        FormatError::Saved(err)
+    }
+}
+
+#[cfg(feature = "binary")]
+impl From<BinaryError> for FormatError {
+    fn from(err: BinaryError) -> Self {
+        FormatError::Binary(err)
+    }
+}
+
+impl From<io::Error> for FormatError {
+    fn from(err: io::Error) -> Self {
+        FormatError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::{self, Value};
+
+    use use_case_diagram::{Actor, ActorId, ActorKind, Association, Extend, ExtensionPoint,
+                           Multiplicity, Note, NoteAnchor, SystemBoundary, UseCase};
+
+    /// Everything serialization keeps, which is everything but events.
+    fn everything(diagram: &UseCaseDiagram) -> Value {
+        serde_json::to_value(diagram).unwrap()
+    }
+
+    fn diagram() -> UseCaseDiagram {
+        let mut diagram = UseCaseDiagram::with_unique_names();
+        let mut moderator = Actor::new("Moderator".into());
+        moderator.description = Some("Keeps the peace".into());
+        moderator.metadata.insert("owner".to_string(), "trust and safety".to_string());
+        let moderator = diagram.insert_actor(moderator).unwrap();
+        let mut mailer = Actor::new("Mailer".into());
+        mailer.kind = ActorKind::System;
+        mailer.sort_key = Some(-1);
+        let mailer = diagram.insert_actor(mailer).unwrap();
+        let administrator = diagram.insert_actor(Actor::new("Administrator".into())).unwrap();
+        let mut ban = UseCase::new("Ban subscriber".into());
+        ban.tags = vec!["moderation".into()];
+        let ban = diagram.insert_use_case(ban).unwrap();
+        let notify = diagram.insert_use_case(UseCase::new("Notify \"subscriber\"".into())).unwrap();
+        let review = diagram.insert_use_case(UseCase::new("Review report".into())).unwrap();
+        diagram.insert_association_with(moderator, ban, Association{
+            label: Some("bans".into()),
+            actor_multiplicity: Some(Multiplicity{lower: 1, upper: None}),
+            ..Association::default()
+        }).unwrap();
+        diagram.insert_association(mailer, notify).unwrap();
+        diagram.insert_association(moderator, review).unwrap();
+        diagram.insert_include(ban, notify).unwrap();
+        let appeal = diagram.insert_extension_point(ExtensionPoint{
+            use_case: review, name: "appeal".into(),
+        }).unwrap();
+        diagram.insert_extend(Extend{extension: ban, base: review,
+                                     extension_point: Some(appeal)}).unwrap();
+        diagram.insert_actor_generalization(administrator, moderator).unwrap();
+        diagram.insert_system_boundary(SystemBoundary{
+            name: "Forum".into(), use_cases: vec![ban, review].into_iter().collect(),
+        }).unwrap();
+        diagram.insert_note(Note{anchor: NoteAnchor::UseCase(ban), text: "Permanent".into()})
+            .unwrap();
+        diagram
+    }
+
+    /// Diagrams that every format can emit: the one above, an empty one, and
+    /// one with gaps in its identifiers, odd characters in its labels and
+    /// the relationships the one above lacks.
+    fn diagrams() -> Vec<UseCaseDiagram> {
+        let mut odd = UseCaseDiagram::with_unique_names();
+        let _ = odd.insert_actor(Actor::new("Removed".into())).unwrap();
+        let guest = odd.insert_actor(Actor::new("Guest <anonymous> & \"friends\"".into()))
+            .unwrap();
+        let member = odd.insert_actor(Actor::new("Mitglied (ü)".into())).unwrap();
+        odd.remove_actor(ActorId(0));
+        let read = odd.insert_use_case(UseCase::new("Read [post] {draft}".into())).unwrap();
+        let browse = odd.insert_use_case(UseCase::new("Browse; then read".into())).unwrap();
+        odd.insert_association(guest, read).unwrap();
+        odd.insert_association(member, browse).unwrap();
+        odd.insert_use_case_generalization(browse, read).unwrap();
+        odd.insert_actor_generalization(member, guest).unwrap();
+        odd.insert_dependency(browse, read, Some("needs".into())).unwrap();
+        odd.insert_note(Note{anchor: NoteAnchor::Association(guest, read),
+                             text: "Rate \"limited\"".into()})
+            .unwrap();
+        vec![diagram(), UseCaseDiagram::with_unique_names(), odd]
+    }
+
+    #[test]
+    fn test_detect_format() {
+        let diagram = diagram();
+        for format in formats() {
+            let emitted = format.emit(&diagram).unwrap();
+            assert_eq!(detect_format("diagram", &emitted).name(), format.name());
+        }
+        assert_eq!(detect_format("model/main.bfly", b"").name(), "dsl");
+        assert_eq!(detect_format("diagram.YML", b"{}").name(), "yaml");
+        assert_eq!(detect_format("diagram.json", b"{\"format_version\": 1}").name(), "saved");
+        assert_eq!(detect_format("diagram", b"# Forum\n\nuse_cases: []\n").name(), "yaml");
+        assert_eq!(detect_format("diagram", b"# Forum\nactor Administrator\n").name(), "dsl");
+    }
+
+    #[test]
+    fn test_lossless_round_trip() {
+        let diagram = diagram();
+        for format in formats().iter().filter(|format| format.is_lossless()) {
+            let emitted = format.emit(&diagram).unwrap();
+            let parsed = format.parse(&emitted).unwrap();
+            assert_eq!(everything(&parsed), everything(&diagram), "{}", format.name());
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for (i, diagram) in diagrams().iter().enumerate() {
+            for format in formats() {
+                let parsed = format.parse(&format.emit(diagram).unwrap()).unwrap();
+                let emitted = format.emit(&parsed).unwrap();
+                let reparsed = format.parse(&emitted).unwrap();
+                assert_eq!(everything(&reparsed), everything(&parsed),
+                           "{} {}", format.name(), i);
+                assert_eq!(format.emit(&reparsed).unwrap(), emitted, "{} {}", format.name(), i);
+            }
+        }
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let mut diagram = UseCaseDiagram::new();
+        let _ = diagram.insert_actor(Actor::new("Moderator".into())).unwrap();
+        let _ = diagram.insert_actor(Actor::new("Moderator".into())).unwrap();
+        for name in &["json", "dsl", "yaml", "mermaid", "plantuml"] {
+            assert!(format_by_name(name).unwrap().emit(&diagram).is_err(), "{}", name);
+        }
+        match Mermaid.emit(&diagram) {
+            Err(FormatError::Name(NameError::DuplicateActorName(..))) => (),
+            result => panic!("{:?}", result.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_format_by_name() {
+        let names = formats().iter().map(|format| format.name()).collect::<Vec<_>>();
+        for name in names {
+            assert_eq!(format_by_name(name).unwrap().name(), name);
+        }
+        assert!(format_by_name("docx").is_none());
+        let err = Json.parse(b"\xff").unwrap_err();
+        assert_eq!(err.to_string(), "invalid UTF-8: invalid utf-8 sequence of 1 bytes from \
+                                     index 0");
+    }
+}
@@ -0,0 +1,166 @@
+//! Canonical text in the language of the `parser` module, so that diagrams
+//! stored as text have clean diffs.
+
+use std::fmt::Write;
+
+use use_case_diagram::parser::{self, ParseError, Position, Statement};
+use use_case_diagram::{NameError, UseCaseDiagram};
+
+/// The text of the diagram's actors, then its use cases, then its
+/// associations, each in the order of `actors_in_order` and
+/// `use_cases_in_order`, separated by blank lines. Everything else about the
+/// diagram, such as descriptions and relationships between use cases, cannot
+/// be written in the language and is left out. Names and titles are quoted
+/// only where they must be. Fails if names or titles are not unique, as the
+/// text would not parse.
+pub fn format<S>(diagram: &UseCaseDiagram<S>) -> Result<String, NameError>
+    where S: AsRef<str> {
+    diagram.validate_unique_names()?;
+    let actors = diagram.actors_in_order();
+    let use_cases = diagram.use_cases_in_order();
+    let mut sections = Vec::new();
+    sections.push(actors.iter()
+                  .map(|&(_, actor)| format!("actor {}\n", quote(actor.name.as_ref())))
+                  .collect::<String>());
+    sections.push(use_cases.iter()
+                  .map(|&(_, use_case)| format!("usecase {}\n", quote(use_case.title.as_ref())))
+                  .collect::<String>());
+    let mut associations = String::new();
+    for &(actor_id, actor) in &actors {
+        for &(use_case_id, use_case) in &use_cases {
+            if diagram.association(actor_id, use_case_id).is_some() {
+                writeln!(associations, "{} -> {}", quote(actor.name.as_ref()),
+                         quote(use_case.title.as_ref())).unwrap();
+            }
+        }
+    }
+    sections.push(associations);
+    sections.retain(|section| !section.is_empty());
+    Ok(sections.join("\n"))
+}
+
+/// Normalize text without changing its meaning or the order of its lines.
+/// Statements are written with single spaces between tokens and with names
+/// and titles quoted only where they must be, indentation and trailing
+/// whitespace are removed, runs of blank lines are collapsed into one and
+/// comments are kept. Fails if the text does not parse.
+pub fn fmt(text: &str) -> Result<String, ParseError> {
+    parser::parse::<String>(text)?;
+    let mut output = String::new();
+    let mut blank = false;
+    for (index, line) in text.lines().enumerate() {
+        let (tokens, comment) = parser::tokenize(index + 1, line)?;
+        let end = Position{line: index + 1, column: line.chars().count() + 1};
+        let mut formatted = match parser::parse_statement(&tokens, end)? {
+            None => String::new(),
+            Some(Statement::Actor(_, name)) => format!("actor {}", quote(&name)),
+            Some(Statement::UseCase(_, title)) => format!("usecase {}", quote(&title)),
+            Some(Statement::Association((_, actor), (_, use_case))) =>
+                format!("{} -> {}", quote(&actor), quote(&use_case)),
+            Some(Statement::Include(_, path)) => format!("include {}", string(&path)),
+        };
+        if let Some(comment) = comment {
+            if !formatted.is_empty() {
+                formatted.push(' ');
+            }
+            formatted.push('#');
+            formatted.push_str(comment.trim_end());
+        }
+        if formatted.is_empty() {
+            blank = !output.is_empty();
+            continue;
+        }
+        if blank {
+            output.push('\n');
+            blank = false;
+        }
+        output.push_str(&formatted);
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+fn quote(name: &str) -> String {
+    if !name.is_empty() && name.chars().all(parser::is_word_char) && !parser::is_keyword(name) {
+        return name.to_string();
+    }
+    string(name)
+}
+
+fn string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    use use_case_diagram::{Actor, UseCase};
+
+    #[test]
+    fn test_format() {
+        let mut diagram = UseCaseDiagram::new();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let mut administrator = Actor::new(Rc::from("Site administrator"));
+        administrator.sort_key = Some(0);
+        let a = diagram.insert_actor(administrator).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban \"subscriber\""))).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("usecase"))).unwrap();
+        diagram.insert_association(s, li).unwrap();
+        diagram.insert_association(a, li).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        let text = format(&diagram).unwrap();
+        assert_eq!(text, concat!(
+            "actor \"Site administrator\"\n",
+            "actor Subscriber\n",
+            "\n",
+            "usecase \"Ban \\\"subscriber\\\"\"\n",
+            "usecase \"usecase\"\n",
+            "\n",
+            "\"Site administrator\" -> \"Ban \\\"subscriber\\\"\"\n",
+            "\"Site administrator\" -> \"usecase\"\n",
+            "Subscriber -> \"usecase\"\n",
+        ));
+        let parsed = parser::parse::<Rc<str>>(&text).unwrap();
+        assert_eq!(format(&parsed).unwrap(), text);
+        assert_eq!(fmt(&text).unwrap(), text);
+
+        assert_eq!(format(&UseCaseDiagram::<Rc<str>>::new()).unwrap(), "");
+        diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        assert!(format(&diagram).is_err());
+    }
+
+    #[test]
+    fn test_fmt() {
+        let text = concat!(
+            "\n",
+            "  # Moderation   \n",
+            "actor   \"Administrator\"#admins\n",
+            "\n",
+            "\n",
+            "\t\"Administrator\"->\"Ban subscriber\"\n",
+            "usecase \"Ban subscriber\"\n",
+            "\n",
+        );
+        assert_eq!(fmt(text).unwrap(), concat!(
+            "# Moderation\n",
+            "actor Administrator #admins\n",
+            "\n",
+            "Administrator -> \"Ban subscriber\"\n",
+            "usecase \"Ban subscriber\"\n",
+        ));
+        assert_eq!(fmt("actor A\nB -> A").unwrap_err().position, Position{line: 2, column: 1});
+    }
+}
@@ -0,0 +1,381 @@
+//! Diagrams from Gherkin feature files, so that BDD suites and diagrams stay
+//! in sync.
+//!
+//! ```text
+//! @actor:Administrator
+//! Feature: Moderation
+//!
+//!   Background:
+//!     Given a subscriber who posted spam
+//!
+//!   @role:Site_moderator @slow
+//!   Scenario: Ban subscriber
+//!     When the administrator bans the subscriber
+//!     Then the subscriber cannot post
+//! ```
+//!
+//! Every scenario, example, and scenario outline is a use case in a package
+//! named after its feature and, if it is in a rule, the rule. Its steps are
+//! the main flow, the steps of the backgrounds that apply are
+//! preconditions, and the text between its header and its steps is its
+//! description. Roles are annotated explicitly with `@actor:NAME` or
+//! `@role:NAME` tags, with underscores for spaces, on the feature, rule or
+//! scenario; they are the actors of the use cases they apply to. Other tags
+//! are use case tags, without the `@`. Examples, data tables and doc strings
+//! are skipped, and only English keywords are understood.
+
+use std::error::Error;
+use std::fmt;
+
+use use_case_diagram::parser::{error, Cursor, ParseError, ParseErrorKind, Position};
+use use_case_diagram::{Actor, ActorId, Span, UseCase, UseCaseDiagram};
+
+const STEP_KEYWORDS: [&str; 6] = ["Given ", "When ", "Then ", "And ", "But ", "* "];
+const ROLE_TAGS: [&str; 2] = ["@actor:", "@role:"];
+
+/// An error in one of the files given to `from_gherkin`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GherkinError {
+    pub file: String,
+    pub error: ParseError,
+}
+
+impl fmt::Display for GherkinError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.error)
+    }
+}
+
+impl Error for GherkinError {
+    fn description(&self) -> &str {
+        "invalid feature file"
+    }
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Read feature files, given by name and contents, into one diagram. Use
+/// cases are in the order of the files and of the scenarios in them, and
+/// actors in the order they are first tagged. Elements have spans into the
+/// files. Scenarios may share titles, so names are not unique.
+pub fn from_gherkin<'a, S, I>(files: I) -> Result<UseCaseDiagram<S>, GherkinError>
+    where S: AsRef<str> + for<'b> From<&'b str>, I: IntoIterator<Item = (&'a str, &'a str)> {
+    let mut diagram = UseCaseDiagram::new();
+    for (file, text) in files {
+        read_feature(&mut diagram, file, text)
+            .map_err(|error| GherkinError{file: file.to_string(), error})?;
+    }
+    Ok(diagram)
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Block {
+    Preamble,
+    Feature,
+    Background,
+    Scenario,
+    Examples,
+}
+
+type Tag = (Position, String);
+
+/// A feature or rule, and the tags and background steps that apply to the
+/// scenarios in it.
+#[derive(Default)]
+struct Group {
+    name: String,
+    tags: Vec<Tag>,
+    background: Vec<String>,
+}
+
+struct Scenario {
+    position: Position,
+    title: String,
+    tags: Vec<Tag>,
+    description: Vec<String>,
+    steps: Vec<String>,
+}
+
+fn read_feature<S>(diagram: &mut UseCaseDiagram<S>, file: &str, text: &str)
+                   -> Result<(), ParseError>
+    where S: AsRef<str> + for<'b> From<&'b str> {
+    let mut block = Block::Preamble;
+    let mut feature = Group::default();
+    let mut rule = None::<Group>;
+    let mut scenario = None;
+    let mut tags = Vec::new();
+    let mut doc_string = None;
+    let mut steps_seen = false;
+    for (index, line) in text.lines().enumerate() {
+        let mut cursor = Cursor::new(index + 1, line);
+        cursor.skip_whitespace();
+        let start = cursor.position();
+        let line = cursor.rest().trim_end();
+        if let Some((delimiter, _)) = doc_string {
+            if line.starts_with(delimiter) {
+                doc_string = None;
+            }
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with("\"\"\"") || line.starts_with("```") {
+            doc_string = Some((&line[..3], start));
+            continue;
+        }
+        if line.starts_with('|') {
+            continue;
+        }
+        if line.starts_with('@') {
+            read_tags(&mut cursor, &mut tags)?;
+            continue;
+        }
+
+        if let Some((keyword, name)) = header(line) {
+            finish(diagram, file, &feature, rule.as_ref(), scenario.take());
+            steps_seen = false;
+            let name = name.to_string();
+            match keyword {
+                "Feature" if block == Block::Preamble => {
+                    feature = Group{name, tags: tags.split_off(0), background: Vec::new()};
+                    block = Block::Feature;
+                },
+                "Rule" if block != Block::Preamble => {
+                    rule = Some(Group{name, tags: tags.split_off(0), background: Vec::new()});
+                    block = Block::Feature;
+                },
+                "Background" if block != Block::Preamble => block = Block::Background,
+                "Examples" | "Scenarios" if block == Block::Scenario || block == Block::Examples =>
+                    block = Block::Examples,
+                "Scenario" | "Example" | "Scenario Outline" | "Scenario Template"
+                    if block != Block::Preamble => {
+                    scenario = Some(Scenario{position: start, title: name, tags: tags.split_off(0),
+                                             description: Vec::new(), steps: Vec::new()});
+                    block = Block::Scenario;
+                },
+                _ => return Err(error(start, ParseErrorKind::UnsupportedStatement)),
+            }
+            tags.clear();
+            continue;
+        }
+
+        let is_step = STEP_KEYWORDS.iter().any(|keyword| line.starts_with(keyword));
+        let step = line.trim_start_matches("* ").to_string();
+        match (block, scenario.as_mut()) {
+            (Block::Background, _) if is_step => match rule {
+                Some(ref mut rule) => rule.background.push(step),
+                None => feature.background.push(step),
+            },
+            (Block::Scenario, Some(scenario)) if is_step => scenario.steps.push(step),
+            (Block::Scenario, Some(scenario)) if !steps_seen =>
+                scenario.description.push(line.to_string()),
+            (Block::Feature, _) => (),
+            (Block::Background, _) if !steps_seen => (),
+            _ => return Err(error(start, ParseErrorKind::UnsupportedStatement)),
+        }
+        steps_seen |= is_step;
+    }
+    if let Some((_, start)) = doc_string {
+        return Err(error(start, ParseErrorKind::UnterminatedBlock));
+    }
+    finish(diagram, file, &feature, rule.as_ref(), scenario);
+    Ok(())
+}
+
+/// Read a line of tags, which may end in a comment.
+fn read_tags(cursor: &mut Cursor, tags: &mut Vec<Tag>) -> Result<(), ParseError> {
+    loop {
+        cursor.skip_whitespace();
+        let position = cursor.position();
+        match cursor.peek() {
+            None | Some('#') => return Ok(()),
+            Some('@') => {
+                let rest = cursor.rest();
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                tags.push((position, rest[..end].to_string()));
+                for _ in rest[..end].chars() {
+                    cursor.bump();
+                }
+            },
+            Some(c) => return Err(error(position, ParseErrorKind::UnexpectedCharacter(c))),
+        }
+    }
+}
+
+/// The keyword and name of a header line such as `Scenario: Ban subscriber`.
+fn header(line: &str) -> Option<(&str, &str)> {
+    let colon = line.find(':')?;
+    let keyword = &line[..colon];
+    match keyword {
+        "Feature" | "Rule" | "Background" | "Scenario" | "Example" | "Scenario Outline" |
+        "Scenario Template" | "Examples" | "Scenarios" => Some((keyword, line[colon + 1..].trim())),
+        _ => None,
+    }
+}
+
+/// The background steps that apply to scenarios in the rule, if any, or
+/// otherwise in the feature.
+fn background<'a>(feature: &'a Group, rule: Option<&'a Group>)
+                  -> impl Iterator<Item = &'a String> + 'a {
+    feature.background.iter().chain(rule.into_iter().flat_map(|rule| &rule.background))
+}
+
+/// Insert the scenario, if any, as a use case, with its actors.
+fn finish<S>(diagram: &mut UseCaseDiagram<S>, file: &str, feature: &Group, rule: Option<&Group>,
+             scenario: Option<Scenario>)
+    where S: AsRef<str> + for<'b> From<&'b str> {
+    let scenario = match scenario {
+        Some(scenario) => scenario,
+        None => return,
+    };
+    let span = |position: Position| Span{file: S::from(file), line: position.line,
+                                          column: position.column};
+    let mut use_case = UseCase::new(S::from(&scenario.title));
+    use_case.package = Some(feature).into_iter().chain(rule)
+        .map(|group| S::from(&group.name))
+        .collect();
+    if !scenario.description.is_empty() {
+        use_case.description = Some(S::from(&scenario.description.join("\n")));
+    }
+    use_case.preconditions = background(feature, rule).map(|step| S::from(step)).collect();
+    use_case.main_flow = scenario.steps.iter().map(|step| S::from(step)).collect();
+    use_case.span = Some(span(scenario.position));
+
+    let mut actor_ids = Vec::<ActorId>::new();
+    let all_tags = feature.tags.iter()
+        .chain(rule.into_iter().flat_map(|rule| &rule.tags))
+        .chain(&scenario.tags);
+    for &(position, ref tag) in all_tags {
+        match ROLE_TAGS.iter().find(|&&prefix| tag.starts_with(prefix)) {
+            Some(prefix) => {
+                let name = tag[prefix.len()..].replace('_', " ");
+                let actor_id = match diagram.actor_by_name(&name) {
+                    Some((actor_id, _)) => actor_id,
+                    None => {
+                        let mut actor = Actor::new(S::from(&name));
+                        actor.span = Some(span(position));
+                        diagram.insert_actor(actor).expect("name is not taken")
+                    },
+                };
+                if !actor_ids.contains(&actor_id) {
+                    actor_ids.push(actor_id);
+                }
+            },
+            None => {
+                if !use_case.has_tag(&tag[1..]) {
+                    use_case.tags.push(S::from(&tag[1..]));
+                }
+            },
+        }
+    }
+    let use_case_id = diagram.insert_use_case(use_case).expect("names are not unique");
+    for actor_id in actor_ids {
+        diagram.insert_association(actor_id, use_case_id).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_from_gherkin() {
+        let moderation = concat!(
+            "# Moderation features\n",
+            "@actor:Administrator @moderation\n",
+            "Feature: Moderation\n",
+            "  As an administrator I want to keep the forum clean.\n",
+            "\n",
+            "  Background:\n",
+            "    Given a subscriber who posted spam\n",
+            "\n",
+            "  @role:Site_moderator @slow\n",
+            "  Scenario: Ban subscriber\n",
+            "    Bans are permanent.\n",
+            "    When the administrator bans the subscriber\n",
+            "    Then the subscriber cannot post\n",
+            "      \"\"\"\n",
+            "      Scenario: not a header\n",
+            "      \"\"\"\n",
+            "\n",
+            "  Rule: Appeals\n",
+            "    Background:\n",
+            "      * an appeal\n",
+            "\n",
+            "    Scenario Outline: Review appeal\n",
+            "      When the moderator reviews the appeal as <outcome>\n",
+            "      | not | a step |\n",
+            "\n",
+            "      @slow\n",
+            "      Examples:\n",
+            "        | outcome |\n",
+            "        | upheld  |\n",
+        );
+        let posting = concat!(
+            "Feature: Posting\n",
+            "  @actor:Subscriber @role:Site_moderator\n",
+            "  Scenario: Post comment\n",
+            "    When the subscriber posts a comment\n",
+        );
+        let diagram = from_gherkin::<Rc<str>, _>(vec![("moderation.feature", moderation),
+                                                      ("posting.feature", posting)]).unwrap();
+
+        let actors = diagram.actors_in_order().iter()
+            .map(|&(_, actor)| (actor.name.to_string(), actor.span.as_ref().unwrap().to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(actors, vec![
+            ("Administrator".to_string(), "moderation.feature:2:1".to_string()),
+            ("Site moderator".to_string(), "moderation.feature:9:3".to_string()),
+            ("Subscriber".to_string(), "posting.feature:2:3".to_string()),
+        ]);
+
+        let use_cases = diagram.use_cases_in_order();
+        let titles = use_cases.iter()
+            .map(|&(_, use_case)| use_case.qualified_title())
+            .collect::<Vec<_>>();
+        assert_eq!(titles, vec!["Moderation::Ban subscriber", "Moderation::Appeals::Review appeal",
+                                "Posting::Post comment"]);
+        let (ban_id, ban) = use_cases[0];
+        assert_eq!(ban.description.as_deref(), Some("Bans are permanent."));
+        assert_eq!(ban.preconditions, vec![Rc::from("Given a subscriber who posted spam")]);
+        assert_eq!(ban.main_flow, vec![Rc::from("When the administrator bans the subscriber"),
+                                       Rc::from("Then the subscriber cannot post")]);
+        assert_eq!(ban.tags, vec![Rc::from("moderation"), Rc::from("slow")]);
+        assert_eq!(ban.span.as_ref().unwrap().to_string(), "moderation.feature:10:3");
+        let (review_id, review) = use_cases[1];
+        assert_eq!(review.preconditions, vec![Rc::from("Given a subscriber who posted spam"),
+                                              Rc::from("an appeal")]);
+        assert_eq!(review.main_flow.len(), 1);
+        assert_eq!(review.tags, vec![Rc::from("moderation")]);
+
+        let (administrator, _) = diagram.actor_by_name("Administrator").unwrap();
+        let (moderator, _) = diagram.actor_by_name("Site moderator").unwrap();
+        let (subscriber, _) = diagram.actor_by_name("Subscriber").unwrap();
+        let (post_id, _) = use_cases[2];
+        let mut associations = diagram.associations().collect::<Vec<_>>();
+        associations.sort();
+        assert_eq!(associations, vec![(administrator, ban_id), (administrator, review_id),
+                                      (moderator, ban_id), (moderator, post_id),
+                                      (subscriber, post_id)]);
+    }
+
+    #[test]
+    fn test_from_gherkin_errors() {
+        fn err(text: &str) -> String {
+            from_gherkin::<Rc<str>, _>(vec![("a.feature", text)]).unwrap_err().to_string()
+        }
+        assert_eq!(err("Scenario: Too early\n"),
+                   "a.feature:1:1: unsupported statement");
+        assert_eq!(err("Feature: A\n  Scenario: B\n    Given c\n    d\n"),
+                   "a.feature:4:5: unsupported statement");
+        assert_eq!(err("Feature: A\n  Scenario: B\n    Given c\n    \"\"\"\n    d\n"),
+                   "a.feature:4:5: unterminated block");
+        assert_eq!(err("Feature: A\n  @b c\n"), "a.feature:2:6: unexpected character 'c'");
+        assert_eq!(err("Feature: A\nFeature: B\n"), "a.feature:2:1: unsupported statement");
+    }
+}
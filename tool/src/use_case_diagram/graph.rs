@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use petgraph::Graph;
+
+use use_case_diagram::{ActorId, ExtensionPointId, UseCaseDiagram, UseCaseId};
+
+/// A node of the graph returned by `UseCaseDiagram::to_petgraph`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum GraphNode {
+    Actor(ActorId),
+    UseCase(UseCaseId),
+}
+
+/// An edge of the graph returned by `UseCaseDiagram::to_petgraph`, with the
+/// direction given for each kind.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum GraphEdge {
+    /// From the actor to the use case.
+    Association,
+
+    /// From the including to the included use case.
+    Include,
+
+    /// From the extension to the base use case, at the extension point if
+    /// any.
+    Extend(Option<ExtensionPointId>),
+
+    /// From the dependent use case to the use case it depends on.
+    Dependency,
+
+    /// From the child to the parent actor or use case.
+    Generalization,
+}
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> {
+    /// This diagram as a directed `petgraph` graph, with a node for every
+    /// actor and use case, in order of identifier with actors first, and an
+    /// edge for every relationship. Look up the details of nodes and edges in
+    /// this diagram by their identifiers.
+    pub fn to_petgraph(&self) -> Graph<GraphNode, GraphEdge> {
+        let mut graph = Graph::with_capacity(self.actors.len() + self.use_cases.len(),
+                                             self.associations.len());
+        let actor_nodes =
+            self.actors()
+            .map(|(actor_id, _)| (actor_id, graph.add_node(GraphNode::Actor(actor_id))))
+            .collect::<HashMap<_, _>>();
+        let use_case_nodes =
+            self.use_cases()
+            .map(|(use_case_id, _)| (use_case_id, graph.add_node(GraphNode::UseCase(use_case_id))))
+            .collect::<HashMap<_, _>>();
+
+        for (actor_id, use_case_id) in self.associations() {
+            graph.add_edge(actor_nodes[&actor_id], use_case_nodes[&use_case_id],
+                           GraphEdge::Association);
+        }
+        for (base, included) in self.includes() {
+            graph.add_edge(use_case_nodes[&base], use_case_nodes[&included], GraphEdge::Include);
+        }
+        for extend in self.extends() {
+            graph.add_edge(use_case_nodes[&extend.extension], use_case_nodes[&extend.base],
+                           GraphEdge::Extend(extend.extension_point));
+        }
+        for (dependent, dependency) in self.dependencies() {
+            graph.add_edge(use_case_nodes[&dependent], use_case_nodes[&dependency],
+                           GraphEdge::Dependency);
+        }
+        for (child, parent) in self.actor_generalizations() {
+            graph.add_edge(actor_nodes[&child], actor_nodes[&parent], GraphEdge::Generalization);
+        }
+        for (child, parent) in self.use_case_generalizations() {
+            graph.add_edge(use_case_nodes[&child], use_case_nodes[&parent],
+                           GraphEdge::Generalization);
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use petgraph::algo::connected_components;
+
+    use use_case_diagram::{Actor, UseCase};
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_to_petgraph() {
+        let mut diagram = UseCaseDiagram::new();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        diagram.insert_include(pc, li).unwrap();
+
+        let graph = diagram.to_petgraph();
+        assert_eq!(graph.node_weights().cloned().collect::<Vec<_>>(), vec![
+            GraphNode::Actor(s),
+            GraphNode::Actor(a),
+            GraphNode::UseCase(pc),
+            GraphNode::UseCase(li),
+        ]);
+        assert_eq!(graph.edge_weights().cloned().collect::<Vec<_>>(),
+                   vec![GraphEdge::Association, GraphEdge::Include]);
+        assert_eq!(connected_components(&graph), 2);
+    }
+}
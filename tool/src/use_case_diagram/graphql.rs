@@ -0,0 +1,707 @@
+//! Diagrams from GraphQL schemas, to start from the operations an existing
+//! API offers.
+//!
+//! ```text
+//! type Query @auth(role: "reader") {
+//!   "All comments below an article."
+//!   comments(article: ID!): [Comment!]!
+//! }
+//!
+//! type Mutation {
+//!   banSubscriber(id: ID!): Boolean @auth(roles: [ADMIN, MODERATOR])
+//! }
+//! ```
+//!
+//! Every field of the query, mutation and subscription types is a use case
+//! titled by its name, described by its description, tagged with its kind
+//! of operation, and keyed by its type and name, e.g. `Query.comments`.
+//! The root types are `Query`, `Mutation` and `Subscription` unless a schema
+//! definition names others, and extensions of them count too.
+//!
+//! Roles are read from the `role`, `roles` and `requires` arguments of any
+//! directive, as strings, enum values or lists of them, and are the actors
+//! of the fields they are on. Fields without roles of their own have the
+//! roles of their type. A `@deprecated` field is a deprecated use case. All
+//! other definitions are checked for syntax and skipped.
+
+use use_case_diagram::parser::{error, ParseError, ParseErrorKind, Position};
+use use_case_diagram::source_map::span;
+use use_case_diagram::{Actor, ActorId, SourceMap, UseCase, UseCaseDiagram};
+
+const ROOT_TYPES: [(&str, &str); 3] =
+    [("query", "Query"), ("mutation", "Mutation"), ("subscription", "Subscription")];
+const ROLE_ARGUMENTS: [&str; 3] = ["role", "roles", "requires"];
+const DEFINITION_KEYWORDS: [&str; 9] =
+    ["schema", "scalar", "type", "interface", "union", "enum", "input", "directive", "extend"];
+const PUNCTUATORS: [&str; 14] =
+    ["!", "$", "&", "(", ")", "...", ":", "=", "@", "[", "]", "{", "|", "}"];
+
+/// Read a schema into a diagram. Names need not be unique, as queries and
+/// mutations may share them.
+pub fn from_graphql<S>(text: &str) -> Result<UseCaseDiagram<S>, ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    from_graphql_with_source_map(text, "").map(|(diagram, _)| diagram)
+}
+
+/// Read a schema in the file like `from_graphql`, and map every use case to
+/// the name of its field, and every actor and association to the name of
+/// the directive that first gives the role.
+pub fn from_graphql_with_source_map<S>(text: &str, file: &str)
+                                       -> Result<(UseCaseDiagram<S>, SourceMap<S>), ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    let schema = Parser::new(text)?.schema()?;
+    let root_types = if schema.root_types.is_empty() {
+        ROOT_TYPES.iter().map(|&(operation, name)| (operation.to_string(), name.to_string()))
+            .collect()
+    } else {
+        schema.root_types
+    };
+
+    let mut diagram = UseCaseDiagram::new();
+    let mut source_map = SourceMap::new();
+    for object_type in &schema.object_types {
+        let operation = match root_types.iter().find(|&(_, name)| *name == object_type.name) {
+            Some((operation, _)) => operation,
+            None => continue,
+        };
+        let type_roles = roles(&object_type.directives);
+        for field in &object_type.fields {
+            let mut use_case = UseCase::new(S::from(&field.name));
+            use_case.key = Some(S::from(&format!("{}.{}", object_type.name, field.name)));
+            use_case.description = field.description.as_deref().map(S::from);
+            use_case.tags = vec![S::from(operation)];
+            use_case.deprecated = field.directives.iter()
+                .find(|directive| directive.name == "deprecated")
+                .map(|directive| {
+                    let reason = directive.arguments.iter()
+                        .find(|&(name, _)| name == "reason")
+                        .and_then(|(_, values)| values.first());
+                    S::from(reason.map_or("No longer supported", String::as_str))
+                });
+            let use_case_id = diagram.insert_use_case(use_case).expect("names are not unique");
+            source_map.use_cases.insert(use_case_id, span(file, field.position));
+
+            let mut field_roles = roles(&field.directives);
+            if field_roles.is_empty() {
+                field_roles = type_roles.clone();
+            }
+            for (role, position) in field_roles {
+                let actor_id = actor(&mut diagram, role);
+                diagram.insert_association(actor_id, use_case_id).unwrap();
+                source_map.actors.entry(actor_id).or_insert_with(|| span(file, position));
+                source_map.associations.insert((actor_id, use_case_id), span(file, position));
+            }
+        }
+    }
+    Ok((diagram, source_map))
+}
+
+/// The actor with the name, inserted if there is none.
+fn actor<S>(diagram: &mut UseCaseDiagram<S>, name: &str) -> ActorId
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    match diagram.actor_by_name(name) {
+        Some((actor_id, _)) => actor_id,
+        None => diagram.insert_actor(Actor::new(S::from(name))).expect("name is not taken"),
+    }
+}
+
+/// The roles the directives declare, in order and without duplicates, each
+/// with the position of the directive that first declares it.
+fn roles(directives: &[Directive]) -> Vec<(&str, Position)> {
+    let mut roles: Vec<(&str, Position)> = Vec::new();
+    for directive in directives {
+        for (name, values) in &directive.arguments {
+            if ROLE_ARGUMENTS.contains(&name.as_str()) {
+                for value in values {
+                    if !roles.iter().any(|&(role, _)| role == value) {
+                        roles.push((value.as_str(), directive.position));
+                    }
+                }
+            }
+        }
+    }
+    roles
+}
+
+/// What the importer needs of a schema.
+struct Schema {
+    /// The operations and types of the schema definition, if any.
+    root_types: Vec<(String, String)>,
+
+    /// Object types and their extensions in document order.
+    object_types: Vec<ObjectType>,
+}
+
+struct ObjectType {
+    name: String,
+    directives: Vec<Directive>,
+    fields: Vec<Field>,
+}
+
+struct Field {
+    name: String,
+    position: Position,
+    description: Option<String>,
+    directives: Vec<Directive>,
+}
+
+/// A directive with the strings and enum values of its arguments, and where
+/// its name is. Other values are dropped.
+struct Directive {
+    name: String,
+    position: Position,
+    arguments: Vec<(String, Vec<String>)>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Name(String),
+
+    /// A string or block string, with escape sequences and indentation
+    /// resolved.
+    String(String),
+
+    /// An integer or float, as written.
+    Number(String),
+
+    Punctuator(&'static str),
+}
+
+struct Parser {
+    tokens: Vec<(Position, Token)>,
+    index: usize,
+    end: Position,
+}
+
+impl Parser {
+    fn new(text: &str) -> Result<Self, ParseError> {
+        let mut lexer = Lexer{text, offset: 0, position: Position{line: 1, column: 1}};
+        let mut tokens = Vec::new();
+        while let Some(token) = lexer.next_token()? {
+            tokens.push(token);
+        }
+        Ok(Parser{tokens, index: 0, end: lexer.position})
+    }
+
+    fn schema(&mut self) -> Result<Schema, ParseError> {
+        let mut schema = Schema{root_types: Vec::new(), object_types: Vec::new()};
+        while self.peek().is_some() {
+            self.description();
+            let position = self.position();
+            let mut keyword = self.name()?;
+            if keyword == "extend" {
+                keyword = self.name()?;
+            }
+            match keyword.as_str() {
+                "schema" => {
+                    self.directives()?;
+                    if self.eat("{") {
+                        while !self.eat("}") {
+                            let operation = self.name()?;
+                            self.expect(":")?;
+                            schema.root_types.push((operation, self.name()?));
+                        }
+                    }
+                },
+                "type" | "interface" | "input" => {
+                    let object_type = self.object_type()?;
+                    if keyword == "type" {
+                        schema.object_types.push(object_type);
+                    }
+                },
+                "enum" => {
+                    self.name()?;
+                    self.directives()?;
+                    if self.eat("{") {
+                        while !self.eat("}") {
+                            self.description();
+                            self.name()?;
+                            self.directives()?;
+                        }
+                    }
+                },
+                "union" => {
+                    self.name()?;
+                    self.directives()?;
+                    if self.eat("=") {
+                        self.eat("|");
+                        self.name()?;
+                        while self.eat("|") {
+                            self.name()?;
+                        }
+                    }
+                },
+                "scalar" => {
+                    self.name()?;
+                    self.directives()?;
+                },
+                "directive" => {
+                    self.expect("@")?;
+                    self.name()?;
+                    if self.eat("(") {
+                        self.skip_past(")")?;
+                    }
+                    self.eat_name("repeatable");
+                    if !self.eat_name("on") {
+                        return Err(self.invalid("expected on"));
+                    }
+                    self.eat("|");
+                    self.name()?;
+                    while self.eat("|") {
+                        self.name()?;
+                    }
+                },
+                _ => return Err(error(position, ParseErrorKind::UnsupportedStatement)),
+            }
+        }
+        Ok(schema)
+    }
+
+    /// Parse the rest of an object, interface or input type, after its
+    /// keyword.
+    fn object_type(&mut self) -> Result<ObjectType, ParseError> {
+        let name = self.name()?;
+        if self.eat_name("implements") {
+            self.eat("&");
+            self.name()?;
+            while self.eat("&") || self.at_interface_name() {
+                self.name()?;
+            }
+        }
+        let directives = self.directives()?;
+        let mut fields = Vec::new();
+        if self.eat("{") {
+            while !self.eat("}") {
+                let description = self.description();
+                let position = self.position();
+                let name = self.name()?;
+                if self.eat("(") {
+                    self.skip_past(")")?;
+                }
+                self.expect(":")?;
+                self.type_reference()?;
+                if self.eat("=") {
+                    self.value()?;
+                }
+                let directives = self.directives()?;
+                fields.push(Field{name, position, description, directives});
+            }
+        }
+        Ok(ObjectType{name, directives, fields})
+    }
+
+    /// Whether the next token is an interface in the old syntax without
+    /// ampersands, rather than the start of the next definition.
+    fn at_interface_name(&self) -> bool {
+        match self.peek() {
+            Some(Token::Name(name)) => !DEFINITION_KEYWORDS.contains(&name.as_str()),
+            _ => false,
+        }
+    }
+
+    fn type_reference(&mut self) -> Result<(), ParseError> {
+        if self.eat("[") {
+            self.type_reference()?;
+            self.expect("]")?;
+        } else {
+            self.name()?;
+        }
+        self.eat("!");
+        Ok(())
+    }
+
+    fn directives(&mut self) -> Result<Vec<Directive>, ParseError> {
+        let mut directives = Vec::new();
+        while self.eat("@") {
+            let position = self.position();
+            let name = self.name()?;
+            let mut arguments = Vec::new();
+            if self.eat("(") {
+                while !self.eat(")") {
+                    let argument = self.name()?;
+                    self.expect(":")?;
+                    arguments.push((argument, self.value()?));
+                }
+            }
+            directives.push(Directive{name, position, arguments});
+        }
+        Ok(directives)
+    }
+
+    /// Parse a value and return the strings and enum values in it.
+    fn value(&mut self) -> Result<Vec<String>, ParseError> {
+        let position = self.position();
+        match self.next() {
+            Some(Token::String(value)) => Ok(vec![value]),
+            Some(Token::Name(ref name)) if name == "true" || name == "false" || name == "null" =>
+                Ok(Vec::new()),
+            Some(Token::Name(value)) => Ok(vec![value]),
+            Some(Token::Number(_)) => Ok(Vec::new()),
+            Some(Token::Punctuator("$")) => {
+                self.name()?;
+                Ok(Vec::new())
+            },
+            Some(Token::Punctuator("[")) => {
+                let mut values = Vec::new();
+                while !self.eat("]") {
+                    values.extend(self.value()?);
+                }
+                Ok(values)
+            },
+            Some(Token::Punctuator("{")) => {
+                while !self.eat("}") {
+                    self.name()?;
+                    self.expect(":")?;
+                    self.value()?;
+                }
+                Ok(Vec::new())
+            },
+            _ => Err(error(position, ParseErrorKind::InvalidGraphQl("expected value".to_string()))),
+        }
+    }
+
+    fn description(&mut self) -> Option<String> {
+        match self.peek().cloned() {
+            Some(Token::String(description)) => {
+                self.index += 1;
+                Some(description)
+            },
+            _ => None,
+        }
+    }
+
+    fn name(&mut self) -> Result<String, ParseError> {
+        let position = self.position();
+        match self.next() {
+            Some(Token::Name(name)) => Ok(name),
+            _ => Err(error(position, ParseErrorKind::ExpectedName)),
+        }
+    }
+
+    /// Skip tokens up to and including the punctuator that closes the
+    /// brackets just opened.
+    fn skip_past(&mut self, close: &str) -> Result<(), ParseError> {
+        let mut depth = 0;
+        loop {
+            match self.next() {
+                Some(Token::Punctuator(p)) if p == close && depth == 0 => return Ok(()),
+                Some(Token::Punctuator("(")) | Some(Token::Punctuator("[")) |
+                Some(Token::Punctuator("{")) => depth += 1,
+                Some(Token::Punctuator(")")) | Some(Token::Punctuator("]")) |
+                Some(Token::Punctuator("}")) => depth -= 1,
+                Some(_) => (),
+                None => return Err(self.invalid(&format!("expected {}", close))),
+            }
+        }
+    }
+
+    fn expect(&mut self, punctuator: &str) -> Result<(), ParseError> {
+        if self.eat(punctuator) {
+            Ok(())
+        } else {
+            Err(self.invalid(&format!("expected {}", punctuator)))
+        }
+    }
+
+    fn eat(&mut self, punctuator: &str) -> bool {
+        match self.peek() {
+            Some(&Token::Punctuator(p)) if p == punctuator => {
+                self.index += 1;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn eat_name(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Name(name)) if name == keyword => {
+                self.index += 1;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.index += 1;
+        token
+    }
+
+    /// Where the next token starts, or the end of the text.
+    fn position(&self) -> Position {
+        self.tokens.get(self.index).map_or(self.end, |&(position, _)| position)
+    }
+
+    fn invalid(&self, reason: &str) -> ParseError {
+        error(self.position(), ParseErrorKind::InvalidGraphQl(reason.to_string()))
+    }
+}
+
+/// Splits the text into tokens, skipping whitespace, commas and comments.
+struct Lexer<'a> {
+    text: &'a str,
+    offset: usize,
+    position: Position,
+}
+
+impl<'a> Lexer<'a> {
+    fn next_token(&mut self) -> Result<Option<(Position, Token)>, ParseError> {
+        loop {
+            let c = match self.peek() {
+                Some(c) => c,
+                None => return Ok(None),
+            };
+            if c.is_whitespace() || c == ',' || c == '\u{feff}' {
+                self.bump();
+            } else if c == '#' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.bump();
+                }
+            } else {
+                break;
+            }
+        }
+
+        let position = self.position;
+        let rest = &self.text[self.offset..];
+        let c = rest.chars().next().unwrap();
+        let token = if rest.starts_with("\"\"\"") {
+            Token::String(self.block_string()?)
+        } else if c == '"' {
+            Token::String(self.string()?)
+        } else if c == '_' || c.is_ascii_alphabetic() {
+            Token::Name(self.take_while(|c| c == '_' || c.is_ascii_alphanumeric()).to_string())
+        } else if c == '-' || c.is_ascii_digit() {
+            self.bump();
+            let digits = self.take_while(|c| {
+                c.is_ascii_alphanumeric() || c == '.' || c == '+' || c == '-'
+            });
+            Token::Number(format!("{}{}", c, digits))
+        } else if let Some(&punctuator) = PUNCTUATORS.iter().find(|&&p| rest.starts_with(p)) {
+            for _ in 0..punctuator.len() {
+                self.bump();
+            }
+            Token::Punctuator(punctuator)
+        } else {
+            return Err(error(position, ParseErrorKind::UnexpectedCharacter(c)));
+        };
+        Ok(Some((position, token)))
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        let start = self.position;
+        self.bump();
+        let mut value = String::new();
+        loop {
+            let position = self.position;
+            match self.bump() {
+                None | Some('\n') | Some('\r') =>
+                    return Err(error(start, ParseErrorKind::UnterminatedString)),
+                Some('"') => return Ok(value),
+                Some('\\') => {
+                    let c = match self.bump() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let digits = self.take_while(|c| c.is_ascii_hexdigit());
+                            u32::from_str_radix(digits, 16).ok()
+                                .filter(|_| digits.len() == 4)
+                                .and_then(::std::char::from_u32)
+                                .ok_or_else(|| error(position, ParseErrorKind::InvalidEscape('u')))?
+                        },
+                        None => return Err(error(start, ParseErrorKind::UnterminatedString)),
+                        Some(c) => return Err(error(position, ParseErrorKind::InvalidEscape(c))),
+                    };
+                    value.push(c);
+                },
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    /// Read a block string and remove its common indentation and its blank
+    /// first and last lines.
+    fn block_string(&mut self) -> Result<String, ParseError> {
+        let start = self.position;
+        for _ in 0..3 {
+            self.bump();
+        }
+        let mut raw = String::new();
+        loop {
+            let rest = &self.text[self.offset..];
+            if rest.starts_with("\\\"\"\"") {
+                raw.push_str("\"\"\"");
+                for _ in 0..4 {
+                    self.bump();
+                }
+            } else if rest.starts_with("\"\"\"") {
+                for _ in 0..3 {
+                    self.bump();
+                }
+                break;
+            } else {
+                match self.bump() {
+                    Some(c) => raw.push(c),
+                    None => return Err(error(start, ParseErrorKind::UnterminatedString)),
+                }
+            }
+        }
+
+        let lines = raw.lines().collect::<Vec<_>>();
+        let indent = lines.iter().skip(1)
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+            .min()
+            .unwrap_or(0);
+        let mut lines = lines.iter().enumerate()
+            .map(|(index, line)| if index == 0 { *line } else { line.get(indent..).unwrap_or("") })
+            .skip_while(|line| line.trim().is_empty())
+            .collect::<Vec<_>>();
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+        Ok(lines.join("\n"))
+    }
+
+    fn take_while<F>(&mut self, mut predicate: F) -> &'a str where F: FnMut(char) -> bool {
+        let start = self.offset;
+        while self.peek().is_some_and(&mut predicate) {
+            self.bump();
+        }
+        &self.text[start..self.offset]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.offset..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.position = Position{line: self.position.line + 1, column: 1};
+        } else {
+            self.position.column += 1;
+        }
+        Some(c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    use use_case_diagram::Span;
+
+    #[test]
+    fn test_from_graphql() {
+        let diagram = from_graphql::<Rc<str>>(concat!(
+            "# Forum\n",
+            "schema { query: Root, mutation: Change }\n",
+            "\n",
+            "directive @auth(role: String, roles: [Role!]) on OBJECT | FIELD_DEFINITION\n",
+            "enum Role { ADMIN MODERATOR }\n",
+            "union Post = Article | Comment\n",
+            "input Filter { since: Int = 0 }\n",
+            "\n",
+            "type Root @auth(role: \"reader\") {\n",
+            "  \"\"\"\n",
+            "  All comments below an article,\n",
+            "    newest first.\n",
+            "  \"\"\"\n",
+            "  comments(article: ID!, filter: Filter = {since: 1}): [Comment!]!\n",
+            "  health: Boolean @auth(roles: [])\n",
+            "}\n",
+            "\n",
+            "type Query { ignored: Int }\n",
+            "\n",
+            "extend type Change {\n",
+            "  \"Ban a subscriber.\" banSubscriber(id: ID!): Boolean\n",
+            "    @auth(roles: [ADMIN, MODERATOR, ADMIN])\n",
+            "  ban(id: ID!): Boolean @deprecated(reason: \"Use \\\"banSubscriber\\\".\")\n",
+            "}\n",
+        )).unwrap();
+        let titles = diagram.use_cases_in_order().iter()
+            .map(|&(_, use_case)| use_case.title.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(titles, vec!["comments", "health", "banSubscriber", "ban"]);
+        let names = diagram.actors_in_order().iter()
+            .map(|&(_, actor)| actor.name.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["reader", "ADMIN", "MODERATOR"]);
+
+        let (comments, use_case) = diagram.use_case_by_title("comments").unwrap();
+        assert_eq!(use_case.key.as_deref(), Some("Root.comments"));
+        assert_eq!(use_case.description.as_deref(),
+                   Some("All comments below an article,\n  newest first."));
+        assert_eq!(use_case.tags, vec![Rc::from("query")]);
+        let (ban_subscriber, use_case) = diagram.use_case_by_title("banSubscriber").unwrap();
+        assert_eq!(use_case.description.as_deref(), Some("Ban a subscriber."));
+        assert_eq!(use_case.tags, vec![Rc::from("mutation")]);
+        assert_eq!(use_case.deprecated, None);
+        let (_, use_case) = diagram.use_case_by_title("ban").unwrap();
+        assert_eq!(use_case.deprecated.as_deref(), Some("Use \"banSubscriber\"."));
+
+        let (health, _) = diagram.use_case_by_title("health").unwrap();
+        let (reader, _) = diagram.actor_by_name("reader").unwrap();
+        let (admin, _) = diagram.actor_by_name("ADMIN").unwrap();
+        let (moderator, _) = diagram.actor_by_name("MODERATOR").unwrap();
+        let mut associations = diagram.associations().collect::<Vec<_>>();
+        associations.sort();
+        assert_eq!(associations, vec![(reader, comments), (reader, health),
+                                      (admin, ban_subscriber), (moderator, ban_subscriber)]);
+    }
+
+    #[test]
+    fn test_from_graphql_with_source_map() {
+        let (diagram, source_map) = from_graphql_with_source_map::<Rc<str>>(concat!(
+            "type Query @auth(role: \"reader\") {\n",
+            "  comments: [String!]!\n",
+            "  banned: [ID!]! @auth(roles: [ADMIN, reader])\n",
+            "}\n",
+        ), "schema.graphql").unwrap();
+        let (reader, _) = diagram.actor_by_name("reader").unwrap();
+        let (admin, _) = diagram.actor_by_name("ADMIN").unwrap();
+        let (comments, _) = diagram.use_case_by_title("comments").unwrap();
+        let (banned, _) = diagram.use_case_by_title("banned").unwrap();
+        let span = |line, column| Span{file: Rc::from("schema.graphql"), line, column};
+        assert_eq!(source_map.use_cases[&comments], span(2, 3));
+        assert_eq!(source_map.use_cases[&banned], span(3, 3));
+        assert_eq!(source_map.actors[&reader], span(1, 13));
+        assert_eq!(source_map.actors[&admin], span(3, 19));
+        assert_eq!(source_map.associations[&(reader, comments)], span(1, 13));
+        assert_eq!(source_map.associations[&(reader, banned)], span(3, 19));
+    }
+
+    #[test]
+    fn test_from_graphql_errors() {
+        fn err(text: &str) -> (usize, usize, ParseErrorKind) {
+            let err = from_graphql::<Rc<str>>(text).unwrap_err();
+            (err.position.line, err.position.column, err.kind)
+        }
+        assert_eq!(err("query { comments }"), (1, 1, ParseErrorKind::UnsupportedStatement));
+        assert_eq!(err("type Query {\n  comments ID\n}"),
+                   (2, 12, ParseErrorKind::InvalidGraphQl("expected :".to_string())));
+        assert_eq!(err("type Query {\n  comments: ID"),
+                   (2, 15, ParseErrorKind::ExpectedName));
+        assert_eq!(err("type Query { a: ID @auth(role: \"admin) }"),
+                   (1, 32, ParseErrorKind::UnterminatedString));
+        assert_eq!(err("type Query { a: ID @auth(role: \"\\q\") }"),
+                   (1, 33, ParseErrorKind::InvalidEscape('q')));
+        assert_eq!(err("type Query { a: ID; }"), (1, 19, ParseErrorKind::UnexpectedCharacter(';')));
+    }
+}
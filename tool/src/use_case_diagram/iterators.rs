@@ -0,0 +1,366 @@
+use std::collections::{btree_map, btree_set};
+use std::iter::FusedIterator;
+use std::rc::Rc;
+
+use use_case_diagram::{Actor, ActorId, Association, Extend, ExtensionPoint, ExtensionPointId,
+                       Note, NoteId, SystemBoundary, SystemBoundaryId, UseCase, UseCaseDiagram,
+                       UseCaseId};
+
+// The iterators returned by the diagram's accessors are opaque, so that the
+// collections backing the diagram can change without breaking callers. Each
+// wraps an iterator over one of those collections and yields identifiers by
+// value.
+
+/// An iterator over the elements of one kind, with their identifiers.
+macro_rules! element_iterator {
+    ($(#[$attr:meta])* $name:ident, $id:ty, $element:ident) => {
+        $(#[$attr])*
+        #[derive(Debug)]
+        pub struct $name<'a, S = Rc<str>> where S: 'a {
+            pub(super) inner: btree_map::Iter<'a, $id, $element<S>>,
+        }
+
+        impl<'a, S> Clone for $name<'a, S> {
+            fn clone(&self) -> Self {
+                $name{inner: self.inner.clone()}
+            }
+        }
+
+        impl<'a, S> Iterator for $name<'a, S> {
+            type Item = ($id, &'a $element<S>);
+
+            fn next(&mut self) -> Option<Self::Item> {
+                self.inner.next().map(|(&id, element)| (id, element))
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.inner.size_hint()
+            }
+        }
+
+        impl<'a, S> DoubleEndedIterator for $name<'a, S> {
+            fn next_back(&mut self) -> Option<Self::Item> {
+                self.inner.next_back().map(|(&id, element)| (id, element))
+            }
+        }
+
+        impl<'a, S> ExactSizeIterator for $name<'a, S> {}
+
+        impl<'a, S> FusedIterator for $name<'a, S> {}
+    };
+}
+
+/// An iterator over the relationships of one kind, backed by a set.
+macro_rules! relationship_iterator {
+    ($(#[$attr:meta])* $name:ident, $item:ty) => {
+        $(#[$attr])*
+        #[derive(Clone, Debug)]
+        pub struct $name<'a> {
+            pub(super) inner: btree_set::Iter<'a, $item>,
+        }
+
+        impl<'a> Iterator for $name<'a> {
+            type Item = $item;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                self.inner.next().cloned()
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.inner.size_hint()
+            }
+        }
+
+        impl<'a> DoubleEndedIterator for $name<'a> {
+            fn next_back(&mut self) -> Option<Self::Item> {
+                self.inner.next_back().cloned()
+            }
+        }
+
+        impl<'a> ExactSizeIterator for $name<'a> {}
+
+        impl<'a> FusedIterator for $name<'a> {}
+    };
+}
+
+/// An iterator over the relationships of one kind, backed by a map from the
+/// relationship to its details.
+macro_rules! detailed_relationship_iterator {
+    ($(#[$attr:meta])* $name:ident, $item:ty, $details:ty) => {
+        $(#[$attr])*
+        #[derive(Debug)]
+        pub struct $name<'a, S = Rc<str>> where S: 'a {
+            pub(super) inner: btree_map::Keys<'a, $item, $details>,
+        }
+
+        impl<'a, S> Clone for $name<'a, S> {
+            fn clone(&self) -> Self {
+                $name{inner: self.inner.clone()}
+            }
+        }
+
+        impl<'a, S> Iterator for $name<'a, S> {
+            type Item = $item;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                self.inner.next().cloned()
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.inner.size_hint()
+            }
+        }
+
+        impl<'a, S> DoubleEndedIterator for $name<'a, S> {
+            fn next_back(&mut self) -> Option<Self::Item> {
+                self.inner.next_back().cloned()
+            }
+        }
+
+        impl<'a, S> ExactSizeIterator for $name<'a, S> {}
+
+        impl<'a, S> FusedIterator for $name<'a, S> {}
+    };
+}
+
+element_iterator!(
+    /// Iterator of actors.
+    Actors, ActorId, Actor);
+
+element_iterator!(
+    /// Iterator of use cases.
+    UseCases, UseCaseId, UseCase);
+
+element_iterator!(
+    /// Iterator of extension points.
+    ExtensionPoints, ExtensionPointId, ExtensionPoint);
+
+element_iterator!(
+    /// Iterator of system boundaries.
+    SystemBoundaries, SystemBoundaryId, SystemBoundary);
+
+element_iterator!(
+    /// Iterator of notes.
+    Notes, NoteId, Note);
+
+detailed_relationship_iterator!(
+    /// Iterator of associations.
+    Associations, (ActorId, UseCaseId), Association<S>);
+
+detailed_relationship_iterator!(
+    /// Iterator of dependencies.
+    Dependencies, (UseCaseId, UseCaseId), Option<S>);
+
+relationship_iterator!(
+    /// Iterator of includes.
+    Includes, (UseCaseId, UseCaseId));
+
+relationship_iterator!(
+    /// Iterator of extends.
+    Extends, Extend);
+
+relationship_iterator!(
+    /// Iterator of actor generalizations.
+    ActorGeneralizations, (ActorId, ActorId));
+
+relationship_iterator!(
+    /// Iterator of use case generalizations.
+    UseCaseGeneralizations, (UseCaseId, UseCaseId));
+
+/// An actor, use case, or association of a diagram, as visited when
+/// iterating over a borrowed diagram.
+#[derive(Debug)]
+pub enum Element<'a, S = Rc<str>> where S: 'a {
+    Actor(ActorId, &'a Actor<S>),
+    UseCase(UseCaseId, &'a UseCase<S>),
+    Association(ActorId, UseCaseId, &'a Association<S>),
+}
+
+impl<'a, S> Clone for Element<'a, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, S> Copy for Element<'a, S> {}
+
+/// An actor, use case, or association of a diagram, as visited when
+/// iterating over a diagram by value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OwnedElement<S = Rc<str>> {
+    Actor(ActorId, Actor<S>),
+    UseCase(UseCaseId, UseCase<S>),
+    Association(ActorId, UseCaseId, Association<S>),
+}
+
+/// Iterator of the actors, then the use cases, then the associations of a
+/// borrowed diagram.
+#[derive(Debug)]
+pub struct Elements<'a, S = Rc<str>> where S: 'a {
+    actors: btree_map::Iter<'a, ActorId, Actor<S>>,
+    use_cases: btree_map::Iter<'a, UseCaseId, UseCase<S>>,
+    associations: btree_map::Iter<'a, (ActorId, UseCaseId), Association<S>>,
+}
+
+impl<'a, S> Clone for Elements<'a, S> {
+    fn clone(&self) -> Self {
+        Elements{
+            actors: self.actors.clone(),
+            use_cases: self.use_cases.clone(),
+            associations: self.associations.clone(),
+        }
+    }
+}
+
+impl<'a, S> Iterator for Elements<'a, S> {
+    type Item = Element<'a, S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((&actor_id, actor)) = self.actors.next() {
+            return Some(Element::Actor(actor_id, actor));
+        }
+        if let Some((&use_case_id, use_case)) = self.use_cases.next() {
+            return Some(Element::UseCase(use_case_id, use_case));
+        }
+        self.associations.next()
+            .map(|(&(actor_id, use_case_id), association)|
+                 Element::Association(actor_id, use_case_id, association))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a, S> ExactSizeIterator for Elements<'a, S> {
+    fn len(&self) -> usize {
+        self.actors.len() + self.use_cases.len() + self.associations.len()
+    }
+}
+
+impl<'a, S> FusedIterator for Elements<'a, S> {}
+
+/// Iterator of the actors, then the use cases, then the associations of a
+/// diagram taken by value.
+#[derive(Debug)]
+pub struct IntoElements<S = Rc<str>> {
+    actors: btree_map::IntoIter<ActorId, Actor<S>>,
+    use_cases: btree_map::IntoIter<UseCaseId, UseCase<S>>,
+    associations: btree_map::IntoIter<(ActorId, UseCaseId), Association<S>>,
+}
+
+impl<S> Iterator for IntoElements<S> {
+    type Item = OwnedElement<S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((actor_id, actor)) = self.actors.next() {
+            return Some(OwnedElement::Actor(actor_id, actor));
+        }
+        if let Some((use_case_id, use_case)) = self.use_cases.next() {
+            return Some(OwnedElement::UseCase(use_case_id, use_case));
+        }
+        self.associations.next()
+            .map(|((actor_id, use_case_id), association)|
+                 OwnedElement::Association(actor_id, use_case_id, association))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+impl<S> ExactSizeIterator for IntoElements<S> {
+    fn len(&self) -> usize {
+        self.actors.len() + self.use_cases.len() + self.associations.len()
+    }
+}
+
+impl<S> FusedIterator for IntoElements<S> {}
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> {
+    /// All actors, then all use cases, then all associations in this use
+    /// case diagram. Other relationships and notes are not visited.
+    pub fn elements(&self) -> Elements<'_, S> {
+        Elements{
+            actors: self.actors.iter(),
+            use_cases: self.use_cases.iter(),
+            associations: self.associations.iter(),
+        }
+    }
+}
+
+impl<'a, S> IntoIterator for &'a UseCaseDiagram<S> where S: AsRef<str> {
+    type Item = Element<'a, S>;
+    type IntoIter = Elements<'a, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elements()
+    }
+}
+
+/// Consumes the diagram, visiting the same elements as `elements`. Other
+/// relationships and notes are dropped.
+impl<S> IntoIterator for UseCaseDiagram<S> where S: AsRef<str> {
+    type Item = OwnedElement<S>;
+    type IntoIter = IntoElements<S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoElements{
+            actors: self.actors.into_iter(),
+            use_cases: self.use_cases.into_iter(),
+            associations: self.associations.into_iter(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_iterators() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(a, pc).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+
+        let mut actors = diagram.actors();
+        assert_eq!(actors.len(), 2);
+        assert_eq!(actors.next_back().map(|(id, _)| id), Some(s));
+        assert_eq!(actors.clone().len(), 1);
+        assert_eq!(actors.next().map(|(id, _)| id), Some(a));
+        assert!(actors.next().is_none());
+        assert_eq!(diagram.associations().rev().collect::<Vec<_>>(), vec![(s, pc), (a, pc)]);
+    }
+
+    #[test]
+    fn test_into_iterator() {
+        let mut diagram = UseCaseDiagram::new();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+
+        let mut visited = Vec::new();
+        for element in &diagram {
+            visited.push(match element {
+                Element::Actor(actor_id, actor) => format!("{} {}", actor_id, actor.name),
+                Element::UseCase(use_case_id, use_case) =>
+                    format!("{} {}", use_case_id, use_case.title),
+                Element::Association(actor_id, use_case_id, _) =>
+                    format!("{} -- {}", actor_id, use_case_id),
+            });
+        }
+        assert_eq!(visited, vec!["0 Subscriber", "0 Post comment", "0 -- 0"]);
+
+        let elements = diagram.into_iter();
+        assert_eq!(elements.len(), 3);
+        assert_eq!(elements.last(),
+                   Some(OwnedElement::Association(s, pc, Default::default())));
+    }
+}
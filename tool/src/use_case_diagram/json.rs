@@ -0,0 +1,1378 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+use std::fmt;
+
+use serde_json;
+
+use use_case_diagram::{Actor, ActorKind, Association, Extend, ExtensionPoint, Flow,
+                       Multiplicity, NameError, Navigability, Note, NoteAnchor,
+                       PACKAGE_SEPARATOR, Priority, RelationshipError, Status, SystemBoundary,
+                       UseCase, UseCaseDiagram};
+
+/// The `format` of documents in the interchange format.
+pub const INTERCHANGE_FORMAT: &str = "butterfly/use-case-diagram";
+
+/// The `version` of documents in the interchange format written by this
+/// library. It versions the keys of the document, and is independent of
+/// `FORMAT_VERSION`, which versions saved diagrams.
+pub const INTERCHANGE_VERSION: u64 = 1;
+
+/// An error that occurred while converting a diagram to or from JSON.
+#[derive(Debug)]
+pub enum JsonError {
+    /// The JSON could not be produced or parsed.
+    Json(serde_json::Error),
+
+    /// The envelope names a format other than `INTERCHANGE_FORMAT`.
+    UnknownFormat(String),
+
+    /// The envelope has a version other than `INTERCHANGE_VERSION`.
+    UnsupportedVersion(u64),
+
+    /// The diagram has names or titles that cannot serve as keys.
+    Name(NameError),
+
+    /// A relationship refers to an actor name that is not in the document.
+    UnknownActorName(String),
+
+    /// A relationship refers to a use case title that is not in the document.
+    UnknownUseCaseTitle(String),
+
+    /// An extend refers to an extension point name that is not in its base
+    /// use case.
+    UnknownExtensionPointName(String),
+
+    /// An association has a multiplicity not in UML notation.
+    InvalidMultiplicity(String),
+
+    /// A note is attached to neither an actor nor a use case.
+    UnanchoredNote(String),
+
+    /// A relationship between use cases is invalid, e.g. closes a cycle.
+    Relationship(RelationshipError),
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JsonError::Json(ref err) =>
+                write!(f, "invalid JSON: {}", err),
+            JsonError::UnknownFormat(ref format) =>
+                write!(f, "unknown format {:?}", format),
+            JsonError::UnsupportedVersion(version) =>
+                write!(f, "version {} is not {}", version, INTERCHANGE_VERSION),
+            JsonError::Name(ref err) =>
+                write!(f, "{}", err),
+            JsonError::UnknownActorName(ref name) =>
+                write!(f, "invalid relationship: unknown actor {:?}", name),
+            JsonError::UnknownUseCaseTitle(ref title) =>
+                write!(f, "invalid relationship: unknown use case {:?}", title),
+            JsonError::UnknownExtensionPointName(ref name) =>
+                write!(f, "invalid extend: unknown extension point {:?}", name),
+            JsonError::InvalidMultiplicity(ref multiplicity) =>
+                write!(f, "invalid association: multiplicity {:?}", multiplicity),
+            JsonError::UnanchoredNote(ref text) =>
+                write!(f, "invalid note: {:?} is not attached to anything", text),
+            JsonError::Relationship(ref err) =>
+                write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for JsonError {
+    fn description(&self) -> &str {
+        match *self {
+            JsonError::Json(_) =>
+                "invalid JSON",
+            JsonError::UnknownFormat(_) =>
+                "unknown format",
+            JsonError::UnsupportedVersion(_) =>
+                "unsupported version",
+            JsonError::Name(_) =>
+                "duplicate name",
+            JsonError::UnknownActorName(_) =>
+                "invalid relationship: unknown actor",
+            JsonError::UnknownUseCaseTitle(_) =>
+                "invalid relationship: unknown use case",
+            JsonError::UnknownExtensionPointName(_) =>
+                "invalid extend: unknown extension point",
+            JsonError::InvalidMultiplicity(_) =>
+                "invalid association: multiplicity",
+            JsonError::UnanchoredNote(_) =>
+                "invalid note: not attached to anything",
+            JsonError::Relationship(_) =>
+                "invalid relationship",
+        }
+    }
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            JsonError::Json(ref err) => Some(err),
+            JsonError::Name(ref err) => Some(err),
+            JsonError::Relationship(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for JsonError {
+    fn from(err: serde_json::Error) -> Self {
+        JsonError::Json(err)
+    }
+}
+
+impl From<NameError> for JsonError {
+    fn from(err: NameError) -> Self {
+        JsonError::Name(err)
+    }
+}
+
+impl From<RelationshipError> for JsonError {
+    fn from(err: RelationshipError) -> Self {
+        JsonError::Relationship(err)
+    }
+}
+
+// The keys below are the on-disk format and must not change with the Rust
+// field names:
+//
+// - `actors`: object mapping each actor name to an actor object, which has
+//   a stable `key` string, a `kind` of `"secondary"`, `"system"`, or
+//   `"time"` (omitted for primary actors), a `description` string, an array
+//   of `stereotypes`, a string-to-string `metadata` object, an `icon` name
+//   and a `color` for renderers, a `localized_names` object mapping locales
+//   to names, an array of `layers`, and an integer `sort_key`, each omitted
+//   when empty.
+// - `use_cases`: object mapping each use case title to a use case object,
+//   which has a stable `key` string, a `package` path such as
+//   `"Billing::Invoices"`, `abstract` set to `true` if it is abstract, a
+//   `description` string, arrays of `preconditions`, `postconditions`,
+//   `main_flow` steps, `alternative_flows` objects with a `name` and an
+//   array of `steps`, `tags`, `stereotypes`, and `extension_points` names, a
+//   `priority` of `"low"`, `"medium"`, or `"high"`, a `status` of `"draft"`,
+//   `"approved"`, or `"implemented"`, a `deprecated` reason, a `metadata`
+//   object, a `localized_titles` object mapping locales to titles, an array
+//   of `layers`, and an integer `sort_key`, each omitted when empty.
+// - `associations`: array of objects with an `actor` name and a `use_case`
+//   title, sorted by actor and then use case. Optionally also a `label`, an
+//   `actor_multiplicity` and a `use_case_multiplicity` in UML notation such
+//   as `"0..*"`, a `navigability` of `"to_use_case"` or `"to_actor"`, a
+//   numeric `weight`, a `metadata` object, and an array of `layers`.
+// - `includes`: array of objects with a `base` and an `included` use case
+//   title, sorted by base and then included use case. Omitted when empty.
+// - `dependencies`: array of objects with a `dependent` use case title, the
+//   title of the use case it depends on as `dependency`, and optionally a
+//   `label`, sorted. Omitted when empty.
+// - `actor_generalizations`: array of objects with a `child` and a `parent`
+//   actor name, sorted. Omitted when empty.
+// - `use_case_generalizations`: array of objects with a `child` and a
+//   `parent` use case title, sorted. Omitted when empty.
+// - `extends`: array of objects with an `extension` and a `base` use case
+//   title and optionally the name of an `extension_point` of the base,
+//   sorted. Omitted when empty.
+// - `system_boundaries`: object mapping each system boundary name to an
+//   object with an array of contained `use_cases` titles. Omitted when
+//   empty.
+// - `notes`: array of objects with the `text` of the note and the `actor`
+//   name or `use_case` title it is attached to; a note on an association has
+//   both. Sorted, with identical notes repeated. Omitted when empty.
+//
+// The interchange format wraps such a document in an envelope object with
+// the `format` string `"butterfly/use-case-diagram"`, the integer `version`
+// of the document, and the document itself as `diagram`. The version goes up
+// whenever a key is added that readers must understand or a key changes
+// meaning, so that older readers reject documents they would misread.
+
+#[derive(Serialize)]
+struct EnvelopeRef<'a> {
+    #[serde(rename = "format")]
+    format: &'a str,
+
+    #[serde(rename = "version")]
+    version: u64,
+
+    #[serde(rename = "diagram")]
+    diagram: &'a ByNameDocument,
+}
+
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(rename = "format")]
+    format: String,
+
+    #[serde(rename = "version")]
+    version: u64,
+
+    #[serde(rename = "diagram")]
+    diagram: serde_json::Value,
+}
+
+#[derive(Deserialize, Serialize)]
+struct ByNameDocument {
+    #[serde(rename = "actors")]
+    actors: BTreeMap<String, ByNameActor>,
+
+    #[serde(rename = "use_cases")]
+    use_cases: BTreeMap<String, ByNameUseCase>,
+
+    #[serde(rename = "associations")]
+    associations: BTreeSet<ByNameAssociation>,
+
+    #[serde(rename = "includes", default, skip_serializing_if = "BTreeSet::is_empty")]
+    includes: BTreeSet<ByNameInclude>,
+
+    #[serde(rename = "dependencies", default, skip_serializing_if = "BTreeSet::is_empty")]
+    dependencies: BTreeSet<ByNameDependency>,
+
+    #[serde(rename = "extends", default, skip_serializing_if = "BTreeSet::is_empty")]
+    extends: BTreeSet<ByNameExtend>,
+
+    #[serde(rename = "actor_generalizations", default,
+            skip_serializing_if = "BTreeSet::is_empty")]
+    actor_generalizations: BTreeSet<ByNameGeneralization>,
+
+    #[serde(rename = "use_case_generalizations", default,
+            skip_serializing_if = "BTreeSet::is_empty")]
+    use_case_generalizations: BTreeSet<ByNameGeneralization>,
+
+    #[serde(rename = "system_boundaries", default, skip_serializing_if = "BTreeMap::is_empty")]
+    system_boundaries: BTreeMap<String, ByNameSystemBoundary>,
+
+    #[serde(rename = "notes", default, skip_serializing_if = "Vec::is_empty")]
+    notes: Vec<ByNameNote>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct ByNameActor {
+    #[serde(rename = "key", default, skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+
+    #[serde(rename = "kind", default, skip_serializing_if = "Option::is_none")]
+    kind: Option<ByNameActorKind>,
+
+    #[serde(rename = "description", default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+
+    #[serde(rename = "stereotypes", default, skip_serializing_if = "Vec::is_empty")]
+    stereotypes: Vec<String>,
+
+    #[serde(rename = "metadata", default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
+
+    #[serde(rename = "icon", default, skip_serializing_if = "Option::is_none")]
+    icon: Option<String>,
+
+    #[serde(rename = "color", default, skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+
+    #[serde(rename = "localized_names", default, skip_serializing_if = "BTreeMap::is_empty")]
+    localized_names: BTreeMap<String, String>,
+
+    #[serde(rename = "layers", default, skip_serializing_if = "Vec::is_empty")]
+    layers: Vec<String>,
+
+    #[serde(rename = "sort_key", default, skip_serializing_if = "Option::is_none")]
+    sort_key: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct ByNameUseCase {
+    #[serde(rename = "key", default, skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+
+    #[serde(rename = "package", default, skip_serializing_if = "Option::is_none")]
+    package: Option<String>,
+
+    #[serde(rename = "abstract", default, skip_serializing_if = "is_false")]
+    is_abstract: bool,
+
+    #[serde(rename = "description", default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+
+    #[serde(rename = "preconditions", default, skip_serializing_if = "Vec::is_empty")]
+    preconditions: Vec<String>,
+
+    #[serde(rename = "postconditions", default, skip_serializing_if = "Vec::is_empty")]
+    postconditions: Vec<String>,
+
+    #[serde(rename = "main_flow", default, skip_serializing_if = "Vec::is_empty")]
+    main_flow: Vec<String>,
+
+    #[serde(rename = "alternative_flows", default, skip_serializing_if = "Vec::is_empty")]
+    alternative_flows: Vec<ByNameFlow>,
+
+    #[serde(rename = "priority", default, skip_serializing_if = "Option::is_none")]
+    priority: Option<ByNamePriority>,
+
+    #[serde(rename = "status", default, skip_serializing_if = "Option::is_none")]
+    status: Option<ByNameStatus>,
+
+    #[serde(rename = "deprecated", default, skip_serializing_if = "Option::is_none")]
+    deprecated: Option<String>,
+
+    #[serde(rename = "tags", default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+
+    #[serde(rename = "stereotypes", default, skip_serializing_if = "Vec::is_empty")]
+    stereotypes: Vec<String>,
+
+    #[serde(rename = "metadata", default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
+
+    #[serde(rename = "extension_points", default,
+            skip_serializing_if = "BTreeSet::is_empty")]
+    extension_points: BTreeSet<String>,
+
+    #[serde(rename = "localized_titles", default,
+            skip_serializing_if = "BTreeMap::is_empty")]
+    localized_titles: BTreeMap<String, String>,
+
+    #[serde(rename = "layers", default, skip_serializing_if = "Vec::is_empty")]
+    layers: Vec<String>,
+
+    #[serde(rename = "sort_key", default, skip_serializing_if = "Option::is_none")]
+    sort_key: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct ByNameFlow {
+    #[serde(rename = "name")]
+    name: String,
+
+    #[serde(rename = "steps")]
+    steps: Vec<String>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+enum ByNameActorKind {
+    #[serde(rename = "primary")]
+    Primary,
+
+    #[serde(rename = "secondary")]
+    Secondary,
+
+    #[serde(rename = "system")]
+    System,
+
+    #[serde(rename = "time")]
+    Time,
+}
+
+impl From<ActorKind> for ByNameActorKind {
+    fn from(kind: ActorKind) -> Self {
+        match kind {
+            ActorKind::Primary => ByNameActorKind::Primary,
+            ActorKind::Secondary => ByNameActorKind::Secondary,
+            ActorKind::System => ByNameActorKind::System,
+            ActorKind::Time => ByNameActorKind::Time,
+        }
+    }
+}
+
+impl From<ByNameActorKind> for ActorKind {
+    fn from(kind: ByNameActorKind) -> Self {
+        match kind {
+            ByNameActorKind::Primary => ActorKind::Primary,
+            ByNameActorKind::Secondary => ActorKind::Secondary,
+            ByNameActorKind::System => ActorKind::System,
+            ByNameActorKind::Time => ActorKind::Time,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+enum ByNamePriority {
+    #[serde(rename = "low")]
+    Low,
+
+    #[serde(rename = "medium")]
+    Medium,
+
+    #[serde(rename = "high")]
+    High,
+}
+
+impl From<Priority> for ByNamePriority {
+    fn from(priority: Priority) -> Self {
+        match priority {
+            Priority::Low => ByNamePriority::Low,
+            Priority::Medium => ByNamePriority::Medium,
+            Priority::High => ByNamePriority::High,
+        }
+    }
+}
+
+impl From<ByNamePriority> for Priority {
+    fn from(priority: ByNamePriority) -> Self {
+        match priority {
+            ByNamePriority::Low => Priority::Low,
+            ByNamePriority::Medium => Priority::Medium,
+            ByNamePriority::High => Priority::High,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+enum ByNameStatus {
+    #[serde(rename = "draft")]
+    Draft,
+
+    #[serde(rename = "approved")]
+    Approved,
+
+    #[serde(rename = "implemented")]
+    Implemented,
+}
+
+impl From<Status> for ByNameStatus {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Draft => ByNameStatus::Draft,
+            Status::Approved => ByNameStatus::Approved,
+            Status::Implemented => ByNameStatus::Implemented,
+        }
+    }
+}
+
+impl From<ByNameStatus> for Status {
+    fn from(status: ByNameStatus) -> Self {
+        match status {
+            ByNameStatus::Draft => Status::Draft,
+            ByNameStatus::Approved => Status::Approved,
+            ByNameStatus::Implemented => Status::Implemented,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct ByNameSystemBoundary {
+    #[serde(rename = "use_cases")]
+    use_cases: BTreeSet<String>,
+}
+
+#[derive(Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+struct ByNameAssociation {
+    #[serde(rename = "actor")]
+    actor: String,
+
+    #[serde(rename = "use_case")]
+    use_case: String,
+
+    #[serde(rename = "label", default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+
+    #[serde(rename = "actor_multiplicity", default, skip_serializing_if = "Option::is_none")]
+    actor_multiplicity: Option<String>,
+
+    #[serde(rename = "use_case_multiplicity", default,
+            skip_serializing_if = "Option::is_none")]
+    use_case_multiplicity: Option<String>,
+
+    #[serde(rename = "navigability", default, skip_serializing_if = "Option::is_none")]
+    navigability: Option<ByNameNavigability>,
+
+    #[serde(rename = "weight", default, skip_serializing_if = "Option::is_none")]
+    weight: Option<u64>,
+
+    #[serde(rename = "metadata", default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
+
+    #[serde(rename = "layers", default, skip_serializing_if = "Vec::is_empty")]
+    layers: Vec<String>,
+}
+
+#[derive(Clone, Copy, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+enum ByNameNavigability {
+    #[serde(rename = "to_use_case")]
+    ToUseCase,
+
+    #[serde(rename = "to_actor")]
+    ToActor,
+}
+
+#[derive(Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+struct ByNameInclude {
+    #[serde(rename = "base")]
+    base: String,
+
+    #[serde(rename = "included")]
+    included: String,
+}
+
+#[derive(Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+struct ByNameDependency {
+    #[serde(rename = "dependent")]
+    dependent: String,
+
+    #[serde(rename = "dependency")]
+    dependency: String,
+
+    #[serde(rename = "label", default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+}
+
+#[derive(Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+struct ByNameGeneralization {
+    #[serde(rename = "child")]
+    child: String,
+
+    #[serde(rename = "parent")]
+    parent: String,
+}
+
+#[derive(Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+struct ByNameNote {
+    #[serde(rename = "actor", default, skip_serializing_if = "Option::is_none")]
+    actor: Option<String>,
+
+    #[serde(rename = "use_case", default, skip_serializing_if = "Option::is_none")]
+    use_case: Option<String>,
+
+    #[serde(rename = "text")]
+    text: String,
+}
+
+#[derive(Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+struct ByNameExtend {
+    #[serde(rename = "extension")]
+    extension: String,
+
+    #[serde(rename = "base")]
+    base: String,
+
+    #[serde(rename = "extension_point", default, skip_serializing_if = "Option::is_none")]
+    extension_point: Option<String>,
+}
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> {
+    /// Export this diagram as JSON in which actors and use cases are keyed by
+    /// name and title rather than by identifier. The output does not depend
+    /// on the order in which elements were inserted, so documents edited on
+    /// different branches merge cleanly. Return an error if names or titles
+    /// are not unique.
+    pub fn to_json_by_name(&self) -> Result<String, JsonError> {
+        Ok(serde_json::to_string_pretty(&self.by_name_document()?)?)
+    }
+
+    /// Export this diagram in the interchange format, for exchanging diagrams
+    /// with other tools: the document of `to_json_by_name` in an envelope
+    /// with the format and its version. Like that document, it needs unique
+    /// names and does not keep identifiers, so it is not lossless; diagrams
+    /// are saved in the serde representation of the `format::Saved` format.
+    pub fn to_json(&self) -> Result<String, JsonError> {
+        let envelope = EnvelopeRef{
+            format: INTERCHANGE_FORMAT,
+            version: INTERCHANGE_VERSION,
+            diagram: &self.by_name_document()?,
+        };
+        Ok(serde_json::to_string_pretty(&envelope)?)
+    }
+
+    fn by_name_document(&self) -> Result<ByNameDocument, JsonError> {
+        self.validate_unique_names()?;
+        let mut document = ByNameDocument{
+            actors:
+                self.actors()
+                .map(|(_, actor)| {
+                    let stereotypes = to_strings(&actor.stereotypes);
+                    let actor_dto = ByNameActor{
+                        key: actor.key.as_ref().map(|k| k.as_ref().to_string()),
+                        kind:
+                            if actor.kind == ActorKind::Primary { None }
+                            else { Some(ByNameActorKind::from(actor.kind)) },
+                        description: actor.description.as_ref().map(|d| d.as_ref().to_string()),
+                        stereotypes,
+                        metadata: actor.metadata.clone(),
+                        icon: actor.icon.as_ref().map(|i| i.as_ref().to_string()),
+                        color: actor.color.as_ref().map(|c| c.as_ref().to_string()),
+                        localized_names: to_localized_strings(&actor.localized_names),
+                        layers: to_strings(&actor.layers),
+                        sort_key: actor.sort_key,
+                    };
+                    (actor.name.as_ref().to_string(), actor_dto)
+                })
+                .collect(),
+            use_cases:
+                self.use_cases()
+                .map(|(use_case_id, use_case)| {
+                    let extension_points =
+                        self.extension_points_of_use_case(use_case_id)
+                        .map(|(_, extension_point)| extension_point.name.as_ref().to_string())
+                        .collect();
+                    let stereotypes = to_strings(&use_case.stereotypes);
+                    let use_case_dto = ByNameUseCase{
+                        key: use_case.key.as_ref().map(|k| k.as_ref().to_string()),
+                        package:
+                            if use_case.package.is_empty() { None }
+                            else { Some(to_strings(&use_case.package).join(PACKAGE_SEPARATOR)) },
+                        is_abstract: use_case.is_abstract,
+                        description:
+                            use_case.description.as_ref().map(|d| d.as_ref().to_string()),
+                        preconditions: to_strings(&use_case.preconditions),
+                        postconditions: to_strings(&use_case.postconditions),
+                        main_flow: to_strings(&use_case.main_flow),
+                        alternative_flows:
+                            use_case.alternative_flows.iter()
+                            .map(|flow| ByNameFlow{name: flow.name.as_ref().to_string(),
+                                                   steps: to_strings(&flow.steps)})
+                            .collect(),
+                        priority: use_case.priority.map(ByNamePriority::from),
+                        status: use_case.status.map(ByNameStatus::from),
+                        deprecated:
+                            use_case.deprecated.as_ref().map(|d| d.as_ref().to_string()),
+                        tags: to_strings(&use_case.tags),
+                        stereotypes,
+                        metadata: use_case.metadata.clone(),
+                        extension_points,
+                        localized_titles: to_localized_strings(&use_case.localized_titles),
+                        layers: to_strings(&use_case.layers),
+                        sort_key: use_case.sort_key,
+                    };
+                    (use_case.title.as_ref().to_string(), use_case_dto)
+                })
+                .collect(),
+            associations:
+                self.associations()
+                .map(|(actor_id, use_case_id)| {
+                    let association = &self.associations[&(actor_id, use_case_id)];
+                    ByNameAssociation{
+                        actor: self.actors[&actor_id].name.as_ref().to_string(),
+                        use_case: self.use_cases[&use_case_id].title.as_ref().to_string(),
+                        label: association.label.as_ref().map(|label| label.as_ref().to_string()),
+                        actor_multiplicity:
+                            association.actor_multiplicity.map(|m| m.to_string()),
+                        use_case_multiplicity:
+                            association.use_case_multiplicity.map(|m| m.to_string()),
+                        navigability: match association.navigability {
+                            Navigability::Undirected => None,
+                            Navigability::ToUseCase => Some(ByNameNavigability::ToUseCase),
+                            Navigability::ToActor => Some(ByNameNavigability::ToActor),
+                        },
+                        weight: association.weight,
+                        metadata: association.metadata.clone(),
+                        layers: to_strings(&association.layers),
+                    }
+                })
+                .collect(),
+            includes:
+                self.includes()
+                .map(|(base, included)| ByNameInclude{
+                    base: self.use_cases[&base].title.as_ref().to_string(),
+                    included: self.use_cases[&included].title.as_ref().to_string(),
+                })
+                .collect(),
+            dependencies:
+                self.dependencies()
+                .map(|(dependent, dependency)| ByNameDependency{
+                    dependent: self.use_cases[&dependent].title.as_ref().to_string(),
+                    dependency: self.use_cases[&dependency].title.as_ref().to_string(),
+                    label:
+                        self.dependency_label(dependent, dependency)
+                        .map(|label| label.as_ref().to_string()),
+                })
+                .collect(),
+            extends:
+                self.extends()
+                .map(|extend| ByNameExtend{
+                    extension: self.use_cases[&extend.extension].title.as_ref().to_string(),
+                    base: self.use_cases[&extend.base].title.as_ref().to_string(),
+                    extension_point:
+                        extend.extension_point
+                        .map(|id| self.extension_points[&id].name.as_ref().to_string()),
+                })
+                .collect(),
+            actor_generalizations:
+                self.actor_generalizations()
+                .map(|(child, parent)| ByNameGeneralization{
+                    child: self.actors[&child].name.as_ref().to_string(),
+                    parent: self.actors[&parent].name.as_ref().to_string(),
+                })
+                .collect(),
+            use_case_generalizations:
+                self.use_case_generalizations()
+                .map(|(child, parent)| ByNameGeneralization{
+                    child: self.use_cases[&child].title.as_ref().to_string(),
+                    parent: self.use_cases[&parent].title.as_ref().to_string(),
+                })
+                .collect(),
+            system_boundaries:
+                self.system_boundaries()
+                .map(|(_, system_boundary)| {
+                    let use_cases =
+                        system_boundary.use_cases.iter()
+                        .map(|use_case_id| self.use_cases[use_case_id].title.as_ref().to_string())
+                        .collect();
+                    (system_boundary.name.as_ref().to_string(), ByNameSystemBoundary{use_cases})
+                })
+                .collect(),
+            notes:
+                self.notes()
+                .map(|(_, note)| {
+                    let actor_name = |actor_id| self.actors[&actor_id].name.as_ref().to_string();
+                    let use_case_title =
+                        |use_case_id| self.use_cases[&use_case_id].title.as_ref().to_string();
+                    let (actor, use_case) = match note.anchor {
+                        NoteAnchor::Actor(actor_id) => (Some(actor_name(actor_id)), None),
+                        NoteAnchor::UseCase(use_case_id) =>
+                            (None, Some(use_case_title(use_case_id))),
+                        NoteAnchor::Association(actor_id, use_case_id) =>
+                            (Some(actor_name(actor_id)), Some(use_case_title(use_case_id))),
+                    };
+                    ByNameNote{actor, use_case, text: note.text.as_ref().to_string()}
+                })
+                .collect(),
+        };
+        document.notes.sort();
+        Ok(document)
+    }
+
+    /// Import a diagram exported by `to_json_by_name`. Identifiers are
+    /// assigned in order of name and title, so equal documents yield equal
+    /// diagrams.
+    pub fn from_json_by_name(json: &str) -> Result<Self, JsonError>
+        where S: for<'a> From<&'a str> {
+        Self::from_by_name_document(serde_json::from_str(json)?)
+    }
+
+    /// Import a diagram exported by `to_json`. The envelope is checked
+    /// before the document, so documents of other formats or versions are
+    /// rejected as such rather than as malformed.
+    pub fn from_json(json: &str) -> Result<Self, JsonError>
+        where S: for<'a> From<&'a str> {
+        let envelope: Envelope = serde_json::from_str(json)?;
+        if envelope.format != INTERCHANGE_FORMAT {
+            return Err(JsonError::UnknownFormat(envelope.format));
+        }
+        if envelope.version != INTERCHANGE_VERSION {
+            return Err(JsonError::UnsupportedVersion(envelope.version));
+        }
+        Self::from_by_name_document(serde_json::from_value(envelope.diagram)?)
+    }
+
+    fn from_by_name_document(document: ByNameDocument) -> Result<Self, JsonError>
+        where S: for<'a> From<&'a str> {
+        let mut diagram = UseCaseDiagram::new();
+        let mut actor_ids = HashMap::new();
+        for (name, actor) in &document.actors {
+            let actor = Actor{
+                name: S::from(name),
+                key: actor.key.as_ref().map(|k| S::from(k)),
+                kind: actor.kind.map(ActorKind::from).unwrap_or_default(),
+                description: actor.description.as_ref().map(|d| S::from(d)),
+                stereotypes: from_strings(&actor.stereotypes),
+                metadata: actor.metadata.clone(),
+                icon: actor.icon.as_ref().map(|i| S::from(i)),
+                color: actor.color.as_ref().map(|c| S::from(c)),
+                localized_names: from_localized_strings(&actor.localized_names),
+                layers: from_strings(&actor.layers),
+                sort_key: actor.sort_key,
+                span: None,
+            };
+            let actor_id = diagram.insert_actor(actor)?;
+            actor_ids.insert(name.as_str(), actor_id);
+        }
+        let mut use_case_ids = HashMap::new();
+        let mut extension_point_ids = HashMap::new();
+        for (title, use_case) in &document.use_cases {
+            let use_case_id = diagram.insert_use_case(UseCase{
+                title: S::from(title),
+                key: use_case.key.as_ref().map(|k| S::from(k)),
+                package: match use_case.package {
+                    Some(ref package) => package.split(PACKAGE_SEPARATOR).map(S::from).collect(),
+                    None => Vec::new(),
+                },
+                is_abstract: use_case.is_abstract,
+                description: use_case.description.as_ref().map(|d| S::from(d)),
+                preconditions: from_strings(&use_case.preconditions),
+                postconditions: from_strings(&use_case.postconditions),
+                main_flow: from_strings(&use_case.main_flow),
+                alternative_flows:
+                    use_case.alternative_flows.iter()
+                    .map(|flow| Flow{name: S::from(&flow.name), steps: from_strings(&flow.steps)})
+                    .collect(),
+                priority: use_case.priority.map(Priority::from),
+                status: use_case.status.map(Status::from),
+                deprecated: use_case.deprecated.as_ref().map(|d| S::from(d)),
+                tags: from_strings(&use_case.tags),
+                stereotypes: from_strings(&use_case.stereotypes),
+                metadata: use_case.metadata.clone(),
+                localized_titles: from_localized_strings(&use_case.localized_titles),
+                layers: from_strings(&use_case.layers),
+                sort_key: use_case.sort_key,
+                span: None,
+            })?;
+            use_case_ids.insert(title.as_str(), use_case_id);
+            for name in &use_case.extension_points {
+                let extension_point = ExtensionPoint{use_case: use_case_id, name: S::from(name)};
+                let extension_point_id = diagram.insert_extension_point(extension_point)?;
+                extension_point_ids.insert((use_case_id, name.as_str()), extension_point_id);
+            }
+        }
+        let actor_id = |name: &String| {
+            actor_ids.get(name.as_str()).cloned()
+                .ok_or_else(|| JsonError::UnknownActorName(name.clone()))
+        };
+        let use_case_id = |title: &String| {
+            use_case_ids.get(title.as_str()).cloned()
+                .ok_or_else(|| JsonError::UnknownUseCaseTitle(title.clone()))
+        };
+        let multiplicity = |multiplicity: &Option<String>| match *multiplicity {
+            Some(ref multiplicity) =>
+                multiplicity.parse::<Multiplicity>().map(Some)
+                .map_err(|_| JsonError::InvalidMultiplicity(multiplicity.clone())),
+            None => Ok(None),
+        };
+        for association in &document.associations {
+            let details = Association{
+                label: association.label.as_ref().map(|label| S::from(label)),
+                actor_multiplicity: multiplicity(&association.actor_multiplicity)?,
+                use_case_multiplicity: multiplicity(&association.use_case_multiplicity)?,
+                navigability: match association.navigability {
+                    None => Navigability::Undirected,
+                    Some(ByNameNavigability::ToUseCase) => Navigability::ToUseCase,
+                    Some(ByNameNavigability::ToActor) => Navigability::ToActor,
+                },
+                weight: association.weight,
+                metadata: association.metadata.clone(),
+                layers: from_strings(&association.layers),
+                span: None,
+            };
+            diagram.insert_association_with(actor_id(&association.actor)?,
+                                            use_case_id(&association.use_case)?, details)
+                .expect("identifiers were just inserted");
+        }
+        for generalization in &document.actor_generalizations {
+            let child = actor_id(&generalization.child)?;
+            let parent = actor_id(&generalization.parent)?;
+            diagram.insert_actor_generalization(child, parent)?;
+        }
+        for generalization in &document.use_case_generalizations {
+            let child = use_case_id(&generalization.child)?;
+            let parent = use_case_id(&generalization.parent)?;
+            diagram.insert_use_case_generalization(child, parent)?;
+        }
+        for include in &document.includes {
+            let base = use_case_id(&include.base)?;
+            let included = use_case_id(&include.included)?;
+            diagram.insert_include(base, included)?;
+        }
+        for dependency in &document.dependencies {
+            let dependent = use_case_id(&dependency.dependent)?;
+            let label = dependency.label.as_ref().map(|label| S::from(label));
+            diagram.insert_dependency(dependent, use_case_id(&dependency.dependency)?, label)?;
+        }
+        for extend in &document.extends {
+            let base = use_case_id(&extend.base)?;
+            let extension_point = match extend.extension_point {
+                Some(ref name) => Some(
+                    *extension_point_ids.get(&(base, name.as_str()))
+                    .ok_or_else(|| JsonError::UnknownExtensionPointName(name.clone()))?
+                ),
+                None => None,
+            };
+            diagram.insert_extend(Extend{extension: use_case_id(&extend.extension)?, base,
+                                         extension_point})?;
+        }
+        for (name, system_boundary) in &document.system_boundaries {
+            let use_cases =
+                system_boundary.use_cases.iter()
+                .map(use_case_id)
+                .collect::<Result<_, _>>()?;
+            diagram.insert_system_boundary(SystemBoundary{name: S::from(name), use_cases})?;
+        }
+        for note in &document.notes {
+            let anchor = match (&note.actor, &note.use_case) {
+                (Some(actor), None) => NoteAnchor::Actor(actor_id(actor)?),
+                (None, Some(use_case)) => NoteAnchor::UseCase(use_case_id(use_case)?),
+                (Some(actor), Some(use_case)) =>
+                    NoteAnchor::Association(actor_id(actor)?, use_case_id(use_case)?),
+                (None, None) => return Err(JsonError::UnanchoredNote(note.text.clone())),
+            };
+            diagram.insert_note(Note{anchor, text: S::from(&note.text)})?;
+        }
+        Ok(diagram)
+    }
+}
+
+fn to_strings<S>(strings: &[S]) -> Vec<String> where S: AsRef<str> {
+    strings.iter().map(|s| s.as_ref().to_string()).collect()
+}
+
+fn from_strings<S>(strings: &[String]) -> Vec<S> where S: for<'a> From<&'a str> {
+    strings.iter().map(|s| S::from(s)).collect()
+}
+
+fn to_localized_strings<S>(strings: &BTreeMap<String, S>) -> BTreeMap<String, String>
+    where S: AsRef<str> {
+    strings.iter().map(|(locale, s)| (locale.clone(), s.as_ref().to_string())).collect()
+}
+
+fn from_localized_strings<S>(strings: &BTreeMap<String, String>) -> BTreeMap<String, S>
+    where S: for<'a> From<&'a str> {
+    strings.iter().map(|(locale, s)| (locale.clone(), S::from(s))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+    use std::sync::{Arc, RwLock};
+    use std::thread;
+
+    #[test]
+    fn test_by_name_round_trip() {
+        let mut left = UseCaseDiagram::new();
+        let a = left.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = left.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = left.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = left.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        left.insert_association(a, bs).unwrap();
+        left.insert_association(s, pc).unwrap();
+
+        let mut right = UseCaseDiagram::new();
+        let pc = right.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let s = right.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = right.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let a = right.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        right.insert_association(s, pc).unwrap();
+        right.insert_association(a, bs).unwrap();
+
+        let left_json = left.to_json_by_name().unwrap();
+        let right_json = right.to_json_by_name().unwrap();
+        assert_eq!(left_json, right_json);
+
+        let left: UseCaseDiagram = UseCaseDiagram::from_json_by_name(&left_json).unwrap();
+        let right: UseCaseDiagram = UseCaseDiagram::from_json_by_name(&right_json).unwrap();
+        assert_eq!(left.actors().collect::<HashMap<_, _>>(),
+                   right.actors().collect::<HashMap<_, _>>());
+        assert_eq!(left.use_cases().collect::<HashMap<_, _>>(),
+                   right.use_cases().collect::<HashMap<_, _>>());
+        assert_eq!(left.associations().collect::<BTreeSet<_>>(),
+                   right.associations().collect::<BTreeSet<_>>());
+        assert_eq!(left.to_json_by_name().unwrap(), left_json);
+    }
+
+    #[test]
+    fn test_by_name_errors() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let a2 = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        match diagram.to_json_by_name() {
+            Err(JsonError::Name(err)) =>
+                assert_eq!(err, NameError::DuplicateActorName(a, a2)),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let json = r#"{
+            "actors": {},
+            "use_cases": {"Ban subscriber": {}},
+            "associations": [{"actor": "Administrator", "use_case": "Ban subscriber"}]
+        }"#;
+        match UseCaseDiagram::<Rc<str>>::from_json_by_name(json) {
+            Err(JsonError::UnknownActorName(name)) =>
+                assert_eq!(name, "Administrator"),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_by_name_snapshot() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+
+        assert_eq!(diagram.to_json_by_name().unwrap(), concat!(
+            "{\n",
+            "  \"actors\": {\n",
+            "    \"Administrator\": {}\n",
+            "  },\n",
+            "  \"use_cases\": {\n",
+            "    \"Ban subscriber\": {}\n",
+            "  },\n",
+            "  \"associations\": [\n",
+            "    {\n",
+            "      \"actor\": \"Administrator\",\n",
+            "      \"use_case\": \"Ban subscriber\"\n",
+            "    }\n",
+            "  ]\n",
+            "}",
+        ));
+    }
+
+    #[test]
+    fn test_by_name_includes() {
+        let mut diagram = UseCaseDiagram::new();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        diagram.insert_include(pc, li).unwrap();
+
+        let json = diagram.to_json_by_name().unwrap();
+        assert!(json.contains("\"base\": \"Post comment\""));
+        let diagram: UseCaseDiagram = UseCaseDiagram::from_json_by_name(&json).unwrap();
+        let pc = diagram.use_cases().find(|&(_, uc)| &*uc.title == "Post comment").unwrap().0;
+        let li = diagram.use_cases().find(|&(_, uc)| &*uc.title == "Log in").unwrap().0;
+        assert_eq!(diagram.includes().collect::<Vec<_>>(), vec![(pc, li)]);
+
+        let json = r#"{
+            "actors": {},
+            "use_cases": {"Log in": {}},
+            "associations": [],
+            "includes": [{"base": "Log in", "included": "Log in"}]
+        }"#;
+        match UseCaseDiagram::<Rc<str>>::from_json_by_name(json) {
+            Err(JsonError::Relationship(RelationshipError::UseCaseCycle(_, _))) => (),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_by_name_extends() {
+        let mut diagram = UseCaseDiagram::new();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let ai = diagram.insert_use_case(UseCase::new(Rc::from("Attach image"))).unwrap();
+        let sc = diagram.insert_use_case(UseCase::new(Rc::from("Spell check"))).unwrap();
+        let compose = diagram.insert_extension_point(ExtensionPoint{use_case: pc,
+                                                                    name: Rc::from("Compose")})
+            .unwrap();
+        diagram.insert_extend(Extend{extension: ai, base: pc, extension_point: Some(compose)})
+            .unwrap();
+        diagram.insert_extend(Extend{extension: sc, base: pc, extension_point: None}).unwrap();
+
+        let json = diagram.to_json_by_name().unwrap();
+        let imported: UseCaseDiagram = UseCaseDiagram::from_json_by_name(&json).unwrap();
+        assert_eq!(imported.extension_points().len(), 1);
+        assert_eq!(imported.extends().len(), 2);
+        assert_eq!(imported.to_json_by_name().unwrap(), json);
+
+        diagram.insert_extension_point(ExtensionPoint{use_case: pc, name: Rc::from("Compose")})
+            .unwrap();
+        match diagram.to_json_by_name() {
+            Err(JsonError::Name(NameError::DuplicateExtensionPointName(_, _))) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_by_name_actor_generalizations() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        diagram.insert_actor_generalization(a, s).unwrap();
+
+        let json = diagram.to_json_by_name().unwrap();
+        let imported: UseCaseDiagram = UseCaseDiagram::from_json_by_name(&json).unwrap();
+        let a = imported.actors().find(|&(_, actor)| &*actor.name == "Administrator").unwrap().0;
+        let s = imported.actors().find(|&(_, actor)| &*actor.name == "Subscriber").unwrap().0;
+        assert_eq!(imported.actor_generalizations().collect::<Vec<_>>(), vec![(a, s)]);
+    }
+
+
+    #[test]
+    fn test_by_name_use_case_generalizations() {
+        let mut diagram = UseCaseDiagram::new();
+        let p = diagram.insert_use_case(UseCase::new(Rc::from("Pay"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Pay by card"))).unwrap();
+        diagram.insert_use_case_generalization(pc, p).unwrap();
+
+        let json = diagram.to_json_by_name().unwrap();
+        let imported: UseCaseDiagram = UseCaseDiagram::from_json_by_name(&json).unwrap();
+        let p = imported.use_cases().find(|&(_, uc)| &*uc.title == "Pay").unwrap().0;
+        let pc = imported.use_cases().find(|&(_, uc)| &*uc.title == "Pay by card").unwrap().0;
+        assert_eq!(imported.use_case_generalizations().collect::<Vec<_>>(), vec![(pc, p)]);
+    }
+
+
+    #[test]
+    fn test_by_name_system_boundaries() {
+        let mut diagram = UseCaseDiagram::new();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        diagram.insert_system_boundary(SystemBoundary{name: Rc::from("Moderation"),
+                                                      use_cases: [bs].iter().cloned().collect()})
+            .unwrap();
+
+        let json = diagram.to_json_by_name().unwrap();
+        assert!(json.contains("\"system_boundaries\""));
+        let imported: UseCaseDiagram = UseCaseDiagram::from_json_by_name(&json).unwrap();
+        let (_, moderation) = imported.system_boundaries().next().unwrap();
+        assert_eq!(&*moderation.name, "Moderation");
+        assert_eq!(moderation.use_cases, [bs].iter().cloned().collect());
+
+        let json = r#"{"actors": {}, "use_cases": {}, "associations": [],
+                       "system_boundaries": {"Moderation": {"use_cases": ["Ban"]}}}"#;
+        match UseCaseDiagram::<Rc<str>>::from_json_by_name(json) {
+            Err(JsonError::UnknownUseCaseTitle(ref title)) => assert_eq!(title, "Ban"),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+
+    #[test]
+    fn test_by_name_notes() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_note(Note{anchor: NoteAnchor::Actor(a), text: Rc::from("Staff")})
+            .unwrap();
+        diagram.insert_note(Note{anchor: NoteAnchor::Association(a, bs), text: Rc::from("2FA")})
+            .unwrap();
+        diagram.insert_note(Note{anchor: NoteAnchor::Actor(a), text: Rc::from("Staff")})
+            .unwrap();
+
+        let json = diagram.to_json_by_name().unwrap();
+        let imported: UseCaseDiagram = UseCaseDiagram::from_json_by_name(&json).unwrap();
+        let mut notes = imported.notes()
+            .map(|(_, note)| (note.anchor, note.text.to_string()))
+            .collect::<Vec<_>>();
+        notes.sort();
+        assert_eq!(notes, vec![(NoteAnchor::Actor(a), "Staff".to_string()),
+                               (NoteAnchor::Actor(a), "Staff".to_string()),
+                               (NoteAnchor::Association(a, bs), "2FA".to_string())]);
+
+        let json = r#"{"actors": {}, "use_cases": {}, "associations": [],
+                       "notes": [{"text": "Lost"}]}"#;
+        match UseCaseDiagram::<Rc<str>>::from_json_by_name(json) {
+            Err(JsonError::UnanchoredNote(ref text)) => assert_eq!(text, "Lost"),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+
+    #[test]
+    fn test_by_name_rich_associations() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let association = Association{
+            label: Some(Rc::from("bans")),
+            actor_multiplicity: Some(Multiplicity{lower: 1, upper: None}),
+            use_case_multiplicity: Some(Multiplicity{lower: 0, upper: Some(3)}),
+            navigability: Navigability::ToActor,
+            weight: Some(40),
+            metadata: [("ticket".to_string(), "JRN-12".to_string())].iter().cloned().collect(),
+            layers: vec![Rc::from("MVP")],
+            span: None,
+        };
+        diagram.insert_association_with(a, bs, association.clone()).unwrap();
+
+        let json = diagram.to_json_by_name().unwrap();
+        assert!(json.contains("\"actor_multiplicity\": \"1..*\""));
+        let imported: UseCaseDiagram = UseCaseDiagram::from_json_by_name(&json).unwrap();
+        assert_eq!(imported.association(a, bs), Some(&association));
+
+        let json = r#"{"actors": {"A": {}}, "use_cases": {"U": {}},
+                       "associations": [{"actor": "A", "use_case": "U",
+                                         "actor_multiplicity": "3..1"}]}"#;
+        match UseCaseDiagram::<Rc<str>>::from_json_by_name(json) {
+            Err(JsonError::InvalidMultiplicity(ref m)) => assert_eq!(m, "3..1"),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+
+    #[test]
+    fn test_by_name_stereotypes() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut scheduler = Actor::new(Rc::from("Scheduler"));
+        scheduler.stereotypes.push(Rc::from("system"));
+        let s = diagram.insert_actor(scheduler).unwrap();
+        let mut purge = UseCase::new(Rc::from("Purge accounts"));
+        purge.stereotypes.push(Rc::from("batch"));
+        let p = diagram.insert_use_case(purge).unwrap();
+
+        let json = diagram.to_json_by_name().unwrap();
+        let imported: UseCaseDiagram = UseCaseDiagram::from_json_by_name(&json).unwrap();
+        assert_eq!(imported.actor(s), diagram.actor(s));
+        assert_eq!(imported.use_case(p), diagram.use_case(p));
+    }
+
+
+    #[test]
+    fn test_by_name_metadata() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut actor = Actor::new(Rc::from("Administrator"));
+        actor.metadata.insert("owner".to_string(), "ops".to_string());
+        let a = diagram.insert_actor(actor).unwrap();
+        let mut use_case = UseCase::new(Rc::from("Ban subscriber"));
+        use_case.metadata.insert("ticket".to_string(), "JRN-7".to_string());
+        let bs = diagram.insert_use_case(use_case).unwrap();
+
+        let json = diagram.to_json_by_name().unwrap();
+        assert!(json.contains("\"owner\": \"ops\""));
+        let imported: UseCaseDiagram = UseCaseDiagram::from_json_by_name(&json).unwrap();
+        assert_eq!(imported.actor(a), diagram.actor(a));
+        assert_eq!(imported.use_case(bs), diagram.use_case(bs));
+    }
+
+
+    #[test]
+    fn test_by_name_use_case_specification() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut actor = Actor::new(Rc::from("Administrator"));
+        actor.description = Some(Rc::from("Staff member."));
+        let a = diagram.insert_actor(actor).unwrap();
+        let mut use_case = UseCase::new(Rc::from("Ban subscriber"));
+        use_case.description = Some(Rc::from("Prevent a subscriber\nfrom logging in."));
+        use_case.preconditions.push(Rc::from("The administrator is logged in."));
+        use_case.postconditions.push(Rc::from("The subscriber cannot log in."));
+        use_case.priority = Some(Priority::High);
+        use_case.status = Some(Status::Approved);
+        use_case.tags.push(Rc::from("mvp"));
+        let bs = diagram.insert_use_case(use_case).unwrap();
+
+        let json = diagram.to_json_by_name().unwrap();
+        let imported: UseCaseDiagram = UseCaseDiagram::from_json_by_name(&json).unwrap();
+        assert_eq!(imported.actor(a), diagram.actor(a));
+        assert_eq!(imported.use_case(bs), diagram.use_case(bs));
+    }
+
+    #[test]
+    fn test_by_name_use_case_details() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut actor = Actor::new(Rc::from("Administrator"));
+        actor.key = Some(Rc::from("9f0c"));
+        actor.kind = ActorKind::Secondary;
+        let a = diagram.insert_actor(actor).unwrap();
+        let mut use_case = UseCase::new(Rc::from("Ban subscriber"));
+        use_case.key = Some(Rc::from("UC-7"));
+        use_case.is_abstract = true;
+        use_case.package = vec![Rc::from("Moderation"), Rc::from("Bans")];
+        use_case.main_flow.push(Rc::from("Select the subscriber."));
+        use_case.alternative_flows.push(Flow{name: Rc::from("Already banned"), steps: vec![]});
+        use_case.deprecated = Some(Rc::from("Use Suspend subscriber."));
+        let bs = diagram.insert_use_case(use_case).unwrap();
+
+        let json = diagram.to_json_by_name().unwrap();
+        assert!(json.contains("\"key\": \"UC-7\""));
+        assert!(json.contains("\"kind\": \"secondary\""));
+        assert!(json.contains("\"abstract\": true"));
+        assert!(json.contains("\"package\": \"Moderation::Bans\""));
+        assert!(json.contains("\"main_flow\": [\n"));
+        assert!(json.contains("\"deprecated\": \"Use Suspend subscriber.\""));
+        let imported: UseCaseDiagram = UseCaseDiagram::from_json_by_name(&json).unwrap();
+        assert_eq!(imported.actor(a), diagram.actor(a));
+        assert_eq!(imported.use_case(bs), diagram.use_case(bs));
+    }
+
+    #[test]
+    fn test_by_name_across_threads() {
+        let json = r#"{"actors": {"Administrator": {}},
+                       "use_cases": {"Ban subscriber": {}},
+                       "associations": [{"actor": "Administrator",
+                                         "use_case": "Ban subscriber"}]}"#;
+        let diagram: UseCaseDiagram<Arc<str>> =
+            thread::spawn(move || UseCaseDiagram::from_json_by_name(json).unwrap())
+            .join().unwrap();
+        let shared = Arc::new(RwLock::new(diagram));
+
+        let writer = Arc::clone(&shared);
+        thread::spawn(move || {
+            writer.write().unwrap().insert_actor(Actor::new(Arc::from("Moderator"))).unwrap();
+        }).join().unwrap();
+
+        let reader = Arc::clone(&shared);
+        let names = thread::spawn(move || {
+            reader.read().unwrap().actors()
+                .map(|(_, actor)| actor.name.to_string())
+                .collect::<Vec<_>>()
+        }).join().unwrap();
+        assert_eq!(names, vec!["Administrator".to_string(), "Moderator".to_string()]);
+    }
+
+    #[test]
+    fn test_by_name_dependencies() {
+        let mut diagram = UseCaseDiagram::new();
+        let cs = diagram.insert_use_case(UseCase::new(Rc::from("Create subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_dependency(pc, cs, Some(Rc::from("needs an author"))).unwrap();
+
+        let json = diagram.to_json_by_name().unwrap();
+        assert!(json.contains("\"dependent\": \"Post comment\""));
+        let diagram: UseCaseDiagram = UseCaseDiagram::from_json_by_name(&json).unwrap();
+        let (cs, _) = diagram.use_case_by_title("Create subscriber").unwrap();
+        let (pc, _) = diagram.use_case_by_title("Post comment").unwrap();
+        assert_eq!(diagram.dependencies().collect::<Vec<_>>(), vec![(pc, cs)]);
+        assert_eq!(diagram.dependency_label(pc, cs).map(|l| &**l), Some("needs an author"));
+    }
+
+    #[test]
+    fn test_by_name_render_hints() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut actor = Actor::new(Rc::from("Administrator"));
+        actor.icon = Some(Rc::from("shield"));
+        actor.color = Some(Rc::from("#1f77b4"));
+        let a = diagram.insert_actor(actor).unwrap();
+
+        let json = diagram.to_json_by_name().unwrap();
+        assert!(json.contains("\"icon\": \"shield\""));
+        let imported: UseCaseDiagram = UseCaseDiagram::from_json_by_name(&json).unwrap();
+        assert_eq!(imported.actor(a), diagram.actor(a));
+    }
+
+    #[test]
+    fn test_by_name_localized() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut actor = Actor::new(Rc::from("Administrator"));
+        actor.localized_names.insert("nl".to_string(), Rc::from("Beheerder"));
+        let a = diagram.insert_actor(actor).unwrap();
+        let mut use_case = UseCase::new(Rc::from("Ban subscriber"));
+        use_case.localized_titles.insert("nl".to_string(), Rc::from("Abonnee blokkeren"));
+        let bs = diagram.insert_use_case(use_case).unwrap();
+
+        let json = diagram.to_json_by_name().unwrap();
+        assert!(json.contains("\"nl\": \"Beheerder\""));
+        let imported: UseCaseDiagram = UseCaseDiagram::from_json_by_name(&json).unwrap();
+        assert_eq!(imported.actor(a), diagram.actor(a));
+        assert_eq!(imported.use_case(bs), diagram.use_case(bs));
+    }
+
+    #[test]
+    fn test_interchange() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+
+        let json = diagram.to_json().unwrap();
+        assert_eq!(json, concat!(
+            "{\n",
+            "  \"format\": \"butterfly/use-case-diagram\",\n",
+            "  \"version\": 1,\n",
+            "  \"diagram\": {\n",
+            "    \"actors\": {\n",
+            "      \"Administrator\": {}\n",
+            "    },\n",
+            "    \"use_cases\": {\n",
+            "      \"Ban subscriber\": {}\n",
+            "    },\n",
+            "    \"associations\": [\n",
+            "      {\n",
+            "        \"actor\": \"Administrator\",\n",
+            "        \"use_case\": \"Ban subscriber\"\n",
+            "      }\n",
+            "    ]\n",
+            "  }\n",
+            "}",
+        ));
+        let imported = UseCaseDiagram::<Rc<str>>::from_json(&json).unwrap();
+        assert!(imported.equivalent(&diagram));
+
+        let other = json.replace("butterfly/use-case-diagram", "other");
+        match UseCaseDiagram::<Rc<str>>::from_json(&other) {
+            Err(JsonError::UnknownFormat(format)) => assert_eq!(format, "other"),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+        let newer = json.replace("\"version\": 1", "\"version\": 2")
+            .replace("\"actors\"", "\"participants\"");
+        match UseCaseDiagram::<Rc<str>>::from_json(&newer) {
+            Err(JsonError::UnsupportedVersion(version)) => assert_eq!(version, 2),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::BTreeSet;
+
+use use_case_diagram::{Actor, Association, UseCase, UseCaseDiagram};
+
+impl<S> Actor<S> where S: AsRef<str> {
+    /// Whether the actor is shown in the given layer: if it belongs to no
+    /// layer at all, to that layer, or to a layer before it in `order`.
+    /// Layers are cumulative, so what is in the MVP carries forward into
+    /// every later phase.
+    pub fn visible_in<L>(&self, order: &[L], layer: &str) -> bool where L: AsRef<str> {
+        visible_in(&self.layers, order, layer)
+    }
+}
+
+impl<S> UseCase<S> where S: AsRef<str> {
+    /// Whether the use case is shown in the given layer, like
+    /// `Actor::visible_in`.
+    pub fn visible_in<L>(&self, order: &[L], layer: &str) -> bool where L: AsRef<str> {
+        visible_in(&self.layers, order, layer)
+    }
+}
+
+impl<S> Association<S> where S: AsRef<str> {
+    /// Whether the association is shown in the given layer, like
+    /// `Actor::visible_in`.
+    pub fn visible_in<L>(&self, order: &[L], layer: &str) -> bool where L: AsRef<str> {
+        visible_in(&self.layers, order, layer)
+    }
+}
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> {
+    /// The layers that any actor, use case, or association belongs to.
+    pub fn layers(&self) -> BTreeSet<&str> {
+        let actor_layers = self.actors().flat_map(|(_, actor)| &actor.layers);
+        let use_case_layers = self.use_cases().flat_map(|(_, use_case)| &use_case.layers);
+        let association_layers =
+            self.associations.values().flat_map(|association| &association.layers);
+        actor_layers.chain(use_case_layers).chain(association_layers)
+            .map(|layer| layer.as_ref())
+            .collect()
+    }
+
+    /// The part of this diagram shown in the given layer, with the layers in
+    /// the given order: the actors, use cases, and associations visible in
+    /// it, and the other relationships between those. Identifiers are
+    /// preserved. Renderers and generators produce the staged output for a
+    /// layer from this subdiagram.
+    pub fn layer_subdiagram<L>(&self, order: &[L], layer: &str) -> UseCaseDiagram<S>
+        where S: Clone, L: AsRef<str> {
+        let mut subdiagram = self.filter(|_, actor| actor.visible_in(order, layer),
+                                         |_, use_case| use_case.visible_in(order, layer));
+        let hidden =
+            subdiagram.associations.iter()
+            .filter(|&(_, association)| !association.visible_in(order, layer))
+            .map(|(&key, _)| key)
+            .collect::<Vec<_>>();
+        for (actor_id, use_case_id) in hidden {
+            subdiagram.remove_association(actor_id, use_case_id);
+        }
+        subdiagram
+    }
+}
+
+fn visible_in<S, L>(layers: &[S], order: &[L], layer: &str) -> bool
+    where S: AsRef<str>, L: AsRef<str> {
+    let position = order.iter().position(|l| l.as_ref() == layer);
+    let earlier = position.map_or(&[][..], |position| &order[..position]);
+    layers.is_empty() ||
+    layers.iter().any(|l| {
+        l.as_ref() == layer || earlier.iter().any(|earlier| earlier.as_ref() == l.as_ref())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_layer_subdiagram() {
+        let mut diagram = UseCaseDiagram::new();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let mut moderator = Actor::new(Rc::from("Moderator"));
+        moderator.layers.push(Rc::from("Phase 2"));
+        let m = diagram.insert_actor(moderator).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let mut report = UseCase::new(Rc::from("Report comment"));
+        report.layers.push(Rc::from("Phase 2"));
+        let r = diagram.insert_use_case(report).unwrap();
+        let posting = Association{layers: vec![Rc::from("MVP"), Rc::from("Phase 2")],
+                                  ..Association::default()};
+        diagram.insert_association_with(s, pc, posting).unwrap();
+        diagram.insert_association(m, pc).unwrap();
+        diagram.insert_association(s, r).unwrap();
+
+        assert_eq!(diagram.layers().into_iter().collect::<Vec<_>>(), vec!["MVP", "Phase 2"]);
+        let order = ["MVP", "Phase 2", "Phase 3"];
+        let mvp = diagram.layer_subdiagram(&order, "MVP");
+        assert_eq!(mvp.actors().map(|(id, _)| id).collect::<Vec<_>>(), vec![s]);
+        assert_eq!(mvp.use_cases().map(|(id, _)| id).collect::<Vec<_>>(), vec![pc]);
+        assert_eq!(mvp.associations().collect::<Vec<_>>(), vec![(s, pc)]);
+        let phase_2 = diagram.layer_subdiagram(&order, "Phase 2");
+        assert_eq!(phase_2.associations().collect::<Vec<_>>(), vec![(s, pc), (s, r), (m, pc)]);
+    }
+
+    #[test]
+    fn test_cumulative_layers() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut subscriber = Actor::new(Rc::from("Subscriber"));
+        subscriber.layers.push(Rc::from("MVP"));
+        let s = diagram.insert_actor(subscriber).unwrap();
+        let mut moderator = Actor::new(Rc::from("Moderator"));
+        moderator.layers.push(Rc::from("Phase 2"));
+        let m = diagram.insert_actor(moderator).unwrap();
+        let mut analyst = Actor::new(Rc::from("Analyst"));
+        analyst.layers.push(Rc::from("Phase 3"));
+        let a = diagram.insert_actor(analyst).unwrap();
+
+        let order = ["MVP", "Phase 2", "Phase 3"];
+        let visible = |layer| {
+            diagram.layer_subdiagram(&order, layer).actors().map(|(id, _)| id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(visible("MVP"), vec![s]);
+        assert_eq!(visible("Phase 2"), vec![s, m]);
+        assert_eq!(visible("Phase 3"), vec![s, m, a]);
+        assert_eq!(diagram.layer_subdiagram::<&str>(&[], "Phase 2").actors()
+                   .map(|(id, _)| id).collect::<Vec<_>>(), vec![m]);
+    }
+}
@@ -0,0 +1,204 @@
+//! Positions for drawing diagrams in tools that do not lay them out.
+//!
+//! Use cases are stacked in a column, grouped by the first system boundary
+//! that contains them, which is drawn around the group. Human actors stand to
+//! the left of the column and other actors to the right, each level with the
+//! use cases it is associated with where there is room. Coordinates are in
+//! pixels from the top left corner.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use use_case_diagram::{ActorId, SystemBoundaryId, UseCaseDiagram, UseCaseId};
+
+const MARGIN: i64 = 20;
+const SPACING: i64 = 20;
+const COLUMN_GAP: i64 = 120;
+
+const ACTOR_COLUMN_WIDTH: i64 = 100;
+const ACTOR_WIDTH: i64 = 30;
+const ACTOR_HEIGHT: i64 = 60;
+
+/// Room below an actor for its name.
+const ACTOR_LABEL_HEIGHT: i64 = 30;
+
+const USE_CASE_MIN_WIDTH: i64 = 120;
+const USE_CASE_HEIGHT: i64 = 60;
+const USE_CASE_CHARACTER_WIDTH: i64 = 7;
+
+const BOUNDARY_PADDING: i64 = 20;
+const BOUNDARY_HEADER_HEIGHT: i64 = 30;
+
+/// An axis-aligned rectangle.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Rectangle {
+    pub x: i64,
+    pub y: i64,
+    pub width: i64,
+    pub height: i64,
+}
+
+impl Rectangle {
+    /// The point halfway across and halfway down.
+    pub fn center(&self) -> (i64, i64) {
+        (self.x + self.width / 2, self.y + self.height / 2)
+    }
+
+    /// The coordinate of the bottom edge.
+    pub fn bottom(&self) -> i64 {
+        self.y + self.height
+    }
+}
+
+/// Where every actor, use case and system boundary of a diagram is drawn.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Layout {
+    /// The stick figures, without the names below them.
+    pub actors: BTreeMap<ActorId, Rectangle>,
+
+    pub use_cases: BTreeMap<UseCaseId, Rectangle>,
+
+    /// Only around the use cases grouped with the system boundary, so use
+    /// cases that are also in an earlier system boundary are outside it.
+    pub system_boundaries: BTreeMap<SystemBoundaryId, Rectangle>,
+
+    /// The size of the drawing, including margins.
+    pub width: i64,
+    pub height: i64,
+}
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> {
+    /// Lay out the diagram. Use cases are in the order of
+    /// `use_cases_in_order` within their groups, and are all as wide as the
+    /// widest title needs.
+    pub fn layout(&self) -> Layout {
+        let use_cases = self.use_cases_in_order();
+        let use_case_width = use_cases.iter()
+            .map(|&(_, use_case)| {
+                use_case.title.as_ref().chars().count() as i64 * USE_CASE_CHARACTER_WIDTH +
+                    2 * BOUNDARY_PADDING
+            })
+            .fold(USE_CASE_MIN_WIDTH, i64::max);
+        let boundary_x = MARGIN + ACTOR_COLUMN_WIDTH + COLUMN_GAP;
+        let use_case_x = boundary_x + BOUNDARY_PADDING;
+
+        let mut layout = Layout{actors: BTreeMap::new(), use_cases: BTreeMap::new(),
+                                system_boundaries: BTreeMap::new(), width: 0, height: 0};
+        let mut placed = BTreeSet::new();
+        let mut y = MARGIN;
+        let mut system_boundaries = self.system_boundaries().collect::<Vec<_>>();
+        system_boundaries.sort_by_key(|&(system_boundary_id, _)| system_boundary_id);
+        for (system_boundary_id, system_boundary) in system_boundaries {
+            let top = y;
+            y += BOUNDARY_HEADER_HEIGHT;
+            for &(use_case_id, _) in &use_cases {
+                if system_boundary.use_cases.contains(&use_case_id) && placed.insert(use_case_id) {
+                    let rectangle = Rectangle{x: use_case_x, y, width: use_case_width,
+                                              height: USE_CASE_HEIGHT};
+                    layout.use_cases.insert(use_case_id, rectangle);
+                    y += USE_CASE_HEIGHT + SPACING;
+                }
+            }
+            y += BOUNDARY_PADDING - SPACING;
+            let rectangle = Rectangle{x: boundary_x, y: top,
+                                      width: use_case_width + 2 * BOUNDARY_PADDING,
+                                      height: y.max(top + BOUNDARY_HEADER_HEIGHT) - top};
+            layout.system_boundaries.insert(system_boundary_id, rectangle);
+            y = rectangle.bottom() + SPACING;
+        }
+        for &(use_case_id, _) in &use_cases {
+            if placed.insert(use_case_id) {
+                let rectangle = Rectangle{x: use_case_x, y, width: use_case_width,
+                                          height: USE_CASE_HEIGHT};
+                layout.use_cases.insert(use_case_id, rectangle);
+                y += USE_CASE_HEIGHT + SPACING;
+            }
+        }
+
+        let right_x = boundary_x + use_case_width + 2 * BOUNDARY_PADDING + COLUMN_GAP;
+        let actors = self.actors_in_order();
+        let (left, right): (Vec<_>, Vec<_>) =
+            actors.iter().partition(|&&(_, actor)| actor.kind.is_human());
+        for &(side, column_x) in &[(&left, MARGIN), (&right, right_x)] {
+            self.layout_actors(&mut layout, side.iter().map(|&&(actor_id, _)| actor_id),
+                               column_x + (ACTOR_COLUMN_WIDTH - ACTOR_WIDTH) / 2);
+        }
+
+        let mut width = boundary_x + use_case_width + 2 * BOUNDARY_PADDING;
+        if !right.is_empty() {
+            width = right_x + ACTOR_COLUMN_WIDTH;
+        }
+        let bottom = layout.use_cases.values().chain(layout.system_boundaries.values())
+            .map(Rectangle::bottom)
+            .chain(layout.actors.values().map(|actor| actor.bottom() + ACTOR_LABEL_HEIGHT))
+            .max()
+            .unwrap_or(MARGIN);
+        layout.width = width + MARGIN;
+        layout.height = bottom + MARGIN;
+        layout
+    }
+
+    /// Stack the actors in a column, each centred on its use cases if it
+    /// does not overlap the actor above. Actors without use cases go last.
+    fn layout_actors<I>(&self, layout: &mut Layout, actor_ids: I, x: i64)
+        where I: Iterator<Item = ActorId> {
+        let mut wanted = actor_ids
+            .map(|actor_id| {
+                let centers = self.use_cases_of_actor(actor_id)
+                    .map(|use_case_id| layout.use_cases[&use_case_id].center().1)
+                    .collect::<Vec<_>>();
+                let center = if centers.is_empty() {
+                    None
+                } else {
+                    Some(centers.iter().sum::<i64>() / centers.len() as i64)
+                };
+                (center.is_none(), center, actor_id)
+            })
+            .collect::<Vec<_>>();
+        wanted.sort_by_key(|&(unassociated, center, _)| (unassociated, center));
+        let mut y = MARGIN;
+        for (_, center, actor_id) in wanted {
+            let top = center.map_or(y, |center| (center - ACTOR_HEIGHT / 2).max(y));
+            let rectangle = Rectangle{x, y: top, width: ACTOR_WIDTH, height: ACTOR_HEIGHT};
+            layout.actors.insert(actor_id, rectangle);
+            y = rectangle.bottom() + ACTOR_LABEL_HEIGHT + SPACING;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use use_case_diagram::{Actor, ActorKind, SystemBoundary, UseCase};
+
+    #[test]
+    fn test_layout() {
+        let mut diagram = UseCaseDiagram::<&str>::new();
+        let subscriber = diagram.insert_actor(Actor::new("Subscriber")).unwrap();
+        let mut mailer = Actor::new("Mailer");
+        mailer.kind = ActorKind::System;
+        let mailer = diagram.insert_actor(mailer).unwrap();
+        let lurker = diagram.insert_actor(Actor::new("Lurker")).unwrap();
+        let post = diagram.insert_use_case(UseCase::new("Post comment")).unwrap();
+        let notify =
+            diagram.insert_use_case(UseCase::new("Notify subscribers of replies")).unwrap();
+        let forum = diagram.insert_system_boundary(SystemBoundary{
+            name: "Forum", use_cases: vec![notify].into_iter().collect(),
+        }).unwrap();
+        diagram.insert_association(subscriber, post).unwrap();
+        diagram.insert_association(subscriber, notify).unwrap();
+        diagram.insert_association(mailer, notify).unwrap();
+
+        let layout = diagram.layout();
+        let width = 29 * USE_CASE_CHARACTER_WIDTH + 2 * BOUNDARY_PADDING;
+        assert_eq!(layout.system_boundaries[&forum],
+                   Rectangle{x: 240, y: 20, width: width + 40, height: 110});
+        assert_eq!(layout.use_cases[&notify], Rectangle{x: 260, y: 50, width, height: 60});
+        assert_eq!(layout.use_cases[&post], Rectangle{x: 260, y: 150, width, height: 60});
+        assert_eq!(layout.actors[&subscriber], Rectangle{x: 55, y: 100, width: 30, height: 60});
+        assert_eq!(layout.actors[&lurker], Rectangle{x: 55, y: 210, width: 30, height: 60});
+        assert_eq!(layout.actors[&mailer],
+                   Rectangle{x: 240 + width + 40 + 120 + 35, y: 50, width: 30, height: 60});
+        assert_eq!((layout.width, layout.height), (240 + width + 40 + 120 + 100 + 20, 320));
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use use_case_diagram::{ActorId, Span, UseCaseDiagram, UseCaseId};
+
+/// How serious a lint or a parse diagnostic is.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Severity {
+    /// The diagram is valid, but probably not what was intended, or the
+    /// parser skipped text that probably does not matter.
+    Warning,
+
+    /// The diagram cannot be used for code generation as is, or the parser
+    /// skipped text that does matter.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// The problem a lint reports.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum LintKind {
+    /// The actor has an empty name.
+    EmptyActorName(ActorId),
+
+    /// The use case has an empty title.
+    EmptyUseCaseTitle(UseCaseId),
+
+    /// The second actor has the same name as the first.
+    DuplicateActorName(ActorId, ActorId),
+
+    /// The second use case has the same title as the first.
+    DuplicateUseCaseTitle(UseCaseId, UseCaseId),
+
+    /// The actor is not associated with any use case, neither directly nor
+    /// through an ancestor.
+    OrphanActor(ActorId),
+
+    /// The use case is neither associated with any actor, nor included by,
+    /// extending, or specializing any use case, nor an abstract use case
+    /// specialized by another.
+    OrphanUseCase(UseCaseId),
+
+    /// The actor is associated with the use case, but also with another use
+    /// case that includes it.
+    RedundantAssociation(ActorId, UseCaseId),
+
+    /// The actor is associated with an abstract use case, which cannot be
+    /// performed.
+    AbstractAssociation(ActorId, UseCaseId),
+
+    /// The actor is associated with a deprecated use case.
+    DeprecatedAssociation(ActorId, UseCaseId),
+}
+
+impl LintKind {
+    /// The severity of this kind of lint.
+    pub fn severity(&self) -> Severity {
+        match *self {
+            LintKind::EmptyActorName(_) => Severity::Error,
+            LintKind::EmptyUseCaseTitle(_) => Severity::Error,
+            LintKind::DuplicateActorName(_, _) => Severity::Error,
+            LintKind::DuplicateUseCaseTitle(_, _) => Severity::Error,
+            LintKind::OrphanActor(_) => Severity::Warning,
+            LintKind::OrphanUseCase(_) => Severity::Warning,
+            LintKind::RedundantAssociation(_, _) => Severity::Warning,
+            LintKind::AbstractAssociation(_, _) => Severity::Warning,
+            LintKind::DeprecatedAssociation(_, _) => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LintKind::EmptyActorName(actor_id) =>
+                write!(f, "actor {} has an empty name", actor_id),
+            LintKind::EmptyUseCaseTitle(use_case_id) =>
+                write!(f, "use case {} has an empty title", use_case_id),
+            LintKind::DuplicateActorName(first, second) =>
+                write!(f, "actor {} has the same name as actor {}", second, first),
+            LintKind::DuplicateUseCaseTitle(first, second) =>
+                write!(f, "use case {} has the same title as use case {}", second, first),
+            LintKind::OrphanActor(actor_id) =>
+                write!(f, "actor {} is not associated with any use case", actor_id),
+            LintKind::OrphanUseCase(use_case_id) =>
+                write!(f, "use case {} is neither associated, included, nor extending",
+                       use_case_id),
+            LintKind::RedundantAssociation(actor_id, use_case_id) =>
+                write!(f, "actor {} reaches use case {} through an include", actor_id,
+                       use_case_id),
+            LintKind::AbstractAssociation(actor_id, use_case_id) =>
+                write!(f, "actor {} is associated with abstract use case {}", actor_id,
+                       use_case_id),
+            LintKind::DeprecatedAssociation(actor_id, use_case_id) =>
+                write!(f, "actor {} is associated with deprecated use case {}", actor_id,
+                       use_case_id),
+        }
+    }
+}
+
+/// A problem found in a diagram.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Lint {
+    pub severity: Severity,
+    pub kind: LintKind,
+}
+
+impl Lint {
+    /// A lint of the given kind, with the severity of that kind.
+    pub fn new(kind: LintKind) -> Self {
+        Lint{severity: kind.severity(), kind}
+    }
+
+    /// A human-readable description of the problem.
+    pub fn message(&self) -> String {
+        self.kind.to_string()
+    }
+
+    /// Where the element the lint is about was defined in the diagram, if it
+    /// was parsed from a source file. Lints about two actors or use cases
+    /// point at the second, and lints about associations at the association.
+    pub fn span<'a, S>(&self, diagram: &'a UseCaseDiagram<S>) -> Option<&'a Span<S>>
+        where S: AsRef<str> {
+        match self.kind {
+            LintKind::EmptyActorName(actor_id) |
+            LintKind::DuplicateActorName(_, actor_id) |
+            LintKind::OrphanActor(actor_id) =>
+                diagram.actor(actor_id)?.span.as_ref(),
+            LintKind::EmptyUseCaseTitle(use_case_id) |
+            LintKind::DuplicateUseCaseTitle(_, use_case_id) |
+            LintKind::OrphanUseCase(use_case_id) =>
+                diagram.use_case(use_case_id)?.span.as_ref(),
+            LintKind::RedundantAssociation(actor_id, use_case_id) |
+            LintKind::AbstractAssociation(actor_id, use_case_id) |
+            LintKind::DeprecatedAssociation(actor_id, use_case_id) =>
+                diagram.association(actor_id, use_case_id)?.span.as_ref(),
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.kind)
+    }
+}
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> {
+    /// Run every diagram check, returning the problems found ordered by
+    /// severity, most severe first. A diagram without error-severity lints
+    /// is fit for code generation.
+    pub fn lint(&self) -> Vec<Lint> {
+        let mut kinds = Vec::new();
+
+        let mut actors = self.actors().collect::<Vec<_>>();
+        actors.sort_by_key(|&(actor_id, _)| actor_id);
+        let mut names = HashMap::new();
+        for &(actor_id, actor) in &actors {
+            let name = actor.name.as_ref();
+            if name.is_empty() {
+                kinds.push(LintKind::EmptyActorName(actor_id));
+            }
+            match names.get(name) {
+                Some(&first) => kinds.push(LintKind::DuplicateActorName(first, actor_id)),
+                None => { names.insert(name, actor_id); },
+            }
+            if self.effective_use_cases(actor_id).is_empty() {
+                kinds.push(LintKind::OrphanActor(actor_id));
+            }
+        }
+
+        let mut use_cases = self.use_cases().collect::<Vec<_>>();
+        use_cases.sort_by_key(|&(use_case_id, _)| use_case_id);
+        let mut titles = HashMap::new();
+        for &(use_case_id, use_case) in &use_cases {
+            let title = use_case.title.as_ref();
+            if title.is_empty() {
+                kinds.push(LintKind::EmptyUseCaseTitle(use_case_id));
+            }
+            match titles.get(title) {
+                Some(&first) => kinds.push(LintKind::DuplicateUseCaseTitle(first, use_case_id)),
+                None => { titles.insert(title, use_case_id); },
+            }
+            let orphan =
+                self.actors_of_use_case(use_case_id).next().is_none()
+                && !self.includes().any(|(_, included)| included == use_case_id)
+                && !self.extends().any(|extend| extend.extension == use_case_id)
+                && self.use_case_parents(use_case_id).next().is_none()
+                && !(use_case.is_abstract &&
+                     self.use_case_generalizations().any(|(_, parent)| parent == use_case_id));
+            if orphan {
+                kinds.push(LintKind::OrphanUseCase(use_case_id));
+            }
+        }
+
+        for (actor_id, use_case_id) in self.associations() {
+            let redundant =
+                self.associations_of_actor(actor_id)
+                .filter(|&(_, other_id)| other_id != use_case_id)
+                .any(|(_, other_id)|
+                     self.transitively_included_use_cases(other_id).contains(&use_case_id));
+            if redundant {
+                kinds.push(LintKind::RedundantAssociation(actor_id, use_case_id));
+            }
+            if self[use_case_id].is_abstract {
+                kinds.push(LintKind::AbstractAssociation(actor_id, use_case_id));
+            }
+            if self[use_case_id].deprecated.is_some() {
+                kinds.push(LintKind::DeprecatedAssociation(actor_id, use_case_id));
+            }
+        }
+
+        let mut lints = kinds.into_iter().map(Lint::new).collect::<Vec<_>>();
+        lints.sort_by(|a, b| b.severity.cmp(&a.severity).then(a.kind.cmp(&b.kind)));
+        lints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use use_case_diagram::{Actor, UseCase};
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_lint() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let g = diagram.insert_actor(Actor::new(Rc::from("Guest"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let bs2 = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_association(a, bs2).unwrap();
+
+        let lints = diagram.lint();
+        assert_eq!(lints, vec![
+            Lint{severity: Severity::Error,
+                 kind: LintKind::DuplicateUseCaseTitle(bs, bs2)},
+            Lint{severity: Severity::Warning,
+                 kind: LintKind::OrphanActor(g)},
+        ]);
+        assert_eq!(lints[1].to_string(),
+                   format!("warning: actor {} is not associated with any use case", g));
+    }
+
+    #[test]
+    fn test_lint_empty_labels() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from(""))).unwrap();
+        let u = diagram.insert_use_case(UseCase::new(Rc::from(""))).unwrap();
+        diagram.insert_association(a, u).unwrap();
+
+        assert_eq!(diagram.lint().into_iter().map(|lint| lint.kind).collect::<Vec<_>>(),
+                   vec![LintKind::EmptyActorName(a), LintKind::EmptyUseCaseTitle(u)]);
+    }
+
+    #[test]
+    fn test_lint_redundant_association() {
+        let mut diagram = UseCaseDiagram::new();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        diagram.insert_association(s, li).unwrap();
+        assert_eq!(diagram.lint(), vec![]);
+
+        let vc = diagram.insert_use_case(UseCase::new(Rc::from("Verify credentials"))).unwrap();
+        diagram.insert_include(li, vc).unwrap();
+        diagram.insert_include(pc, li).unwrap();
+        assert_eq!(diagram.lint(), vec![Lint::new(LintKind::RedundantAssociation(s, li))]);
+    }
+
+    #[test]
+    fn test_lint_abstract_use_case() {
+        let mut diagram = UseCaseDiagram::new();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let mut pay = UseCase::new(Rc::from("Pay"));
+        pay.is_abstract = true;
+        let p = diagram.insert_use_case(pay).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Pay by card"))).unwrap();
+        diagram.insert_use_case_generalization(pc, p).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        assert_eq!(diagram.lint(), vec![]);
+
+        diagram.insert_association(s, p).unwrap();
+        assert_eq!(diagram.lint(), vec![Lint::new(LintKind::AbstractAssociation(s, p))]);
+    }
+
+    #[test]
+    fn test_lint_span() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut guest = Actor::new(Rc::from("Guest"));
+        guest.span = Some(Span{file: Rc::from("journal.uc"), line: 3, column: 1});
+        diagram.insert_actor(guest).unwrap();
+
+        let lints = diagram.lint();
+        assert_eq!(lints.len(), 1);
+        let span = lints[0].span(&diagram).unwrap();
+        assert_eq!(span.to_string(), "journal.uc:3:1");
+    }
+
+    #[test]
+    fn test_lint_deprecated_use_case() {
+        let mut diagram = UseCaseDiagram::new();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let mut fax = UseCase::new(Rc::from("Fax comment"));
+        fax.deprecated = Some(Rc::from("Use Post comment instead."));
+        let f = diagram.insert_use_case(fax).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        diagram.insert_association(s, f).unwrap();
+
+        let lints = diagram.lint();
+        assert_eq!(lints, vec![Lint::new(LintKind::DeprecatedAssociation(s, f))]);
+        assert_eq!(lints[0].to_string(),
+                   format!("warning: actor {} is associated with deprecated use case {}", s, f));
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+
+use use_case_diagram::{Actor, NameError, UseCase, UseCaseDiagram};
+
+impl<S> Actor<S> {
+    /// The name of the actor in the given locale, falling back from a
+    /// regional locale such as `nl-BE` to its language `nl`, and from there
+    /// to `name`.
+    pub fn name_for(&self, locale: &str) -> &S {
+        localize(&self.localized_names, locale).unwrap_or(&self.name)
+    }
+}
+
+impl<S> UseCase<S> {
+    /// The title of the use case in the given locale, falling back like
+    /// `Actor::name_for`.
+    pub fn title_for(&self, locale: &str) -> &S {
+        localize(&self.localized_titles, locale).unwrap_or(&self.title)
+    }
+}
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> + Clone {
+    /// A copy of this diagram with every actor named by `Actor::name_for` and
+    /// every use case titled by `UseCase::title_for` the given locale, for
+    /// generating code or documentation in that language. Return an error if
+    /// this diagram has unique names and two translations coincide.
+    pub fn localized(&self, locale: &str) -> Result<UseCaseDiagram<S>, NameError> {
+        let mut localized = self.clone();
+        let unique_names = localized.unique_names;
+        localized.unique_names = false;
+        for (actor_id, actor) in self.actors() {
+            localized.actor_mut(actor_id).unwrap().name = actor.name_for(locale).clone();
+        }
+        for (use_case_id, use_case) in self.use_cases() {
+            localized.use_case_mut(use_case_id).unwrap().title =
+                use_case.title_for(locale).clone();
+        }
+        localized.unique_names = unique_names;
+        if unique_names {
+            localized.validate_unique_names()?;
+        }
+        Ok(localized)
+    }
+}
+
+fn localize<'a, S>(localizations: &'a BTreeMap<String, S>, locale: &str) -> Option<&'a S> {
+    localizations.get(locale).or_else(|| {
+        let language = locale.split(&['-', '_'][..]).next()?;
+        localizations.get(language)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_localized() {
+        let mut diagram = UseCaseDiagram::with_unique_names();
+        let mut administrator = Actor::new(Rc::from("Administrator"));
+        administrator.localized_names.insert("nl".to_string(), Rc::from("Beheerder"));
+        let a = diagram.insert_actor(administrator).unwrap();
+        let mut ban = UseCase::new(Rc::from("Ban subscriber"));
+        ban.localized_titles.insert("nl".to_string(), Rc::from("Abonnee blokkeren"));
+        ban.localized_titles.insert("nl-BE".to_string(), Rc::from("Abonnee bannen"));
+        let bs = diagram.insert_use_case(ban).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+
+        assert_eq!(diagram[a].name_for("nl_NL").as_ref(), "Beheerder");
+        assert_eq!(diagram[a].name_for("fr").as_ref(), "Administrator");
+        assert_eq!(diagram[bs].title_for("nl-BE").as_ref(), "Abonnee bannen");
+
+        let dutch = diagram.localized("nl").unwrap();
+        assert_eq!(dutch.actor_by_name("Beheerder").map(|(id, _)| id), Some(a));
+        assert_eq!(dutch.use_case_by_title("Abonnee blokkeren").map(|(id, _)| id), Some(bs));
+        assert_eq!(&*dutch[li].title, "Log in");
+        assert_eq!(dutch.use_cases_of_actor(a).collect::<Vec<_>>(), vec![bs]);
+
+        diagram.use_case_mut(li).unwrap().localized_titles
+            .insert("nl".to_string(), Rc::from("Abonnee blokkeren"));
+        assert!(diagram.localized("nl").is_err());
+    }
+}
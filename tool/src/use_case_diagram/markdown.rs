@@ -0,0 +1,262 @@
+//! Association matrices in Markdown, as found in requirements documents.
+//!
+//! ```text
+//! |               | Ban subscriber | Post comment |
+//! |---------------|:--------------:|:------------:|
+//! | Administrator | ✓ bans         |              |
+//! | **Subscriber**|                | ✓            |
+//! ```
+//!
+//! The first pipe table outside code blocks is read, with actors down the
+//! side and use cases across the top as in `format_matrix`; the rest of the
+//! document is skipped. The top left cell is ignored. A cell marked with `✓`,
+//! `✔`, `✅`, `☑`, `x` or `X` associates its actor with its use case, and
+//! any text after the mark and a space labels the association. Cells that
+//! are empty or hold `✗`, `✘`, `❌` or a dash do not. Emphasis and code
+//! spans around names and titles are dropped, and `\|` is a pipe.
+
+use use_case_diagram::parser::{error, Cursor, ParseError, ParseErrorKind, Position};
+use use_case_diagram::source_map::span;
+use use_case_diagram::{Actor, Association, SourceMap, UseCase, UseCaseDiagram};
+
+const MARKS: [&str; 8] = ["✓", "✔\u{fe0f}", "✔", "✅", "☑\u{fe0f}", "☑", "x", "X"];
+const NON_MARKS: [&str; 6] = ["✗", "✘", "❌", "-", "–", "—"];
+
+/// Read the matrix of a Markdown document into a diagram with unique
+/// names. Actors and use cases are inserted in the order of the rows and
+/// columns.
+pub fn from_markdown<S>(text: &str) -> Result<UseCaseDiagram<S>, ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    from_markdown_with_source_map(text, "").map(|(diagram, _)| diagram)
+}
+
+/// Read the matrix of a Markdown document in the file like `from_markdown`,
+/// and map every actor to its row header, every use case to its column
+/// header, and every association to its cell.
+pub fn from_markdown_with_source_map<S>(text: &str, file: &str)
+                                        -> Result<(UseCaseDiagram<S>, SourceMap<S>), ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    let lines = text.lines().collect::<Vec<_>>();
+    let mut fence = None;
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            continue;
+        }
+        let header = cells(index + 1, line);
+        let is_table = line.contains('|') && lines.get(index + 1).is_some_and(|next| {
+            let delimiters = cells(index + 2, next);
+            next.contains('|') && delimiters.len() == header.len() &&
+                delimiters.iter().all(|(_, cell)| is_delimiter(cell))
+        });
+        if is_table {
+            return read_table(header, &lines[index + 2..], index + 3, file);
+        }
+    }
+    Err(error(Position{line: 1, column: 1}, ParseErrorKind::MissingTable))
+}
+
+fn read_table<S>(header: Vec<(Position, String)>, rows: &[&str], first_line_number: usize,
+                 file: &str) -> Result<(UseCaseDiagram<S>, SourceMap<S>), ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    let mut diagram = UseCaseDiagram::with_unique_names();
+    let mut source_map = SourceMap::new();
+    let mut use_case_ids = Vec::new();
+    for (position, cell) in header.into_iter().skip(1) {
+        let title = strip_emphasis(&cell);
+        if title.is_empty() {
+            return Err(error(position, ParseErrorKind::ExpectedName));
+        }
+        let use_case_id = diagram.insert_use_case(UseCase::new(S::from(title)))
+            .map_err(|err| error(position, ParseErrorKind::Name(err)))?;
+        source_map.use_cases.insert(use_case_id, span(file, position));
+        use_case_ids.push(use_case_id);
+    }
+
+    for (index, row) in rows.iter().enumerate() {
+        if row.trim().is_empty() || !row.contains('|') {
+            break;
+        }
+        let cells = cells(first_line_number + index, row);
+        if cells.iter().all(|(_, cell)| cell.is_empty()) {
+            continue;
+        }
+        let (position, ref cell) = cells[0];
+        let name = strip_emphasis(cell);
+        if name.is_empty() {
+            return Err(error(position, ParseErrorKind::ExpectedName));
+        }
+        let actor_id = diagram.insert_actor(Actor::new(S::from(name)))
+            .map_err(|err| error(position, ParseErrorKind::Name(err)))?;
+        source_map.actors.insert(actor_id, span(file, position));
+        for (&use_case_id, &(position, ref cell)) in use_case_ids.iter().zip(&cells[1..]) {
+            let label = match mark(cell) {
+                Some(label) => label,
+                None if cell.is_empty() || NON_MARKS.contains(&cell.as_str()) => continue,
+                None => {
+                    let c = cell.chars().next().unwrap();
+                    return Err(error(position, ParseErrorKind::UnexpectedCharacter(c)));
+                },
+            };
+            let association = Association{label: label.map(S::from), ..Association::default()};
+            diagram.insert_association_with(actor_id, use_case_id, association).unwrap();
+            source_map.associations.insert((actor_id, use_case_id), span(file, position));
+        }
+    }
+    Ok((diagram, source_map))
+}
+
+/// The trimmed cells of a table row, and where they start.
+fn cells(line_number: usize, line: &str) -> Vec<(Position, String)> {
+    let mut cursor = Cursor::new(line_number, line);
+    cursor.skip_whitespace();
+    cursor.eat('|');
+    let mut cells = Vec::new();
+    loop {
+        cursor.skip_whitespace();
+        if cursor.at_end() {
+            return cells;
+        }
+        let position = cursor.position();
+        let mut cell = String::new();
+        while let Some(c) = cursor.peek() {
+            if c == '|' {
+                break;
+            }
+            cursor.bump();
+            if c == '\\' && cursor.eat('|') {
+                cell.push('|');
+            } else {
+                cell.push(c);
+            }
+        }
+        cells.push((position, cell.trim_end().to_string()));
+        if !cursor.eat('|') {
+            return cells;
+        }
+    }
+}
+
+/// Whether the cell is a cell of the row below the header, e.g. `:---:`.
+fn is_delimiter(cell: &str) -> bool {
+    let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+    !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+}
+
+/// The label after the mark if the cell is marked, `None` inside if the
+/// mark has no label.
+fn mark(cell: &str) -> Option<Option<&str>> {
+    let rest = MARKS.iter().find(|&&mark| cell.starts_with(mark))
+        .map(|&mark| &cell[mark.len()..])?;
+    if rest.is_empty() {
+        Some(None)
+    } else if rest.starts_with(char::is_whitespace) {
+        Some(Some(rest.trim()))
+    } else {
+        None
+    }
+}
+
+/// The text without the emphasis or code span around it, if any.
+fn strip_emphasis(mut text: &str) -> &str {
+    text = text.trim();
+    for delimiter in &["**", "__", "*", "_", "`"] {
+        if text.len() > 2 * delimiter.len() && text.starts_with(delimiter) &&
+           text.ends_with(delimiter) {
+            return strip_emphasis(&text[delimiter.len()..text.len() - delimiter.len()]);
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    use use_case_diagram::Span;
+
+    #[test]
+    fn test_from_markdown() {
+        let diagram = from_markdown::<Rc<str>>(concat!(
+            "# Moderation\n",
+            "\n",
+            "```\n",
+            "| Not | A table |\n",
+            "|-----|---------|\n",
+            "```\n",
+            "\n",
+            "| Actor         | Ban subscriber | `Post \\| reply` |\n",
+            "|---------------|:--------------:|:----------------:|\n",
+            "| Administrator | ✓ bans         | ✗                |\n",
+            "| **Subscriber**|                | ✅\n",
+            "|               |                |                  |\n",
+            "| Lurker        | -              |\n",
+            "\n",
+            "| Other | Table |\n",
+            "|-------|-------|\n",
+        )).unwrap();
+        let names = diagram.actors_in_order().iter()
+            .map(|&(_, actor)| actor.name.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["Administrator", "Subscriber", "Lurker"]);
+        let titles = diagram.use_cases_in_order().iter()
+            .map(|&(_, use_case)| use_case.title.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(titles, vec!["Ban subscriber", "Post | reply"]);
+        let (administrator, _) = diagram.actor_by_name("Administrator").unwrap();
+        let (subscriber, _) = diagram.actor_by_name("Subscriber").unwrap();
+        let (ban, _) = diagram.use_case_by_title("Ban subscriber").unwrap();
+        let (post, _) = diagram.use_case_by_title("Post | reply").unwrap();
+        let mut associations = diagram.associations().collect::<Vec<_>>();
+        associations.sort();
+        assert_eq!(associations, vec![(administrator, ban), (subscriber, post)]);
+        assert_eq!(diagram.association(administrator, ban).unwrap().label.as_deref(),
+                   Some("bans"));
+        assert_eq!(diagram.association(subscriber, post).unwrap().label, None);
+    }
+
+    #[test]
+    fn test_from_markdown_with_source_map() {
+        let (diagram, source_map) = from_markdown_with_source_map::<Rc<str>>(concat!(
+            "# Moderation\n",
+            "\n",
+            "|               | Ban subscriber |\n",
+            "|---------------|----------------|\n",
+            "| Administrator | ✓              |\n",
+        ), "moderation.md").unwrap();
+        let (administrator, _) = diagram.actor_by_name("Administrator").unwrap();
+        let (ban, _) = diagram.use_case_by_title("Ban subscriber").unwrap();
+        let span = |line, column| Span{file: Rc::from("moderation.md"), line, column};
+        assert_eq!(source_map.actors[&administrator], span(5, 3));
+        assert_eq!(source_map.use_cases[&ban], span(3, 19));
+        assert_eq!(source_map.associations[&(administrator, ban)], span(5, 19));
+    }
+
+    #[test]
+    fn test_from_markdown_errors() {
+        fn err(text: &str) -> (usize, usize, ParseErrorKind) {
+            let err = from_markdown::<Rc<str>>(text).unwrap_err();
+            (err.position.line, err.position.column, err.kind)
+        }
+        assert_eq!(err("# Nothing\n\n| but | pipes |\n"), (1, 1, ParseErrorKind::MissingTable));
+        assert_eq!(err("| | A |\n|-|-|\n| B | maybe |\n"),
+                   (3, 7, ParseErrorKind::UnexpectedCharacter('m')));
+        assert_eq!(err("| | A |\n|-|-|\n| B | xylophone |\n"),
+                   (3, 7, ParseErrorKind::UnexpectedCharacter('x')));
+        assert_eq!(err("| | A | |\n|-|-|-|\n"), (1, 9, ParseErrorKind::ExpectedName));
+        assert_eq!(err("| | A |\n|-|-|\n| | ✓ |\n"), (3, 3, ParseErrorKind::ExpectedName));
+        match err("| | A | A |\n|-|-|-|\n").2 {
+            ParseErrorKind::Name(_) => (),
+            kind => panic!("unexpected {:?}", kind),
+        }
+    }
+}
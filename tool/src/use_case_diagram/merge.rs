@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use use_case_diagram::{ActorId, Extend, ExtensionPoint, ExtensionPointId, Note, NoteAnchor,
+                       RelationshipError, SystemBoundary, UseCaseDiagram, UseCaseId};
+
+/// What `UseCaseDiagram::merge` does when both diagrams have an element with
+/// different details.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum MergeStrategy {
+    /// Return the conflict as an error.
+    Fail,
+
+    /// Keep the details from the diagram being merged into.
+    PreferSelf,
+
+    /// Take the details from the diagram being merged in.
+    PreferOther,
+}
+
+/// A conflict that prevents two diagrams from being merged. Identifiers
+/// refer to elements of the diagram being merged in, except where noted.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MergeConflict {
+    /// The actors, of the diagram being merged into and the diagram being
+    /// merged in respectively, have the same name but different details. With
+    /// `PreferOther`, also the actors whose details cannot be taken because
+    /// the name of the latter is taken in a diagram with unique names.
+    Actor(ActorId, ActorId),
+
+    /// The use cases, of the diagram being merged into and the diagram being
+    /// merged in respectively, have the same title but different details.
+    /// With `PreferOther`, also the use cases whose details cannot be taken
+    /// because the title of the latter is taken in a diagram with unique
+    /// names.
+    UseCase(UseCaseId, UseCaseId),
+
+    /// Both diagrams associate the actor with the use case, with different
+    /// details.
+    Association(ActorId, UseCaseId),
+
+    /// The generalization from the first to the second actor would close a
+    /// cycle in the merged diagram.
+    ActorCycle(ActorId, ActorId),
+
+    /// The relationship from the first to the second use case would close a
+    /// cycle in the merged diagram.
+    UseCaseCycle(UseCaseId, UseCaseId),
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MergeConflict::Actor(self_id, other_id) =>
+                write!(f, "merge conflict: actors {} and {}", self_id, other_id),
+            MergeConflict::UseCase(self_id, other_id) =>
+                write!(f, "merge conflict: use cases {} and {}", self_id, other_id),
+            MergeConflict::Association(actor_id, use_case_id) =>
+                write!(f, "merge conflict: association between actor {} and use case {}",
+                       actor_id, use_case_id),
+            MergeConflict::ActorCycle(child, parent) =>
+                write!(f, "merge conflict: cycle: actors {} and {}", child, parent),
+            MergeConflict::UseCaseCycle(a, b) =>
+                write!(f, "merge conflict: cycle: use cases {} and {}", a, b),
+        }
+    }
+}
+
+impl Error for MergeConflict {
+    fn description(&self) -> &str {
+        match *self {
+            MergeConflict::Actor(_, _) =>
+                "merge conflict: actors",
+            MergeConflict::UseCase(_, _) =>
+                "merge conflict: use cases",
+            MergeConflict::Association(_, _) =>
+                "merge conflict: association",
+            MergeConflict::ActorCycle(_, _) =>
+                "merge conflict: cycle: actors",
+            MergeConflict::UseCaseCycle(_, _) =>
+                "merge conflict: cycle: use cases",
+        }
+    }
+}
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> + Clone + PartialEq {
+    /// Merge another diagram into a copy of this one. Actors are matched by
+    /// key or else by name, use cases by key or else by title, extension
+    /// points by name within their use case, and system boundaries by name;
+    /// unmatched elements of the other diagram are added with fresh
+    /// identifiers, while this diagram's identifiers are preserved.
+    /// Relationships and notes of the other diagram are added between the
+    /// matched elements. Matched elements with different details are
+    /// resolved according to the strategy. Cycles formed by combining the
+    /// relationships of both diagrams are always conflicts.
+    pub fn merge(&self, other: &UseCaseDiagram<S>, strategy: MergeStrategy)
+                 -> Result<UseCaseDiagram<S>, MergeConflict> {
+        let mut merged = self.clone();
+
+        let mut actor_map = HashMap::new();
+        for (other_id, other_actor) in other.actors() {
+            let matched = match other_actor.key {
+                Some(ref key) => merged.actor_by_key(key.as_ref()),
+                None => None,
+            }.or_else(|| merged.actor_by_name(other_actor.name.as_ref()));
+            let merged_id = match matched {
+                Some((merged_id, merged_actor)) if !merged_actor.eq_ignoring_span(other_actor) => {
+                    match strategy {
+                        MergeStrategy::Fail =>
+                            return Err(MergeConflict::Actor(merged_id, other_id)),
+                        MergeStrategy::PreferSelf => (),
+                        MergeStrategy::PreferOther => {
+                            merged.rename_actor(merged_id, other_actor.name.clone())
+                                .map_err(|_| MergeConflict::Actor(merged_id, other_id))?;
+                            *merged.actor_mut(merged_id).unwrap() = other_actor.clone();
+                        },
+                    }
+                    merged_id
+                },
+                Some((merged_id, _)) => merged_id,
+                None => merged.insert_actor(other_actor.clone()).expect("name is not taken"),
+            };
+            actor_map.insert(other_id, merged_id);
+        }
+
+        let mut use_case_map = HashMap::new();
+        for (other_id, other_use_case) in other.use_cases() {
+            let matched = match other_use_case.key {
+                Some(ref key) => merged.use_case_by_key(key.as_ref()),
+                None => None,
+            }.or_else(|| merged.use_case_by_title(other_use_case.title.as_ref()));
+            let merged_id = match matched {
+                Some((merged_id, merged_use_case))
+                    if !merged_use_case.eq_ignoring_span(other_use_case) => {
+                    match strategy {
+                        MergeStrategy::Fail =>
+                            return Err(MergeConflict::UseCase(merged_id, other_id)),
+                        MergeStrategy::PreferSelf => (),
+                        MergeStrategy::PreferOther => {
+                            merged.retitle_use_case(merged_id, other_use_case.title.clone())
+                                .map_err(|_| MergeConflict::UseCase(merged_id, other_id))?;
+                            *merged.use_case_mut(merged_id).unwrap() = other_use_case.clone();
+                        },
+                    }
+                    merged_id
+                },
+                Some((merged_id, _)) => merged_id,
+                None => {
+                    merged.insert_use_case(other_use_case.clone()).expect("title is not taken")
+                },
+            };
+            use_case_map.insert(other_id, merged_id);
+        }
+
+        let mut extension_point_map = HashMap::<ExtensionPointId, ExtensionPointId>::new();
+        for (other_id, other_extension_point) in other.extension_points() {
+            let use_case_id = use_case_map[&other_extension_point.use_case];
+            let existing =
+                merged.extension_points_of_use_case(use_case_id)
+                .find(|&(_, extension_point)|
+                      extension_point.name.as_ref() == other_extension_point.name.as_ref())
+                .map(|(extension_point_id, _)| extension_point_id);
+            let merged_id = match existing {
+                Some(merged_id) => merged_id,
+                None => {
+                    let extension_point = ExtensionPoint{
+                        use_case: use_case_id,
+                        name: other_extension_point.name.clone(),
+                    };
+                    merged.insert_extension_point(extension_point).unwrap()
+                },
+            };
+            extension_point_map.insert(other_id, merged_id);
+        }
+
+        for (other_actor_id, other_use_case_id) in other.associations() {
+            let other_association = other.association(other_actor_id, other_use_case_id).unwrap();
+            let actor_id = actor_map[&other_actor_id];
+            let use_case_id = use_case_map[&other_use_case_id];
+            match merged.association(actor_id, use_case_id) {
+                Some(association) if !association.eq_ignoring_span(other_association) => {
+                    match strategy {
+                        MergeStrategy::Fail =>
+                            return Err(MergeConflict::Association(other_actor_id,
+                                                                  other_use_case_id)),
+                        MergeStrategy::PreferSelf => continue,
+                        MergeStrategy::PreferOther => (),
+                    }
+                },
+                Some(_) => continue,
+                None => (),
+            }
+            merged.insert_association_with(actor_id, use_case_id, other_association.clone())
+                .unwrap();
+        }
+
+        for (other_child, other_parent) in other.actor_generalizations() {
+            merged.insert_actor_generalization(actor_map[&other_child], actor_map[&other_parent])
+                .map_err(|err| cycle_conflict(err, other_child, other_parent))?;
+        }
+        for (other_child, other_parent) in other.use_case_generalizations() {
+            merged.insert_use_case_generalization(use_case_map[&other_child],
+                                                  use_case_map[&other_parent])
+                .map_err(|err| use_case_cycle_conflict(err, other_child, other_parent))?;
+        }
+        for (other_base, other_included) in other.includes() {
+            merged.insert_include(use_case_map[&other_base], use_case_map[&other_included])
+                .map_err(|err| use_case_cycle_conflict(err, other_base, other_included))?;
+        }
+        for (other_dependent, other_dependency) in other.dependencies() {
+            let label = other.dependency_label(other_dependent, other_dependency).cloned();
+            merged.insert_dependency(use_case_map[&other_dependent],
+                                     use_case_map[&other_dependency], label)
+                .map_err(|err| use_case_cycle_conflict(err, other_dependent, other_dependency))?;
+        }
+        for other_extend in other.extends() {
+            let extend = Extend{
+                extension: use_case_map[&other_extend.extension],
+                base: use_case_map[&other_extend.base],
+                extension_point: other_extend.extension_point.map(|id| extension_point_map[&id]),
+            };
+            merged.insert_extend(extend)
+                .map_err(|err| use_case_cycle_conflict(err, other_extend.extension,
+                                                       other_extend.base))?;
+        }
+
+        for (_, other_system_boundary) in other.system_boundaries() {
+            let use_case_ids = other_system_boundary.use_cases.iter().map(|id| use_case_map[id]);
+            let existing =
+                merged.system_boundaries()
+                .filter(|&(_, system_boundary)|
+                        system_boundary.name.as_ref() == other_system_boundary.name.as_ref())
+                .map(|(system_boundary_id, _)| system_boundary_id)
+                .min();
+            match existing {
+                Some(system_boundary_id) =>
+                    for use_case_id in use_case_ids {
+                        merged.insert_system_boundary_use_case(system_boundary_id, use_case_id)
+                            .unwrap();
+                    },
+                None => {
+                    let system_boundary = SystemBoundary{
+                        name: other_system_boundary.name.clone(),
+                        use_cases: use_case_ids.collect(),
+                    };
+                    merged.insert_system_boundary(system_boundary).unwrap();
+                },
+            }
+        }
+
+        for (_, other_note) in other.notes() {
+            let anchor = match other_note.anchor {
+                NoteAnchor::Actor(actor_id) => NoteAnchor::Actor(actor_map[&actor_id]),
+                NoteAnchor::UseCase(use_case_id) => NoteAnchor::UseCase(use_case_map[&use_case_id]),
+                NoteAnchor::Association(actor_id, use_case_id) =>
+                    NoteAnchor::Association(actor_map[&actor_id], use_case_map[&use_case_id]),
+            };
+            let duplicate =
+                merged.notes_of(anchor)
+                .any(|(_, note)| note.text.as_ref() == other_note.text.as_ref());
+            if !duplicate {
+                merged.insert_note(Note{anchor, text: other_note.text.clone()}).unwrap();
+            }
+        }
+
+        Ok(merged)
+    }
+}
+
+fn cycle_conflict(err: RelationshipError, child: ActorId, parent: ActorId) -> MergeConflict {
+    match err {
+        RelationshipError::ActorCycle(_, _) => MergeConflict::ActorCycle(child, parent),
+        _ => unreachable!("merged relationship refers to existing elements"),
+    }
+}
+
+fn use_case_cycle_conflict(err: RelationshipError, a: UseCaseId, b: UseCaseId)
+                           -> MergeConflict {
+    match err {
+        RelationshipError::UseCaseCycle(_, _) => MergeConflict::UseCaseCycle(a, b),
+        _ => unreachable!("merged relationship refers to existing elements"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use use_case_diagram::{Actor, UseCase};
+
+    use std::collections::HashSet;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_merge() {
+        let mut left = UseCaseDiagram::new();
+        let a = left.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let bs = left.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        left.insert_association(a, bs).unwrap();
+
+        let mut right = UseCaseDiagram::new();
+        let s = right.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let a2 = right.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let pc = right.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let bs2 = right.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        right.insert_association(s, pc).unwrap();
+        right.insert_association(a2, bs2).unwrap();
+        right.insert_include(bs2, pc).unwrap();
+        right.insert_note(Note{anchor: NoteAnchor::UseCase(pc), text: Rc::from("Moderated")})
+            .unwrap();
+
+        let merged = left.merge(&right, MergeStrategy::Fail).unwrap();
+        assert_eq!(merged.actors().count(), 2);
+        assert_eq!(merged.use_cases().count(), 2);
+        let (merged_s, _) = merged.actor_by_name("Subscriber").unwrap();
+        let (merged_pc, _) = merged.use_case_by_title("Post comment").unwrap();
+        assert_eq!(merged.actor_by_name("Administrator").map(|(id, _)| id), Some(a));
+        assert_eq!(merged.associations().collect::<HashSet<_>>(),
+                   vec![(a, bs), (merged_s, merged_pc)].into_iter().collect());
+        assert_eq!(merged.includes().collect::<Vec<_>>(), vec![(bs, merged_pc)]);
+        assert_eq!(merged.notes_of(NoteAnchor::UseCase(merged_pc)).count(), 1);
+    }
+
+    #[test]
+    fn test_merge_conflicts() {
+        let mut left = UseCaseDiagram::new();
+        let a = left.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let bs = left.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = left.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        left.insert_include(bs, pc).unwrap();
+
+        let mut right = UseCaseDiagram::new();
+        let mut administrator = Actor::new(Rc::from("Administrator"));
+        administrator.description = Some(Rc::from("Runs the site"));
+        let a2 = right.insert_actor(administrator).unwrap();
+        let pc2 = right.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let bs2 = right.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+
+        assert_eq!(left.merge(&right, MergeStrategy::Fail).err(),
+                   Some(MergeConflict::Actor(a, a2)));
+        let kept = left.merge(&right, MergeStrategy::PreferSelf).unwrap();
+        assert_eq!(kept.actor(a).unwrap().description, None);
+        let taken = left.merge(&right, MergeStrategy::PreferOther).unwrap();
+        assert_eq!(taken.actor(a).unwrap().description.as_ref().map(|d| d.as_ref()),
+                   Some("Runs the site"));
+
+        right.insert_include(pc2, bs2).unwrap();
+        assert_eq!(left.merge(&right, MergeStrategy::PreferSelf).err(),
+                   Some(MergeConflict::UseCaseCycle(pc2, bs2)));
+    }
+
+    #[test]
+    fn test_merge_by_key() {
+        let mut left = UseCaseDiagram::new();
+        let mut administrator = Actor::new(Rc::from("Admin"));
+        administrator.key = Some(Rc::from("9f0c"));
+        let a = left.insert_actor(administrator).unwrap();
+
+        let mut right = UseCaseDiagram::new();
+        let mut administrator = Actor::new(Rc::from("Administrator"));
+        administrator.key = Some(Rc::from("9f0c"));
+        right.insert_actor(administrator).unwrap();
+
+        let merged = left.merge(&right, MergeStrategy::PreferOther).unwrap();
+        assert_eq!(merged.actors().count(), 1);
+        assert_eq!(&*merged.actor(a).unwrap().name, "Administrator");
+        assert_eq!(merged.actor_by_key("9f0c").map(|(id, _)| id), Some(a));
+    }
+
+    #[test]
+    fn test_merge_by_key_name_taken() {
+        let mut left = UseCaseDiagram::with_unique_names();
+        let mut admin = Actor::new(Rc::from("Admin"));
+        admin.key = Some(Rc::from("9f0c"));
+        let a = left.insert_actor(admin).unwrap();
+        left.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+
+        let mut right = UseCaseDiagram::new();
+        let mut administrator = Actor::new(Rc::from("Administrator"));
+        administrator.key = Some(Rc::from("9f0c"));
+        let a2 = right.insert_actor(administrator).unwrap();
+
+        assert_eq!(left.merge(&right, MergeStrategy::PreferOther).err(),
+                   Some(MergeConflict::Actor(a, a2)));
+    }
+}
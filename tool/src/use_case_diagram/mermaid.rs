@@ -0,0 +1,626 @@
+//! Mermaid flowcharts, which render in GitHub and GitLab markdown.
+//!
+//! Actors are rectangles with identifiers starting with `actor_`, use cases
+//! are stadiums with identifiers starting with `use_case_`, and system
+//! boundaries are subgraphs. Associations are plain lines, labelled if the
+//! association is. Includes, extends and dependencies are dotted arrows
+//! labelled `«include»`, `«extend»` and `«depends on»`, and generalizations
+//! are thick arrows towards the parent. Importing reads charts written this
+//! way, and hand-written charts that follow the same conventions.
+
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+
+use use_case_diagram::code_generation::generate_note_comments;
+use use_case_diagram::parser::{error, relate, Arrow, Cursor, Element, ParseError,
+                               ParseErrorKind, Position};
+use use_case_diagram::source_map::span;
+use use_case_diagram::{Actor, SourceMap, SystemBoundary, UseCase, UseCaseDiagram, UseCaseId};
+
+/// Write the diagram as a left-to-right Mermaid flowchart. Actors and use
+/// cases are declared in the order of `actors_in_order` and
+/// `use_cases_in_order`, use cases inside the subgraph of the first system
+/// boundary that contains them, and their stereotypes are shown above their
+/// names. Notes are written as comments.
+pub fn to_mermaid<W, S>(diagram: &UseCaseDiagram<S>, w: &mut W) -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    writeln!(w, "flowchart LR")?;
+    generate_note_comments(w, diagram, "  %%")?;
+    for (actor_id, actor) in diagram.actors_in_order() {
+        writeln!(w, "  actor_{}[{}]", actor_id,
+                 label(&actor.stereotypes, actor.name.as_ref()))?;
+    }
+    let mut system_boundaries = diagram.system_boundaries().collect::<Vec<_>>();
+    system_boundaries.sort_by_key(|&(system_boundary_id, _)| system_boundary_id);
+    let use_cases = diagram.use_cases_in_order();
+    let mut declared = BTreeSet::new();
+    for (system_boundary_id, system_boundary) in system_boundaries {
+        writeln!(w, "  subgraph system_boundary_{}[{}]", system_boundary_id,
+                 quote(system_boundary.name.as_ref()))?;
+        for &(use_case_id, use_case) in &use_cases {
+            if system_boundary.use_cases.contains(&use_case_id) && declared.insert(use_case_id) {
+                generate_use_case(w, use_case_id, use_case, "    ")?;
+            }
+        }
+        writeln!(w, "  end")?;
+    }
+    for &(use_case_id, use_case) in &use_cases {
+        if !declared.contains(&use_case_id) {
+            generate_use_case(w, use_case_id, use_case, "  ")?;
+        }
+    }
+    let mut associations = diagram.associations().collect::<Vec<_>>();
+    associations.sort();
+    for (actor_id, use_case_id) in associations {
+        match diagram.association(actor_id, use_case_id).unwrap().label {
+            Some(ref label) =>
+                writeln!(w, "  actor_{} ---|{}| use_case_{}", actor_id, quote(label.as_ref()),
+                         use_case_id)?,
+            None => writeln!(w, "  actor_{} --- use_case_{}", actor_id, use_case_id)?,
+        }
+    }
+    let mut includes = diagram.includes().collect::<Vec<_>>();
+    includes.sort();
+    for (base, included) in includes {
+        writeln!(w, "  use_case_{} -.->|\"«include»\"| use_case_{}", base, included)?;
+    }
+    let mut extends = diagram.extends().collect::<Vec<_>>();
+    extends.sort();
+    for extend in extends {
+        writeln!(w, "  use_case_{} -.->|\"«extend»\"| use_case_{}",
+                 extend.extension, extend.base)?;
+    }
+    let mut dependencies = diagram.dependencies().collect::<Vec<_>>();
+    dependencies.sort();
+    for (dependent, dependency) in dependencies {
+        let label = match diagram.dependency_label(dependent, dependency) {
+            Some(label) => format!("«depends on» {}", label.as_ref()),
+            None => "«depends on»".to_string(),
+        };
+        writeln!(w, "  use_case_{} -.->|{}| use_case_{}", dependent, quote(&label),
+                 dependency)?;
+    }
+    let mut generalizations = diagram.actor_generalizations().collect::<Vec<_>>();
+    generalizations.sort();
+    for (child, parent) in generalizations {
+        writeln!(w, "  actor_{} ==> actor_{}", child, parent)?;
+    }
+    let mut generalizations = diagram.use_case_generalizations().collect::<Vec<_>>();
+    generalizations.sort();
+    for (child, parent) in generalizations {
+        writeln!(w, "  use_case_{} ==> use_case_{}", child, parent)?;
+    }
+    Ok(())
+}
+
+/// Write the given layer of the diagram as a Mermaid flowchart, like
+/// `to_mermaid`, with the layers in the given order; see
+/// `UseCaseDiagram::layer_subdiagram`.
+pub fn to_mermaid_in_layer<W, S, L>(diagram: &UseCaseDiagram<S>, order: &[L], layer: &str,
+                                    w: &mut W) -> io::Result<()>
+    where W: io::Write, S: AsRef<str> + Clone, L: AsRef<str> {
+    to_mermaid(&diagram.layer_subdiagram(order, layer), w)
+}
+
+/// Parse a Mermaid flowchart into a diagram with unique names. The chart
+/// must start with `flowchart` or `graph`. Nodes shaped as rectangles are
+/// actors and nodes shaped as stadiums, circles or rounded rectangles are use
+/// cases; nodes that are never given a shape are actors if their identifiers
+/// start with `actor` and use cases if they start with `use_case` or `uc`.
+/// Labels default to identifiers, and stereotypes in guillemets on a line of
+/// their own above the label are kept. Subgraphs are system boundaries
+/// containing the use cases declared on lines of their own inside them.
+/// Edges are related as `to_mermaid` writes them, with thick arrows as
+/// generalizations; they must have their labels between bars. Styling is
+/// skipped.
+pub fn from_mermaid<S>(text: &str) -> Result<UseCaseDiagram<S>, ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    from_mermaid_with_source_map(text, "").map(|(diagram, _)| diagram)
+}
+
+/// Parse a Mermaid flowchart in the file like `from_mermaid`, and map every
+/// actor and use case to where its node is first mentioned, and every
+/// association to the start of its first edge.
+pub fn from_mermaid_with_source_map<S>(text: &str, file: &str)
+                                       -> Result<(UseCaseDiagram<S>, SourceMap<S>), ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    let mut nodes = Nodes{nodes: Vec::new(), indices: HashMap::new()};
+    let mut subgraphs = Vec::<Subgraph>::new();
+    let mut open = Vec::new();
+    let mut edges = Vec::new();
+    let mut header = false;
+    for (index, line) in text.lines().enumerate() {
+        let mut cursor = Cursor::new(index + 1, line);
+        cursor.skip_whitespace();
+        if cursor.at_end() || cursor.rest().starts_with("%%") {
+            continue;
+        }
+        let start = cursor.position();
+        let keyword = cursor.peek_word();
+        if !header {
+            if keyword != "flowchart" && keyword != "graph" {
+                return Err(error(start, ParseErrorKind::UnsupportedStatement));
+            }
+            header = true;
+            continue;
+        }
+        match keyword {
+            "classDef" | "class" | "style" | "linkStyle" | "click" | "direction" => continue,
+            "subgraph" => {
+                cursor.word();
+                cursor.skip_whitespace();
+                let id_position = cursor.position();
+                let id = cursor.word();
+                if id.is_empty() {
+                    return Err(error(id_position, ParseErrorKind::ExpectedName));
+                }
+                let name = if cursor.eat('[') {
+                    parse_label(&mut cursor, "]", id_position)?
+                } else {
+                    id.to_string()
+                };
+                cursor.expect_end()?;
+                subgraphs.push(Subgraph{name, position: start, nodes: Vec::new()});
+                open.push(subgraphs.len() - 1);
+            },
+            "end" => {
+                cursor.word();
+                cursor.expect_end()?;
+                if open.pop().is_none() {
+                    return Err(error(start, ParseErrorKind::UnbalancedBrace));
+                }
+            },
+            _ => {
+                let left = nodes.parse(&mut cursor)?;
+                cursor.skip_whitespace();
+                if cursor.at_end() {
+                    if let Some(&subgraph) = open.last() {
+                        subgraphs[subgraph].nodes.push(left);
+                    }
+                    continue;
+                }
+                let arrow = match cursor.mermaid_arrow() {
+                    Some(arrow) => arrow,
+                    None => return Err(error(cursor.position(), ParseErrorKind::ExpectedEndOfLine)),
+                };
+                cursor.skip_whitespace();
+                let label_position = cursor.position();
+                let edge_label = if cursor.eat('|') {
+                    Some(parse_label(&mut cursor, "|", label_position)?)
+                } else {
+                    None
+                };
+                cursor.skip_whitespace();
+                let right_position = cursor.position();
+                let right = nodes.parse(&mut cursor)?;
+                cursor.expect_end()?;
+                edges.push((left, arrow, edge_label, right, start, right_position));
+            },
+        }
+    }
+    if let Some(&subgraph) = open.last() {
+        return Err(error(subgraphs[subgraph].position, ParseErrorKind::UnterminatedBlock));
+    }
+
+    let mut diagram = UseCaseDiagram::with_unique_names();
+    let mut source_map = SourceMap::new();
+    let mut elements = Vec::with_capacity(nodes.nodes.len());
+    for node in &nodes.nodes {
+        let kind = match node.kind {
+            Some(kind) => kind,
+            None if node.id.starts_with("actor") => NodeKind::Actor,
+            None if node.id.starts_with("use_case") || node.id.starts_with("uc") =>
+                NodeKind::UseCase,
+            None =>
+                return Err(error(node.position, ParseErrorKind::UntaggedNode(node.id.clone()))),
+        };
+        let (stereotypes, name) = split_label(node.label.as_ref().unwrap_or(&node.id));
+        let name_error = |err| error(node.position, ParseErrorKind::Name(err));
+        let element = match kind {
+            NodeKind::Actor => {
+                let mut actor = Actor::new(S::from(name));
+                actor.stereotypes = stereotypes.into_iter().map(S::from).collect();
+                let actor_id = diagram.insert_actor(actor).map_err(name_error)?;
+                source_map.actors.insert(actor_id, span(file, node.position));
+                Element::Actor(actor_id)
+            },
+            NodeKind::UseCase => {
+                let mut use_case = UseCase::new(S::from(name));
+                use_case.stereotypes = stereotypes.into_iter().map(S::from).collect();
+                let use_case_id = diagram.insert_use_case(use_case).map_err(name_error)?;
+                source_map.use_cases.insert(use_case_id, span(file, node.position));
+                Element::UseCase(use_case_id)
+            },
+        };
+        elements.push(element);
+    }
+    for subgraph in subgraphs {
+        let use_cases = subgraph.nodes.iter()
+            .filter_map(|&node| match elements[node] {
+                Element::UseCase(use_case_id) => Some(use_case_id),
+                Element::Actor(_) => None,
+            })
+            .collect();
+        diagram.insert_system_boundary(SystemBoundary{name: S::from(&subgraph.name), use_cases})
+            .expect("system boundary of existing use cases");
+    }
+    for (left, arrow, label, right, start, right_position) in edges {
+        relate(&mut diagram, elements[left], arrow, elements[right], label.as_deref(), start,
+               right_position)?;
+        if let (Element::Actor(actor_id), Element::UseCase(use_case_id)) |
+               (Element::UseCase(use_case_id), Element::Actor(actor_id)) =
+               (elements[left], elements[right]) {
+            if diagram.association(actor_id, use_case_id).is_some() {
+                source_map.associations.entry((actor_id, use_case_id))
+                    .or_insert_with(|| span(file, start));
+            }
+        }
+    }
+    Ok((diagram, source_map))
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum NodeKind {
+    Actor,
+    UseCase,
+}
+
+struct Node {
+    id: String,
+    kind: Option<NodeKind>,
+    label: Option<String>,
+    position: Position,
+}
+
+/// The nodes of a chart in order of appearance, by identifier.
+struct Nodes {
+    nodes: Vec<Node>,
+    indices: HashMap<String, usize>,
+}
+
+impl Nodes {
+    /// Parse a node identifier with an optional shape and label, returning
+    /// the index of the node. The first shape and label given to a node are
+    /// kept.
+    fn parse(&mut self, cursor: &mut Cursor) -> Result<usize, ParseError> {
+        let position = cursor.position();
+        let id = cursor.word();
+        if id.is_empty() {
+            return Err(error(position, ParseErrorKind::ExpectedName));
+        }
+        let nodes = &mut self.nodes;
+        let index = *self.indices.entry(id.to_string()).or_insert_with(|| {
+            nodes.push(Node{id: id.to_string(), kind: None, label: None, position});
+            nodes.len() - 1
+        });
+        let rest = cursor.rest();
+        let shape = if rest.starts_with("([") {
+            Some((NodeKind::UseCase, "([", "])"))
+        } else if rest.starts_with("((") {
+            Some((NodeKind::UseCase, "((", "))"))
+        } else if rest.starts_with('(') {
+            Some((NodeKind::UseCase, "(", ")"))
+        } else if rest.starts_with('[') {
+            Some((NodeKind::Actor, "[", "]"))
+        } else {
+            None
+        };
+        if let Some((kind, open, close)) = shape {
+            let label_position = cursor.position();
+            cursor.offset += open.len();
+            let text = parse_label(cursor, close, label_position)?;
+            let node = &mut self.nodes[index];
+            node.kind = node.kind.or(Some(kind));
+            node.label = node.label.take().or(Some(text));
+        }
+        Ok(index)
+    }
+}
+
+struct Subgraph {
+    name: String,
+    position: Position,
+    nodes: Vec<usize>,
+}
+
+impl<'a> Cursor<'a> {
+    /// An arrow such as `---`, `-->`, `-.->` or `==>`, if there is one.
+    fn mermaid_arrow(&mut self) -> Option<Arrow> {
+        let rest = self.rest();
+        let end = rest.find(|c| !"-.=<>".contains(c)).unwrap_or(rest.len());
+        let arrow = &rest[..end];
+        if arrow.len() < 2 || !arrow.contains(['-', '=']) {
+            return None;
+        }
+        self.offset += end;
+        Some(Arrow{
+            left_head: arrow.starts_with('<'),
+            right_head: arrow.ends_with('>'),
+            generalization: arrow.contains('='),
+        })
+    }
+}
+
+/// A label up to the closing delimiter, quoted or not, decoded.
+fn parse_label(cursor: &mut Cursor, close: &str, start: Position)
+               -> Result<String, ParseError> {
+    let rest = cursor.rest();
+    let (raw, length) = if let Some(quoted) = rest.strip_prefix('"') {
+        match quoted.find('"') {
+            Some(end) if quoted[end + 1..].starts_with(close) =>
+                (&quoted[..end], end + 2 + close.len()),
+            _ => return Err(error(start, ParseErrorKind::UnterminatedString)),
+        }
+    } else {
+        match rest.find(close) {
+            Some(end) => (rest[..end].trim(), end + close.len()),
+            None => return Err(error(start, ParseErrorKind::UnterminatedString)),
+        }
+    };
+    cursor.offset += length;
+    Ok(decode(raw))
+}
+
+/// Replace entity codes and `<br>` line breaks in a label.
+fn decode(raw: &str) -> String {
+    let raw = raw.replace("<br>", "\n").replace("<br/>", "\n").replace("<br />", "\n");
+    let mut decoded = String::with_capacity(raw.len());
+    let mut rest = &raw[..];
+    while let Some(start) = rest.find('#') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..].find(';').map(|end| &rest[1..end + 1]);
+        let c = match entity {
+            Some("quot") => Some('"'),
+            Some("amp") => Some('&'),
+            Some("lt") => Some('<'),
+            Some("gt") => Some('>'),
+            Some(code) => code.parse().ok().and_then(char::from_u32),
+            None => None,
+        };
+        match (c, entity) {
+            (Some(c), Some(entity)) => {
+                decoded.push(c);
+                rest = &rest[entity.len() + 2..];
+            },
+            _ => {
+                decoded.push('#');
+                rest = &rest[1..];
+            },
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// The stereotypes on the first line of a label, if it is in guillemets, and
+/// the rest of the label.
+fn split_label(label: &str) -> (Vec<&str>, &str) {
+    if let Some(stereotyped) = label.strip_prefix('«') {
+        if let Some(end) = stereotyped.find("»\n") {
+            let stereotypes = stereotyped[..end].split(',').map(str::trim).collect();
+            return (stereotypes, &stereotyped[end + "»\n".len()..]);
+        }
+    }
+    (Vec::new(), label)
+}
+
+fn generate_use_case<W, S>(w: &mut W, use_case_id: UseCaseId, use_case: &UseCase<S>,
+                           indent: &str) -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    writeln!(w, "{}use_case_{}([{}])", indent, use_case_id,
+             label(&use_case.stereotypes, use_case.title.as_ref()))
+}
+
+fn label<S>(stereotypes: &[S], name: &str) -> String where S: AsRef<str> {
+    if stereotypes.is_empty() {
+        return quote(name);
+    }
+    let stereotypes = stereotypes.iter().map(|s| s.as_ref()).collect::<Vec<_>>();
+    quote(&format!("«{}»\n{}", stereotypes.join(", "), name))
+}
+
+/// Quote a string as a Mermaid label, with entity codes for double quotes
+/// and `<br>` for line breaks.
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("#quot;"),
+            '#' => quoted.push_str("#35;"),
+            '\n' => quoted.push_str("<br>"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    use use_case_diagram::{Association, Extend, Note, NoteAnchor, Span};
+    use use_case_diagram::parser::ParseErrorKind;
+
+    #[test]
+    fn test_to_mermaid() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut administrator = Actor::new(Rc::from("Administrator"));
+        administrator.stereotypes.push(Rc::from("human"));
+        let a = diagram.insert_actor(administrator).unwrap();
+        let m = diagram.insert_actor(Actor::new(Rc::from("Moderator \"#1\""))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        let n = diagram.insert_use_case(UseCase::new(Rc::from("Notify"))).unwrap();
+        diagram.insert_association_with(a, bs, Association{
+            label: Some(Rc::from("bans")),
+            ..Association::default()
+        }).unwrap();
+        diagram.insert_association(m, bs).unwrap();
+        diagram.insert_include(bs, li).unwrap();
+        diagram.insert_extend(Extend{extension: n, base: bs, extension_point: None}).unwrap();
+        diagram.insert_dependency(n, li, None).unwrap();
+        diagram.insert_actor_generalization(m, a).unwrap();
+        diagram.insert_system_boundary(SystemBoundary{
+            name: Rc::from("Forum"),
+            use_cases: vec![bs, n].into_iter().collect(),
+        }).unwrap();
+        diagram.insert_note(Note{anchor: NoteAnchor::UseCase(li), text: Rc::from("Required")})
+            .unwrap();
+
+        let mut output = Vec::new();
+        to_mermaid(&diagram, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), concat!(
+            "flowchart LR\n",
+            "  %% use case \"Log in\": Required\n",
+            "  actor_0[\"«human»<br>Administrator\"]\n",
+            "  actor_1[\"Moderator #quot;#35;1#quot;\"]\n",
+            "  subgraph system_boundary_0[\"Forum\"]\n",
+            "    use_case_0([\"Ban subscriber\"])\n",
+            "    use_case_2([\"Notify\"])\n",
+            "  end\n",
+            "  use_case_1([\"Log in\"])\n",
+            "  actor_0 ---|\"bans\"| use_case_0\n",
+            "  actor_1 --- use_case_0\n",
+            "  use_case_0 -.->|\"«include»\"| use_case_1\n",
+            "  use_case_2 -.->|\"«extend»\"| use_case_0\n",
+            "  use_case_2 -.->|\"«depends on»\"| use_case_1\n",
+            "  actor_1 ==> actor_0\n",
+        ));
+    }
+
+    #[test]
+    fn test_to_mermaid_in_layer() {
+        let mut diagram = UseCaseDiagram::new();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let mut moderator = Actor::new(Rc::from("Moderator"));
+        moderator.layers.push(Rc::from("Phase 2"));
+        let m = diagram.insert_actor(moderator).unwrap();
+        let mut post = UseCase::new(Rc::from("Post comment"));
+        post.layers.push(Rc::from("MVP"));
+        let pc = diagram.insert_use_case(post).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        diagram.insert_association(m, pc).unwrap();
+
+        let order = ["MVP", "Phase 2"];
+        let mut output = Vec::new();
+        to_mermaid_in_layer(&diagram, &order, "MVP", &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), concat!(
+            "flowchart LR\n",
+            "  actor_0[\"Subscriber\"]\n",
+            "  use_case_0([\"Post comment\"])\n",
+            "  actor_0 --- use_case_0\n",
+        ));
+        let mut output = Vec::new();
+        to_mermaid_in_layer(&diagram, &order, "Phase 2", &mut output).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("  actor_1 --- use_case_0\n"));
+    }
+
+    #[test]
+    fn test_from_mermaid() {
+        let diagram = from_mermaid::<Rc<str>>(concat!(
+            "%% Forum\n",
+            "graph TD\n",
+            "  classDef deprecated stroke-dasharray: 3 3\n",
+            "  admin[\"«human»<br>Site #quot;admin#quot;\"]\n",
+            "  subgraph forum[Forum]\n",
+            "    ban([Ban subscriber])\n",
+            "  end\n",
+            "  admin --> ban\n",
+            "  actor_moderator ---|\"bans #35;1\"| ban\n",
+            "  ban -.->|«include»| login((Log in))\n",
+            "  uc_notify -.->|\"«extend»\"| ban\n",
+            "  actor_moderator ==> admin\n",
+            "  class ban deprecated\n",
+        )).unwrap();
+        let (a, admin) = diagram.actor_by_name("Site \"admin\"").unwrap();
+        let (m, _) = diagram.actor_by_name("actor_moderator").unwrap();
+        let (bs, _) = diagram.use_case_by_title("Ban subscriber").unwrap();
+        let (li, _) = diagram.use_case_by_title("Log in").unwrap();
+        let (n, _) = diagram.use_case_by_title("uc_notify").unwrap();
+        assert!(admin.has_stereotype("human"));
+        assert!(diagram.association(a, bs).is_some());
+        assert_eq!(diagram.association(m, bs).unwrap().label.as_ref().map(|l| l.as_ref()),
+                   Some("bans #1"));
+        assert_eq!(diagram.includes().collect::<Vec<_>>(), vec![(bs, li)]);
+        assert_eq!(diagram.extends().collect::<Vec<_>>(),
+                   vec![Extend{extension: n, base: bs, extension_point: None}]);
+        assert_eq!(diagram.actor_generalizations().collect::<Vec<_>>(), vec![(m, a)]);
+        let (_, forum) = diagram.system_boundaries().next().unwrap();
+        assert_eq!(forum.name.as_ref(), "Forum");
+        assert_eq!(forum.use_cases.iter().cloned().collect::<Vec<_>>(), vec![bs]);
+    }
+
+    #[test]
+    fn test_from_mermaid_with_source_map() {
+        let (diagram, source_map) = from_mermaid_with_source_map::<Rc<str>>(concat!(
+            "flowchart LR\n",
+            "  actor_0[\"Guest\"] --- uc_eat([\"Eat\"])\n",
+            "  uc_eat --- actor_1[\"Chef\"]\n",
+            "  actor_0 --- uc_eat\n",
+        ), "restaurant.mmd").unwrap();
+        let (guest, _) = diagram.actor_by_name("Guest").unwrap();
+        let (chef, _) = diagram.actor_by_name("Chef").unwrap();
+        let (eat, _) = diagram.use_case_by_title("Eat").unwrap();
+        let span = |line, column| Span{file: Rc::from("restaurant.mmd"), line, column};
+        assert_eq!(source_map.actors[&guest], span(2, 3));
+        assert_eq!(source_map.actors[&chef], span(3, 14));
+        assert_eq!(source_map.use_cases[&eat], span(2, 24));
+        assert_eq!(source_map.associations[&(guest, eat)], span(2, 3));
+        assert_eq!(source_map.associations[&(chef, eat)], span(3, 3));
+    }
+
+    #[test]
+    fn test_mermaid_round_trip() {
+        let mut diagram = UseCaseDiagram::<Rc<str>>::new();
+        let mut administrator = Actor::new(Rc::from("Administrator"));
+        administrator.stereotypes.push(Rc::from("human"));
+        let a = diagram.insert_actor(administrator).unwrap();
+        let moderator = Actor::new(Rc::from("Moderator \"#1\"\nof the forum"));
+        let m = diagram.insert_actor(moderator).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        let n = diagram.insert_use_case(UseCase::new(Rc::from("Notify"))).unwrap();
+        diagram.insert_association_with(a, bs, Association{
+            label: Some(Rc::from("bans")),
+            ..Association::default()
+        }).unwrap();
+        diagram.insert_association(m, li).unwrap();
+        diagram.insert_include(bs, li).unwrap();
+        diagram.insert_extend(Extend{extension: n, base: bs, extension_point: None}).unwrap();
+        diagram.insert_dependency(n, li, Some(Rc::from("session"))).unwrap();
+        diagram.insert_use_case_generalization(n, li).unwrap();
+        diagram.insert_system_boundary(SystemBoundary{
+            name: Rc::from("Forum"),
+            use_cases: vec![bs, n].into_iter().collect(),
+        }).unwrap();
+        let mut output = Vec::new();
+        to_mermaid(&diagram, &mut output).unwrap();
+        let imported = from_mermaid::<Rc<str>>(&String::from_utf8(output).unwrap()).unwrap();
+        assert!(imported.equivalent(&diagram));
+    }
+
+    #[test]
+    fn test_from_mermaid_errors() {
+        fn err(text: &str) -> (usize, usize, ParseErrorKind) {
+            let err = from_mermaid::<Rc<str>>(text).unwrap_err();
+            (err.position.line, err.position.column, err.kind)
+        }
+        use self::ParseErrorKind::*;
+        assert_eq!(err("sequenceDiagram"), (1, 1, UnsupportedStatement));
+        assert_eq!(err("graph LR\n  a[\"Admin] --> b"), (2, 4, UnterminatedString));
+        assert_eq!(err("graph LR\n  a[Admin] b"), (2, 12, ExpectedEndOfLine));
+        assert_eq!(err("graph LR\n  a[Admin] --> b"), (2, 16, UntaggedNode("b".to_string())));
+        assert_eq!(err("graph LR\n  a[A] --> b[B]"), (2, 12, UnsupportedRelationship));
+        assert_eq!(err("graph LR\nend"), (2, 1, UnbalancedBrace));
+        assert_eq!(err("graph LR\nsubgraph s\nuc_a"), (2, 1, UnterminatedBlock));
+        match err("graph LR\na[A]\nb[A]") {
+            (3, 1, Name(_)) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+}
@@ -1,12 +1,30 @@
-use std::collections::{HashMap, HashSet};
-use std::collections::{hash_map, hash_set};
+use std::cmp;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::collections::btree_map;
 use std::error::Error;
 use std::fmt;
-use std::iter;
+use std::ops::Index;
 use std::rc::Rc;
+use std::str::FromStr;
+
+use self::names::{NameIndex, index_matches, unindex_key, unindex_name};
+
+pub use self::events::Event;
+#[cfg(feature = "petgraph")]
+pub use self::graph::{GraphEdge, GraphNode};
+pub use self::iterators::{ActorGeneralizations, Actors, Associations, Dependencies, Element,
+                          Elements, Extends, ExtensionPoints, Includes, IntoElements, Notes,
+                          OwnedElement, SystemBoundaries, UseCaseGeneralizations, UseCases};
+pub use self::names::{ActorMut, UseCaseMut};
+pub use self::remap::IdMapping;
+pub use self::search::{SearchField, SearchHit};
+pub use self::serialization::FORMAT_VERSION;
+pub use self::snapshot::DiagramSnapshot;
+pub use self::source_map::SourceMap;
+pub use self::stats::DiagramStats;
 
 /// An actor identifier is unique per use case diagram.
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct ActorId(pub usize);
 
 impl fmt::Display for ActorId {
@@ -16,7 +34,7 @@ impl fmt::Display for ActorId {
 }
 
 /// A use case identifier is unique per use case diagram.
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct UseCaseId(pub usize);
 
 impl fmt::Display for UseCaseId {
@@ -25,16 +43,515 @@ impl fmt::Display for UseCaseId {
     }
 }
 
-/// An actor of zero or more use cases.
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct Actor {
-    pub name: Rc<str>,
+/// An extension point identifier is unique per use case diagram.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct ExtensionPointId(pub usize);
+
+impl fmt::Display for ExtensionPointId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A system boundary identifier is unique per use case diagram.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct SystemBoundaryId(pub usize);
+
+impl fmt::Display for SystemBoundaryId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A note identifier is unique per use case diagram.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct NoteId(pub usize);
+
+impl fmt::Display for NoteId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The stereotype of actors that are other systems rather than people.
+/// Generated portals offer no buttons to such actors.
+pub const SYSTEM_STEREOTYPE: &str = "system";
+
+/// The separator between package names, and between a package and a use case
+/// title, in qualified titles.
+pub const PACKAGE_SEPARATOR: &str = "::";
+
+/// What kind of party an actor is.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd,
+         Serialize)]
+pub enum ActorKind {
+    /// A person who uses the system to achieve a goal.
+    #[default]
+    Primary,
+
+    /// A person who supports the system, e.g. an operator.
+    Secondary,
+
+    /// Another system.
+    System,
+
+    /// The passage of time, e.g. a scheduler triggering a batch job.
+    Time,
+}
+
+impl ActorKind {
+    /// Whether actors of this kind are people.
+    pub fn is_human(self) -> bool {
+        match self {
+            ActorKind::Primary | ActorKind::Secondary => true,
+            ActorKind::System | ActorKind::Time => false,
+        }
+    }
+}
+
+/// Where an element was defined in a source file, so that diagnostics can
+/// point at it. Lines and columns count from one.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct Span<S = Rc<str>> {
+    pub file: S,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl<S> fmt::Display for Span<S> where S: AsRef<str> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file.as_ref(), self.line, self.column)
+    }
+}
+
+/// An actor of zero or more use cases. The name is stored as `S`, which
+/// defaults to `Rc<str>`.
+#[derive(Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct Actor<S = Rc<str>> {
+    pub name: S,
+
+    /// A stable identifier chosen by the user, e.g. a UUID. Unlike the
+    /// `ActorId`, it survives serialization and merging, so other documents
+    /// can refer to the actor by it. Should be unique among actors.
+    pub key: Option<S>,
+
+    /// Whether the actor is a person, another system, or time.
+    #[serde(default)]
+    pub kind: ActorKind,
+
+    /// What the actor is, possibly spanning multiple lines.
+    pub description: Option<S>,
+
+    /// UML stereotypes such as `system`, without guillemets.
+    pub stereotypes: Vec<S>,
+
+    /// Tagged values for downstream tools, e.g. an owner or a ticket.
+    pub metadata: BTreeMap<String, String>,
+
+    /// The name of an icon for renderers to draw the actor with, e.g.
+    /// `shield`. Which names are understood is up to the renderer.
+    pub icon: Option<S>,
+
+    /// The color for renderers to draw the actor in, as a CSS color such as
+    /// `#1f77b4` or `teal`.
+    pub color: Option<S>,
+
+    /// The name in other languages, by locale such as `nl` or `nl-BE`. See
+    /// `name_for`.
+    #[serde(default = "BTreeMap::new")]
+    pub localized_names: BTreeMap<String, S>,
+
+    /// The layers the actor belongs to, e.g. `MVP`; see `visible_in`.
+    #[serde(default = "Vec::new")]
+    pub layers: Vec<S>,
+
+    /// Where the actor goes in generated output relative to other actors;
+    /// see `UseCaseDiagram::actors_in_order`.
+    pub sort_key: Option<i64>,
+
+    /// Where the actor was defined, if it was parsed from a source file.
+    pub span: Option<Span<S>>,
+}
+
+impl<S> Actor<S> {
+    /// An actor with the given name and nothing else.
+    pub fn new(name: S) -> Self {
+        Actor{
+            name,
+            key: None,
+            kind: ActorKind::Primary,
+            description: None,
+            stereotypes: Vec::new(),
+            metadata: BTreeMap::new(),
+            icon: None,
+            color: None,
+            localized_names: BTreeMap::new(),
+            layers: Vec::new(),
+            sort_key: None,
+            span: None,
+        }
+    }
+
+    /// Whether the actors are equal apart from where they were defined.
+    pub fn eq_ignoring_span(&self, other: &Self) -> bool where S: PartialEq {
+        let Actor{ref name, ref key, kind, ref description, ref stereotypes, ref metadata,
+                  ref icon, ref color, ref localized_names, ref layers, sort_key,
+                  span: _} = *self;
+        *name == other.name && *key == other.key && kind == other.kind &&
+            *description == other.description && *stereotypes == other.stereotypes &&
+            *metadata == other.metadata && *icon == other.icon && *color == other.color &&
+            *localized_names == other.localized_names && *layers == other.layers &&
+            sort_key == other.sort_key
+    }
+
+    /// Whether the actor has the given stereotype.
+    pub fn has_stereotype(&self, stereotype: &str) -> bool where S: AsRef<str> {
+        self.stereotypes.iter().any(|s| s.as_ref() == stereotype)
+    }
+}
+
+/// A use case. The title is stored as `S`, which defaults to `Rc<str>`.
+#[derive(Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct UseCase<S = Rc<str>> {
+    pub title: S,
+
+    /// A stable identifier chosen by the user, e.g. a UUID. Unlike the
+    /// `UseCaseId`, it survives serialization and merging, so other
+    /// documents can refer to the use case by it. Should be unique among use
+    /// cases.
+    pub key: Option<S>,
+
+    /// The package the use case belongs to, as the path of package names
+    /// from the outermost, e.g. `Billing` and then `Invoices`. Empty for use
+    /// cases at the top level.
+    #[serde(default = "Vec::new")]
+    pub package: Vec<S>,
+
+    /// Whether the use case only generalizes other use cases and cannot be
+    /// performed itself. Abstract use cases get no code generated, and
+    /// should not be associated with actors.
+    #[serde(default, rename = "abstract")]
+    pub is_abstract: bool,
+
+    /// What the use case achieves, possibly spanning multiple lines.
+    pub description: Option<S>,
+
+    /// What must hold before the use case starts.
+    pub preconditions: Vec<S>,
+
+    /// What holds after the use case completes successfully.
+    pub postconditions: Vec<S>,
+
+    /// The steps of the usual way through the use case, in order.
+    #[serde(default = "Vec::new")]
+    pub main_flow: Vec<S>,
+
+    /// Other ways through the use case, e.g. when a step fails.
+    #[serde(default = "Vec::new")]
+    pub alternative_flows: Vec<Flow<S>>,
+
+    pub priority: Option<Priority>,
+    pub status: Option<Status>,
+
+    /// Why the use case should no longer be used, if it is deprecated.
+    pub deprecated: Option<S>,
+
+    /// Free-form labels for selecting part of the diagram, e.g. `mvp`.
+    pub tags: Vec<S>,
+
+    /// UML stereotypes such as `batch`, without guillemets.
+    pub stereotypes: Vec<S>,
+
+    /// Tagged values for downstream tools, e.g. an owner or a ticket.
+    pub metadata: BTreeMap<String, String>,
+
+    /// The title in other languages, by locale such as `nl` or `nl-BE`. See
+    /// `title_for`.
+    #[serde(default = "BTreeMap::new")]
+    pub localized_titles: BTreeMap<String, S>,
+
+    /// The layers the use case belongs to, e.g. `MVP`; see `visible_in`.
+    #[serde(default = "Vec::new")]
+    pub layers: Vec<S>,
+
+    /// Where the use case goes in generated output relative to other use
+    /// cases; see `UseCaseDiagram::use_cases_in_order`.
+    pub sort_key: Option<i64>,
+
+    /// Where the use case was defined, if it was parsed from a source file.
+    pub span: Option<Span<S>>,
+}
+
+impl<S> UseCase<S> {
+    /// A use case with the given title and nothing else.
+    pub fn new(title: S) -> Self {
+        UseCase{
+            title,
+            key: None,
+            package: Vec::new(),
+            is_abstract: false,
+            description: None,
+            preconditions: Vec::new(),
+            postconditions: Vec::new(),
+            main_flow: Vec::new(),
+            alternative_flows: Vec::new(),
+            priority: None,
+            status: None,
+            deprecated: None,
+            tags: Vec::new(),
+            stereotypes: Vec::new(),
+            metadata: BTreeMap::new(),
+            localized_titles: BTreeMap::new(),
+            layers: Vec::new(),
+            sort_key: None,
+            span: None,
+        }
+    }
+
+    /// Whether the use cases are equal apart from where they were defined.
+    pub fn eq_ignoring_span(&self, other: &Self) -> bool where S: PartialEq {
+        let UseCase{ref title, ref key, ref package, is_abstract, ref description,
+                    ref preconditions, ref postconditions, ref main_flow, ref alternative_flows,
+                    priority, status, ref deprecated, ref tags, ref stereotypes, ref metadata,
+                    ref localized_titles, ref layers, sort_key, span: _} = *self;
+        *title == other.title && *key == other.key && *package == other.package &&
+            is_abstract == other.is_abstract && *description == other.description &&
+            *preconditions == other.preconditions && *postconditions == other.postconditions &&
+            *main_flow == other.main_flow && *alternative_flows == other.alternative_flows &&
+            priority == other.priority && status == other.status &&
+            *deprecated == other.deprecated && *tags == other.tags &&
+            *stereotypes == other.stereotypes && *metadata == other.metadata &&
+            *localized_titles == other.localized_titles && *layers == other.layers &&
+            sort_key == other.sort_key
+    }
+
+    /// Whether the use case has the given stereotype.
+    pub fn has_stereotype(&self, stereotype: &str) -> bool where S: AsRef<str> {
+        self.stereotypes.iter().any(|s| s.as_ref() == stereotype)
+    }
+
+    /// Whether the use case has the given tag.
+    pub fn has_tag(&self, tag: &str) -> bool where S: AsRef<str> {
+        self.tags.iter().any(|t| t.as_ref() == tag)
+    }
+
+    /// The title preceded by the package path, separated by
+    /// `PACKAGE_SEPARATOR`, e.g. `Billing::Cancel subscription`.
+    pub fn qualified_title(&self) -> String where S: AsRef<str> {
+        let mut qualified_title = String::new();
+        for name in &self.package {
+            qualified_title.push_str(name.as_ref());
+            qualified_title.push_str(PACKAGE_SEPARATOR);
+        }
+        qualified_title.push_str(self.title.as_ref());
+        qualified_title
+    }
+
+    /// Whether the use case belongs directly to the given package.
+    pub fn is_in_package(&self, package: &[&str]) -> bool where S: AsRef<str> {
+        self.package.len() == package.len() &&
+        self.package.iter().zip(package).all(|(name, &other)| name.as_ref() == other)
+    }
+}
+
+/// A named way through a use case, as a list of steps in order.
+#[derive(Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct Flow<S = Rc<str>> {
+    pub name: S,
+    pub steps: Vec<S>,
+}
+
+/// How important a use case is, in increasing order.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+/// How far along a use case is, in increasing order.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum Status {
+    Draft,
+    Approved,
+    Implemented,
+}
+
+/// A named system, or subject, that contains use cases. A use case may be
+/// contained by any number of system boundaries.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SystemBoundary<S = Rc<str>> {
+    pub name: S,
+
+    /// The use cases inside this system boundary.
+    pub use_cases: BTreeSet<UseCaseId>,
+}
+
+/// A named point in a use case at which other use cases may extend it.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ExtensionPoint<S = Rc<str>> {
+    /// The use case this extension point belongs to.
+    pub use_case: UseCaseId,
+
+    pub name: S,
+}
+
+/// A free-text note attached to an element of the diagram.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Note<S = Rc<str>> {
+    pub anchor: NoteAnchor,
+    pub text: S,
+}
+
+/// The details of an association between an actor and a use case. The
+/// actor and use case themselves are the key under which the association is
+/// stored.
+#[derive(Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct Association<S = Rc<str>> {
+    pub label: Option<S>,
+
+    /// How many instances of the actor take part in the association.
+    pub actor_multiplicity: Option<Multiplicity>,
+
+    /// How many instances of the use case take part in the association.
+    pub use_case_multiplicity: Option<Multiplicity>,
+
+    pub navigability: Navigability,
+
+    /// How often the actor performs the use case, e.g. the number of times
+    /// per day as measured in production. Only relative values matter.
+    pub weight: Option<u64>,
+
+    /// Tagged values for downstream tools, e.g. an owner or a ticket.
+    pub metadata: BTreeMap<String, String>,
+
+    /// The layers the association belongs to, e.g. `MVP`; see `visible_in`.
+    #[serde(default = "Vec::new")]
+    pub layers: Vec<S>,
+
+    /// Where the association was defined, if it was parsed from a source
+    /// file.
+    pub span: Option<Span<S>>,
+}
+
+impl<S> Association<S> {
+    /// Whether the associations are equal apart from where they were
+    /// defined.
+    pub fn eq_ignoring_span(&self, other: &Self) -> bool where S: PartialEq {
+        let Association{ref label, actor_multiplicity, use_case_multiplicity, navigability,
+                        weight, ref metadata, ref layers, span: _} = *self;
+        *label == other.label && actor_multiplicity == other.actor_multiplicity &&
+            use_case_multiplicity == other.use_case_multiplicity &&
+            navigability == other.navigability && weight == other.weight &&
+            *metadata == other.metadata && *layers == other.layers
+    }
+}
+
+impl<S> Default for Association<S> {
+    fn default() -> Self {
+        Association{
+            label: None,
+            actor_multiplicity: None,
+            use_case_multiplicity: None,
+            navigability: Navigability::Undirected,
+            weight: None,
+            metadata: BTreeMap::new(),
+            layers: Vec::new(),
+            span: None,
+        }
+    }
+}
+
+/// The number of instances at one end of an association, from `lower` to
+/// `upper` inclusive. An `upper` of `None` means unbounded.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct Multiplicity {
+    pub lower: usize,
+    pub upper: Option<usize>,
+}
+
+impl fmt::Display for Multiplicity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.upper {
+            Some(upper) if upper == self.lower => write!(f, "{}", upper),
+            Some(upper) => write!(f, "{}..{}", self.lower, upper),
+            None if self.lower == 0 => write!(f, "*"),
+            None => write!(f, "{}..*", self.lower),
+        }
+    }
+}
+
+impl FromStr for Multiplicity {
+    type Err = ParseMultiplicityError;
+
+    /// Parse the UML notation: `n`, `*`, `n..m`, or `n..*`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bound = |s: &str| -> Result<Option<usize>, ParseMultiplicityError> {
+            if s == "*" {
+                Ok(None)
+            } else {
+                s.parse().map(Some).map_err(|_| ParseMultiplicityError)
+            }
+        };
+        let (lower, upper) = match s.find("..") {
+            Some(i) => (bound(&s[.. i])?.ok_or(ParseMultiplicityError)?, bound(&s[i + 2 ..])?),
+            None => match bound(s)? {
+                Some(n) => (n, Some(n)),
+                None => (0, None),
+            },
+        };
+        if upper.is_some_and(|upper| upper < lower) {
+            return Err(ParseMultiplicityError);
+        }
+        Ok(Multiplicity{lower, upper})
+    }
+}
+
+/// An error that describes a malformed multiplicity.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ParseMultiplicityError;
+
+impl fmt::Display for ParseMultiplicityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid multiplicity")
+    }
+}
+
+impl Error for ParseMultiplicityError {
+    fn description(&self) -> &str {
+        "invalid multiplicity"
+    }
+}
+
+/// The direction in which an association can be navigated.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum Navigability {
+    /// Both ends are navigable, or navigability is unspecified.
+    Undirected,
+
+    /// The actor initiates the use case.
+    ToUseCase,
+
+    /// The use case involves the actor, e.g. a secondary actor.
+    ToActor,
+}
+
+/// The element a note is attached to.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum NoteAnchor {
+    Actor(ActorId),
+    UseCase(UseCaseId),
+    Association(ActorId, UseCaseId),
 }
 
-/// A use case.
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct UseCase {
-    pub title: Rc<str>,
+/// An extend relationship: the extension use case optionally adds behavior
+/// to the base use case, at the given extension point of the base use case
+/// if any.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct Extend {
+    pub extension: UseCaseId,
+    pub base: UseCaseId,
+    pub extension_point: Option<ExtensionPointId>,
 }
 
 /// An error that describes an invalid association.
@@ -49,10 +566,10 @@ pub enum AssociationError {
 
 impl fmt::Display for AssociationError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &AssociationError::NonexistentActor(actor_id) =>
+        match *self {
+            AssociationError::NonexistentActor(actor_id) =>
                 write!(f, "invalid association: nonexistent actor {}", actor_id),
-            &AssociationError::NonexistentUseCase(use_case_id) =>
+            AssociationError::NonexistentUseCase(use_case_id) =>
                 write!(f, "invalid association: nonexistent use case {}", use_case_id),
         }
     }
@@ -60,42 +577,446 @@ impl fmt::Display for AssociationError {
 
 impl Error for AssociationError {
     fn description(&self) -> &str {
-        match self {
-            &AssociationError::NonexistentActor(_) =>
+        match *self {
+            AssociationError::NonexistentActor(_) =>
                 "invalid association: nonexistent actor",
-            &AssociationError::NonexistentUseCase(_) =>
+            AssociationError::NonexistentUseCase(_) =>
                 "invalid association: nonexistent use case",
         }
     }
 }
 
+/// An error that describes a name or title shared by two elements.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NameError {
+    /// Two actors have the same name.
+    DuplicateActorName(ActorId, ActorId),
+
+    /// Two use cases have the same title.
+    DuplicateUseCaseTitle(UseCaseId, UseCaseId),
+
+    /// Two extension points of the same use case have the same name.
+    DuplicateExtensionPointName(ExtensionPointId, ExtensionPointId),
+
+    /// Two system boundaries have the same name.
+    DuplicateSystemBoundaryName(SystemBoundaryId, SystemBoundaryId),
+
+    /// Two actors have the same key.
+    DuplicateActorKey(ActorId, ActorId),
+
+    /// Two use cases have the same key.
+    DuplicateUseCaseKey(UseCaseId, UseCaseId),
+
+    /// The name is already that of the given actor.
+    ActorNameTaken(ActorId),
+
+    /// The title is already that of the given use case.
+    UseCaseTitleTaken(UseCaseId),
+
+    /// The key is already that of the given actor.
+    ActorKeyTaken(ActorId),
+
+    /// The key is already that of the given use case.
+    UseCaseKeyTaken(UseCaseId),
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NameError::DuplicateActorName(a, b) =>
+                write!(f, "duplicate name: actors {} and {}", a, b),
+            NameError::DuplicateUseCaseTitle(a, b) =>
+                write!(f, "duplicate title: use cases {} and {}", a, b),
+            NameError::DuplicateExtensionPointName(a, b) =>
+                write!(f, "duplicate name: extension points {} and {}", a, b),
+            NameError::DuplicateSystemBoundaryName(a, b) =>
+                write!(f, "duplicate name: system boundaries {} and {}", a, b),
+            NameError::DuplicateActorKey(a, b) =>
+                write!(f, "duplicate key: actors {} and {}", a, b),
+            NameError::DuplicateUseCaseKey(a, b) =>
+                write!(f, "duplicate key: use cases {} and {}", a, b),
+            NameError::ActorNameTaken(actor_id) =>
+                write!(f, "duplicate name: taken by actor {}", actor_id),
+            NameError::UseCaseTitleTaken(use_case_id) =>
+                write!(f, "duplicate title: taken by use case {}", use_case_id),
+            NameError::ActorKeyTaken(actor_id) =>
+                write!(f, "duplicate key: taken by actor {}", actor_id),
+            NameError::UseCaseKeyTaken(use_case_id) =>
+                write!(f, "duplicate key: taken by use case {}", use_case_id),
+        }
+    }
+}
+
+impl Error for NameError {
+    fn description(&self) -> &str {
+        match *self {
+            NameError::DuplicateActorName(_, _) =>
+                "duplicate name: actors",
+            NameError::DuplicateUseCaseTitle(_, _) =>
+                "duplicate title: use cases",
+            NameError::DuplicateExtensionPointName(_, _) =>
+                "duplicate name: extension points",
+            NameError::DuplicateSystemBoundaryName(_, _) =>
+                "duplicate name: system boundaries",
+            NameError::DuplicateActorKey(_, _) =>
+                "duplicate key: actors",
+            NameError::DuplicateUseCaseKey(_, _) =>
+                "duplicate key: use cases",
+            NameError::ActorNameTaken(_) =>
+                "duplicate name: actors",
+            NameError::UseCaseTitleTaken(_) =>
+                "duplicate title: use cases",
+            NameError::ActorKeyTaken(_) =>
+                "duplicate key: actors",
+            NameError::UseCaseKeyTaken(_) =>
+                "duplicate key: use cases",
+        }
+    }
+}
+
+/// An error that describes an invalid relationship between use cases.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RelationshipError {
+    /// The relationship refers to a nonexistent actor.
+    NonexistentActor(ActorId),
+
+    /// The relationship from the first to the second actor would close a
+    /// cycle.
+    ActorCycle(ActorId, ActorId),
+
+    /// The relationship refers to a nonexistent use case.
+    NonexistentUseCase(UseCaseId),
+
+    /// The relationship from the first to the second use case would close a
+    /// cycle.
+    UseCaseCycle(UseCaseId, UseCaseId),
+
+    /// The relationship refers to a nonexistent extension point.
+    NonexistentExtensionPoint(ExtensionPointId),
+
+    /// The extension point does not belong to the given base use case.
+    ForeignExtensionPoint(ExtensionPointId, UseCaseId),
+
+    /// The relationship refers to a nonexistent system boundary.
+    NonexistentSystemBoundary(SystemBoundaryId),
+
+    /// The relationship refers to a nonexistent association.
+    NonexistentAssociation(ActorId, UseCaseId),
+}
+
+impl fmt::Display for RelationshipError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RelationshipError::NonexistentActor(actor_id) =>
+                write!(f, "invalid relationship: nonexistent actor {}", actor_id),
+            RelationshipError::ActorCycle(from, to) =>
+                write!(f, "invalid relationship: actor {} to {} closes a cycle", from, to),
+            RelationshipError::NonexistentUseCase(use_case_id) =>
+                write!(f, "invalid relationship: nonexistent use case {}", use_case_id),
+            RelationshipError::UseCaseCycle(from, to) =>
+                write!(f, "invalid relationship: use case {} to {} closes a cycle", from, to),
+            RelationshipError::NonexistentExtensionPoint(extension_point_id) =>
+                write!(f, "invalid relationship: nonexistent extension point {}",
+                       extension_point_id),
+            RelationshipError::ForeignExtensionPoint(extension_point_id, use_case_id) =>
+                write!(f, "invalid relationship: extension point {} is not in use case {}",
+                       extension_point_id, use_case_id),
+            RelationshipError::NonexistentSystemBoundary(system_boundary_id) =>
+                write!(f, "invalid relationship: nonexistent system boundary {}",
+                       system_boundary_id),
+            RelationshipError::NonexistentAssociation(actor_id, use_case_id) =>
+                write!(f, "invalid relationship: nonexistent association of actor {} with \
+                           use case {}", actor_id, use_case_id),
+        }
+    }
+}
+
+impl Error for RelationshipError {
+    fn description(&self) -> &str {
+        match *self {
+            RelationshipError::NonexistentActor(_) =>
+                "invalid relationship: nonexistent actor",
+            RelationshipError::ActorCycle(_, _) =>
+                "invalid relationship: cycle",
+            RelationshipError::NonexistentUseCase(_) =>
+                "invalid relationship: nonexistent use case",
+            RelationshipError::UseCaseCycle(_, _) =>
+                "invalid relationship: cycle",
+            RelationshipError::NonexistentExtensionPoint(_) =>
+                "invalid relationship: nonexistent extension point",
+            RelationshipError::ForeignExtensionPoint(_, _) =>
+                "invalid relationship: extension point of another use case",
+            RelationshipError::NonexistentSystemBoundary(_) =>
+                "invalid relationship: nonexistent system boundary",
+            RelationshipError::NonexistentAssociation(_, _) =>
+                "invalid relationship: nonexistent association",
+        }
+    }
+}
+
+/// A way in which a diagram is internally inconsistent. Diagrams changed
+/// only through this module's API never are; see `UseCaseDiagram::validate`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InvariantViolation {
+    /// A counter would hand out an identifier that is already in use.
+    StaleIdentifierCounter,
+
+    /// The index from names, titles, and keys to elements is out of date.
+    StaleNameIndex,
+
+    /// The index between actors and their associated use cases is out of
+    /// date.
+    StaleAssociationIndex,
+
+    /// The diagram has unique names, but two elements share a name.
+    DuplicateName(NameError),
+
+    /// The association refers to a nonexistent actor or use case.
+    DanglingAssociation(ActorId, UseCaseId),
+
+    /// The include refers to a nonexistent use case.
+    DanglingInclude(UseCaseId, UseCaseId),
+
+    /// The dependency refers to a nonexistent use case.
+    DanglingDependency(UseCaseId, UseCaseId),
+
+    /// The extend refers to a nonexistent use case or extension point, or
+    /// to an extension point outside its base use case.
+    DanglingExtend(Extend),
+
+    /// The actor generalization refers to a nonexistent actor.
+    DanglingActorGeneralization(ActorId, ActorId),
+
+    /// The use case generalization refers to a nonexistent use case.
+    DanglingUseCaseGeneralization(UseCaseId, UseCaseId),
+
+    /// The extension point belongs to a nonexistent use case.
+    DanglingExtensionPoint(ExtensionPointId),
+
+    /// The system boundary contains a nonexistent use case.
+    DanglingSystemBoundary(SystemBoundaryId),
+
+    /// The note is attached to a nonexistent element.
+    DanglingNote(NoteId),
+
+    /// The actor is its own ancestor.
+    ActorCycle(ActorId),
+
+    /// The use case includes, extends, specializes, or depends on itself,
+    /// directly or indirectly.
+    UseCaseCycle(UseCaseId),
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InvariantViolation::StaleIdentifierCounter =>
+                write!(f, "invariant violation: identifier counter is stale"),
+            InvariantViolation::StaleNameIndex =>
+                write!(f, "invariant violation: name index is stale"),
+            InvariantViolation::StaleAssociationIndex =>
+                write!(f, "invariant violation: association index is stale"),
+            InvariantViolation::DuplicateName(err) =>
+                write!(f, "invariant violation: {}", err),
+            InvariantViolation::DanglingAssociation(actor_id, use_case_id) =>
+                write!(f, "invariant violation: association between actor {} and use case {} \
+                           refers to nonexistent element", actor_id, use_case_id),
+            InvariantViolation::DanglingInclude(base, included) =>
+                write!(f, "invariant violation: include of use case {} by {} \
+                           refers to nonexistent use case", included, base),
+            InvariantViolation::DanglingDependency(dependent, dependency) =>
+                write!(f, "invariant violation: dependency of use case {} on {} \
+                           refers to nonexistent use case", dependent, dependency),
+            InvariantViolation::DanglingExtend(extend) =>
+                write!(f, "invariant violation: extend of use case {} by {} \
+                           refers to nonexistent element", extend.base, extend.extension),
+            InvariantViolation::DanglingActorGeneralization(child, parent) =>
+                write!(f, "invariant violation: generalization of actor {} by {} \
+                           refers to nonexistent actor", parent, child),
+            InvariantViolation::DanglingUseCaseGeneralization(child, parent) =>
+                write!(f, "invariant violation: generalization of use case {} by {} \
+                           refers to nonexistent use case", parent, child),
+            InvariantViolation::DanglingExtensionPoint(extension_point_id) =>
+                write!(f, "invariant violation: extension point {} \
+                           refers to nonexistent use case", extension_point_id),
+            InvariantViolation::DanglingSystemBoundary(system_boundary_id) =>
+                write!(f, "invariant violation: system boundary {} \
+                           contains nonexistent use case", system_boundary_id),
+            InvariantViolation::DanglingNote(note_id) =>
+                write!(f, "invariant violation: note {} refers to nonexistent element",
+                       note_id),
+            InvariantViolation::ActorCycle(actor_id) =>
+                write!(f, "invariant violation: cycle: actor {}", actor_id),
+            InvariantViolation::UseCaseCycle(use_case_id) =>
+                write!(f, "invariant violation: cycle: use case {}", use_case_id),
+        }
+    }
+}
+
+impl Error for InvariantViolation {
+    fn description(&self) -> &str {
+        match *self {
+            InvariantViolation::StaleIdentifierCounter =>
+                "invariant violation: identifier counter is stale",
+            InvariantViolation::StaleNameIndex =>
+                "invariant violation: name index is stale",
+            InvariantViolation::StaleAssociationIndex =>
+                "invariant violation: association index is stale",
+            InvariantViolation::DuplicateName(_) =>
+                "invariant violation: duplicate name",
+            InvariantViolation::DanglingAssociation(_, _) =>
+                "invariant violation: dangling association",
+            InvariantViolation::DanglingInclude(_, _) =>
+                "invariant violation: dangling include",
+            InvariantViolation::DanglingDependency(_, _) =>
+                "invariant violation: dangling dependency",
+            InvariantViolation::DanglingExtend(_) =>
+                "invariant violation: dangling extend",
+            InvariantViolation::DanglingActorGeneralization(_, _) =>
+                "invariant violation: dangling generalization: actors",
+            InvariantViolation::DanglingUseCaseGeneralization(_, _) =>
+                "invariant violation: dangling generalization: use cases",
+            InvariantViolation::DanglingExtensionPoint(_) =>
+                "invariant violation: dangling extension point",
+            InvariantViolation::DanglingSystemBoundary(_) =>
+                "invariant violation: dangling system boundary",
+            InvariantViolation::DanglingNote(_) =>
+                "invariant violation: dangling note",
+            InvariantViolation::ActorCycle(_) =>
+                "invariant violation: cycle: actors",
+            InvariantViolation::UseCaseCycle(_) =>
+                "invariant violation: cycle: use cases",
+        }
+    }
+}
+
 /// A use case diagram is a graph containing actors, use cases, and
-/// associations.
+/// associations. Use cases may include, extend, and specialize other use
+/// cases, and actors may specialize other actors; none of these
+/// relationships ever form a cycle. Use cases may be grouped into system
+/// boundaries, and any element may carry notes.
+///
+/// Every iterator yields elements in order of their identifiers, and
+/// relationships in order of the identifiers they relate. Identifiers are
+/// assigned in increasing order, so elements come out in the order they were
+/// inserted and generated code does not change from run to run.
+///
+/// Names and titles are stored as `S`. The default, `Rc<str>`, makes the
+/// diagram neither `Send` nor `Sync`; use `Arc<str>` or `String` for a
+/// diagram that can be shared across threads.
 #[derive(Clone, Debug)]
-pub struct UseCaseDiagram {
+pub struct UseCaseDiagram<S = Rc<str>> {
     next_actor_id: usize,
     next_use_case_id: usize,
+    next_extension_point_id: usize,
+    next_system_boundary_id: usize,
+    next_note_id: usize,
 
-    actors: HashMap<ActorId, Actor>,
-    use_cases: HashMap<UseCaseId, UseCase>,
-    associations: HashSet<(ActorId, UseCaseId)>,
+    actors: BTreeMap<ActorId, Actor<S>>,
+    use_cases: BTreeMap<UseCaseId, UseCase<S>>,
+    extension_points: BTreeMap<ExtensionPointId, ExtensionPoint<S>>,
+    associations: BTreeMap<(ActorId, UseCaseId), Association<S>>,
+    includes: BTreeSet<(UseCaseId, UseCaseId)>,
+    dependencies: BTreeMap<(UseCaseId, UseCaseId), Option<S>>,
+    extends: BTreeSet<Extend>,
+    actor_generalizations: BTreeSet<(ActorId, ActorId)>,
+    use_case_generalizations: BTreeSet<(UseCaseId, UseCaseId)>,
+    system_boundaries: BTreeMap<SystemBoundaryId, SystemBoundary<S>>,
+    notes: BTreeMap<NoteId, Note<S>>,
+
+    actor_use_cases: HashMap<ActorId, BTreeSet<UseCaseId>>,
+    use_case_actors: HashMap<UseCaseId, BTreeSet<ActorId>>,
+    actor_names: NameIndex<ActorId>,
+    use_case_titles: NameIndex<UseCaseId>,
+    actor_keys: NameIndex<ActorId>,
+    use_case_keys: NameIndex<UseCaseId>,
+    unique_names: bool,
+    invariant_checks: bool,
+    event_recording: bool,
+    events: Vec<Event>,
 }
 
-impl UseCaseDiagram {
+impl<S> UseCaseDiagram<S> where S: AsRef<str> {
     /// A new use case diagram with no actors and no use cases.
     pub fn new() -> Self {
         let diagram = UseCaseDiagram{
             next_actor_id: 0,
             next_use_case_id: 0,
+            next_extension_point_id: 0,
+            next_system_boundary_id: 0,
+            next_note_id: 0,
+
+            actors: BTreeMap::new(),
+            use_cases: BTreeMap::new(),
+            extension_points: BTreeMap::new(),
+            associations: BTreeMap::new(),
+            includes: BTreeSet::new(),
+            dependencies: BTreeMap::new(),
+            extends: BTreeSet::new(),
+            actor_generalizations: BTreeSet::new(),
+            use_case_generalizations: BTreeSet::new(),
+            system_boundaries: BTreeMap::new(),
+            notes: BTreeMap::new(),
 
-            actors: HashMap::new(),
-            use_cases: HashMap::new(),
-            associations: HashSet::new(),
+            actor_use_cases: HashMap::new(),
+            use_case_actors: HashMap::new(),
+            actor_names: HashMap::new(),
+            use_case_titles: HashMap::new(),
+            actor_keys: HashMap::new(),
+            use_case_keys: HashMap::new(),
+            unique_names: false,
+            invariant_checks: cfg!(debug_assertions),
+            event_recording: false,
+            events: Vec::new(),
         };
         diagram.assert_invariants();
         diagram
     }
 
+    /// A new use case diagram with no actors and no use cases, with room for
+    /// the given numbers of elements and associations. Elements and
+    /// relationships are kept in ordered maps, which allocate per entry, so
+    /// only the name indices and the index between actors and their use
+    /// cases reserve space up front.
+    pub fn with_capacity(actors: usize, use_cases: usize, associations: usize) -> Self {
+        let mut diagram = UseCaseDiagram::new();
+        diagram.actor_names.reserve(actors);
+        diagram.use_case_titles.reserve(use_cases);
+        diagram.actor_use_cases.reserve(cmp::min(actors, associations));
+        diagram.use_case_actors.reserve(cmp::min(use_cases, associations));
+        diagram
+    }
+
+    /// A new use case diagram in which no two actors may share a name or a
+    /// key and no two use cases may share a title or a key. See
+    /// `insert_actor` and `insert_use_case`.
+    pub fn with_unique_names() -> Self {
+        let mut diagram = UseCaseDiagram::new();
+        diagram.unique_names = true;
+        diagram.assert_invariants();
+        diagram
+    }
+
+    /// Whether this diagram rejects duplicate actor names and use case
+    /// titles.
+    pub fn has_unique_names(&self) -> bool {
+        self.unique_names
+    }
+
+    /// Whether every change to this diagram is followed by a full `check`,
+    /// panicking on the first violation. The cycle checks walk the
+    /// relationships from every element, so this costs time quadratic in the
+    /// size of the diagram per change; it defaults to on only in debug
+    /// builds.
+    pub fn set_invariant_checks(&mut self, enabled: bool) {
+        self.invariant_checks = enabled;
+    }
+
+    /// Whether every change to this diagram is followed by a full `check`.
+    pub fn has_invariant_checks(&self) -> bool {
+        self.invariant_checks
+    }
+
     fn next_actor_id(&mut self) -> ActorId {
         let actor_id = ActorId(self.next_actor_id);
         self.next_actor_id += 1;
@@ -110,95 +1031,1351 @@ impl UseCaseDiagram {
         use_case_id
     }
 
+    fn next_extension_point_id(&mut self) -> ExtensionPointId {
+        let extension_point_id = ExtensionPointId(self.next_extension_point_id);
+        self.next_extension_point_id += 1;
+        self.assert_invariants();
+        extension_point_id
+    }
+
+    fn next_system_boundary_id(&mut self) -> SystemBoundaryId {
+        let system_boundary_id = SystemBoundaryId(self.next_system_boundary_id);
+        self.next_system_boundary_id += 1;
+        self.assert_invariants();
+        system_boundary_id
+    }
+
+    fn next_note_id(&mut self) -> NoteId {
+        let note_id = NoteId(self.next_note_id);
+        self.next_note_id += 1;
+        self.assert_invariants();
+        note_id
+    }
+
     /// Get the actor with the given identifier.
-    pub fn actor(&self, actor_id: ActorId) -> Option<&Actor> {
+    pub fn actor(&self, actor_id: ActorId) -> Option<&Actor<S>> {
         self.actors.get(&actor_id)
     }
 
     /// Get the use case with the given identifier.
-    pub fn use_case(&self, use_case_id: UseCaseId) -> Option<&UseCase> {
+    pub fn use_case(&self, use_case_id: UseCaseId) -> Option<&UseCase<S>> {
         self.use_cases.get(&use_case_id)
     }
 
-    /// All actors in this use case diagram.
-    pub fn actors(&self) -> Actors {
-        self.actors.iter().map(|(&k, v)| (k, v))
+    /// Get the extension point with the given identifier.
+    pub fn extension_point(&self, extension_point_id: ExtensionPointId)
+                           -> Option<&ExtensionPoint<S>> {
+        self.extension_points.get(&extension_point_id)
     }
 
-    /// All use cases in this use case diagram.
-    pub fn use_cases(&self) -> UseCases {
-        self.use_cases.iter().map(|(&k, v)| (k, v))
+    /// Get the system boundary with the given identifier.
+    pub fn system_boundary(&self, system_boundary_id: SystemBoundaryId)
+                           -> Option<&SystemBoundary<S>> {
+        self.system_boundaries.get(&system_boundary_id)
     }
 
-    /// All associations in this use case diagram.
-    pub fn associations(&self) -> Associations {
-        self.associations.iter().cloned()
+    /// Get the note with the given identifier.
+    pub fn note(&self, note_id: NoteId) -> Option<&Note<S>> {
+        self.notes.get(&note_id)
     }
 
-    /// Insert a new actor, returning its identifier.
-    pub fn insert_actor(&mut self, actor: Actor) -> ActorId {
-        let actor_id = self.next_actor_id();
-        self.actors.insert(actor_id, actor);
-        self.assert_invariants();
-        actor_id
+    /// Get an owned copy of the actor with the given identifier. With the
+    /// default `Rc<str>` names, the copy shares its name with the diagram and
+    /// is not `Send`.
+    pub fn actor_cloned(&self, actor_id: ActorId) -> Option<Actor<S>>
+        where S: Clone {
+        self.actor(actor_id).cloned()
     }
 
-    /// Insert a new use case, returning its identifier.
-    pub fn insert_use_case(&mut self, use_case: UseCase) -> UseCaseId {
-        let use_case_id = self.next_use_case_id();
-        self.use_cases.insert(use_case_id, use_case);
-        self.assert_invariants();
-        use_case_id
+    /// Get an owned copy of the use case with the given identifier. With the
+    /// default `Rc<str>` titles, the copy shares its title with the diagram
+    /// and is not `Send`.
+    pub fn use_case_cloned(&self, use_case_id: UseCaseId) -> Option<UseCase<S>>
+        where S: Clone {
+        self.use_case(use_case_id).cloned()
+    }
+
+    /// All actors in this use case diagram.
+    pub fn actors(&self) -> Actors<'_, S> {
+        Actors{inner: self.actors.iter()}
+    }
+
+    /// All use cases in this use case diagram.
+    pub fn use_cases(&self) -> UseCases<'_, S> {
+        UseCases{inner: self.use_cases.iter()}
+    }
+
+    /// The actors with the given stereotype.
+    pub fn actors_with_stereotype<'a>(&'a self, stereotype: &'a str)
+                                      -> impl Iterator<Item = (ActorId, &'a Actor<S>)> {
+        self.actors().filter(move |&(_, actor)| actor.has_stereotype(stereotype))
+    }
+
+    /// The use cases with the given stereotype.
+    pub fn use_cases_with_stereotype<'a>(&'a self, stereotype: &'a str)
+                                         -> impl Iterator<Item = (UseCaseId, &'a UseCase<S>)> {
+        self.use_cases().filter(move |&(_, use_case)| use_case.has_stereotype(stereotype))
+    }
+
+    /// Every package that contains a use case, directly or through nested
+    /// packages, as a path of package names. The top level is not included.
+    pub fn packages(&self) -> BTreeSet<Vec<&str>> {
+        let mut packages = BTreeSet::new();
+        for (_, use_case) in self.use_cases() {
+            let package = use_case.package.iter().map(|name| name.as_ref()).collect::<Vec<_>>();
+            for len in 1 .. package.len() + 1 {
+                packages.insert(package[.. len].to_vec());
+            }
+        }
+        packages
+    }
+
+    /// The use cases directly in the given package; for the empty path, the
+    /// use cases at the top level.
+    pub fn use_cases_in_package<'a>(&'a self, package: &'a [&'a str])
+                                    -> impl Iterator<Item = (UseCaseId, &'a UseCase<S>)> {
+        self.use_cases().filter(move |&(_, use_case)| use_case.is_in_package(package))
+    }
+
+    /// The use cases with the given tag.
+    pub fn use_cases_with_tag<'a>(&'a self, tag: &'a str)
+                                  -> impl Iterator<Item = (UseCaseId, &'a UseCase<S>)> {
+        self.use_cases().filter(move |&(_, use_case)| use_case.has_tag(tag))
+    }
+
+    /// The use cases with the given status.
+    pub fn use_cases_with_status(&self, status: Status)
+                                 -> impl Iterator<Item = (UseCaseId, &UseCase<S>)> {
+        self.use_cases().filter(move |&(_, use_case)| use_case.status == Some(status))
+    }
+
+    /// The use cases with at least the given priority.
+    pub fn use_cases_with_priority_at_least(&self, priority: Priority)
+                                            -> impl Iterator<Item = (UseCaseId, &UseCase<S>)> {
+        self.use_cases()
+            .filter(move |&(_, use_case)| use_case.priority.is_some_and(|p| p >= priority))
+    }
+
+    /// All associations in this use case diagram.
+    pub fn associations(&self) -> Associations<'_, S> {
+        Associations{inner: self.associations.keys()}
+    }
+
+    /// Get the details of the association between the given actor and use
+    /// case.
+    pub fn association(&self, actor_id: ActorId, use_case_id: UseCaseId)
+                       -> Option<&Association<S>> {
+        self.associations.get(&(actor_id, use_case_id))
+    }
+
+    /// Get mutable access to the details of the association between the
+    /// given actor and use case.
+    pub fn association_mut(&mut self, actor_id: ActorId, use_case_id: UseCaseId)
+                           -> Option<&mut Association<S>> {
+        if self.associations.contains_key(&(actor_id, use_case_id)) {
+            self.emit(Event::AssociationChanged(actor_id, use_case_id));
+        }
+        self.associations.get_mut(&(actor_id, use_case_id))
+    }
+
+    /// All includes in this use case diagram, as pairs of the including and
+    /// the included use case.
+    pub fn includes(&self) -> Includes<'_> {
+        Includes{inner: self.includes.iter()}
+    }
+
+    /// The use cases directly included by the given use case.
+    pub fn included_use_cases(&self, use_case_id: UseCaseId)
+                              -> impl Iterator<Item = UseCaseId> + '_ {
+        self.includes()
+            .filter(move |&(base, _)| base == use_case_id)
+            .map(|(_, included)| included)
+    }
+
+    /// All dependencies in this use case diagram, as pairs of the dependent
+    /// use case and the use case it depends on.
+    pub fn dependencies(&self) -> Dependencies<'_, S> {
+        Dependencies{inner: self.dependencies.keys()}
+    }
+
+    /// The label of the dependency of one use case on another, if the
+    /// dependency exists and has a label.
+    pub fn dependency_label(&self, dependent: UseCaseId, dependency: UseCaseId) -> Option<&S> {
+        self.dependencies.get(&(dependent, dependency)).and_then(|label| label.as_ref())
+    }
+
+    /// The use cases the given use case directly depends on.
+    pub fn use_case_dependencies(&self, use_case_id: UseCaseId)
+                                 -> impl Iterator<Item = UseCaseId> + '_ {
+        self.dependencies()
+            .filter(move |&(dependent, _)| dependent == use_case_id)
+            .map(|(_, dependency)| dependency)
+    }
+
+    /// The given use case and every use case it depends on, directly or
+    /// through other dependencies.
+    pub fn transitive_use_case_dependencies(&self, use_case_id: UseCaseId)
+                                            -> HashSet<UseCaseId> {
+        let mut reached = HashSet::new();
+        let mut pending = vec![use_case_id];
+        while let Some(use_case_id) = pending.pop() {
+            if reached.insert(use_case_id) {
+                pending.extend(self.use_case_dependencies(use_case_id));
+            }
+        }
+        reached
+    }
+
+    /// All extension points in this use case diagram.
+    pub fn extension_points(&self) -> ExtensionPoints<'_, S> {
+        ExtensionPoints{inner: self.extension_points.iter()}
+    }
+
+    /// The extension points of the given use case.
+    pub fn extension_points_of_use_case(&self, use_case_id: UseCaseId)
+                                        -> impl Iterator<Item = (ExtensionPointId,
+                                                                 &ExtensionPoint<S>)> {
+        self.extension_points()
+            .filter(move |&(_, extension_point)| extension_point.use_case == use_case_id)
+    }
+
+    /// All system boundaries in this use case diagram.
+    pub fn system_boundaries(&self) -> SystemBoundaries<'_, S> {
+        SystemBoundaries{inner: self.system_boundaries.iter()}
+    }
+
+    /// All notes in this use case diagram.
+    pub fn notes(&self) -> Notes<'_, S> {
+        Notes{inner: self.notes.iter()}
+    }
+
+    /// The notes attached to the given element.
+    pub fn notes_of(&self, anchor: NoteAnchor) -> impl Iterator<Item = (NoteId, &Note<S>)> {
+        self.notes().filter(move |&(_, note)| note.anchor == anchor)
+    }
+
+    /// The system boundaries that contain the given use case.
+    pub fn system_boundaries_of_use_case(&self, use_case_id: UseCaseId)
+                                         -> impl Iterator<Item = (SystemBoundaryId,
+                                                                  &SystemBoundary<S>)> {
+        self.system_boundaries()
+            .filter(move |&(_, system_boundary)| system_boundary.use_cases.contains(&use_case_id))
+    }
+
+    /// All extends in this use case diagram.
+    pub fn extends(&self) -> Extends<'_> {
+        Extends{inner: self.extends.iter()}
+    }
+
+    /// All actor generalizations in this use case diagram, as pairs of the
+    /// child and the parent actor.
+    pub fn actor_generalizations(&self) -> ActorGeneralizations<'_> {
+        ActorGeneralizations{inner: self.actor_generalizations.iter()}
+    }
+
+    /// The direct parents of the given actor.
+    pub fn actor_parents(&self, actor_id: ActorId) -> impl Iterator<Item = ActorId> + '_ {
+        self.actor_generalizations()
+            .filter(move |&(child, _)| child == actor_id)
+            .map(|(_, parent)| parent)
+    }
+
+    /// The given actor and all of its ancestors.
+    pub fn actor_ancestors(&self, actor_id: ActorId) -> HashSet<ActorId> {
+        let mut reached = HashSet::new();
+        let mut pending = vec![actor_id];
+        while let Some(actor_id) = pending.pop() {
+            if reached.insert(actor_id) {
+                pending.extend(self.actor_parents(actor_id));
+            }
+        }
+        reached
+    }
+
+    /// The given actor and all of its specializations, directly or through
+    /// other specializations.
+    pub fn actor_descendants(&self, actor_id: ActorId) -> HashSet<ActorId> {
+        let mut reached = HashSet::new();
+        let mut pending = vec![actor_id];
+        while let Some(actor_id) = pending.pop() {
+            if reached.insert(actor_id) {
+                pending.extend(self.actor_generalizations()
+                               .filter(|&(_, parent)| parent == actor_id)
+                               .map(|(child, _)| child));
+            }
+        }
+        reached
+    }
+
+    /// The use cases the given actor is associated with, directly or through
+    /// any of its ancestors, together with all specializations of those use
+    /// cases.
+    pub fn effective_use_cases(&self, actor_id: ActorId) -> HashSet<UseCaseId> {
+        self.actor_ancestors(actor_id).into_iter()
+            .flat_map(|ancestor_id| self.use_cases_of_actor(ancestor_id))
+            .flat_map(|use_case_id| self.use_case_descendants(use_case_id))
+            .collect()
+    }
+
+    /// The actors associated with the given use case, directly or through
+    /// generalization of either the actor or the use case.
+    pub fn effective_actors(&self, use_case_id: UseCaseId) -> HashSet<ActorId> {
+        self.use_case_ancestors(use_case_id).into_iter()
+            .flat_map(|ancestor_id| self.actors_of_use_case(ancestor_id))
+            .flat_map(|actor_id| self.actor_descendants(actor_id))
+            .collect()
+    }
+
+    /// All use case generalizations in this use case diagram, as pairs of the
+    /// child and the parent use case.
+    pub fn use_case_generalizations(&self) -> UseCaseGeneralizations<'_> {
+        UseCaseGeneralizations{inner: self.use_case_generalizations.iter()}
+    }
+
+    /// The direct parents of the given use case.
+    pub fn use_case_parents(&self, use_case_id: UseCaseId)
+                            -> impl Iterator<Item = UseCaseId> + '_ {
+        self.use_case_generalizations()
+            .filter(move |&(child, _)| child == use_case_id)
+            .map(|(_, parent)| parent)
+    }
+
+    /// The given use case and all of its ancestors.
+    pub fn use_case_ancestors(&self, use_case_id: UseCaseId) -> HashSet<UseCaseId> {
+        let mut reached = HashSet::new();
+        let mut pending = vec![use_case_id];
+        while let Some(use_case_id) = pending.pop() {
+            if reached.insert(use_case_id) {
+                pending.extend(self.use_case_parents(use_case_id));
+            }
+        }
+        reached
+    }
+
+    /// The given use case and all of its specializations, directly or
+    /// through other specializations.
+    pub fn use_case_descendants(&self, use_case_id: UseCaseId) -> HashSet<UseCaseId> {
+        let mut reached = HashSet::new();
+        let mut pending = vec![use_case_id];
+        while let Some(use_case_id) = pending.pop() {
+            if reached.insert(use_case_id) {
+                pending.extend(self.use_case_generalizations()
+                               .filter(|&(_, parent)| parent == use_case_id)
+                               .map(|(child, _)| child));
+            }
+        }
+        reached
+    }
+
+    /// All associations of the given actor.
+    pub fn associations_of_actor(&self, actor_id: ActorId)
+                                 -> impl Iterator<Item = (ActorId, UseCaseId)> + '_ {
+        self.use_cases_of_actor(actor_id).map(move |use_case_id| (actor_id, use_case_id))
     }
 
-    /// Insert a new association. Return an error if either the actor or the
+    /// The use cases directly associated with the given actor. Empty if the
+    /// actor does not exist.
+    pub fn use_cases_of_actor(&self, actor_id: ActorId)
+                              -> impl Iterator<Item = UseCaseId> + '_ {
+        self.actor_use_cases.get(&actor_id).into_iter().flatten().cloned()
+    }
+
+    /// The actors directly associated with the given use case. Empty if the
     /// use case does not exist.
+    pub fn actors_of_use_case(&self, use_case_id: UseCaseId)
+                              -> impl Iterator<Item = ActorId> + '_ {
+        self.use_case_actors.get(&use_case_id).into_iter().flatten().cloned()
+    }
+
+    /// Insert new actors, returning their identifiers in order. Return an
+    /// error, inserting none of them, if this diagram has unique names and a
+    /// name is taken or given twice. Invariants are checked once for the
+    /// whole batch rather than after every actor.
+    pub fn extend_actors<I>(&mut self, actors: I) -> Result<Vec<ActorId>, NameError>
+        where I: IntoIterator<Item = Actor<S>> {
+        let actors = actors.into_iter().collect::<Vec<_>>();
+        if self.unique_names {
+            let mut names = HashMap::new();
+            for (i, actor) in actors.iter().enumerate() {
+                let actor_id = ActorId(self.next_actor_id + i);
+                if let Some((other_id, _)) = self.actor_by_name(actor.name.as_ref()) {
+                    return Err(NameError::ActorNameTaken(other_id));
+                }
+                if let Some(other_id) = names.insert(actor.name.as_ref(), actor_id) {
+                    return Err(NameError::DuplicateActorName(other_id, actor_id));
+                }
+            }
+        }
+        self.batch(|diagram| actors.into_iter().map(|actor| diagram.insert_actor(actor)).collect())
+    }
+
+    /// Insert new use cases, returning their identifiers in order. Return an
+    /// error, inserting none of them, if this diagram has unique names and a
+    /// title is taken or given twice. Invariants are checked once for the
+    /// whole batch rather than after every use case.
+    pub fn extend_use_cases<I>(&mut self, use_cases: I) -> Result<Vec<UseCaseId>, NameError>
+        where I: IntoIterator<Item = UseCase<S>> {
+        let use_cases = use_cases.into_iter().collect::<Vec<_>>();
+        if self.unique_names {
+            let mut titles = HashMap::new();
+            for (i, use_case) in use_cases.iter().enumerate() {
+                let use_case_id = UseCaseId(self.next_use_case_id + i);
+                if let Some((other_id, _)) = self.use_case_by_title(use_case.title.as_ref()) {
+                    return Err(NameError::UseCaseTitleTaken(other_id));
+                }
+                if let Some(other_id) = titles.insert(use_case.title.as_ref(), use_case_id) {
+                    return Err(NameError::DuplicateUseCaseTitle(other_id, use_case_id));
+                }
+            }
+        }
+        self.batch(|diagram| {
+            use_cases.into_iter().map(|use_case| diagram.insert_use_case(use_case)).collect()
+        })
+    }
+
+    /// Insert new associations as by `insert_association`. Return an error,
+    /// inserting none of them, if any actor or use case does not exist.
+    /// Invariants are checked once for the whole batch rather than after
+    /// every association.
+    pub fn extend_associations<I>(&mut self, associations: I) -> Result<(), AssociationError>
+        where I: IntoIterator<Item = (ActorId, UseCaseId)> {
+        let associations = associations.into_iter().collect::<Vec<_>>();
+        for &(actor_id, use_case_id) in &associations {
+            if !self.actors.contains_key(&actor_id) {
+                return Err(AssociationError::NonexistentActor(actor_id));
+            }
+            if !self.use_cases.contains_key(&use_case_id) {
+                return Err(AssociationError::NonexistentUseCase(use_case_id));
+            }
+        }
+        self.batch(|diagram| {
+            associations.into_iter()
+                .try_for_each(|(actor_id, use_case_id)|
+                              diagram.insert_association(actor_id, use_case_id))
+        })
+    }
+
+    /// Run the given changes with per-change invariant checks turned off,
+    /// checking once afterwards instead. The setting is restored even if the
+    /// changes panic.
+    fn batch<T, F>(&mut self, f: F) -> T where F: FnOnce(&mut UseCaseDiagram<S>) -> T {
+        let guard = Batch{invariant_checks: self.invariant_checks, diagram: self};
+        guard.diagram.invariant_checks = false;
+        let result = f(guard.diagram);
+        drop(guard);
+        self.assert_invariants();
+        result
+    }
+
+    /// Insert a new association without a label, multiplicities, or
+    /// navigability. An existing association keeps its details. Return an
+    /// error if either the actor or the use case does not exist.
     pub fn insert_association(&mut self, actor_id: ActorId, use_case_id: UseCaseId)
                               -> Result<(), AssociationError> {
+        if !self.associations.contains_key(&(actor_id, use_case_id)) {
+            self.insert_association_with(actor_id, use_case_id, Association::default())?;
+        }
+        Ok(())
+    }
+
+    /// Insert a new association with the given details, replacing the
+    /// details of an existing association. Return an error if either the
+    /// actor or the use case does not exist.
+    pub fn insert_association_with(&mut self, actor_id: ActorId, use_case_id: UseCaseId,
+                                   association: Association<S>)
+                                   -> Result<(), AssociationError> {
         if !self.actors.contains_key(&actor_id) {
             return Err(AssociationError::NonexistentActor(actor_id));
         }
         if !self.use_cases.contains_key(&use_case_id) {
             return Err(AssociationError::NonexistentUseCase(use_case_id));
         }
-        self.associations.insert((actor_id, use_case_id));
+        let replaced = self.associations.insert((actor_id, use_case_id), association).is_some();
+        self.actor_use_cases.entry(actor_id).or_default().insert(use_case_id);
+        self.use_case_actors.entry(use_case_id).or_default().insert(actor_id);
+        self.emit(if replaced {
+            Event::AssociationChanged(actor_id, use_case_id)
+        } else {
+            Event::AssociationInserted(actor_id, use_case_id)
+        });
+        self.assert_invariants();
+        Ok(())
+    }
+
+    /// Insert a new include of one use case by another. Return an error if
+    /// either use case does not exist, or if the include would close a cycle.
+    pub fn insert_include(&mut self, base: UseCaseId, included: UseCaseId)
+                          -> Result<(), RelationshipError> {
+        if !self.use_cases.contains_key(&base) {
+            return Err(RelationshipError::NonexistentUseCase(base));
+        }
+        if !self.use_cases.contains_key(&included) {
+            return Err(RelationshipError::NonexistentUseCase(included));
+        }
+        if self.transitively_included_use_cases(included).contains(&base) {
+            return Err(RelationshipError::UseCaseCycle(base, included));
+        }
+        if self.includes.insert((base, included)) {
+            self.emit(Event::IncludeInserted(base, included));
+        }
         self.assert_invariants();
         Ok(())
     }
 
+    /// Insert a new dependency of one use case on another, e.g. to say that a
+    /// subscriber must be created before they can post a comment. An
+    /// existing dependency gets the given label. Return an error if either
+    /// use case does not exist, or if the dependency would close a cycle.
+    pub fn insert_dependency(&mut self, dependent: UseCaseId, dependency: UseCaseId,
+                             label: Option<S>)
+                             -> Result<(), RelationshipError> {
+        if !self.use_cases.contains_key(&dependent) {
+            return Err(RelationshipError::NonexistentUseCase(dependent));
+        }
+        if !self.use_cases.contains_key(&dependency) {
+            return Err(RelationshipError::NonexistentUseCase(dependency));
+        }
+        if self.transitive_use_case_dependencies(dependency).contains(&dependent) {
+            return Err(RelationshipError::UseCaseCycle(dependent, dependency));
+        }
+        let replaced = self.dependencies.insert((dependent, dependency), label).is_some();
+        self.emit(if replaced {
+            Event::DependencyChanged(dependent, dependency)
+        } else {
+            Event::DependencyInserted(dependent, dependency)
+        });
+        self.assert_invariants();
+        Ok(())
+    }
+
+    /// Remove a dependency, returning whether it existed.
+    pub fn remove_dependency(&mut self, dependent: UseCaseId, dependency: UseCaseId) -> bool {
+        let removed = self.dependencies.remove(&(dependent, dependency)).is_some();
+        if removed {
+            self.emit(Event::DependencyRemoved(dependent, dependency));
+        }
+        self.assert_invariants();
+        removed
+    }
+
+    /// Insert a new extension point, returning its identifier. Return an
+    /// error if its use case does not exist.
+    pub fn insert_extension_point(&mut self, extension_point: ExtensionPoint<S>)
+                                  -> Result<ExtensionPointId, RelationshipError> {
+        if !self.use_cases.contains_key(&extension_point.use_case) {
+            return Err(RelationshipError::NonexistentUseCase(extension_point.use_case));
+        }
+        let extension_point_id = self.next_extension_point_id();
+        self.extension_points.insert(extension_point_id, extension_point);
+        self.emit(Event::ExtensionPointInserted(extension_point_id));
+        self.assert_invariants();
+        Ok(extension_point_id)
+    }
+
+    /// Insert a new system boundary, returning its identifier. Return an
+    /// error if any of its use cases does not exist.
+    pub fn insert_system_boundary(&mut self, system_boundary: SystemBoundary<S>)
+                                  -> Result<SystemBoundaryId, RelationshipError> {
+        let mut use_case_ids = system_boundary.use_cases.iter().collect::<Vec<_>>();
+        use_case_ids.sort();
+        for &&use_case_id in &use_case_ids {
+            if !self.use_cases.contains_key(&use_case_id) {
+                return Err(RelationshipError::NonexistentUseCase(use_case_id));
+            }
+        }
+        let system_boundary_id = self.next_system_boundary_id();
+        self.system_boundaries.insert(system_boundary_id, system_boundary);
+        self.emit(Event::SystemBoundaryInserted(system_boundary_id));
+        self.assert_invariants();
+        Ok(system_boundary_id)
+    }
+
+    /// Place a use case inside a system boundary. Return an error if either
+    /// does not exist.
+    pub fn insert_system_boundary_use_case(&mut self, system_boundary_id: SystemBoundaryId,
+                                           use_case_id: UseCaseId)
+                                           -> Result<(), RelationshipError> {
+        if !self.use_cases.contains_key(&use_case_id) {
+            return Err(RelationshipError::NonexistentUseCase(use_case_id));
+        }
+        let inserted = match self.system_boundaries.get_mut(&system_boundary_id) {
+            Some(system_boundary) => system_boundary.use_cases.insert(use_case_id),
+            None => return Err(RelationshipError::NonexistentSystemBoundary(system_boundary_id)),
+        };
+        if inserted {
+            self.emit(Event::SystemBoundaryChanged(system_boundary_id));
+        }
+        self.assert_invariants();
+        Ok(())
+    }
+
+    /// Take a use case out of a system boundary, returning whether it was
+    /// inside.
+    pub fn remove_system_boundary_use_case(&mut self, system_boundary_id: SystemBoundaryId,
+                                           use_case_id: UseCaseId) -> bool {
+        let removed =
+            self.system_boundaries.get_mut(&system_boundary_id)
+            .is_some_and(|system_boundary| system_boundary.use_cases.remove(&use_case_id));
+        if removed {
+            self.emit(Event::SystemBoundaryChanged(system_boundary_id));
+        }
+        self.assert_invariants();
+        removed
+    }
+
+    /// Remove a system boundary, returning it if it existed. The use cases
+    /// inside it are kept.
+    pub fn remove_system_boundary(&mut self, system_boundary_id: SystemBoundaryId)
+                                  -> Option<SystemBoundary<S>> {
+        let system_boundary = self.system_boundaries.remove(&system_boundary_id)?;
+        self.emit(Event::SystemBoundaryRemoved(system_boundary_id));
+        self.assert_invariants();
+        Some(system_boundary)
+    }
+
+    /// Insert a new note, returning its identifier. Return an error if the
+    /// element it is attached to does not exist.
+    pub fn insert_note(&mut self, note: Note<S>) -> Result<NoteId, RelationshipError> {
+        match note.anchor {
+            NoteAnchor::Actor(actor_id) if !self.actors.contains_key(&actor_id) =>
+                return Err(RelationshipError::NonexistentActor(actor_id)),
+            NoteAnchor::UseCase(use_case_id) if !self.use_cases.contains_key(&use_case_id) =>
+                return Err(RelationshipError::NonexistentUseCase(use_case_id)),
+            NoteAnchor::Association(actor_id, use_case_id)
+                if !self.associations.contains_key(&(actor_id, use_case_id)) =>
+                return Err(RelationshipError::NonexistentAssociation(actor_id, use_case_id)),
+            _ => (),
+        }
+        let note_id = self.next_note_id();
+        self.notes.insert(note_id, note);
+        self.emit(Event::NoteInserted(note_id));
+        self.assert_invariants();
+        Ok(note_id)
+    }
+
+    /// Remove a note, returning it if it existed.
+    pub fn remove_note(&mut self, note_id: NoteId) -> Option<Note<S>> {
+        let note = self.notes.remove(&note_id)?;
+        self.emit(Event::NoteRemoved(note_id));
+        self.assert_invariants();
+        Some(note)
+    }
+
+    /// Insert a new extend. Return an error if either use case or the
+    /// extension point does not exist, if the extension point is not in the
+    /// base use case, or if the extend would close a cycle.
+    pub fn insert_extend(&mut self, extend: Extend) -> Result<(), RelationshipError> {
+        if !self.use_cases.contains_key(&extend.extension) {
+            return Err(RelationshipError::NonexistentUseCase(extend.extension));
+        }
+        if !self.use_cases.contains_key(&extend.base) {
+            return Err(RelationshipError::NonexistentUseCase(extend.base));
+        }
+        if let Some(extension_point_id) = extend.extension_point {
+            match self.extension_points.get(&extension_point_id) {
+                None =>
+                    return Err(RelationshipError::NonexistentExtensionPoint(
+                        extension_point_id)),
+                Some(extension_point) if extension_point.use_case != extend.base =>
+                    return Err(RelationshipError::ForeignExtensionPoint(
+                        extension_point_id, extend.base)),
+                Some(_) => (),
+            }
+        }
+        let mut reached = HashSet::new();
+        let mut pending = vec![extend.base];
+        while let Some(use_case_id) = pending.pop() {
+            if use_case_id == extend.extension {
+                return Err(RelationshipError::UseCaseCycle(extend.extension, extend.base));
+            }
+            if reached.insert(use_case_id) {
+                pending.extend(self.extends.iter()
+                               .filter(|other| other.extension == use_case_id)
+                               .map(|other| other.base));
+            }
+        }
+        if self.extends.insert(extend) {
+            self.emit(Event::ExtendInserted(extend));
+        }
+        self.assert_invariants();
+        Ok(())
+    }
+
+    /// Remove an extend, returning whether it existed.
+    pub fn remove_extend(&mut self, extend: Extend) -> bool {
+        let removed = self.extends.remove(&extend);
+        if removed {
+            self.emit(Event::ExtendRemoved(extend));
+        }
+        self.assert_invariants();
+        removed
+    }
+
+    /// Remove an extension point, returning it if it existed. All extends at
+    /// the extension point are removed along with it.
+    pub fn remove_extension_point(&mut self, extension_point_id: ExtensionPointId)
+                                  -> Option<ExtensionPoint<S>> {
+        let extension_point = self.extension_points.remove(&extension_point_id)?;
+        self.remove_extends_where(|extend| extend.extension_point == Some(extension_point_id));
+        self.emit(Event::ExtensionPointRemoved(extension_point_id));
+        self.assert_invariants();
+        Some(extension_point)
+    }
+
+    /// Insert a new actor generalization, making the child actor a
+    /// specialization of the parent actor. Return an error if either actor
+    /// does not exist, or if the generalization would close a cycle.
+    pub fn insert_actor_generalization(&mut self, child: ActorId, parent: ActorId)
+                                       -> Result<(), RelationshipError> {
+        if !self.actors.contains_key(&child) {
+            return Err(RelationshipError::NonexistentActor(child));
+        }
+        if !self.actors.contains_key(&parent) {
+            return Err(RelationshipError::NonexistentActor(parent));
+        }
+        if self.actor_ancestors(parent).contains(&child) {
+            return Err(RelationshipError::ActorCycle(child, parent));
+        }
+        if self.actor_generalizations.insert((child, parent)) {
+            self.emit(Event::ActorGeneralizationInserted(child, parent));
+        }
+        self.assert_invariants();
+        Ok(())
+    }
+
+    /// Insert a new use case generalization, making the child use case a
+    /// specialization of the parent use case. Return an error if either use
+    /// case does not exist, or if the generalization would close a cycle.
+    pub fn insert_use_case_generalization(&mut self, child: UseCaseId, parent: UseCaseId)
+                                          -> Result<(), RelationshipError> {
+        if !self.use_cases.contains_key(&child) {
+            return Err(RelationshipError::NonexistentUseCase(child));
+        }
+        if !self.use_cases.contains_key(&parent) {
+            return Err(RelationshipError::NonexistentUseCase(parent));
+        }
+        if self.use_case_ancestors(parent).contains(&child) {
+            return Err(RelationshipError::UseCaseCycle(child, parent));
+        }
+        if self.use_case_generalizations.insert((child, parent)) {
+            self.emit(Event::UseCaseGeneralizationInserted(child, parent));
+        }
+        self.assert_invariants();
+        Ok(())
+    }
+
+    /// Remove a use case generalization, returning whether it existed.
+    pub fn remove_use_case_generalization(&mut self, child: UseCaseId, parent: UseCaseId)
+                                          -> bool {
+        let removed = self.use_case_generalizations.remove(&(child, parent));
+        if removed {
+            self.emit(Event::UseCaseGeneralizationRemoved(child, parent));
+        }
+        self.assert_invariants();
+        removed
+    }
+
+    /// Remove an actor generalization, returning whether it existed.
+    pub fn remove_actor_generalization(&mut self, child: ActorId, parent: ActorId) -> bool {
+        let removed = self.actor_generalizations.remove(&(child, parent));
+        if removed {
+            self.emit(Event::ActorGeneralizationRemoved(child, parent));
+        }
+        self.assert_invariants();
+        removed
+    }
+
+    /// Remove an include, returning whether it existed.
+    pub fn remove_include(&mut self, base: UseCaseId, included: UseCaseId) -> bool {
+        let removed = self.includes.remove(&(base, included));
+        if removed {
+            self.emit(Event::IncludeRemoved(base, included));
+        }
+        self.assert_invariants();
+        removed
+    }
+
+    /// The given use case and every use case it includes, directly or
+    /// through other includes.
+    pub fn transitively_included_use_cases(&self, use_case_id: UseCaseId)
+                                           -> HashSet<UseCaseId> {
+        let mut reached = HashSet::new();
+        let mut pending = vec![use_case_id];
+        while let Some(use_case_id) = pending.pop() {
+            if reached.insert(use_case_id) {
+                pending.extend(self.included_use_cases(use_case_id));
+            }
+        }
+        reached
+    }
+
+    /// Remove an association, returning whether it existed.
+    pub fn remove_association(&mut self, actor_id: ActorId, use_case_id: UseCaseId) -> bool {
+        let removed = self.associations.remove(&(actor_id, use_case_id)).is_some();
+        self.remove_notes_where(|anchor| anchor == NoteAnchor::Association(actor_id, use_case_id));
+        if removed {
+            self.unindex_association(actor_id, use_case_id);
+            self.emit(Event::AssociationRemoved(actor_id, use_case_id));
+        }
+        self.assert_invariants();
+        removed
+    }
+
+    /// Remove an actor, returning it if it existed. All associations,
+    /// generalizations, and notes of the actor are removed along with it.
+    pub fn remove_actor(&mut self, actor_id: ActorId) -> Option<Actor<S>> {
+        let actor = self.actors.remove(&actor_id)?;
+        unindex_name(&mut self.actor_names, actor.name.as_ref(), actor_id);
+        unindex_key(&mut self.actor_keys, actor.key.as_ref().map(AsRef::as_ref), actor_id);
+        self.remove_notes_where(|anchor| match anchor {
+            NoteAnchor::Actor(anchor_actor_id) |
+            NoteAnchor::Association(anchor_actor_id, _) => anchor_actor_id == actor_id,
+            NoteAnchor::UseCase(_) => false,
+        });
+        for use_case_id in self.actor_use_cases.remove(&actor_id).unwrap_or_default() {
+            self.associations.remove(&(actor_id, use_case_id));
+            self.unindex_association(actor_id, use_case_id);
+            self.emit(Event::AssociationRemoved(actor_id, use_case_id));
+        }
+        let generalizations =
+            self.actor_generalizations.iter()
+            .filter(|&&(child, parent)| child == actor_id || parent == actor_id)
+            .cloned()
+            .collect::<Vec<_>>();
+        for (child, parent) in generalizations {
+            self.actor_generalizations.remove(&(child, parent));
+            self.emit(Event::ActorGeneralizationRemoved(child, parent));
+        }
+        self.emit(Event::ActorRemoved(actor_id));
+        self.assert_invariants();
+        Some(actor)
+    }
+
+    /// Remove a use case, returning it if it existed. All associations,
+    /// includes, dependencies, extends, generalizations, extension points,
+    /// and notes of the use case are removed along with it, and it is taken
+    /// out of every system boundary.
+    pub fn remove_use_case(&mut self, use_case_id: UseCaseId) -> Option<UseCase<S>> {
+        let use_case = self.use_cases.remove(&use_case_id)?;
+        unindex_name(&mut self.use_case_titles, use_case.title.as_ref(), use_case_id);
+        unindex_key(&mut self.use_case_keys, use_case.key.as_ref().map(AsRef::as_ref),
+                    use_case_id);
+        self.remove_notes_where(|anchor| match anchor {
+            NoteAnchor::UseCase(anchor_use_case_id) |
+            NoteAnchor::Association(_, anchor_use_case_id) => anchor_use_case_id == use_case_id,
+            NoteAnchor::Actor(_) => false,
+        });
+        for actor_id in self.use_case_actors.remove(&use_case_id).unwrap_or_default() {
+            self.associations.remove(&(actor_id, use_case_id));
+            self.unindex_association(actor_id, use_case_id);
+            self.emit(Event::AssociationRemoved(actor_id, use_case_id));
+        }
+        let includes =
+            self.includes.iter()
+            .filter(|&&(base, included)| base == use_case_id || included == use_case_id)
+            .cloned()
+            .collect::<Vec<_>>();
+        for (base, included) in includes {
+            self.includes.remove(&(base, included));
+            self.emit(Event::IncludeRemoved(base, included));
+        }
+        let dependencies =
+            self.dependencies()
+            .filter(|&(dependent, dependency)| dependent == use_case_id ||
+                                               dependency == use_case_id)
+            .collect::<Vec<_>>();
+        for (dependent, dependency) in dependencies {
+            self.dependencies.remove(&(dependent, dependency));
+            self.emit(Event::DependencyRemoved(dependent, dependency));
+        }
+        self.remove_extends_where(|extend| extend.extension == use_case_id ||
+                                           extend.base == use_case_id);
+        let extension_point_ids =
+            self.extension_points_of_use_case(use_case_id)
+            .map(|(extension_point_id, _)| extension_point_id)
+            .collect::<Vec<_>>();
+        for extension_point_id in extension_point_ids {
+            self.extension_points.remove(&extension_point_id);
+            self.emit(Event::ExtensionPointRemoved(extension_point_id));
+        }
+        let generalizations =
+            self.use_case_generalizations.iter()
+            .filter(|&&(child, parent)| child == use_case_id || parent == use_case_id)
+            .cloned()
+            .collect::<Vec<_>>();
+        for (child, parent) in generalizations {
+            self.use_case_generalizations.remove(&(child, parent));
+            self.emit(Event::UseCaseGeneralizationRemoved(child, parent));
+        }
+        let system_boundary_ids =
+            self.system_boundaries_of_use_case(use_case_id)
+            .map(|(system_boundary_id, _)| system_boundary_id)
+            .collect::<Vec<_>>();
+        for system_boundary_id in system_boundary_ids {
+            self.system_boundaries.get_mut(&system_boundary_id).unwrap()
+                .use_cases.remove(&use_case_id);
+            self.emit(Event::SystemBoundaryChanged(system_boundary_id));
+        }
+        self.emit(Event::UseCaseRemoved(use_case_id));
+        self.assert_invariants();
+        Some(use_case)
+    }
+
+    /// Remove every actor for which the predicate returns false, along with
+    /// its relationships and notes as in `remove_actor`.
+    pub fn retain_actors<F>(&mut self, mut predicate: F)
+        where F: FnMut(ActorId, &Actor<S>) -> bool {
+        let actor_ids =
+            self.actors()
+            .filter(|&(actor_id, actor)| !predicate(actor_id, actor))
+            .map(|(actor_id, _)| actor_id)
+            .collect::<Vec<_>>();
+        self.batch(|diagram| for actor_id in actor_ids {
+            diagram.remove_actor(actor_id);
+        });
+    }
+
+    /// Remove every use case for which the predicate returns false, along
+    /// with its relationships, extension points, and notes as in
+    /// `remove_use_case`.
+    pub fn retain_use_cases<F>(&mut self, mut predicate: F)
+        where F: FnMut(UseCaseId, &UseCase<S>) -> bool {
+        let use_case_ids =
+            self.use_cases()
+            .filter(|&(use_case_id, use_case)| !predicate(use_case_id, use_case))
+            .map(|(use_case_id, _)| use_case_id)
+            .collect::<Vec<_>>();
+        self.batch(|diagram| for use_case_id in use_case_ids {
+            diagram.remove_use_case(use_case_id);
+        });
+    }
+
+    /// Merge two actors into one. Every association, generalization, and note
+    /// of the removed actor is moved onto the kept actor, after which the
+    /// removed actor is deleted. Generalizations that would relate the kept
+    /// actor to itself or close a cycle are dropped. Return an error if
+    /// either actor does not exist.
+    pub fn merge_actors(&mut self, keep: ActorId, remove: ActorId)
+                        -> Result<(), AssociationError> {
+        if !self.actors.contains_key(&keep) {
+            return Err(AssociationError::NonexistentActor(keep));
+        }
+        if !self.actors.contains_key(&remove) {
+            return Err(AssociationError::NonexistentActor(remove));
+        }
+        if keep == remove {
+            return Ok(());
+        }
+        let moved = self.actor_use_cases.remove(&remove).unwrap_or_default();
+        for use_case_id in moved {
+            let association = self.associations.remove(&(remove, use_case_id)).unwrap();
+            self.unindex_association(remove, use_case_id);
+            self.emit(Event::AssociationRemoved(remove, use_case_id));
+            if let btree_map::Entry::Vacant(entry) = self.associations.entry((keep, use_case_id)) {
+                entry.insert(association);
+                self.actor_use_cases.entry(keep).or_default().insert(use_case_id);
+                self.use_case_actors.entry(use_case_id).or_default().insert(keep);
+                self.emit(Event::AssociationInserted(keep, use_case_id));
+            }
+        }
+        let generalizations =
+            self.actor_generalizations.iter()
+            .filter(|&&(child, parent)| child == remove || parent == remove)
+            .cloned()
+            .collect::<Vec<_>>();
+        for &(child, parent) in &generalizations {
+            self.actor_generalizations.remove(&(child, parent));
+            self.emit(Event::ActorGeneralizationRemoved(child, parent));
+        }
+        for (child, parent) in generalizations {
+            let replace = |actor_id| if actor_id == remove { keep } else { actor_id };
+            let _ = self.insert_actor_generalization(replace(child), replace(parent));
+        }
+        let note_ids = self.notes.keys().cloned().collect::<Vec<_>>();
+        for note_id in note_ids {
+            let anchor = self.notes[&note_id].anchor;
+            let new_anchor = match anchor {
+                NoteAnchor::Actor(actor_id) if actor_id == remove =>
+                    NoteAnchor::Actor(keep),
+                NoteAnchor::Association(actor_id, use_case_id) if actor_id == remove =>
+                    NoteAnchor::Association(keep, use_case_id),
+                anchor => anchor,
+            };
+            if new_anchor != anchor {
+                self.notes.get_mut(&note_id).unwrap().anchor = new_anchor;
+                self.emit(Event::NoteChanged(note_id));
+            }
+        }
+        let removed = self.actors.remove(&remove).unwrap();
+        unindex_name(&mut self.actor_names, removed.name.as_ref(), remove);
+        unindex_key(&mut self.actor_keys, removed.key.as_ref().map(AsRef::as_ref), remove);
+        self.emit(Event::ActorRemoved(remove));
+        self.assert_invariants();
+        Ok(())
+    }
+
+    /// Apply a batch of changes atomically. The closure is given this
+    /// diagram to change; if it returns an error, every change it made is
+    /// undone, including the events it recorded, and the error is returned.
+    pub fn transaction<T, E, F>(&mut self, f: F) -> Result<T, E>
+        where S: Clone, F: FnOnce(&mut UseCaseDiagram<S>) -> Result<T, E> {
+        let before = self.clone();
+        let result = f(self);
+        if result.is_err() {
+            *self = before;
+        }
+        result
+    }
+
+    /// Check that this diagram is internally consistent, returning every
+    /// violation found. Diagrams changed only through this module's API
+    /// always are; this is for diagnosing corruption, e.g. through a bug in
+    /// this module. See also `set_invariant_checks`.
+    pub fn validate(&self) -> Result<(), Vec<InvariantViolation>> {
+        let mut violations = Vec::new();
+
+        let counter_stale =
+            self.actors.keys().next_back().is_some_and(|id| id.0 >= self.next_actor_id) ||
+            self.use_cases.keys().next_back().is_some_and(|id| id.0 >= self.next_use_case_id) ||
+            self.extension_points.keys().next_back()
+                .is_some_and(|id| id.0 >= self.next_extension_point_id) ||
+            self.system_boundaries.keys().next_back()
+                .is_some_and(|id| id.0 >= self.next_system_boundary_id) ||
+            self.notes.keys().next_back().is_some_and(|id| id.0 >= self.next_note_id);
+        if counter_stale {
+            violations.push(InvariantViolation::StaleIdentifierCounter);
+        }
+
+        let names_match =
+            index_matches(&self.actor_names,
+                          self.actors.iter().map(|(&id, actor)| (id, actor.name.as_ref()))) &&
+            index_matches(&self.use_case_titles,
+                          self.use_cases.iter()
+                          .map(|(&id, use_case)| (id, use_case.title.as_ref()))) &&
+            index_matches(&self.actor_keys,
+                          self.actors.iter()
+                          .filter_map(|(&id, actor)| Some((id, actor.key.as_ref()?.as_ref())))) &&
+            index_matches(&self.use_case_keys,
+                          self.use_cases.iter()
+                          .filter_map(|(&id, use_case)| {
+                              Some((id, use_case.key.as_ref()?.as_ref()))
+                          }));
+        if !names_match {
+            violations.push(InvariantViolation::StaleNameIndex);
+        }
+        if self.unique_names {
+            for ids in self.actor_names.values().filter(|ids| ids.len() > 1) {
+                let mut ids = ids.iter().cloned();
+                let err = NameError::DuplicateActorName(ids.next().unwrap(), ids.next().unwrap());
+                violations.push(InvariantViolation::DuplicateName(err));
+            }
+            for ids in self.use_case_titles.values().filter(|ids| ids.len() > 1) {
+                let mut ids = ids.iter().cloned();
+                let err = NameError::DuplicateUseCaseTitle(ids.next().unwrap(),
+                                                           ids.next().unwrap());
+                violations.push(InvariantViolation::DuplicateName(err));
+            }
+            for ids in self.actor_keys.values().filter(|ids| ids.len() > 1) {
+                let mut ids = ids.iter().cloned();
+                let err = NameError::DuplicateActorKey(ids.next().unwrap(), ids.next().unwrap());
+                violations.push(InvariantViolation::DuplicateName(err));
+            }
+            for ids in self.use_case_keys.values().filter(|ids| ids.len() > 1) {
+                let mut ids = ids.iter().cloned();
+                let err = NameError::DuplicateUseCaseKey(ids.next().unwrap(),
+                                                         ids.next().unwrap());
+                violations.push(InvariantViolation::DuplicateName(err));
+            }
+        }
+
+        let adjacency_matches =
+            self.actor_use_cases.values().all(|ids| !ids.is_empty()) &&
+            self.use_case_actors.values().all(|ids| !ids.is_empty()) &&
+            self.actor_use_cases.values().map(BTreeSet::len).sum::<usize>() ==
+                self.associations.len() &&
+            self.use_case_actors.values().map(BTreeSet::len).sum::<usize>() ==
+                self.associations.len() &&
+            self.associations.keys().all(|&(actor_id, use_case_id)|
+                self.actor_use_cases.get(&actor_id)
+                .is_some_and(|use_case_ids| use_case_ids.contains(&use_case_id)) &&
+                self.use_case_actors.get(&use_case_id)
+                .is_some_and(|actor_ids| actor_ids.contains(&actor_id)));
+        if !adjacency_matches {
+            violations.push(InvariantViolation::StaleAssociationIndex);
+        }
+
+        for &(actor_id, use_case_id) in self.associations.keys() {
+            if !self.actors.contains_key(&actor_id) || !self.use_cases.contains_key(&use_case_id) {
+                violations.push(InvariantViolation::DanglingAssociation(actor_id, use_case_id));
+            }
+        }
+        for &(base, included) in &self.includes {
+            if !self.use_cases.contains_key(&base) || !self.use_cases.contains_key(&included) {
+                violations.push(InvariantViolation::DanglingInclude(base, included));
+            }
+        }
+        for &(dependent, dependency) in self.dependencies.keys() {
+            if !self.use_cases.contains_key(&dependent) ||
+               !self.use_cases.contains_key(&dependency) {
+                violations.push(InvariantViolation::DanglingDependency(dependent, dependency));
+            }
+        }
+        for &extend in &self.extends {
+            let extension_point_valid = match extend.extension_point {
+                Some(extension_point_id) =>
+                    self.extension_points.get(&extension_point_id)
+                    .is_some_and(|extension_point| extension_point.use_case == extend.base),
+                None => true,
+            };
+            if !self.use_cases.contains_key(&extend.extension) ||
+               !self.use_cases.contains_key(&extend.base) || !extension_point_valid {
+                violations.push(InvariantViolation::DanglingExtend(extend));
+            }
+        }
+        for &(child, parent) in &self.actor_generalizations {
+            if !self.actors.contains_key(&child) || !self.actors.contains_key(&parent) {
+                violations.push(InvariantViolation::DanglingActorGeneralization(child, parent));
+            }
+        }
+        for &(child, parent) in &self.use_case_generalizations {
+            if !self.use_cases.contains_key(&child) || !self.use_cases.contains_key(&parent) {
+                violations.push(InvariantViolation::DanglingUseCaseGeneralization(child, parent));
+            }
+        }
+        for (&extension_point_id, extension_point) in &self.extension_points {
+            if !self.use_cases.contains_key(&extension_point.use_case) {
+                violations.push(InvariantViolation::DanglingExtensionPoint(extension_point_id));
+            }
+        }
+        for (&system_boundary_id, system_boundary) in &self.system_boundaries {
+            if !system_boundary.use_cases.iter().all(|id| self.use_cases.contains_key(id)) {
+                violations.push(InvariantViolation::DanglingSystemBoundary(system_boundary_id));
+            }
+        }
+        for (&note_id, note) in &self.notes {
+            if !self.note_anchor_exists(note.anchor) {
+                violations.push(InvariantViolation::DanglingNote(note_id));
+            }
+        }
+
+        for &actor_id in self.actors.keys() {
+            let cyclic =
+                self.actor_parents(actor_id)
+                .any(|parent| self.actor_ancestors(parent).contains(&actor_id));
+            if cyclic {
+                violations.push(InvariantViolation::ActorCycle(actor_id));
+            }
+        }
+        for &use_case_id in self.use_cases.keys() {
+            let cyclic =
+                self.included_use_cases(use_case_id)
+                .any(|included| self.transitively_included_use_cases(included)
+                                .contains(&use_case_id)) ||
+                self.use_case_parents(use_case_id)
+                .any(|parent| self.use_case_ancestors(parent).contains(&use_case_id)) ||
+                self.extended_use_cases(use_case_id).contains(&use_case_id) ||
+                self.use_case_dependencies(use_case_id)
+                .any(|dependency| self.transitive_use_case_dependencies(dependency)
+                                  .contains(&use_case_id));
+            if cyclic {
+                violations.push(InvariantViolation::UseCaseCycle(use_case_id));
+            }
+        }
+
+        if violations.is_empty() { Ok(()) } else { Err(violations) }
+    }
+
+    /// The use cases extended by the given use case, directly or through
+    /// other extends, excluding the use case itself unless it is on a cycle.
+    fn extended_use_cases(&self, use_case_id: UseCaseId) -> HashSet<UseCaseId> {
+        let mut reached = HashSet::new();
+        let mut pending = vec![use_case_id];
+        while let Some(use_case_id) = pending.pop() {
+            for extend in self.extends.iter().filter(|extend| extend.extension == use_case_id) {
+                if reached.insert(extend.base) {
+                    pending.push(extend.base);
+                }
+            }
+        }
+        reached
+    }
+
+    /// Check that no two actors share a name, no two use cases share a title,
+    /// no two extension points of a use case share a name, and no two system
+    /// boundaries share a name. Return an error describing the first
+    /// collision found.
+    pub fn validate_unique_names(&self) -> Result<(), NameError> {
+        let mut names = HashMap::new();
+        for (actor_id, actor) in self.actors() {
+            if let Some(&other_id) = names.get(actor.name.as_ref()) {
+                let (a, b) = (cmp::min(actor_id, other_id), cmp::max(actor_id, other_id));
+                return Err(NameError::DuplicateActorName(a, b));
+            }
+            names.insert(actor.name.as_ref(), actor_id);
+        }
+        let mut titles = HashMap::new();
+        for (use_case_id, use_case) in self.use_cases() {
+            if let Some(&other_id) = titles.get(use_case.title.as_ref()) {
+                let (a, b) = (cmp::min(use_case_id, other_id),
+                              cmp::max(use_case_id, other_id));
+                return Err(NameError::DuplicateUseCaseTitle(a, b));
+            }
+            titles.insert(use_case.title.as_ref(), use_case_id);
+        }
+        let mut extension_point_names = HashMap::new();
+        for (extension_point_id, extension_point) in self.extension_points() {
+            let key = (extension_point.use_case, extension_point.name.as_ref());
+            if let Some(&other_id) = extension_point_names.get(&key) {
+                let (a, b) = (cmp::min(extension_point_id, other_id),
+                              cmp::max(extension_point_id, other_id));
+                return Err(NameError::DuplicateExtensionPointName(a, b));
+            }
+            extension_point_names.insert(key, extension_point_id);
+        }
+        let mut system_boundary_names = HashMap::new();
+        for (system_boundary_id, system_boundary) in self.system_boundaries() {
+            if let Some(&other_id) = system_boundary_names.get(system_boundary.name.as_ref()) {
+                let (a, b) = (cmp::min(system_boundary_id, other_id),
+                              cmp::max(system_boundary_id, other_id));
+                return Err(NameError::DuplicateSystemBoundaryName(a, b));
+            }
+            system_boundary_names.insert(system_boundary.name.as_ref(), system_boundary_id);
+        }
+        let mut actor_keys = HashMap::new();
+        for (actor_id, actor) in self.actors() {
+            let key = match actor.key { Some(ref key) => key.as_ref(), None => continue };
+            if let Some(&other_id) = actor_keys.get(key) {
+                let (a, b) = (cmp::min(actor_id, other_id), cmp::max(actor_id, other_id));
+                return Err(NameError::DuplicateActorKey(a, b));
+            }
+            actor_keys.insert(key, actor_id);
+        }
+        let mut use_case_keys = HashMap::new();
+        for (use_case_id, use_case) in self.use_cases() {
+            let key = match use_case.key { Some(ref key) => key.as_ref(), None => continue };
+            if let Some(&other_id) = use_case_keys.get(key) {
+                let (a, b) = (cmp::min(use_case_id, other_id),
+                              cmp::max(use_case_id, other_id));
+                return Err(NameError::DuplicateUseCaseKey(a, b));
+            }
+            use_case_keys.insert(key, use_case_id);
+        }
+        Ok(())
+    }
+
+    /// Remove the notes whose anchors match the given predicate.
+    fn remove_notes_where<F>(&mut self, mut predicate: F) where F: FnMut(NoteAnchor) -> bool {
+        let note_ids =
+            self.notes.iter()
+            .filter(|&(_, note)| predicate(note.anchor))
+            .map(|(&note_id, _)| note_id)
+            .collect::<Vec<_>>();
+        for note_id in note_ids {
+            self.notes.remove(&note_id);
+            self.emit(Event::NoteRemoved(note_id));
+        }
+    }
+
+    /// Remove the extends that match the given predicate.
+    fn remove_extends_where<F>(&mut self, mut predicate: F) where F: FnMut(Extend) -> bool {
+        let extends = self.extends.iter().cloned().filter(|&extend| predicate(extend))
+                      .collect::<Vec<_>>();
+        for extend in extends {
+            self.extends.remove(&extend);
+            self.emit(Event::ExtendRemoved(extend));
+        }
+    }
+
+    /// Forget an association in the adjacency maps, dropping sets that
+    /// become empty.
+    fn unindex_association(&mut self, actor_id: ActorId, use_case_id: UseCaseId) {
+        let now_empty = match self.actor_use_cases.get_mut(&actor_id) {
+            Some(use_case_ids) => { use_case_ids.remove(&use_case_id); use_case_ids.is_empty() },
+            None => false,
+        };
+        if now_empty {
+            self.actor_use_cases.remove(&actor_id);
+        }
+        let now_empty = match self.use_case_actors.get_mut(&use_case_id) {
+            Some(actor_ids) => { actor_ids.remove(&actor_id); actor_ids.is_empty() },
+            None => false,
+        };
+        if now_empty {
+            self.use_case_actors.remove(&use_case_id);
+        }
+    }
+
+    fn note_anchor_exists(&self, anchor: NoteAnchor) -> bool {
+        match anchor {
+            NoteAnchor::Actor(actor_id) => self.actors.contains_key(&actor_id),
+            NoteAnchor::UseCase(use_case_id) => self.use_cases.contains_key(&use_case_id),
+            NoteAnchor::Association(actor_id, use_case_id) =>
+                self.associations.contains_key(&(actor_id, use_case_id)),
+        }
+    }
+
+    /// Like `validate`, but report only the first violation found.
+    pub fn check(&self) -> Result<(), InvariantViolation> {
+        self.validate().map_err(|violations| violations[0])
+    }
+
     fn assert_invariants(&self) {
-        for &(actor_id, use_case_id) in &self.associations {
-            assert!(self.actors.contains_key(&actor_id),
-                    concat!("UseCaseDiagram invariant violation: association ",
-                            "refers to nonexistent actor."));
-            assert!(self.use_cases.contains_key(&use_case_id),
-                    concat!("UseCaseDiagram invariant violation: association ",
-                            "refers to nonexistent use case."));
+        if self.invariant_checks {
+            if let Err(violation) = self.check() {
+                panic!("UseCaseDiagram {}", violation);
+            }
         }
     }
 }
 
-/// Iterator of actors.
-pub type Actors<'a> =
-    iter::Map<hash_map::Iter<'a, ActorId, Actor>,
-              fn((&'a ActorId, &'a Actor)) -> (ActorId, &'a Actor)>;
+impl<S> Default for UseCaseDiagram<S> where S: AsRef<str> {
+    fn default() -> Self {
+        UseCaseDiagram::new()
+    }
+}
+
+/// Like `actor`, but panics if the actor does not exist.
+impl<S> Index<ActorId> for UseCaseDiagram<S> where S: AsRef<str> {
+    type Output = Actor<S>;
+
+    fn index(&self, actor_id: ActorId) -> &Actor<S> {
+        self.actor(actor_id).unwrap_or_else(|| panic!("no actor {}", actor_id))
+    }
+}
+
+/// Like `use_case`, but panics if the use case does not exist.
+impl<S> Index<UseCaseId> for UseCaseDiagram<S> where S: AsRef<str> {
+    type Output = UseCase<S>;
 
-/// Iterator of use cases.
-pub type UseCases<'a> =
-    iter::Map<hash_map::Iter<'a, UseCaseId, UseCase>,
-              fn((&'a UseCaseId, &'a UseCase)) -> (UseCaseId, &'a UseCase)>;
+    fn index(&self, use_case_id: UseCaseId) -> &UseCase<S> {
+        self.use_case(use_case_id).unwrap_or_else(|| panic!("no use case {}", use_case_id))
+    }
+}
 
-/// Iterator of associations.
-pub type Associations<'a> =
-    iter::Cloned<hash_set::Iter<'a, (ActorId, UseCaseId)>>;
+/// Restores the invariant checks setting of a diagram when dropped, as used
+/// by `batch`.
+struct Batch<'a, S> where S: AsRef<str> + 'a {
+    diagram: &'a mut UseCaseDiagram<S>,
+    invariant_checks: bool,
+}
+
+impl<'a, S> Drop for Batch<'a, S> where S: AsRef<str> {
+    fn drop(&mut self) {
+        self.diagram.invariant_checks = self.invariant_checks;
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use std::panic;
+    use std::sync::Arc;
+    use std::thread;
+
     #[test]
     fn test_new() {
-        let diagram = UseCaseDiagram::new();
+        let diagram: UseCaseDiagram = UseCaseDiagram::new();
 
         assert_eq!(diagram.actors().len(), 0);
         assert_eq!(diagram.use_cases().len(), 0);
@@ -208,11 +2385,11 @@ mod tests {
     #[test]
     fn test_insert_actor() {
         let mut diagram = UseCaseDiagram::new();
-        let actor_1 = Actor{name: Rc::from("Actor 1")};
-        let actor_2 = Actor{name: Rc::from("Actor 2")};
+        let actor_1 = Actor::new(Rc::from("Actor 1"));
+        let actor_2 = Actor::new(Rc::from("Actor 2"));
 
-        let actor_id_1 = diagram.insert_actor(actor_1.clone());
-        let actor_id_2 = diagram.insert_actor(actor_2.clone());
+        let actor_id_1 = diagram.insert_actor(actor_1.clone()).unwrap();
+        let actor_id_2 = diagram.insert_actor(actor_2.clone()).unwrap();
 
         assert_eq!(diagram.actors().collect::<HashMap<_, _>>(),
                    [(actor_id_1, &actor_1),
@@ -224,11 +2401,11 @@ mod tests {
     #[test]
     fn test_insert_use_case() {
         let mut diagram = UseCaseDiagram::new();
-        let use_case_1 = UseCase{title: Rc::from("Use case 1")};
-        let use_case_2 = UseCase{title: Rc::from("Use case 2")};
+        let use_case_1 = UseCase::new(Rc::from("Use case 1"));
+        let use_case_2 = UseCase::new(Rc::from("Use case 2"));
 
-        let use_case_id_1 = diagram.insert_use_case(use_case_1.clone());
-        let use_case_id_2 = diagram.insert_use_case(use_case_2.clone());
+        let use_case_id_1 = diagram.insert_use_case(use_case_1.clone()).unwrap();
+        let use_case_id_2 = diagram.insert_use_case(use_case_2.clone()).unwrap();
 
         assert_eq!(diagram.actors().len(), 0);
         assert_eq!(diagram.use_cases().collect::<HashMap<_, _>>(),
@@ -240,14 +2417,14 @@ mod tests {
     #[test]
     fn test_insert_association() {
         let mut diagram = UseCaseDiagram::new();
-        let actor = Actor{name: Rc::from("Actor 1")};
-        let use_case = UseCase{title: Rc::from("Use case 1")};
+        let actor = Actor::new(Rc::from("Actor 1"));
+        let use_case = UseCase::new(Rc::from("Use case 1"));
 
         let err = diagram.insert_association(ActorId(0), UseCaseId(0));
         assert!(err.is_err());
 
-        let actor_id = diagram.insert_actor(actor.clone());
-        let use_case_id = diagram.insert_use_case(use_case.clone());
+        let actor_id = diagram.insert_actor(actor.clone()).unwrap();
+        let use_case_id = diagram.insert_use_case(use_case.clone()).unwrap();
 
         let ok = diagram.insert_association(actor_id, use_case_id);
         assert!(ok.is_ok());
@@ -257,6 +2434,805 @@ mod tests {
         assert_eq!(diagram.associations().collect::<Vec<_>>(),
                    vec![(actor_id, use_case_id)]);
     }
+
+    #[test]
+    fn test_merge_actors() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let m = diagram.insert_actor(Actor::new(Rc::from("Moderator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let cs = diagram.insert_use_case(UseCase::new(Rc::from("Create subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_association(a, pc).unwrap();
+        diagram.insert_association(m, cs).unwrap();
+        diagram.insert_association(m, pc).unwrap();
+
+        let err = diagram.merge_actors(a, ActorId(42));
+        assert_eq!(err, Err(AssociationError::NonexistentActor(ActorId(42))));
+        let err = diagram.merge_actors(ActorId(42), m);
+        assert_eq!(err, Err(AssociationError::NonexistentActor(ActorId(42))));
+
+        diagram.merge_actors(a, m).unwrap();
+
+        assert!(diagram.actor(m).is_none());
+        assert_eq!(diagram.actors().len(), 1);
+        assert_eq!(diagram.associations().collect::<HashSet<_>>(),
+                   [(a, bs), (a, cs), (a, pc)].iter().cloned().collect());
+    }
+
+    #[test]
+    fn test_cloned() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let m = diagram.insert_actor(Actor::new(Rc::from("Moderator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+
+        let mut use_case = diagram.use_case_cloned(bs).unwrap();
+        use_case.title = Rc::from("Unban subscriber");
+        assert_eq!(&*diagram.use_case(bs).unwrap().title, "Ban subscriber");
+
+        let actor = diagram.actor_cloned(m).unwrap();
+        diagram.merge_actors(a, m).unwrap();
+        assert_eq!(&*actor.name, "Moderator");
+        assert_eq!(diagram.actor_cloned(m), None);
+        assert_eq!(diagram.use_case_cloned(UseCaseId(42)), None);
+    }
+
+    #[test]
+    fn test_arc_labels() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Arc::<str>::from("Administrator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Arc::<str>::from("Ban subscriber"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        assert_send_sync(&diagram);
+
+        let handle = thread::spawn(move || {
+            diagram.actors().map(|(_, actor)| actor.name.to_string())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(handle.join().unwrap(), vec!["Administrator".to_string()]);
+    }
+
+    #[test]
+    fn test_validate_unique_names() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        assert_eq!(diagram.validate_unique_names(), Ok(()));
+
+        let a2 = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        assert_eq!(diagram.validate_unique_names(),
+                   Err(NameError::DuplicateActorName(a, a2)));
+
+        diagram.merge_actors(a, a2).unwrap();
+        let bs2 = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        assert_eq!(diagram.validate_unique_names(),
+                   Err(NameError::DuplicateUseCaseTitle(bs, bs2)));
+    }
+
+    #[test]
+    fn test_remove_actor() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_association(a, pc).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+
+        assert_eq!(diagram.remove_actor(a), Some(Actor::new(Rc::from("Administrator"))));
+        assert_eq!(diagram.remove_actor(a), None);
+
+        assert_eq!(diagram.actors().map(|(id, _)| id).collect::<Vec<_>>(), vec![s]);
+        assert_eq!(diagram.use_cases().len(), 2);
+        assert_eq!(diagram.associations().collect::<Vec<_>>(), vec![(s, pc)]);
+        assert_eq!(diagram.insert_association(a, bs),
+                   Err(AssociationError::NonexistentActor(a)));
+    }
+
+    #[test]
+    fn test_remove_use_case() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_association(a, pc).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+
+        assert_eq!(diagram.remove_use_case(pc),
+                   Some(UseCase::new(Rc::from("Post comment"))));
+        assert_eq!(diagram.remove_use_case(pc), None);
+
+        assert_eq!(diagram.actors().len(), 2);
+        assert_eq!(diagram.use_cases().map(|(id, _)| id).collect::<Vec<_>>(), vec![bs]);
+        assert_eq!(diagram.associations().collect::<Vec<_>>(), vec![(a, bs)]);
+        assert_eq!(diagram.insert_association(s, pc),
+                   Err(AssociationError::NonexistentUseCase(pc)));
+    }
+
+    #[test]
+    fn test_remove_association() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_association(a, pc).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+
+        assert!(diagram.remove_association(a, pc));
+        assert!(!diagram.remove_association(a, pc));
+        assert!(!diagram.remove_association(ActorId(42), pc));
+
+        assert_eq!(diagram.associations_of_actor(a).collect::<Vec<_>>(), vec![(a, bs)]);
+        assert_eq!(diagram.associations_of_actor(s).collect::<Vec<_>>(), vec![(s, pc)]);
+        assert_eq!(diagram.actors().len(), 2);
+        assert_eq!(diagram.use_cases().len(), 2);
+    }
+
+    #[test]
+    fn test_mut() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Admin"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban user"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+
+        diagram.actor_mut(a).unwrap().name = Rc::from("Administrator");
+        diagram.use_case_mut(bs).unwrap().title = Rc::from("Ban subscriber");
+        assert!(diagram.actor_mut(ActorId(42)).is_none());
+        assert!(diagram.use_case_mut(UseCaseId(42)).is_none());
+
+        assert_eq!(diagram.actor(a), Some(&Actor::new(Rc::from("Administrator"))));
+        assert_eq!(diagram.use_case(bs), Some(&UseCase::new(Rc::from("Ban subscriber"))));
+        assert_eq!(diagram.associations().collect::<Vec<_>>(), vec![(a, bs)]);
+    }
+
+    #[test]
+    fn test_insert_include() {
+        let mut diagram = UseCaseDiagram::new();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        let vc = diagram.insert_use_case(UseCase::new(Rc::from("Verify credentials"))).unwrap();
+
+        assert_eq!(diagram.insert_include(pc, UseCaseId(42)),
+                   Err(RelationshipError::NonexistentUseCase(UseCaseId(42))));
+        diagram.insert_include(pc, li).unwrap();
+        diagram.insert_include(li, vc).unwrap();
+        assert_eq!(diagram.insert_include(vc, pc),
+                   Err(RelationshipError::UseCaseCycle(vc, pc)));
+        assert_eq!(diagram.insert_include(li, li),
+                   Err(RelationshipError::UseCaseCycle(li, li)));
+
+        assert_eq!(diagram.included_use_cases(pc).collect::<Vec<_>>(), vec![li]);
+        assert_eq!(diagram.transitively_included_use_cases(pc),
+                   [pc, li, vc].iter().cloned().collect());
+
+        diagram.remove_use_case(li);
+        assert_eq!(diagram.includes().len(), 0);
+        diagram.insert_include(vc, pc).unwrap();
+        assert!(diagram.remove_include(vc, pc));
+        assert!(!diagram.remove_include(vc, pc));
+    }
+
+    #[test]
+    fn test_insert_extend() {
+        let mut diagram = UseCaseDiagram::new();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let aa = diagram.insert_use_case(UseCase::new(Rc::from("Attach image"))).unwrap();
+        let sp = diagram.insert_use_case(UseCase::new(Rc::from("Spell check"))).unwrap();
+
+        let err = diagram.insert_extension_point(ExtensionPoint{use_case: UseCaseId(42),
+                                                                name: Rc::from("Compose")});
+        assert_eq!(err, Err(RelationshipError::NonexistentUseCase(UseCaseId(42))));
+        let compose = diagram.insert_extension_point(ExtensionPoint{use_case: pc,
+                                                                    name: Rc::from("Compose")})
+            .unwrap();
+        assert_eq!(diagram.extension_points_of_use_case(pc).map(|(id, _)| id)
+                   .collect::<Vec<_>>(), vec![compose]);
+
+        let extend = Extend{extension: aa, base: pc, extension_point: Some(compose)};
+        diagram.insert_extend(extend).unwrap();
+        let err = diagram.insert_extend(Extend{extension: pc, base: sp,
+                                               extension_point: Some(compose)});
+        assert_eq!(err, Err(RelationshipError::ForeignExtensionPoint(compose, sp)));
+        let err = diagram.insert_extend(Extend{extension: sp, base: pc,
+                                               extension_point: Some(ExtensionPointId(42))});
+        assert_eq!(err, Err(RelationshipError::NonexistentExtensionPoint(ExtensionPointId(42))));
+        let err = diagram.insert_extend(Extend{extension: pc, base: aa, extension_point: None});
+        assert_eq!(err, Err(RelationshipError::UseCaseCycle(pc, aa)));
+        diagram.insert_extend(Extend{extension: sp, base: aa, extension_point: None}).unwrap();
+        assert_eq!(diagram.extends().len(), 2);
+
+        assert!(diagram.remove_extension_point(compose).is_some());
+        assert_eq!(diagram.extends().collect::<Vec<_>>(),
+                   vec![Extend{extension: sp, base: aa, extension_point: None}]);
+        diagram.insert_extension_point(ExtensionPoint{use_case: aa, name: Rc::from("Upload")})
+            .unwrap();
+        diagram.remove_use_case(aa);
+        assert_eq!(diagram.extends().len(), 0);
+        assert_eq!(diagram.extension_points().len(), 0);
+    }
+
+    #[test]
+    fn test_actor_generalization() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let m = diagram.insert_actor(Actor::new(Rc::from("Moderator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let dc = diagram.insert_use_case(UseCase::new(Rc::from("Delete comment"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_association(m, dc).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+
+        assert_eq!(diagram.insert_actor_generalization(a, ActorId(42)),
+                   Err(RelationshipError::NonexistentActor(ActorId(42))));
+        diagram.insert_actor_generalization(a, m).unwrap();
+        diagram.insert_actor_generalization(m, s).unwrap();
+        assert_eq!(diagram.insert_actor_generalization(s, a),
+                   Err(RelationshipError::ActorCycle(s, a)));
+
+        assert_eq!(diagram.effective_use_cases(a), [bs, dc, pc].iter().cloned().collect());
+        assert_eq!(diagram.effective_use_cases(m), [dc, pc].iter().cloned().collect());
+        assert_eq!(diagram.effective_use_cases(s), [pc].iter().cloned().collect());
+
+        diagram.remove_actor(m);
+        assert_eq!(diagram.actor_generalizations().len(), 0);
+        assert_eq!(diagram.effective_use_cases(a), [bs].iter().cloned().collect());
+    }
+
+    #[test]
+    fn test_merge_actors_generalizations() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let m = diagram.insert_actor(Actor::new(Rc::from("Moderator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        diagram.insert_actor_generalization(a, m).unwrap();
+        diagram.insert_actor_generalization(m, s).unwrap();
+
+        diagram.merge_actors(a, m).unwrap();
+        assert_eq!(diagram.actor_generalizations().collect::<Vec<_>>(), vec![(a, s)]);
+    }
+
+    #[test]
+    fn test_use_case_generalization() {
+        let mut diagram = UseCaseDiagram::new();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let p = diagram.insert_use_case(UseCase::new(Rc::from("Pay"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Pay by card"))).unwrap();
+        let pv = diagram.insert_use_case(UseCase::new(Rc::from("Pay by Visa"))).unwrap();
+        let r = diagram.insert_use_case(UseCase::new(Rc::from("Refund"))).unwrap();
+        diagram.insert_association(s, p).unwrap();
+        diagram.insert_association(a, r).unwrap();
+        diagram.insert_actor_generalization(a, s).unwrap();
+
+        assert_eq!(diagram.insert_use_case_generalization(pc, UseCaseId(42)),
+                   Err(RelationshipError::NonexistentUseCase(UseCaseId(42))));
+        diagram.insert_use_case_generalization(pc, p).unwrap();
+        diagram.insert_use_case_generalization(pv, pc).unwrap();
+        assert_eq!(diagram.insert_use_case_generalization(p, pv),
+                   Err(RelationshipError::UseCaseCycle(p, pv)));
+
+        assert_eq!(diagram.use_case_ancestors(pv), [pv, pc, p].iter().cloned().collect());
+        assert_eq!(diagram.use_case_descendants(p), [p, pc, pv].iter().cloned().collect());
+        assert_eq!(diagram.effective_use_cases(s), [p, pc, pv].iter().cloned().collect());
+        assert_eq!(diagram.effective_actors(pv), [s, a].iter().cloned().collect());
+        assert_eq!(diagram.effective_actors(r), [a].iter().cloned().collect());
+
+        diagram.remove_use_case(pc);
+        assert_eq!(diagram.use_case_generalizations().len(), 0);
+        assert_eq!(diagram.effective_actors(pv), HashSet::new());
+    }
+
+
+    #[test]
+    fn test_system_boundaries() {
+        let mut diagram = UseCaseDiagram::new();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+
+        let nonexistent = SystemBoundary{name: Rc::from("Journal"),
+                                         use_cases: [bs, UseCaseId(42)].iter().cloned().collect()};
+        assert_eq!(diagram.insert_system_boundary(nonexistent),
+                   Err(RelationshipError::NonexistentUseCase(UseCaseId(42))));
+        let journal = diagram.insert_system_boundary(SystemBoundary{name: Rc::from("Journal"),
+                                                                    use_cases: BTreeSet::new()})
+            .unwrap();
+        diagram.insert_system_boundary_use_case(journal, bs).unwrap();
+        diagram.insert_system_boundary_use_case(journal, pc).unwrap();
+        assert_eq!(diagram.insert_system_boundary_use_case(SystemBoundaryId(42), pc),
+                   Err(RelationshipError::NonexistentSystemBoundary(SystemBoundaryId(42))));
+        assert_eq!(diagram.system_boundaries_of_use_case(pc).map(|(id, _)| id)
+                   .collect::<Vec<_>>(), vec![journal]);
+
+        assert!(diagram.remove_system_boundary_use_case(journal, pc));
+        assert!(!diagram.remove_system_boundary_use_case(journal, pc));
+        diagram.remove_use_case(bs);
+        assert!(diagram.system_boundary(journal).unwrap().use_cases.is_empty());
+
+        let journal2 = diagram.insert_system_boundary(SystemBoundary{name: Rc::from("Journal"),
+                                                                     use_cases: BTreeSet::new()})
+            .unwrap();
+        assert_eq!(diagram.validate_unique_names(),
+                   Err(NameError::DuplicateSystemBoundaryName(journal, journal2)));
+        assert_eq!(diagram.remove_system_boundary(journal2).map(|b| b.name),
+                   Some(Rc::from("Journal")));
+        assert_eq!(diagram.validate_unique_names(), Ok(()));
+    }
+
+
+    #[test]
+    fn test_notes() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let m = diagram.insert_actor(Actor::new(Rc::from("Moderator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        diagram.insert_association(m, bs).unwrap();
+
+        assert_eq!(diagram.insert_note(Note{anchor: NoteAnchor::Association(a, bs),
+                                            text: Rc::from("2FA")}),
+                   Err(RelationshipError::NonexistentAssociation(a, bs)));
+        let on_actor = diagram.insert_note(Note{anchor: NoteAnchor::Actor(m),
+                                                text: Rc::from("Staff")}).unwrap();
+        let on_assoc = diagram.insert_note(Note{anchor: NoteAnchor::Association(m, bs),
+                                                text: Rc::from("2FA")}).unwrap();
+        let on_use_case = diagram.insert_note(Note{anchor: NoteAnchor::UseCase(bs),
+                                                   text: Rc::from("Permanent")}).unwrap();
+        assert_eq!(diagram.notes_of(NoteAnchor::UseCase(bs)).map(|(id, _)| id)
+                   .collect::<Vec<_>>(), vec![on_use_case]);
+
+        diagram.merge_actors(a, m).unwrap();
+        assert_eq!(diagram.note(on_actor).unwrap().anchor, NoteAnchor::Actor(a));
+        assert_eq!(diagram.note(on_assoc).unwrap().anchor, NoteAnchor::Association(a, bs));
+
+        diagram.remove_association(a, bs);
+        assert!(diagram.note(on_assoc).is_none());
+        diagram.remove_use_case(bs);
+        assert!(diagram.note(on_use_case).is_none());
+        assert_eq!(diagram.remove_note(on_actor).map(|note| note.text), Some(Rc::from("Staff")));
+        assert_eq!(diagram.notes().len(), 0);
+    }
+
+
+    #[test]
+    fn test_multiplicity() {
+        for &(s, lower, upper) in &[("1", 1, Some(1)), ("*", 0, None), ("0..1", 0, Some(1)),
+                                    ("2..*", 2, None)] {
+            let multiplicity = Multiplicity{lower, upper};
+            assert_eq!(s.parse(), Ok(multiplicity));
+            assert_eq!(multiplicity.to_string(), s);
+        }
+        assert_eq!("0..*".parse::<Multiplicity>().unwrap().to_string(), "*");
+        for s in &["", "x", "*..1", "2..1", "1..2..3"] {
+            assert_eq!(s.parse::<Multiplicity>(), Err(ParseMultiplicityError));
+        }
+    }
+
+    #[test]
+    fn test_association_details() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let m = diagram.insert_actor(Actor::new(Rc::from("Moderator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        assert_eq!(diagram.association(a, bs), Some(&Association::default()));
+
+        diagram.association_mut(a, bs).unwrap().label = Some(Rc::from("bans"));
+        diagram.insert_association(a, bs).unwrap();
+        assert_eq!(diagram.association(a, bs).unwrap().label, Some(Rc::from("bans")));
+
+        let moderates = Association{label: Some(Rc::from("moderates")),
+                                    navigability: Navigability::ToUseCase,
+                                    ..Association::default()};
+        diagram.insert_association_with(m, bs, moderates.clone()).unwrap();
+        diagram.merge_actors(a, m).unwrap();
+        assert_eq!(diagram.association(a, bs).unwrap().label, Some(Rc::from("bans")));
+
+        diagram.remove_association(a, bs);
+        let c = diagram.insert_actor(Actor::new(Rc::from("Cron"))).unwrap();
+        diagram.insert_association_with(c, bs, moderates.clone()).unwrap();
+        diagram.merge_actors(a, c).unwrap();
+        assert_eq!(diagram.association(a, bs), Some(&moderates));
+    }
+
+
+    #[test]
+    fn test_stereotypes() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut scheduler = Actor::new(Rc::from("Scheduler"));
+        scheduler.stereotypes.push(Rc::from(SYSTEM_STEREOTYPE));
+        let s = diagram.insert_actor(scheduler).unwrap();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let mut purge = UseCase::new(Rc::from("Purge accounts"));
+        purge.stereotypes.push(Rc::from("batch"));
+        let p = diagram.insert_use_case(purge).unwrap();
+
+        assert!(diagram.actor(s).unwrap().has_stereotype("system"));
+        assert!(!diagram.actor(a).unwrap().has_stereotype("system"));
+        assert_eq!(diagram.actors_with_stereotype("system").map(|(id, _)| id)
+                   .collect::<Vec<_>>(), vec![s]);
+        assert_eq!(diagram.use_cases_with_stereotype("batch").map(|(id, _)| id)
+                   .collect::<Vec<_>>(), vec![p]);
+        assert_eq!(diagram.use_cases_with_stereotype("system").count(), 0);
+    }
+
+
+    #[test]
+    fn test_use_case_filters() {
+        let mut diagram: UseCaseDiagram = UseCaseDiagram::new();
+        let mut ban = UseCase::new(Rc::from("Ban subscriber"));
+        ban.priority = Some(Priority::Medium);
+        ban.status = Some(Status::Approved);
+        ban.tags.push(Rc::from("moderation"));
+        let bs = diagram.insert_use_case(ban).unwrap();
+        let mut post = UseCase::new(Rc::from("Post comment"));
+        post.priority = Some(Priority::High);
+        post.status = Some(Status::Implemented);
+        post.tags.push(Rc::from("mvp"));
+        let pc = diagram.insert_use_case(post).unwrap();
+        let ex = diagram.insert_use_case(UseCase::new(Rc::from("Export comments"))).unwrap();
+
+        let ids = |it: &mut dyn Iterator<Item = (UseCaseId, &UseCase)>| {
+            let mut ids = it.map(|(id, _)| id).collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids(&mut diagram.use_cases_with_tag("mvp")), vec![pc]);
+        assert_eq!(ids(&mut diagram.use_cases_with_tag("beta")), vec![]);
+        assert_eq!(ids(&mut diagram.use_cases_with_status(Status::Approved)), vec![bs]);
+        assert_eq!(ids(&mut diagram.use_cases_with_priority_at_least(Priority::Medium)),
+                   vec![bs, pc]);
+        assert_eq!(ids(&mut diagram.use_cases_with_priority_at_least(Priority::Low)),
+                   vec![bs, pc]);
+        assert!(!diagram.use_case(ex).unwrap().has_tag("mvp"));
+    }
+
+
+    #[test]
+    fn test_association_adjacency() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let m = diagram.insert_actor(Actor::new(Rc::from("Moderator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_association(m, bs).unwrap();
+        diagram.insert_association(m, pc).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+
+        assert_eq!(diagram.use_cases_of_actor(m).collect::<HashSet<_>>(),
+                   [bs, pc].iter().cloned().collect());
+        assert_eq!(diagram.actors_of_use_case(bs).collect::<HashSet<_>>(),
+                   [a, m].iter().cloned().collect());
+        assert_eq!(diagram.use_cases_of_actor(ActorId(42)).count(), 0);
+
+        diagram.remove_association(s, pc);
+        assert_eq!(diagram.use_cases_of_actor(s).count(), 0);
+        assert_eq!(diagram.actors_of_use_case(pc).collect::<Vec<_>>(), vec![m]);
+
+        diagram.merge_actors(a, m).unwrap();
+        assert_eq!(diagram.use_cases_of_actor(a).collect::<HashSet<_>>(),
+                   [bs, pc].iter().cloned().collect());
+        assert_eq!(diagram.actors_of_use_case(bs).collect::<Vec<_>>(), vec![a]);
+        assert_eq!(diagram.actors_of_use_case(pc).collect::<Vec<_>>(), vec![a]);
+
+        diagram.remove_use_case(bs);
+        assert_eq!(diagram.use_cases_of_actor(a).collect::<Vec<_>>(), vec![pc]);
+        diagram.remove_actor(a);
+        assert_eq!(diagram.actors_of_use_case(pc).count(), 0);
+    }
+
+    #[test]
+    fn test_effective_actors() {
+        let mut diagram = UseCaseDiagram::new();
+        let u = diagram.insert_actor(Actor::new(Rc::from("User"))).unwrap();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let m = diagram.insert_use_case(UseCase::new(Rc::from("Manage account"))).unwrap();
+        let cp = diagram.insert_use_case(UseCase::new(Rc::from("Change password"))).unwrap();
+        diagram.insert_actor_generalization(a, u).unwrap();
+        diagram.insert_use_case_generalization(cp, m).unwrap();
+        diagram.insert_association(u, m).unwrap();
+
+        assert_eq!(diagram.effective_actors(cp), [u, a].iter().cloned().collect());
+        assert_eq!(diagram.actor_descendants(u), [u, a].iter().cloned().collect());
+        assert_eq!(diagram.actor_descendants(s), [s].iter().cloned().collect());
+    }
+
+    #[test]
+    fn test_iteration_order() {
+        let mut diagram = UseCaseDiagram::new();
+        let names = ["Subscriber", "Administrator", "Moderator", "Guest", "Scheduler"];
+        let actor_ids = names.iter()
+            .map(|&name| diagram.insert_actor(Actor::new(Rc::from(name))).unwrap())
+            .collect::<Vec<_>>();
+        let titles = ["Post comment", "Ban subscriber", "Log in", "Purge accounts"];
+        let use_case_ids = titles.iter()
+            .map(|&title| diagram.insert_use_case(UseCase::new(Rc::from(title))).unwrap())
+            .collect::<Vec<_>>();
+        for &actor_id in actor_ids.iter().rev() {
+            for &use_case_id in use_case_ids.iter().rev() {
+                diagram.insert_association(actor_id, use_case_id).unwrap();
+            }
+        }
+
+        assert_eq!(diagram.actors().map(|(_, actor)| &*actor.name).collect::<Vec<_>>(), names);
+        assert_eq!(diagram.use_cases().map(|(_, use_case)| &*use_case.title)
+                   .collect::<Vec<_>>(), titles);
+        let associations = diagram.associations().collect::<Vec<_>>();
+        let mut sorted = associations.clone();
+        sorted.sort();
+        assert_eq!(associations, sorted);
+        assert_eq!(diagram.use_cases_of_actor(actor_ids[0]).collect::<Vec<_>>(), use_case_ids);
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_include(bs, pc).unwrap();
+        assert_eq!(diagram.validate(), Ok(()));
+
+        diagram.includes.insert((pc, bs));
+        diagram.use_cases.remove(&pc);
+        diagram.actor_use_cases.clear();
+        let violations = diagram.validate().unwrap_err();
+        assert_eq!(violations, vec![
+            InvariantViolation::StaleNameIndex,
+            InvariantViolation::StaleAssociationIndex,
+            InvariantViolation::DanglingInclude(bs, pc),
+            InvariantViolation::DanglingInclude(pc, bs),
+            InvariantViolation::UseCaseCycle(bs),
+        ]);
+        assert_eq!(violations[2].to_string(),
+                   format!("invariant violation: include of use case {} by {} refers to \
+                            nonexistent use case", pc, bs));
+    }
+
+    #[test]
+    fn test_invariant_checks() {
+        let mut diagram = UseCaseDiagram::new();
+        assert_eq!(diagram.has_invariant_checks(), cfg!(debug_assertions));
+        diagram.set_invariant_checks(false);
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        diagram.actor_names.clear();
+        diagram.insert_actor(Actor::new(Rc::from("Guest"))).unwrap();
+        assert_eq!(diagram.check(), Err(InvariantViolation::StaleNameIndex));
+
+        diagram.actor_names.clear();
+        diagram.actors.clear();
+        assert_eq!(diagram.check(), Ok(()));
+        diagram.set_invariant_checks(true);
+        assert!(diagram.insert_actor(Actor::new(Rc::from("Guest"))).unwrap() > a);
+    }
+
+    #[test]
+    #[should_panic(expected = "name index is stale")]
+    fn test_invariant_checks_panic() {
+        let mut diagram = UseCaseDiagram::new();
+        diagram.set_invariant_checks(true);
+        diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        diagram.actor_names.clear();
+        diagram.insert_actor(Actor::new(Rc::from("Guest"))).unwrap();
+    }
+
+    #[test]
+    fn test_transaction() {
+        let mut diagram = UseCaseDiagram::new();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+
+        let result = diagram.transaction(|tx| {
+            let g = tx.insert_actor(Actor::new(Rc::from("Guest"))).unwrap();
+            tx.insert_association(g, pc)?;
+            tx.remove_use_case(pc);
+            tx.insert_association(s, pc)
+        });
+        assert_eq!(result, Err(AssociationError::NonexistentUseCase(pc)));
+        assert_eq!(diagram.actors().len(), 1);
+        assert!(diagram.use_case(pc).is_some());
+        assert_eq!(diagram.associations().len(), 0);
+        assert!(diagram.actor_by_name("Guest").is_none());
+
+        let g = diagram.transaction(|tx| {
+            let g = tx.insert_actor(Actor::new(Rc::from("Guest"))).unwrap();
+            tx.insert_association(g, pc).map(|()| g)
+        }).unwrap();
+        assert_eq!(diagram.use_cases_of_actor(g).collect::<Vec<_>>(), vec![pc]);
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut diagram = UseCaseDiagram::with_capacity(2, 2, 2);
+        diagram.set_invariant_checks(true);
+        let actor_ids = diagram.extend_actors(vec![Actor::new(Rc::from("Administrator")),
+                                                   Actor::new(Rc::from("Subscriber"))])
+            .unwrap();
+        let use_case_ids = diagram.extend_use_cases(vec![UseCase::new(Rc::from("Ban subscriber")),
+                                                         UseCase::new(Rc::from("Post comment"))])
+            .unwrap();
+        assert_eq!(diagram.actors().map(|(id, _)| id).collect::<Vec<_>>(), actor_ids);
+        assert_eq!(diagram.use_cases().map(|(id, _)| id).collect::<Vec<_>>(), use_case_ids);
+        assert!(diagram.has_invariant_checks());
+
+        let (a, s) = (actor_ids[0], actor_ids[1]);
+        let (bs, pc) = (use_case_ids[0], use_case_ids[1]);
+        assert_eq!(diagram.extend_associations(vec![(a, bs), (s, UseCaseId(7))]),
+                   Err(AssociationError::NonexistentUseCase(UseCaseId(7))));
+        assert_eq!(diagram.associations().len(), 0);
+        diagram.extend_associations(vec![(a, bs), (s, pc)]).unwrap();
+        assert_eq!(diagram.associations().collect::<Vec<_>>(), vec![(a, bs), (s, pc)]);
+    }
+
+    #[test]
+    fn test_batch_panic_restores_invariant_checks() {
+        let mut diagram = UseCaseDiagram::<Rc<str>>::new();
+        diagram.set_invariant_checks(true);
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            diagram.batch(|_| panic!("batch failed"))
+        }));
+        assert!(result.is_err());
+        assert!(diagram.has_invariant_checks());
+    }
+
+    #[test]
+    fn test_extend_unique_names() {
+        let mut diagram = UseCaseDiagram::with_unique_names();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        assert_eq!(diagram.extend_actors(vec![Actor::new(Rc::from("Subscriber")),
+                                              Actor::new(Rc::from("Administrator"))]),
+                   Err(NameError::ActorNameTaken(a)));
+        assert_eq!(diagram.extend_use_cases(vec![UseCase::new(Rc::from("Ban")),
+                                                 UseCase::new(Rc::from("Post")),
+                                                 UseCase::new(Rc::from("Ban"))]),
+                   Err(NameError::DuplicateUseCaseTitle(UseCaseId(0), UseCaseId(2))));
+        assert_eq!(diagram.actors().count(), 1);
+        assert_eq!(diagram.use_cases().count(), 0);
+    }
+
+    #[test]
+    fn test_insert_dependency() {
+        let mut diagram = UseCaseDiagram::new();
+        let cs = diagram.insert_use_case(UseCase::new(Rc::from("Create subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let mc = diagram.insert_use_case(UseCase::new(Rc::from("Moderate comment"))).unwrap();
+
+        assert_eq!(diagram.insert_dependency(pc, UseCaseId(42), None),
+                   Err(RelationshipError::NonexistentUseCase(UseCaseId(42))));
+        diagram.insert_dependency(pc, cs, None).unwrap();
+        diagram.insert_dependency(mc, pc, Some(Rc::from("needs a comment"))).unwrap();
+        assert_eq!(diagram.insert_dependency(cs, mc, None),
+                   Err(RelationshipError::UseCaseCycle(cs, mc)));
+        assert_eq!(diagram.dependency_label(pc, cs), None);
+        assert_eq!(diagram.dependency_label(mc, pc).map(|l| &**l), Some("needs a comment"));
+        assert_eq!(diagram.transitive_use_case_dependencies(mc),
+                   [mc, pc, cs].iter().cloned().collect());
+        assert_eq!(diagram.validate(), Ok(()));
+
+        diagram.remove_use_case(pc);
+        assert_eq!(diagram.dependencies().len(), 0);
+        diagram.insert_dependency(mc, cs, None).unwrap();
+        assert!(diagram.remove_dependency(mc, cs));
+        assert!(!diagram.remove_dependency(mc, cs));
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let tmp = diagram.insert_use_case(UseCase::new(Rc::from("TODO"))).unwrap();
+        diagram.insert_association(a, tmp).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        diagram.insert_association(s, tmp).unwrap();
+        diagram.insert_include(pc, tmp).unwrap();
+
+        diagram.retain_use_cases(|_, use_case| &*use_case.title != "TODO");
+        assert_eq!(diagram.use_cases().map(|(id, _)| id).collect::<Vec<_>>(), vec![pc]);
+        assert_eq!(diagram.associations().collect::<Vec<_>>(), vec![(s, pc)]);
+        assert_eq!(diagram.includes().len(), 0);
+
+        diagram.retain_actors(|actor_id, _| actor_id != s);
+        assert_eq!(diagram.actors().map(|(id, _)| id).collect::<Vec<_>>(), vec![a]);
+        assert_eq!(diagram.associations().count(), 0);
+        assert_eq!(diagram.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_index() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        assert_eq!(&*diagram[a].name, "Administrator");
+        assert_eq!(&*diagram[bs].title, "Ban subscriber");
+    }
+
+    #[test]
+    #[should_panic(expected = "no actor 42")]
+    fn test_index_nonexistent() {
+        let diagram = UseCaseDiagram::<Rc<str>>::new();
+        let _ = &diagram[ActorId(42)];
+    }
+
+    #[test]
+    fn test_packages() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut cs = UseCase::new(Rc::from("Cancel subscription"));
+        cs.package = vec![Rc::from("Billing")];
+        let cs = diagram.insert_use_case(cs).unwrap();
+        let mut si = UseCase::new(Rc::from("Send invoice"));
+        si.package = vec![Rc::from("Billing"), Rc::from("Invoices")];
+        let si = diagram.insert_use_case(si).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+
+        assert_eq!(diagram[si].qualified_title(), "Billing::Invoices::Send invoice");
+        assert_eq!(diagram[li].qualified_title(), "Log in");
+        assert_eq!(diagram.packages().into_iter().collect::<Vec<_>>(),
+                   vec![vec!["Billing"], vec!["Billing", "Invoices"]]);
+        assert_eq!(diagram.use_cases_in_package(&["Billing"]).map(|(id, _)| id)
+                   .collect::<Vec<_>>(), vec![cs]);
+        assert_eq!(diagram.use_cases_in_package(&[]).map(|(id, _)| id).collect::<Vec<_>>(),
+                   vec![li]);
+    }
 }
 
+mod analysis;
+#[cfg(feature = "binary")]
+pub mod binary;
+pub mod builder;
+mod canonical;
 pub mod code_generation;
+pub mod csv;
+pub mod diff;
+pub mod drawio;
+pub mod editor;
+mod equivalence;
+mod events;
+pub mod excalidraw;
+pub mod format;
+pub mod formatter;
+pub mod gherkin;
+#[cfg(feature = "petgraph")]
+mod graph;
+pub mod graphql;
+mod iterators;
+pub mod json;
+mod layers;
+pub mod layout;
+pub mod lint;
+mod localization;
+pub mod markdown;
+pub mod merge;
+pub mod mermaid;
+mod names;
+pub mod openapi;
+mod ordering;
+pub mod parser;
+pub mod plantuml;
+pub mod project;
+mod remap;
+mod search;
+mod serialization;
+mod snapshot;
+mod source_map;
+mod stats;
+mod subdiagram;
+mod table;
+pub mod toml;
+pub mod xmi;
+mod xml;
+pub mod yaml;
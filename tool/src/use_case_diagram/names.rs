@@ -0,0 +1,525 @@
+use std::collections::{BTreeSet, HashMap};
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::thread;
+
+use use_case_diagram::{Actor, ActorId, Event, NameError, UseCase, UseCaseDiagram, UseCaseId};
+
+/// An index from names to the identifiers of the elements with that name.
+pub(super) type NameIndex<I> = HashMap<String, BTreeSet<I>>;
+
+/// Record that the element with the given identifier has the given name.
+pub(super) fn index_name<I>(index: &mut NameIndex<I>, name: &str, id: I) where I: Ord {
+    index.entry(name.to_string()).or_default().insert(id);
+}
+
+/// Forget that the element with the given identifier has the given name.
+pub(super) fn unindex_name<I>(index: &mut NameIndex<I>, name: &str, id: I) where I: Ord {
+    let now_empty = match index.get_mut(name) {
+        Some(ids) => { ids.remove(&id); ids.is_empty() },
+        None => false,
+    };
+    if now_empty {
+        index.remove(name);
+    }
+}
+
+/// Record that the element with the given identifier has the given key, if
+/// it has one.
+pub(super) fn index_key<I>(index: &mut NameIndex<I>, key: Option<&str>, id: I) where I: Ord {
+    if let Some(key) = key {
+        index_name(index, key, id);
+    }
+}
+
+/// Forget that the element with the given identifier has the given key, if
+/// it has one.
+pub(super) fn unindex_key<I>(index: &mut NameIndex<I>, key: Option<&str>, id: I) where I: Ord {
+    if let Some(key) = key {
+        unindex_name(index, key, id);
+    }
+}
+
+/// Whether the index maps exactly the given names to their identifiers.
+pub(super) fn index_matches<'a, I, N>(index: &NameIndex<I>, names: N) -> bool
+    where I: Ord + Copy + 'a, N: Iterator<Item = (I, &'a str)> {
+    let mut count = 0;
+    for (id, name) in names {
+        if !index.get(name).is_some_and(|ids| ids.contains(&id)) {
+            return false;
+        }
+        count += 1;
+    }
+    count == index.values().map(|ids| ids.len()).sum::<usize>()
+}
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> {
+    /// Find the actor with the given name. If several actors share the name,
+    /// return the one with the lowest identifier.
+    pub fn actor_by_name(&self, name: &str) -> Option<(ActorId, &Actor<S>)> {
+        let &actor_id = self.actor_names.get(name)?.iter().next()?;
+        Some((actor_id, &self.actors[&actor_id]))
+    }
+
+    /// Find the use case with the given title. If several use cases share the
+    /// title, return the one with the lowest identifier.
+    pub fn use_case_by_title(&self, title: &str) -> Option<(UseCaseId, &UseCase<S>)> {
+        let &use_case_id = self.use_case_titles.get(title)?.iter().next()?;
+        Some((use_case_id, &self.use_cases[&use_case_id]))
+    }
+
+    /// Find the actor with the given key. If several actors share the key,
+    /// return the one with the lowest identifier.
+    pub fn actor_by_key(&self, key: &str) -> Option<(ActorId, &Actor<S>)> {
+        let &actor_id = self.actor_keys.get(key)?.iter().next()?;
+        Some((actor_id, &self.actors[&actor_id]))
+    }
+
+    /// Find the use case with the given key. If several use cases share the
+    /// key, return the one with the lowest identifier.
+    pub fn use_case_by_key(&self, key: &str) -> Option<(UseCaseId, &UseCase<S>)> {
+        let &use_case_id = self.use_case_keys.get(key)?.iter().next()?;
+        Some((use_case_id, &self.use_cases[&use_case_id]))
+    }
+
+    /// Insert a new actor, returning its identifier. Return an error, and
+    /// leave the diagram as it was, if this diagram has unique names and the
+    /// name or the key is taken. Diagrams without unique names never return
+    /// one.
+    pub fn insert_actor(&mut self, actor: Actor<S>) -> Result<ActorId, NameError> {
+        if self.unique_names {
+            if let Some((other_id, _)) = self.actor_by_name(actor.name.as_ref()) {
+                return Err(NameError::ActorNameTaken(other_id));
+            }
+            if let Some((other_id, _)) = actor.key.as_ref().and_then(|key| {
+                self.actor_by_key(key.as_ref())
+            }) {
+                return Err(NameError::ActorKeyTaken(other_id));
+            }
+        }
+        let actor_id = self.next_actor_id();
+        index_name(&mut self.actor_names, actor.name.as_ref(), actor_id);
+        index_key(&mut self.actor_keys, actor.key.as_ref().map(AsRef::as_ref), actor_id);
+        self.actors.insert(actor_id, actor);
+        self.emit(Event::ActorInserted(actor_id));
+        self.assert_invariants();
+        Ok(actor_id)
+    }
+
+    /// Insert a new use case, returning its identifier. Return an error, and
+    /// leave the diagram as it was, if this diagram has unique names and the
+    /// title or the key is taken. Diagrams without unique names never return
+    /// one.
+    pub fn insert_use_case(&mut self, use_case: UseCase<S>) -> Result<UseCaseId, NameError> {
+        if self.unique_names {
+            if let Some((other_id, _)) = self.use_case_by_title(use_case.title.as_ref()) {
+                return Err(NameError::UseCaseTitleTaken(other_id));
+            }
+            if let Some((other_id, _)) = use_case.key.as_ref().and_then(|key| {
+                self.use_case_by_key(key.as_ref())
+            }) {
+                return Err(NameError::UseCaseKeyTaken(other_id));
+            }
+        }
+        let use_case_id = self.next_use_case_id();
+        index_name(&mut self.use_case_titles, use_case.title.as_ref(), use_case_id);
+        index_key(&mut self.use_case_keys, use_case.key.as_ref().map(AsRef::as_ref),
+                  use_case_id);
+        self.use_cases.insert(use_case_id, use_case);
+        self.emit(Event::UseCaseInserted(use_case_id));
+        self.assert_invariants();
+        Ok(use_case_id)
+    }
+
+    /// Rename an actor, returning whether it exists. Return an error if this
+    /// diagram has unique names and another actor has the name.
+    pub fn rename_actor(&mut self, actor_id: ActorId, name: S) -> Result<bool, NameError> {
+        if self.unique_names {
+            match self.actor_by_name(name.as_ref()) {
+                Some((other_id, _)) if other_id != actor_id =>
+                    return Err(NameError::ActorNameTaken(other_id)),
+                _ => (),
+            }
+        }
+        let renamed = match self.actors.get_mut(&actor_id) {
+            Some(actor) => {
+                unindex_name(&mut self.actor_names, actor.name.as_ref(), actor_id);
+                index_name(&mut self.actor_names, name.as_ref(), actor_id);
+                actor.name = name;
+                true
+            },
+            None => false,
+        };
+        if renamed {
+            self.emit(Event::ActorChanged(actor_id));
+        }
+        self.assert_invariants();
+        Ok(renamed)
+    }
+
+    /// Retitle a use case, returning whether it exists. Return an error if
+    /// this diagram has unique names and another use case has the title.
+    pub fn retitle_use_case(&mut self, use_case_id: UseCaseId, title: S)
+                            -> Result<bool, NameError> {
+        if self.unique_names {
+            match self.use_case_by_title(title.as_ref()) {
+                Some((other_id, _)) if other_id != use_case_id =>
+                    return Err(NameError::UseCaseTitleTaken(other_id)),
+                _ => (),
+            }
+        }
+        let retitled = match self.use_cases.get_mut(&use_case_id) {
+            Some(use_case) => {
+                unindex_name(&mut self.use_case_titles, use_case.title.as_ref(), use_case_id);
+                index_name(&mut self.use_case_titles, title.as_ref(), use_case_id);
+                use_case.title = title;
+                true
+            },
+            None => false,
+        };
+        if retitled {
+            self.emit(Event::UseCaseChanged(use_case_id));
+        }
+        self.assert_invariants();
+        Ok(retitled)
+    }
+
+    /// Get mutable access to the actor with the given identifier, e.g. to
+    /// edit its description without changing its identifier. The name and key
+    /// indices are updated when the returned guard is dropped. If this
+    /// diagram has unique names and the actor was given a name that is taken,
+    /// dropping the guard gives it back its old name and panics; use
+    /// `rename_actor` to get an error instead. The same goes for a key that is
+    /// taken; check it with `actor_by_key` first.
+    pub fn actor_mut(&mut self, actor_id: ActorId) -> Option<ActorMut<'_, S>> where S: Clone {
+        if self.actors.contains_key(&actor_id) {
+            self.emit(Event::ActorChanged(actor_id));
+        }
+        let unique_names = self.unique_names;
+        let UseCaseDiagram{ref mut actors, ref mut actor_names, ref mut actor_keys, ..} = *self;
+        let actor = actors.get_mut(&actor_id)?;
+        let old_name = actor.name.clone();
+        let old_key = actor.key.clone();
+        Some(ActorMut{actor_id, actor, actor_names, actor_keys, old_name, old_key, unique_names})
+    }
+
+    /// Get mutable access to the use case with the given identifier, e.g. to
+    /// edit its description without changing its identifier. The title and
+    /// key indices are updated when the returned guard is dropped. If this
+    /// diagram has unique names and the use case was given a title that is
+    /// taken, dropping the guard gives it back its old title and panics; use
+    /// `retitle_use_case` to get an error instead. The same goes for a key
+    /// that is taken; check it with `use_case_by_key` first.
+    pub fn use_case_mut(&mut self, use_case_id: UseCaseId) -> Option<UseCaseMut<'_, S>>
+        where S: Clone {
+        if self.use_cases.contains_key(&use_case_id) {
+            self.emit(Event::UseCaseChanged(use_case_id));
+        }
+        let unique_names = self.unique_names;
+        let UseCaseDiagram{ref mut use_cases, ref mut use_case_titles, ref mut use_case_keys, ..} =
+            *self;
+        let use_case = use_cases.get_mut(&use_case_id)?;
+        let old_title = use_case.title.clone();
+        let old_key = use_case.key.clone();
+        Some(UseCaseMut{use_case_id, use_case, use_case_titles, use_case_keys, old_title, old_key,
+                        unique_names})
+    }
+}
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> + for<'a> From<&'a str> {
+    /// The identifier of the actor with the given name, as found by
+    /// `actor_by_name`, inserting a new actor with the name if there is none.
+    pub fn actor_entry(&mut self, name: &str) -> ActorId {
+        match self.actor_by_name(name) {
+            Some((actor_id, _)) => actor_id,
+            None => self.insert_actor(Actor::new(S::from(name))).expect("name is not taken"),
+        }
+    }
+
+    /// The identifier of the use case with the given title, as found by
+    /// `use_case_by_title`, inserting a new use case with the title if there
+    /// is none.
+    pub fn use_case_entry(&mut self, title: &str) -> UseCaseId {
+        match self.use_case_by_title(title) {
+            Some((use_case_id, _)) => use_case_id,
+            None => {
+                self.insert_use_case(UseCase::new(S::from(title))).expect("title is not taken")
+            },
+        }
+    }
+}
+
+/// Mutable access to an actor in a diagram, returned by `actor_mut`.
+pub struct ActorMut<'a, S> where S: AsRef<str> + 'a {
+    actor_id: ActorId,
+    actor: &'a mut Actor<S>,
+    actor_names: &'a mut NameIndex<ActorId>,
+    actor_keys: &'a mut NameIndex<ActorId>,
+    old_name: S,
+    old_key: Option<S>,
+    unique_names: bool,
+}
+
+impl<'a, S> Deref for ActorMut<'a, S> where S: AsRef<str> {
+    type Target = Actor<S>;
+
+    fn deref(&self) -> &Actor<S> {
+        self.actor
+    }
+}
+
+impl<'a, S> DerefMut for ActorMut<'a, S> where S: AsRef<str> {
+    fn deref_mut(&mut self) -> &mut Actor<S> {
+        self.actor
+    }
+}
+
+impl<'a, S> Drop for ActorMut<'a, S> where S: AsRef<str> {
+    fn drop(&mut self) {
+        let key_taken = {
+            let key = self.actor.key.as_ref().map(AsRef::as_ref);
+            self.unique_names && key != self.old_key.as_ref().map(AsRef::as_ref) &&
+                key.is_some_and(|key| self.actor_keys.contains_key(key))
+        };
+        if key_taken {
+            mem::swap(&mut self.actor.key, &mut self.old_key);
+        }
+        let key = self.actor.key.as_ref().map(AsRef::as_ref);
+        let old_key = self.old_key.as_ref().map(AsRef::as_ref);
+        if key != old_key && !key_taken {
+            unindex_key(self.actor_keys, old_key, self.actor_id);
+            index_key(self.actor_keys, key, self.actor_id);
+        }
+        if self.actor.name.as_ref() != self.old_name.as_ref() {
+            if self.unique_names && self.actor_names.contains_key(self.actor.name.as_ref()) {
+                mem::swap(&mut self.actor.name, &mut self.old_name);
+                if !thread::panicking() {
+                    panic!("actor name {:?} is taken; use rename_actor", self.old_name.as_ref());
+                }
+                return;
+            }
+            unindex_name(self.actor_names, self.old_name.as_ref(), self.actor_id);
+            index_name(self.actor_names, self.actor.name.as_ref(), self.actor_id);
+        }
+        if key_taken && !thread::panicking() {
+            panic!("actor key {:?} is taken", self.old_key.as_ref().map(AsRef::as_ref));
+        }
+    }
+}
+
+/// Mutable access to a use case in a diagram, returned by `use_case_mut`.
+pub struct UseCaseMut<'a, S> where S: AsRef<str> + 'a {
+    use_case_id: UseCaseId,
+    use_case: &'a mut UseCase<S>,
+    use_case_titles: &'a mut NameIndex<UseCaseId>,
+    use_case_keys: &'a mut NameIndex<UseCaseId>,
+    old_title: S,
+    old_key: Option<S>,
+    unique_names: bool,
+}
+
+impl<'a, S> Deref for UseCaseMut<'a, S> where S: AsRef<str> {
+    type Target = UseCase<S>;
+
+    fn deref(&self) -> &UseCase<S> {
+        self.use_case
+    }
+}
+
+impl<'a, S> DerefMut for UseCaseMut<'a, S> where S: AsRef<str> {
+    fn deref_mut(&mut self) -> &mut UseCase<S> {
+        self.use_case
+    }
+}
+
+impl<'a, S> Drop for UseCaseMut<'a, S> where S: AsRef<str> {
+    fn drop(&mut self) {
+        let key_taken = {
+            let key = self.use_case.key.as_ref().map(AsRef::as_ref);
+            self.unique_names && key != self.old_key.as_ref().map(AsRef::as_ref) &&
+                key.is_some_and(|key| self.use_case_keys.contains_key(key))
+        };
+        if key_taken {
+            mem::swap(&mut self.use_case.key, &mut self.old_key);
+        }
+        let key = self.use_case.key.as_ref().map(AsRef::as_ref);
+        let old_key = self.old_key.as_ref().map(AsRef::as_ref);
+        if key != old_key && !key_taken {
+            unindex_key(self.use_case_keys, old_key, self.use_case_id);
+            index_key(self.use_case_keys, key, self.use_case_id);
+        }
+        if self.use_case.title.as_ref() != self.old_title.as_ref() {
+            if self.unique_names &&
+               self.use_case_titles.contains_key(self.use_case.title.as_ref()) {
+                mem::swap(&mut self.use_case.title, &mut self.old_title);
+                if !thread::panicking() {
+                    panic!("use case title {:?} is taken; use retitle_use_case",
+                           self.old_title.as_ref());
+                }
+                return;
+            }
+            unindex_name(self.use_case_titles, self.old_title.as_ref(), self.use_case_id);
+            index_name(self.use_case_titles, self.use_case.title.as_ref(), self.use_case_id);
+        }
+        if key_taken && !thread::panicking() {
+            panic!("use case key {:?} is taken", self.old_key.as_ref().map(AsRef::as_ref));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use use_case_diagram::{Actor, NameError, UseCase, UseCaseDiagram};
+
+    use std::panic;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_lookup_by_name() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let a2 = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+
+        assert_eq!(diagram.actor_by_name("Administrator").map(|(id, _)| id), Some(a));
+        assert_eq!(diagram.use_case_by_title("Ban subscriber").map(|(id, _)| id), Some(bs));
+        assert!(diagram.actor_by_name("Subscriber").is_none());
+
+        diagram.remove_actor(a);
+        assert_eq!(diagram.actor_by_name("Administrator").map(|(id, _)| id), Some(a2));
+        diagram.remove_use_case(bs);
+        assert!(diagram.use_case_by_title("Ban subscriber").is_none());
+    }
+
+    #[test]
+    fn test_lookup_after_rename() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Admin"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban"))).unwrap();
+        diagram.actor_mut(a).unwrap().name = Rc::from("Administrator");
+        diagram.use_case_mut(bs).unwrap().title = Rc::from("Ban subscriber");
+
+        assert!(diagram.actor_by_name("Admin").is_none());
+        assert_eq!(diagram.actor_by_name("Administrator").map(|(id, _)| id), Some(a));
+        assert!(diagram.use_case_by_title("Ban").is_none());
+        assert_eq!(diagram.use_case_by_title("Ban subscriber").map(|(id, _)| id), Some(bs));
+    }
+
+
+    #[test]
+    fn test_unique_names() {
+        let mut diagram = UseCaseDiagram::with_unique_names();
+        assert!(diagram.has_unique_names());
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+
+        assert_eq!(diagram.insert_actor(Actor::new(Rc::from("Administrator"))),
+                   Err(NameError::ActorNameTaken(a)));
+        assert_eq!(diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))),
+                   Err(NameError::UseCaseTitleTaken(bs)));
+        assert_eq!(diagram.actors().count(), 2);
+        assert_eq!(diagram.use_cases().count(), 1);
+
+        assert_eq!(diagram.rename_actor(s, Rc::from("Administrator")),
+                   Err(NameError::ActorNameTaken(a)));
+        assert_eq!(diagram.rename_actor(a, Rc::from("Administrator")), Ok(true));
+        assert_eq!(diagram.rename_actor(a, Rc::from("Moderator")), Ok(true));
+        assert!(diagram.insert_actor(Actor::new(Rc::from("Administrator"))).is_ok());
+        assert_eq!(diagram.retitle_use_case(bs, Rc::from("Ban")), Ok(true));
+        assert!(diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).is_ok());
+    }
+
+    #[test]
+    fn test_duplicate_names_allowed_by_default() {
+        let mut diagram = UseCaseDiagram::new();
+        assert!(!diagram.has_unique_names());
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let a2 = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        assert_ne!(a, a2);
+        assert_eq!(diagram.rename_actor(a2, Rc::from("Administrator")), Ok(true));
+    }
+
+    #[test]
+    fn test_unique_names_kept_on_rename_through_guard() {
+        let mut diagram = UseCaseDiagram::with_unique_names();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let b = diagram.insert_use_case(UseCase::new(Rc::from("Ban"))).unwrap();
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            diagram.use_case_mut(b).unwrap().title = Rc::from("Ban subscriber");
+        }));
+        assert!(result.is_err());
+        assert_eq!(&*diagram[b].title, "Ban");
+        assert_eq!(diagram.use_case_by_title("Ban subscriber").map(|(id, _)| id), Some(bs));
+        assert_eq!(diagram.use_case_by_title("Ban").map(|(id, _)| id), Some(b));
+        diagram.use_case_mut(b).unwrap().title = Rc::from("Unban subscriber");
+        assert_eq!(diagram.use_case_by_title("Unban subscriber").map(|(id, _)| id), Some(b));
+    }
+
+    #[test]
+    fn test_lookup_by_key() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut administrator = Actor::new(Rc::from("Administrator"));
+        administrator.key = Some(Rc::from("9f0c"));
+        let a = diagram.insert_actor(administrator).unwrap();
+        let mut ban_subscriber = UseCase::new(Rc::from("Ban subscriber"));
+        ban_subscriber.key = Some(Rc::from("UC-7"));
+        let bs = diagram.insert_use_case(ban_subscriber).unwrap();
+
+        assert_eq!(diagram.actor_by_key("9f0c").map(|(id, _)| id), Some(a));
+        assert_eq!(diagram.use_case_by_key("UC-7").map(|(id, _)| id), Some(bs));
+        assert!(diagram.actor_by_key("UC-7").is_none());
+        assert_eq!(diagram.validate_unique_names(), Ok(()));
+
+        diagram.use_case_mut(bs).unwrap().key = Some(Rc::from("UC-8"));
+        assert!(diagram.use_case_by_key("UC-7").is_none());
+        assert_eq!(diagram.use_case_by_key("UC-8").map(|(id, _)| id), Some(bs));
+
+        let mut moderator = Actor::new(Rc::from("Moderator"));
+        moderator.key = Some(Rc::from("9f0c"));
+        let m = diagram.insert_actor(moderator).unwrap();
+        assert_eq!(diagram.validate_unique_names(), Err(NameError::DuplicateActorKey(a, m)));
+    }
+
+    #[test]
+    fn test_unique_keys() {
+        let mut diagram = UseCaseDiagram::with_unique_names();
+        let mut administrator = Actor::new(Rc::from("Administrator"));
+        administrator.key = Some(Rc::from("9f0c"));
+        let a = diagram.insert_actor(administrator).unwrap();
+        let mut ban_subscriber = UseCase::new(Rc::from("Ban subscriber"));
+        ban_subscriber.key = Some(Rc::from("UC-7"));
+        let bs = diagram.insert_use_case(ban_subscriber).unwrap();
+
+        let mut moderator = Actor::new(Rc::from("Moderator"));
+        moderator.key = Some(Rc::from("9f0c"));
+        assert_eq!(diagram.insert_actor(moderator), Err(NameError::ActorKeyTaken(a)));
+        let mut ban = UseCase::new(Rc::from("Ban"));
+        ban.key = Some(Rc::from("UC-7"));
+        assert_eq!(diagram.insert_use_case(ban), Err(NameError::UseCaseKeyTaken(bs)));
+
+        let m = diagram.insert_actor(Actor::new(Rc::from("Moderator"))).unwrap();
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            let mut moderator = diagram.actor_mut(m).unwrap();
+            moderator.key = Some(Rc::from("9f0c"));
+            moderator.name = Rc::from("Mod");
+        }));
+        assert!(result.is_err());
+        assert!(diagram[m].key.is_none());
+        assert_eq!(diagram.actor_by_name("Mod").map(|(id, _)| id), Some(m));
+        assert_eq!(diagram.actor_by_key("9f0c").map(|(id, _)| id), Some(a));
+        assert_eq!(diagram.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut diagram = UseCaseDiagram::<Rc<str>>::with_unique_names();
+        let a = diagram.actor_entry("Administrator");
+        assert_eq!(diagram.actor_entry("Administrator"), a);
+        assert_eq!(diagram.actors().count(), 1);
+        let bs = diagram.use_case_entry("Ban subscriber");
+        assert_eq!(diagram.use_case_entry("Ban subscriber"), bs);
+        assert_ne!(diagram.use_case_entry("Log in"), bs);
+        assert_eq!(diagram.use_cases().count(), 2);
+    }
+}
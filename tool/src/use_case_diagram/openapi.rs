@@ -0,0 +1,418 @@
+//! Diagrams from OpenAPI specifications, to see who may do what through an
+//! existing API.
+//!
+//! ```text
+//! openapi: 3.0.3
+//! paths:
+//!   /comments:
+//!     post:
+//!       operationId: postComment
+//!       summary: Post comment
+//!       security:
+//!         - forum_auth: [comments:write]
+//! components:
+//!   securitySchemes:
+//!     forum_auth:
+//!       type: oauth2
+//!       flows:
+//!         implicit:
+//!           authorizationUrl: https://example.com/authorize
+//!           scopes:
+//!             comments:write: Subscriber
+//! ```
+//!
+//! Every operation is a use case titled by its summary, its operation ID,
+//! or else its method and path, e.g. `POST /comments`. Its operation ID is
+//! its key, and its description and tags are kept. Operations are in order
+//! of path, and then of method in the order OpenAPI lists methods: `GET`,
+//! `PUT`, `POST`, `DELETE`, `OPTIONS`, `HEAD`, `PATCH` and `TRACE`.
+//! `from_openapi_tags` makes a use case of every tag instead.
+//!
+//! Every scope an operation requires is an actor, and so is every security
+//! scheme it requires without scopes, such as an API key. Roles are scopes
+//! as far as OpenAPI is concerned. Operations without security requirements
+//! of their own have the top-level ones, and operations that require
+//! nothing have no actors. Actors are described by the descriptions of
+//! their scopes or schemes. Both OpenAPI 3 and Swagger 2 specifications are
+//! read, as JSON or as YAML.
+//!
+//! Unlike the other importers, this one has no variant that returns a
+//! `SourceMap`: specifications are read into JSON values, which do not
+//! remember where they were parsed.
+
+use serde_json::{Map, Value};
+
+use use_case_diagram::parser::{error, ParseError, ParseErrorKind, Position};
+use use_case_diagram::yaml;
+use use_case_diagram::{Actor, ActorId, UseCase, UseCaseDiagram, UseCaseId};
+
+const METHODS: [&str; 8] = ["get", "put", "post", "delete", "options", "head", "patch", "trace"];
+const FLOWS: [&str; 6] = [
+    "implicit", "password", "clientCredentials", "authorizationCode", "application", "accessCode",
+];
+
+/// Read a specification into a diagram with a use case for every
+/// operation. Names need not be unique, as summaries often repeat.
+pub fn from_openapi<S>(text: &str) -> Result<UseCaseDiagram<S>, ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    let specification = read_specification(text)?;
+    let mut diagram = UseCaseDiagram::new();
+    for operation in operations(&specification)? {
+        let use_case_id =
+            diagram.insert_use_case(operation.use_case()).expect("names are not unique");
+        associate(&mut diagram, &specification, &operation, use_case_id)?;
+    }
+    Ok(diagram)
+}
+
+/// Read a specification into a diagram with a use case for every tag,
+/// titled by the tag and described by the description of the tag, with the
+/// operations of the tag as its main flow. Operations without tags are use
+/// cases on their own as in `from_openapi`.
+pub fn from_openapi_tags<S>(text: &str) -> Result<UseCaseDiagram<S>, ParseError>
+    where S: AsRef<str> + Clone + for<'a> From<&'a str> {
+    let specification = read_specification(text)?;
+    let mut diagram = UseCaseDiagram::new();
+    let mut tags: Vec<(String, UseCaseId)> = Vec::new();
+    for operation in operations(&specification)? {
+        let operation_tags = operation.tags();
+        if operation_tags.is_empty() {
+            let use_case_id =
+                diagram.insert_use_case(operation.use_case()).expect("names are not unique");
+            associate(&mut diagram, &specification, &operation, use_case_id)?;
+            continue;
+        }
+        for tag in operation_tags {
+            let use_case_id = match tags.iter().find(|&(name, _)| name == tag) {
+                Some(&(_, use_case_id)) => use_case_id,
+                None => {
+                    let mut use_case = UseCase::new(S::from(tag));
+                    use_case.description = tag_description(&specification, tag).map(S::from);
+                    let use_case_id =
+                        diagram.insert_use_case(use_case).expect("names are not unique");
+                    tags.push((tag.to_string(), use_case_id));
+                    use_case_id
+                },
+            };
+            diagram.use_case_mut(use_case_id).unwrap().main_flow
+                .push(S::from(&operation.title()));
+            associate(&mut diagram, &specification, &operation, use_case_id)?;
+        }
+    }
+    Ok(diagram)
+}
+
+/// An operation of a path.
+struct Operation<'a> {
+    method: &'static str,
+    path: &'a str,
+    value: &'a Map<String, Value>,
+}
+
+impl<'a> Operation<'a> {
+    fn title(&self) -> String {
+        string(self.value, "summary").or_else(|| string(self.value, "operationId"))
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{} {}", self.method.to_uppercase(), self.path))
+    }
+
+    fn use_case<S>(&self) -> UseCase<S> where S: for<'b> From<&'b str> {
+        let mut use_case = UseCase::new(S::from(&self.title()));
+        use_case.key = string(self.value, "operationId").map(S::from);
+        use_case.description = string(self.value, "description").map(S::from);
+        use_case.tags = self.tags().into_iter().map(S::from).collect();
+        use_case
+    }
+
+    fn tags(&self) -> Vec<&'a str> {
+        match self.value.get("tags") {
+            Some(Value::Array(tags)) => tags.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Parse JSON or YAML and check that it is a specification.
+fn read_specification(text: &str) -> Result<Map<String, Value>, ParseError> {
+    let value = if text.trim_start().starts_with('{') {
+        ::serde_json::from_str(text).map_err(|err| {
+            let message = err.to_string();
+            let reason = message.split(" at line ").next().unwrap_or(&message).to_string();
+            let position = Position{line: err.line(), column: err.column()};
+            error(position, ParseErrorKind::InvalidOpenApi(reason))
+        })?
+    } else {
+        yaml::parse_value(text)?
+    };
+    match value {
+        Value::Object(specification) => {
+            if !specification.contains_key("openapi") && !specification.contains_key("swagger") {
+                return Err(invalid_at_start(ParseErrorKind::MissingKey("openapi".to_string())));
+            }
+            Ok(specification)
+        },
+        _ => Err(invalid("the document is not a mapping".to_string())),
+    }
+}
+
+/// The operations of the specification in order of path and method.
+fn operations(specification: &Map<String, Value>) -> Result<Vec<Operation<'_>>, ParseError> {
+    let paths = match specification.get("paths") {
+        None | Some(Value::String(_)) => return Ok(Vec::new()),
+        Some(Value::Object(paths)) => paths,
+        Some(_) => return Err(invalid("paths is not a mapping".to_string())),
+    };
+    let mut operations = Vec::new();
+    for (path, item) in paths {
+        let item = item.as_object()
+            .ok_or_else(|| invalid(format!("path {} is not a mapping", path)))?;
+        for &method in &METHODS {
+            if let Some(value) = item.get(method) {
+                let value = value.as_object().ok_or_else(|| {
+                    invalid(format!("operation {} {} is not a mapping",
+                                    method.to_uppercase(), path))
+                })?;
+                operations.push(Operation{method, path, value});
+            }
+        }
+    }
+    Ok(operations)
+}
+
+/// Associate the use case with the actors the operation requires, inserting
+/// those that are not yet in the diagram.
+fn associate<S>(diagram: &mut UseCaseDiagram<S>, specification: &Map<String, Value>,
+                operation: &Operation, use_case_id: UseCaseId) -> Result<(), ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    let requirements = match operation.value.get("security")
+        .or_else(|| specification.get("security")) {
+        None | Some(Value::String(_)) => return Ok(()),
+        Some(Value::Array(requirements)) => requirements,
+        Some(_) => {
+            return Err(invalid(format!("security of {} {} is not a sequence",
+                                       operation.method.to_uppercase(), operation.path)));
+        },
+    };
+    for requirement in requirements {
+        let requirement = match *requirement {
+            Value::Object(ref requirement) => requirement,
+            Value::String(_) => continue,
+            _ => {
+                return Err(invalid(format!("security requirement of {} {} is not a mapping",
+                                           operation.method.to_uppercase(), operation.path)));
+            },
+        };
+        for (scheme_name, scopes) in requirement {
+            let scheme = security_scheme(specification, scheme_name).ok_or_else(|| {
+                invalid_at_start(ParseErrorKind::UnknownReference(scheme_name.clone()))
+            })?;
+            let scopes = match *scopes {
+                Value::Array(ref scopes) => scopes.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            let actors = if scopes.is_empty() {
+                vec![(scheme_name.as_str(), string(scheme, "description"))]
+            } else {
+                scopes.into_iter()
+                    .map(|scope| (scope, scope_description(scheme, scope)))
+                    .collect()
+            };
+            for (name, description) in actors {
+                let actor_id = actor(diagram, name, description);
+                diagram.insert_association(actor_id, use_case_id).unwrap();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The actor with the name, inserted if there is none.
+fn actor<S>(diagram: &mut UseCaseDiagram<S>, name: &str, description: Option<&str>) -> ActorId
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    match diagram.actor_by_name(name) {
+        Some((actor_id, _)) => actor_id,
+        None => {
+            let mut actor = Actor::new(S::from(name));
+            actor.description = description.map(S::from);
+            diagram.insert_actor(actor).expect("name is not taken")
+        },
+    }
+}
+
+/// The definition of a security scheme, in OpenAPI 3 or Swagger 2.
+fn security_scheme<'a>(specification: &'a Map<String, Value>, name: &str)
+                       -> Option<&'a Map<String, Value>> {
+    specification.get("components").and_then(|components| components.get("securitySchemes"))
+        .or_else(|| specification.get("securityDefinitions"))
+        .and_then(|schemes| schemes.get(name))
+        .and_then(Value::as_object)
+}
+
+/// The description of a scope in any flow of a security scheme.
+fn scope_description<'a>(scheme: &'a Map<String, Value>, scope: &str) -> Option<&'a str> {
+    let flows = scheme.get("flows").and_then(Value::as_object);
+    let scope_maps = FLOWS.iter()
+        .filter_map(|&flow| flows.and_then(|flows| flows.get(flow)))
+        .filter_map(|flow| flow.get("scopes"))
+        .chain(scheme.get("scopes"));
+    scope_maps.filter_map(|scopes| scopes.get(scope)).filter_map(Value::as_str)
+        .find(|description| !description.is_empty())
+}
+
+fn tag_description<'a>(specification: &'a Map<String, Value>, tag: &str) -> Option<&'a str> {
+    match specification.get("tags") {
+        Some(Value::Array(tags)) => tags.iter()
+            .find(|definition| definition.get("name").and_then(Value::as_str) == Some(tag))
+            .and_then(|definition| definition.get("description"))
+            .and_then(Value::as_str)
+            .filter(|description| !description.is_empty()),
+        _ => None,
+    }
+}
+
+/// The string under the key, if it is one and is not empty.
+fn string<'a>(object: &'a Map<String, Value>, key: &str) -> Option<&'a str> {
+    object.get(key).and_then(Value::as_str).filter(|s| !s.is_empty())
+}
+
+fn invalid(reason: String) -> ParseError {
+    invalid_at_start(ParseErrorKind::InvalidOpenApi(reason))
+}
+
+/// Errors in the structure of a specification have no position, as values
+/// do not remember where they were parsed.
+fn invalid_at_start(kind: ParseErrorKind) -> ParseError {
+    error(Position{line: 1, column: 1}, kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    const YAML: &str = concat!(
+        "openapi: 3.0.3\n",
+        "info:\n",
+        "  title: Forum\n",
+        "  version: 1.0.0\n",
+        "tags:\n",
+        "  - name: comments\n",
+        "    description: Discussion below articles\n",
+        "security:\n",
+        "  - forum_auth: [comments:read]\n",
+        "paths:\n",
+        "  /comments:\n",
+        "    post:\n",
+        "      operationId: postComment\n",
+        "      summary: Post comment\n",
+        "      description: |\n",
+        "        Post a comment,\n",
+        "        or a reply.\n",
+        "      tags: [comments]\n",
+        "      security:\n",
+        "        - forum_auth: [comments:write]\n",
+        "    get:\n",
+        "      operationId: listComments\n",
+        "      tags: [comments]\n",
+        "  /health:\n",
+        "    get:\n",
+        "      security: []\n",
+        "  /subscribers/{id}:\n",
+        "    delete:\n",
+        "      summary: Ban subscriber\n",
+        "      security:\n",
+        "        - api_key: []\n",
+        "components:\n",
+        "  securitySchemes:\n",
+        "    api_key:\n",
+        "      type: apiKey\n",
+        "      in: header\n",
+        "      name: X-API-Key\n",
+        "      description: Administrator\n",
+        "    forum_auth:\n",
+        "      type: oauth2\n",
+        "      flows:\n",
+        "        implicit:\n",
+        "          authorizationUrl: https://example.com/authorize\n",
+        "          scopes:\n",
+        "            comments:read: Reader\n",
+        "            comments:write: Subscriber\n",
+    );
+
+    #[test]
+    fn test_from_openapi() {
+        let diagram = from_openapi::<Rc<str>>(YAML).unwrap();
+        let titles = diagram.use_cases_in_order().iter()
+            .map(|&(_, use_case)| use_case.title.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(titles, vec!["listComments", "Post comment", "GET /health", "Ban subscriber"]);
+        let names = diagram.actors_in_order().iter()
+            .map(|&(_, actor)| (actor.name.to_string(), actor.description.as_deref().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec![("comments:read".to_string(), "Reader"),
+                               ("comments:write".to_string(), "Subscriber"),
+                               ("api_key".to_string(), "Administrator")]);
+
+        let (post, use_case) = diagram.use_case_by_title("Post comment").unwrap();
+        assert_eq!(use_case.key.as_deref(), Some("postComment"));
+        assert_eq!(use_case.description.as_deref(), Some("Post a comment,\nor a reply.\n"));
+        assert_eq!(use_case.tags, vec![Rc::from("comments")]);
+        let (list, _) = diagram.use_case_by_title("listComments").unwrap();
+        let (ban, _) = diagram.use_case_by_title("Ban subscriber").unwrap();
+        let (read, _) = diagram.actor_by_name("comments:read").unwrap();
+        let (write, _) = diagram.actor_by_name("comments:write").unwrap();
+        let (api_key, _) = diagram.actor_by_name("api_key").unwrap();
+        let mut associations = diagram.associations().collect::<Vec<_>>();
+        associations.sort();
+        assert_eq!(associations, vec![(read, list), (write, post), (api_key, ban)]);
+
+        let json = concat!(
+            "{\"swagger\": \"2.0\", \"paths\": {\"/comments\": {\"get\": {",
+            "\"security\": [{\"auth\": [\"read\"]}]}}},",
+            "\"securityDefinitions\": {\"auth\": {\"type\": \"oauth2\", \"flow\": \"implicit\",",
+            "\"scopes\": {\"read\": \"Reader\"}}}}",
+        );
+        let diagram = from_openapi::<Rc<str>>(json).unwrap();
+        let (reader, actor) = diagram.actor_by_name("read").unwrap();
+        assert_eq!(actor.description.as_deref(), Some("Reader"));
+        let (list, _) = diagram.use_case_by_title("GET /comments").unwrap();
+        assert_eq!(diagram.associations().collect::<Vec<_>>(), vec![(reader, list)]);
+    }
+
+    #[test]
+    fn test_from_openapi_tags() {
+        let diagram = from_openapi_tags::<Rc<str>>(YAML).unwrap();
+        let titles = diagram.use_cases_in_order().iter()
+            .map(|&(_, use_case)| use_case.title.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(titles, vec!["comments", "GET /health", "Ban subscriber"]);
+        let (comments, use_case) = diagram.use_case_by_title("comments").unwrap();
+        assert_eq!(use_case.description.as_deref(), Some("Discussion below articles"));
+        assert_eq!(use_case.main_flow, vec![Rc::from("listComments"), Rc::from("Post comment")]);
+        let (read, _) = diagram.actor_by_name("comments:read").unwrap();
+        let (write, _) = diagram.actor_by_name("comments:write").unwrap();
+        assert!(diagram.association(read, comments).is_some());
+        assert!(diagram.association(write, comments).is_some());
+    }
+
+    #[test]
+    fn test_from_openapi_errors() {
+        fn err(text: &str) -> (usize, usize, ParseErrorKind) {
+            let err = from_openapi::<Rc<str>>(text).unwrap_err();
+            (err.position.line, err.position.column, err.kind)
+        }
+        assert_eq!(err("info:\n  title: Forum\n"),
+                   (1, 1, ParseErrorKind::MissingKey("openapi".to_string())));
+        assert_eq!(err(concat!("openapi: 3.0.0\npaths:\n  /a:\n    get:\n",
+                               "      security:\n        - x: []\n")),
+                   (1, 1, ParseErrorKind::UnknownReference("x".to_string())));
+        assert_eq!(err("openapi: 3.0.0\npaths:\n  /a:\n    get: nothing\n"),
+                   (1, 1, ParseErrorKind::InvalidOpenApi(
+                       "operation GET /a is not a mapping".to_string())));
+        match err("{\"openapi\": \"3.0.0\",\n  \"paths\": }") {
+            (2, 12, ParseErrorKind::InvalidOpenApi(_)) => (),
+            err => panic!("unexpected {:?}", err),
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use use_case_diagram::{Actor, ActorId, UseCase, UseCaseDiagram, UseCaseId};
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> {
+    /// All actors in the order generated output should list them: actors
+    /// with a sort key first, by sort key, then the others, with ties broken
+    /// by identifier.
+    pub fn actors_in_order(&self) -> Vec<(ActorId, &Actor<S>)> {
+        let mut actors = self.actors().collect::<Vec<_>>();
+        actors.sort_by_key(|&(actor_id, actor)| (actor.sort_key.is_none(), actor.sort_key,
+                                                 actor_id));
+        actors
+    }
+
+    /// All use cases in the order generated output should list them, like
+    /// `actors_in_order`.
+    pub fn use_cases_in_order(&self) -> Vec<(UseCaseId, &UseCase<S>)> {
+        let mut use_cases = self.use_cases().collect::<Vec<_>>();
+        use_cases.sort_by_key(|&(use_case_id, use_case)| {
+            (use_case.sort_key.is_none(), use_case.sort_key, use_case_id)
+        });
+        use_cases
+    }
+
+    /// Sort actors of this diagram into the order of `actors_in_order`.
+    pub fn sort_actors(&self, actor_ids: &mut [ActorId]) {
+        actor_ids.sort_by_key(|&actor_id| {
+            let sort_key = self.actor(actor_id).and_then(|actor| actor.sort_key);
+            (sort_key.is_none(), sort_key, actor_id)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_in_order() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let mut subscriber = Actor::new(Rc::from("Subscriber"));
+        subscriber.sort_key = Some(10);
+        let s = diagram.insert_actor(subscriber).unwrap();
+        let mut ban = UseCase::new(Rc::from("Ban subscriber"));
+        ban.sort_key = Some(20);
+        let bs = diagram.insert_use_case(ban).unwrap();
+        let mut log_in = UseCase::new(Rc::from("Log in"));
+        log_in.sort_key = Some(-5);
+        let li = diagram.insert_use_case(log_in).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+
+        assert_eq!(diagram.actors_in_order().into_iter().map(|(id, _)| id).collect::<Vec<_>>(),
+                   vec![s, a]);
+        assert_eq!(diagram.use_cases_in_order().into_iter().map(|(id, _)| id)
+                   .collect::<Vec<_>>(),
+                   vec![li, bs, pc]);
+        let mut actor_ids = vec![a, s];
+        diagram.sort_actors(&mut actor_ids);
+        assert_eq!(actor_ids, vec![s, a]);
+    }
+}
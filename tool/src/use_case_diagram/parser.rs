@@ -0,0 +1,847 @@
+//! A small declarative language for use case diagrams. Every line holds at
+//! most one statement:
+//!
+//! ```text
+//! # Moderation
+//! actor Administrator
+//! usecase "Ban subscriber"
+//! Administrator -> "Ban subscriber"
+//! ```
+//!
+//! - `actor NAME` declares an actor.
+//! - `usecase TITLE` declares a use case.
+//! - `NAME -> TITLE` associates a declared actor with a declared use case.
+//!   Declarations may follow the associations that refer to them.
+//! - `include "FILE"` reads another file in its place, in projects read with
+//!   the `project` module.
+//!
+//! Names and titles are either identifiers, made of letters, digits and
+//! underscores, or double-quoted strings in which `\"`, `\\` and `\n` are
+//! escapes. `actor` and `usecase` are keywords; quote them to use them as
+//! names, while `include` is only one when followed by a string. `#`
+//! starts a comment that runs until the end of the line. Names and titles
+//! must be unique.
+
+use std::error::Error;
+use std::fmt;
+
+use use_case_diagram::lint::Severity;
+use use_case_diagram::source_map::span;
+use use_case_diagram::{Actor, ActorId, Association, Extend, NameError, RelationshipError,
+                       SourceMap, UseCase, UseCaseDiagram, UseCaseId};
+
+/// A position in the text, with lines and columns counted from one.
+/// Columns count characters, not bytes.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// An error in the text, at the position where it was detected. Importers of
+/// other textual formats, such as PlantUML, report this error too.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+    pub position: Position,
+    pub kind: ParseErrorKind,
+}
+
+/// What is wrong with the text.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseErrorKind {
+    /// A character that cannot start a token.
+    UnexpectedCharacter(char),
+
+    /// A string that is not closed before the end of the line.
+    UnterminatedString,
+
+    /// A backslash in a string followed by a character other than `"`, `\`
+    /// or `n`.
+    InvalidEscape(char),
+
+    /// A name or title was expected.
+    ExpectedName,
+
+    /// A name at the start of a statement is not followed by `->`.
+    ExpectedArrow,
+
+    /// A complete statement is followed by more tokens.
+    ExpectedEndOfLine,
+
+    /// An association refers to an actor that is not declared.
+    UnknownActorName(String),
+
+    /// An association refers to a use case that is not declared.
+    UnknownUseCaseTitle(String),
+
+    /// A name or title is declared twice.
+    Name(NameError),
+
+    /// A name or title is declared again in a project, after being declared
+    /// in the given file at the given position.
+    Redefinition(String, String, Position),
+
+    /// A file cannot be included, for the given reason.
+    Include(String, String),
+
+    /// A statement that is valid in the imported format but cannot be
+    /// represented in a diagram, or is not understood.
+    UnsupportedStatement,
+
+    /// A relationship between elements of kinds that it cannot relate.
+    UnsupportedRelationship,
+
+    /// A closing brace, or the end of a block, without an opening one.
+    UnbalancedBrace,
+
+    /// A node that is neither shaped nor named like an actor or a use case.
+    UntaggedNode(String),
+
+    /// The text is not well-formed XML, for the given reason.
+    InvalidXml(String),
+
+    /// The text is not YAML this library reads, for the given reason.
+    InvalidYaml(String),
+
+    /// The text is not TOML, for the given reason.
+    InvalidToml(String),
+
+    /// A mapping has a key that is not understood.
+    UnknownKey(String),
+
+    /// A mapping lacks a key it must have.
+    MissingKey(String),
+
+    /// An element refers to an identifier that no element has, or that
+    /// belongs to an element of the wrong kind.
+    UnknownReference(String),
+
+    /// The text has no table where one is expected.
+    MissingTable,
+
+    /// The text is not an OpenAPI specification, for the given reason.
+    InvalidOpenApi(String),
+
+    /// The text is not a GraphQL schema, for the given reason.
+    InvalidGraphQl(String),
+
+    /// A block, such as a note or comment, is not closed before the end of the
+    /// text.
+    UnterminatedBlock,
+
+    /// A relationship is invalid, e.g. closes a cycle.
+    Relationship(RelationshipError),
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseErrorKind::UnexpectedCharacter(c) =>
+                write!(f, "unexpected character {:?}", c),
+            ParseErrorKind::UnterminatedString =>
+                write!(f, "unterminated string"),
+            ParseErrorKind::InvalidEscape(c) =>
+                write!(f, "invalid escape sequence \\{}", c),
+            ParseErrorKind::ExpectedName =>
+                write!(f, "expected name"),
+            ParseErrorKind::ExpectedArrow =>
+                write!(f, "expected ->"),
+            ParseErrorKind::ExpectedEndOfLine =>
+                write!(f, "expected end of line"),
+            ParseErrorKind::UnknownActorName(ref name) =>
+                write!(f, "unknown actor {:?}", name),
+            ParseErrorKind::UnknownUseCaseTitle(ref title) =>
+                write!(f, "unknown use case {:?}", title),
+            ParseErrorKind::Name(ref err) =>
+                write!(f, "{}", err),
+            ParseErrorKind::Redefinition(ref name, ref file, position) =>
+                write!(f, "{:?} is already defined at {}:{}", name, file, position),
+            ParseErrorKind::Include(ref path, ref reason) =>
+                write!(f, "cannot include {:?}: {}", path, reason),
+            ParseErrorKind::UnsupportedStatement =>
+                write!(f, "unsupported statement"),
+            ParseErrorKind::UnsupportedRelationship =>
+                write!(f, "unsupported relationship"),
+            ParseErrorKind::UnbalancedBrace =>
+                write!(f, "unbalanced brace"),
+            ParseErrorKind::UntaggedNode(ref id) =>
+                write!(f, "node {:?} is neither an actor nor a use case", id),
+            ParseErrorKind::InvalidXml(ref message) =>
+                write!(f, "invalid XML: {}", message),
+            ParseErrorKind::InvalidYaml(ref message) =>
+                write!(f, "invalid YAML: {}", message),
+            ParseErrorKind::InvalidToml(ref message) =>
+                write!(f, "invalid TOML: {}", message),
+            ParseErrorKind::UnknownKey(ref key) =>
+                write!(f, "unknown key {:?}", key),
+            ParseErrorKind::MissingKey(ref key) =>
+                write!(f, "missing key {:?}", key),
+            ParseErrorKind::UnknownReference(ref id) =>
+                write!(f, "unknown reference {:?}", id),
+            ParseErrorKind::MissingTable =>
+                write!(f, "no table found"),
+            ParseErrorKind::InvalidOpenApi(ref reason) =>
+                write!(f, "invalid OpenAPI specification: {}", reason),
+            ParseErrorKind::InvalidGraphQl(ref reason) =>
+                write!(f, "invalid GraphQL schema: {}", reason),
+            ParseErrorKind::UnterminatedBlock =>
+                write!(f, "unterminated block"),
+            ParseErrorKind::Relationship(ref err) =>
+                write!(f, "{}", err),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.position, self.kind)
+    }
+}
+
+impl Error for ParseError {
+    fn description(&self) -> &str {
+        match self.kind {
+            ParseErrorKind::UnexpectedCharacter(_) => "unexpected character",
+            ParseErrorKind::UnterminatedString => "unterminated string",
+            ParseErrorKind::InvalidEscape(_) => "invalid escape sequence",
+            ParseErrorKind::ExpectedName => "expected name",
+            ParseErrorKind::ExpectedArrow => "expected ->",
+            ParseErrorKind::ExpectedEndOfLine => "expected end of line",
+            ParseErrorKind::UnknownActorName(_) => "unknown actor",
+            ParseErrorKind::UnknownUseCaseTitle(_) => "unknown use case",
+            ParseErrorKind::Name(_) => "duplicate name",
+            ParseErrorKind::Redefinition(_, _, _) => "redefinition",
+            ParseErrorKind::Include(_, _) => "cannot include file",
+            ParseErrorKind::UnsupportedStatement => "unsupported statement",
+            ParseErrorKind::UnsupportedRelationship => "unsupported relationship",
+            ParseErrorKind::UnbalancedBrace => "unbalanced brace",
+            ParseErrorKind::UntaggedNode(_) => "node is neither an actor nor a use case",
+            ParseErrorKind::InvalidXml(_) => "invalid XML",
+            ParseErrorKind::InvalidYaml(_) => "invalid YAML",
+            ParseErrorKind::InvalidToml(_) => "invalid TOML",
+            ParseErrorKind::UnknownKey(_) => "unknown key",
+            ParseErrorKind::MissingKey(_) => "missing key",
+            ParseErrorKind::UnknownReference(_) => "unknown reference",
+            ParseErrorKind::MissingTable => "missing table",
+            ParseErrorKind::InvalidOpenApi(_) => "invalid OpenAPI specification",
+            ParseErrorKind::InvalidGraphQl(_) => "invalid GraphQL schema",
+            ParseErrorKind::UnterminatedBlock => "unterminated block",
+            ParseErrorKind::Relationship(_) => "invalid relationship",
+        }
+    }
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self.kind {
+            ParseErrorKind::Name(ref err) => Some(err),
+            ParseErrorKind::Relationship(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// A problem found by a parser that recovers from errors, which skipped the
+/// text from `start` to `end` because of it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub start: Position,
+    pub end: Position,
+    pub kind: ParseErrorKind,
+}
+
+impl Diagnostic {
+    /// A diagnostic for the error, skipping to `end`.
+    pub(super) fn new(severity: Severity, err: ParseError, end: Position) -> Self {
+        Diagnostic{severity, start: err.position, end, kind: err.kind}
+    }
+
+    /// A human-readable description of the problem.
+    pub fn message(&self) -> String {
+        self.kind.to_string()
+    }
+
+    /// The error a parser that does not recover reports instead.
+    pub fn into_error(self) -> ParseError {
+        error(self.start, self.kind)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.start, self.severity, self.kind)
+    }
+}
+
+/// Parse the text into a diagram with unique names.
+pub fn parse<S>(text: &str) -> Result<UseCaseDiagram<S>, ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    parse_with_source_map(text, "").map(|(diagram, _)| diagram)
+}
+
+/// Parse the text of the file into a diagram with unique names like
+/// `parse`, and map every element to the start of the statement that
+/// defines it.
+pub fn parse_with_source_map<S>(text: &str, file: &str)
+                                -> Result<(UseCaseDiagram<S>, SourceMap<S>), ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    let (diagram, source_map, diagnostics) = read(text, file);
+    match diagnostics.into_iter().next() {
+        Some(diagnostic) => Err(diagnostic.into_error()),
+        None => Ok((diagram, source_map)),
+    }
+}
+
+/// Parse the text into a diagram with unique names like `parse`, but skip
+/// the lines with errors and the associations that refer to elements that
+/// are not declared, for editors to show every problem at once. The
+/// diagnostics are errors, for lines and then for associations, each in
+/// order.
+pub fn parse_with_diagnostics<S>(text: &str) -> (UseCaseDiagram<S>, Vec<Diagnostic>)
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    let (diagram, _, diagnostics) = read(text, "");
+    (diagram, diagnostics)
+}
+
+fn read<S>(text: &str, file: &str) -> (UseCaseDiagram<S>, SourceMap<S>, Vec<Diagnostic>)
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    let mut reader = Reader::new(false);
+    let mut diagnostics = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let result = reader.read_line(file, index + 1, line).and_then(|include| match include {
+            Some((position, path)) => {
+                let reason = "includes are only read in projects".to_string();
+                Err(error(position, ParseErrorKind::Include(path, reason)))
+            },
+            None => Ok(()),
+        });
+        if let Err(err) = result {
+            diagnostics.push(Diagnostic::new(Severity::Error, err, line_end(index + 1, line)));
+        }
+    }
+    diagnostics.extend(reader.associate().into_iter().map(|(_, diagnostic)| diagnostic));
+    (reader.diagram, reader.source_map, diagnostics)
+}
+
+/// Reads the statements of one or more files into a diagram with unique
+/// names, and then the associations between the elements they declare.
+pub(super) struct Reader<S> {
+    pub(super) diagram: UseCaseDiagram<S>,
+    pub(super) source_map: SourceMap<S>,
+    /// Whether a name declared twice is reported as a redefinition, with
+    /// where it was first declared, rather than as a name error.
+    redefinitions: bool,
+    associations: Vec<(String, Name, Name, Position)>,
+}
+
+impl<S> Reader<S> where S: AsRef<str> + for<'a> From<&'a str> {
+    pub(super) fn new(redefinitions: bool) -> Self {
+        Reader{diagram: UseCaseDiagram::with_unique_names(), source_map: SourceMap::new(),
+               redefinitions, associations: Vec::new()}
+    }
+
+    /// Read a line of the file, returning the path and position of the file
+    /// it includes, if any, for the caller to read.
+    pub(super) fn read_line(&mut self, file: &str, line_number: usize, line: &str)
+                            -> Result<Option<Name>, ParseError> {
+        let end = line_end(line_number, line);
+        let (tokens, _) = tokenize(line_number, line)?;
+        match parse_statement(&tokens, end)? {
+            None => (),
+            Some(Statement::Actor(position, name)) => {
+                let actor_id = match self.diagram.insert_actor(Actor::new(S::from(&name))) {
+                    Ok(actor_id) => actor_id,
+                    Err(err) => return Err(self.name_error(position, name, err)),
+                };
+                self.source_map.actors.insert(actor_id, span(file, tokens[0].0));
+            },
+            Some(Statement::UseCase(position, title)) => {
+                let use_case = UseCase::new(S::from(&title));
+                let use_case_id = match self.diagram.insert_use_case(use_case) {
+                    Ok(use_case_id) => use_case_id,
+                    Err(err) => return Err(self.name_error(position, title, err)),
+                };
+                self.source_map.use_cases.insert(use_case_id, span(file, tokens[0].0));
+            },
+            Some(Statement::Association(actor, use_case)) =>
+                self.associations.push((file.to_string(), actor, use_case, end)),
+            Some(Statement::Include(position, path)) => return Ok(Some((position, path))),
+        }
+        Ok(None)
+    }
+
+    fn name_error(&self, position: Position, name: String, err: NameError) -> ParseError {
+        let first = match err {
+            NameError::ActorNameTaken(actor_id) => self.source_map.actors.get(&actor_id),
+            NameError::UseCaseTitleTaken(use_case_id) =>
+                self.source_map.use_cases.get(&use_case_id),
+            _ => None,
+        };
+        match first {
+            Some(first) if self.redefinitions => {
+                let first_position = Position{line: first.line, column: first.column};
+                let file = first.file.as_ref().to_string();
+                error(position, ParseErrorKind::Redefinition(name, file, first_position))
+            },
+            _ => error(position, ParseErrorKind::Name(err)),
+        }
+    }
+
+    /// Insert the associations of the lines read, in order, returning the
+    /// errors for those that refer to elements that are not declared, with
+    /// the files they are in.
+    pub(super) fn associate(&mut self) -> Vec<(String, Diagnostic)> {
+        let mut diagnostics = Vec::new();
+        for (file, (actor_position, actor), (use_case_position, use_case), end)
+            in self.associations.drain(..) {
+            let actor_id = self.diagram.actor_by_name(&actor).map(|(actor_id, _)| actor_id);
+            let use_case_id = self.diagram.use_case_by_title(&use_case)
+                .map(|(use_case_id, _)| use_case_id);
+            let err = match (actor_id, use_case_id) {
+                (Some(actor_id), Some(use_case_id)) => {
+                    self.diagram.insert_association(actor_id, use_case_id)
+                        .expect("association of existing elements");
+                    self.source_map.associations.entry((actor_id, use_case_id))
+                        .or_insert_with(|| span(&file, actor_position));
+                    continue;
+                },
+                (None, _) => error(actor_position, ParseErrorKind::UnknownActorName(actor)),
+                (_, None) =>
+                    error(use_case_position, ParseErrorKind::UnknownUseCaseTitle(use_case)),
+            };
+            diagnostics.push((file, Diagnostic::new(Severity::Error, err, end)));
+        }
+        diagnostics
+    }
+}
+
+/// The position after the last character of the line.
+fn line_end(line_number: usize, line: &str) -> Position {
+    Position{line: line_number, column: line.chars().count() + 1}
+}
+
+pub(super) fn error(position: Position, kind: ParseErrorKind) -> ParseError {
+    ParseError{position, kind}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(super) enum Token {
+    Word(String),
+    String(String),
+    Arrow,
+}
+
+pub(super) type Tokens = Vec<(Position, Token)>;
+
+/// The tokens of a line, and the comment at its end without the `#`.
+pub(super) fn tokenize(line_number: usize, line: &str)
+                       -> Result<(Tokens, Option<&str>), ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices().enumerate().peekable();
+    while let Some((index, (offset, c))) = chars.next() {
+        let position = Position{line: line_number, column: index + 1};
+        match c {
+            '#' => return Ok((tokens, Some(&line[offset + 1..]))),
+            '-' if chars.peek().map(|&(_, (_, c))| c) == Some('>') => {
+                chars.next();
+                tokens.push((position, Token::Arrow));
+            },
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err(error(position, ParseErrorKind::UnterminatedString)),
+                        Some((_, (_, '"'))) => break,
+                        Some((index, (_, '\\'))) => match chars.next() {
+                            Some((_, (_, '"'))) => string.push('"'),
+                            Some((_, (_, '\\'))) => string.push('\\'),
+                            Some((_, (_, 'n'))) => string.push('\n'),
+                            Some((_, (_, c))) => {
+                                let position = Position{line: line_number, column: index + 1};
+                                return Err(error(position, ParseErrorKind::InvalidEscape(c)));
+                            },
+                            None =>
+                                return Err(error(position, ParseErrorKind::UnterminatedString)),
+                        },
+                        Some((_, (_, c))) => string.push(c),
+                    }
+                }
+                tokens.push((position, Token::String(string)));
+            },
+            c if is_word_char(c) => {
+                let mut word = c.to_string();
+                while let Some(&(_, (_, c))) = chars.peek() {
+                    if !is_word_char(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push((position, Token::Word(word)));
+            },
+            c if c.is_whitespace() => (),
+            c => return Err(error(position, ParseErrorKind::UnexpectedCharacter(c))),
+        }
+    }
+    Ok((tokens, None))
+}
+
+pub(super) fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+pub(super) type Name = (Position, String);
+
+pub(super) enum Statement {
+    Actor(Position, String),
+    UseCase(Position, String),
+    Association(Name, Name),
+    Include(Position, String),
+}
+
+pub(super) fn parse_statement(tokens: &[(Position, Token)], end: Position)
+                              -> Result<Option<Statement>, ParseError> {
+    let position_at = |index: usize| tokens.get(index).map(|&(position, _)| position)
+        .unwrap_or(end);
+    let statement = match tokens.first() {
+        None => return Ok(None),
+        Some(&(_, Token::Word(ref keyword))) if keyword == "actor" => {
+            let (position, name) = expect_name(tokens, 1, end)?;
+            Statement::Actor(position, name)
+        },
+        Some(&(_, Token::Word(ref keyword))) if keyword == "usecase" => {
+            let (position, title) = expect_name(tokens, 1, end)?;
+            Statement::UseCase(position, title)
+        },
+        Some(&(_, Token::Word(ref keyword))) if keyword == "include" => match tokens.get(1) {
+            Some(&(position, Token::String(ref path))) =>
+                Statement::Include(position, path.clone()),
+            _ => association(tokens, end)?,
+        },
+        Some(_) => association(tokens, end)?,
+    };
+    let length = match statement {
+        Statement::Association(_, _) => 3,
+        _ => 2,
+    };
+    if tokens.len() > length {
+        return Err(error(position_at(length), ParseErrorKind::ExpectedEndOfLine));
+    }
+    Ok(Some(statement))
+}
+
+fn association(tokens: &[(Position, Token)], end: Position) -> Result<Statement, ParseError> {
+    let actor = expect_name(tokens, 0, end)?;
+    match tokens.get(1) {
+        Some(&(_, Token::Arrow)) => (),
+        Some(&(position, _)) => return Err(error(position, ParseErrorKind::ExpectedArrow)),
+        None => return Err(error(end, ParseErrorKind::ExpectedArrow)),
+    }
+    let use_case = expect_name(tokens, 2, end)?;
+    Ok(Statement::Association(actor, use_case))
+}
+
+fn expect_name(tokens: &[(Position, Token)], index: usize, end: Position)
+               -> Result<Name, ParseError> {
+    match tokens.get(index) {
+        Some(&(position, Token::String(ref string))) => Ok((position, string.clone())),
+        Some(&(position, Token::Word(ref word))) if !is_keyword(word) =>
+            Ok((position, word.clone())),
+        Some(&(position, _)) => Err(error(position, ParseErrorKind::ExpectedName)),
+        None => Err(error(end, ParseErrorKind::ExpectedName)),
+    }
+}
+
+pub(super) fn is_keyword(word: &str) -> bool {
+    word == "actor" || word == "usecase"
+}
+
+/// An element of a diagram being imported.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum Element {
+    Actor(ActorId),
+    UseCase(UseCaseId),
+}
+
+/// The shape of an arrow in an imported diagram: whether it has a head on
+/// either side, and whether it stands for a generalization.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) struct Arrow {
+    pub(super) left_head: bool,
+    pub(super) right_head: bool,
+    pub(super) generalization: bool,
+}
+
+/// Insert the relationship drawn as an arrow between two elements. Arrows
+/// between an actor and a use case are associations, with the label as
+/// theirs. Arrows between use cases must be generalizations or be labelled
+/// with a stereotype, in guillemets or not: `include` and `extend` (or
+/// `extends`) from the base and the extension respectively, and `depends
+/// on`, followed by the label of the dependency, from the dependent use case.
+/// Generalizations between two actors or two use cases point towards the
+/// parent. Errors are reported at `start`, or at `right_position` if the
+/// elements cannot be related this way.
+pub(super) fn relate<S>(diagram: &mut UseCaseDiagram<S>, left: Element, arrow: Arrow,
+                        right: Element, label: Option<&str>, start: Position,
+                        right_position: Position) -> Result<(), ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    let relationship_error = |err| error(start, ParseErrorKind::Relationship(err));
+    // The tail and the head of the arrow, or left and right if it has no head
+    // or two.
+    let (tail, head) = if arrow.left_head && !arrow.right_head {
+        (right, left)
+    } else {
+        (left, right)
+    };
+    let (stereotype, rest) = match label {
+        Some(label) => split_stereotype(label),
+        None => (String::new(), None),
+    };
+    match (tail, head) {
+        (Element::Actor(actor_id), Element::UseCase(use_case_id)) |
+        (Element::UseCase(use_case_id), Element::Actor(actor_id)) if !arrow.generalization => {
+            let association = Association{
+                label: label.map(S::from),
+                ..Association::default()
+            };
+            diagram.insert_association_with(actor_id, use_case_id, association)
+                .expect("association of existing elements");
+        },
+        (Element::Actor(child), Element::Actor(parent)) if arrow.generalization => {
+            diagram.insert_actor_generalization(child, parent).map_err(relationship_error)?;
+        },
+        (Element::UseCase(child), Element::UseCase(parent)) if arrow.generalization => {
+            diagram.insert_use_case_generalization(child, parent).map_err(relationship_error)?;
+        },
+        (Element::UseCase(base), Element::UseCase(included)) if stereotype == "include" => {
+            diagram.insert_include(base, included).map_err(relationship_error)?;
+        },
+        (Element::UseCase(extension), Element::UseCase(base))
+            if stereotype == "extend" || stereotype == "extends" => {
+            diagram.insert_extend(Extend{extension, base, extension_point: None})
+                .map_err(relationship_error)?;
+        },
+        (Element::UseCase(dependent), Element::UseCase(dependency))
+            if stereotype == "depends on" => {
+            diagram.insert_dependency(dependent, dependency, rest.map(S::from))
+                .map_err(relationship_error)?;
+        },
+        _ => return Err(error(right_position, ParseErrorKind::UnsupportedRelationship)),
+    }
+    Ok(())
+}
+
+/// The lowercased stereotype a label starts with and the rest of the label,
+/// or the whole label if it has no delimited stereotype.
+fn split_stereotype(label: &str) -> (String, Option<&str>) {
+    let label = label.trim();
+    for &(open, close) in &[("<<", ">>"), ("«", "»")] {
+        if label.starts_with(open) {
+            if let Some(end) = label.find(close) {
+                let rest = label[end + close.len()..].trim();
+                return (label[open.len()..end].trim().to_lowercase(),
+                        if rest.is_empty() { None } else { Some(rest) });
+            }
+        }
+    }
+    (label.to_lowercase(), None)
+}
+
+/// A position in a line of text, for importers that scan lines themselves.
+pub(super) struct Cursor<'a> {
+    pub(super) line_number: usize,
+    pub(super) line: &'a str,
+    pub(super) offset: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub(super) fn new(line_number: usize, line: &'a str) -> Self {
+        Cursor{line_number, line, offset: 0}
+    }
+
+    pub(super) fn position(&self) -> Position {
+        Position{line: self.line_number, column: self.line[..self.offset].chars().count() + 1}
+    }
+
+    pub(super) fn rest(&self) -> &'a str {
+        &self.line[self.offset..]
+    }
+
+    pub(super) fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    pub(super) fn at_end(&self) -> bool {
+        self.rest().is_empty()
+    }
+
+    pub(super) fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        Some(c)
+    }
+
+    pub(super) fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(super) fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    pub(super) fn peek_word(&self) -> &'a str {
+        let rest = self.rest();
+        let end = rest.find(|c| !is_word_char(c)).unwrap_or(rest.len());
+        &rest[..end]
+    }
+
+    pub(super) fn word(&mut self) -> &'a str {
+        let word = self.peek_word();
+        self.offset += word.len();
+        word
+    }
+
+    pub(super) fn expect_end(&mut self) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        if self.at_end() {
+            Ok(())
+        } else {
+            Err(error(self.position(), ParseErrorKind::ExpectedEndOfLine))
+        }
+    }
+
+    /// The text up to the closing delimiter, which is consumed.
+    pub(super) fn delimited(&mut self, close: char, start: Position)
+                            -> Result<&'a str, ParseError> {
+        let rest = self.rest();
+        match rest.find(close) {
+            Some(end) => {
+                self.offset += end + close.len_utf8();
+                Ok(&rest[..end])
+            },
+            None => Err(error(start, ParseErrorKind::UnterminatedString)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    use use_case_diagram::Span;
+
+    fn parse_err(text: &str) -> (usize, usize, ParseErrorKind) {
+        let err = parse::<Rc<str>>(text).unwrap_err();
+        (err.position.line, err.position.column, err.kind)
+    }
+
+    #[test]
+    fn test_parse() {
+        let diagram = parse::<Rc<str>>(concat!(
+            "# Moderation\n",
+            "actor Administrator\n",
+            "Administrator -> \"Ban subscriber\"  # forward reference\n",
+            "\n",
+            "usecase \"Ban subscriber\"\n",
+            "usecase \"Say \\\"hi\\\"\"\n",
+            "actor \"actor\"\n",
+            "\"actor\"->\"Say \\\"hi\\\"\"\n",
+        )).unwrap();
+        assert!(diagram.has_unique_names());
+        let (a, _) = diagram.actor_by_name("Administrator").unwrap();
+        let (q, _) = diagram.actor_by_name("actor").unwrap();
+        let (bs, _) = diagram.use_case_by_title("Ban subscriber").unwrap();
+        let (sh, _) = diagram.use_case_by_title("Say \"hi\"").unwrap();
+        assert_eq!(diagram.actors().count(), 2);
+        assert_eq!(diagram.use_cases().count(), 2);
+        assert_eq!(diagram.associations().count(), 2);
+        assert!(diagram.association(a, bs).is_some());
+        assert!(diagram.association(q, sh).is_some());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        use self::ParseErrorKind::*;
+        assert_eq!(parse_err("actor A\nactor B!"), (2, 8, UnexpectedCharacter('!')));
+        assert_eq!(parse_err("usecase \"Log in"), (1, 9, UnterminatedString));
+        assert_eq!(parse_err("usecase \"Log\\tin\""), (1, 13, InvalidEscape('t')));
+        assert_eq!(parse_err("actor"), (1, 6, ExpectedName));
+        assert_eq!(parse_err("actor usecase"), (1, 7, ExpectedName));
+        assert_eq!(parse_err("A \"Log in\""), (1, 3, ExpectedArrow));
+        assert_eq!(parse_err("actor A B"), (1, 9, ExpectedEndOfLine));
+        assert_eq!(parse_err("usecase B\nA -> B"), (2, 1, UnknownActorName("A".to_string())));
+        assert_eq!(parse_err("actor A\nA -> B"), (2, 6, UnknownUseCaseTitle("B".to_string())));
+        match parse_err("actor A\n  actor A") {
+            (2, 9, Name(NameError::ActorNameTaken(_))) => (),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(parse_err("include \"actors.bfly\""), (1, 9, Include(
+            "actors.bfly".to_string(), "includes are only read in projects".to_string())));
+        assert_eq!(parse_err("include \"a\" b"), (1, 13, ExpectedEndOfLine));
+        assert_eq!(parse_err("include B"), (1, 9, ExpectedArrow));
+        let err = parse::<Rc<str>>("actor ä\nactor ä").unwrap_err();
+        assert!(err.to_string().starts_with("2:7: duplicate name"));
+    }
+
+    #[test]
+    fn test_parse_with_diagnostics() {
+        let (diagram, diagnostics) = parse_with_diagnostics::<Rc<str>>(concat!(
+            "actor Administrator\n",
+            "actor \"Subscriber\n",
+            "usecase Ban\n",
+            "usecase Ban\n",
+            "Administrator -> Ban\n",
+            "Subscriber -> Ban # undeclared\n",
+            "Administrator Ban\n",
+        ));
+        assert_eq!(diagram.actors().count(), 1);
+        assert_eq!(diagram.use_cases().count(), 1);
+        assert_eq!(diagram.associations().count(), 1);
+        let found = diagnostics.iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.start, diagnostic.end))
+            .collect::<Vec<_>>();
+        assert_eq!(found, vec![
+            (Severity::Error, Position{line: 2, column: 7}, Position{line: 2, column: 18}),
+            (Severity::Error, Position{line: 4, column: 9}, Position{line: 4, column: 12}),
+            (Severity::Error, Position{line: 7, column: 15}, Position{line: 7, column: 18}),
+            (Severity::Error, Position{line: 6, column: 1}, Position{line: 6, column: 31}),
+        ]);
+        assert_eq!(diagnostics[3].kind, ParseErrorKind::UnknownActorName("Subscriber".to_string()));
+        assert_eq!(diagnostics[0].to_string(), "2:7: error: unterminated string");
+        assert_eq!(parse::<Rc<str>>("actor A\nactor \"Subscriber\nactor A B").unwrap_err(),
+                   diagnostics[0].clone().into_error());
+    }
+
+    #[test]
+    fn test_parse_with_source_map() {
+        let (mut diagram, source_map) = parse_with_source_map::<Rc<str>>(concat!(
+            "Administrator -> Ban\n",
+            "  actor Administrator\n",
+            "usecase Ban # by moderators\n",
+        ), "moderation.bfly").unwrap();
+        let (administrator, _) = diagram.actor_by_name("Administrator").unwrap();
+        let (ban, _) = diagram.use_case_by_title("Ban").unwrap();
+        let span = |line, column| Span{file: Rc::from("moderation.bfly"), line, column};
+        assert_eq!(source_map.actors[&administrator], span(2, 3));
+        assert_eq!(source_map.use_cases[&ban], span(3, 1));
+        assert_eq!(source_map.associations[&(administrator, ban)], span(1, 1));
+
+        source_map.apply(&mut diagram);
+        assert_eq!(diagram.actor(administrator).unwrap().span, Some(span(2, 3)));
+        assert_eq!(diagram.association(administrator, ban).unwrap().span, Some(span(1, 1)));
+    }
+}
@@ -0,0 +1,804 @@
+//! PlantUML use case diagrams.
+//!
+//! Importing understands actors (`actor Guest`, `:Main Admin: as Admin`),
+//! use cases (`usecase "Eat food" as UC1`, `(Checkout)`), associations
+//! between actors and use cases in either direction, `<<include>>` and
+//! `<<extend>>` arrows between use cases and generalizations (`<|--`).
+//! Elements used in arrows without being declared are declared implicitly,
+//! as PlantUML does. Stereotypes on declarations are kept. Named rectangles
+//! are system boundaries of the use cases first mentioned inside them.
+//! Layout and styling (`left to right direction`, `skinparam`, `title`,
+//! colors) are skipped, and so are notes, which are lost, and other groups
+//! such as packages, which are dropped but whose elements are kept. Dotted
+//! arrows labelled `<<depends on>>` between use cases, as written by
+//! exporting, are dependencies.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io;
+
+use use_case_diagram::lint::Severity;
+use use_case_diagram::parser::{error, is_word_char, relate, Arrow, Cursor, Diagnostic, Element,
+                               ParseError, ParseErrorKind, Position};
+use use_case_diagram::source_map::span;
+use use_case_diagram::{NoteAnchor, SourceMap, SystemBoundary, SystemBoundaryId, UseCase,
+                       UseCaseDiagram, UseCaseId};
+
+/// Parse a PlantUML use case diagram into a diagram with unique names.
+pub fn from_plantuml<S>(text: &str) -> Result<UseCaseDiagram<S>, ParseError>
+    where S: AsRef<str> + Clone + for<'a> From<&'a str> {
+    from_plantuml_with_source_map(text, "").map(|(diagram, _)| diagram)
+}
+
+/// Parse a PlantUML use case diagram in the file like `from_plantuml`, and
+/// map every actor and use case to where it is declared or, if it is only
+/// used in arrows, first used, and every association to its arrow.
+pub fn from_plantuml_with_source_map<S>(text: &str, file: &str)
+                                        -> Result<(UseCaseDiagram<S>, SourceMap<S>), ParseError>
+    where S: AsRef<str> + Clone + for<'a> From<&'a str> {
+    let (importer, diagnostics) = import(text, file);
+    match diagnostics.into_iter().next() {
+        Some(diagnostic) => Err(diagnostic.into_error()),
+        None => Ok((importer.diagram, importer.source_map)),
+    }
+}
+
+/// Parse a PlantUML use case diagram like `from_plantuml`, but skip the
+/// lines with errors, for editors to show every problem at once. Statements
+/// that are not understood are warnings, as PlantUML has many that do not
+/// matter to use cases, and other problems are errors. The diagnostics are
+/// in order.
+pub fn from_plantuml_with_diagnostics<S>(text: &str) -> (UseCaseDiagram<S>, Vec<Diagnostic>)
+    where S: AsRef<str> + Clone + for<'a> From<&'a str> {
+    let (importer, diagnostics) = import(text, "");
+    (importer.diagram, diagnostics)
+}
+
+fn import<S>(text: &str, file: &str) -> (Importer<S>, Vec<Diagnostic>)
+    where S: AsRef<str> + Clone + for<'a> From<&'a str> {
+    let mut importer = Importer{
+        diagram: UseCaseDiagram::with_unique_names(),
+        aliases: HashMap::new(),
+        block: None,
+        groups: Vec::new(),
+        file: file.to_string(),
+        source_map: SourceMap::new(),
+    };
+    let mut diagnostics = Vec::new();
+    let mut last = Position{line: 1, column: 1};
+    for (index, line) in text.lines().enumerate() {
+        last = Position{line: index + 1, column: line.chars().count() + 1};
+        if let Err(err) = importer.read_line(Cursor::new(index + 1, line)) {
+            let severity = match err.kind {
+                ParseErrorKind::UnsupportedStatement => Severity::Warning,
+                _ => Severity::Error,
+            };
+            diagnostics.push(Diagnostic::new(severity, err, last));
+        }
+    }
+    if let Some((position, _)) = importer.block {
+        let err = error(position, ParseErrorKind::UnterminatedBlock);
+        diagnostics.push(Diagnostic::new(Severity::Error, err, last));
+    }
+    if !importer.groups.is_empty() {
+        let err = error(Position{line: last.line, column: 1}, ParseErrorKind::UnterminatedBlock);
+        diagnostics.push(Diagnostic::new(Severity::Error, err, last));
+    }
+    (importer, diagnostics)
+}
+
+/// Write the diagram as a PlantUML use case diagram that `from_plantuml`
+/// reads back. Actors and use cases are declared with their stereotypes under
+/// aliases derived from their identifiers, in the order of `actors_in_order`
+/// and `use_cases_in_order`, use cases inside the rectangle of the first
+/// system boundary that contains them. Associations, includes, extends,
+/// dependencies and generalizations follow, and notes are attached to their
+/// elements. PlantUML strings cannot contain double quotes, so they are
+/// replaced by single quotes.
+pub fn to_plantuml<W, S>(diagram: &UseCaseDiagram<S>, w: &mut W) -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    writeln!(w, "@startuml")?;
+    for (actor_id, actor) in diagram.actors_in_order() {
+        writeln!(w, "actor {} as actor_{}{}", quote(actor.name.as_ref()), actor_id,
+                 stereotypes(&actor.stereotypes))?;
+        generate_notes(w, diagram, NoteAnchor::Actor(actor_id), &format!("actor_{}", actor_id))?;
+    }
+    let mut system_boundaries = diagram.system_boundaries().collect::<Vec<_>>();
+    system_boundaries.sort_by_key(|&(system_boundary_id, _)| system_boundary_id);
+    let use_cases = diagram.use_cases_in_order();
+    let first_system_boundary = |use_case_id| {
+        system_boundaries.iter()
+            .find(|&&(_, system_boundary)| system_boundary.use_cases.contains(&use_case_id))
+            .map(|&(system_boundary_id, _)| system_boundary_id)
+    };
+    for &(system_boundary_id, system_boundary) in &system_boundaries {
+        writeln!(w, "rectangle {} {{", quote(system_boundary.name.as_ref()))?;
+        for &(use_case_id, use_case) in &use_cases {
+            if first_system_boundary(use_case_id) == Some(system_boundary_id) {
+                generate_use_case(w, diagram, use_case_id, use_case, "  ")?;
+            }
+        }
+        writeln!(w, "}}")?;
+    }
+    for &(use_case_id, use_case) in &use_cases {
+        if first_system_boundary(use_case_id).is_none() {
+            generate_use_case(w, diagram, use_case_id, use_case, "")?;
+        }
+    }
+    let mut associations = diagram.associations().collect::<Vec<_>>();
+    associations.sort();
+    for (actor_id, use_case_id) in associations {
+        match diagram.association(actor_id, use_case_id).unwrap().label {
+            Some(ref label) =>
+                writeln!(w, "actor_{} -- use_case_{} : {}", actor_id, use_case_id,
+                         single_line(label.as_ref()))?,
+            None => writeln!(w, "actor_{} -- use_case_{}", actor_id, use_case_id)?,
+        }
+        let notes = diagram.notes_of(NoteAnchor::Association(actor_id, use_case_id))
+            .collect::<BTreeMap<_, _>>();
+        for (_, note) in notes {
+            generate_note(w, note.text.as_ref(), "note on link")?;
+        }
+    }
+    let mut includes = diagram.includes().collect::<Vec<_>>();
+    includes.sort();
+    for (base, included) in includes {
+        writeln!(w, "use_case_{} ..> use_case_{} : <<include>>", base, included)?;
+    }
+    let mut extends = diagram.extends().collect::<Vec<_>>();
+    extends.sort();
+    for extend in extends {
+        writeln!(w, "use_case_{} ..> use_case_{} : <<extend>>", extend.extension, extend.base)?;
+    }
+    let mut dependencies = diagram.dependencies().collect::<Vec<_>>();
+    dependencies.sort();
+    for (dependent, dependency) in dependencies {
+        match diagram.dependency_label(dependent, dependency) {
+            Some(label) =>
+                writeln!(w, "use_case_{} ..> use_case_{} : <<depends on>> {}",
+                         dependent, dependency, single_line(label.as_ref()))?,
+            None =>
+                writeln!(w, "use_case_{} ..> use_case_{} : <<depends on>>",
+                         dependent, dependency)?,
+        }
+    }
+    let mut generalizations = diagram.actor_generalizations().collect::<Vec<_>>();
+    generalizations.sort();
+    for (child, parent) in generalizations {
+        writeln!(w, "actor_{} --|> actor_{}", child, parent)?;
+    }
+    let mut generalizations = diagram.use_case_generalizations().collect::<Vec<_>>();
+    generalizations.sort();
+    for (child, parent) in generalizations {
+        writeln!(w, "use_case_{} --|> use_case_{}", child, parent)?;
+    }
+    writeln!(w, "@enduml")?;
+    Ok(())
+}
+
+/// Write the given layer of the diagram as a PlantUML use case diagram, like
+/// `to_plantuml`, with the layers in the given order; see
+/// `UseCaseDiagram::layer_subdiagram`.
+pub fn to_plantuml_in_layer<W, S, L>(diagram: &UseCaseDiagram<S>, order: &[L], layer: &str,
+                                     w: &mut W) -> io::Result<()>
+    where W: io::Write, S: AsRef<str> + Clone, L: AsRef<str> {
+    to_plantuml(&diagram.layer_subdiagram(order, layer), w)
+}
+
+fn generate_use_case<W, S>(w: &mut W, diagram: &UseCaseDiagram<S>, use_case_id: UseCaseId,
+                           use_case: &UseCase<S>, indent: &str) -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    writeln!(w, "{}usecase {} as use_case_{}{}", indent, quote(use_case.title.as_ref()),
+             use_case_id, stereotypes(&use_case.stereotypes))?;
+    generate_notes(w, diagram, NoteAnchor::UseCase(use_case_id),
+                   &format!("use_case_{}", use_case_id))
+}
+
+fn generate_notes<W, S>(w: &mut W, diagram: &UseCaseDiagram<S>, anchor: NoteAnchor,
+                        alias: &str) -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    let notes = diagram.notes_of(anchor).collect::<BTreeMap<_, _>>();
+    for (_, note) in notes {
+        generate_note(w, note.text.as_ref(), &format!("note right of {}", alias))?;
+    }
+    Ok(())
+}
+
+fn generate_note<W>(w: &mut W, text: &str, opening: &str) -> io::Result<()>
+    where W: io::Write {
+    writeln!(w, "{}", opening)?;
+    for line in text.lines() {
+        writeln!(w, "  {}", line)?;
+    }
+    writeln!(w, "end note")
+}
+
+fn stereotypes<S>(stereotypes: &[S]) -> String where S: AsRef<str> {
+    stereotypes.iter()
+        .map(|stereotype| format!(" <<{}>>", single_line(stereotype.as_ref())))
+        .collect()
+}
+
+/// Quote a string as a PlantUML name.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", single_line(s).replace('"', "'"))
+}
+
+fn single_line(s: &str) -> String {
+    s.replace('\n', "\\n")
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Block {
+    Comment,
+    Braces,
+    Note,
+    Legend,
+}
+
+impl Block {
+    fn ends_at(self, line: &str) -> bool {
+        let line = line.trim_end();
+        match self {
+            Block::Comment => line.ends_with("'/"),
+            Block::Braces => line == "}",
+            Block::Note => line.eq_ignore_ascii_case("end note") ||
+                           line.eq_ignore_ascii_case("endnote"),
+            Block::Legend => line.eq_ignore_ascii_case("end legend") ||
+                             line.eq_ignore_ascii_case("endlegend"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Reference {
+    /// A bare word or a quoted string, which may be an alias.
+    Name(String),
+    /// `:name:`.
+    Actor(String),
+    /// `(title)`, which may be an alias.
+    UseCase(String),
+}
+
+struct Importer<S> {
+    diagram: UseCaseDiagram<S>,
+    aliases: HashMap<String, Element>,
+
+    /// The block being skipped and where it starts, if any.
+    block: Option<(Position, Block)>,
+
+    /// The groups such as rectangles that are open, with the system
+    /// boundaries of those that are named rectangles.
+    groups: Vec<Option<SystemBoundaryId>>,
+
+    file: String,
+    source_map: SourceMap<S>,
+}
+
+impl<S> Importer<S> where S: AsRef<str> + Clone + for<'a> From<&'a str> {
+    fn read_line(&mut self, mut cursor: Cursor) -> Result<(), ParseError> {
+        cursor.skip_whitespace();
+        if let Some((_, kind)) = self.block {
+            if kind.ends_at(cursor.rest()) {
+                self.block = None;
+            }
+            return Ok(());
+        }
+        if cursor.at_end() || cursor.rest().starts_with('\'') ||
+           cursor.rest().starts_with('@') {
+            return Ok(());
+        }
+        if cursor.rest().starts_with("/'") {
+            if !cursor.rest()[2..].contains("'/") {
+                self.block = Some((cursor.position(), Block::Comment));
+            }
+            return Ok(());
+        }
+        if cursor.rest().trim_end() == "}" {
+            if self.groups.pop().is_none() {
+                return Err(error(cursor.position(), ParseErrorKind::UnbalancedBrace));
+            }
+            return Ok(());
+        }
+        let start = cursor.position();
+        match cursor.peek_word().to_lowercase().as_str() {
+            "left" | "top" | "title" | "hide" | "show" | "scale" | "caption" | "header" |
+            "footer" | "skin" => (),
+            "skinparam" => {
+                if cursor.rest().trim_end().ends_with('{') {
+                    self.block = Some((start, Block::Braces));
+                }
+            },
+            "note" => {
+                // `note right of A : Text` and `note "Text" as N` are single
+                // lines, but `note as N` starts a block like `note right of A`.
+                if !cursor.rest().contains(':') && !cursor.rest().contains('"') {
+                    self.block = Some((start, Block::Note));
+                }
+            },
+            "legend" => {
+                self.block = Some((start, Block::Legend));
+            },
+            "rectangle" | "package" | "frame" | "cloud" | "node" | "folder" | "database" |
+            "together" => {
+                if cursor.rest().trim_end().ends_with('{') {
+                    let group = cursor.word().to_lowercase();
+                    cursor.skip_whitespace();
+                    let system_boundary_id = match cursor.reference() {
+                        Ok((_, Reference::Name(name))) if group == "rectangle" => {
+                            let system_boundary = SystemBoundary{
+                                name: S::from(&name),
+                                use_cases: BTreeSet::new(),
+                            };
+                            let system_boundary_id =
+                                self.diagram.insert_system_boundary(system_boundary)
+                                .expect("no use cases to be missing");
+                            Some(system_boundary_id)
+                        },
+                        _ => None,
+                    };
+                    self.groups.push(system_boundary_id);
+                }
+            },
+            "actor" => {
+                cursor.word();
+                cursor.skip_whitespace();
+                let (position, reference) = cursor.reference()?;
+                let name = match reference {
+                    Reference::Name(name) | Reference::Actor(name) => name,
+                    Reference::UseCase(_) =>
+                        return Err(error(position, ParseErrorKind::ExpectedName)),
+                };
+                self.declare_actor(&name, start, &mut cursor)?;
+            },
+            "usecase" => {
+                cursor.word();
+                cursor.skip_whitespace();
+                let (position, reference) = cursor.reference()?;
+                let title = match reference {
+                    Reference::Name(title) | Reference::UseCase(title) => title,
+                    Reference::Actor(_) =>
+                        return Err(error(position, ParseErrorKind::ExpectedName)),
+                };
+                self.declare_use_case(&title, start, &mut cursor)?;
+            },
+            _ => {
+                let (left_position, left) = cursor.reference()?;
+                cursor.skip_whitespace();
+                if cursor.at_arrow() {
+                    let arrow = cursor.arrow();
+                    cursor.skip_whitespace();
+                    let (right_position, right) = cursor.reference()?;
+                    cursor.skip_whitespace();
+                    let label = if cursor.eat(':') {
+                        Some(cursor.rest().trim().to_string())
+                    } else {
+                        cursor.expect_end()?;
+                        None
+                    };
+                    let left = self.resolve(left);
+                    let right = self.resolve(right);
+                    self.locate(left, left_position);
+                    self.locate(right, right_position);
+                    relate(&mut self.diagram, left, arrow, right, label.as_deref(), start,
+                           right_position)?;
+                    if let (Element::Actor(actor_id), Element::UseCase(use_case_id)) |
+                           (Element::UseCase(use_case_id), Element::Actor(actor_id)) =
+                           (left, right) {
+                        let file = &self.file;
+                        self.source_map.associations.entry((actor_id, use_case_id))
+                            .or_insert_with(|| span(file, start));
+                    }
+                } else {
+                    match left {
+                        Reference::Actor(name) =>
+                            self.declare_actor(&name, start, &mut cursor)?,
+                        Reference::UseCase(title) =>
+                            self.declare_use_case(&title, start, &mut cursor)?,
+                        Reference::Name(_) =>
+                            return Err(error(start, ParseErrorKind::UnsupportedStatement)),
+                    }
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn declare_actor(&mut self, name: &str, position: Position, cursor: &mut Cursor)
+                     -> Result<(), ParseError> {
+        let actor_id = self.diagram.actor_entry(name);
+        self.locate(Element::Actor(actor_id), position);
+        let (alias, stereotypes) = cursor.declaration_tail()?;
+        if let Some(alias) = alias {
+            self.aliases.insert(alias, Element::Actor(actor_id));
+        }
+        let mut actor = self.diagram.actor_mut(actor_id).unwrap();
+        for stereotype in stereotypes {
+            if !actor.has_stereotype(&stereotype) {
+                actor.stereotypes.push(S::from(&stereotype));
+            }
+        }
+        Ok(())
+    }
+
+    fn declare_use_case(&mut self, title: &str, position: Position, cursor: &mut Cursor)
+                        -> Result<(), ParseError> {
+        let use_case_id = self.diagram.use_case_entry(title);
+        self.locate(Element::UseCase(use_case_id), position);
+        let (alias, stereotypes) = cursor.declaration_tail()?;
+        if let Some(alias) = alias {
+            self.aliases.insert(alias, Element::UseCase(use_case_id));
+        }
+        let mut use_case = self.diagram.use_case_mut(use_case_id).unwrap();
+        for stereotype in stereotypes {
+            if !use_case.has_stereotype(&stereotype) {
+                use_case.stereotypes.push(S::from(&stereotype));
+            }
+        }
+        Ok(())
+    }
+
+    /// Map the element to the position, unless it was mentioned before, and
+    /// place use cases mentioned for the first time in the open rectangles.
+    fn locate(&mut self, element: Element, position: Position) {
+        let file = &self.file;
+        match element {
+            Element::Actor(actor_id) => {
+                self.source_map.actors.entry(actor_id).or_insert_with(|| span(file, position));
+            },
+            Element::UseCase(use_case_id) => {
+                if self.source_map.use_cases.contains_key(&use_case_id) {
+                    return;
+                }
+                self.source_map.use_cases.insert(use_case_id, span(file, position));
+                for &system_boundary_id in self.groups.iter().flatten() {
+                    self.diagram.insert_system_boundary_use_case(system_boundary_id, use_case_id)
+                        .expect("both exist");
+                }
+            },
+        }
+    }
+
+    fn resolve(&mut self, reference: Reference) -> Element {
+        match reference {
+            Reference::Actor(name) => Element::Actor(self.diagram.actor_entry(&name)),
+            Reference::UseCase(title) => match self.aliases.get(&title) {
+                Some(&Element::UseCase(use_case_id)) => Element::UseCase(use_case_id),
+                _ => Element::UseCase(self.diagram.use_case_entry(&title)),
+            },
+            Reference::Name(name) => {
+                if let Some(&element) = self.aliases.get(&name) {
+                    element
+                } else if let Some((actor_id, _)) = self.diagram.actor_by_name(&name) {
+                    Element::Actor(actor_id)
+                } else if let Some((use_case_id, _)) = self.diagram.use_case_by_title(&name) {
+                    Element::UseCase(use_case_id)
+                } else {
+                    Element::Actor(self.diagram.actor_entry(&name))
+                }
+            },
+        }
+    }
+}
+
+impl<'a> Cursor<'a> {
+    fn reference(&mut self) -> Result<(Position, Reference), ParseError> {
+        let position = self.position();
+        let reference = match self.peek() {
+            Some('"') => {
+                self.bump();
+                Reference::Name(self.delimited('"', position)?.to_string())
+            },
+            Some(':') => {
+                self.bump();
+                Reference::Actor(self.delimited(':', position)?.trim().to_string())
+            },
+            Some('(') => {
+                self.bump();
+                Reference::UseCase(self.delimited(')', position)?.trim().to_string())
+            },
+            Some(c) if is_word_char(c) => Reference::Name(self.word().to_string()),
+            _ => return Err(error(position, ParseErrorKind::ExpectedName)),
+        };
+        Ok((position, reference))
+    }
+
+    /// An optional alias, `as NAME` or `as (NAME)`, followed by optional
+    /// stereotypes and an optional color, and the end of the line.
+    fn declaration_tail(&mut self) -> Result<(Option<String>, Vec<String>), ParseError> {
+        self.skip_whitespace();
+        let mut alias = None;
+        if self.peek_word().eq_ignore_ascii_case("as") {
+            self.word();
+            self.skip_whitespace();
+            alias = match self.reference()? {
+                (_, Reference::Name(alias)) | (_, Reference::UseCase(alias)) => Some(alias),
+                (position, Reference::Actor(_)) =>
+                    return Err(error(position, ParseErrorKind::ExpectedName)),
+            };
+            self.skip_whitespace();
+        }
+        let mut stereotypes = Vec::new();
+        while self.rest().starts_with("<<") {
+            let start = self.position();
+            self.offset += 2;
+            let rest = self.rest();
+            match rest.find(">>") {
+                Some(end) => {
+                    stereotypes.push(rest[..end].trim().to_string());
+                    self.offset += end + 2;
+                },
+                None => return Err(error(start, ParseErrorKind::UnterminatedString)),
+            }
+            self.skip_whitespace();
+        }
+        if self.eat('#') {
+            self.word();
+        }
+        self.expect_end()?;
+        Ok((alias, stereotypes))
+    }
+
+    fn at_arrow(&self) -> bool {
+        matches!(self.peek(), Some('-') | Some('.') | Some('<'))
+    }
+
+    /// An arrow such as `-->`, `<..`, `-up->` or `<|--`.
+    fn arrow(&mut self) -> Arrow {
+        let start = self.offset;
+        while let Some(c) = self.peek() {
+            if is_arrow_char(c) {
+                self.bump();
+            } else if c.is_alphabetic() &&
+                      self.rest().trim_start_matches(char::is_alphabetic)
+                          .starts_with(['-', '.']) {
+                self.word();
+            } else {
+                break;
+            }
+        }
+        let arrow = &self.line[start..self.offset];
+        Arrow{
+            left_head: arrow.starts_with('<'),
+            right_head: arrow.ends_with('>'),
+            generalization: arrow.contains('|'),
+        }
+    }
+}
+
+fn is_arrow_char(c: char) -> bool {
+    c == '-' || c == '.' || c == '<' || c == '>' || c == '|'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    use use_case_diagram::{Actor, Association, Extend, Note, RelationshipError, Span,
+                           SystemBoundary};
+
+    #[test]
+    fn test_from_plantuml() {
+        let diagram = from_plantuml::<Rc<str>>(concat!(
+            "@startuml\n",
+            "' Restaurant\n",
+            "left to right direction\n",
+            "skinparam actor {\n",
+            "  BackgroundColor Gold\n",
+            "}\n",
+            "actor Guest as g\n",
+            "actor \"Food Critic\" as fc <<Human>>\n",
+            ":Main Chef: as Chef\n",
+            "/' A block\n",
+            "   comment '/\n",
+            "rectangle Restaurant {\n",
+            "  usecase \"Eat Food\" as UC1\n",
+            "  (Pay for Food) as (Pay)\n",
+            "  usecase (Drink)\n",
+            "}\n",
+            "note right of g : Hungry\n",
+            "note left of fc\n",
+            "  Picky\n",
+            "end note\n",
+            "g --> UC1\n",
+            "(Pay) <-- fc : pays\n",
+            "fc -up-> (Drink)\n",
+            "(Review) <-- fc\n",
+            "(Pay) .> (Tip) : <<include>>\n",
+            "(Complain) .> (Pay) : extends\n",
+            "Chef <|-- :Sous Chef:\n",
+            "@enduml\n",
+        )).unwrap();
+        let (g, _) = diagram.actor_by_name("Guest").unwrap();
+        let (fc, critic) = diagram.actor_by_name("Food Critic").unwrap();
+        let (c, _) = diagram.actor_by_name("Main Chef").unwrap();
+        let (sc, _) = diagram.actor_by_name("Sous Chef").unwrap();
+        let (ef, _) = diagram.use_case_by_title("Eat Food").unwrap();
+        let (p, _) = diagram.use_case_by_title("Pay for Food").unwrap();
+        let (d, _) = diagram.use_case_by_title("Drink").unwrap();
+        let (r, _) = diagram.use_case_by_title("Review").unwrap();
+        let (t, _) = diagram.use_case_by_title("Tip").unwrap();
+        let (cp, _) = diagram.use_case_by_title("Complain").unwrap();
+        assert_eq!(diagram.actors().count(), 4);
+        assert_eq!(diagram.use_cases().count(), 6);
+        assert!(critic.has_stereotype("Human"));
+        assert!(diagram.association(g, ef).is_some());
+        assert_eq!(diagram.association(fc, p).unwrap().label.as_ref().map(|l| l.as_ref()),
+                   Some("pays"));
+        assert!(diagram.association(fc, d).is_some());
+        assert!(diagram.association(fc, r).is_some());
+        assert_eq!(diagram.associations().count(), 4);
+        assert_eq!(diagram.includes().collect::<Vec<_>>(), vec![(p, t)]);
+        assert_eq!(diagram.extends().collect::<Vec<_>>(),
+                   vec![Extend{extension: cp, base: p, extension_point: None}]);
+        assert_eq!(diagram.actor_generalizations().collect::<Vec<_>>(), vec![(sc, c)]);
+        let system_boundaries = diagram.system_boundaries()
+            .map(|(_, system_boundary)| system_boundary).collect::<Vec<_>>();
+        assert_eq!(system_boundaries.len(), 1);
+        assert_eq!(&*system_boundaries[0].name, "Restaurant");
+        assert_eq!(system_boundaries[0].use_cases, vec![ef, p, d].into_iter().collect());
+    }
+
+    #[test]
+    fn test_from_plantuml_floating_notes() {
+        let text = concat!(
+            "@startuml\n",
+            "actor Guest\n",
+            "note \"Floating\" as N1\n",
+            "note as N2\n",
+            "  Floating over\n",
+            "  two lines\n",
+            "end note\n",
+            "(Eat) <-- Guest\n",
+            "@enduml\n",
+        );
+        let (diagram, diagnostics) = from_plantuml_with_diagnostics::<Rc<str>>(text);
+        assert_eq!(diagnostics, vec![]);
+        assert_eq!(diagram.use_cases().count(), 1);
+        assert_eq!(diagram.associations().count(), 1);
+    }
+
+    #[test]
+    fn test_from_plantuml_errors() {
+        fn err(text: &str) -> (usize, usize, ParseErrorKind) {
+            let err = from_plantuml::<Rc<str>>(text).unwrap_err();
+            (err.position.line, err.position.column, err.kind)
+        }
+        use self::ParseErrorKind::*;
+        assert_eq!(err("actor \"Guest"), (1, 7, UnterminatedString));
+        assert_eq!(err("usecase :Guest:"), (1, 9, ExpectedName));
+        assert_eq!(err("actor A B"), (1, 9, ExpectedEndOfLine));
+        assert_eq!(err("Guest"), (1, 1, UnsupportedStatement));
+        assert_eq!(err("(A) --> (B)"), (1, 9, UnsupportedRelationship));
+        assert_eq!(err("A --> B"), (1, 7, UnsupportedRelationship));
+        assert_eq!(err("}"), (1, 1, UnbalancedBrace));
+        assert_eq!(err("note left of A\nPicky"), (1, 1, UnterminatedBlock));
+        assert_eq!(err("package P {\n(A)"), (2, 1, UnterminatedBlock));
+        match err("(A) ..> (B) : include\n(B) ..> (A) : include") {
+            (2, 1, Relationship(RelationshipError::UseCaseCycle(_, _))) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_to_plantuml() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut administrator = Actor::new(Rc::from("Administrator"));
+        administrator.stereotypes.push(Rc::from("human"));
+        let a = diagram.insert_actor(administrator).unwrap();
+        let m = diagram.insert_actor(Actor::new(Rc::from("Moderator \"mod\""))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        let n = diagram.insert_use_case(UseCase::new(Rc::from("Notify"))).unwrap();
+        diagram.insert_association_with(a, bs, Association{
+            label: Some(Rc::from("bans")),
+            ..Association::default()
+        }).unwrap();
+        diagram.insert_association(m, bs).unwrap();
+        diagram.insert_include(bs, li).unwrap();
+        diagram.insert_extend(Extend{extension: n, base: bs, extension_point: None}).unwrap();
+        diagram.insert_dependency(n, li, Some(Rc::from("session"))).unwrap();
+        diagram.insert_actor_generalization(m, a).unwrap();
+        diagram.insert_system_boundary(SystemBoundary{
+            name: Rc::from("Forum"),
+            use_cases: vec![bs, n].into_iter().collect(),
+        }).unwrap();
+        diagram.insert_note(Note{anchor: NoteAnchor::Association(a, bs),
+                                 text: Rc::from("Permanently")}).unwrap();
+        diagram.insert_note(Note{anchor: NoteAnchor::Actor(a), text: Rc::from("Root\nOnly")})
+            .unwrap();
+
+        let mut output = Vec::new();
+        to_plantuml(&diagram, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, concat!(
+            "@startuml\n",
+            "actor \"Administrator\" as actor_0 <<human>>\n",
+            "note right of actor_0\n",
+            "  Root\n",
+            "  Only\n",
+            "end note\n",
+            "actor \"Moderator 'mod'\" as actor_1\n",
+            "rectangle \"Forum\" {\n",
+            "  usecase \"Ban subscriber\" as use_case_0\n",
+            "  usecase \"Notify\" as use_case_2\n",
+            "}\n",
+            "usecase \"Log in\" as use_case_1\n",
+            "actor_0 -- use_case_0 : bans\n",
+            "note on link\n",
+            "  Permanently\n",
+            "end note\n",
+            "actor_1 -- use_case_0\n",
+            "use_case_0 ..> use_case_1 : <<include>>\n",
+            "use_case_2 ..> use_case_0 : <<extend>>\n",
+            "use_case_2 ..> use_case_1 : <<depends on>> session\n",
+            "actor_1 --|> actor_0\n",
+            "@enduml\n",
+        ));
+
+        let imported = from_plantuml::<Rc<str>>(&output).unwrap();
+        assert_eq!(imported.actors().count(), 2);
+        assert_eq!(imported.use_cases().count(), 3);
+        assert_eq!(imported.associations().count(), 2);
+        assert_eq!(imported.includes().count(), 1);
+        assert_eq!(imported.extends().count(), 1);
+        assert_eq!(imported.actor_generalizations().count(), 1);
+        let (n, _) = imported.use_case_by_title("Notify").unwrap();
+        let (li, _) = imported.use_case_by_title("Log in").unwrap();
+        assert_eq!(imported.dependency_label(n, li).map(|l| l.as_ref()), Some("session"));
+        assert!(imported.actor_by_name("Administrator").unwrap().1.has_stereotype("human"));
+        let (bs, _) = imported.use_case_by_title("Ban subscriber").unwrap();
+        let system_boundaries = imported.system_boundaries()
+            .map(|(_, system_boundary)| system_boundary).collect::<Vec<_>>();
+        assert_eq!(system_boundaries.len(), 1);
+        assert_eq!(&*system_boundaries[0].name, "Forum");
+        assert_eq!(system_boundaries[0].use_cases, vec![bs, n].into_iter().collect());
+    }
+
+    #[test]
+    fn test_from_plantuml_with_diagnostics() {
+        let (diagram, diagnostics) = from_plantuml_with_diagnostics::<Rc<str>>(concat!(
+            "@startuml\n",
+            "Waiter\n",
+            "actor \"Guest\n",
+            "Guest --> (Eat)\n",
+            "}\n",
+            "package P {\n",
+            "@enduml\n",
+        ));
+        assert_eq!(diagram.actors().count(), 1);
+        assert_eq!(diagram.associations().count(), 1);
+        let found = diagnostics.iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.start.line, diagnostic.kind.clone()))
+            .collect::<Vec<_>>();
+        use self::ParseErrorKind::*;
+        assert_eq!(found, vec![(Severity::Warning, 2, UnsupportedStatement),
+                               (Severity::Error, 3, UnterminatedString),
+                               (Severity::Error, 5, UnbalancedBrace),
+                               (Severity::Error, 7, UnterminatedBlock)]);
+    }
+
+    #[test]
+    fn test_from_plantuml_with_source_map() {
+        let (diagram, source_map) = from_plantuml_with_source_map::<Rc<str>>(concat!(
+            "@startuml\n",
+            "Guest --> (Eat)\n",
+            "actor Guest\n",
+            "  usecase Pay as P\n",
+            "(Eat) <-- :Chef:\n",
+            "@enduml\n",
+        ), "restaurant.puml").unwrap();
+        let (guest, _) = diagram.actor_by_name("Guest").unwrap();
+        let (chef, _) = diagram.actor_by_name("Chef").unwrap();
+        let (eat, _) = diagram.use_case_by_title("Eat").unwrap();
+        let (pay, _) = diagram.use_case_by_title("Pay").unwrap();
+        let span = |line, column| Span{file: Rc::from("restaurant.puml"), line, column};
+        assert_eq!(source_map.actors[&guest], span(2, 1));
+        assert_eq!(source_map.actors[&chef], span(5, 11));
+        assert_eq!(source_map.use_cases[&eat], span(2, 11));
+        assert_eq!(source_map.use_cases[&pay], span(4, 3));
+        assert_eq!(source_map.associations[&(guest, eat)], span(2, 1));
+        assert_eq!(source_map.associations[&(chef, eat)], span(5, 1));
+    }
+}
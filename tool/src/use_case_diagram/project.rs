@@ -0,0 +1,190 @@
+//! Diagrams split across files in the language of the `parser` module, which
+//! include each other with `include "FILE"` statements, so that large models
+//! can be kept in several files. A file that only includes others serves as
+//! the manifest of a project.
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use use_case_diagram::parser::{error, ParseError, ParseErrorKind, Position, Reader};
+use use_case_diagram::{SourceMap, UseCaseDiagram};
+
+/// An error in a file of a project.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProjectError {
+    pub file: String,
+    pub error: ParseError,
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.error)
+    }
+}
+
+impl Error for ProjectError {
+    fn description(&self) -> &str {
+        "invalid project"
+    }
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Parse the file and the files it includes into one diagram with unique
+/// names, and map every element to the file and statement that defines it.
+/// `load` gives the text of a file by its path, e.g. with
+/// `fs::read_to_string`. Paths in includes are relative to the directory of
+/// the including file. Each file is read once, where it is first included,
+/// so files may include each other. Names and titles must be unique across
+/// the files, and associations may refer to elements of any of them. Paths,
+/// including that of the root, are passed to `load` and reported without
+/// the `.` and `..` components that can be removed.
+pub fn parse_project<S, F>(root: &str, mut load: F)
+                           -> Result<(UseCaseDiagram<S>, SourceMap<S>), ProjectError>
+    where S: AsRef<str> + for<'a> From<&'a str>, F: FnMut(&str) -> io::Result<String> {
+    let root = &normalize(Path::new(root));
+    let text = load(root).map_err(|err| {
+        let kind = ParseErrorKind::Include(root.to_string(), err.to_string());
+        ProjectError{file: root.to_string(), error: error(Position{line: 1, column: 1}, kind)}
+    })?;
+    let mut reader = Reader::new(true);
+    let mut files = BTreeSet::new();
+    files.insert(root.to_string());
+    read_file(&mut reader, &mut files, &mut load, root, &text)?;
+    match reader.associate().into_iter().next() {
+        Some((file, diagnostic)) => Err(ProjectError{file, error: diagnostic.into_error()}),
+        None => Ok((reader.diagram, reader.source_map)),
+    }
+}
+
+fn read_file<S, F>(reader: &mut Reader<S>, files: &mut BTreeSet<String>, load: &mut F,
+                   file: &str, text: &str) -> Result<(), ProjectError>
+    where S: AsRef<str> + for<'a> From<&'a str>, F: FnMut(&str) -> io::Result<String> {
+    let project_error = |error| ProjectError{file: file.to_string(), error};
+    for (index, line) in text.lines().enumerate() {
+        let (position, path) = match reader.read_line(file, index + 1, line) {
+            Ok(Some(include)) => include,
+            Ok(None) => continue,
+            Err(err) => return Err(project_error(err)),
+        };
+        let included = resolve(file, &path);
+        if !files.insert(included.clone()) {
+            continue;
+        }
+        let text = load(&included).map_err(|err| {
+            project_error(error(position, ParseErrorKind::Include(path, err.to_string())))
+        })?;
+        read_file(reader, files, load, &included, &text)?;
+    }
+    Ok(())
+}
+
+/// The path of a file included by another, relative to the directory of the
+/// other, normalized as by `normalize`.
+fn resolve(file: &str, path: &str) -> String {
+    normalize(&Path::new(file).parent().unwrap_or_else(|| Path::new("")).join(path))
+}
+
+/// The given path without the `.` and `..` components that can be removed.
+fn normalize(path: &Path) -> String {
+    let mut components = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => match components.last() {
+                Some(&Component::Normal(_)) => {
+                    components.pop();
+                },
+                _ => components.push(component),
+            },
+            component => components.push(component),
+        }
+    }
+    components.iter().collect::<PathBuf>().to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    use use_case_diagram::Span;
+
+    fn load<'a>(files: &'a HashMap<&str, &str>) -> impl FnMut(&str) -> io::Result<String> + 'a {
+        move |path| files.get(path).map(|text| text.to_string())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not found"))
+    }
+
+    #[test]
+    fn test_parse_project() {
+        let mut files = HashMap::new();
+        files.insert("model/main.bfly", concat!(
+            "include \"actors.bfly\"\n",
+            "include \"moderation/bans.bfly\"\n",
+            "Administrator -> \"Ban subscriber\"\n",
+        ));
+        files.insert("model/actors.bfly", "actor Administrator\n");
+        files.insert("model/moderation/bans.bfly", concat!(
+            "include \"../actors.bfly\"\n",
+            "usecase \"Ban subscriber\"\n",
+            "Administrator -> \"Unban subscriber\"\n",
+            "usecase \"Unban subscriber\"\n",
+        ));
+        let (diagram, source_map) = parse_project::<Rc<str>, _>("model/main.bfly", load(&files))
+            .unwrap();
+        let (administrator, _) = diagram.actor_by_name("Administrator").unwrap();
+        let (ban, _) = diagram.use_case_by_title("Ban subscriber").unwrap();
+        let (unban, _) = diagram.use_case_by_title("Unban subscriber").unwrap();
+        assert_eq!(diagram.associations().count(), 2);
+        let span = |file: &str, line, column| Span{file: Rc::from(file), line, column};
+        assert_eq!(source_map.actors[&administrator], span("model/actors.bfly", 1, 1));
+        assert_eq!(source_map.use_cases[&ban], span("model/moderation/bans.bfly", 2, 1));
+        assert_eq!(source_map.associations[&(administrator, ban)],
+                   span("model/main.bfly", 3, 1));
+        assert_eq!(source_map.associations[&(administrator, unban)],
+                   span("model/moderation/bans.bfly", 3, 1));
+
+        let mut files = HashMap::new();
+        files.insert("main.bfly", "include \"a.bfly\"\nactor Administrator\n");
+        files.insert("a.bfly", "include \"main.bfly\"\nusecase Ban\n");
+        let (diagram, source_map) = parse_project::<Rc<str>, _>("./main.bfly", load(&files))
+            .unwrap();
+        let (administrator, _) = diagram.actor_by_name("Administrator").unwrap();
+        assert_eq!(source_map.actors[&administrator], span("main.bfly", 2, 1));
+        assert_eq!(diagram.use_cases().count(), 1);
+    }
+
+    #[test]
+    fn test_parse_project_errors() {
+        let mut files = HashMap::new();
+        files.insert("main.bfly", "include \"a.bfly\"\ninclude \"b.bfly\"\n");
+        files.insert("a.bfly", "actor Administrator\n");
+        files.insert("b.bfly", "usecase Ban\nactor  Administrator\n");
+        let err = parse_project::<Rc<str>, _>("main.bfly", load(&files)).unwrap_err();
+        assert_eq!(err.file, "b.bfly");
+        assert_eq!(err.error.position, Position{line: 2, column: 8});
+        assert_eq!(err.error.kind, ParseErrorKind::Redefinition(
+            "Administrator".to_string(), "a.bfly".to_string(), Position{line: 1, column: 1}));
+        assert_eq!(err.to_string(),
+                   "b.bfly:2:8: \"Administrator\" is already defined at a.bfly:1:1");
+
+        files.insert("b.bfly", "include \"c.bfly\"\n");
+        let err = parse_project::<Rc<str>, _>("main.bfly", load(&files)).unwrap_err();
+        assert_eq!(err.file, "b.bfly");
+        assert_eq!(err.error.position, Position{line: 1, column: 9});
+        assert_eq!(err.error.kind,
+                   ParseErrorKind::Include("c.bfly".to_string(), "not found".to_string()));
+
+        files.insert("b.bfly", "Administrator -> Ban\n");
+        let err = parse_project::<Rc<str>, _>("main.bfly", load(&files)).unwrap_err();
+        assert_eq!(err.file, "b.bfly");
+        assert_eq!(err.error.kind, ParseErrorKind::UnknownUseCaseTitle("Ban".to_string()));
+    }
+}
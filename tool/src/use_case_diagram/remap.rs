@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+
+use use_case_diagram::{ActorId, Extend, ExtensionPoint, ExtensionPointId, Note, NoteAnchor,
+                       NoteId, SystemBoundary, SystemBoundaryId, UseCaseDiagram, UseCaseId};
+
+/// The identifiers of the elements of a diagram, mapped to the identifiers
+/// of the same elements in a copy made by `UseCaseDiagram::clone_remapped`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct IdMapping {
+    pub actors: BTreeMap<ActorId, ActorId>,
+    pub use_cases: BTreeMap<UseCaseId, UseCaseId>,
+    pub extension_points: BTreeMap<ExtensionPointId, ExtensionPointId>,
+    pub system_boundaries: BTreeMap<SystemBoundaryId, SystemBoundaryId>,
+    pub notes: BTreeMap<NoteId, NoteId>,
+}
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> + Clone {
+    /// A copy of this diagram in which every element has a new identifier,
+    /// together with the mapping from the old identifiers to the new ones.
+    /// New identifiers are handed out from where this diagram's counters
+    /// are, so no identifier in the copy also refers to an element of this
+    /// diagram. Event recording is off in the copy.
+    pub fn clone_remapped(&self) -> (UseCaseDiagram<S>, IdMapping) {
+        let mut copy = UseCaseDiagram::new();
+        copy.unique_names = self.unique_names;
+        copy.invariant_checks = self.invariant_checks;
+        copy.next_actor_id = self.next_actor_id;
+        copy.next_use_case_id = self.next_use_case_id;
+        copy.next_extension_point_id = self.next_extension_point_id;
+        copy.next_system_boundary_id = self.next_system_boundary_id;
+        copy.next_note_id = self.next_note_id;
+
+        let mut mapping = IdMapping::default();
+        copy.batch(|copy| {
+            for (actor_id, actor) in self.actors() {
+                let copy_id = copy.insert_actor(actor.clone()).expect("names of the original");
+                mapping.actors.insert(actor_id, copy_id);
+            }
+            for (use_case_id, use_case) in self.use_cases() {
+                let copy_id =
+                    copy.insert_use_case(use_case.clone()).expect("titles of the original");
+                mapping.use_cases.insert(use_case_id, copy_id);
+            }
+            for (extension_point_id, extension_point) in self.extension_points() {
+                let extension_point = ExtensionPoint{
+                    use_case: mapping.use_cases[&extension_point.use_case],
+                    name: extension_point.name.clone(),
+                };
+                let copy_id = copy.insert_extension_point(extension_point)
+                    .expect("use case was just copied");
+                mapping.extension_points.insert(extension_point_id, copy_id);
+            }
+
+            for (actor_id, use_case_id) in self.associations() {
+                let association = self.association(actor_id, use_case_id).unwrap().clone();
+                copy.insert_association_with(mapping.actors[&actor_id],
+                                             mapping.use_cases[&use_case_id], association)
+                    .expect("actor and use case were just copied");
+            }
+            for (base, included) in self.includes() {
+                copy.insert_include(mapping.use_cases[&base], mapping.use_cases[&included])
+                    .expect("includes of a valid diagram are acyclic");
+            }
+            for (dependent, dependency) in self.dependencies() {
+                let label = self.dependency_label(dependent, dependency).cloned();
+                copy.insert_dependency(mapping.use_cases[&dependent],
+                                       mapping.use_cases[&dependency], label)
+                    .expect("dependencies of a valid diagram are acyclic");
+            }
+            for extend in self.extends() {
+                let extend = Extend{
+                    extension: mapping.use_cases[&extend.extension],
+                    base: mapping.use_cases[&extend.base],
+                    extension_point: extend.extension_point.map(|id| mapping.extension_points[&id]),
+                };
+                copy.insert_extend(extend).expect("extends of a valid diagram are acyclic");
+            }
+            for (child, parent) in self.actor_generalizations() {
+                copy.insert_actor_generalization(mapping.actors[&child], mapping.actors[&parent])
+                    .expect("actor generalizations of a valid diagram are acyclic");
+            }
+            for (child, parent) in self.use_case_generalizations() {
+                copy.insert_use_case_generalization(mapping.use_cases[&child],
+                                                    mapping.use_cases[&parent])
+                    .expect("use case generalizations of a valid diagram are acyclic");
+            }
+
+            for (system_boundary_id, system_boundary) in self.system_boundaries() {
+                let system_boundary = SystemBoundary{
+                    name: system_boundary.name.clone(),
+                    use_cases:
+                        system_boundary.use_cases.iter()
+                        .map(|use_case_id| mapping.use_cases[use_case_id])
+                        .collect(),
+                };
+                let copy_id = copy.insert_system_boundary(system_boundary)
+                    .expect("use cases were just copied");
+                mapping.system_boundaries.insert(system_boundary_id, copy_id);
+            }
+            for (note_id, note) in self.notes() {
+                let anchor = match note.anchor {
+                    NoteAnchor::Actor(actor_id) => NoteAnchor::Actor(mapping.actors[&actor_id]),
+                    NoteAnchor::UseCase(use_case_id) =>
+                        NoteAnchor::UseCase(mapping.use_cases[&use_case_id]),
+                    NoteAnchor::Association(actor_id, use_case_id) =>
+                        NoteAnchor::Association(mapping.actors[&actor_id],
+                                                mapping.use_cases[&use_case_id]),
+                };
+                let copy_id = copy.insert_note(Note{anchor, text: note.text.clone()})
+                    .expect("anchor was just copied");
+                mapping.notes.insert(note_id, copy_id);
+            }
+        });
+        (copy, mapping)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use use_case_diagram::{Actor, UseCase};
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_clone_remapped() {
+        let mut diagram = UseCaseDiagram::new();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        diagram.insert_include(pc, li).unwrap();
+        let n = diagram.insert_note(Note{anchor: NoteAnchor::Association(s, pc),
+                                         text: Rc::from("Rate limited")})
+            .unwrap();
+
+        let (copy, mapping) = diagram.clone_remapped();
+        assert_eq!(copy.validate(), Ok(()));
+        let (s2, pc2, li2) = (mapping.actors[&s], mapping.use_cases[&pc], mapping.use_cases[&li]);
+        assert!(diagram.actor(s2).is_none());
+        assert!(diagram.use_case(pc2).is_none() && diagram.use_case(li2).is_none());
+        assert_eq!(&*copy.actor(s2).unwrap().name, "Subscriber");
+        assert_eq!(copy.associations().collect::<Vec<_>>(), vec![(s2, pc2)]);
+        assert_eq!(copy.includes().collect::<Vec<_>>(), vec![(pc2, li2)]);
+        assert_eq!(copy.note(mapping.notes[&n]).unwrap().anchor,
+                   NoteAnchor::Association(s2, pc2));
+    }
+}
@@ -0,0 +1,138 @@
+use std::ops::Range;
+
+use use_case_diagram::{ActorId, NoteId, UseCaseDiagram, UseCaseId};
+
+/// The text of the diagram that a search hit was found in.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum SearchField {
+    ActorName(ActorId),
+    ActorDescription(ActorId),
+    UseCaseTitle(UseCaseId),
+    UseCaseDescription(UseCaseId),
+    NoteText(NoteId),
+}
+
+/// A match found by `UseCaseDiagram::search` or
+/// `UseCaseDiagram::fuzzy_search`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SearchHit {
+    pub field: SearchField,
+
+    /// The byte range of the field's text that matched, from the first to
+    /// the last matched character.
+    pub range: Range<usize>,
+}
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> {
+    /// Every actor name and description, use case title and description,
+    /// and note that contains the query, ignoring case. Hits are in order of
+    /// actors, use cases, and notes, each by identifier, and give the first
+    /// occurrence in each field. An empty query matches nothing.
+    pub fn search(&self, query: &str) -> impl Iterator<Item = SearchHit> {
+        self.search_by(query, find_substring)
+    }
+
+    /// Like `search`, but the characters of the query need only occur in
+    /// order, not next to each other, so that e.g. `"pstcmt"` finds
+    /// `"Post comment"`.
+    pub fn fuzzy_search(&self, query: &str) -> impl Iterator<Item = SearchHit> {
+        self.search_by(query, find_subsequence)
+    }
+
+    fn search_by<F>(&self, query: &str, find: F) -> impl Iterator<Item = SearchHit>
+        where F: Fn(&str, &[char]) -> Option<Range<usize>> {
+        let query = query.chars().flat_map(char::to_lowercase).collect::<Vec<_>>();
+        let mut fields = Vec::new();
+        for (actor_id, actor) in self.actors() {
+            fields.push((SearchField::ActorName(actor_id), Some(&actor.name)));
+            fields.push((SearchField::ActorDescription(actor_id), actor.description.as_ref()));
+        }
+        for (use_case_id, use_case) in self.use_cases() {
+            fields.push((SearchField::UseCaseTitle(use_case_id), Some(&use_case.title)));
+            fields.push((SearchField::UseCaseDescription(use_case_id),
+                         use_case.description.as_ref()));
+        }
+        for (note_id, note) in self.notes() {
+            fields.push((SearchField::NoteText(note_id), Some(&note.text)));
+        }
+
+        let mut hits = Vec::new();
+        if !query.is_empty() {
+            for (field, text) in fields {
+                if let Some(range) = text.and_then(|text| find(text.as_ref(), &query)) {
+                    hits.push(SearchHit{field, range});
+                }
+            }
+        }
+        hits.into_iter()
+    }
+}
+
+/// The lowercase characters of the text, each with the byte range of the
+/// character it comes from.
+fn lowercase_chars(text: &str) -> Vec<(char, Range<usize>)> {
+    text.char_indices()
+        .flat_map(|(start, c)| {
+            let range = start..start + c.len_utf8();
+            c.to_lowercase().map(move |lower| (lower, range.clone()))
+        })
+        .collect()
+}
+
+fn find_substring(text: &str, query: &[char]) -> Option<Range<usize>> {
+    let chars = lowercase_chars(text);
+    if chars.len() < query.len() {
+        return None;
+    }
+    (0..chars.len() - query.len() + 1)
+        .find(|&start| {
+            chars[start..start + query.len()].iter().map(|&(c, _)| c).eq(query.iter().cloned())
+        })
+        .map(|start| chars[start].1.start..chars[start + query.len() - 1].1.end)
+}
+
+fn find_subsequence(text: &str, query: &[char]) -> Option<Range<usize>> {
+    let mut chars = lowercase_chars(text).into_iter();
+    let mut range: Option<Range<usize>> = None;
+    for &q in query {
+        let (_, matched) = chars.find(|&(c, _)| c == q)?;
+        range = Some(range.map_or(matched.start, |range| range.start)..matched.end);
+    }
+    range
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use use_case_diagram::{Actor, Note, NoteAnchor, UseCase};
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_search() {
+        let mut diagram = UseCaseDiagram::new();
+        let mut moderator = Actor::new(Rc::from("Moderator"));
+        moderator.description = Some(Rc::from("Removes spam comments"));
+        let m = diagram.insert_actor(moderator).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post Comment"))).unwrap();
+        let n = diagram.insert_note(Note{anchor: NoteAnchor::UseCase(pc),
+                                         text: Rc::from("Ümlaut COMMENTS allowed")})
+            .unwrap();
+
+        assert_eq!(diagram.search("comment").collect::<Vec<_>>(), vec![
+            SearchHit{field: SearchField::ActorDescription(m), range: 13..20},
+            SearchHit{field: SearchField::UseCaseTitle(pc), range: 5..12},
+            SearchHit{field: SearchField::NoteText(n), range: 8..15},
+        ]);
+        assert_eq!(diagram.search("ümlaut").collect::<Vec<_>>(),
+                   vec![SearchHit{field: SearchField::NoteText(n), range: 0..7}]);
+        assert_eq!(diagram.search("pstcmt").count(), 0);
+        assert_eq!(diagram.search("").count(), 0);
+
+        assert_eq!(diagram.fuzzy_search("pstcmt").collect::<Vec<_>>(),
+                   vec![SearchHit{field: SearchField::UseCaseTitle(pc), range: 0..12}]);
+        assert_eq!(diagram.fuzzy_search("mdr").map(|hit| hit.field).collect::<Vec<_>>(),
+                   vec![SearchField::ActorName(m)]);
+    }
+}
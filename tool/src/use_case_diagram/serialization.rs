@@ -0,0 +1,316 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use use_case_diagram::names::{index_key, index_name};
+use use_case_diagram::{Actor, ActorId, Association, Extend, ExtensionPoint, ExtensionPointId,
+                       Note, NoteId, SystemBoundary, SystemBoundaryId, UseCase, UseCaseDiagram,
+                       UseCaseId};
+
+// The serde representation of a diagram mirrors its fields, except that
+// associations are a list, since JSON object keys cannot be pairs, and the
+// derived indices are left out and rebuilt on deserialization. The format
+// version is stored along.
+
+/// The version of the format a `UseCaseDiagram` is serialized in, as stored
+/// under `format_version`. Documents without a version were written before
+/// the format was versioned, and are of version 0. They lack dependencies
+/// and other fields added since diagrams could first be saved, which load
+/// only because deserialization defaults them; see `model::migrate`.
+pub const FORMAT_VERSION: u64 = 1;
+
+#[derive(Serialize)]
+struct DiagramRef<'a, S> where S: 'a {
+    format_version: u64,
+    next_actor_id: usize,
+    next_use_case_id: usize,
+    next_extension_point_id: usize,
+    next_system_boundary_id: usize,
+    next_note_id: usize,
+    unique_names: bool,
+    actors: &'a BTreeMap<ActorId, Actor<S>>,
+    use_cases: &'a BTreeMap<UseCaseId, UseCase<S>>,
+    extension_points: &'a BTreeMap<ExtensionPointId, ExtensionPoint<S>>,
+    associations: Vec<AssociationRef<'a, S>>,
+    includes: &'a BTreeSet<(UseCaseId, UseCaseId)>,
+    dependencies: Vec<DependencyRef<'a, S>>,
+    extends: &'a BTreeSet<Extend>,
+    actor_generalizations: &'a BTreeSet<(ActorId, ActorId)>,
+    use_case_generalizations: &'a BTreeSet<(UseCaseId, UseCaseId)>,
+    system_boundaries: &'a BTreeMap<SystemBoundaryId, SystemBoundary<S>>,
+    notes: &'a BTreeMap<NoteId, Note<S>>,
+}
+
+#[derive(Serialize)]
+struct AssociationRef<'a, S> where S: 'a {
+    actor: ActorId,
+    use_case: UseCaseId,
+    details: &'a Association<S>,
+}
+
+#[derive(Serialize)]
+struct DependencyRef<'a, S> where S: 'a {
+    dependent: UseCaseId,
+    dependency: UseCaseId,
+    label: &'a Option<S>,
+}
+
+#[derive(Deserialize)]
+struct DiagramData<S> {
+    #[serde(default)]
+    format_version: u64,
+    next_actor_id: usize,
+    next_use_case_id: usize,
+    next_extension_point_id: usize,
+    next_system_boundary_id: usize,
+    next_note_id: usize,
+    unique_names: bool,
+    actors: BTreeMap<ActorId, Actor<S>>,
+    use_cases: BTreeMap<UseCaseId, UseCase<S>>,
+    extension_points: BTreeMap<ExtensionPointId, ExtensionPoint<S>>,
+    associations: Vec<AssociationData<S>>,
+    includes: BTreeSet<(UseCaseId, UseCaseId)>,
+    #[serde(default = "Vec::new")]
+    dependencies: Vec<DependencyData<S>>,
+    extends: BTreeSet<Extend>,
+    actor_generalizations: BTreeSet<(ActorId, ActorId)>,
+    use_case_generalizations: BTreeSet<(UseCaseId, UseCaseId)>,
+    system_boundaries: BTreeMap<SystemBoundaryId, SystemBoundary<S>>,
+    notes: BTreeMap<NoteId, Note<S>>,
+}
+
+#[derive(Deserialize)]
+struct AssociationData<S> {
+    actor: ActorId,
+    use_case: UseCaseId,
+    details: Association<S>,
+}
+
+#[derive(Deserialize)]
+struct DependencyData<S> {
+    dependent: UseCaseId,
+    dependency: UseCaseId,
+    label: Option<S>,
+}
+
+/// Identifiers and the next-identifier counters are preserved, so a
+/// deserialized diagram hands out the same identifiers as the original.
+impl<S> Serialize for UseCaseDiagram<S> where S: AsRef<str> + Serialize {
+    fn serialize<T>(&self, serializer: T) -> Result<T::Ok, T::Error> where T: Serializer {
+        DiagramRef{
+            format_version: FORMAT_VERSION,
+            next_actor_id: self.next_actor_id,
+            next_use_case_id: self.next_use_case_id,
+            next_extension_point_id: self.next_extension_point_id,
+            next_system_boundary_id: self.next_system_boundary_id,
+            next_note_id: self.next_note_id,
+            unique_names: self.unique_names,
+            actors: &self.actors,
+            use_cases: &self.use_cases,
+            extension_points: &self.extension_points,
+            associations:
+                self.associations.iter()
+                .map(|(&(actor, use_case), details)| AssociationRef{actor, use_case, details})
+                .collect(),
+            includes: &self.includes,
+            dependencies:
+                self.dependencies.iter()
+                .map(|(&(dependent, dependency), label)|
+                     DependencyRef{dependent, dependency, label})
+                .collect(),
+            extends: &self.extends,
+            actor_generalizations: &self.actor_generalizations,
+            use_case_generalizations: &self.use_case_generalizations,
+            system_boundaries: &self.system_boundaries,
+            notes: &self.notes,
+        }.serialize(serializer)
+    }
+}
+
+/// Deserialization fails if the data is of a newer format version or does
+/// not describe a valid diagram, e.g. if a relationship refers to a
+/// nonexistent element or closes a cycle, or if a counter would hand out an
+/// identifier already in use. Documents of version 0 are read as they are,
+/// with every field they lack defaulted.
+impl<'de, S> Deserialize<'de> for UseCaseDiagram<S>
+    where S: AsRef<str> + Deserialize<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        let data: DiagramData<S> = DiagramData::deserialize(deserializer)?;
+        if data.format_version > FORMAT_VERSION {
+            return Err(D::Error::custom(format_args!(
+                "format version {} is newer than {}", data.format_version, FORMAT_VERSION)));
+        }
+        let mut diagram = UseCaseDiagram::new();
+        let invariant_checks = diagram.invariant_checks;
+        diagram.invariant_checks = false;
+        diagram.unique_names = data.unique_names;
+
+        check_counter("actor", data.next_actor_id, data.actors.keys().map(|id| id.0))?;
+        check_counter("use case", data.next_use_case_id, data.use_cases.keys().map(|id| id.0))?;
+        check_counter("extension point", data.next_extension_point_id,
+                      data.extension_points.keys().map(|id| id.0))?;
+        check_counter("system boundary", data.next_system_boundary_id,
+                      data.system_boundaries.keys().map(|id| id.0))?;
+        check_counter("note", data.next_note_id, data.notes.keys().map(|id| id.0))?;
+        diagram.next_actor_id = data.next_actor_id;
+        diagram.next_use_case_id = data.next_use_case_id;
+        diagram.next_extension_point_id = data.next_extension_point_id;
+        diagram.next_system_boundary_id = data.next_system_boundary_id;
+        diagram.next_note_id = data.next_note_id;
+
+        for (actor_id, actor) in data.actors {
+            index_name(&mut diagram.actor_names, actor.name.as_ref(), actor_id);
+            index_key(&mut diagram.actor_keys, actor.key.as_ref().map(AsRef::as_ref), actor_id);
+            diagram.actors.insert(actor_id, actor);
+        }
+        for (use_case_id, use_case) in data.use_cases {
+            index_name(&mut diagram.use_case_titles, use_case.title.as_ref(), use_case_id);
+            index_key(&mut diagram.use_case_keys, use_case.key.as_ref().map(AsRef::as_ref),
+                      use_case_id);
+            diagram.use_cases.insert(use_case_id, use_case);
+        }
+        if diagram.unique_names {
+            diagram.validate_unique_names().map_err(D::Error::custom)?;
+        }
+        for (extension_point_id, extension_point) in data.extension_points {
+            if !diagram.use_cases.contains_key(&extension_point.use_case) {
+                return Err(D::Error::custom(format_args!(
+                    "extension point {} refers to nonexistent use case {}",
+                    extension_point_id, extension_point.use_case)));
+            }
+            diagram.extension_points.insert(extension_point_id, extension_point);
+        }
+
+        for association in data.associations {
+            diagram.insert_association_with(association.actor, association.use_case,
+                                            association.details)
+                .map_err(D::Error::custom)?;
+        }
+        for (base, included) in data.includes {
+            diagram.insert_include(base, included).map_err(D::Error::custom)?;
+        }
+        for dependency in data.dependencies {
+            diagram.insert_dependency(dependency.dependent, dependency.dependency,
+                                      dependency.label)
+                .map_err(D::Error::custom)?;
+        }
+        for extend in data.extends {
+            diagram.insert_extend(extend).map_err(D::Error::custom)?;
+        }
+        for (child, parent) in data.actor_generalizations {
+            diagram.insert_actor_generalization(child, parent).map_err(D::Error::custom)?;
+        }
+        for (child, parent) in data.use_case_generalizations {
+            diagram.insert_use_case_generalization(child, parent).map_err(D::Error::custom)?;
+        }
+
+        for (system_boundary_id, system_boundary) in data.system_boundaries {
+            let nonexistent =
+                system_boundary.use_cases.iter()
+                .find(|use_case_id| !diagram.use_cases.contains_key(use_case_id));
+            if let Some(use_case_id) = nonexistent {
+                return Err(D::Error::custom(format_args!(
+                    "system boundary {} contains nonexistent use case {}",
+                    system_boundary_id, use_case_id)));
+            }
+            diagram.system_boundaries.insert(system_boundary_id, system_boundary);
+        }
+        for (note_id, note) in data.notes {
+            if !diagram.note_anchor_exists(note.anchor) {
+                return Err(D::Error::custom(format_args!(
+                    "note {} refers to nonexistent element", note_id)));
+            }
+            diagram.notes.insert(note_id, note);
+        }
+
+        if let Err(violations) = diagram.validate() {
+            return Err(D::Error::custom(violations[0]));
+        }
+        diagram.invariant_checks = invariant_checks;
+        Ok(diagram)
+    }
+}
+
+fn check_counter<I, E>(kind: &str, next: usize, mut ids: I) -> Result<(), E>
+    where I: Iterator<Item = usize>, E: Error {
+    match ids.find(|&id| id >= next) {
+        Some(id) =>
+            Err(E::custom(format_args!("next {} identifier {} is not above {}", kind, next, id))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use use_case_diagram::{Actor, Extend, ExtensionPoint, Note, NoteAnchor, UseCase,
+                           UseCaseDiagram};
+
+    use serde_json;
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_serde_round_trip() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let r = diagram.insert_use_case(UseCase::new(Rc::from("Report comment"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        let ep = diagram.insert_extension_point(ExtensionPoint{use_case: pc,
+                                                               name: Rc::from("After")})
+            .unwrap();
+        diagram.insert_extend(Extend{extension: r, base: pc, extension_point: Some(ep)})
+            .unwrap();
+        diagram.insert_note(Note{anchor: NoteAnchor::Association(s, pc),
+                                 text: Rc::from("Rate limited")})
+            .unwrap();
+        diagram.remove_actor(a);
+
+        let json = serde_json::to_string(&diagram).unwrap();
+        let mut imported: UseCaseDiagram = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&imported).unwrap(), json);
+        assert_eq!(imported.actors().collect::<Vec<_>>(), diagram.actors().collect::<Vec<_>>());
+        assert_eq!(imported.use_cases_of_actor(s).collect::<Vec<_>>(), vec![pc]);
+        assert_eq!(imported.actor_by_name("Subscriber").map(|(id, _)| id), Some(s));
+        assert_eq!(imported.insert_actor(Actor::new(Rc::from("Guest"))).unwrap(),
+                   diagram.insert_actor(Actor::new(Rc::from("Guest"))).unwrap());
+    }
+
+    #[test]
+    fn test_serde_round_trip_keys() {
+        let mut diagram = UseCaseDiagram::with_unique_names();
+        let mut administrator = Actor::new(Rc::from("Administrator"));
+        administrator.key = Some(Rc::from("9f0c"));
+        let a = diagram.insert_actor(administrator).unwrap();
+        let mut ban_subscriber = UseCase::new(Rc::from("Ban subscriber"));
+        ban_subscriber.key = Some(Rc::from("UC-7"));
+        let bs = diagram.insert_use_case(ban_subscriber).unwrap();
+        diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+
+        let json = serde_json::to_string(&diagram).unwrap();
+        let imported: UseCaseDiagram = serde_json::from_str(&json).unwrap();
+        assert!(imported.has_unique_names());
+        assert_eq!(imported.actor_by_key("9f0c").map(|(id, _)| id), Some(a));
+        assert_eq!(imported.use_case_by_key("UC-7").map(|(id, _)| id), Some(bs));
+    }
+
+    #[test]
+    fn test_serde_invalid() {
+        let mut diagram = UseCaseDiagram::new();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_include(bs, pc).unwrap();
+        let json = serde_json::to_string(&diagram).unwrap();
+        assert!(json.contains("\"includes\":[[0,1]]"));
+        assert!(json.contains("\"next_use_case_id\":2"));
+
+        let cyclic = json.replace("\"includes\":[[0,1]]", "\"includes\":[[0,1],[1,0]]");
+        assert!(serde_json::from_str::<UseCaseDiagram>(&cyclic).is_err());
+        let stale = json.replace("\"next_use_case_id\":2", "\"next_use_case_id\":1");
+        assert!(serde_json::from_str::<UseCaseDiagram>(&stale).is_err());
+    }
+}
@@ -0,0 +1,245 @@
+use std::ops::Deref;
+use std::sync::Arc;
+
+use use_case_diagram::{Actor, Association, ExtensionPoint, Flow, Note, Span, SystemBoundary,
+                       UseCase, UseCaseDiagram};
+
+/// A read-only copy of a diagram as it was when the snapshot was taken,
+/// which is cheap to clone. Hand it to renderers and generators while the
+/// diagram itself is being edited further; with names stored as `Arc<str>`,
+/// as `UseCaseDiagram::snapshot` stores them, it can be sent to and shared
+/// between threads. All queries of `UseCaseDiagram` are available through
+/// `Deref`.
+#[derive(Debug)]
+pub struct DiagramSnapshot<S = Arc<str>> {
+    diagram: Arc<UseCaseDiagram<S>>,
+}
+
+impl<S> DiagramSnapshot<S> {
+    /// Whether the two snapshots are clones of the same snapshot.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.diagram, &other.diagram)
+    }
+
+    /// A mutable diagram with the contents of this snapshot.
+    pub fn to_diagram(&self) -> UseCaseDiagram<S> where S: Clone {
+        (*self.diagram).clone()
+    }
+}
+
+impl<S> Clone for DiagramSnapshot<S> {
+    fn clone(&self) -> Self {
+        DiagramSnapshot{diagram: Arc::clone(&self.diagram)}
+    }
+}
+
+impl<S> Deref for DiagramSnapshot<S> {
+    type Target = UseCaseDiagram<S>;
+
+    fn deref(&self) -> &UseCaseDiagram<S> {
+        &self.diagram
+    }
+}
+
+/// Take a snapshot of a diagram that will not be edited any further,
+/// without copying it. Recorded events are discarded.
+impl<S> From<UseCaseDiagram<S>> for DiagramSnapshot<S> where S: AsRef<str> {
+    fn from(mut diagram: UseCaseDiagram<S>) -> Self {
+        diagram.events.clear();
+        DiagramSnapshot{diagram: Arc::new(diagram)}
+    }
+}
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> {
+    /// A snapshot of this diagram as it is now, with its names stored as
+    /// `Arc<str>` so that it can be sent to other threads whatever `S` is.
+    /// Taking it copies the diagram once, without the recorded events;
+    /// cloning it copies nothing.
+    pub fn snapshot(&self) -> DiagramSnapshot {
+        DiagramSnapshot{diagram: Arc::new(shared(self))}
+    }
+}
+
+/// A copy of the diagram with its names stored as `Arc<str>`, without the
+/// recorded events.
+fn shared<S>(diagram: &UseCaseDiagram<S>) -> UseCaseDiagram<Arc<str>> where S: AsRef<str> {
+    UseCaseDiagram{
+        next_actor_id: diagram.next_actor_id,
+        next_use_case_id: diagram.next_use_case_id,
+        next_extension_point_id: diagram.next_extension_point_id,
+        next_system_boundary_id: diagram.next_system_boundary_id,
+        next_note_id: diagram.next_note_id,
+
+        actors: diagram.actors.iter().map(|(&id, actor)| (id, shared_actor(actor))).collect(),
+        use_cases: diagram.use_cases.iter()
+            .map(|(&id, use_case)| (id, shared_use_case(use_case)))
+            .collect(),
+        extension_points: diagram.extension_points.iter()
+            .map(|(&id, extension_point)| {
+                (id, ExtensionPoint{use_case: extension_point.use_case,
+                                    name: share(&extension_point.name)})
+            })
+            .collect(),
+        associations: diagram.associations.iter()
+            .map(|(&key, association)| (key, shared_association(association)))
+            .collect(),
+        includes: diagram.includes.clone(),
+        dependencies: diagram.dependencies.iter()
+            .map(|(&key, label)| (key, label.as_ref().map(share)))
+            .collect(),
+        extends: diagram.extends.clone(),
+        actor_generalizations: diagram.actor_generalizations.clone(),
+        use_case_generalizations: diagram.use_case_generalizations.clone(),
+        system_boundaries: diagram.system_boundaries.iter()
+            .map(|(&id, system_boundary)| {
+                (id, SystemBoundary{name: share(&system_boundary.name),
+                                    use_cases: system_boundary.use_cases.clone()})
+            })
+            .collect(),
+        notes: diagram.notes.iter()
+            .map(|(&id, note)| (id, Note{anchor: note.anchor, text: share(&note.text)}))
+            .collect(),
+
+        actor_use_cases: diagram.actor_use_cases.clone(),
+        use_case_actors: diagram.use_case_actors.clone(),
+        actor_names: diagram.actor_names.clone(),
+        use_case_titles: diagram.use_case_titles.clone(),
+        actor_keys: diagram.actor_keys.clone(),
+        use_case_keys: diagram.use_case_keys.clone(),
+        unique_names: diagram.unique_names,
+        invariant_checks: diagram.invariant_checks,
+        event_recording: diagram.event_recording,
+        events: Vec::new(),
+    }
+}
+
+fn shared_actor<S>(actor: &Actor<S>) -> Actor<Arc<str>> where S: AsRef<str> {
+    Actor{
+        name: share(&actor.name),
+        key: actor.key.as_ref().map(share),
+        kind: actor.kind,
+        description: actor.description.as_ref().map(share),
+        stereotypes: share_all(&actor.stereotypes),
+        metadata: actor.metadata.clone(),
+        icon: actor.icon.as_ref().map(share),
+        color: actor.color.as_ref().map(share),
+        localized_names: actor.localized_names.iter()
+            .map(|(locale, name)| (locale.clone(), share(name)))
+            .collect(),
+        layers: share_all(&actor.layers),
+        sort_key: actor.sort_key,
+        span: actor.span.as_ref().map(shared_span),
+    }
+}
+
+fn shared_use_case<S>(use_case: &UseCase<S>) -> UseCase<Arc<str>> where S: AsRef<str> {
+    UseCase{
+        title: share(&use_case.title),
+        key: use_case.key.as_ref().map(share),
+        package: share_all(&use_case.package),
+        is_abstract: use_case.is_abstract,
+        description: use_case.description.as_ref().map(share),
+        preconditions: share_all(&use_case.preconditions),
+        postconditions: share_all(&use_case.postconditions),
+        main_flow: share_all(&use_case.main_flow),
+        alternative_flows: use_case.alternative_flows.iter()
+            .map(|flow| Flow{name: share(&flow.name), steps: share_all(&flow.steps)})
+            .collect(),
+        priority: use_case.priority,
+        status: use_case.status,
+        deprecated: use_case.deprecated.as_ref().map(share),
+        tags: share_all(&use_case.tags),
+        stereotypes: share_all(&use_case.stereotypes),
+        metadata: use_case.metadata.clone(),
+        localized_titles: use_case.localized_titles.iter()
+            .map(|(locale, title)| (locale.clone(), share(title)))
+            .collect(),
+        layers: share_all(&use_case.layers),
+        sort_key: use_case.sort_key,
+        span: use_case.span.as_ref().map(shared_span),
+    }
+}
+
+fn shared_association<S>(association: &Association<S>) -> Association<Arc<str>>
+    where S: AsRef<str> {
+    Association{
+        label: association.label.as_ref().map(share),
+        actor_multiplicity: association.actor_multiplicity,
+        use_case_multiplicity: association.use_case_multiplicity,
+        navigability: association.navigability,
+        weight: association.weight,
+        metadata: association.metadata.clone(),
+        layers: share_all(&association.layers),
+        span: association.span.as_ref().map(shared_span),
+    }
+}
+
+fn shared_span<S>(span: &Span<S>) -> Span<Arc<str>> where S: AsRef<str> {
+    Span{file: share(&span.file), line: span.line, column: span.column}
+}
+
+fn share_all<S>(strings: &[S]) -> Vec<Arc<str>> where S: AsRef<str> {
+    strings.iter().map(share).collect()
+}
+
+fn share<S>(string: &S) -> Arc<str> where S: AsRef<str> {
+    Arc::from(string.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use use_case_diagram::{Actor, UseCase};
+
+    use std::rc::Rc;
+    use std::thread;
+
+    #[test]
+    fn test_snapshot_of_rc_diagram() {
+        let mut diagram = UseCaseDiagram::<Rc<str>>::new();
+        diagram.set_event_recording(true);
+        let mut administrator = Actor::new(Rc::from("Administrator"));
+        administrator.layers.push(Rc::from("MVP"));
+        let a = diagram.insert_actor(administrator).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+
+        let snapshot = diagram.snapshot();
+        assert_eq!(diagram.drain_events().count(), 3);
+        let sent = thread::spawn(move || snapshot).join().unwrap();
+        assert_eq!(&*sent[a].name, "Administrator");
+        assert_eq!(sent[a].layers, vec![Arc::from("MVP")]);
+        assert_eq!(sent.actor_by_name("Administrator").map(|(actor_id, _)| actor_id), Some(a));
+        assert_eq!(sent.use_cases_of_actor(a).collect::<Vec<_>>(), vec![bs]);
+        sent.assert_invariants();
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut diagram = UseCaseDiagram::<Arc<str>>::new();
+        diagram.set_event_recording(true);
+        let a = diagram.insert_actor(Actor::new(Arc::from("Administrator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Arc::from("Ban subscriber"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+
+        let snapshot = diagram.snapshot();
+        diagram.insert_actor(Actor::new(Arc::from("Moderator"))).unwrap();
+        assert_eq!(diagram.drain_events().count(), 4);
+
+        let shared = snapshot.clone();
+        assert!(DiagramSnapshot::ptr_eq(&snapshot, &shared));
+        let names = thread::spawn(move || {
+            shared.actors()
+                .map(|(_, actor)| actor.name.to_string())
+                .collect::<Vec<_>>()
+        }).join().unwrap();
+        assert_eq!(names, vec!["Administrator".to_string()]);
+        assert_eq!(snapshot.use_cases_of_actor(a).collect::<Vec<_>>(), vec![bs]);
+
+        let mut copy = snapshot.to_diagram();
+        assert_eq!(copy.drain_events().count(), 0);
+        copy.insert_actor(Actor::new(Arc::from("Moderator"))).unwrap();
+        assert_eq!(copy.actors().count(), diagram.actors().count());
+    }
+}
@@ -0,0 +1,55 @@
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use use_case_diagram::parser::Position;
+use use_case_diagram::{ActorId, Span, UseCaseDiagram, UseCaseId};
+
+/// Where the elements of a parsed diagram were defined in the text, for
+/// reporting problems found later, e.g. by lints, diffs or code generation,
+/// against it. Elements declared implicitly are where they are first
+/// mentioned. Every text importer but `openapi` has a variant that returns
+/// one, such as `parser::parse_with_source_map`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SourceMap<S = Rc<str>> {
+    pub actors: BTreeMap<ActorId, Span<S>>,
+    pub use_cases: BTreeMap<UseCaseId, Span<S>>,
+    pub associations: BTreeMap<(ActorId, UseCaseId), Span<S>>,
+}
+
+impl<S> SourceMap<S> {
+    pub fn new() -> Self {
+        SourceMap{actors: BTreeMap::new(), use_cases: BTreeMap::new(),
+                  associations: BTreeMap::new()}
+    }
+
+    /// Set the span of every element of the diagram that is in this map, so
+    /// that the diagram keeps them, e.g. for `Lint::span`.
+    pub fn apply(&self, diagram: &mut UseCaseDiagram<S>) where S: AsRef<str> + Clone {
+        for (&actor_id, span) in &self.actors {
+            if let Some(mut actor) = diagram.actor_mut(actor_id) {
+                actor.span = Some(span.clone());
+            }
+        }
+        for (&use_case_id, span) in &self.use_cases {
+            if let Some(mut use_case) = diagram.use_case_mut(use_case_id) {
+                use_case.span = Some(span.clone());
+            }
+        }
+        for (&(actor_id, use_case_id), span) in &self.associations {
+            if let Some(association) = diagram.association_mut(actor_id, use_case_id) {
+                association.span = Some(span.clone());
+            }
+        }
+    }
+}
+
+impl<S> Default for SourceMap<S> {
+    fn default() -> Self {
+        SourceMap::new()
+    }
+}
+
+/// The span of a position in the file.
+pub(super) fn span<S>(file: &str, position: Position) -> Span<S> where S: for<'a> From<&'a str> {
+    Span{file: S::from(file), line: position.line, column: position.column}
+}
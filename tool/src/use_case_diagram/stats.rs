@@ -0,0 +1,101 @@
+use std::collections::BTreeMap;
+
+use use_case_diagram::lint::LintKind;
+use use_case_diagram::{ActorId, UseCaseDiagram, UseCaseId};
+
+/// Summary figures of a diagram, as computed by `UseCaseDiagram::stats`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DiagramStats {
+    pub actors: usize,
+    pub use_cases: usize,
+    pub associations: usize,
+    pub includes: usize,
+    pub extends: usize,
+    pub dependencies: usize,
+    pub generalizations: usize,
+    pub system_boundaries: usize,
+    pub notes: usize,
+
+    /// For every actor, the number of use cases it is directly associated
+    /// with.
+    pub actor_degrees: BTreeMap<ActorId, usize>,
+
+    /// For every use case, the number of actors directly associated with it
+    /// plus the number of use cases that include it.
+    pub use_case_fan_in: BTreeMap<UseCaseId, usize>,
+
+    /// The number of actors reported as orphans by `lint`.
+    pub orphan_actors: usize,
+
+    /// The number of use cases reported as orphans by `lint`.
+    pub orphan_use_cases: usize,
+}
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> {
+    /// Counts of the elements and relationships in this diagram, and of how
+    /// connected each actor and use case is.
+    pub fn stats(&self) -> DiagramStats {
+        let actor_degrees =
+            self.actors()
+            .map(|(actor_id, _)| (actor_id, self.use_cases_of_actor(actor_id).count()))
+            .collect();
+        let mut use_case_fan_in =
+            self.use_cases()
+            .map(|(use_case_id, _)| (use_case_id, self.actors_of_use_case(use_case_id).count()))
+            .collect::<BTreeMap<_, _>>();
+        for (_, included) in self.includes() {
+            *use_case_fan_in.get_mut(&included).unwrap() += 1;
+        }
+        let lints = self.lint();
+        DiagramStats{
+            actors: self.actors.len(),
+            use_cases: self.use_cases.len(),
+            associations: self.associations.len(),
+            includes: self.includes.len(),
+            extends: self.extends.len(),
+            dependencies: self.dependencies.len(),
+            generalizations:
+                self.actor_generalizations.len() + self.use_case_generalizations.len(),
+            system_boundaries: self.system_boundaries.len(),
+            notes: self.notes.len(),
+            actor_degrees,
+            use_case_fan_in,
+            orphan_actors:
+                lints.iter().filter(|lint| matches!(lint.kind, LintKind::OrphanActor(_))).count(),
+            orphan_use_cases:
+                lints.iter()
+                .filter(|lint| matches!(lint.kind, LintKind::OrphanUseCase(_)))
+                .count(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use use_case_diagram::{Actor, UseCase};
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_stats() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        let ex = diagram.insert_use_case(UseCase::new(Rc::from("Export"))).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        diagram.insert_association(s, li).unwrap();
+        diagram.insert_include(pc, li).unwrap();
+
+        let stats = diagram.stats();
+        assert_eq!((stats.actors, stats.use_cases, stats.associations, stats.includes),
+                   (2, 3, 2, 1));
+        assert_eq!(stats.actor_degrees, vec![(a, 0), (s, 2)].into_iter().collect());
+        assert_eq!(stats.use_case_fan_in,
+                   vec![(pc, 1), (li, 2), (ex, 0)].into_iter().collect());
+        assert_eq!((stats.orphan_actors, stats.orphan_use_cases), (1, 1));
+    }
+}
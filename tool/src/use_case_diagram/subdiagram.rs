@@ -0,0 +1,354 @@
+use std::collections::{BTreeSet, HashSet};
+
+use use_case_diagram::{Actor, ActorId, NoteAnchor, SystemBoundary, SystemBoundaryId, UseCase,
+                       UseCaseDiagram, UseCaseId};
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> + Clone {
+    /// The part of this diagram reachable from the given actors: those
+    /// actors, every use case they are effectively associated with (see
+    /// `effective_use_cases`), every use case
+    /// included by those directly or through other includes, and the
+    /// associations and includes between them. Identifiers are preserved,
+    /// and roots that do not exist are ignored.
+    pub fn reachable_subdiagram(&self, roots: &HashSet<ActorId>) -> UseCaseDiagram<S> {
+        let use_case_ids =
+            roots.iter()
+            .flat_map(|&actor_id| self.effective_use_cases(actor_id))
+            .flat_map(|use_case_id| self.transitively_included_use_cases(use_case_id))
+            .collect::<HashSet<_>>();
+        self.induced_subdiagram(roots, &use_case_ids)
+    }
+
+    /// The part of this diagram relevant to the given actor, as computed by
+    /// `reachable_subdiagram` with the actor as the only root. Return `None`
+    /// if the actor does not exist.
+    pub fn subdiagram_for_actor(&self, actor_id: ActorId) -> Option<UseCaseDiagram<S>> {
+        self.actor(actor_id)?;
+        let mut roots = HashSet::new();
+        roots.insert(actor_id);
+        Some(self.reachable_subdiagram(&roots))
+    }
+
+    /// The subdiagram with the actors and use cases that satisfy the given
+    /// predicates, and all relationships between them. Identifiers are
+    /// preserved.
+    pub fn filter<P, Q>(&self, mut actor_predicate: P, mut use_case_predicate: Q)
+                        -> UseCaseDiagram<S>
+        where P: FnMut(ActorId, &Actor<S>) -> bool,
+              Q: FnMut(UseCaseId, &UseCase<S>) -> bool {
+        let actor_ids =
+            self.actors()
+            .filter(|&(actor_id, actor)| actor_predicate(actor_id, actor))
+            .map(|(actor_id, _)| actor_id)
+            .collect();
+        let use_case_ids =
+            self.use_cases()
+            .filter(|&(use_case_id, use_case)| use_case_predicate(use_case_id, use_case))
+            .map(|(use_case_id, _)| use_case_id)
+            .collect();
+        self.induced_subdiagram(&actor_ids, &use_case_ids)
+    }
+
+    /// The part of this diagram inside the given system boundary: its use
+    /// cases, the actors effectively associated with them (see
+    /// `effective_actors`), and the relationships between those. Other
+    /// system boundaries are kept with only the use cases they share.
+    /// Identifiers are preserved. Return `None` if the system boundary does
+    /// not exist.
+    pub fn system_boundary_subdiagram(&self, system_boundary_id: SystemBoundaryId)
+                                      -> Option<UseCaseDiagram<S>> {
+        let use_case_ids =
+            self.system_boundary(system_boundary_id)?.use_cases.iter().cloned()
+            .collect::<HashSet<_>>();
+        let actor_ids =
+            use_case_ids.iter()
+            .flat_map(|&use_case_id| self.effective_actors(use_case_id))
+            .collect();
+        Some(self.induced_subdiagram(&actor_ids, &use_case_ids))
+    }
+
+    /// The part of this diagram directly in the given package: its use
+    /// cases, the actors effectively associated with them (see
+    /// `effective_actors`), and the relationships between those. Identifiers
+    /// are preserved.
+    pub fn package_subdiagram(&self, package: &[&str]) -> UseCaseDiagram<S> {
+        let use_case_ids =
+            self.use_cases_in_package(package)
+            .map(|(use_case_id, _)| use_case_id)
+            .collect::<HashSet<_>>();
+        let actor_ids =
+            use_case_ids.iter()
+            .flat_map(|&use_case_id| self.effective_actors(use_case_id))
+            .collect();
+        self.induced_subdiagram(&actor_ids, &use_case_ids)
+    }
+
+    /// The subdiagram with the given actors and use cases, their extension
+    /// points, and all relationships between them, preserving identifiers.
+    /// System boundaries that contain any of the use cases are kept, as are
+    /// notes on kept elements.
+    fn induced_subdiagram(&self, actor_ids: &HashSet<ActorId>,
+                          use_case_ids: &HashSet<UseCaseId>) -> UseCaseDiagram<S> {
+        let subdiagram = UseCaseDiagram{
+            next_actor_id: self.next_actor_id,
+            next_use_case_id: self.next_use_case_id,
+            next_extension_point_id: self.next_extension_point_id,
+            next_system_boundary_id: self.next_system_boundary_id,
+            next_note_id: self.next_note_id,
+
+            actors:
+                self.actors.iter()
+                .filter(|&(actor_id, _)| actor_ids.contains(actor_id))
+                .map(|(&actor_id, actor)| (actor_id, actor.clone()))
+                .collect(),
+            use_cases:
+                self.use_cases.iter()
+                .filter(|&(use_case_id, _)| use_case_ids.contains(use_case_id))
+                .map(|(&use_case_id, use_case)| (use_case_id, use_case.clone()))
+                .collect(),
+            extension_points:
+                self.extension_points.iter()
+                .filter(|&(_, extension_point)| use_case_ids.contains(&extension_point.use_case))
+                .map(|(&extension_point_id, extension_point)|
+                     (extension_point_id, extension_point.clone()))
+                .collect(),
+            associations:
+                self.associations.iter()
+                .filter(|&(&(actor_id, use_case_id), _)|
+                        actor_ids.contains(&actor_id) && use_case_ids.contains(&use_case_id))
+                .map(|(&key, association)| (key, association.clone()))
+                .collect(),
+            actor_use_cases:
+                self.actor_use_cases.iter()
+                .filter(|&(actor_id, _)| actor_ids.contains(actor_id))
+                .map(|(&actor_id, ids)| (actor_id, ids.iter().cloned()
+                                                   .filter(|id| use_case_ids.contains(id))
+                                                   .collect::<BTreeSet<_>>()))
+                .filter(|(_, ids)| !ids.is_empty())
+                .collect(),
+            use_case_actors:
+                self.use_case_actors.iter()
+                .filter(|&(use_case_id, _)| use_case_ids.contains(use_case_id))
+                .map(|(&use_case_id, ids)| (use_case_id, ids.iter().cloned()
+                                                         .filter(|id| actor_ids.contains(id))
+                                                         .collect::<BTreeSet<_>>()))
+                .filter(|(_, ids)| !ids.is_empty())
+                .collect(),
+            includes:
+                self.includes.iter()
+                .filter(|&&(base, included)|
+                        use_case_ids.contains(&base) && use_case_ids.contains(&included))
+                .cloned()
+                .collect(),
+            dependencies:
+                self.dependencies.iter()
+                .filter(|&(&(dependent, dependency), _)|
+                        use_case_ids.contains(&dependent) && use_case_ids.contains(&dependency))
+                .map(|(&pair, label)| (pair, label.clone()))
+                .collect(),
+            extends:
+                self.extends.iter()
+                .filter(|extend|
+                        use_case_ids.contains(&extend.extension) &&
+                        use_case_ids.contains(&extend.base))
+                .cloned()
+                .collect(),
+            actor_generalizations:
+                self.actor_generalizations.iter()
+                .filter(|&&(child, parent)|
+                        actor_ids.contains(&child) && actor_ids.contains(&parent))
+                .cloned()
+                .collect(),
+            use_case_generalizations:
+                self.use_case_generalizations.iter()
+                .filter(|&&(child, parent)|
+                        use_case_ids.contains(&child) && use_case_ids.contains(&parent))
+                .cloned()
+                .collect(),
+            system_boundaries:
+                self.system_boundaries.iter()
+                .map(|(&system_boundary_id, system_boundary)| {
+                    let use_cases = system_boundary.use_cases.iter()
+                        .cloned()
+                        .filter(|use_case_id| use_case_ids.contains(use_case_id))
+                        .collect::<BTreeSet<_>>();
+                    (system_boundary_id,
+                     SystemBoundary{name: system_boundary.name.clone(), use_cases})
+                })
+                .filter(|(_, system_boundary)| !system_boundary.use_cases.is_empty())
+                .collect(),
+            notes:
+                self.notes.iter()
+                .filter(|&(_, note)| match note.anchor {
+                    NoteAnchor::Actor(actor_id) => actor_ids.contains(&actor_id),
+                    NoteAnchor::UseCase(use_case_id) => use_case_ids.contains(&use_case_id),
+                    NoteAnchor::Association(actor_id, use_case_id) =>
+                        actor_ids.contains(&actor_id) && use_case_ids.contains(&use_case_id),
+                })
+                .map(|(&note_id, note)| (note_id, note.clone()))
+                .collect(),
+
+            actor_names:
+                self.actor_names.iter()
+                .map(|(name, ids)| (name.clone(), ids.iter().cloned()
+                                                  .filter(|id| actor_ids.contains(id))
+                                                  .collect::<BTreeSet<_>>()))
+                .filter(|(_, ids)| !ids.is_empty())
+                .collect(),
+            use_case_titles:
+                self.use_case_titles.iter()
+                .map(|(title, ids)| (title.clone(), ids.iter().cloned()
+                                                    .filter(|id| use_case_ids.contains(id))
+                                                    .collect::<BTreeSet<_>>()))
+                .filter(|(_, ids)| !ids.is_empty())
+                .collect(),
+            actor_keys:
+                self.actor_keys.iter()
+                .map(|(key, ids)| (key.clone(), ids.iter().cloned()
+                                                .filter(|id| actor_ids.contains(id))
+                                                .collect::<BTreeSet<_>>()))
+                .filter(|(_, ids)| !ids.is_empty())
+                .collect(),
+            use_case_keys:
+                self.use_case_keys.iter()
+                .map(|(key, ids)| (key.clone(), ids.iter().cloned()
+                                                .filter(|id| use_case_ids.contains(id))
+                                                .collect::<BTreeSet<_>>()))
+                .filter(|(_, ids)| !ids.is_empty())
+                .collect(),
+            unique_names: self.unique_names,
+            invariant_checks: self.invariant_checks,
+            event_recording: false,
+            events: Vec::new(),
+        };
+        subdiagram.assert_invariants();
+        subdiagram
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use use_case_diagram::{Actor, ActorId, SystemBoundary, SystemBoundaryId, UseCase,
+                           UseCaseDiagram};
+
+    use std::collections::HashSet;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_reachable_subdiagram() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_association(a, pc).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+
+        let roots = [s].iter().cloned().collect();
+        let subdiagram = diagram.reachable_subdiagram(&roots);
+
+        assert_eq!(subdiagram.actors().map(|(id, _)| id).collect::<Vec<_>>(), vec![s]);
+        assert_eq!(subdiagram.use_cases().map(|(id, _)| id).collect::<Vec<_>>(), vec![pc]);
+        assert_eq!(subdiagram.associations().collect::<Vec<_>>(), vec![(s, pc)]);
+        assert_eq!(subdiagram.use_case(pc), diagram.use_case(pc));
+
+        let subdiagram = diagram.reachable_subdiagram(&HashSet::new());
+        assert_eq!(subdiagram.actors().len(), 0);
+        assert_eq!(subdiagram.use_cases().len(), 0);
+    }
+
+    #[test]
+    fn test_reachable_subdiagram_includes() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        let vc = diagram.insert_use_case(UseCase::new(Rc::from("Verify credentials"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        diagram.insert_include(pc, li).unwrap();
+        diagram.insert_include(li, vc).unwrap();
+        diagram.insert_include(bs, vc).unwrap();
+
+        let roots = [s].iter().cloned().collect();
+        let subdiagram = diagram.reachable_subdiagram(&roots);
+
+        assert_eq!(subdiagram.use_cases().map(|(id, _)| id).collect::<HashSet<_>>(),
+                   [pc, li, vc].iter().cloned().collect());
+        assert_eq!(subdiagram.includes().collect::<HashSet<_>>(),
+                   [(pc, li), (li, vc)].iter().cloned().collect());
+        assert_eq!(subdiagram.associations().collect::<Vec<_>>(), vec![(s, pc)]);
+    }
+
+
+    #[test]
+    fn test_system_boundary_subdiagram() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+        let moderation = diagram.insert_system_boundary(SystemBoundary{
+            name: Rc::from("Moderation"),
+            use_cases: [bs].iter().cloned().collect(),
+        }).unwrap();
+        let journal = diagram.insert_system_boundary(SystemBoundary{
+            name: Rc::from("Journal"),
+            use_cases: [bs, pc].iter().cloned().collect(),
+        }).unwrap();
+
+        let subdiagram = diagram.system_boundary_subdiagram(moderation).unwrap();
+        assert_eq!(subdiagram.actors().map(|(id, _)| id).collect::<Vec<_>>(), vec![a]);
+        assert_eq!(subdiagram.use_cases().map(|(id, _)| id).collect::<Vec<_>>(), vec![bs]);
+        assert_eq!(subdiagram.system_boundary(journal).unwrap().use_cases,
+                   [bs].iter().cloned().collect());
+
+        let roots = [s].iter().cloned().collect();
+        let subdiagram = diagram.reachable_subdiagram(&roots);
+        assert_eq!(subdiagram.system_boundaries().map(|(id, _)| id).collect::<Vec<_>>(),
+                   vec![journal]);
+
+        assert!(diagram.system_boundary_subdiagram(SystemBoundaryId(42)).is_none());
+    }
+
+
+    #[test]
+    fn test_subdiagram_for_actor() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+
+        let subdiagram = diagram.subdiagram_for_actor(a).unwrap();
+        assert_eq!(subdiagram.actors().map(|(id, _)| id).collect::<Vec<_>>(), vec![a]);
+        assert_eq!(subdiagram.use_cases().map(|(id, _)| id).collect::<Vec<_>>(), vec![bs]);
+        assert_eq!(subdiagram.associations().collect::<Vec<_>>(), vec![(a, bs)]);
+        assert!(diagram.subdiagram_for_actor(ActorId(42)).is_none());
+    }
+
+    #[test]
+    fn test_filter() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_association(a, pc).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+
+        let subdiagram = diagram.filter(|_, _| true,
+                                        |_, use_case| &*use_case.title != "Post comment");
+        assert_eq!(subdiagram.actors().len(), 2);
+        assert_eq!(subdiagram.use_cases().map(|(id, _)| id).collect::<Vec<_>>(), vec![bs]);
+        assert_eq!(subdiagram.associations().collect::<Vec<_>>(), vec![(a, bs)]);
+    }
+}
@@ -0,0 +1,100 @@
+use std::cmp;
+use std::fmt::Write;
+
+use use_case_diagram::UseCaseDiagram;
+
+impl<S> UseCaseDiagram<S> where S: AsRef<str> {
+    /// Format the associations of this diagram as an aligned table for
+    /// terminal output, with actors down the side, use cases across the top,
+    /// and an `X` for every association. Rows and columns are ordered as by
+    /// `actors_in_order` and `use_cases_in_order`. Long names and titles are
+    /// truncated with `…` to keep lines within `max_width` characters where
+    /// possible; every column keeps room for at least one character.
+    pub fn format_matrix(&self, max_width: usize) -> String {
+        let actors = self.actors_in_order();
+        let use_cases = self.use_cases_in_order();
+
+        let header_width =
+            actors.iter()
+            .map(|&(_, actor)| actor.name.as_ref().chars().count())
+            .max().unwrap_or(0)
+            .min(cmp::max(1, max_width / 2));
+        let available = max_width.saturating_sub(header_width + 3 * use_cases.len());
+        let column_cap = cmp::max(1, available / cmp::max(1, use_cases.len()));
+        let column_widths =
+            use_cases.iter()
+            .map(|&(_, use_case)| cmp::max(1, cmp::min(column_cap,
+                                                       use_case.title.as_ref().chars().count())))
+            .collect::<Vec<_>>();
+
+        let mut table = String::new();
+        table.push_str(&" ".repeat(header_width));
+        for (&(_, use_case), &width) in use_cases.iter().zip(&column_widths) {
+            write!(table, " | {:<w$}", truncate(use_case.title.as_ref(), width), w = width)
+                .unwrap();
+        }
+        table.push('\n');
+        table.push_str(&"-".repeat(header_width));
+        for &width in &column_widths {
+            write!(table, "-+-{}", "-".repeat(width)).unwrap();
+        }
+        table.push('\n');
+        for &(actor_id, actor) in &actors {
+            write!(table, "{:<w$}", truncate(actor.name.as_ref(), header_width),
+                   w = header_width).unwrap();
+            for (&(use_case_id, _), &width) in use_cases.iter().zip(&column_widths) {
+                let mark =
+                    if self.associations.contains_key(&(actor_id, use_case_id)) { "X" }
+                    else { "" };
+                write!(table, " | {:^w$}", mark, w = width).unwrap();
+            }
+            table.push('\n');
+        }
+        table
+    }
+}
+
+/// Truncate a string to at most `width` characters, marking truncation with
+/// an ellipsis.
+fn truncate(s: &str, width: usize) -> String {
+    if s.chars().count() <= width {
+        s.to_string()
+    } else {
+        let mut truncated = s.chars().take(width.saturating_sub(1)).collect::<String>();
+        truncated.push('…');
+        truncated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use use_case_diagram::{Actor, UseCase, UseCaseDiagram};
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_format_matrix() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let s = diagram.insert_actor(Actor::new(Rc::from("Subscriber"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("Post comment"))).unwrap();
+        diagram.insert_association(a, bs).unwrap();
+        diagram.insert_association(a, pc).unwrap();
+        diagram.insert_association(s, pc).unwrap();
+
+        assert_eq!(diagram.format_matrix(80), concat!(
+            "              | Ban subscriber | Post comment\n",
+            "--------------+----------------+-------------\n",
+            "Administrator |       X        |      X      \n",
+            "Subscriber    |                |      X      \n",
+        ));
+
+        assert_eq!(diagram.format_matrix(33), concat!(
+            "              | Ban su… | Post c…\n",
+            "--------------+---------+--------\n",
+            "Administrator |    X    |    X   \n",
+            "Subscriber    |         |    X   \n",
+        ));
+    }
+}
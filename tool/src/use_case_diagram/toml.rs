@@ -0,0 +1,641 @@
+//! Diagram definitions in TOML, such as in the `butterfly.toml` file of a
+//! project:
+//!
+//! ```toml
+//! [actors.Administrator]
+//! description = "Runs the site"
+//!
+//! [use_cases."Ban subscriber"]
+//!
+//! [[associations]]
+//! actor = "Administrator"
+//! use_case = "Ban subscriber"
+//! label = "bans"
+//! ```
+//!
+//! Actors and use cases are tables keyed by name and title, with an
+//! optional `description`, and associations are an array of tables. The
+//! whole of TOML is read except that dates, times and numbers are not
+//! interpreted, so other tables of a project file may hold anything.
+
+use use_case_diagram::parser::{error, ParseError, ParseErrorKind, Position};
+use use_case_diagram::source_map::span;
+use use_case_diagram::{Actor, Association, SourceMap, UseCase, UseCaseDiagram};
+
+/// Read a diagram with unique names from the top level of a TOML document.
+pub fn from_toml<S>(text: &str) -> Result<UseCaseDiagram<S>, ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    from_toml_with_source_map(text, "").map(|(diagram, _)| diagram)
+}
+
+/// Read a diagram from a TOML document in the file like `from_toml`, and
+/// map every actor and use case to its key and every association to its
+/// table.
+pub fn from_toml_with_source_map<S>(text: &str, file: &str)
+                                    -> Result<(UseCaseDiagram<S>, SourceMap<S>), ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    let root = parse_document(text)?;
+    diagram(&root, file)
+}
+
+/// Read a diagram with unique names from the table at the given dotted path
+/// of bare keys in a TOML document, e.g. `diagrams.moderation`.
+pub fn from_toml_table<S>(text: &str, path: &str) -> Result<UseCaseDiagram<S>, ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    from_toml_table_with_source_map(text, path, "").map(|(diagram, _)| diagram)
+}
+
+/// Read a diagram from a table of a TOML document in the file like
+/// `from_toml_table`, with a source map like `from_toml_with_source_map`.
+pub fn from_toml_table_with_source_map<S>(text: &str, path: &str, file: &str)
+                                          -> Result<(UseCaseDiagram<S>, SourceMap<S>), ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    let root = parse_document(text)?;
+    let mut node = &root;
+    for key in path.split('.') {
+        node = match node.value {
+            Value::Table(ref table) => table.get(key),
+            _ => None,
+        }.ok_or_else(|| {
+            error(Position{line: 1, column: 1}, ParseErrorKind::MissingKey(path.to_string()))
+        })?;
+    }
+    diagram(node, file)
+}
+
+fn diagram<S>(node: &Node, file: &str) -> Result<(UseCaseDiagram<S>, SourceMap<S>), ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    let mut diagram = UseCaseDiagram::with_unique_names();
+    let mut source_map = SourceMap::new();
+    let sections = fields(node, &["actors", "use_cases", "associations"])?;
+    for (name, node) in elements(sections[0])? {
+        let mut actor = Actor::new(S::from(name));
+        actor.description = fields(node, &["description"])?[0].map(string).transpose()?
+            .map(S::from);
+        let actor_id = diagram.insert_actor(actor)
+            .map_err(|err| error(node.position, ParseErrorKind::Name(err)))?;
+        source_map.actors.insert(actor_id, span(file, node.key_position));
+    }
+    for (title, node) in elements(sections[1])? {
+        let mut use_case = UseCase::new(S::from(title));
+        use_case.description = fields(node, &["description"])?[0].map(string).transpose()?
+            .map(S::from);
+        let use_case_id = diagram.insert_use_case(use_case)
+            .map_err(|err| error(node.position, ParseErrorKind::Name(err)))?;
+        source_map.use_cases.insert(use_case_id, span(file, node.key_position));
+    }
+    let associations = match sections[2].map(|node| (node, &node.value)) {
+        None => &[][..],
+        Some((_, Value::Array(nodes))) => nodes,
+        Some((node, _)) => return Err(invalid(node.position, "expected an array")),
+    };
+    for node in associations {
+        let fields = fields(node, &["actor", "use_case", "label"])?;
+        let required = |index: usize, key: &str| match fields[index] {
+            Some(field) => string(field),
+            None => Err(error(node.position, ParseErrorKind::MissingKey(key.to_string()))),
+        };
+        let actor = required(0, "actor")?;
+        let use_case = required(1, "use_case")?;
+        let actor_id = diagram.actor_by_name(actor).map(|(actor_id, _)| actor_id)
+            .ok_or_else(|| {
+                error(node.position, ParseErrorKind::UnknownActorName(actor.to_string()))
+            })?;
+        let use_case_id = diagram.use_case_by_title(use_case).map(|(use_case_id, _)| use_case_id)
+            .ok_or_else(|| {
+                error(node.position, ParseErrorKind::UnknownUseCaseTitle(use_case.to_string()))
+            })?;
+        let label = fields[2].map(string).transpose()?.map(S::from);
+        let association = Association{label, ..Association::default()};
+        diagram.insert_association_with(actor_id, use_case_id, association).unwrap();
+        source_map.associations.insert((actor_id, use_case_id), span(file, node.position));
+    }
+    Ok((diagram, source_map))
+}
+
+/// The values of the table under the given keys, failing on other keys.
+fn fields<'a>(node: &'a Node, keys: &[&str]) -> Result<Vec<Option<&'a Node>>, ParseError> {
+    let table = match node.value {
+        Value::Table(ref table) => table,
+        _ => return Err(invalid(node.position, "expected a table")),
+    };
+    let mut fields = vec![None; keys.len()];
+    for (key, value) in &table.entries {
+        match keys.iter().position(|k| k == key) {
+            Some(index) => fields[index] = Some(value),
+            None => return Err(error(value.key_position,
+                                     ParseErrorKind::UnknownKey(key.to_string()))),
+        }
+    }
+    Ok(fields)
+}
+
+/// The entries of a table of actors or use cases, which may be missing.
+fn elements(node: Option<&Node>) -> Result<&[(String, Node)], ParseError> {
+    match node {
+        None => Ok(&[]),
+        Some(&Node{value: Value::Table(ref table), ..}) => Ok(&table.entries),
+        Some(node) => Err(invalid(node.position, "expected a table")),
+    }
+}
+
+fn string(node: &Node) -> Result<&str, ParseError> {
+    match node.value {
+        Value::String(ref s) => Ok(s),
+        _ => Err(invalid(node.position, "expected a string")),
+    }
+}
+
+fn invalid(position: Position, message: &str) -> ParseError {
+    error(position, ParseErrorKind::InvalidToml(message.to_string()))
+}
+
+/// A value and where it starts, and where its key starts.
+struct Node {
+    position: Position,
+    key_position: Position,
+    value: Value,
+}
+
+enum Value {
+    String(String),
+
+    /// A boolean, number, date or time.
+    Other,
+
+    Array(Vec<Node>),
+    Table(Table),
+}
+
+#[derive(Default)]
+struct Table {
+    entries: Vec<(String, Node)>,
+
+    /// Whether a header defines the table, so that no other may.
+    defined: bool,
+
+    /// Whether the table is inline, so that it cannot be extended.
+    inline: bool,
+
+    /// Whether the table is in an array made by `[[...]]` headers, so that
+    /// more may be appended.
+    appendable: bool,
+}
+
+impl Table {
+    fn get(&self, key: &str) -> Option<&Node> {
+        self.entries.iter().find(|&(k, _)| k == key).map(|(_, node)| node)
+    }
+
+    fn get_mut(&mut self, key: &str) -> Option<&mut Node> {
+        self.entries.iter_mut().find(|(k, _)| k == key).map(|(_, node)| node)
+    }
+}
+
+type Key = (String, Position);
+
+fn parse_document(text: &str) -> Result<Node, ParseError> {
+    let start = Position{line: 1, column: 1};
+    let mut root = Table::default();
+    let mut current = Vec::<Key>::new();
+    let mut parser = Parser{text, offset: 0, position: start};
+    loop {
+        parser.skip_blank_lines();
+        if parser.rest().is_empty() {
+            return Ok(Node{position: start, key_position: start, value: Value::Table(root)});
+        }
+        let header = parser.position;
+        let array = parser.eat("[[");
+        if array || parser.eat("[") {
+            let path = parser.keys()?;
+            parser.expect(if array { "]]" } else { "]" })?;
+            let (&(ref key, key_position), parent) = path.split_last().unwrap();
+            let table = descend(&mut root, parent)?;
+            let node = match table.get_mut(key) {
+                Some(node) => node,
+                None => {
+                    let value = if array {
+                        Value::Array(Vec::new())
+                    } else {
+                        Value::Table(Table::default())
+                    };
+                    table.entries.push((key.clone(), Node{position: header, key_position, value}));
+                    &mut table.entries.last_mut().unwrap().1
+                },
+            };
+            match node.value {
+                Value::Array(ref mut nodes) if array && nodes.iter().all(is_appendable) => {
+                    let table = Table{defined: true, appendable: true, ..Table::default()};
+                    nodes.push(Node{position: header, key_position, value: Value::Table(table)});
+                },
+                Value::Table(ref mut table) if !array && !table.defined && !table.inline =>
+                    table.defined = true,
+                _ => return Err(invalid(key_position, "key is already defined")),
+            }
+            current = path;
+        } else {
+            let table = descend(&mut root, &current)?;
+            parser.key_value(table)?;
+        }
+        parser.expect_end_of_line()?;
+    }
+}
+
+fn is_appendable(node: &Node) -> bool {
+    matches!(node.value, Value::Table(Table{appendable: true, ..}))
+}
+
+/// The table at the path, creating tables that are missing. Arrays made by
+/// `[[...]]` headers stand for their last table.
+fn descend<'a>(mut table: &'a mut Table, path: &[Key]) -> Result<&'a mut Table, ParseError> {
+    for &(ref key, position) in path {
+        let index = match table.entries.iter().position(|(k, _)| k == key) {
+            Some(index) => index,
+            None => {
+                let node = Node{position, key_position: position,
+                                value: Value::Table(Table::default())};
+                table.entries.push((key.clone(), node));
+                table.entries.len() - 1
+            },
+        };
+        let node = &mut table.entries[index].1;
+        table = match node.value {
+            Value::Table(ref mut table) if !table.inline => table,
+            Value::Array(ref mut nodes) => match nodes.last_mut() {
+                Some(&mut Node{value: Value::Table(ref mut table), ..}) if table.appendable =>
+                    table,
+                _ => return Err(invalid(position, "key is already defined")),
+            },
+            _ => return Err(invalid(position, "key is already defined")),
+        };
+    }
+    Ok(table)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    offset: usize,
+    position: Position,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.offset..]
+    }
+
+    fn advance(&mut self, length: usize) {
+        for c in self.text[self.offset..self.offset + length].chars() {
+            if c == '\n' {
+                self.position.line += 1;
+                self.position.column = 1;
+            } else {
+                self.position.column += 1;
+            }
+        }
+        self.offset += length;
+    }
+
+    fn eat(&mut self, s: &str) -> bool {
+        let found = self.rest().starts_with(s);
+        if found {
+            self.advance(s.len());
+        }
+        found
+    }
+
+    fn expect(&mut self, s: &str) -> Result<(), ParseError> {
+        self.skip_spaces();
+        if self.eat(s) {
+            Ok(())
+        } else {
+            Err(invalid(self.position, &format!("expected {}", s)))
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        let rest = self.rest();
+        let length = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+        self.advance(length);
+    }
+
+    fn skip_comment(&mut self) {
+        if self.rest().starts_with('#') {
+            let length = self.rest().find('\n').unwrap_or(self.rest().len());
+            self.advance(length);
+        }
+    }
+
+    /// Skip whitespace, newlines and comments.
+    fn skip_blank_lines(&mut self) {
+        loop {
+            self.skip_spaces();
+            self.skip_comment();
+            if !self.eat("\n") && !self.eat("\r\n") {
+                return;
+            }
+        }
+    }
+
+    fn expect_end_of_line(&mut self) -> Result<(), ParseError> {
+        self.skip_spaces();
+        self.skip_comment();
+        if self.rest().is_empty() || self.eat("\n") || self.eat("\r\n") {
+            Ok(())
+        } else {
+            Err(invalid(self.position, "expected end of line"))
+        }
+    }
+
+    /// A dotted key.
+    fn keys(&mut self) -> Result<Vec<Key>, ParseError> {
+        let mut keys = Vec::new();
+        loop {
+            self.skip_spaces();
+            let position = self.position;
+            let key = match self.rest().chars().next() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let rest = self.rest();
+                    let length = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_' &&
+                                           c != '-')
+                        .unwrap_or(rest.len());
+                    if length == 0 {
+                        return Err(invalid(position, "expected a key"));
+                    }
+                    self.advance(length);
+                    rest[..length].to_string()
+                },
+            };
+            keys.push((key, position));
+            self.skip_spaces();
+            if !self.eat(".") {
+                return Ok(keys);
+            }
+        }
+    }
+
+    /// A key, `=` and a value, inserted into the table.
+    fn key_value(&mut self, table: &mut Table) -> Result<(), ParseError> {
+        let keys = self.keys()?;
+        self.expect("=")?;
+        self.skip_spaces();
+        let mut value = self.value()?;
+        let (&(ref key, key_position), parent) = keys.split_last().unwrap();
+        value.key_position = key_position;
+        let table = descend(table, parent)?;
+        if table.get(key).is_some() {
+            return Err(invalid(key_position, "key is already defined"));
+        }
+        table.entries.push((key.clone(), value));
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Node, ParseError> {
+        let position = self.position;
+        let value = match self.rest().chars().next() {
+            Some('"') => Value::String(self.basic_string()?),
+            Some('\'') => Value::String(self.literal_string()?),
+            Some('[') => {
+                self.advance(1);
+                let mut nodes = Vec::new();
+                loop {
+                    self.skip_blank_lines();
+                    if self.eat("]") {
+                        break;
+                    }
+                    nodes.push(self.value()?);
+                    self.skip_blank_lines();
+                    if !self.eat(",") {
+                        self.skip_blank_lines();
+                        if !self.eat("]") {
+                            return Err(invalid(self.position, "expected , or ]"));
+                        }
+                        break;
+                    }
+                }
+                Value::Array(nodes)
+            },
+            Some('{') => {
+                self.advance(1);
+                let mut table = Table::default();
+                self.skip_spaces();
+                if !self.eat("}") {
+                    loop {
+                        self.key_value(&mut table)?;
+                        self.skip_spaces();
+                        if self.eat("}") {
+                            break;
+                        }
+                        self.expect(",")?;
+                    }
+                }
+                table.inline = true;
+                Value::Table(table)
+            },
+            _ => {
+                let rest = self.rest();
+                let length = rest.find(|c: char| {
+                    !c.is_ascii_alphanumeric() && !"_:.+-".contains(c)
+                }).unwrap_or(rest.len());
+                let token = &rest[..length];
+                let scalar = ["true", "false", "inf", "+inf", "-inf", "nan", "+nan", "-nan"]
+                    .contains(&token) ||
+                    token.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-');
+                if !scalar {
+                    return Err(invalid(position, "expected a value"));
+                }
+                self.advance(length);
+                Value::Other
+            },
+        };
+        Ok(Node{position, key_position: position, value})
+    }
+
+    fn basic_string(&mut self) -> Result<String, ParseError> {
+        let start = self.position;
+        let multiline = self.eat("\"\"\"");
+        if multiline {
+            self.eat("\r\n");
+            self.eat("\n");
+        } else {
+            self.advance(1);
+        }
+        let mut value = String::new();
+        loop {
+            let c = match self.rest().chars().next() {
+                Some(c) => c,
+                None => return Err(error(start, ParseErrorKind::UnterminatedString)),
+            };
+            if multiline && self.eat("\"\"\"") {
+                return Ok(value);
+            }
+            if !multiline && c == '"' {
+                self.advance(1);
+                return Ok(value);
+            }
+            if !multiline && c == '\n' {
+                return Err(error(start, ParseErrorKind::UnterminatedString));
+            }
+            if c != '\\' {
+                self.advance(c.len_utf8());
+                value.push(c);
+                continue;
+            }
+            let escape = self.position;
+            self.advance(1);
+            let escaped = self.rest().chars().next()
+                .ok_or_else(|| error(start, ParseErrorKind::UnterminatedString))?;
+            self.advance(escaped.len_utf8());
+            let digits = match escaped {
+                'b' => { value.push('\u{8}'); continue },
+                't' => { value.push('\t'); continue },
+                'n' => { value.push('\n'); continue },
+                'f' => { value.push('\u{c}'); continue },
+                'r' => { value.push('\r'); continue },
+                '"' => { value.push('"'); continue },
+                '\\' => { value.push('\\'); continue },
+                'u' => 4,
+                'U' => 8,
+                c if multiline && c.is_whitespace() => {
+                    let rest = self.rest();
+                    let length = rest.len() - rest.trim_start().len();
+                    self.advance(length);
+                    continue;
+                },
+                c => return Err(error(escape, ParseErrorKind::InvalidEscape(c))),
+            };
+            let code = self.rest().get(..digits)
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .and_then(char::from_u32)
+                .ok_or_else(|| error(escape, ParseErrorKind::InvalidEscape(escaped)))?;
+            self.advance(digits);
+            value.push(code);
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, ParseError> {
+        let start = self.position;
+        let (delimiter, multiline) = if self.eat("'''") {
+            self.eat("\r\n");
+            self.eat("\n");
+            ("'''", true)
+        } else {
+            self.advance(1);
+            ("'", false)
+        };
+        let rest = self.rest();
+        match rest.find(delimiter) {
+            Some(length) if multiline || !rest[..length].contains('\n') => {
+                self.advance(length + delimiter.len());
+                Ok(rest[..length].to_string())
+            },
+            _ => Err(error(start, ParseErrorKind::UnterminatedString)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    use use_case_diagram::Span;
+
+    #[test]
+    fn test_from_toml() {
+        let text = concat!(
+            "# Moderation\n",
+            "[actors.Administrator]\n",
+            "description = \"Runs \\\"the\\\" site\"\n",
+            "\n",
+            "[actors.'Site moderator']\n",
+            "\n",
+            "[use_cases]\n",
+            "\"Ban subscriber\" = {}\n",
+            "\"Post comment\".description = '''\n",
+            "Writes a comment'''\n",
+            "\n",
+            "[[associations]]\n",
+            "actor = \"Administrator\"  # who\n",
+            "use_case = \"Ban subscriber\"\n",
+            "label = \"bans\"\n",
+            "[[associations]]\n",
+            "actor = \"Site moderator\"\n",
+            "use_case = \"Ban subscriber\"\n",
+        );
+        let diagram = from_toml::<Rc<str>>(text).unwrap();
+        let (a, administrator) = diagram.actor_by_name("Administrator").unwrap();
+        assert_eq!(administrator.description.as_deref(), Some("Runs \"the\" site"));
+        let (m, _) = diagram.actor_by_name("Site moderator").unwrap();
+        let (bs, _) = diagram.use_case_by_title("Ban subscriber").unwrap();
+        let (_, post) = diagram.use_case_by_title("Post comment").unwrap();
+        assert_eq!(post.description.as_deref(), Some("Writes a comment"));
+        assert_eq!(diagram.associations().collect::<Vec<_>>(), vec![(a, bs), (m, bs)]);
+        assert_eq!(diagram.association(a, bs).unwrap().label.as_deref(), Some("bans"));
+
+        let project = concat!(
+            "[package]\n",
+            "name = \"forum\"\n",
+            "version = 1.5\n",
+            "released = 2017-03-04T10:00:00Z\n",
+            "authors = [\n",
+            "  \"A\", # first\n",
+            "  \"B\",\n",
+            "]\n",
+            "\n",
+            "[diagrams.moderation.use_cases.\"Ban subscriber\"]\n",
+            "[diagrams.moderation.actors.Administrator]\n",
+            "[[diagrams.moderation.associations]]\n",
+            "actor = \"Administrator\"\n",
+            "use_case = \"Ban subscriber\"\n",
+        );
+        let diagram = from_toml_table::<Rc<str>>(project, "diagrams.moderation").unwrap();
+        assert_eq!(diagram.associations().count(), 1);
+        assert_eq!(from_toml_table::<Rc<str>>(project, "diagrams.other").unwrap_err().kind,
+                   ParseErrorKind::MissingKey("diagrams.other".to_string()));
+    }
+
+    #[test]
+    fn test_from_toml_with_source_map() {
+        let (diagram, source_map) = from_toml_with_source_map::<Rc<str>>(concat!(
+            "[actors.Administrator]\n",
+            "[use_cases]\n",
+            "\"Ban subscriber\" = { description = \"Keep the peace\" }\n",
+            "\n",
+            "[[associations]]\n",
+            "actor = \"Administrator\"\n",
+            "use_case = \"Ban subscriber\"\n",
+        ), "butterfly.toml").unwrap();
+        let (administrator, _) = diagram.actor_by_name("Administrator").unwrap();
+        let (ban, _) = diagram.use_case_by_title("Ban subscriber").unwrap();
+        let span = |line, column| Span{file: Rc::from("butterfly.toml"), line, column};
+        assert_eq!(source_map.actors[&administrator], span(1, 9));
+        assert_eq!(source_map.use_cases[&ban], span(3, 1));
+        assert_eq!(source_map.associations[&(administrator, ban)], span(5, 1));
+    }
+
+    #[test]
+    fn test_from_toml_errors() {
+        fn err(text: &str) -> (usize, usize, ParseErrorKind) {
+            let err = from_toml::<Rc<str>>(text).unwrap_err();
+            (err.position.line, err.position.column, err.kind)
+        }
+        use self::ParseErrorKind::*;
+        assert_eq!(err("[actors.A]\n[actors.A]\n"),
+                   (2, 9, InvalidToml("key is already defined".to_string())));
+        assert_eq!(err("[actors]\nA = {}\nA = {}\n"),
+                   (3, 1, InvalidToml("key is already defined".to_string())));
+        assert_eq!(err("a = \"x\" b\n"), (1, 9, InvalidToml("expected end of line".to_string())));
+        assert_eq!(err("a = \"x\\q\"\n"), (1, 7, InvalidEscape('q')));
+        assert_eq!(err("a = \"x\n"), (1, 5, UnterminatedString));
+        assert_eq!(err("roles = []\n"), (1, 1, UnknownKey("roles".to_string())));
+        assert_eq!(err("[[associations]]\nactor = \"A\"\n"),
+                   (1, 1, MissingKey("use_case".to_string())));
+        assert_eq!(err("[actors.A]\n[[associations]]\nactor = \"A\"\nuse_case = \"U\"\n"),
+                   (2, 1, UnknownUseCaseTitle("U".to_string())));
+        assert_eq!(err("[actors]\nA.description = 1\n"),
+                   (2, 17, InvalidToml("expected a string".to_string())));
+    }
+}
@@ -0,0 +1,967 @@
+//! UML models in XMI 2.1, the interchange format of modelers such as
+//! Enterprise Architect and MagicDraw.
+//!
+//! Every element gets an `xmi:id` derived from the identifiers of the
+//! elements it stands for, e.g. `actor_0`, `association_0_1` or
+//! `include_1_2`, so exporting the same diagram twice gives the same
+//! document and modelers recognize elements they imported before.
+//!
+//! Models kept in such modelers can be imported the other way, to bring
+//! them into the code generation pipeline. Exports of whole enterprise
+//! models can be read in chunks with `XmiReader`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io;
+use std::rc::Rc;
+
+use use_case_diagram::parser::{error, ParseError, ParseErrorKind, Position};
+use use_case_diagram::source_map::span;
+use use_case_diagram::xml::{escape, Event, Reader, StreamReader};
+use use_case_diagram::{Actor, ActorId, Association, Event as DiagramEvent, Extend, ExtensionPoint,
+                       Multiplicity, Navigability, Note, NoteAnchor, SourceMap, SystemBoundary,
+                       UseCase, UseCaseDiagram, UseCaseId};
+
+/// The namespace of XMI 2.1.
+pub const XMI_NAMESPACE: &str = "http://schema.omg.org/spec/XMI/2.1";
+
+/// The namespace of UML 2.1.
+pub const UML_NAMESPACE: &str = "http://schema.omg.org/spec/UML/2.1";
+
+/// Write the diagram as an XMI document holding a UML model with the given
+/// name. Actors, use cases, associations and dependencies are packaged
+/// elements of the model, in the order of `actors_in_order` and
+/// `use_cases_in_order` and then by identifier. Use cases own their
+/// includes, extends and extension points, and children own their
+/// generalizations. Associations own both ends, with their multiplicities,
+/// and list their navigable ends. System boundaries are components that are
+/// the subjects of their use cases, and descriptions and notes are comments.
+pub fn to_xmi<W, S>(diagram: &UseCaseDiagram<S>, name: &str, w: &mut W) -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    writeln!(w, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(w, "<xmi:XMI xmi:version=\"2.1\" xmlns:xmi=\"{}\" xmlns:uml=\"{}\">",
+             XMI_NAMESPACE, UML_NAMESPACE)?;
+    writeln!(w, "  <uml:Model xmi:type=\"uml:Model\" xmi:id=\"model\" name=\"{}\">",
+             escape(name))?;
+
+    for (actor_id, actor) in diagram.actors_in_order() {
+        writeln!(w, "    <packagedElement xmi:type=\"uml:Actor\" xmi:id=\"actor_{}\" \
+                     name=\"{}\">", actor_id, escape(actor.name.as_ref()))?;
+        generate_comment(w, &actor.description, &format!("actor_{}", actor_id))?;
+        let mut parents = diagram.actor_parents(actor_id).collect::<Vec<_>>();
+        parents.sort();
+        for parent in parents {
+            writeln!(w, "      <generalization xmi:type=\"uml:Generalization\" \
+                         xmi:id=\"generalization_actor_{}_{}\" general=\"actor_{}\"/>",
+                     actor_id, parent, parent)?;
+        }
+        writeln!(w, "    </packagedElement>")?;
+    }
+
+    let mut system_boundaries = diagram.system_boundaries().collect::<Vec<_>>();
+    system_boundaries.sort_by_key(|&(system_boundary_id, _)| system_boundary_id);
+    for &(system_boundary_id, system_boundary) in &system_boundaries {
+        let use_cases = system_boundary.use_cases.iter()
+            .map(|use_case_id| format!("use_case_{}", use_case_id))
+            .collect::<Vec<_>>();
+        writeln!(w, "    <packagedElement xmi:type=\"uml:Component\" \
+                     xmi:id=\"system_boundary_{}\" name=\"{}\" useCase=\"{}\"/>",
+                 system_boundary_id, escape(system_boundary.name.as_ref()),
+                 use_cases.join(" "))?;
+    }
+
+    for (use_case_id, use_case) in diagram.use_cases_in_order() {
+        let subjects = system_boundaries.iter()
+            .filter(|&&(_, system_boundary)| system_boundary.use_cases.contains(&use_case_id))
+            .map(|&(system_boundary_id, _)| format!("system_boundary_{}", system_boundary_id))
+            .collect::<Vec<_>>();
+        let subject = if subjects.is_empty() {
+            String::new()
+        } else {
+            format!(" subject=\"{}\"", subjects.join(" "))
+        };
+        writeln!(w, "    <packagedElement xmi:type=\"uml:UseCase\" xmi:id=\"use_case_{}\" \
+                     name=\"{}\"{}>", use_case_id, escape(use_case.title.as_ref()), subject)?;
+        generate_comment(w, &use_case.description, &format!("use_case_{}", use_case_id))?;
+        let mut extension_points = diagram.extension_points_of_use_case(use_case_id)
+            .collect::<Vec<_>>();
+        extension_points.sort_by_key(|&(extension_point_id, _)| extension_point_id);
+        for (extension_point_id, extension_point) in extension_points {
+            writeln!(w, "      <extensionPoint xmi:type=\"uml:ExtensionPoint\" \
+                         xmi:id=\"extension_point_{}\" name=\"{}\"/>",
+                     extension_point_id, escape(extension_point.name.as_ref()))?;
+        }
+        let mut included = diagram.included_use_cases(use_case_id).collect::<Vec<_>>();
+        included.sort();
+        for included in included {
+            writeln!(w, "      <include xmi:type=\"uml:Include\" xmi:id=\"include_{}_{}\" \
+                         addition=\"use_case_{}\"/>", use_case_id, included, included)?;
+        }
+        let mut extends = diagram.extends()
+            .filter(|extend| extend.extension == use_case_id)
+            .collect::<Vec<_>>();
+        extends.sort();
+        for extend in extends {
+            let location = match extend.extension_point {
+                Some(id) => format!(" extensionLocation=\"extension_point_{}\"", id),
+                None => String::new(),
+            };
+            writeln!(w, "      <extend xmi:type=\"uml:Extend\" xmi:id=\"extend_{}_{}\" \
+                         extendedCase=\"use_case_{}\"{}/>",
+                     use_case_id, extend.base, extend.base, location)?;
+        }
+        let mut parents = diagram.use_case_parents(use_case_id).collect::<Vec<_>>();
+        parents.sort();
+        for parent in parents {
+            writeln!(w, "      <generalization xmi:type=\"uml:Generalization\" \
+                         xmi:id=\"generalization_use_case_{}_{}\" general=\"use_case_{}\"/>",
+                     use_case_id, parent, parent)?;
+        }
+        writeln!(w, "    </packagedElement>")?;
+    }
+
+    let mut associations = diagram.associations().collect::<Vec<_>>();
+    associations.sort();
+    for (actor_id, use_case_id) in associations {
+        let association = diagram.association(actor_id, use_case_id).unwrap();
+        let id = association_xmi_id(actor_id, use_case_id);
+        let name = match association.label {
+            Some(ref label) => format!(" name=\"{}\"", escape(label.as_ref())),
+            None => String::new(),
+        };
+        let navigable = match association.navigability {
+            Navigability::Undirected => format!("{0}_actor {0}_use_case", id),
+            Navigability::ToUseCase => format!("{}_use_case", id),
+            Navigability::ToActor => format!("{}_actor", id),
+        };
+        writeln!(w, "    <packagedElement xmi:type=\"uml:Association\" xmi:id=\"{0}\"{1} \
+                     memberEnd=\"{0}_actor {0}_use_case\" navigableOwnedEnd=\"{2}\">",
+                 id, name, navigable)?;
+        generate_end(w, &id, "actor", &format!("actor_{}", actor_id),
+                     association.actor_multiplicity)?;
+        generate_end(w, &id, "use_case", &format!("use_case_{}", use_case_id),
+                     association.use_case_multiplicity)?;
+        writeln!(w, "    </packagedElement>")?;
+    }
+
+    let mut dependencies = diagram.dependencies().collect::<Vec<_>>();
+    dependencies.sort();
+    for (dependent, dependency) in dependencies {
+        let name = match diagram.dependency_label(dependent, dependency) {
+            Some(label) => format!(" name=\"{}\"", escape(label.as_ref())),
+            None => String::new(),
+        };
+        writeln!(w, "    <packagedElement xmi:type=\"uml:Dependency\" \
+                     xmi:id=\"dependency_{}_{}\"{} client=\"use_case_{}\" \
+                     supplier=\"use_case_{}\"/>",
+                 dependent, dependency, name, dependent, dependency)?;
+    }
+
+    let mut notes = diagram.notes().collect::<Vec<_>>();
+    notes.sort_by_key(|&(note_id, _)| note_id);
+    for (note_id, note) in notes {
+        let annotated = match note.anchor {
+            NoteAnchor::Actor(actor_id) => format!("actor_{}", actor_id),
+            NoteAnchor::UseCase(use_case_id) => format!("use_case_{}", use_case_id),
+            NoteAnchor::Association(actor_id, use_case_id) =>
+                association_xmi_id(actor_id, use_case_id),
+        };
+        writeln!(w, "    <ownedComment xmi:type=\"uml:Comment\" xmi:id=\"note_{}\" \
+                     body=\"{}\" annotatedElement=\"{}\"/>",
+                 note_id, escape(note.text.as_ref()), annotated)?;
+    }
+
+    writeln!(w, "  </uml:Model>")?;
+    writeln!(w, "</xmi:XMI>")?;
+    Ok(())
+}
+
+/// Parse an XMI document into a diagram. Elements are recognized by their
+/// `xmi:type` in the UML namespace, wherever they are in the document, and
+/// related through their `xmi:id`s, given as attributes or as child elements
+/// with an `xmi:idref`. Actors, use cases and their extension points,
+/// includes, extends and generalizations are read, and so are associations
+/// between an actor and a use case, with their names, multiplicities and
+/// navigability, and dependencies between use cases. Elements that are the
+/// subject of use cases are system boundaries. Comments owned by an actor or
+/// use case that annotate only their owner are descriptions, and other
+/// comments on actors, use cases and associations are notes. Everything
+/// else, such as classes and the associations between them, is skipped, but
+/// references to identifiers that no element has are errors.
+pub fn from_xmi<S>(text: &str) -> Result<UseCaseDiagram<S>, ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    from_xmi_with_source_map(text, "").map(|(diagram, _)| diagram)
+}
+
+/// Parse an XMI document in the file like `from_xmi`, and map every actor,
+/// use case and association to the start tag of its element.
+pub fn from_xmi_with_source_map<S>(text: &str, file: &str)
+                                   -> Result<(UseCaseDiagram<S>, SourceMap<S>), ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    let mut reader = Reader::new(text);
+    let mut objects = Objects::new(file);
+    while let Some((position, event)) = reader.next_event()? {
+        objects.read(position, event)?;
+    }
+    objects.finish()
+}
+
+/// Reads an XMI document in chunks, for documents too large to hold in
+/// memory, with the same result as `from_xmi`. Only the UML elements that
+/// can still be converted or referred to are kept, which in exports from
+/// modelers are a fraction of the document.
+pub struct XmiReader<S = Rc<str>> {
+    xml: StreamReader,
+    objects: Objects<S>,
+}
+
+impl<S> XmiReader<S> where S: AsRef<str> + for<'a> From<&'a str> {
+    pub fn new() -> Self {
+        XmiReader{xml: StreamReader::new(), objects: Objects::new("")}
+    }
+
+    /// Read the next chunk of the document, which may end anywhere, even
+    /// within a character. Return an event for every actor and use case
+    /// whose element ended in the chunk.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<DiagramEvent>, ParseError> {
+        let objects = &mut self.objects;
+        self.xml.feed(chunk, |position, event| objects.read(position, event))?;
+        Ok(objects.diagram.drain_events().collect())
+    }
+
+    /// Read the end of the document and return the diagram. Relationships,
+    /// system boundaries and notes are only converted here, as references
+    /// may point forward.
+    pub fn finish(mut self) -> Result<UseCaseDiagram<S>, ParseError> {
+        let objects = &mut self.objects;
+        self.xml.finish(|position, event| objects.read(position, event))?;
+        self.objects.finish().map(|(diagram, _)| diagram)
+    }
+}
+
+impl<S> Default for XmiReader<S> where S: AsRef<str> + for<'a> From<&'a str> {
+    fn default() -> Self {
+        XmiReader::new()
+    }
+}
+
+/// The elements of a document read so far, with the actors and use cases
+/// converted as soon as their elements end. Elements are numbered in
+/// document order, and those that nothing can refer to are dropped when they
+/// end.
+struct Objects<S> {
+    objects: BTreeMap<usize, Object>,
+    count: usize,
+    frames: Vec<Frame>,
+    namespaces: Vec<Vec<(String, String)>>,
+    diagram: UseCaseDiagram<S>,
+    actors: HashMap<usize, ActorId>,
+    use_cases: HashMap<usize, UseCaseId>,
+
+    /// The comment that describes each open actor and use case that has
+    /// one.
+    descriptions: HashMap<usize, usize>,
+
+    /// The comments that turned out to be descriptions.
+    described: HashSet<usize>,
+
+    file: String,
+    source_map: SourceMap<S>,
+}
+
+impl<S> Objects<S> where S: AsRef<str> + for<'a> From<&'a str> {
+    fn new(file: &str) -> Self {
+        let mut diagram = UseCaseDiagram::new();
+        diagram.set_event_recording(true);
+        Objects{objects: BTreeMap::new(), count: 0, frames: Vec::new(), namespaces: Vec::new(),
+                diagram, actors: HashMap::new(), use_cases: HashMap::new(),
+                descriptions: HashMap::new(), described: HashSet::new(),
+                file: file.to_string(), source_map: SourceMap::new()}
+    }
+
+    fn read(&mut self, position: Position, event: Event) -> Result<(), ParseError> {
+        let (name, attributes, empty) = match event {
+            Event::Start{name, attributes, empty} => (name, attributes, empty),
+            Event::End{..} => {
+                if let Some(frame) = self.frames.pop() {
+                    self.close(frame);
+                }
+                self.namespaces.pop();
+                return Ok(());
+            },
+            Event::Text(text) => {
+                if let Some(&Frame::Value(index, ref name)) = self.frames.last() {
+                    self.objects.get_mut(&index).expect("open objects are kept").attributes
+                        .entry(name.clone()).or_default().push_str(&text);
+                }
+                return Ok(());
+            },
+        };
+        self.namespaces.push(attributes.iter()
+            .filter_map(|&(attribute, ref value)| match attribute.split_once(':') {
+                Some(("xmlns", prefix)) => Some((prefix.to_string(), value.clone())),
+                None if attribute == "xmlns" => Some((String::new(), value.clone())),
+                _ => None,
+            })
+            .collect());
+        let namespaces = &self.namespaces;
+        let resolve = |qualified: &str| {
+            let (prefix, local) = qualified.split_once(':').unwrap_or(("", qualified));
+            let namespace = namespaces.iter().rev().flat_map(|scope| scope.iter())
+                .find(|&(declared, _)| declared == prefix)
+                .map(|(_, namespace)| namespace.as_str());
+            (namespace, local.to_string())
+        };
+
+        let mut type_ = None;
+        let mut id = None;
+        let mut idref = None;
+        let mut unqualified = HashMap::new();
+        for (attribute, value) in attributes {
+            if !attribute.contains(':') {
+                if attribute != "xmlns" {
+                    unqualified.insert(attribute.to_string(), value);
+                }
+                continue;
+            }
+            let (namespace, local) = resolve(attribute);
+            let xmi = namespace.is_some_and(is_xmi);
+            match local.as_str() {
+                "type" if namespace.is_some() => type_ = Some(value),
+                "id" if xmi => id = Some(value),
+                "idref" if xmi => idref = Some(value),
+                _ => (),
+            }
+        }
+        let type_ = match type_ {
+            Some(type_) => match resolve(&type_) {
+                (Some(namespace), local) if is_uml(namespace) => Some(local),
+                _ => None,
+            },
+            // Metaclasses are capitalized, unlike the properties around them.
+            None => match resolve(name) {
+                (Some(namespace), local)
+                    if is_uml(namespace) && local.starts_with(char::is_uppercase) => Some(local),
+                _ => None,
+            },
+        };
+        let parent = self.frames.iter().rev().filter_map(|frame| match *frame {
+            Frame::Object(index) => Some(index),
+            _ => None,
+        }).next();
+        let local = resolve(name).1;
+        let frame = match (type_, parent) {
+            (Some(type_), _) => {
+                let index = self.count;
+                self.count += 1;
+                self.objects.insert(index, Object{type_, element: local, id, parent, position,
+                                                  attributes: unqualified});
+                Frame::Object(index)
+            },
+            (None, Some(parent)) if matches!(self.frames.last(), Some(&Frame::Object(_))) => {
+                if let Some(idref) = idref {
+                    let references = self.objects.get_mut(&parent).expect("open objects are kept")
+                        .attributes.entry(local.clone()).or_default();
+                    if !references.is_empty() {
+                        references.push(' ');
+                    }
+                    references.push_str(&idref);
+                }
+                Frame::Value(parent, local)
+            },
+            _ => Frame::Other,
+        };
+        if empty {
+            self.namespaces.pop();
+            self.close(frame);
+        } else {
+            self.frames.push(frame);
+        }
+        Ok(())
+    }
+
+    /// Convert the element that ended, if it is an actor or a use case, or
+    /// note the comment that ended if it describes one. Then drop the
+    /// element if nothing can refer to it, or else what `finish` does not
+    /// need of it.
+    fn close(&mut self, frame: Frame) {
+        let index = match frame {
+            Frame::Object(index) => index,
+            _ => return,
+        };
+        let description = match self.descriptions.remove(&index) {
+            Some(comment) => {
+                let body = S::from(self.objects[&comment].attribute("body"));
+                self.described.insert(comment);
+                self.shrink(comment);
+                Some(body)
+            },
+            None => None,
+        };
+        let objects = &self.objects;
+        let object = &objects[&index];
+        let name = || S::from(object.attribute("name"));
+        match (object.type_.as_str(), object.parent) {
+            ("Comment", Some(owner)) => {
+                let annotated = object.references("annotatedElement");
+                let owner_id = objects[&owner].id.as_deref();
+                let is_element = ["Actor", "UseCase"].contains(&objects[&owner].type_.as_str());
+                if is_element && annotated.iter().all(|&id| Some(id) == owner_id) {
+                    self.descriptions.entry(owner).or_insert(index);
+                }
+            },
+            ("Actor", _) => {
+                let mut actor = Actor::new(name());
+                actor.description = description;
+                let actor_id = self.diagram.insert_actor(actor).expect("names are not unique");
+                self.actors.insert(index, actor_id);
+                self.source_map.actors.insert(actor_id, span(&self.file, object.position));
+            },
+            ("UseCase", _) => {
+                let mut use_case = UseCase::new(name());
+                use_case.description = description;
+                let use_case_id =
+                    self.diagram.insert_use_case(use_case).expect("names are not unique");
+                self.use_cases.insert(index, use_case_id);
+                self.source_map.use_cases.insert(use_case_id, span(&self.file, object.position));
+            },
+            _ => (),
+        }
+
+        let converted = CONVERTED_TYPES.contains(&object.type_.as_str()) ||
+            ["lowerValue", "upperValue"].contains(&object.element.as_str());
+        if !converted {
+            self.shrink(index);
+        }
+    }
+
+    /// Keep only the attributes of an element that ended that are needed of
+    /// elements `finish` does not convert: the references between use cases
+    /// and their subjects, and the names of the subjects. Drop the element
+    /// entirely if it has no identifier to be referred to by and no such
+    /// references.
+    fn shrink(&mut self, index: usize) {
+        let object = self.objects.get_mut(&index).expect("objects are kept until they end");
+        object.attributes.retain(|name, _| ["name", "subject", "useCase"].contains(&&**name));
+        let refers = object.attributes.contains_key("subject") ||
+            object.attributes.contains_key("useCase");
+        if object.id.is_none() && !refers {
+            self.objects.remove(&index);
+        }
+    }
+
+    /// Convert everything but actors and use cases, now that every
+    /// reference can be resolved.
+    fn finish(self) -> Result<(UseCaseDiagram<S>, SourceMap<S>), ParseError> {
+        let Objects{objects, mut diagram, actors, use_cases, described, file, mut source_map, ..} =
+            self;
+        let mut indices = HashMap::new();
+        let mut children = HashMap::<usize, Vec<usize>>::new();
+        for (&index, object) in &objects {
+            if let Some(ref id) = object.id {
+                indices.insert(id.as_str(), index);
+            }
+            if let Some(parent) = object.parent {
+                children.entry(parent).or_default().push(index);
+            }
+        }
+        let children_of = |index: usize| children.get(&index).map(Vec::as_slice).unwrap_or(&[]);
+        let lookup = |object: &Object, id: &str| {
+            indices.get(id).cloned().ok_or_else(|| {
+                error(object.position, ParseErrorKind::UnknownReference(id.to_string()))
+            })
+        };
+        let parent_of = |index: usize, attribute: &str| match objects[&index].reference(attribute) {
+            Some(id) => lookup(&objects[&index], id).map(Some),
+            None => Ok(objects[&index].parent),
+        };
+
+        let use_case_at = |object: &Object, index: Option<usize>| -> Result<UseCaseId, ParseError> {
+            let id = index.and_then(|index| objects.get(&index)?.id.clone()).unwrap_or_default();
+            index.and_then(|index| use_cases.get(&index).cloned())
+                .ok_or_else(|| error(object.position, ParseErrorKind::UnknownReference(id)))
+        };
+        let relationship_error = |object: &Object| {
+            let position = object.position;
+            move |err| error(position, ParseErrorKind::Relationship(err))
+        };
+
+        let mut extension_points = HashMap::new();
+        let mut subjects = BTreeMap::<usize, BTreeSet<UseCaseId>>::new();
+        for (&index, object) in &objects {
+            if object.type_ == "ExtensionPoint" {
+                let use_case = use_case_at(object, parent_of(index, "useCase")?)?;
+                let name = S::from(object.attribute("name"));
+                let extension_point = diagram.insert_extension_point(ExtensionPoint{use_case, name})
+                    .map_err(relationship_error(object))?;
+                extension_points.insert(index, extension_point);
+            }
+            for id in object.references("subject") {
+                let use_case = use_case_at(object, Some(index))?;
+                subjects.entry(lookup(object, id)?).or_default().insert(use_case);
+            }
+            for id in object.references("useCase") {
+                let use_case = use_case_at(object, Some(lookup(object, id)?))?;
+                subjects.entry(index).or_default().insert(use_case);
+            }
+        }
+        for (index, use_cases) in subjects {
+            let name = S::from(objects[&index].attribute("name"));
+            diagram.insert_system_boundary(SystemBoundary{name, use_cases})
+                .map_err(relationship_error(&objects[&index]))?;
+        }
+
+        let mut associations = HashMap::new();
+        for (&index, object) in &objects {
+            let reference = |attribute| match object.reference(attribute) {
+                Some(id) => lookup(object, id).map(Some),
+                None => Ok(None),
+            };
+            match object.type_.as_str() {
+                "Include" => {
+                    let base = use_case_at(object, parent_of(index, "includingCase")?)?;
+                    let included = use_case_at(object, reference("addition")?)?;
+                    diagram.insert_include(base, included).map_err(relationship_error(object))?;
+                },
+                "Extend" => {
+                    let extension = use_case_at(object, parent_of(index, "extension")?)?;
+                    let base = use_case_at(object, reference("extendedCase")?)?;
+                    let extension_point = match reference("extensionLocation")? {
+                        Some(location) => Some(*extension_points.get(&location).ok_or_else(|| {
+                            let id = object.reference("extensionLocation").unwrap().to_string();
+                            error(object.position, ParseErrorKind::UnknownReference(id))
+                        })?),
+                        None => None,
+                    };
+                    diagram.insert_extend(Extend{extension, base, extension_point})
+                        .map_err(relationship_error(object))?;
+                },
+                "Generalization" => {
+                    let specific = parent_of(index, "specific")?;
+                    let (child, parent) = match (specific, reference("general")?) {
+                        (Some(child), Some(parent)) => (child, parent),
+                        _ => continue,
+                    };
+                    if let (Some(&child), Some(&parent)) =
+                        (actors.get(&child), actors.get(&parent)) {
+                        diagram.insert_actor_generalization(child, parent)
+                            .map_err(relationship_error(object))?;
+                    }
+                    if let (Some(&child), Some(&parent)) =
+                        (use_cases.get(&child), use_cases.get(&parent)) {
+                        diagram.insert_use_case_generalization(child, parent)
+                            .map_err(relationship_error(object))?;
+                    }
+                },
+                "Dependency" => {
+                    let (client, supplier) = match (reference("client")?, reference("supplier")?) {
+                        (Some(client), Some(supplier)) => (client, supplier),
+                        _ => continue,
+                    };
+                    if let (Some(&dependent), Some(&dependency)) =
+                        (use_cases.get(&client), use_cases.get(&supplier)) {
+                        let label = object.attributes.get("name")
+                            .map(|name| S::from(name.as_str()));
+                        diagram.insert_dependency(dependent, dependency, label)
+                            .map_err(relationship_error(object))?;
+                    }
+                },
+                "Association" => {
+                    let mut ends = Vec::new();
+                    for id in object.references("memberEnd") {
+                        ends.push(lookup(object, id)?);
+                    }
+                    ends.extend(children_of(index).iter().cloned()
+                        .filter(|end| objects[end].type_ == "Property"));
+                    ends.sort();
+                    ends.dedup();
+                    let mut actor_end = None;
+                    let mut use_case_end = None;
+                    for &end in &ends {
+                        let type_ = match objects[&end].reference("type") {
+                            Some(id) => lookup(&objects[&end], id)?,
+                            None => continue,
+                        };
+                        if let Some(&actor_id) = actors.get(&type_) {
+                            actor_end = Some((end, actor_id));
+                        } else if let Some(&use_case_id) = use_cases.get(&type_) {
+                            use_case_end = Some((end, use_case_id));
+                        }
+                    }
+                    let ((actor_end, actor_id), (use_case_end, use_case_id)) =
+                        match (actor_end, use_case_end) {
+                            (Some(actor_end), Some(use_case_end)) if ends.len() == 2 =>
+                                (actor_end, use_case_end),
+                            _ => continue,
+                        };
+                    let navigable_ends = object.references("navigableOwnedEnd");
+                    let navigable = |end: usize| {
+                        objects[&end].parent != Some(index) ||
+                            objects[&end].id.as_deref()
+                                .is_some_and(|id| navigable_ends.contains(&id))
+                    };
+                    let navigability = match (navigable(actor_end), navigable(use_case_end)) {
+                        (false, true) => Navigability::ToUseCase,
+                        (true, false) => Navigability::ToActor,
+                        _ => Navigability::Undirected,
+                    };
+                    let association = Association{
+                        label: object.attributes.get("name").map(|name| S::from(name.as_str())),
+                        actor_multiplicity: multiplicity(&objects, children_of(actor_end))?,
+                        use_case_multiplicity:
+                            multiplicity(&objects, children_of(use_case_end))?,
+                        navigability,
+                        ..Association::default()
+                    };
+                    diagram.insert_association_with(actor_id, use_case_id, association).unwrap();
+                    associations.insert(index, (actor_id, use_case_id));
+                    source_map.associations.entry((actor_id, use_case_id))
+                        .or_insert_with(|| span(&file, object.position));
+                },
+                _ => (),
+            }
+        }
+
+        for (&index, object) in &objects {
+            if object.type_ != "Comment" || described.contains(&index) {
+                continue;
+            }
+            for id in object.references("annotatedElement") {
+                let annotated = lookup(object, id)?;
+                let anchor = if let Some(&actor_id) = actors.get(&annotated) {
+                    NoteAnchor::Actor(actor_id)
+                } else if let Some(&use_case_id) = use_cases.get(&annotated) {
+                    NoteAnchor::UseCase(use_case_id)
+                } else if let Some(&(actor_id, use_case_id)) = associations.get(&annotated) {
+                    NoteAnchor::Association(actor_id, use_case_id)
+                } else {
+                    continue;
+                };
+                let text = S::from(object.attribute("body"));
+                diagram.insert_note(Note{anchor, text})
+                    .map_err(relationship_error(object))?;
+            }
+        }
+        diagram.set_event_recording(false);
+        Ok((diagram, source_map))
+    }
+}
+
+/// An element with a UML type, read before any are converted so that
+/// references may point forward.
+struct Object {
+    type_: String,
+    element: String,
+    id: Option<String>,
+    parent: Option<usize>,
+    position: Position,
+
+    /// Unqualified attributes, and child elements without a type holding a
+    /// reference or text.
+    attributes: HashMap<String, String>,
+}
+
+impl Object {
+    fn attribute(&self, name: &str) -> &str {
+        self.attributes.get(name).map(String::as_str).unwrap_or("")
+    }
+
+    fn reference(&self, name: &str) -> Option<&str> {
+        self.references(name).into_iter().next()
+    }
+
+    fn references(&self, name: &str) -> Vec<&str> {
+        self.attribute(name).split_whitespace().collect()
+    }
+}
+
+/// The types of the elements that `finish` converts, and so keeps whole.
+const CONVERTED_TYPES: &[&str] = &[
+    "Association", "Comment", "Dependency", "Extend", "ExtensionPoint", "Generalization",
+    "Include", "Property",
+];
+
+/// What an open element of the document is to the objects.
+enum Frame {
+    Object(usize),
+    Value(usize, String),
+    Other,
+}
+
+fn is_xmi(namespace: &str) -> bool {
+    namespace.contains("/XMI")
+}
+
+fn is_uml(namespace: &str) -> bool {
+    namespace.contains("/spec/UML/") || namespace.contains("/uml2/")
+}
+
+/// The multiplicity of an association end from its lower and upper values,
+/// each of which defaults to one if the other is given, among the children
+/// of the end.
+fn multiplicity(objects: &BTreeMap<usize, Object>, children: &[usize])
+                -> Result<Option<Multiplicity>, ParseError> {
+    let value = |element: &str| children.iter().map(|child| &objects[child])
+        .find(|object| object.element == element)
+        .map(|object| (object, object.attribute("value")));
+    let lower = match value("lowerValue") {
+        Some((_, "")) => Some(0),
+        Some((object, value)) => Some(value.parse().map_err(|_| {
+            error(object.position, ParseErrorKind::InvalidXml(format!("invalid bound {:?}", value)))
+        })?),
+        None => None,
+    };
+    let upper = match value("upperValue") {
+        Some((_, "*")) | Some((_, "-1")) => Some(None),
+        Some((_, "")) => Some(Some(0)),
+        Some((object, value)) => Some(Some(value.parse().map_err(|_| {
+            error(object.position, ParseErrorKind::InvalidXml(format!("invalid bound {:?}", value)))
+        })?)),
+        None => None,
+    };
+    Ok(match (lower, upper) {
+        (None, None) => None,
+        (lower, upper) => Some(Multiplicity{lower: lower.unwrap_or(1),
+                                            upper: upper.unwrap_or(Some(1))}),
+    })
+}
+
+fn association_xmi_id(actor_id: ActorId, use_case_id: UseCaseId) -> String {
+    format!("association_{}_{}", actor_id, use_case_id)
+}
+
+fn generate_comment<W, S>(w: &mut W, description: &Option<S>, owner: &str) -> io::Result<()>
+    where W: io::Write, S: AsRef<str> {
+    if let Some(ref description) = *description {
+        writeln!(w, "      <ownedComment xmi:type=\"uml:Comment\" xmi:id=\"{}_description\" \
+                     body=\"{}\" annotatedElement=\"{}\"/>",
+                 owner, escape(description.as_ref()), owner)?;
+    }
+    Ok(())
+}
+
+fn generate_end<W>(w: &mut W, association: &str, end: &str, type_: &str,
+                   multiplicity: Option<Multiplicity>) -> io::Result<()>
+    where W: io::Write {
+    let id = format!("{}_{}", association, end);
+    let multiplicity = match multiplicity {
+        Some(multiplicity) => multiplicity,
+        None => {
+            return writeln!(w, "      <ownedEnd xmi:type=\"uml:Property\" xmi:id=\"{}\" \
+                                type=\"{}\" association=\"{}\"/>", id, type_, association);
+        },
+    };
+    writeln!(w, "      <ownedEnd xmi:type=\"uml:Property\" xmi:id=\"{}\" type=\"{}\" \
+                 association=\"{}\">", id, type_, association)?;
+    writeln!(w, "        <lowerValue xmi:type=\"uml:LiteralInteger\" xmi:id=\"{}_lower\" \
+                 value=\"{}\"/>", id, multiplicity.lower)?;
+    let upper = match multiplicity.upper {
+        Some(upper) => upper.to_string(),
+        None => "*".to_string(),
+    };
+    writeln!(w, "        <upperValue xmi:type=\"uml:LiteralUnlimitedNatural\" \
+                 xmi:id=\"{}_upper\" value=\"{}\"/>", id, upper)?;
+    writeln!(w, "      </ownedEnd>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+    use std::str;
+
+    use use_case_diagram::Span;
+
+    fn diagram() -> UseCaseDiagram {
+        let mut diagram = UseCaseDiagram::new();
+        let mut administrator = Actor::new(Rc::from("Administrator"));
+        administrator.description = Some(Rc::from("Runs <the> site"));
+        let a = diagram.insert_actor(administrator).unwrap();
+        let m = diagram.insert_actor(Actor::new(Rc::from("Moderator"))).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban \"bad\" subscriber"))).unwrap();
+        let li = diagram.insert_use_case(UseCase::new(Rc::from("Log in"))).unwrap();
+        diagram.insert_association_with(a, bs, Association{
+            label: Some(Rc::from("bans")),
+            actor_multiplicity: Some(Multiplicity{lower: 1, upper: None}),
+            navigability: Navigability::ToUseCase,
+            ..Association::default()
+        }).unwrap();
+        diagram.insert_include(bs, li).unwrap();
+        let confirm = diagram.insert_extension_point(ExtensionPoint{use_case: bs,
+                                                                    name: Rc::from("Confirm")})
+            .unwrap();
+        diagram.insert_extend(Extend{extension: li, base: bs, extension_point: Some(confirm)})
+            .unwrap();
+        diagram.insert_actor_generalization(m, a).unwrap();
+        diagram.insert_dependency(bs, li, Some(Rc::from("session"))).unwrap();
+        diagram.insert_system_boundary(SystemBoundary{
+            name: Rc::from("Forum & co"),
+            use_cases: vec![bs].into_iter().collect(),
+        }).unwrap();
+        diagram.insert_note(Note{anchor: NoteAnchor::Association(a, bs),
+                                 text: Rc::from("Two\nlines")}).unwrap();
+        diagram
+    }
+
+    #[test]
+    fn test_to_xmi() {
+        let diagram = diagram();
+        let mut output = Vec::new();
+        to_xmi(&diagram, "Journal", &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<xmi:XMI xmi:version=\"2.1\" xmlns:xmi=\"http://schema.omg.org/spec/XMI/2.1\" ",
+            "xmlns:uml=\"http://schema.omg.org/spec/UML/2.1\">\n",
+            "  <uml:Model xmi:type=\"uml:Model\" xmi:id=\"model\" name=\"Journal\">\n",
+            "    <packagedElement xmi:type=\"uml:Actor\" xmi:id=\"actor_0\" ",
+            "name=\"Administrator\">\n",
+            "      <ownedComment xmi:type=\"uml:Comment\" xmi:id=\"actor_0_description\" ",
+            "body=\"Runs &lt;the&gt; site\" annotatedElement=\"actor_0\"/>\n",
+            "    </packagedElement>\n",
+            "    <packagedElement xmi:type=\"uml:Actor\" xmi:id=\"actor_1\" name=\"Moderator\">\n",
+            "      <generalization xmi:type=\"uml:Generalization\" ",
+            "xmi:id=\"generalization_actor_1_0\" general=\"actor_0\"/>\n",
+            "    </packagedElement>\n",
+            "    <packagedElement xmi:type=\"uml:Component\" xmi:id=\"system_boundary_0\" ",
+            "name=\"Forum &amp; co\" useCase=\"use_case_0\"/>\n",
+            "    <packagedElement xmi:type=\"uml:UseCase\" xmi:id=\"use_case_0\" ",
+            "name=\"Ban &quot;bad&quot; subscriber\" subject=\"system_boundary_0\">\n",
+            "      <extensionPoint xmi:type=\"uml:ExtensionPoint\" ",
+            "xmi:id=\"extension_point_0\" name=\"Confirm\"/>\n",
+            "      <include xmi:type=\"uml:Include\" xmi:id=\"include_0_1\" ",
+            "addition=\"use_case_1\"/>\n",
+            "    </packagedElement>\n",
+            "    <packagedElement xmi:type=\"uml:UseCase\" xmi:id=\"use_case_1\" ",
+            "name=\"Log in\">\n",
+            "      <extend xmi:type=\"uml:Extend\" xmi:id=\"extend_1_0\" ",
+            "extendedCase=\"use_case_0\" extensionLocation=\"extension_point_0\"/>\n",
+            "    </packagedElement>\n",
+            "    <packagedElement xmi:type=\"uml:Association\" xmi:id=\"association_0_0\" ",
+            "name=\"bans\" memberEnd=\"association_0_0_actor association_0_0_use_case\" ",
+            "navigableOwnedEnd=\"association_0_0_use_case\">\n",
+            "      <ownedEnd xmi:type=\"uml:Property\" xmi:id=\"association_0_0_actor\" ",
+            "type=\"actor_0\" association=\"association_0_0\">\n",
+            "        <lowerValue xmi:type=\"uml:LiteralInteger\" ",
+            "xmi:id=\"association_0_0_actor_lower\" value=\"1\"/>\n",
+            "        <upperValue xmi:type=\"uml:LiteralUnlimitedNatural\" ",
+            "xmi:id=\"association_0_0_actor_upper\" value=\"*\"/>\n",
+            "      </ownedEnd>\n",
+            "      <ownedEnd xmi:type=\"uml:Property\" xmi:id=\"association_0_0_use_case\" ",
+            "type=\"use_case_0\" association=\"association_0_0\"/>\n",
+            "    </packagedElement>\n",
+            "    <packagedElement xmi:type=\"uml:Dependency\" xmi:id=\"dependency_0_1\" ",
+            "name=\"session\" client=\"use_case_0\" supplier=\"use_case_1\"/>\n",
+            "    <ownedComment xmi:type=\"uml:Comment\" xmi:id=\"note_0\" ",
+            "body=\"Two&#10;lines\" annotatedElement=\"association_0_0\"/>\n",
+            "  </uml:Model>\n",
+            "</xmi:XMI>\n",
+        ));
+    }
+
+    #[test]
+    fn test_from_xmi() {
+        let diagram = diagram();
+        let mut output = Vec::new();
+        to_xmi(&diagram, "Journal", &mut output).unwrap();
+        let parsed = from_xmi::<Rc<str>>(str::from_utf8(&output).unwrap()).unwrap();
+        assert!(parsed.equivalent(&diagram));
+
+        let text = concat!(
+            "<?xml version=\"1.0\"?>\n",
+            "<xmi:XMI xmlns:x=\"http://www.omg.org/spec/XMI/20131001\"\n",
+            "         xmlns:xmi=\"http://www.omg.org/spec/XMI/20131001\">\n",
+            "  <Model xmlns=\"http://www.omg.org/spec/UML/20131001\" name=\"Shop\">\n",
+            "    <packagedElement x:type=\"Actor\" x:id=\"c\" name=\"Customer\">\n",
+            "      <ownedComment x:type=\"Comment\">\n",
+            "        <body>Buys &amp; pays</body>\n",
+            "      </ownedComment>\n",
+            "    </packagedElement>\n",
+            "    <packagedElement x:type=\"UseCase\" x:id=\"o\" name=\"Order\"/>\n",
+            "    <packagedElement x:type=\"Class\" x:id=\"k\" name=\"Cart\">\n",
+            "      <ownedAttribute x:type=\"Property\" x:id=\"k_o\" type=\"o\"/>\n",
+            "    </packagedElement>\n",
+            "    <packagedElement x:type=\"Association\" x:id=\"a\">\n",
+            "      <memberEnd x:idref=\"a_c\"/>\n",
+            "      <memberEnd x:idref=\"a_o\"/>\n",
+            "      <ownedEnd x:type=\"Property\" x:id=\"a_c\" type=\"c\">\n",
+            "        <upperValue x:type=\"LiteralUnlimitedNatural\" value=\"-1\"/>\n",
+            "      </ownedEnd>\n",
+            "      <ownedEnd x:type=\"Property\" x:id=\"a_o\">\n",
+            "        <type x:idref=\"o\"/>\n",
+            "      </ownedEnd>\n",
+            "    </packagedElement>\n",
+            "    <packagedElement x:type=\"Association\" x:id=\"b\" memberEnd=\"k_o b_k\">\n",
+            "      <ownedEnd x:type=\"Property\" x:id=\"b_k\" type=\"k\"/>\n",
+            "    </packagedElement>\n",
+            "  </Model>\n",
+            "</xmi:XMI>\n",
+        );
+        let (parsed, source_map) = from_xmi_with_source_map::<Rc<str>>(text, "shop.xmi").unwrap();
+        let (customer, actor) = parsed.actor_by_name("Customer").unwrap();
+        let (order, _) = parsed.use_case_by_title("Order").unwrap();
+        assert_eq!(actor.description.as_deref(), Some("Buys & pays"));
+        assert_eq!(parsed.associations().collect::<Vec<_>>(), vec![(customer, order)]);
+        let association = parsed.association(customer, order).unwrap();
+        assert_eq!(association.actor_multiplicity, Some(Multiplicity{lower: 1, upper: None}));
+        assert_eq!(association.navigability, Navigability::Undirected);
+        assert_eq!(parsed.notes().count(), 0);
+        let span = |line, column| Span{file: Rc::from("shop.xmi"), line, column};
+        assert_eq!(source_map.actors[&customer], span(5, 5));
+        assert_eq!(source_map.use_cases[&order], span(10, 5));
+        assert_eq!(source_map.associations[&(customer, order)], span(14, 5));
+
+        let err = from_xmi::<Rc<str>>(&text.replace("<type x:idref=\"o\"/>",
+                                                      "<type x:idref=\"p\"/>"))
+            .unwrap_err();
+        assert_eq!(err.position, Position{line: 20, column: 7});
+        assert_eq!(err.kind, ParseErrorKind::UnknownReference("p".to_string()));
+        let err = from_xmi::<Rc<str>>("<a>\n<b>\n</a>").unwrap_err();
+        assert_eq!(err.position, Position{line: 3, column: 1});
+    }
+
+    #[test]
+    fn test_xmi_reader() {
+        let mut output = Vec::new();
+        to_xmi(&diagram(), "Journal", &mut output).unwrap();
+        let expected = from_xmi::<Rc<str>>(str::from_utf8(&output).unwrap()).unwrap();
+
+        let mut reader = XmiReader::<Rc<str>>::new();
+        let mut events = Vec::new();
+        for chunk in output.chunks(7) {
+            events.extend(reader.feed(chunk).unwrap());
+        }
+        let diagram = reader.finish().unwrap();
+        assert_eq!(::serde_json::to_value(&diagram).unwrap(),
+                   ::serde_json::to_value(&expected).unwrap());
+        let mut inserted = diagram.actors()
+            .map(|(actor_id, _)| DiagramEvent::ActorInserted(actor_id))
+            .chain(diagram.use_cases().map(|(use_case_id, _)| {
+                DiagramEvent::UseCaseInserted(use_case_id)
+            }))
+            .collect::<Vec<_>>();
+        inserted.sort_by_key(|event| format!("{:?}", event));
+        events.sort_by_key(|event| format!("{:?}", event));
+        assert_eq!(events, inserted);
+
+        let mut reader = XmiReader::<Rc<str>>::new();
+        let header = &output[..output.iter().position(|&byte| byte == b'>').unwrap() + 1];
+        reader.feed(header).unwrap();
+        reader.feed(b"<xmi:XMI xmlns:xmi=\"http://schema.omg.org/spec/XMI/2.1\" \
+                      xmlns:uml=\"http://schema.omg.org/spec/UML/2.1\">").unwrap();
+        for _ in 0..100 {
+            reader.feed(b"<packagedElement xmi:type=\"uml:Class\" visibility=\"public\">\
+                          <ownedOperation xmi:type=\"uml:Operation\" name=\"run\"/>\
+                          </packagedElement>").unwrap();
+        }
+        reader.feed(b"<packagedElement xmi:type=\"uml:Class\" xmi:id=\"k\" name=\"Cart\"/>")
+            .unwrap();
+        assert_eq!(reader.objects.objects.len(), 1);
+        assert_eq!(reader.objects.objects[&200].attributes.len(), 1);
+
+        let mut reader = XmiReader::<Rc<str>>::new();
+        reader.feed(b"<a>\n<b>\xe2\x9c").unwrap();
+        let err = reader.feed(b"\x93</b>\n</c>").unwrap_err();
+        assert_eq!(err.position, Position{line: 3, column: 1});
+    }
+}
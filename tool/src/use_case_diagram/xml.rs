@@ -0,0 +1,420 @@
+//! A small XML reader for importers. It checks that tags are balanced and
+//! decodes references, but skips document type declarations and processing
+//! instructions without interpreting them.
+
+use std::mem;
+use std::str;
+
+use use_case_diagram::parser::{error, ParseError, ParseErrorKind, Position};
+
+/// What the reader found in the document.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(super) enum Event<'a> {
+    /// A start tag, or an empty-element tag if `empty`, with its qualified
+    /// name and its attributes in document order.
+    Start{name: &'a str, attributes: Vec<(&'a str, String)>, empty: bool},
+
+    /// An end tag.
+    End{name: &'a str},
+
+    /// Character data that is not only whitespace, including CDATA sections.
+    Text(String),
+}
+
+/// Reads the events of a document in order.
+pub(super) struct Reader<'a> {
+    text: &'a str,
+    offset: usize,
+    position: Position,
+    open: Vec<String>,
+
+    /// Whether more text may follow, so that a construct cut off at the end
+    /// is incomplete rather than malformed.
+    partial: bool,
+}
+
+impl<'a> Reader<'a> {
+    pub(super) fn new(text: &'a str) -> Self {
+        Reader{text, offset: 0, position: Position{line: 1, column: 1}, open: Vec::new(),
+               partial: false}
+    }
+
+    /// The next event and where it starts, or `None` at the end of the
+    /// document, or of the text read so far if partial.
+    pub(super) fn next_event(&mut self) -> Result<Option<(Position, Event<'a>)>, ParseError> {
+        loop {
+            let position = self.position;
+            let rest = &self.text[self.offset..];
+            if self.partial && !is_complete(rest) {
+                return Ok(None);
+            }
+            if rest.is_empty() {
+                return match self.open.last() {
+                    Some(name) => Err(invalid(position, format!("unclosed element <{}>", name))),
+                    None => Ok(None),
+                };
+            }
+            if rest.starts_with("<?") {
+                self.skip_past("?>", position)?;
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->", position)?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.advance("<![CDATA[".len());
+                let text = self.skip_past("]]>", position)?;
+                return Ok(Some((position, Event::Text(text.to_string()))));
+            } else if rest.starts_with("<!") {
+                self.skip_past(declaration_end(rest), position)?;
+            } else if rest.starts_with("</") {
+                self.advance(2);
+                let name = self.skip_past(">", position)?.trim();
+                match self.open.pop() {
+                    Some(ref open) if open == name => (),
+                    _ => return Err(invalid(position, format!("unexpected </{}>", name))),
+                }
+                return Ok(Some((position, Event::End{name})));
+            } else if rest.starts_with('<') {
+                return self.start_tag(position).map(Some);
+            } else {
+                let end = rest.find('<').unwrap_or(rest.len());
+                let raw = &rest[..end];
+                self.advance(end);
+                if !raw.trim().is_empty() {
+                    let text = unescape(raw).map_err(|message| invalid(position, message))?;
+                    return Ok(Some((position, Event::Text(text))));
+                }
+            }
+        }
+    }
+
+    fn start_tag(&mut self, position: Position) -> Result<(Position, Event<'a>), ParseError> {
+        self.advance(1);
+        let name = self.name();
+        if name.is_empty() {
+            return Err(invalid(position, "expected element name".to_string()));
+        }
+        let mut attributes = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let rest = &self.text[self.offset..];
+            if rest.starts_with("/>") {
+                self.advance(2);
+                return Ok((position, Event::Start{name, attributes, empty: true}));
+            }
+            if rest.starts_with('>') {
+                self.advance(1);
+                self.open.push(name.to_string());
+                return Ok((position, Event::Start{name, attributes, empty: false}));
+            }
+            let attribute_position = self.position;
+            let attribute = self.name();
+            if attribute.is_empty() {
+                return Err(invalid(attribute_position, format!("malformed tag <{}>", name)));
+            }
+            self.skip_whitespace();
+            if !self.text[self.offset..].starts_with('=') {
+                return Err(invalid(self.position, "expected =".to_string()));
+            }
+            self.advance(1);
+            self.skip_whitespace();
+            let value_position = self.position;
+            let quote = match self.text[self.offset..].chars().next() {
+                Some(quote) if quote == '"' || quote == '\'' => quote,
+                _ => return Err(invalid(value_position, "expected quoted value".to_string())),
+            };
+            self.advance(1);
+            let raw = self.skip_past(if quote == '"' { "\"" } else { "'" }, value_position)?;
+            let value = unescape(raw).map_err(|message| invalid(value_position, message))?;
+            attributes.push((attribute, value));
+        }
+    }
+
+    fn name(&mut self) -> &'a str {
+        let rest = &self.text[self.offset..];
+        let end = rest.find(|c: char| c.is_whitespace() || "/>=\"'".contains(c))
+            .unwrap_or(rest.len());
+        self.advance(end);
+        &rest[..end]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.offset..];
+        let end = rest.find(|c: char| !c.is_whitespace()).unwrap_or(rest.len());
+        self.advance(end);
+    }
+
+    /// Skip past the delimiter, returning the text before it.
+    fn skip_past(&mut self, delimiter: &str, start: Position) -> Result<&'a str, ParseError> {
+        let rest = &self.text[self.offset..];
+        match rest.find(delimiter) {
+            Some(end) => {
+                self.advance(end + delimiter.len());
+                Ok(&rest[..end])
+            },
+            None => Err(error(start, ParseErrorKind::UnterminatedBlock)),
+        }
+    }
+
+    fn advance(&mut self, length: usize) {
+        for c in self.text[self.offset..self.offset + length].chars() {
+            if c == '\n' {
+                self.position.line += 1;
+                self.position.column = 1;
+            } else {
+                self.position.column += 1;
+            }
+        }
+        self.offset += length;
+    }
+}
+
+/// Reads a document that arrives in chunks, e.g. from a file too large to
+/// hold in memory. Only the text of an incomplete construct at the end of the
+/// chunks read so far is kept.
+pub(super) struct StreamReader {
+    buffer: String,
+
+    /// An incomplete UTF-8 sequence at the end of the last chunk.
+    bytes: Vec<u8>,
+
+    position: Position,
+    open: Vec<String>,
+}
+
+impl StreamReader {
+    pub(super) fn new() -> Self {
+        StreamReader{buffer: String::new(), bytes: Vec::new(),
+                     position: Position{line: 1, column: 1}, open: Vec::new()}
+    }
+
+    /// Read the next chunk, which may end anywhere, even within a character,
+    /// and pass the events that are complete to the handler.
+    pub(super) fn feed<F>(&mut self, chunk: &[u8], handler: F) -> Result<(), ParseError>
+        where F: FnMut(Position, Event) -> Result<(), ParseError> {
+        self.bytes.extend_from_slice(chunk);
+        let (valid, invalid_utf8) = match str::from_utf8(&self.bytes) {
+            Ok(text) => (text.len(), false),
+            Err(err) => (err.valid_up_to(), err.error_len().is_some()),
+        };
+        self.buffer.push_str(str::from_utf8(&self.bytes[..valid]).unwrap());
+        self.bytes.drain(..valid);
+        self.read(true, handler)?;
+        if invalid_utf8 {
+            return Err(self.invalid_utf8());
+        }
+        Ok(())
+    }
+
+    /// Read the end of the document, passing the remaining events to the
+    /// handler.
+    pub(super) fn finish<F>(&mut self, handler: F) -> Result<(), ParseError>
+        where F: FnMut(Position, Event) -> Result<(), ParseError> {
+        self.read(false, handler)?;
+        if !self.bytes.is_empty() {
+            return Err(self.invalid_utf8());
+        }
+        Ok(())
+    }
+
+    fn read<F>(&mut self, partial: bool, mut handler: F) -> Result<(), ParseError>
+        where F: FnMut(Position, Event) -> Result<(), ParseError> {
+        let mut reader = Reader{text: &self.buffer, offset: 0, position: self.position,
+                                open: mem::take(&mut self.open), partial};
+        while let Some((position, event)) = reader.next_event()? {
+            handler(position, event)?;
+        }
+        let (offset, position, open) = (reader.offset, reader.position, reader.open);
+        self.buffer.drain(..offset);
+        self.position = position;
+        self.open = open;
+        Ok(())
+    }
+
+    /// An error at the end of the text read, where the invalid bytes are.
+    fn invalid_utf8(&self) -> ParseError {
+        let mut reader = Reader::new(&self.buffer);
+        reader.position = self.position;
+        reader.advance(self.buffer.len());
+        invalid(reader.position, "invalid UTF-8".to_string())
+    }
+}
+
+/// Whether the text starts with a whole event, or with markup that can be
+/// skipped as a whole, so that reading it does not depend on text that has
+/// not arrived yet.
+fn is_complete(rest: &str) -> bool {
+    let end = if rest.starts_with("<?") {
+        "?>"
+    } else if rest.starts_with("<!--") {
+        "-->"
+    } else if rest.starts_with("<![CDATA[") {
+        "]]>"
+    } else if rest.starts_with("<!") {
+        declaration_end(rest)
+    } else if rest.starts_with('<') {
+        let mut quote = None;
+        return rest.chars().any(|c| match quote {
+            Some(open) => {
+                if c == open {
+                    quote = None;
+                }
+                false
+            },
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                false
+            },
+            None => c == '>',
+        });
+    } else {
+        "<"
+    };
+    rest.contains(end)
+}
+
+/// What ends the declaration at the start of the text, which has an
+/// internal subset if it has a bracket before its first `>`.
+fn declaration_end(rest: &str) -> &'static str {
+    if rest.contains('[') && rest.find('[') < rest.find('>') {
+        "]>"
+    } else {
+        ">"
+    }
+}
+
+/// Escape a string for use in an attribute value.
+pub(super) fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            '\t' => escaped.push_str("&#9;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn invalid(position: Position, message: String) -> ParseError {
+    error(position, ParseErrorKind::InvalidXml(message))
+}
+
+/// Replace entity and character references.
+fn unescape(raw: &str) -> Result<String, String> {
+    let mut text = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find('&') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        let end = rest.find(';').ok_or_else(|| "unterminated reference".to_string())?;
+        let reference = &rest[..end];
+        let c = match reference {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ if reference.starts_with("#x") =>
+                u32::from_str_radix(&reference[2..], 16).ok().and_then(char::from_u32),
+            _ if reference.starts_with('#') =>
+                reference[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+        text.push(c.ok_or_else(|| format!("unknown reference &{};", reference))?);
+        rest = &rest[end + 1..];
+    }
+    text.push_str(rest);
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(text: &str) -> Result<Vec<Event<'_>>, ParseError> {
+        let mut reader = Reader::new(text);
+        let mut events = Vec::new();
+        while let Some((_, event)) = reader.next_event()? {
+            events.push(event);
+        }
+        Ok(events)
+    }
+
+    #[test]
+    fn test_reader() {
+        let text = concat!(
+            "<?xml version=\"1.0\"?>\n",
+            "<!DOCTYPE model [ <!ENTITY x \"y\"> ]>\n",
+            "<!-- <ignored/> -->\n",
+            "<a x=\"1 &amp; 2\" y='&#65;&#x42;'>\n",
+            "  <b/>\n",
+            "  <c>text &lt;here&gt;<![CDATA[<raw>]]></c>\n",
+            "</a>\n",
+        );
+        assert_eq!(events(text).unwrap(), vec![
+            Event::Start{name: "a", attributes: vec![("x", "1 & 2".to_string()),
+                                                     ("y", "AB".to_string())],
+                         empty: false},
+            Event::Start{name: "b", attributes: vec![], empty: true},
+            Event::Start{name: "c", attributes: vec![], empty: false},
+            Event::Text("text <here>".to_string()),
+            Event::Text("<raw>".to_string()),
+            Event::End{name: "c"},
+            Event::End{name: "a"},
+        ]);
+        let mut reader = Reader::new(text);
+        let (position, _) = reader.next_event().unwrap().unwrap();
+        assert_eq!(position, Position{line: 4, column: 1});
+    }
+
+    #[test]
+    fn test_reader_errors() {
+        fn err(text: &str) -> (usize, usize, ParseErrorKind) {
+            let err = events(text).unwrap_err();
+            (err.position.line, err.position.column, err.kind)
+        }
+        use self::ParseErrorKind::*;
+        assert_eq!(err("<a>\n</b>"), (2, 1, InvalidXml("unexpected </b>".to_string())));
+        assert_eq!(err("<a>"), (1, 4, InvalidXml("unclosed element <a>".to_string())));
+        assert_eq!(err("<a x=1/>"), (1, 6, InvalidXml("expected quoted value".to_string())));
+        assert_eq!(err("<a x='&nbsp;'/>"),
+                   (1, 6, InvalidXml("unknown reference &nbsp;".to_string())));
+        assert_eq!(err("<!-- a"), (1, 1, UnterminatedBlock));
+    }
+
+    #[test]
+    fn test_stream_reader() {
+        let text = concat!("<?xml version=\"1.0\"?>\n<!-- > -->\n",
+                           "<a x='>'>✓ &amp; ✗<b/><![CDATA[<c>]]></a>");
+        let expected = events(text).unwrap().iter()
+            .map(|event| format!("{:?}", event))
+            .collect::<Vec<_>>();
+        let mut reader = StreamReader::new();
+        let mut actual = Vec::new();
+        for byte in text.as_bytes() {
+            reader.feed(&[*byte], |_, event| {
+                actual.push(format!("{:?}", event));
+                Ok(())
+            }).unwrap();
+        }
+        reader.finish(|_, event| {
+            actual.push(format!("{:?}", event));
+            Ok(())
+        }).unwrap();
+        assert_eq!(actual, expected);
+
+        let mut reader = StreamReader::new();
+        reader.feed(b"<a>\n", |_, _| Ok(())).unwrap();
+        let err = reader.feed(b"\xff</a>", |_, _| Ok(())).unwrap_err();
+        assert_eq!(err.position, Position{line: 2, column: 1});
+        let mut reader = StreamReader::new();
+        reader.feed(b"<a>\xe2\x9c", |_, _| Ok(())).unwrap();
+        let err = reader.finish(|_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::InvalidXml("unclosed element <a>".to_string()));
+    }
+}
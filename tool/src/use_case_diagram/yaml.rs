@@ -0,0 +1,857 @@
+//! Diagrams as YAML, for teams that would rather edit lists of actors, use
+//! cases and associations by hand than learn the language of `parser`.
+//!
+//! A document is a mapping with optional `actors`, `use_cases` and
+//! `associations` lists:
+//!
+//! ```yaml
+//! actors:
+//!   - Administrator
+//!   - name: Subscriber
+//!     description: Reads and comments
+//! use_cases:
+//!   - Ban subscriber
+//! associations:
+//!   - actor: Administrator
+//!     use_case: Ban subscriber
+//!     label: bans
+//! ```
+//!
+//! Actors and use cases are given by name or title alone, or as mappings
+//! with a `description`. Only block-style YAML is read: flow collections
+//! other than empty ones such as `[]` and `{ }`, anchors and tags are
+//! rejected. Literal and folded block scalars may be the values of keys.
+
+use std::fmt::Write;
+
+use use_case_diagram::parser::{error, ParseError, ParseErrorKind, Position};
+use use_case_diagram::source_map::span;
+use use_case_diagram::{Actor, Association, NameError, SourceMap, UseCase, UseCaseDiagram};
+
+/// The diagram's actors, use cases and associations as YAML, in the order
+/// of `actors_in_order` and `use_cases_in_order`, leaving out empty lists.
+/// Everything else about the diagram, such as relationships between use
+/// cases, is left out. Fails if names or titles are not unique, as
+/// associations refer to them.
+pub fn to_yaml<S>(diagram: &UseCaseDiagram<S>) -> Result<String, NameError>
+    where S: AsRef<str> {
+    diagram.validate_unique_names()?;
+    let actors = diagram.actors_in_order();
+    let use_cases = diagram.use_cases_in_order();
+    let mut yaml = String::new();
+    if !actors.is_empty() {
+        yaml.push_str("actors:\n");
+        for &(_, actor) in &actors {
+            generate_element(&mut yaml, "name", actor.name.as_ref(), &actor.description);
+        }
+    }
+    if !use_cases.is_empty() {
+        yaml.push_str("use_cases:\n");
+        for &(_, use_case) in &use_cases {
+            generate_element(&mut yaml, "title", use_case.title.as_ref(), &use_case.description);
+        }
+    }
+    let mut associations = String::new();
+    for &(actor_id, actor) in &actors {
+        for &(use_case_id, use_case) in &use_cases {
+            if let Some(association) = diagram.association(actor_id, use_case_id) {
+                writeln!(associations, "  - actor: {}", quote(actor.name.as_ref())).unwrap();
+                writeln!(associations, "    use_case: {}", quote(use_case.title.as_ref()))
+                    .unwrap();
+                if let Some(ref label) = association.label {
+                    writeln!(associations, "    label: {}", quote(label.as_ref())).unwrap();
+                }
+            }
+        }
+    }
+    if !associations.is_empty() {
+        yaml.push_str("associations:\n");
+        yaml.push_str(&associations);
+    }
+    Ok(yaml)
+}
+
+fn generate_element<S>(yaml: &mut String, key: &str, name: &str, description: &Option<S>)
+    where S: AsRef<str> {
+    match *description {
+        Some(ref description) => {
+            writeln!(yaml, "  - {}: {}", key, quote(name)).unwrap();
+            writeln!(yaml, "    description: {}", quote(description.as_ref())).unwrap();
+        },
+        None => writeln!(yaml, "  - {}", quote(name)).unwrap(),
+    }
+}
+
+/// A plain scalar if it reads back as the same string, in YAML 1.2 as well
+/// as here, and a double-quoted one otherwise.
+fn quote(s: &str) -> String {
+    let plain = !s.is_empty() && s.trim() == s &&
+        !s.starts_with(|c| "-?:,[]{}#&*!|>'\"%@`".contains(c)) &&
+        !s.contains(": ") && !s.contains(" #") && !s.ends_with(':') &&
+        !s.contains(|c: char| c.is_control()) &&
+        !["~", "null", "true", "false", "yes", "no", "on", "off"]
+            .contains(&s.to_lowercase().as_str()) &&
+        s.parse::<f64>().is_err();
+    if plain {
+        return s.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Parse YAML into a diagram with unique names. Associations may refer to
+/// actors and use cases listed after them.
+pub fn from_yaml<S>(text: &str) -> Result<UseCaseDiagram<S>, ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    from_yaml_with_source_map(text, "").map(|(diagram, _)| diagram)
+}
+
+/// Parse YAML in the file like `from_yaml`, and map every element to the
+/// start of the list item that defines it.
+pub fn from_yaml_with_source_map<S>(text: &str, file: &str)
+                                    -> Result<(UseCaseDiagram<S>, SourceMap<S>), ParseError>
+    where S: AsRef<str> + for<'a> From<&'a str> {
+    let mut diagram = UseCaseDiagram::with_unique_names();
+    let mut source_map = SourceMap::new();
+    let document = match parse_document(text, false)? {
+        Some(document) => document,
+        None => return Ok((diagram, source_map)),
+    };
+    let mut sections = fields(&document, &["actors", "use_cases", "associations"])?;
+    let associations = sections.pop().unwrap();
+    let use_cases = sections.pop().unwrap();
+    let actors = sections.pop().unwrap();
+    for node in items(actors)? {
+        let (name, description) = element(node, "name")?;
+        let mut actor = Actor::new(S::from(name));
+        actor.description = description.map(S::from);
+        let actor_id = diagram.insert_actor(actor).map_err(name_error(node))?;
+        source_map.actors.insert(actor_id, span(file, node.position));
+    }
+    for node in items(use_cases)? {
+        let (title, description) = element(node, "title")?;
+        let mut use_case = UseCase::new(S::from(title));
+        use_case.description = description.map(S::from);
+        let use_case_id = diagram.insert_use_case(use_case).map_err(name_error(node))?;
+        source_map.use_cases.insert(use_case_id, span(file, node.position));
+    }
+    for node in items(associations)? {
+        let mut fields = fields(node, &["actor", "use_case", "label"])?;
+        let label = fields.pop().unwrap().map(scalar).transpose()?
+            .filter(|label| !label.is_empty());
+        let use_case = required(node, fields.pop().unwrap(), "use_case")?;
+        let actor = required(node, fields.pop().unwrap(), "actor")?;
+        let actor_id = match diagram.actor_by_name(actor) {
+            Some((actor_id, _)) => actor_id,
+            None => return Err(error(node.position,
+                                     ParseErrorKind::UnknownActorName(actor.to_string()))),
+        };
+        let use_case_id = match diagram.use_case_by_title(use_case) {
+            Some((use_case_id, _)) => use_case_id,
+            None => return Err(error(node.position,
+                                     ParseErrorKind::UnknownUseCaseTitle(use_case.to_string()))),
+        };
+        let association = Association{label: label.map(S::from), ..Association::default()};
+        diagram.insert_association_with(actor_id, use_case_id, association).unwrap();
+        source_map.associations.insert((actor_id, use_case_id), span(file, node.position));
+    }
+    Ok((diagram, source_map))
+}
+
+fn name_error(node: &Node) -> impl Fn(NameError) -> ParseError {
+    let position = node.position;
+    move |err| error(position, ParseErrorKind::Name(err))
+}
+
+/// The values of the mapping under the given keys, failing on other keys.
+fn fields<'a>(node: &'a Node, keys: &[&str]) -> Result<Vec<Option<&'a Node>>, ParseError> {
+    let entries = match node.value {
+        Value::Mapping(ref entries) => entries,
+        _ => return Err(invalid(node.position, "expected a mapping")),
+    };
+    let mut fields = vec![None; keys.len()];
+    for (key, value) in entries {
+        match keys.iter().position(|k| k == key) {
+            Some(index) => fields[index] = Some(value),
+            None => return Err(error(value.key_position,
+                                     ParseErrorKind::UnknownKey(key.to_string()))),
+        }
+    }
+    Ok(fields)
+}
+
+fn required<'a>(node: &Node, field: Option<&'a Node>, key: &str) -> Result<&'a str, ParseError> {
+    match field {
+        Some(field) => scalar(field),
+        None => Err(error(node.position, ParseErrorKind::MissingKey(key.to_string()))),
+    }
+}
+
+/// The items of a list, which may be missing or empty.
+fn items(node: Option<&Node>) -> Result<&[Node], ParseError> {
+    match node.map(|node| (node, &node.value)) {
+        None => Ok(&[]),
+        Some((_, Value::Sequence(items))) => Ok(items),
+        Some((_, Value::Scalar(s))) if s.is_empty() => Ok(&[]),
+        Some((node, _)) => Err(invalid(node.position, "expected a list")),
+    }
+}
+
+/// The name or title of an actor or use case and its description.
+fn element<'a>(node: &'a Node, key: &str) -> Result<(&'a str, Option<&'a str>), ParseError> {
+    if let Value::Scalar(ref name) = node.value {
+        return Ok((name, None));
+    }
+    let mut fields = fields(node, &[key, "description"])?;
+    let description = fields.pop().unwrap().map(scalar).transpose()?
+        .filter(|description| !description.is_empty());
+    Ok((required(node, fields.pop().unwrap(), key)?, description))
+}
+
+fn scalar(node: &Node) -> Result<&str, ParseError> {
+    match node.value {
+        Value::Scalar(ref s) => Ok(s),
+        _ => Err(invalid(node.position, "expected a string")),
+    }
+}
+
+fn invalid(position: Position, message: &str) -> ParseError {
+    error(position, ParseErrorKind::InvalidYaml(message.to_string()))
+}
+
+/// A value and where it starts, and for a value in a mapping, where its key
+/// starts.
+struct Node {
+    position: Position,
+    key_position: Position,
+    value: Value,
+}
+
+enum Value {
+    /// A string, empty for a missing value.
+    Scalar(String),
+    Sequence(Vec<Node>),
+    Mapping(Vec<(String, Node)>),
+}
+
+impl Node {
+    fn into_value(self) -> serde_json::Value {
+        match self.value {
+            Value::Scalar(s) => serde_json::Value::String(s),
+            Value::Sequence(items) => items.into_iter().map(Node::into_value).collect(),
+            Value::Mapping(entries) =>
+                entries.into_iter().map(|(key, node)| (key, node.into_value())).collect(),
+        }
+    }
+}
+
+/// A line that is not blank or only a comment, without its comment.
+struct Line<'a> {
+    number: usize,
+    indent: usize,
+    text: &'a str,
+}
+
+impl<'a> Line<'a> {
+    fn position(&self) -> Position {
+        Position{line: self.number, column: self.indent + 1}
+    }
+
+    fn is_item(&self) -> bool {
+        self.text == "-" || self.text.starts_with("- ")
+    }
+}
+
+struct Parser<'a> {
+    /// Every line of the text, for block scalars.
+    raw: Vec<&'a str>,
+    lines: Vec<Line<'a>>,
+    index: usize,
+    flow_collections: bool,
+}
+
+/// Parse any document this module reads into a JSON value in which every
+/// scalar, including a missing value, is a string. Flow collections on a
+/// single line are read too, as other documents such as OpenAPI
+/// specifications use them freely.
+pub(super) fn parse_value(text: &str) -> Result<serde_json::Value, ParseError> {
+    Ok(parse_document(text, true)?.map_or(serde_json::Value::Null, Node::into_value))
+}
+
+fn parse_document(text: &str, flow_collections: bool) -> Result<Option<Node>, ParseError> {
+    let mut lines = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let content = line.trim_start_matches(' ');
+        let indent = line.len() - content.len();
+        let position = Position{line: index + 1, column: indent + 1};
+        if content.starts_with('\t') {
+            return Err(invalid(position, "tabs are not allowed in indentation"));
+        }
+        let text = strip_comment(content).trim_end();
+        if text.is_empty() || (indent == 0 && (text == "---" || text == "...")) {
+            continue;
+        }
+        lines.push(Line{number: index + 1, indent, text});
+    }
+    let mut parser = Parser{raw: text.lines().collect(), lines, index: 0, flow_collections};
+    if parser.lines.is_empty() {
+        return Ok(None);
+    }
+    let indent = parser.lines[0].indent;
+    let document = parser.block(indent)?;
+    match parser.lines.get(parser.index) {
+        Some(line) => Err(invalid(line.position(), "unexpected indentation")),
+        None => Ok(Some(document)),
+    }
+}
+
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (index, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None if c == '#' && previous.is_whitespace() => return &text[..index],
+            None if (c == '"' || c == '\'') && (previous.is_whitespace() || previous == ':') =>
+                quote = Some(c),
+            None => (),
+        }
+        previous = c;
+    }
+    text
+}
+
+impl<'a> Parser<'a> {
+    /// The collection or scalar starting at the current line, which has the
+    /// given indentation.
+    fn block(&mut self, indent: usize) -> Result<Node, ParseError> {
+        let line = &self.lines[self.index];
+        let is_flow = self.flow_collections &&
+            (line.text.starts_with('[') || line.text.starts_with('{'));
+        if line.is_item() {
+            self.sequence(indent)
+        } else if !is_flow && split_key(line.text, line.position())?.is_some() {
+            self.mapping(indent)
+        } else {
+            let position = line.position();
+            let value = parse_scalar(line.text, position, self.flow_collections)?;
+            self.index += 1;
+            Ok(Node{position, key_position: position, value})
+        }
+    }
+
+    /// The value after a key or dash that ends its line: the block on the
+    /// lines below if they are indented further, or a sequence at the same
+    /// indentation as a key, or an empty value.
+    fn nested(&mut self, indent: usize, position: Position, in_mapping: bool)
+              -> Result<Node, ParseError> {
+        match self.lines.get(self.index) {
+            Some(line) if line.indent > indent || (in_mapping && line.indent == indent &&
+                                                   line.is_item()) => {
+                let indent = line.indent;
+                self.block(indent)
+            },
+            _ => Ok(Node{position, key_position: position, value: Value::Scalar(String::new())}),
+        }
+    }
+
+    fn sequence(&mut self, indent: usize) -> Result<Node, ParseError> {
+        let position = self.lines[self.index].position();
+        let mut items = Vec::new();
+        // A list under a key may be indented as far as the key, and then
+        // ends at the next key.
+        while let Some(line) = self.current(indent)?.filter(|line| line.is_item()) {
+            let rest = &line.text[1..];
+            let content = rest.trim_start();
+            let item_indent = indent + 1 + rest.len() - content.len();
+            if content.is_empty() {
+                let item_position = line.position();
+                self.index += 1;
+                items.push(self.nested(indent, item_position, false)?);
+            } else {
+                let number = line.number;
+                self.lines[self.index] = Line{number, indent: item_indent, text: content};
+                items.push(self.block(item_indent)?);
+            }
+        }
+        Ok(Node{position, key_position: position, value: Value::Sequence(items)})
+    }
+
+    fn mapping(&mut self, indent: usize) -> Result<Node, ParseError> {
+        let position = self.lines[self.index].position();
+        let mut entries = Vec::<(String, Node)>::new();
+        while let Some(line) = self.current(indent)? {
+            let key_position = line.position();
+            let (key, rest) = match split_key(line.text, key_position)? {
+                Some(split) if !line.is_item() => split,
+                _ => return Err(invalid(key_position, "expected a key")),
+            };
+            if entries.iter().any(|(other, _)| *other == key) {
+                return Err(invalid(key_position, "duplicate key"));
+            }
+            let content = rest.trim_start();
+            let mut value = if content.is_empty() {
+                self.index += 1;
+                self.nested(indent, key_position, true)?
+            } else {
+                let column = indent + line.text.len() - content.len() + 1;
+                let value_position = Position{line: line.number, column};
+                let value = if content.starts_with('|') || content.starts_with('>') {
+                    let number = line.number;
+                    self.index += 1;
+                    Value::Scalar(self.block_scalar(content, number, indent, value_position)?)
+                } else {
+                    let value = parse_scalar(content, value_position, self.flow_collections)?;
+                    self.index += 1;
+                    value
+                };
+                Node{position: value_position, key_position, value}
+            };
+            value.key_position = key_position;
+            entries.push((key, value));
+        }
+        Ok(Node{position, key_position: position, value: Value::Mapping(entries)})
+    }
+
+    /// A literal or folded block scalar with the given header, e.g. `|-`, on
+    /// the line with the given number, made of the lines below indented
+    /// further than its key.
+    fn block_scalar(&mut self, header: &str, number: usize, indent: usize, position: Position)
+                    -> Result<String, ParseError> {
+        let chomping = &header[1..];
+        if !["", "-", "+"].contains(&chomping) {
+            return Err(invalid(position, "indentation indicators are not supported"));
+        }
+        let mut lines = Vec::new();
+        let mut block_indent = None;
+        for raw in &self.raw[number..] {
+            let content = raw.trim_start_matches(' ');
+            let line_indent = raw.len() - content.len();
+            if content.trim().is_empty() {
+                lines.push("");
+                continue;
+            }
+            let block_indent = *block_indent.get_or_insert(line_indent);
+            if line_indent <= indent || line_indent < block_indent {
+                break;
+            }
+            lines.push(&raw[block_indent..]);
+        }
+        let last = number + lines.len();
+        while self.lines.get(self.index).is_some_and(|line| line.number <= last) {
+            self.index += 1;
+        }
+
+        let trailing = lines.iter().rev().take_while(|line| line.is_empty()).count();
+        let lines = &lines[..lines.len() - trailing];
+        let mut value = String::new();
+        if header.starts_with('>') {
+            for line in lines {
+                if !line.is_empty() && !value.is_empty() && !value.ends_with('\n') {
+                    value.push(' ');
+                }
+                value.push_str(line);
+                if line.is_empty() {
+                    value.push('\n');
+                }
+            }
+        } else {
+            value = lines.join("\n");
+        }
+        match chomping {
+            "-" => (),
+            "+" => value.push_str(&"\n".repeat(trailing + !lines.is_empty() as usize)),
+            _ if !lines.is_empty() => value.push('\n'),
+            _ => (),
+        }
+        Ok(value)
+    }
+
+    /// The current line if it continues a collection with the given
+    /// indentation.
+    fn current(&self, indent: usize) -> Result<Option<&Line<'a>>, ParseError> {
+        match self.lines.get(self.index) {
+            Some(line) if line.indent == indent => Ok(Some(line)),
+            Some(line) if line.indent > indent =>
+                Err(invalid(line.position(), "unexpected indentation")),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// The key of a mapping entry and the text after its colon, or `None` if
+/// the line is not a mapping entry.
+fn split_key(text: &str, position: Position) -> Result<Option<(String, &str)>, ParseError> {
+    if text.starts_with('"') || text.starts_with('\'') {
+        let (key, length) = parse_quoted(text, position)?;
+        let rest = &text[length..];
+        return Ok(if rest == ":" || rest.starts_with(": ") {
+            Some((key, &rest[1..]))
+        } else {
+            None
+        });
+    }
+    let colon = text.match_indices(':')
+        .map(|(index, _)| index)
+        .find(|&index| text[index + 1..].is_empty() || text[index + 1..].starts_with(' '));
+    Ok(colon.map(|index| (text[..index].trim_end().to_string(), &text[index + 1..])))
+}
+
+fn parse_scalar(text: &str, position: Position, flow_collections: bool)
+                -> Result<Value, ParseError> {
+    let empty = |open, close| {
+        text.len() >= 2 && text.starts_with(open) && text.ends_with(close) &&
+            text[1..text.len() - 1].trim().is_empty()
+    };
+    match text {
+        _ if empty('[', ']') => return Ok(Value::Sequence(Vec::new())),
+        _ if empty('{', '}') => return Ok(Value::Mapping(Vec::new())),
+        _ if flow_collections && (text.starts_with('[') || text.starts_with('{')) => {
+            let mut flow = Flow{text, offset: 0, start: position};
+            let node = flow.value(false)?;
+            flow.skip_whitespace();
+            if flow.offset != text.len() {
+                return Err(invalid(flow.position(), "unexpected text after flow collection"));
+            }
+            return Ok(node.value);
+        },
+        _ => (),
+    }
+    if text.starts_with('"') || text.starts_with('\'') {
+        let (value, length) = parse_quoted(text, position)?;
+        if length != text.len() {
+            return Err(invalid(position, "unexpected text after quoted string"));
+        }
+        return Ok(Value::Scalar(value));
+    }
+    match text.chars().next() {
+        Some('[') | Some('{') => Err(invalid(position, "flow collections are not supported")),
+        Some('|') | Some('>') => Err(invalid(position, "block scalars are not supported")),
+        Some('&') | Some('*') | Some('!') =>
+            Err(invalid(position, "anchors, aliases and tags are not supported")),
+        _ => Ok(Value::Scalar(text.to_string())),
+    }
+}
+
+/// A flow collection on a single line, such as `[read, write]`.
+struct Flow<'a> {
+    text: &'a str,
+    offset: usize,
+    start: Position,
+}
+
+impl<'a> Flow<'a> {
+    fn position(&self) -> Position {
+        Position{line: self.start.line,
+                 column: self.start.column + self.text[..self.offset].chars().count()}
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.offset..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.offset += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        let eaten = self.rest().starts_with(c);
+        if eaten {
+            self.offset += c.len_utf8();
+        }
+        eaten
+    }
+
+    /// Consume the comma after an item unless the collection closes.
+    fn separator(&mut self, close: char) -> Result<(), ParseError> {
+        if self.eat(',') || self.rest().starts_with(close) {
+            Ok(())
+        } else if self.rest().is_empty() {
+            Err(invalid(self.position(), "unterminated flow collection"))
+        } else {
+            Err(invalid(self.position(), &format!("expected , or {}", close)))
+        }
+    }
+
+    /// A collection or scalar, which ends at a colon if it is a key.
+    fn value(&mut self, key: bool) -> Result<Node, ParseError> {
+        self.skip_whitespace();
+        let position = self.position();
+        let value = match self.rest().chars().next() {
+            Some('[') => {
+                self.offset += 1;
+                let mut items = Vec::new();
+                while !self.eat(']') {
+                    items.push(self.value(false)?);
+                    self.separator(']')?;
+                }
+                Value::Sequence(items)
+            },
+            Some('{') => {
+                self.offset += 1;
+                let mut entries = Vec::new();
+                while !self.eat('}') {
+                    let key = match self.value(true)? {
+                        Node{value: Value::Scalar(key), ..} => key,
+                        node => return Err(invalid(node.position, "expected a key")),
+                    };
+                    if !self.eat(':') {
+                        return Err(invalid(self.position(), "expected :"));
+                    }
+                    self.skip_whitespace();
+                    let value = if self.rest().starts_with(',') || self.rest().starts_with('}') {
+                        let position = self.position();
+                        Node{position, key_position: position, value: Value::Scalar(String::new())}
+                    } else {
+                        self.value(false)?
+                    };
+                    entries.push((key, value));
+                    self.separator('}')?;
+                }
+                Value::Mapping(entries)
+            },
+            Some('"') | Some('\'') => {
+                let (value, length) = parse_quoted(self.rest(), position)?;
+                self.offset += length;
+                Value::Scalar(value)
+            },
+            Some(_) => {
+                let rest = self.rest();
+                let end = rest.find(|c| ",[]{}".contains(c) || (key && c == ':'))
+                    .unwrap_or(rest.len());
+                self.offset += end;
+                Value::Scalar(rest[..end].trim_end().to_string())
+            },
+            None => return Err(invalid(position, "unterminated flow collection")),
+        };
+        Ok(Node{position, key_position: position, value})
+    }
+}
+
+/// A single- or double-quoted string at the start of the text, and the
+/// length of its source.
+fn parse_quoted(text: &str, position: Position) -> Result<(String, usize), ParseError> {
+    let quote = text.chars().next().unwrap();
+    let mut value = String::new();
+    let mut chars = text.char_indices().skip(1);
+    while let Some((index, c)) = chars.next() {
+        let escape_position = Position{line: position.line, column: position.column + index};
+        match c {
+            '\'' if quote == '\'' => {
+                if text[index + 1..].starts_with('\'') {
+                    chars.next();
+                    value.push('\'');
+                } else {
+                    return Ok((value, index + 1));
+                }
+            },
+            '"' if quote == '"' => return Ok((value, index + 1)),
+            '\\' if quote == '"' => {
+                let escaped = chars.next().map(|(_, c)| c);
+                value.push(match escaped {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('/') => '/',
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('0') => '\0',
+                    Some('u') => {
+                        let digits = chars.by_ref().take(4).map(|(_, c)| c).collect::<String>();
+                        u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32)
+                            .ok_or_else(|| error(escape_position,
+                                                 ParseErrorKind::InvalidEscape('u')))?
+                    },
+                    Some(c) => return Err(error(escape_position, ParseErrorKind::InvalidEscape(c))),
+                    None => break,
+                });
+            },
+            c => value.push(c),
+        }
+    }
+    Err(error(position, ParseErrorKind::UnterminatedString))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    use use_case_diagram::{ActorId, Span};
+
+    #[test]
+    fn test_yaml_round_trip() {
+        let mut diagram = UseCaseDiagram::new();
+        let a = diagram.insert_actor(Actor::new(Rc::from("Administrator"))).unwrap();
+        let mut subscriber = Actor::new(Rc::from("yes"));
+        subscriber.description = Some(Rc::from("Reads: \"all\"\nand comments"));
+        let s = diagram.insert_actor(subscriber).unwrap();
+        let bs = diagram.insert_use_case(UseCase::new(Rc::from("Ban subscriber"))).unwrap();
+        let pc = diagram.insert_use_case(UseCase::new(Rc::from("- Post #1"))).unwrap();
+        diagram.insert_association_with(a, bs, Association{label: Some(Rc::from("bans")),
+                                                           ..Association::default()})
+            .unwrap();
+        diagram.insert_association(s, pc).unwrap();
+
+        let yaml = to_yaml(&diagram).unwrap();
+        assert_eq!(yaml, concat!(
+            "actors:\n",
+            "  - Administrator\n",
+            "  - name: \"yes\"\n",
+            "    description: \"Reads: \\\"all\\\"\\nand comments\"\n",
+            "use_cases:\n",
+            "  - Ban subscriber\n",
+            "  - \"- Post #1\"\n",
+            "associations:\n",
+            "  - actor: Administrator\n",
+            "    use_case: Ban subscriber\n",
+            "    label: bans\n",
+            "  - actor: \"yes\"\n",
+            "    use_case: \"- Post #1\"\n",
+        ));
+        let parsed = from_yaml::<Rc<str>>(&yaml).unwrap();
+        assert!(parsed.equivalent(&diagram));
+        assert_eq!(to_yaml(&parsed).unwrap(), yaml);
+        assert_eq!(to_yaml(&UseCaseDiagram::<Rc<str>>::new()).unwrap(), "");
+    }
+
+    #[test]
+    fn test_from_yaml() {
+        let yaml = concat!(
+            "---\n",
+            "# Moderation\n",
+            "associations:\n",
+            "- actor: 'Site ''admin'''  # quoted\n",
+            "  use_case: Ban subscriber\n",
+            "\n",
+            "actors:\n",
+            "    -   name: Site 'admin'\n",
+            "        description:\n",
+            "use_cases: [ ]\n",
+        );
+        assert_eq!(from_yaml::<Rc<str>>(yaml).unwrap_err().kind,
+                   ParseErrorKind::UnknownUseCaseTitle("Ban subscriber".to_string()));
+        assert_eq!(from_yaml::<Rc<str>>(&yaml.replace("[ ]", "[Ban subscriber]")).unwrap_err()
+                       .kind,
+                   ParseErrorKind::InvalidYaml("flow collections are not supported".to_string()));
+        let empty = from_yaml::<Rc<str>>("actors: [  ]\nuse_cases: [\t]\nassociations: []\n")
+            .unwrap();
+        assert_eq!(empty.actors().count() + empty.use_cases().count(), 0);
+        let yaml = yaml.replace("[ ]", "\n  - Ban subscriber");
+        let diagram = from_yaml::<Rc<str>>(&yaml).unwrap();
+        let (a, actor) = diagram.actor_by_name("Site 'admin'").unwrap();
+        assert_eq!(actor.description, None);
+        let (bs, _) = diagram.use_case_by_title("Ban subscriber").unwrap();
+        assert_eq!(diagram.associations().collect::<Vec<_>>(), vec![(a, bs)]);
+        assert_eq!(from_yaml::<Rc<str>>("# nothing\n").unwrap().actors().count(), 0);
+
+        fn err(yaml: &str) -> (usize, usize, ParseErrorKind) {
+            let err = from_yaml::<Rc<str>>(yaml).unwrap_err();
+            (err.position.line, err.position.column, err.kind)
+        }
+        use self::ParseErrorKind::*;
+        assert_eq!(err("actors:\n  - A\n   - B\n"),
+                   (3, 4, InvalidYaml("unexpected indentation".to_string())));
+        assert_eq!(err("actors:\n  - A\nroles:\n"), (3, 1, UnknownKey("roles".to_string())));
+        assert_eq!(err("actors:\n  - description: x\n"),
+                   (2, 5, MissingKey("name".to_string())));
+        assert_eq!(err("actors:\n  - A\n  - A\n").2,
+                   Name(NameError::ActorNameTaken(ActorId(0))));
+        assert_eq!(err("associations:\n  - actor: A\n    use_case: U\n"),
+                   (2, 5, UnknownActorName("A".to_string())));
+        assert_eq!(err("actors: \"A\\q\"\n"), (1, 11, InvalidEscape('q')));
+        assert_eq!(err("actors: A\n"), (1, 9, InvalidYaml("expected a list".to_string())));
+    }
+
+    #[test]
+    fn test_from_yaml_with_source_map() {
+        let (diagram, source_map) = from_yaml_with_source_map::<Rc<str>>(concat!(
+            "actors:\n",
+            "  - Administrator\n",
+            "use_cases:\n",
+            "  - title: Ban subscriber\n",
+            "    description: Keep the peace\n",
+            "associations:\n",
+            "  - actor: Administrator\n",
+            "    use_case: Ban subscriber\n",
+        ), "moderation.yaml").unwrap();
+        let (administrator, _) = diagram.actor_by_name("Administrator").unwrap();
+        let (ban, _) = diagram.use_case_by_title("Ban subscriber").unwrap();
+        let span = |line, column| Span{file: Rc::from("moderation.yaml"), line, column};
+        assert_eq!(source_map.actors[&administrator], span(2, 5));
+        assert_eq!(source_map.use_cases[&ban], span(4, 5));
+        assert_eq!(source_map.associations[&(administrator, ban)], span(7, 5));
+    }
+
+    #[test]
+    fn test_parse_value() {
+        let value = parse_value(concat!(
+            "paths:\n",
+            "  /comments:\n",
+            "    post:\n",
+            "      description: |\n",
+            "        Posts a comment.\n",
+            "\n",
+            "          # Not a comment\n",
+            "      summary: >-\n",
+            "        Post\n",
+            "        comment\n",
+            "\n",
+            "        now\n",
+            "      security:\n",
+            "        - oauth: [ 'write:comments', read ]\n",
+            "        - {api_key: [], other: {}}\n",
+            "      kept: |+\n",
+            "        a\n",
+            "\n",
+            "      stripped: |-\n",
+            "      empty: |\n",
+            "      tags: []\n",
+        )).unwrap();
+        assert_eq!(value, serde_json::json!({
+            "paths": {
+                "/comments": {
+                    "post": {
+                        "description": "Posts a comment.\n\n  # Not a comment\n",
+                        "summary": "Post comment\nnow",
+                        "security": [
+                            {"oauth": ["write:comments", "read"]},
+                            {"api_key": [], "other": {}},
+                        ],
+                        "kept": "a\n\n",
+                        "stripped": "",
+                        "empty": "",
+                        "tags": [],
+                    },
+                },
+            },
+        }));
+        assert_eq!(parse_value("").unwrap(), serde_json::Value::Null);
+
+        fn err(yaml: &str) -> (usize, usize, ParseErrorKind) {
+            let err = parse_value(yaml).unwrap_err();
+            (err.position.line, err.position.column, err.kind)
+        }
+        assert_eq!(err("a: [b, c\n"),
+                   (1, 9, ParseErrorKind::InvalidYaml("unterminated flow collection".to_string())));
+        assert_eq!(err("a: {b: c} d\n"), (1, 11, ParseErrorKind::InvalidYaml(
+            "unexpected text after flow collection".to_string())));
+        assert_eq!(err("a: |2\n  b\n"), (1, 4, ParseErrorKind::InvalidYaml(
+            "indentation indicators are not supported".to_string())));
+    }
+}